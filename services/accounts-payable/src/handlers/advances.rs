@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_vendor_advance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateVendorAdvanceRequest>,
+) -> ServiceResult<Json<VendorAdvanceWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let advance = state.advance_service
+        .create_advance(payload, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(advance))
+}
+
+pub async fn get_vendor_advances(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<VendorAdvance>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let vendor_id = params.get("vendor_id")
+        .map(|v| v.parse::<Uuid>())
+        .transpose()
+        .map_err(|_| ServiceError::Validation("Invalid vendor_id".to_string()))?;
+
+    let status = params.get("status")
+        .map(|s| s.parse::<AdvanceStatus>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let advances = state.advance_service
+        .get_advances(company_id, vendor_id, status, pagination)
+        .await?;
+
+    Ok(Json(advances))
+}
+
+pub async fn get_vendor_advance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(advance_id): Path<Uuid>,
+) -> ServiceResult<Json<VendorAdvanceWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let advance = state.advance_service
+        .get_advance(advance_id, company_id)
+        .await?;
+
+    Ok(Json(advance))
+}
+
+pub async fn apply_vendor_advance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(advance_id): Path<Uuid>,
+    Json(payload): Json<ApplyVendorAdvanceRequest>,
+) -> ServiceResult<Json<VendorAdvanceWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let advance = state.advance_service
+        .apply_advance(advance_id, payload, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(advance))
+}
+
+pub async fn refund_vendor_advance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(advance_id): Path<Uuid>,
+    Json(payload): Json<VendorAdvanceRefundRequest>,
+) -> ServiceResult<Json<VendorAdvanceWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let advance = state.advance_service
+        .refund_advance(advance_id, payload, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(advance))
+}
+
+pub async fn cancel_vendor_advance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(advance_id): Path<Uuid>,
+) -> ServiceResult<Json<VendorAdvanceWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let advance = state.advance_service
+        .cancel_advance(advance_id, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(advance))
+}
+
+pub async fn get_advance_aging_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<AdvanceAgingReport>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let as_of_date = params.get("as_of_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+
+    let report = state.aging_service
+        .generate_advance_aging_report(company_id, user_id, as_of_date)
+        .await?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,145 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateCreditMemoRequest>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let credit_memo = state.credit_memo_service
+        .create_credit_memo(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
+
+pub async fn get_credit_memos(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<VendorCreditMemo>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let vendor_id = params.get("vendor_id")
+        .map(|v| v.parse::<Uuid>())
+        .transpose()
+        .map_err(|_| ServiceError::Validation("Invalid vendor_id".to_string()))?;
+
+    let status = params.get("status")
+        .map(|s| s.parse::<CreditMemoStatus>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let credit_memos = state.credit_memo_service
+        .get_credit_memos(company_id, vendor_id, status, pagination)
+        .await?;
+
+    Ok(Json(credit_memos))
+}
+
+pub async fn get_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(credit_memo_id): Path<Uuid>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let credit_memo = state.credit_memo_service
+        .get_credit_memo(credit_memo_id, company_id)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
+
+pub async fn approve_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(credit_memo_id): Path<Uuid>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let credit_memo = state.credit_memo_service
+        .approve_credit_memo(credit_memo_id, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
+
+pub async fn cancel_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(credit_memo_id): Path<Uuid>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let credit_memo = state.credit_memo_service
+        .cancel_credit_memo(credit_memo_id, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
+
+pub async fn apply_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(credit_memo_id): Path<Uuid>,
+    Json(payload): Json<ApplyCreditMemoRequest>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let credit_memo = state.credit_memo_service
+        .apply_credit_memo(credit_memo_id, payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
+
+pub async fn refund_credit_memo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(credit_memo_id): Path<Uuid>,
+    Json(payload): Json<CreditMemoRefundRequest>,
+) -> ServiceResult<Json<VendorCreditMemoWithDetails>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let credit_memo = state.credit_memo_service
+        .refund_credit_memo(credit_memo_id, payload, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(credit_memo))
+}
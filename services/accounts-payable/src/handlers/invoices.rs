@@ -11,9 +11,10 @@ pub async fn create_vendor_invoice(
 ) -> ServiceResult<Json<VendorInvoice>> {
     let company_id = extract_company_id(&headers)?;
     let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
     
     let invoice = state.invoice_service
-        .create_invoice(payload, company_id, user_id)
+        .create_invoice(payload, company_id, user_id, &roles)
         .await?;
     
     Ok(Json(invoice))
@@ -69,14 +70,16 @@ pub async fn update_invoice_status(
 ) -> ServiceResult<Json<VendorInvoice>> {
     let company_id = extract_company_id(&headers)?;
     let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
     
     let status = params.get("status")
         .ok_or_else(|| common::ServiceError::Validation("Missing status parameter".to_string()))?
         .parse::<InvoiceStatus>()
         .map_err(|e| common::ServiceError::Validation(format!("Invalid status: {}", e)))?;
+
     
     let invoice = state.invoice_service
-        .update_invoice_status(invoice_id, company_id, status, user_id)
+        .update_invoice_status(invoice_id, company_id, status, user_id, &roles)
         .await?;
     
     Ok(Json(invoice))
@@ -90,10 +93,57 @@ pub async fn pay_vendor_invoice(
 ) -> ServiceResult<Json<VendorInvoice>> {
     let company_id = extract_company_id(&headers)?;
     let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
     
     let invoice = state.payment_service
-        .process_payment(invoice_id, company_id, payload, user_id)
+        .process_payment(invoice_id, company_id, payload, user_id, &roles)
         .await?;
     
     Ok(Json(invoice))
-}
\ No newline at end of file
+}
+pub async fn get_payments(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<VendorPayment>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let ids = params.get("ids")
+        .map(|ids| {
+            ids.split(',')
+                .filter(|id| !id.trim().is_empty())
+                .map(|id| Uuid::parse_str(id.trim())
+                    .map_err(|_| common::ServiceError::Validation(format!("Invalid payment id: {}", id))))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let filters = PaymentFilters {
+        bank_account_id: params.get("bank_account_id").and_then(|id| Uuid::parse_str(id).ok()),
+        date_from: params.get("date_from")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        date_to: params.get("date_to")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        ids,
+    };
+
+    let payments = state.payment_service
+        .get_payments(company_id, filters)
+        .await?;
+
+    Ok(Json(payments))
+}
+
+pub async fn get_subledger_balance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<SubledgerBalance>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let balance = state.aging_service
+        .get_subledger_balance(company_id)
+        .await?;
+
+    Ok(Json(balance))
+}
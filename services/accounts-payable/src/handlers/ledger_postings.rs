@@ -0,0 +1,40 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*};
+
+/// Ledger postings of AP documents, e.g. `?status=FAILED` for the ones the
+/// ledger rejected.
+pub async fn get_ledger_postings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<LedgerOutboxEntry>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let status = params.get("status").cloned();
+    let source_document_id = params.get("source_document_id").and_then(|id| Uuid::parse_str(id).ok());
+
+    let entries = state.ledger_outbox
+        .get_entries(company_id, status, source_document_id)
+        .await?;
+
+    Ok(Json(entries))
+}
+
+pub async fn retry_ledger_posting(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(posting_id): Path<Uuid>,
+) -> ServiceResult<Json<LedgerOutboxEntry>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let entry = state.ledger_outbox
+        .retry(posting_id, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(entry))
+}
@@ -0,0 +1,87 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn get_matching_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<MatchingSettings>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let settings = state.matching_service
+        .get_settings(company_id)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+pub async fn update_matching_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateMatchingSettingsRequest>,
+) -> ServiceResult<Json<MatchingSettings>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let settings = state.matching_service
+        .update_settings(company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+pub async fn get_invoice_match(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(invoice_id): Path<Uuid>,
+) -> ServiceResult<Json<InvoiceMatch>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let invoice_match = state.matching_service
+        .get_invoice_match(invoice_id, company_id)
+        .await?;
+
+    Ok(Json(invoice_match))
+}
+
+pub async fn rematch_invoice(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(invoice_id): Path<Uuid>,
+) -> ServiceResult<Json<InvoiceMatch>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let invoice_match = state.matching_service
+        .rematch_invoice(invoice_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(invoice_match))
+}
+
+pub async fn get_matching_exceptions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<InvoiceMatch>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let vendor_id = params.get("vendor_id").and_then(|id| Uuid::parse_str(id).ok());
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let exceptions = state.matching_service
+        .get_exceptions(company_id, vendor_id, pagination)
+        .await?;
+
+    Ok(Json(exceptions))
+}
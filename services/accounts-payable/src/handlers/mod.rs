@@ -1,9 +1,23 @@
 pub mod health;
 pub mod vendors;
 pub mod invoices;
+pub mod purchase_orders;
+pub mod matching;
+pub mod payment_runs;
+pub mod withholding;
+pub mod credit_memos;
+pub mod advances;
+pub mod ledger_postings;
 pub mod reports;
 
 pub use health::*;
 pub use vendors::*;
 pub use invoices::*;
+pub use purchase_orders::*;
+pub use matching::*;
+pub use payment_runs::*;
+pub use withholding::*;
+pub use credit_memos::*;
+pub use advances::*;
+pub use ledger_postings::*;
 pub use reports::*;
\ No newline at end of file
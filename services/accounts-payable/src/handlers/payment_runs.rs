@@ -0,0 +1,169 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json},
+};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreatePaymentRunRequest>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let payment_run = state.payment_run_service
+        .create_run(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+pub async fn get_payment_runs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<PaymentRun>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let status = params.get("status")
+        .map(|s| s.parse::<PaymentRunStatus>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let payment_runs = state.payment_run_service
+        .get_runs(company_id, status, pagination)
+        .await?;
+
+    Ok(Json(payment_runs))
+}
+
+pub async fn get_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let payment_run = state.payment_run_service
+        .get_run(run_id, company_id)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+pub async fn remove_payment_run_item(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((run_id, item_id)): Path<(Uuid, Uuid)>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let payment_run = state.payment_run_service
+        .remove_item(run_id, item_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+pub async fn approve_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let payment_run = state.payment_run_service
+        .approve_run(run_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+pub async fn execute_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let payment_run = state.payment_run_service
+        .execute_run(run_id, company_id, user_id, &roles)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+pub async fn cancel_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+) -> ServiceResult<Json<PaymentRunWithItems>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let payment_run = state.payment_run_service
+        .cancel_run(run_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(payment_run))
+}
+
+/// Downloads the bank upload file; `format` is BCA_CSV, MANDIRI_MCM or PAIN_001.
+pub async fn export_payment_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<impl IntoResponse> {
+    let company_id = extract_company_id(&headers)?;
+
+    let format = params.get("format")
+        .ok_or_else(|| ServiceError::Validation("Missing format parameter".to_string()))?
+        .parse::<BankFileFormat>()
+        .map_err(ServiceError::Validation)?;
+
+    let file = state.payment_run_service
+        .export_run(run_id, company_id, format)
+        .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, file.content_type),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file.file_name)),
+        ],
+        file.content,
+    ))
+}
+
+pub async fn get_payment_run_remittances(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<Uuid>,
+) -> ServiceResult<Json<Vec<RemittanceAdvice>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let remittances = state.payment_run_service
+        .get_remittances(run_id, company_id)
+        .await?;
+
+    Ok(Json(remittances))
+}
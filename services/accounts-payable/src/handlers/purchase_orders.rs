@@ -0,0 +1,166 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_purchase_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreatePurchaseOrderRequest>,
+) -> ServiceResult<Json<PurchaseOrderWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let purchase_order = state.purchase_order_service
+        .create_purchase_order(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(purchase_order))
+}
+
+pub async fn get_purchase_orders(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<PurchaseOrder>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let filters = PurchaseOrderFilters {
+        status: params.get("status")
+            .map(|s| s.parse::<PurchaseOrderStatus>())
+            .transpose()
+            .map_err(ServiceError::Validation)?,
+        vendor_id: params.get("vendor_id").and_then(|id| Uuid::parse_str(id).ok()),
+        date_from: params.get("date_from")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        date_to: params.get("date_to")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+    };
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let purchase_orders = state.purchase_order_service
+        .get_purchase_orders(company_id, filters, pagination)
+        .await?;
+
+    Ok(Json(purchase_orders))
+}
+
+pub async fn get_purchase_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+) -> ServiceResult<Json<PurchaseOrderWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let purchase_order = state.purchase_order_service
+        .get_purchase_order(po_id, company_id)
+        .await?;
+
+    Ok(Json(purchase_order))
+}
+
+pub async fn approve_purchase_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+) -> ServiceResult<Json<PurchaseOrderWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let purchase_order = state.purchase_order_service
+        .approve_purchase_order(po_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(purchase_order))
+}
+
+pub async fn close_purchase_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+) -> ServiceResult<Json<PurchaseOrderWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let purchase_order = state.purchase_order_service
+        .close_purchase_order(po_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(purchase_order))
+}
+
+pub async fn cancel_purchase_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+) -> ServiceResult<Json<PurchaseOrderWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let purchase_order = state.purchase_order_service
+        .cancel_purchase_order(po_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(purchase_order))
+}
+
+pub async fn create_goods_receipt(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+    Json(payload): Json<CreateGoodsReceiptRequest>,
+) -> ServiceResult<Json<GoodsReceiptWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let receipt = state.purchase_order_service
+        .receive_goods(po_id, company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(receipt))
+}
+
+pub async fn get_goods_receipts(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(po_id): Path<Uuid>,
+) -> ServiceResult<Json<Vec<GoodsReceiptWithLines>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let receipts = state.purchase_order_service
+        .get_receipts(po_id, company_id)
+        .await?;
+
+    Ok(Json(receipts))
+}
+
+pub async fn get_open_po_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<OpenPurchaseOrderReport>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let vendor_id = params.get("vendor_id").and_then(|id| Uuid::parse_str(id).ok());
+
+    let report = state.purchase_order_service
+        .get_open_po_report(company_id, vendor_id)
+        .await?;
+
+    Ok(Json(report))
+}
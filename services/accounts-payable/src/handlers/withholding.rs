@@ -0,0 +1,43 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*, PaginationParams};
+
+pub async fn get_withholding_slips(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<WithholdingSlip>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let vendor_id = params.get("vendor_id").and_then(|id| Uuid::parse_str(id).ok());
+    let date_from = params.get("date_from")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let date_to = params.get("date_to")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let slips = state.withholding_service
+        .get_slips(company_id, vendor_id, date_from, date_to, pagination)
+        .await?;
+
+    Ok(Json(slips))
+}
+
+pub async fn get_withholding_slip(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(slip_id): Path<Uuid>,
+) -> ServiceResult<Json<WithholdingSlip>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let slip = state.withholding_service
+        .get_slip(slip_id, company_id)
+        .await?;
+
+    Ok(Json(slip))
+}
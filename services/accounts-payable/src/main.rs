@@ -1,5 +1,6 @@
 mod handlers;
 mod models;
+mod scheduler;
 mod services;
 mod utils;
 
@@ -15,6 +16,13 @@ pub struct AppState {
     invoice_service: services::InvoiceService,
     payment_service: services::PaymentService,
     aging_service: services::AgingService,
+    purchase_order_service: services::PurchaseOrderService,
+    matching_service: services::MatchingService,
+    payment_run_service: services::PaymentRunService,
+    withholding_service: services::WithholdingService,
+    credit_memo_service: services::CreditMemoService,
+    advance_service: services::AdvanceService,
+    ledger_outbox: services::LedgerOutbox,
 }
 
 #[tokio::main]
@@ -33,6 +41,16 @@ async fn main() -> anyhow::Result<()> {
     let invoice_service = services::InvoiceService::new(pool.clone());
     let payment_service = services::PaymentService::new(pool.clone());
     let aging_service = services::AgingService::new(pool.clone());
+    let purchase_order_service = services::PurchaseOrderService::new(pool.clone());
+    let matching_service = services::MatchingService::new(pool.clone());
+    let payment_run_service = services::PaymentRunService::new(pool.clone());
+    let withholding_service = services::WithholdingService::new(pool.clone());
+    let credit_memo_service = services::CreditMemoService::new(pool.clone());
+    let advance_service = services::AdvanceService::new(pool.clone());
+    let ledger_outbox = services::LedgerOutbox::new(pool.clone());
+
+    // Sends ledger postings that could not be sent when they were queued
+    tokio::spawn(scheduler::run(pool.clone()));
 
     let app_state = Arc::new(AppState {
         db: pool,
@@ -40,6 +58,13 @@ async fn main() -> anyhow::Result<()> {
         invoice_service,
         payment_service,
         aging_service,
+        purchase_order_service,
+        matching_service,
+        payment_run_service,
+        withholding_service,
+        credit_memo_service,
+        advance_service,
+        ledger_outbox,
     });
 
     let app = Router::new()
@@ -56,8 +81,51 @@ async fn main() -> anyhow::Result<()> {
         .route("/invoices/:id/status", put(update_invoice_status))
         .route("/invoices/:id/pay", put(pay_vendor_invoice))
         .route("/invoices/:id/payments", get(get_payment_history))
+        .route("/invoices/:id/match", get(get_invoice_match))
+        .route("/invoices/:id/match", post(rematch_invoice))
+        .route("/payments", get(get_payments))
         .route("/payments/:id/reverse", put(reverse_payment))
+        .route("/payment-runs", post(create_payment_run))
+        .route("/payment-runs", get(get_payment_runs))
+        .route("/payment-runs/:id", get(get_payment_run))
+        .route("/payment-runs/:id/items/:item_id", axum::routing::delete(remove_payment_run_item))
+        .route("/payment-runs/:id/approve", post(approve_payment_run))
+        .route("/payment-runs/:id/execute", post(execute_payment_run))
+        .route("/payment-runs/:id/cancel", post(cancel_payment_run))
+        .route("/payment-runs/:id/export", get(export_payment_run))
+        .route("/payment-runs/:id/remittances", get(get_payment_run_remittances))
+        .route("/withholding-slips", get(get_withholding_slips))
+        .route("/withholding-slips/:id", get(get_withholding_slip))
+        .route("/credit-memos", post(create_credit_memo))
+        .route("/credit-memos", get(get_credit_memos))
+        .route("/credit-memos/:id", get(get_credit_memo))
+        .route("/credit-memos/:id/approve", post(approve_credit_memo))
+        .route("/credit-memos/:id/cancel", post(cancel_credit_memo))
+        .route("/credit-memos/:id/applications", post(apply_credit_memo))
+        .route("/credit-memos/:id/refunds", post(refund_credit_memo))
+        .route("/vendor-advances", post(create_vendor_advance))
+        .route("/vendor-advances", get(get_vendor_advances))
+        .route("/vendor-advances/aging-report", get(get_advance_aging_report))
+        .route("/vendor-advances/:id", get(get_vendor_advance))
+        .route("/vendor-advances/:id/applications", post(apply_vendor_advance))
+        .route("/vendor-advances/:id/refunds", post(refund_vendor_advance))
+        .route("/vendor-advances/:id/cancel", post(cancel_vendor_advance))
+        .route("/purchase-orders", post(create_purchase_order))
+        .route("/purchase-orders", get(get_purchase_orders))
+        .route("/purchase-orders/open-report", get(get_open_po_report))
+        .route("/purchase-orders/:id", get(get_purchase_order))
+        .route("/purchase-orders/:id/approve", post(approve_purchase_order))
+        .route("/purchase-orders/:id/close", post(close_purchase_order))
+        .route("/purchase-orders/:id/cancel", post(cancel_purchase_order))
+        .route("/purchase-orders/:id/receipts", post(create_goods_receipt))
+        .route("/purchase-orders/:id/receipts", get(get_goods_receipts))
+        .route("/matching-exceptions", get(get_matching_exceptions))
+        .route("/matching-settings", get(get_matching_settings))
+        .route("/matching-settings", put(update_matching_settings))
         .route("/aging-report", get(get_aging_report))
+        .route("/subledger-balance", get(get_subledger_balance))
+        .route("/ledger-postings", get(get_ledger_postings))
+        .route("/ledger-postings/:id/retry", post(retry_ledger_posting))
         .with_state(app_state);
 
     let bind_addr = std::env::var("ACCOUNTS_PAYABLE_SERVICE_BIND")
@@ -53,6 +53,18 @@ pub struct Vendor {
     pub phone: Option<String>,
     pub email: Option<String>,
     pub payment_terms: i32,
+    /// Selects the vendor's posting rules in the general ledger
+    pub vendor_group: Option<String>,
+    /// Destination of transfers in payment run bank files
+    pub bank_name: Option<String>,
+    pub bank_account_number: Option<String>,
+    pub bank_account_name: Option<String>,
+    /// Discount for paying within the given days of the invoice date,
+    /// e.g. 2% within 10 days
+    pub early_payment_discount_percent: Decimal,
+    pub early_payment_discount_days: i32,
+    /// Income tax withheld from payments to the vendor
+    pub withholding_profile: String,
     pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
@@ -75,8 +87,13 @@ pub struct VendorInvoice {
     pub status: InvoiceStatus,
     pub description: Option<String>,
     pub journal_entry_id: Option<Uuid>,
+    /// Whether the ledger has booked the last approval or cancellation
+    pub posting_status: PostingStatus,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Budgets the invoice would exceed, when the ledger has budget warnings enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub budget_warnings: Vec<database::budgets::BudgetWarning>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,15 +101,58 @@ pub struct VendorPayment {
     pub id: Uuid,
     pub invoice_id: Uuid,
     pub company_id: Uuid,
+    pub payment_number: String,
     pub payment_amount: Decimal,
     pub payment_date: NaiveDate,
     pub payment_method: String,
     pub bank_account_id: Option<Uuid>,
     pub payment_reference: Option<String>,
+    pub check_number: Option<String>,
+    /// Early-payment discount settled along with the payment
+    pub discount_amount: Decimal,
+    /// Income tax withheld from the payment; the bank paid the rest
+    pub withholding_amount: Decimal,
+    pub is_reversed: bool,
+    pub journal_entry_id: Option<Uuid>,
+    pub reversal_journal_entry_id: Option<Uuid>,
+    /// A `PostingStatus`; the ledger books payments and their reversals
+    /// after they are saved
+    pub posting_status: String,
     pub created_by: Uuid,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorInvoiceLine {
+    pub id: Uuid,
+    pub invoice_id: Uuid,
+    pub line_number: i32,
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub line_amount: Decimal,
+    /// Expense or inventory account debited when the invoice is approved;
+    /// the posting rules decide when not set
+    pub account_id: Option<Uuid>,
+    pub item_category: Option<String>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    /// Purchase order line the invoice line bills
+    pub po_line_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VendorInvoiceLineRequest {
+    pub description: String,
+    pub quantity: Option<Decimal>,
+    pub unit_price: Decimal,
+    pub account_id: Option<Uuid>,
+    pub item_category: Option<String>,
+    pub po_line_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateVendorRequest {
     pub company_id: Uuid,
@@ -106,6 +166,17 @@ pub struct CreateVendorRequest {
     #[validate(email(message = "Invalid email format"))]
     pub email: Option<String>,
     pub payment_terms: Option<i32>,
+    #[validate(length(min = 1, max = 50, message = "Vendor group must be 1-50 characters"))]
+    pub vendor_group: Option<String>,
+    pub bank_name: Option<String>,
+    #[validate(length(min = 1, max = 50, message = "Bank account number must be 1-50 characters"))]
+    pub bank_account_number: Option<String>,
+    pub bank_account_name: Option<String>,
+    #[validate(range(min = 0, max = 100, message = "Early payment discount must be between 0 and 100 percent"))]
+    pub early_payment_discount_percent: Option<Decimal>,
+    #[validate(range(min = 0, message = "Early payment discount days cannot be negative"))]
+    pub early_payment_discount_days: Option<i32>,
+    pub withholding_profile: Option<WithholdingProfile>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -118,6 +189,20 @@ pub struct UpdateVendorRequest {
     #[validate(email(message = "Invalid email format"))]
     pub email: Option<String>,
     pub payment_terms: i32,
+    #[validate(length(min = 1, max = 50, message = "Vendor group must be 1-50 characters"))]
+    pub vendor_group: Option<String>,
+    pub bank_name: Option<String>,
+    #[validate(length(min = 1, max = 50, message = "Bank account number must be 1-50 characters"))]
+    pub bank_account_number: Option<String>,
+    pub bank_account_name: Option<String>,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 100, message = "Early payment discount must be between 0 and 100 percent"))]
+    pub early_payment_discount_percent: Decimal,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Early payment discount days cannot be negative"))]
+    pub early_payment_discount_days: i32,
+    #[serde(default)]
+    pub withholding_profile: WithholdingProfile,
     pub is_active: bool,
 }
 
@@ -132,11 +217,21 @@ pub struct CreateVendorInvoiceRequest {
     pub subtotal: Decimal,
     #[validate(range(min = 0, message = "Tax amount cannot be negative"))]
     pub tax_amount: Decimal,
+    /// Defaults to the vendor's currency
+    #[serde(default)]
+    pub currency: Option<String>,
     pub description: Option<String>,
+    /// Must add up to the subtotal. Lines without an account post to the
+    /// expense account the ledger's posting rules give them. Without lines
+    /// the subtotal is booked to the vendor's expense account.
+    #[serde(default)]
+    pub lines: Vec<VendorInvoiceLineRequest>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct PaymentRequest {
+    /// Amount settled on the invoice; tax withheld from the vendor comes
+    /// out of it
     #[validate(range(min = 0.01, message = "Payment amount must be positive"))]
     pub payment_amount: Decimal,
     pub payment_date: NaiveDate,
@@ -153,6 +248,14 @@ pub struct InvoiceFilters {
     pub date_to: Option<NaiveDate>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentFilters {
+    pub bank_account_id: Option<Uuid>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub ids: Vec<Uuid>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VendorStatistics {
     pub vendor_id: Uuid,
@@ -161,6 +264,25 @@ pub struct VendorStatistics {
     pub paid_amount: Decimal,
     pub outstanding_amount: Decimal,
     pub average_invoice_amount: Decimal,
+    /// Approved credit memos, and what is left of them to apply or refund
+    pub total_credit_amount: Decimal,
+    pub unapplied_credit_amount: Decimal,
+    /// Outstanding less unapplied credits
+    pub net_outstanding_amount: Decimal,
+    /// Down payments not yet applied or refunded
+    pub open_advance_amount: Decimal,
+}
+
+/// Open payables in the functional currency at the rates they were booked
+/// at, for reconciling the ledger's AP control account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubledgerBalance {
+    pub company_id: Uuid,
+    pub open_invoices: Decimal,
+    /// Credit memos not yet applied or refunded
+    pub open_credits: Decimal,
+    /// Open invoices less open credits
+    pub outstanding: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -169,6 +291,9 @@ pub struct AgingReport {
     pub report_date: NaiveDate,
     pub summary: AgingSummary,
     pub vendor_details: Vec<VendorAgingDetail>,
+    /// Currencies with no rate on the report date; their documents are
+    /// listed but not included in any total
+    pub unrated_currencies: Vec<String>,
     pub generated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -178,8 +303,10 @@ pub struct AgingSummary {
     pub days_31_60: Decimal,
     pub days_61_90: Decimal,
     pub over_90_days: Decimal,
+    /// Net of unapplied credits, which age from their memo date
     pub total_outstanding: Decimal,
     pub invoice_count: usize,
+    pub credit_memo_count: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -192,6 +319,7 @@ pub struct VendorAgingDetail {
     pub over_90_days: Decimal,
     pub total_outstanding: Decimal,
     pub invoices: Vec<InvoiceAgingItem>,
+    pub credit_memos: Vec<CreditMemoAgingItem>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -202,4 +330,1193 @@ pub struct InvoiceAgingItem {
     pub due_date: NaiveDate,
     pub days_overdue: i32,
     pub outstanding_amount: Decimal,
-}
\ No newline at end of file
+    /// Set when no rate was available; the amount is then in this currency
+    pub unrated_currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditMemoAgingItem {
+    pub credit_memo_id: Uuid,
+    pub credit_memo_number: String,
+    pub memo_date: NaiveDate,
+    pub days_outstanding: i32,
+    /// Deducted from the vendor's buckets
+    pub unapplied_amount: Decimal,
+    /// Set when no rate was available; the amount is then in this currency
+    pub unrated_currency: Option<String>,
+}
+
+/// Posting events AP raises in the general ledger
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerEvent {
+    VendorInvoiceApproved,
+    VendorPaymentMade,
+    VendorCreditReceived,
+    VendorRefundReceived,
+    VendorAdvancePaid,
+    VendorAdvanceApplied,
+    VendorAdvanceRefunded,
+    GoodsReceived,
+}
+
+/// Account roles AP posts to; the ledger's posting rules map them to accounts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerAccountRole {
+    ApControl,
+    Expense,
+    InputVat,
+    Bank,
+    PurchaseDiscount,
+    WithholdingTax,
+    VendorAdvance,
+    Inventory,
+    GoodsReceivedNotInvoiced,
+    PurchasePriceVariance,
+    RealizedFxGainLoss,
+}
+
+/// Entry the general ledger service is asked to post for an AP document;
+/// mirrors the ledger's subledger posting request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerPostingRequest {
+    pub source_document_type: String,
+    pub source_document_id: Uuid,
+    pub entry_date: NaiveDate,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub event_type: LedgerEvent,
+    pub vendor_group: Option<String>,
+    pub lines: Vec<LedgerPostingLine>,
+}
+
+/// Addressed by account id when the document names one, otherwise by role.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LedgerPostingLine {
+    pub account_id: Option<Uuid>,
+    pub account_role: Option<LedgerAccountRole>,
+    pub item_category: Option<String>,
+    pub tax_type: Option<String>,
+    pub description: Option<String>,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    /// Set on monetary lines of foreign-currency documents so the ledger
+    /// can revalue them
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub foreign: Option<ForeignAmount>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ForeignAmount {
+    pub currency: String,
+    pub foreign_amount: Decimal,
+    pub exchange_rate: Decimal,
+}
+
+/// Currency of an AP document and the rate it is booked at in the ledger's
+/// functional currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookingRate {
+    pub currency: Option<String>,
+    pub rate: Decimal,
+    pub functional_currency: String,
+}
+
+impl BookingRate {
+    /// The document's rate from the ledger's rate table, if it has one.
+    pub fn from_table(table: &database::exchange_rates::RateTable, currency: Option<&str>) -> Option<Self> {
+        Some(Self {
+            currency: currency.map(str::to_uppercase),
+            rate: table.rate_for(currency)?,
+            functional_currency: table.functional_currency.clone(),
+        })
+    }
+
+    /// The document's currency, when it is not the functional one.
+    pub fn foreign_currency(&self) -> Option<&str> {
+        self.currency
+            .as_deref()
+            .filter(|c| !c.eq_ignore_ascii_case(&self.functional_currency))
+    }
+
+    /// `amount` in the functional currency, rounded the way the ledger
+    /// rounds converted amounts.
+    pub fn to_functional(&self, amount: Decimal) -> Decimal {
+        match self.foreign_currency() {
+            Some(_) => utils::CurrencyUtils::round_to_currency(amount * self.rate, &self.functional_currency),
+            None => amount.round_dp(2),
+        }
+    }
+
+    /// Currency fields for a line booking `amount` of the document's
+    /// currency; none for functional-currency documents.
+    pub fn foreign(&self, amount: Decimal) -> Option<ForeignAmount> {
+        self.foreign_currency().map(|currency| ForeignAmount {
+            currency: currency.to_string(),
+            foreign_amount: amount,
+            exchange_rate: self.rate,
+        })
+    }
+}
+
+/// Spending checked against the ledger's active budgets; mirrors the
+/// ledger's budget check request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerBudgetCheckRequest {
+    pub date: NaiveDate,
+    pub event_type: LedgerEvent,
+    pub vendor_group: Option<String>,
+    pub lines: Vec<LedgerBudgetCheckLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LedgerBudgetCheckLine {
+    pub account_id: Option<Uuid>,
+    pub item_category: Option<String>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+/// The part of the ledger's journal entry response AP keeps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostedJournalEntry {
+    pub id: Uuid,
+    pub entry_number: String,
+    #[serde(default)]
+    pub reversed_by_entry_id: Option<Uuid>,
+}
+
+/// Where an AP document stands with the general ledger.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PostingStatus {
+    /// Nothing to book yet, e.g. a draft invoice
+    NotPosted,
+    /// Saved; the ledger posting is queued
+    PendingPosting,
+    Posted,
+    /// The ledger rejected the posting; it is retried from the outbox
+    PostingFailed,
+}
+
+impl std::str::FromStr for PostingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "NOT_POSTED" => Ok(PostingStatus::NotPosted),
+            "PENDING_POSTING" => Ok(PostingStatus::PendingPosting),
+            "POSTED" => Ok(PostingStatus::Posted),
+            "POSTING_FAILED" => Ok(PostingStatus::PostingFailed),
+            _ => Err(format!("Invalid posting status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PostingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostingStatus::NotPosted => write!(f, "NOT_POSTED"),
+            PostingStatus::PendingPosting => write!(f, "PENDING_POSTING"),
+            PostingStatus::Posted => write!(f, "POSTED"),
+            PostingStatus::PostingFailed => write!(f, "POSTING_FAILED"),
+        }
+    }
+}
+
+/// A queued ledger posting or reversal of an AP document.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerOutboxEntry {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub source_document_type: String,
+    pub source_document_id: Uuid,
+    /// POST or REVERSE
+    pub action: String,
+    /// PENDING, POSTED, FAILED or CANCELLED
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub requested_by: Uuid,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub posted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Approved,
+    PartiallyReceived,
+    Received,
+    Closed,
+    Cancelled,
+}
+
+impl std::str::FromStr for PurchaseOrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DRAFT" => Ok(PurchaseOrderStatus::Draft),
+            "APPROVED" => Ok(PurchaseOrderStatus::Approved),
+            "PARTIALLY_RECEIVED" => Ok(PurchaseOrderStatus::PartiallyReceived),
+            "RECEIVED" => Ok(PurchaseOrderStatus::Received),
+            "CLOSED" => Ok(PurchaseOrderStatus::Closed),
+            "CANCELLED" => Ok(PurchaseOrderStatus::Cancelled),
+            _ => Err(format!("Invalid purchase order status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PurchaseOrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PurchaseOrderStatus::Draft => write!(f, "DRAFT"),
+            PurchaseOrderStatus::Approved => write!(f, "APPROVED"),
+            PurchaseOrderStatus::PartiallyReceived => write!(f, "PARTIALLY_RECEIVED"),
+            PurchaseOrderStatus::Received => write!(f, "RECEIVED"),
+            PurchaseOrderStatus::Closed => write!(f, "CLOSED"),
+            PurchaseOrderStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub vendor_id: Uuid,
+    pub vendor_name: Option<String>,
+    pub po_number: String,
+    pub po_date: NaiveDate,
+    pub expected_delivery_date: Option<NaiveDate>,
+    /// Currency the order is priced in; the vendor's unless given
+    pub currency: Option<String>,
+    pub subtotal: Decimal,
+    pub tax_amount: Decimal,
+    pub total_amount: Decimal,
+    pub status: String,
+    pub notes: Option<String>,
+    pub created_by: Uuid,
+    pub approved_by: Option<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderLine {
+    pub id: Uuid,
+    pub purchase_order_id: Uuid,
+    pub line_number: i32,
+    /// Inventory item; receipts of item lines are booked into stock
+    pub item_id: Option<Uuid>,
+    pub item_code: Option<String>,
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    /// Percentage, e.g. 11 for PPN
+    pub tax_rate: Decimal,
+    pub line_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub received_quantity: Decimal,
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderWithLines {
+    pub purchase_order: PurchaseOrder,
+    pub lines: Vec<PurchaseOrderLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreatePurchaseOrderRequest {
+    pub vendor_id: Uuid,
+    pub po_date: NaiveDate,
+    pub expected_delivery_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub notes: Option<String>,
+    #[validate(length(min = 1, message = "Purchase order must have at least one line"))]
+    pub lines: Vec<PurchaseOrderLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderLineRequest {
+    pub item_id: Option<Uuid>,
+    pub item_code: Option<String>,
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub tax_rate: Option<Decimal>,
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderFilters {
+    pub status: Option<PurchaseOrderStatus>,
+    pub vendor_id: Option<Uuid>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoodsReceipt {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub purchase_order_id: Uuid,
+    pub receipt_number: String,
+    pub receipt_date: NaiveDate,
+    pub notes: Option<String>,
+    /// Order currency and the rate received stock is valued at
+    pub currency: Option<String>,
+    pub exchange_rate: Option<Decimal>,
+    /// Inventory booked against goods received not invoiced
+    pub journal_entry_id: Option<Uuid>,
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoodsReceiptLine {
+    pub id: Uuid,
+    pub receipt_id: Uuid,
+    pub po_line_id: Uuid,
+    pub quantity: Decimal,
+    /// Functional-currency cost of item lines, which go into stock
+    pub unit_cost: Option<Decimal>,
+    /// Stock IN transaction in the inventory service, for item lines; unset
+    /// until the inventory service has booked it
+    pub inventory_transaction_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoodsReceiptWithLines {
+    pub receipt: GoodsReceipt,
+    pub lines: Vec<GoodsReceiptLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateGoodsReceiptRequest {
+    pub receipt_date: NaiveDate,
+    pub notes: Option<String>,
+    #[validate(length(min = 1, message = "Goods receipt must have at least one line"))]
+    pub lines: Vec<GoodsReceiptLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoodsReceiptLineRequest {
+    pub po_line_id: Uuid,
+    pub quantity: Decimal,
+}
+
+/// Ordered vs received vs invoiced for one line of an open purchase order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenPurchaseOrderLine {
+    pub purchase_order_id: Uuid,
+    pub po_number: String,
+    pub po_date: NaiveDate,
+    pub expected_delivery_date: Option<NaiveDate>,
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub status: String,
+    pub po_line_id: Uuid,
+    pub line_number: i32,
+    pub item_code: Option<String>,
+    pub description: String,
+    pub unit_price: Decimal,
+    pub ordered_quantity: Decimal,
+    pub received_quantity: Decimal,
+    pub invoiced_quantity: Decimal,
+    /// Still to be delivered
+    pub open_quantity: Decimal,
+    /// Received but not yet billed
+    pub uninvoiced_quantity: Decimal,
+    pub open_amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenPurchaseOrderReport {
+    pub company_id: Uuid,
+    pub report_date: NaiveDate,
+    pub lines: Vec<OpenPurchaseOrderLine>,
+    pub total_open_amount: Decimal,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The part of the inventory service's transaction response AP keeps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedStockTransaction {
+    pub id: Uuid,
+}
+
+/// Outcome of matching an invoice (or one of its lines) against the
+/// purchase order and goods receipts it bills.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MatchStatus {
+    /// No line of the invoice references a purchase order
+    NotRequired,
+    Matched,
+    /// At least one line is outside the price or quantity tolerance
+    Exception,
+}
+
+impl std::str::FromStr for MatchStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "NOT_REQUIRED" => Ok(MatchStatus::NotRequired),
+            "MATCHED" => Ok(MatchStatus::Matched),
+            "EXCEPTION" => Ok(MatchStatus::Exception),
+            _ => Err(format!("Invalid match status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for MatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchStatus::NotRequired => write!(f, "NOT_REQUIRED"),
+            MatchStatus::Matched => write!(f, "MATCHED"),
+            MatchStatus::Exception => write!(f, "EXCEPTION"),
+        }
+    }
+}
+
+/// Tolerances for three-way matching, as percentages. A company without
+/// settings matches exactly.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchingSettings {
+    pub company_id: Uuid,
+    /// Allowed deviation of the invoiced unit price from the PO price
+    pub price_tolerance_percent: Decimal,
+    /// Allowed quantity billed beyond what was received
+    pub quantity_tolerance_percent: Decimal,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateMatchingSettingsRequest {
+    #[validate(range(min = 0, max = 100, message = "Price tolerance must be between 0 and 100 percent"))]
+    pub price_tolerance_percent: Decimal,
+    #[validate(range(min = 0, max = 100, message = "Quantity tolerance must be between 0 and 100 percent"))]
+    pub quantity_tolerance_percent: Decimal,
+}
+
+/// One invoice line compared with its purchase order line and receipts
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvoiceLineMatch {
+    pub invoice_line_id: Uuid,
+    pub line_number: i32,
+    pub po_line_id: Uuid,
+    pub po_number: String,
+    pub ordered_quantity: Decimal,
+    pub received_quantity: Decimal,
+    /// Billed on other invoices and earlier lines of this one
+    pub previously_invoiced_quantity: Decimal,
+    pub invoice_quantity: Decimal,
+    pub po_unit_price: Decimal,
+    pub invoice_unit_price: Decimal,
+    /// Invoiced unit price minus the PO price
+    pub price_variance: Decimal,
+    pub price_variance_percent: Option<Decimal>,
+    /// Quantity billed in total beyond what was received
+    pub quantity_variance: Decimal,
+    pub status: MatchStatus,
+    /// Why the line is outside tolerance
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvoiceMatch {
+    pub invoice_id: Uuid,
+    pub invoice_number: String,
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub invoice_date: NaiveDate,
+    pub total_amount: Decimal,
+    pub invoice_status: String,
+    pub match_status: MatchStatus,
+    pub matched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub lines: Vec<InvoiceLineMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PaymentRunStatus {
+    Draft,
+    Approved,
+    Completed,
+    Cancelled,
+}
+
+impl std::str::FromStr for PaymentRunStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DRAFT" => Ok(PaymentRunStatus::Draft),
+            "APPROVED" => Ok(PaymentRunStatus::Approved),
+            "COMPLETED" => Ok(PaymentRunStatus::Completed),
+            "CANCELLED" => Ok(PaymentRunStatus::Cancelled),
+            _ => Err(format!("Invalid payment run status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PaymentRunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentRunStatus::Draft => write!(f, "DRAFT"),
+            PaymentRunStatus::Approved => write!(f, "APPROVED"),
+            PaymentRunStatus::Completed => write!(f, "COMPLETED"),
+            PaymentRunStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+/// A batch of vendor payments made on one date from one bank account
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentRun {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub run_number: String,
+    pub payment_date: NaiveDate,
+    pub currency: String,
+    /// Invoices due on or before this date were selected
+    pub due_date_to: NaiveDate,
+    /// Bank account credited in the ledger
+    pub bank_account_id: Option<Uuid>,
+    /// Company account debited at the bank
+    pub source_account_number: String,
+    pub source_account_name: String,
+    pub status: String,
+    pub total_outstanding: Decimal,
+    pub total_discount: Decimal,
+    pub total_payment: Decimal,
+    /// Withheld from the payments; the bank transfers the rest
+    pub total_withholding: Decimal,
+    pub created_by: Uuid,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentRunItem {
+    pub id: Uuid,
+    pub run_id: Uuid,
+    pub invoice_id: Uuid,
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub outstanding_amount: Decimal,
+    pub discount_amount: Decimal,
+    pub payment_amount: Decimal,
+    pub withholding_amount: Decimal,
+    /// Set once the run has paid the invoice
+    pub payment_id: Option<Uuid>,
+    pub payment_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentRunWithItems {
+    pub payment_run: PaymentRun,
+    pub items: Vec<PaymentRunItem>,
+}
+
+/// Proposes a run of the approved invoices due by `due_date_to`. With
+/// `take_early_discounts`, invoices not yet due are included too while
+/// their vendor's early-payment discount is still available.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreatePaymentRunRequest {
+    pub payment_date: NaiveDate,
+    pub due_date_to: NaiveDate,
+    /// Limits the run to these vendors; all vendors when empty
+    #[serde(default)]
+    pub vendor_ids: Vec<Uuid>,
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter code"))]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub take_early_discounts: bool,
+    pub bank_account_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Source account number must be 1-50 characters"))]
+    pub source_account_number: String,
+    #[validate(length(min = 1, max = 255, message = "Source account name must be 1-255 characters"))]
+    pub source_account_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BankFileFormat {
+    /// KlikBCA Bisnis bulk transfer upload
+    BcaCsv,
+    /// Mandiri Cash Management bulk transfer upload
+    MandiriMcm,
+    /// ISO 20022 customer credit transfer initiation
+    Pain001,
+}
+
+impl std::str::FromStr for BankFileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "BCA_CSV" => Ok(BankFileFormat::BcaCsv),
+            "MANDIRI_MCM" => Ok(BankFileFormat::MandiriMcm),
+            "PAIN_001" | "PAIN001" => Ok(BankFileFormat::Pain001),
+            _ => Err(format!("Invalid bank file format: {}", s))
+        }
+    }
+}
+
+/// A generated bank upload file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankFile {
+    pub file_name: String,
+    pub content_type: String,
+    pub content: String,
+}
+
+/// What a run pays one vendor, sent to the vendor as remittance advice
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemittanceAdvice {
+    pub run_number: String,
+    pub payment_date: NaiveDate,
+    pub currency: String,
+    pub vendor_id: Uuid,
+    pub vendor_code: String,
+    pub vendor_name: String,
+    pub vendor_email: Option<String>,
+    pub bank_name: Option<String>,
+    pub bank_account_number: Option<String>,
+    pub bank_account_name: Option<String>,
+    pub lines: Vec<RemittanceLine>,
+    pub total_outstanding: Decimal,
+    pub total_discount: Decimal,
+    pub total_payment: Decimal,
+    pub total_withholding: Decimal,
+    /// Payment less withholding, the amount transferred
+    pub total_transfer: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemittanceLine {
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    pub payment_number: Option<String>,
+    pub outstanding_amount: Decimal,
+    pub discount_amount: Decimal,
+    pub payment_amount: Decimal,
+    pub withholding_amount: Decimal,
+}
+
+/// Income tax a company withholds when paying a vendor
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WithholdingProfile {
+    #[default]
+    None,
+    /// PPh 23 on services: 2% of the amount before VAT, 4% when the vendor
+    /// has no NPWP
+    Pph23,
+}
+
+impl std::str::FromStr for WithholdingProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "NONE" => Ok(WithholdingProfile::None),
+            "PPH23" => Ok(WithholdingProfile::Pph23),
+            _ => Err(format!("Invalid withholding profile: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for WithholdingProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WithholdingProfile::None => write!(f, "NONE"),
+            WithholdingProfile::Pph23 => write!(f, "PPH23"),
+        }
+    }
+}
+
+/// Bukti potong: the numbered slip given to a vendor for the tax withheld
+/// from one payment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WithholdingSlip {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub slip_number: String,
+    pub payment_id: Uuid,
+    pub payment_number: String,
+    pub invoice_id: Uuid,
+    pub invoice_number: String,
+    pub vendor_id: Uuid,
+    pub tax_type: String,
+    pub withholding_date: NaiveDate,
+    pub vendor_npwp: Option<String>,
+    pub vendor_name: String,
+    /// In rupiah, whatever the invoice currency
+    pub tax_base_amount: Decimal,
+    pub tax_rate: Decimal,
+    pub tax_amount: Decimal,
+    /// Transaction the tax service recorded for the slip
+    pub tax_transaction_id: Option<Uuid>,
+    /// When the tax service recorded the slip; unset until it has
+    pub tax_reported_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// ISSUED, or CANCELLED once the payment is reversed
+    pub status: String,
+    pub cancelled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Withholding reported to the tax service; mirrors its tax transaction
+/// request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxTransactionRequest {
+    pub tax_type: String,
+    pub transaction_date: NaiveDate,
+    /// First day of the month the tax is reported in
+    pub tax_period: NaiveDate,
+    pub tax_base_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub tax_invoice_number: Option<String>,
+    pub vendor_npwp: Option<String>,
+    pub vendor_name: Option<String>,
+    pub description: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub source_document_type: String,
+    pub source_document_id: Uuid,
+}
+
+/// The part of the tax service's transaction response AP keeps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedTaxTransaction {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CreditMemoStatus {
+    Draft,
+    /// Booked; its credit can be applied or refunded
+    Approved,
+    /// Fully applied and refunded
+    Closed,
+    Cancelled,
+}
+
+impl std::str::FromStr for CreditMemoStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DRAFT" => Ok(CreditMemoStatus::Draft),
+            "APPROVED" => Ok(CreditMemoStatus::Approved),
+            "CLOSED" => Ok(CreditMemoStatus::Closed),
+            "CANCELLED" => Ok(CreditMemoStatus::Cancelled),
+            _ => Err(format!("Invalid credit memo status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for CreditMemoStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreditMemoStatus::Draft => write!(f, "DRAFT"),
+            CreditMemoStatus::Approved => write!(f, "APPROVED"),
+            CreditMemoStatus::Closed => write!(f, "CLOSED"),
+            CreditMemoStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CreditMemoReason {
+    Return,
+    PriceCorrection,
+    Other,
+}
+
+impl std::fmt::Display for CreditMemoReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreditMemoReason::Return => write!(f, "RETURN"),
+            CreditMemoReason::PriceCorrection => write!(f, "PRICE_CORRECTION"),
+            CreditMemoReason::Other => write!(f, "OTHER"),
+        }
+    }
+}
+
+/// A credit from a vendor, for goods returned or a price corrected: the
+/// vendor's credit memo, or the debit note raised against the vendor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorCreditMemo {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub credit_memo_number: String,
+    /// The vendor's own number for the credit
+    pub vendor_reference: Option<String>,
+    pub memo_date: NaiveDate,
+    pub reason: String,
+    /// Invoice the credit corrects, if any
+    pub invoice_id: Option<Uuid>,
+    pub subtotal: Decimal,
+    pub tax_amount: Decimal,
+    pub total_amount: Decimal,
+    pub applied_amount: Decimal,
+    pub refunded_amount: Decimal,
+    /// Credit left to apply or refund
+    pub unapplied_amount: Decimal,
+    pub currency: String,
+    pub exchange_rate: Decimal,
+    pub status: String,
+    pub description: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub reversal_journal_entry_id: Option<Uuid>,
+    /// Whether the ledger has booked the approval or cancellation, which
+    /// are sent after they are saved
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorCreditMemoLine {
+    pub id: Uuid,
+    pub credit_memo_id: Uuid,
+    pub line_number: i32,
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub line_amount: Decimal,
+    /// Account credited on approval; the posting rules decide when not set
+    pub account_id: Option<Uuid>,
+    pub item_category: Option<String>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorCreditApplication {
+    pub id: Uuid,
+    pub credit_memo_id: Uuid,
+    pub invoice_id: Uuid,
+    pub invoice_number: String,
+    pub amount: Decimal,
+    pub application_date: NaiveDate,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorCreditRefund {
+    pub id: Uuid,
+    pub credit_memo_id: Uuid,
+    pub refund_number: String,
+    pub refund_amount: Decimal,
+    pub refund_date: NaiveDate,
+    pub payment_method: String,
+    pub bank_account_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorCreditMemoWithDetails {
+    pub credit_memo: VendorCreditMemo,
+    pub lines: Vec<VendorCreditMemoLine>,
+    pub applications: Vec<VendorCreditApplication>,
+    pub refunds: Vec<VendorCreditRefund>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreditMemoLineRequest {
+    pub description: String,
+    pub quantity: Option<Decimal>,
+    pub unit_price: Decimal,
+    pub account_id: Option<Uuid>,
+    pub item_category: Option<String>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateCreditMemoRequest {
+    pub vendor_id: Uuid,
+    /// Invoice the credit corrects; the memo takes its currency and rate
+    pub invoice_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Vendor reference must be 1-50 characters"))]
+    pub vendor_reference: Option<String>,
+    pub memo_date: NaiveDate,
+    pub reason: CreditMemoReason,
+    #[validate(range(min = 0.01, message = "Subtotal must be positive"))]
+    pub subtotal: Decimal,
+    #[validate(range(min = 0, message = "Tax amount cannot be negative"))]
+    pub tax_amount: Decimal,
+    pub description: Option<String>,
+    /// Must add up to the subtotal
+    #[validate(length(min = 1, message = "A credit memo needs at least one line"))]
+    pub lines: Vec<CreditMemoLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreditApplicationRequest {
+    pub invoice_id: Uuid,
+    pub amount: Decimal,
+}
+
+/// Applies a credit to open invoices of the same vendor and currency.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ApplyCreditMemoRequest {
+    pub application_date: NaiveDate,
+    #[validate(length(min = 1, message = "Choose at least one invoice"))]
+    pub applications: Vec<CreditApplicationRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreditMemoRefundRequest {
+    #[validate(range(min = 0.01, message = "Refund amount must be positive"))]
+    pub refund_amount: Decimal,
+    pub refund_date: NaiveDate,
+    pub payment_method: String,
+    /// Bank account debited in the ledger
+    pub bank_account_id: Option<Uuid>,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AdvanceStatus {
+    /// Paid; can be applied to invoices or refunded
+    Open,
+    /// Fully applied and refunded
+    Closed,
+    Cancelled,
+}
+
+impl std::str::FromStr for AdvanceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "OPEN" => Ok(AdvanceStatus::Open),
+            "CLOSED" => Ok(AdvanceStatus::Closed),
+            "CANCELLED" => Ok(AdvanceStatus::Cancelled),
+            _ => Err(format!("Invalid advance status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for AdvanceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdvanceStatus::Open => write!(f, "OPEN"),
+            AdvanceStatus::Closed => write!(f, "CLOSED"),
+            AdvanceStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+/// A down payment (uang muka pembelian) made to a vendor before its
+/// invoice, carried as an asset until applied to the invoice
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorAdvance {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub advance_number: String,
+    pub advance_date: NaiveDate,
+    pub purchase_order_id: Option<Uuid>,
+    /// Amount paid, including any PPN on the down payment
+    pub amount: Decimal,
+    /// PPN on the vendor's faktur for the down payment, claimed as input VAT
+    pub tax_amount: Decimal,
+    pub tax_invoice_number: Option<String>,
+    pub applied_amount: Decimal,
+    pub applied_tax_amount: Decimal,
+    pub refunded_amount: Decimal,
+    pub refunded_tax_amount: Decimal,
+    /// Amount left to apply or refund
+    pub open_amount: Decimal,
+    pub currency: String,
+    pub exchange_rate: Decimal,
+    pub payment_method: String,
+    pub bank_account_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub status: String,
+    pub description: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub reversal_journal_entry_id: Option<Uuid>,
+    /// Whether the ledger has booked the payment or cancellation, which are
+    /// sent after they are saved
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorAdvanceApplication {
+    pub id: Uuid,
+    pub advance_id: Uuid,
+    pub invoice_id: Uuid,
+    pub invoice_number: String,
+    pub amount: Decimal,
+    /// Share of the down payment's PPN the application carries
+    pub tax_amount: Decimal,
+    pub application_date: NaiveDate,
+    pub journal_entry_id: Option<Uuid>,
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorAdvanceRefund {
+    pub id: Uuid,
+    pub advance_id: Uuid,
+    pub refund_number: String,
+    pub refund_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub refund_date: NaiveDate,
+    pub payment_method: String,
+    pub bank_account_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub posting_status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorAdvanceWithDetails {
+    pub advance: VendorAdvance,
+    pub applications: Vec<VendorAdvanceApplication>,
+    pub refunds: Vec<VendorAdvanceRefund>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateVendorAdvanceRequest {
+    pub vendor_id: Uuid,
+    /// Order the down payment is for
+    pub purchase_order_id: Option<Uuid>,
+    pub advance_date: NaiveDate,
+    #[validate(range(min = 0.01, message = "Advance amount must be positive"))]
+    pub amount: Decimal,
+    /// PPN on the vendor's faktur for the down payment, included in `amount`
+    #[validate(range(min = 0, message = "Tax amount cannot be negative"))]
+    pub tax_amount: Option<Decimal>,
+    /// Faktur pajak number; required when there is PPN
+    #[validate(length(min = 1, max = 50, message = "Tax invoice number must be 1-50 characters"))]
+    pub tax_invoice_number: Option<String>,
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter code"))]
+    pub currency: Option<String>,
+    pub exchange_rate: Option<Decimal>,
+    pub payment_method: String,
+    /// Bank account credited in the ledger
+    pub bank_account_id: Option<Uuid>,
+    #[validate(length(max = 100, message = "Reference cannot exceed 100 characters"))]
+    pub reference: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdvanceApplicationRequest {
+    pub invoice_id: Uuid,
+    pub amount: Decimal,
+}
+
+/// Applies a down payment to approved invoices of the same vendor and
+/// currency as partial settlement.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ApplyVendorAdvanceRequest {
+    pub application_date: NaiveDate,
+    #[validate(length(min = 1, message = "Choose at least one invoice"))]
+    pub applications: Vec<AdvanceApplicationRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VendorAdvanceRefundRequest {
+    #[validate(range(min = 0.01, message = "Refund amount must be positive"))]
+    pub refund_amount: Decimal,
+    pub refund_date: NaiveDate,
+    pub payment_method: String,
+    /// Bank account debited in the ledger
+    pub bank_account_id: Option<Uuid>,
+    pub reference: Option<String>,
+}
+
+/// Open down payments by how long they have been waiting for an invoice
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvanceAgingReport {
+    pub company_id: Uuid,
+    pub report_date: NaiveDate,
+    pub summary: AdvanceAgingSummary,
+    pub vendor_details: Vec<VendorAdvanceAgingDetail>,
+    /// Currencies with no rate on the report date; their advances are
+    /// listed but not included in any total
+    pub unrated_currencies: Vec<String>,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvanceAgingSummary {
+    pub current: Decimal,       // 0-30 days since payment
+    pub days_31_60: Decimal,
+    pub days_61_90: Decimal,
+    pub over_90_days: Decimal,
+    pub total_open: Decimal,
+    pub advance_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorAdvanceAgingDetail {
+    pub vendor_id: Uuid,
+    pub vendor_name: String,
+    pub current: Decimal,
+    pub days_31_60: Decimal,
+    pub days_61_90: Decimal,
+    pub over_90_days: Decimal,
+    pub total_open: Decimal,
+    pub advances: Vec<AdvanceAgingItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvanceAgingItem {
+    pub advance_id: Uuid,
+    pub advance_number: String,
+    pub advance_date: NaiveDate,
+    pub purchase_order_id: Option<Uuid>,
+    pub days_outstanding: i32,
+    pub open_amount: Decimal,
+    /// Set when no rate was available; the amount is then in this currency
+    pub unrated_currency: Option<String>,
+}
@@ -0,0 +1,45 @@
+//! Background jobs for the accounts payable service
+
+use crate::services::{LedgerOutbox, PurchaseOrderService, WithholdingService};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+pub async fn run(pool: PgPool) {
+    let interval_secs = std::env::var("ACCOUNTS_PAYABLE_SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    let ledger_outbox = LedgerOutbox::new(pool.clone());
+    let purchase_order_service = PurchaseOrderService::new(pool.clone());
+    let withholding_service = WithholdingService::new(pool);
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+
+    info!("Payables scheduler running every {} seconds", interval_secs);
+
+    loop {
+        ticker.tick().await;
+
+        // Ledger postings whose first attempt did not reach the ledger
+        match ledger_outbox.dispatch_pending().await {
+            Ok(0) => {}
+            Ok(count) => info!("Dispatched {} queued ledger postings", count),
+            Err(e) => error!("Ledger posting run failed: {}", e),
+        }
+
+        // Received stock the inventory service has not booked yet
+        match purchase_order_service.book_pending_stock().await {
+            Ok(0) => {}
+            Ok(count) => info!("Booked stock for {} goods receipt lines", count),
+            Err(e) => error!("Goods receipt stock run failed: {}", e),
+        }
+
+        // Withholding slips the tax service has not recorded yet
+        match withholding_service.report_pending_slips().await {
+            Ok(0) => {}
+            Ok(count) => info!("Reported {} withholding slips to the tax service", count),
+            Err(e) => error!("Withholding slip run failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,948 @@
+use crate::models::*;
+use super::{LedgerClient, LedgerOutbox};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Source document type of the ledger entry booked when an advance is paid.
+pub(crate) const ADVANCE_SOURCE: &str = "VENDOR_ADVANCE";
+
+/// Source document type of the ledger entry booked per application.
+pub(crate) const APPLICATION_SOURCE: &str = "VENDOR_ADVANCE_APPLICATION";
+
+/// Source document type of the ledger entry booked for a refund.
+pub(crate) const ADVANCE_REFUND_SOURCE: &str = "VENDOR_ADVANCE_REFUND";
+
+/// Down payments (uang muka pembelian) made to vendors before they invoice.
+/// An advance sits in its own asset account until it is applied to the
+/// vendor's invoices or refunded.
+///
+/// When the vendor issues a faktur for the down payment, its PPN is claimed
+/// as input VAT when the advance is paid. The invoice is booked with the PPN
+/// on the full amount, so applying the advance takes back the share of PPN
+/// already claimed, and a refund does the same for the part refunded.
+pub struct AdvanceService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+    ledger_client: LedgerClient,
+    ledger_outbox: LedgerOutbox,
+}
+
+struct LockedAdvance {
+    advance_number: String,
+    vendor_id: Uuid,
+    vendor_name: String,
+    vendor_group: Option<String>,
+    amount: Decimal,
+    tax_amount: Decimal,
+    used_amount: Decimal,
+    used_tax_amount: Decimal,
+    currency: String,
+    exchange_rate: Decimal,
+    status: AdvanceStatus,
+    applied_amount: Decimal,
+    refunded_amount: Decimal,
+    journal_entry_id: Option<Uuid>,
+}
+
+impl LockedAdvance {
+    fn open_amount(&self) -> Decimal {
+        self.amount - self.used_amount
+    }
+
+    fn tax_share(&self, amount: Decimal) -> Decimal {
+        advance_tax_share(self.amount, self.tax_amount, self.used_amount, self.used_tax_amount, amount)
+    }
+}
+
+impl AdvanceService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self {
+            audit_logger,
+            ledger_client: LedgerClient::from_env(),
+            ledger_outbox: LedgerOutbox::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Records a down payment and books it: Dr Uang Muka Pembelian and PPN
+    /// Masukan for any faktur, Cr the bank account.
+    pub async fn create_advance(
+        &self,
+        request: CreateVendorAdvanceRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorAdvanceWithDetails> {
+        let vendor = sqlx::query!(
+            "SELECT vendor_name, vendor_group, is_active FROM vendors WHERE id = $1 AND company_id = $2",
+            request.vendor_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor not found".to_string()))?;
+
+        if vendor.is_active != Some(true) {
+            return Err(ServiceError::Validation("Vendor is inactive".to_string()));
+        }
+
+        let tax_amount = request.tax_amount.unwrap_or(Decimal::ZERO);
+        if tax_amount >= request.amount {
+            return Err(ServiceError::Validation(
+                "PPN on the down payment must be less than the amount paid".to_string(),
+            ));
+        }
+        let tax_invoice_number = request.tax_invoice_number.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        if tax_amount > Decimal::ZERO && tax_invoice_number.is_none() {
+            return Err(ServiceError::Validation(
+                "A faktur pajak number is required to claim PPN on a down payment".to_string(),
+            ));
+        }
+
+        if let Some(purchase_order_id) = request.purchase_order_id {
+            let order = sqlx::query!(
+                r#"SELECT po_number, status as "status!" FROM purchase_orders WHERE id = $1 AND company_id = $2 AND vendor_id = $3"#,
+                purchase_order_id,
+                company_id,
+                request.vendor_id
+            )
+            .fetch_optional(&self.db)
+            .await
+            .map_err(ServiceError::Database)?
+            .ok_or_else(|| ServiceError::NotFound("Purchase order not found for this vendor".to_string()))?;
+
+            let status: PurchaseOrderStatus = order.status.parse().map_err(ServiceError::Internal)?;
+            if matches!(status, PurchaseOrderStatus::Draft | PurchaseOrderStatus::Closed | PurchaseOrderStatus::Cancelled) {
+                return Err(ServiceError::Validation(format!(
+                    "Purchase order {} is {} and cannot take a down payment", order.po_number, status
+                )));
+            }
+        }
+
+        let requested_currency: Vec<String> = request.currency.iter().map(|c| c.trim().to_uppercase()).collect();
+        let rates = self.ledger_client.rate_table(
+            company_id,
+            user_id,
+            &requested_currency,
+            database::exchange_rates::RateType::BiMiddle,
+            request.advance_date,
+        ).await?;
+        let currency = requested_currency.into_iter().next()
+            .unwrap_or_else(|| rates.functional_currency.clone());
+        let exchange_rate = if currency.eq_ignore_ascii_case(&rates.functional_currency) {
+            Decimal::ONE
+        } else {
+            match request.exchange_rate {
+                Some(rate) if rate > Decimal::ZERO => rate,
+                Some(_) => return Err(ServiceError::Validation("Exchange rate must be positive".to_string())),
+                None => rates.rate_for(Some(&currency)).ok_or_else(|| ServiceError::Validation(format!(
+                    "No BI_MIDDLE exchange rate for {} on or before {}", currency, request.advance_date
+                )))?,
+            }
+        };
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.advance_date, roles).await?;
+
+        let advance_id = Uuid::new_v4();
+        let advance_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::VendorAdvance,
+            request.advance_date,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vendor_advances (
+                id, company_id, vendor_id, advance_number, advance_date, purchase_order_id, amount,
+                tax_amount, tax_invoice_number, currency, exchange_rate, payment_method, bank_account_id,
+                reference, status, posting_status, description, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, 'OPEN', 'PENDING_POSTING', $15, $16, NOW(), NOW())
+            "#,
+            advance_id,
+            company_id,
+            request.vendor_id,
+            advance_number,
+            request.advance_date,
+            request.purchase_order_id,
+            request.amount,
+            tax_amount,
+            tax_invoice_number,
+            currency,
+            exchange_rate,
+            request.payment_method,
+            request.bank_account_id,
+            request.reference,
+            request.description,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let description = format!("Uang muka {} - {}", advance_number, vendor.vendor_name);
+        let outbox_id = self.ledger_outbox.enqueue_posting(
+            &mut tx,
+            company_id,
+            &LedgerPostingRequest {
+                source_document_type: ADVANCE_SOURCE.to_string(),
+                source_document_id: advance_id,
+                entry_date: request.advance_date,
+                description: Some(description.clone()),
+                reference: request.reference.clone().or_else(|| Some(advance_number.clone())),
+                event_type: LedgerEvent::VendorAdvancePaid,
+                vendor_group: vendor.vendor_group.clone(),
+                lines: advance_posting_lines(
+                    AdvancePosting::Paid,
+                    request.bank_account_id,
+                    request.amount,
+                    tax_amount,
+                    exchange_rate,
+                    &description,
+                ),
+            },
+            user_id,
+            roles,
+        ).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_advances",
+            advance_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "advance_number": advance_number,
+                "vendor_id": request.vendor_id,
+                "purchase_order_id": request.purchase_order_id,
+                "amount": request.amount,
+                "tax_amount": tax_amount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.ledger_outbox.dispatch(outbox_id).await?;
+
+        tracing::info!(
+            "Recorded vendor advance {} of {} {} for company {}",
+            advance_number, request.amount, currency, company_id
+        );
+
+        self.get_advance(advance_id, company_id).await
+    }
+
+    pub async fn get_advances(
+        &self,
+        company_id: Uuid,
+        vendor_id: Option<Uuid>,
+        status: Option<AdvanceStatus>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<VendorAdvance>> {
+        sqlx::query_as!(
+            VendorAdvance,
+            r#"
+            SELECT va.id, va.company_id, va.vendor_id, v.vendor_name, va.advance_number, va.advance_date,
+                   va.purchase_order_id, va.amount, va.tax_amount, va.tax_invoice_number,
+                   va.applied_amount, va.applied_tax_amount, va.refunded_amount, va.refunded_tax_amount,
+                   va.amount - va.applied_amount - va.refunded_amount as "open_amount!",
+                   va.currency, va.exchange_rate, va.payment_method, va.bank_account_id, va.reference,
+                   va.status, va.description, va.journal_entry_id, va.reversal_journal_entry_id, va.posting_status,
+                   va.created_by, va.created_at, va.updated_at
+            FROM vendor_advances va
+            JOIN vendors v ON v.id = va.vendor_id
+            WHERE va.company_id = $1
+              AND ($2::uuid IS NULL OR va.vendor_id = $2)
+              AND ($3::text IS NULL OR va.status = $3)
+            ORDER BY va.advance_date DESC, va.advance_number DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            company_id,
+            vendor_id,
+            status.map(|s| s.to_string()),
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_advance(
+        &self,
+        advance_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<VendorAdvanceWithDetails> {
+        let advance = sqlx::query_as!(
+            VendorAdvance,
+            r#"
+            SELECT va.id, va.company_id, va.vendor_id, v.vendor_name, va.advance_number, va.advance_date,
+                   va.purchase_order_id, va.amount, va.tax_amount, va.tax_invoice_number,
+                   va.applied_amount, va.applied_tax_amount, va.refunded_amount, va.refunded_tax_amount,
+                   va.amount - va.applied_amount - va.refunded_amount as "open_amount!",
+                   va.currency, va.exchange_rate, va.payment_method, va.bank_account_id, va.reference,
+                   va.status, va.description, va.journal_entry_id, va.reversal_journal_entry_id, va.posting_status,
+                   va.created_by, va.created_at, va.updated_at
+            FROM vendor_advances va
+            JOIN vendors v ON v.id = va.vendor_id
+            WHERE va.id = $1 AND va.company_id = $2
+            "#,
+            advance_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor advance not found".to_string()))?;
+
+        let applications = sqlx::query_as!(
+            VendorAdvanceApplication,
+            r#"
+            SELECT aa.id, aa.advance_id, aa.invoice_id, vi.invoice_number, aa.amount, aa.tax_amount,
+                   aa.application_date, aa.journal_entry_id, aa.posting_status, aa.created_by, aa.created_at
+            FROM vendor_advance_applications aa
+            JOIN vendor_invoices vi ON vi.id = aa.invoice_id
+            WHERE aa.advance_id = $1
+            ORDER BY aa.created_at
+            "#,
+            advance_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let refunds = sqlx::query_as!(
+            VendorAdvanceRefund,
+            r#"
+            SELECT id, advance_id, refund_number, refund_amount, tax_amount, refund_date, payment_method,
+                   bank_account_id, reference, journal_entry_id, posting_status, created_by, created_at
+            FROM vendor_advance_refunds
+            WHERE advance_id = $1
+            ORDER BY refund_date, created_at
+            "#,
+            advance_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(VendorAdvanceWithDetails { advance, applications, refunds })
+    }
+
+    /// Applies a down payment to approved invoices of the same vendor and
+    /// currency. Like a payment, the amount counts as paid on the invoice; it
+    /// is also tracked in the invoice's advance applied amount. Each
+    /// application books Dr Hutang Dagang, Cr Uang Muka Pembelian and the
+    /// PPN share of the down payment.
+    pub async fn apply_advance(
+        &self,
+        advance_id: Uuid,
+        request: ApplyVendorAdvanceRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorAdvanceWithDetails> {
+        let mut invoice_ids: Vec<Uuid> = request.applications.iter().map(|a| a.invoice_id).collect();
+        invoice_ids.sort();
+        invoice_ids.dedup();
+        if invoice_ids.len() != request.applications.len() {
+            return Err(ServiceError::Validation("Each invoice can only be listed once".to_string()));
+        }
+        if request.applications.iter().any(|a| a.amount <= Decimal::ZERO) {
+            return Err(ServiceError::Validation("Applied amounts must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.application_date, roles).await?;
+
+        let mut advance = self.lock_advance(&mut tx, advance_id, company_id).await?;
+        if advance.status != AdvanceStatus::Open {
+            return Err(ServiceError::Validation(format!(
+                "Advance {} is {}; only open advances can be applied", advance.advance_number, advance.status
+            )));
+        }
+
+        let total_applied: Decimal = request.applications.iter().map(|a| a.amount).sum();
+        if total_applied > advance.open_amount() {
+            return Err(ServiceError::Validation(format!(
+                "Applying {} exceeds the {} left on advance {}",
+                total_applied, advance.open_amount(), advance.advance_number
+            )));
+        }
+
+        let mut total_tax = Decimal::ZERO;
+        let mut outbox_ids = Vec::with_capacity(request.applications.len());
+        for application in &request.applications {
+            let invoice = sqlx::query!(
+                r#"
+                SELECT invoice_number, total_amount, paid_amount, COALESCE(currency, 'IDR') as "currency!",
+                       vendor_id, status as "status_str"
+                FROM vendor_invoices
+                WHERE id = $1 AND company_id = $2
+                FOR UPDATE
+                "#,
+                application.invoice_id,
+                company_id
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?
+            .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+            if invoice.vendor_id != advance.vendor_id {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is not from {}", invoice.invoice_number, advance.vendor_name
+                )));
+            }
+            if invoice.status_str.as_deref() != Some("APPROVED") {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is not open for payment", invoice.invoice_number
+                )));
+            }
+            if !invoice.currency.eq_ignore_ascii_case(&advance.currency) {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is in {} but the advance is in {}",
+                    invoice.invoice_number, invoice.currency, advance.currency
+                )));
+            }
+
+            let paid_amount = invoice.paid_amount.unwrap_or(Decimal::ZERO);
+            let outstanding = invoice.total_amount - paid_amount;
+            if application.amount > outstanding {
+                return Err(ServiceError::Validation(format!(
+                    "Applying {} exceeds invoice {} outstanding balance of {}",
+                    application.amount, invoice.invoice_number, outstanding
+                )));
+            }
+
+            let tax_amount = advance.tax_share(application.amount);
+            let application_id = Uuid::new_v4();
+            let description = format!(
+                "Uang muka {} atas {} - {}", advance.advance_number, invoice.invoice_number, advance.vendor_name
+            );
+
+            // Booked at the advance's rate so the advance account clears
+            outbox_ids.push(self.ledger_outbox.enqueue_posting(
+                &mut tx,
+                company_id,
+                &LedgerPostingRequest {
+                    source_document_type: APPLICATION_SOURCE.to_string(),
+                    source_document_id: application_id,
+                    entry_date: request.application_date,
+                    description: Some(description.clone()),
+                    reference: Some(invoice.invoice_number.clone()),
+                    event_type: LedgerEvent::VendorAdvanceApplied,
+                    vendor_group: advance.vendor_group.clone(),
+                    lines: advance_posting_lines(
+                        AdvancePosting::Applied,
+                        None,
+                        application.amount,
+                        tax_amount,
+                        advance.exchange_rate,
+                        &description,
+                    ),
+                },
+                user_id,
+                roles,
+            ).await?);
+
+            let new_status = if paid_amount + application.amount >= invoice.total_amount {
+                InvoiceStatus::Paid
+            } else {
+                InvoiceStatus::Approved
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE vendor_invoices
+                SET paid_amount = COALESCE(paid_amount, 0) + $1,
+                    advance_applied_amount = advance_applied_amount + $1,
+                    status = $2::invoice_status,
+                    updated_at = NOW()
+                WHERE id = $3
+                "#,
+                application.amount,
+                new_status.to_string(),
+                application.invoice_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO vendor_advance_applications (
+                    id, company_id, advance_id, invoice_id, amount, tax_amount, application_date,
+                    posting_status, created_by, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, 'PENDING_POSTING', $8, NOW())
+                "#,
+                application_id,
+                company_id,
+                advance_id,
+                application.invoice_id,
+                application.amount,
+                tax_amount,
+                request.application_date,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            advance.used_amount += application.amount;
+            advance.used_tax_amount += tax_amount;
+            total_tax += tax_amount;
+        }
+
+        self.record_usage(&mut tx, advance_id, total_applied, total_tax, Decimal::ZERO, Decimal::ZERO, advance.open_amount()).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_advances",
+            advance_id,
+            "APPLY",
+            Some(serde_json::json!({ "applied_amount": advance.applied_amount })),
+            Some(serde_json::json!({
+                "applied_amount": advance.applied_amount + total_applied,
+                "applied_tax_amount": total_tax,
+                "applications": request.applications
+                    .iter()
+                    .map(|a| serde_json::json!({ "invoice_id": a.invoice_id, "amount": a.amount }))
+                    .collect::<Vec<_>>()
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        for outbox_id in outbox_ids {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+
+        tracing::info!(
+            "Applied {} of advance {} to {} invoice(s)",
+            total_applied, advance.advance_number, request.applications.len()
+        );
+
+        self.get_advance(advance_id, company_id).await
+    }
+
+    /// Records money the vendor returned from a down payment: Dr the bank
+    /// account, Cr Uang Muka Pembelian and the PPN share of the refund.
+    pub async fn refund_advance(
+        &self,
+        advance_id: Uuid,
+        request: VendorAdvanceRefundRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorAdvanceWithDetails> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.refund_date, roles).await?;
+
+        let advance = self.lock_advance(&mut tx, advance_id, company_id).await?;
+        if advance.status != AdvanceStatus::Open {
+            return Err(ServiceError::Validation(format!(
+                "Advance {} is {}; only open advances can be refunded", advance.advance_number, advance.status
+            )));
+        }
+        if request.refund_amount > advance.open_amount() {
+            return Err(ServiceError::Validation(format!(
+                "Refund of {} exceeds the {} left on advance {}",
+                request.refund_amount, advance.open_amount(), advance.advance_number
+            )));
+        }
+
+        let tax_amount = advance.tax_share(request.refund_amount);
+        let refund_id = Uuid::new_v4();
+        let refund_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::VendorRefund,
+            request.refund_date,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vendor_advance_refunds (
+                id, company_id, advance_id, refund_number, refund_amount, tax_amount, refund_date,
+                payment_method, bank_account_id, reference, posting_status, created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'PENDING_POSTING', $11, NOW())
+            "#,
+            refund_id,
+            company_id,
+            advance_id,
+            refund_number,
+            request.refund_amount,
+            tax_amount,
+            request.refund_date,
+            request.payment_method,
+            request.bank_account_id,
+            request.reference,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let description = format!("Refund {} - {} {}", refund_number, advance.vendor_name, advance.advance_number);
+        let outbox_id = self.ledger_outbox.enqueue_posting(
+            &mut tx,
+            company_id,
+            &LedgerPostingRequest {
+                source_document_type: ADVANCE_REFUND_SOURCE.to_string(),
+                source_document_id: refund_id,
+                entry_date: request.refund_date,
+                description: Some(description.clone()),
+                reference: request.reference.clone().or_else(|| Some(refund_number.clone())),
+                event_type: LedgerEvent::VendorAdvanceRefunded,
+                vendor_group: advance.vendor_group.clone(),
+                lines: advance_posting_lines(
+                    AdvancePosting::Refunded,
+                    request.bank_account_id,
+                    request.refund_amount,
+                    tax_amount,
+                    advance.exchange_rate,
+                    &description,
+                ),
+            },
+            user_id,
+            roles,
+        ).await?;
+
+        self.record_usage(
+            &mut tx,
+            advance_id,
+            Decimal::ZERO,
+            Decimal::ZERO,
+            request.refund_amount,
+            tax_amount,
+            advance.open_amount() - request.refund_amount,
+        ).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_advances",
+            advance_id,
+            "REFUND",
+            Some(serde_json::json!({ "refunded_amount": advance.refunded_amount })),
+            Some(serde_json::json!({
+                "refunded_amount": advance.refunded_amount + request.refund_amount,
+                "refund_number": refund_number,
+                "tax_amount": tax_amount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.ledger_outbox.dispatch(outbox_id).await?;
+
+        tracing::info!(
+            "Recorded refund {} of {} on advance {}",
+            refund_number, request.refund_amount, advance.advance_number
+        );
+
+        self.get_advance(advance_id, company_id).await
+    }
+
+    /// Cancels an advance recorded in error, before any of it has been
+    /// applied or refunded. Its ledger entry is reversed today.
+    pub async fn cancel_advance(
+        &self,
+        advance_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorAdvanceWithDetails> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let advance = self.lock_advance(&mut tx, advance_id, company_id).await?;
+        if advance.status != AdvanceStatus::Open || advance.used_amount != Decimal::ZERO {
+            return Err(ServiceError::Validation(format!(
+                "Advance {} cannot be cancelled once it has been applied or refunded", advance.advance_number
+            )));
+        }
+
+        // The reversal is booked today
+        let reversal_date = chrono::Utc::now().date_naive();
+        database::periods::ensure_period_open(&mut *tx, company_id, reversal_date, roles).await?;
+
+        let outbox_id = self.ledger_outbox.enqueue_reversal(
+            &mut tx,
+            company_id,
+            ADVANCE_SOURCE,
+            advance_id,
+            advance.journal_entry_id,
+            reversal_date,
+            &format!("Advance {} cancelled", advance.advance_number),
+            user_id,
+            roles,
+        ).await?;
+        let posting_status = match outbox_id {
+            Some(_) => PostingStatus::PendingPosting,
+            None => PostingStatus::NotPosted,
+        }.to_string();
+
+        sqlx::query!(
+            "UPDATE vendor_advances SET status = 'CANCELLED', posting_status = $1, updated_at = NOW() WHERE id = $2",
+            posting_status,
+            advance_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_advances",
+            advance_id,
+            "CANCEL",
+            Some(serde_json::json!({ "status": advance.status.to_string() })),
+            Some(serde_json::json!({
+                "status": AdvanceStatus::Cancelled.to_string(),
+                "posting_status": posting_status
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        if let Some(outbox_id) = outbox_id {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+
+        tracing::info!("Advance {} cancelled by user {}", advance.advance_number, user_id);
+
+        self.get_advance(advance_id, company_id).await
+    }
+
+    /// Adds to what has been applied and refunded, closing the advance once
+    /// nothing is left of it.
+    #[allow(clippy::too_many_arguments)]
+    async fn record_usage(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        advance_id: Uuid,
+        applied: Decimal,
+        applied_tax: Decimal,
+        refunded: Decimal,
+        refunded_tax: Decimal,
+        remaining: Decimal,
+    ) -> ServiceResult<()> {
+        let status = if remaining <= Decimal::ZERO {
+            AdvanceStatus::Closed
+        } else {
+            AdvanceStatus::Open
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE vendor_advances
+            SET applied_amount = applied_amount + $1,
+                applied_tax_amount = applied_tax_amount + $2,
+                refunded_amount = refunded_amount + $3,
+                refunded_tax_amount = refunded_tax_amount + $4,
+                status = $5,
+                updated_at = NOW()
+            WHERE id = $6
+            "#,
+            applied,
+            applied_tax,
+            refunded,
+            refunded_tax,
+            status.to_string(),
+            advance_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    async fn lock_advance(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        advance_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<LockedAdvance> {
+        let row = sqlx::query!(
+            r#"
+            SELECT va.advance_number, va.vendor_id, v.vendor_name, v.vendor_group, va.amount, va.tax_amount,
+                   va.applied_amount, va.applied_tax_amount, va.refunded_amount, va.refunded_tax_amount,
+                   va.currency, va.exchange_rate, va.status, va.journal_entry_id
+            FROM vendor_advances va
+            JOIN vendors v ON v.id = va.vendor_id
+            WHERE va.id = $1 AND va.company_id = $2
+            FOR UPDATE OF va
+            "#,
+            advance_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor advance not found".to_string()))?;
+
+        Ok(LockedAdvance {
+            advance_number: row.advance_number,
+            vendor_id: row.vendor_id,
+            vendor_name: row.vendor_name,
+            vendor_group: row.vendor_group,
+            amount: row.amount,
+            tax_amount: row.tax_amount,
+            used_amount: row.applied_amount + row.refunded_amount,
+            used_tax_amount: row.applied_tax_amount + row.refunded_tax_amount,
+            currency: row.currency,
+            exchange_rate: row.exchange_rate,
+            status: row.status.parse().map_err(ServiceError::Internal)?,
+            applied_amount: row.applied_amount,
+            refunded_amount: row.refunded_amount,
+            journal_entry_id: row.journal_entry_id,
+        })
+    }
+}
+
+/// What a ledger entry for an advance records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AdvancePosting {
+    /// The down payment leaves the bank
+    Paid,
+    /// The down payment settles part of an invoice
+    Applied,
+    /// The vendor returns part of the down payment
+    Refunded,
+}
+
+/// PPN carried by taking `amount` out of an advance: its share of the
+/// faktur's PPN, and whatever PPN is left when the advance is used up, so
+/// rounding never strands a cent in PPN Masukan.
+fn advance_tax_share(
+    advance_amount: Decimal,
+    advance_tax: Decimal,
+    used_amount: Decimal,
+    used_tax: Decimal,
+    amount: Decimal,
+) -> Decimal {
+    let remaining_tax = advance_tax - used_tax;
+    if advance_tax <= Decimal::ZERO || advance_amount <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+    if amount >= advance_amount - used_amount {
+        return remaining_tax;
+    }
+    (amount * advance_tax / advance_amount).round_dp(2).min(remaining_tax)
+}
+
+/// Ledger lines moving `amount` (of which `tax_amount` is PPN) into or out
+/// of the advance account. Paying debits the advance and PPN Masukan against
+/// the bank; applying and refunding credit them against Hutang Dagang or the
+/// bank. Amounts are converted at the advance's rate, the advance line
+/// taking the rounding so the entry balances.
+fn advance_posting_lines(
+    posting: AdvancePosting,
+    bank_account_id: Option<Uuid>,
+    amount: Decimal,
+    tax_amount: Decimal,
+    exchange_rate: Decimal,
+    description: &str,
+) -> Vec<LedgerPostingLine> {
+    let gross = (amount * exchange_rate).round_dp(2);
+    let tax = (tax_amount * exchange_rate).round_dp(2);
+    let into_advance = posting == AdvancePosting::Paid;
+
+    let line = |account_id: Option<Uuid>, role: LedgerAccountRole, tax_type: Option<&str>, description: String, amount: Decimal, debit: bool| {
+        LedgerPostingLine {
+            account_id,
+            account_role: Some(role),
+            item_category: None,
+            tax_type: tax_type.map(str::to_string),
+            description: Some(description),
+            debit_amount: if debit { amount } else { Decimal::ZERO },
+            credit_amount: if debit { Decimal::ZERO } else { amount },
+            department: None,
+            project_code: None,
+            foreign: None,
+        }
+    };
+
+    let mut lines = vec![line(None, LedgerAccountRole::VendorAdvance, None, description.to_string(), gross - tax, into_advance)];
+    if tax > Decimal::ZERO {
+        lines.push(line(None, LedgerAccountRole::InputVat, Some("PPN"), format!("PPN Masukan {}", description), tax, into_advance));
+    }
+    let counter = match posting {
+        AdvancePosting::Applied => line(None, LedgerAccountRole::ApControl, None, description.to_string(), gross, true),
+        AdvancePosting::Paid | AdvancePosting::Refunded => {
+            line(bank_account_id, LedgerAccountRole::Bank, None, description.to_string(), gross, !into_advance)
+        }
+    };
+    lines.push(counter);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_tax_share() {
+        // 11% PPN on a 1,000,000 DPP down payment
+        let amount = Decimal::from(1_110_000);
+        let tax = Decimal::from(110_000);
+
+        assert_eq!(advance_tax_share(amount, tax, Decimal::ZERO, Decimal::ZERO, Decimal::from(555_000)), Decimal::from(55_000));
+
+        // The last use takes whatever PPN is left
+        let share = advance_tax_share(amount, tax, Decimal::ZERO, Decimal::ZERO, Decimal::from(333_333));
+        assert_eq!(share, Decimal::new(3_303_300, 2));
+        assert_eq!(
+            advance_tax_share(amount, tax, Decimal::from(333_333), share, Decimal::from(776_667)),
+            tax - share
+        );
+
+        // No faktur, no PPN
+        assert_eq!(advance_tax_share(amount, Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, amount), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_advance_posting_lines() {
+        let bank = Some(Uuid::new_v4());
+
+        let paid = advance_posting_lines(AdvancePosting::Paid, bank, Decimal::from(1_110_000), Decimal::from(110_000), Decimal::ONE, "VAD-1");
+        assert_eq!(paid.len(), 3);
+        assert_eq!(paid[0].account_role, Some(LedgerAccountRole::VendorAdvance));
+        assert_eq!(paid[0].debit_amount, Decimal::from(1_000_000));
+        assert_eq!(paid[1].account_role, Some(LedgerAccountRole::InputVat));
+        assert_eq!(paid[1].debit_amount, Decimal::from(110_000));
+        assert_eq!(paid[2].account_id, bank);
+        assert_eq!(paid[2].credit_amount, Decimal::from(1_110_000));
+
+        let applied = advance_posting_lines(AdvancePosting::Applied, None, Decimal::from(555_000), Decimal::from(55_000), Decimal::ONE, "VAD-1");
+        assert_eq!(applied[0].credit_amount, Decimal::from(500_000));
+        assert_eq!(applied[1].credit_amount, Decimal::from(55_000));
+        assert_eq!(applied[2].account_role, Some(LedgerAccountRole::ApControl));
+        assert_eq!(applied[2].debit_amount, Decimal::from(555_000));
+
+        // Foreign-currency refund without PPN still balances
+        let refunded = advance_posting_lines(AdvancePosting::Refunded, bank, Decimal::new(1_055, 2), Decimal::ZERO, Decimal::new(155_005, 1), "VAD-2");
+        assert_eq!(refunded.len(), 2);
+        let debits: Decimal = refunded.iter().map(|l| l.debit_amount).sum();
+        let credits: Decimal = refunded.iter().map(|l| l.credit_amount).sum();
+        assert_eq!(debits, credits);
+        assert_eq!(refunded[1].account_role, Some(LedgerAccountRole::Bank));
+    }
+}
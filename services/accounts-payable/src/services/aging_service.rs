@@ -1,6 +1,8 @@
 use crate::models::*;
+use super::LedgerClient;
 use common::{ServiceResult, ServiceError};
 use chrono::NaiveDate;
+use database::exchange_rates::{distinct_currencies, RateType};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -8,16 +10,24 @@ use uuid::Uuid;
 
 pub struct AgingService {
     db: PgPool,
+    ledger_client: LedgerClient,
 }
 
 impl AgingService {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self {
+            db,
+            ledger_client: LedgerClient::from_env(),
+        }
     }
 
+    /// Foreign-currency documents are aged in the functional currency at the
+    /// ledger's BI middle rate on the report date. Documents in a currency
+    /// without a rate are listed as unrated and left out of the totals.
     pub async fn generate_aging_report(
         &self,
         company_id: Uuid,
+        user_id: Uuid,
         as_of_date: Option<NaiveDate>,
     ) -> ServiceResult<AgingReport> {
         let report_date = as_of_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
@@ -32,6 +42,7 @@ impl AgingService {
                 vi.invoice_date,
                 vi.due_date,
                 vi.total_amount - vi.paid_amount as outstanding_amount,
+                vi.currency,
                 $2 - vi.due_date as days_overdue
             FROM vendor_invoices vi
             JOIN vendors v ON vi.vendor_id = v.id
@@ -48,6 +59,41 @@ impl AgingService {
         .await
         .map_err(ServiceError::Database)?;
 
+        // Unapplied credits count against the vendor, aged from the memo date
+        let credit_data = sqlx::query!(
+            r#"
+            SELECT
+                v.id as vendor_id,
+                v.vendor_name,
+                cm.id as credit_memo_id,
+                cm.credit_memo_number,
+                cm.memo_date,
+                cm.total_amount - cm.applied_amount - cm.refunded_amount as "unapplied_amount!",
+                cm.currency,
+                $2 - cm.memo_date as "days_outstanding!"
+            FROM vendor_credit_memos cm
+            JOIN vendors v ON cm.vendor_id = v.id
+            WHERE cm.company_id = $1
+                  AND cm.status = 'APPROVED'
+                  AND cm.total_amount > cm.applied_amount + cm.refunded_amount
+            ORDER BY v.vendor_name, cm.memo_date
+            "#,
+            company_id,
+            report_date
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let currencies = distinct_currencies(
+            aging_data.iter().filter_map(|row| row.currency.as_deref())
+                .chain(credit_data.iter().map(|row| row.currency.as_str()))
+        );
+        let rates = self.ledger_client
+            .rate_table(company_id, user_id, &currencies, RateType::BiMiddle, report_date)
+            .await?;
+        let mut unrated_currencies = Vec::new();
+
         let mut vendor_details: HashMap<Uuid, VendorAgingDetail> = HashMap::new();
         let mut summary = AgingSummary {
             current: Decimal::ZERO,
@@ -56,11 +102,25 @@ impl AgingService {
             over_90_days: Decimal::ZERO,
             total_outstanding: Decimal::ZERO,
             invoice_count: 0,
+            credit_memo_count: 0,
         };
 
         for row in aging_data {
-            let outstanding = row.outstanding_amount.unwrap_or(Decimal::ZERO);
             let days_overdue = row.days_overdue.unwrap_or(0);
+            let foreign_outstanding = row.outstanding_amount.unwrap_or(Decimal::ZERO);
+            let Some(outstanding) = rates.to_functional(foreign_outstanding, row.currency.as_deref()) else {
+                unrated_currencies.extend(row.currency.clone());
+                vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name).invoices.push(InvoiceAgingItem {
+                    invoice_id: row.invoice_id,
+                    invoice_number: row.invoice_number,
+                    invoice_date: row.invoice_date,
+                    due_date: row.due_date,
+                    days_overdue,
+                    outstanding_amount: foreign_outstanding,
+                    unrated_currency: row.currency,
+                });
+                continue;
+            };
             
             // Update summary
             summary.total_outstanding += outstanding;
@@ -74,18 +134,7 @@ impl AgingService {
             }
 
             // Update vendor detail
-            let vendor_detail = vendor_details.entry(row.vendor_id).or_insert_with(|| {
-                VendorAgingDetail {
-                    vendor_id: row.vendor_id,
-                    vendor_name: row.vendor_name.clone(),
-                    current: Decimal::ZERO,
-                    days_31_60: Decimal::ZERO,
-                    days_61_90: Decimal::ZERO,
-                    over_90_days: Decimal::ZERO,
-                    total_outstanding: Decimal::ZERO,
-                    invoices: Vec::new(),
-                }
-            });
+            let vendor_detail = vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name);
 
             vendor_detail.total_outstanding += outstanding;
             match days_overdue {
@@ -102,6 +151,52 @@ impl AgingService {
                 due_date: row.due_date,
                 days_overdue,
                 outstanding_amount: outstanding,
+                unrated_currency: None,
+            });
+        }
+
+        for row in credit_data {
+            let days_outstanding = row.days_outstanding;
+            let Some(unapplied) = rates.to_functional(row.unapplied_amount, Some(&row.currency)) else {
+                vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name).credit_memos.push(CreditMemoAgingItem {
+                    credit_memo_id: row.credit_memo_id,
+                    credit_memo_number: row.credit_memo_number,
+                    memo_date: row.memo_date,
+                    days_outstanding,
+                    unapplied_amount: row.unapplied_amount,
+                    unrated_currency: Some(row.currency.clone()),
+                });
+                unrated_currencies.push(row.currency);
+                continue;
+            };
+
+            summary.total_outstanding -= unapplied;
+            summary.credit_memo_count += 1;
+
+            match days_outstanding {
+                d if d <= 30 => summary.current -= unapplied,
+                d if d <= 60 => summary.days_31_60 -= unapplied,
+                d if d <= 90 => summary.days_61_90 -= unapplied,
+                _ => summary.over_90_days -= unapplied,
+            }
+
+            let vendor_detail = vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name);
+
+            vendor_detail.total_outstanding -= unapplied;
+            match days_outstanding {
+                d if d <= 30 => vendor_detail.current -= unapplied,
+                d if d <= 60 => vendor_detail.days_31_60 -= unapplied,
+                d if d <= 90 => vendor_detail.days_61_90 -= unapplied,
+                _ => vendor_detail.over_90_days -= unapplied,
+            }
+
+            vendor_detail.credit_memos.push(CreditMemoAgingItem {
+                credit_memo_id: row.credit_memo_id,
+                credit_memo_number: row.credit_memo_number,
+                memo_date: row.memo_date,
+                days_outstanding,
+                unapplied_amount: unapplied,
+                unrated_currency: None,
             });
         }
 
@@ -110,6 +205,7 @@ impl AgingService {
             report_date,
             summary,
             vendor_details: vendor_details.into_values().collect(),
+            unrated_currencies: distinct_currencies(unrated_currencies.iter().map(String::as_str)),
             generated_at: chrono::Utc::now(),
         };
 
@@ -119,6 +215,153 @@ impl AgingService {
         Ok(report)
     }
 
+    /// Open vendor advances aged from the day they were paid, in the
+    /// functional currency at the ledger's BI middle rate on the report date.
+    /// Advances in a currency without a rate are listed as unrated.
+    pub async fn generate_advance_aging_report(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        as_of_date: Option<NaiveDate>,
+    ) -> ServiceResult<AdvanceAgingReport> {
+        let report_date = as_of_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+        let advance_data = sqlx::query!(
+            r#"
+            SELECT
+                v.id as vendor_id,
+                v.vendor_name,
+                va.id as advance_id,
+                va.advance_number,
+                va.advance_date,
+                va.purchase_order_id,
+                va.amount - va.applied_amount - va.refunded_amount as "open_amount!",
+                va.currency,
+                $2 - va.advance_date as "days_outstanding!"
+            FROM vendor_advances va
+            JOIN vendors v ON va.vendor_id = v.id
+            WHERE va.company_id = $1
+                  AND va.status = 'OPEN'
+                  AND va.advance_date <= $2
+            ORDER BY v.vendor_name, va.advance_date
+            "#,
+            company_id,
+            report_date
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let currencies = distinct_currencies(advance_data.iter().map(|row| row.currency.as_str()));
+        let rates = self.ledger_client
+            .rate_table(company_id, user_id, &currencies, RateType::BiMiddle, report_date)
+            .await?;
+        let mut unrated_currencies = Vec::new();
+
+        let mut vendor_details: HashMap<Uuid, VendorAdvanceAgingDetail> = HashMap::new();
+        let mut summary = AdvanceAgingSummary {
+            current: Decimal::ZERO,
+            days_31_60: Decimal::ZERO,
+            days_61_90: Decimal::ZERO,
+            over_90_days: Decimal::ZERO,
+            total_open: Decimal::ZERO,
+            advance_count: 0,
+        };
+
+        for row in advance_data {
+            let days_outstanding = row.days_outstanding;
+            let Some(open_amount) = rates.to_functional(row.open_amount, Some(&row.currency)) else {
+                advance_vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name).advances.push(AdvanceAgingItem {
+                    advance_id: row.advance_id,
+                    advance_number: row.advance_number,
+                    advance_date: row.advance_date,
+                    purchase_order_id: row.purchase_order_id,
+                    days_outstanding,
+                    open_amount: row.open_amount,
+                    unrated_currency: Some(row.currency.clone()),
+                });
+                unrated_currencies.push(row.currency);
+                continue;
+            };
+
+            summary.total_open += open_amount;
+            summary.advance_count += 1;
+
+            match days_outstanding {
+                d if d <= 30 => summary.current += open_amount,
+                d if d <= 60 => summary.days_31_60 += open_amount,
+                d if d <= 90 => summary.days_61_90 += open_amount,
+                _ => summary.over_90_days += open_amount,
+            }
+
+            let vendor_detail = advance_vendor_entry(&mut vendor_details, row.vendor_id, &row.vendor_name);
+
+            vendor_detail.total_open += open_amount;
+            match days_outstanding {
+                d if d <= 30 => vendor_detail.current += open_amount,
+                d if d <= 60 => vendor_detail.days_31_60 += open_amount,
+                d if d <= 90 => vendor_detail.days_61_90 += open_amount,
+                _ => vendor_detail.over_90_days += open_amount,
+            }
+
+            vendor_detail.advances.push(AdvanceAgingItem {
+                advance_id: row.advance_id,
+                advance_number: row.advance_number,
+                advance_date: row.advance_date,
+                purchase_order_id: row.purchase_order_id,
+                days_outstanding,
+                open_amount,
+                unrated_currency: None,
+            });
+        }
+
+        let report = AdvanceAgingReport {
+            company_id,
+            report_date,
+            summary,
+            vendor_details: vendor_details.into_values().collect(),
+            unrated_currencies: distinct_currencies(unrated_currencies.iter().map(String::as_str)),
+            generated_at: chrono::Utc::now(),
+        };
+
+        tracing::info!("Generated advance aging report for company {} with {} vendors",
+            company_id, report.vendor_details.len());
+
+        Ok(report)
+    }
+
+    /// What the AP control account should hold before revaluation: approved
+    /// invoices less approved credit memos, at their booked rates.
+    pub async fn get_subledger_balance(&self, company_id: Uuid) -> ServiceResult<SubledgerBalance> {
+        let balance = sqlx::query!(
+            r#"
+            SELECT (
+                SELECT COALESCE(SUM((total_amount - COALESCE(paid_amount, 0)) * COALESCE(exchange_rate, 1)), 0)
+                FROM vendor_invoices
+                WHERE company_id = $1 AND status IN ('APPROVED', 'PAID')
+            ) as "open_invoices!", (
+                SELECT COALESCE(SUM((total_amount - applied_amount - refunded_amount) * exchange_rate), 0)
+                FROM vendor_credit_memos
+                WHERE company_id = $1 AND status IN ('APPROVED', 'CLOSED')
+            ) as "open_credits!"
+            "#,
+            company_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let open_invoices = balance.open_invoices.round_dp(2);
+        let open_credits = balance.open_credits.round_dp(2);
+
+        Ok(SubledgerBalance {
+            company_id,
+            open_invoices,
+            open_credits,
+            outstanding: open_invoices - open_credits,
+        })
+    }
+
     pub async fn get_overdue_invoices(
         &self,
         company_id: Uuid,
@@ -132,7 +375,7 @@ impl AgingService {
             SELECT vi.id, vi.company_id, vi.vendor_id, v.vendor_name, vi.invoice_number, 
                    vi.invoice_date, vi.due_date, vi.subtotal, vi.tax_amount, vi.total_amount, 
                    vi.paid_amount, vi.status as "status_str", vi.description, 
-                   vi.journal_entry_id, vi.created_at, vi.updated_at
+                   vi.journal_entry_id, vi.posting_status, vi.created_at, vi.updated_at
             FROM vendor_invoices vi
             LEFT JOIN vendors v ON vi.vendor_id = v.id
             WHERE vi.company_id = $1 
@@ -172,12 +415,49 @@ impl AgingService {
                     status,
                     description: row.description,
                     journal_entry_id: row.journal_entry_id,
+                    posting_status: row.posting_status.parse().unwrap_or(PostingStatus::NotPosted),
                     created_at: row.created_at,
                     updated_at: row.updated_at,
+                    budget_warnings: Vec::new(),
                 }
             })
             .collect();
 
         Ok(invoices)
     }
-}
\ No newline at end of file
+}
+
+fn vendor_entry<'a>(
+    vendor_details: &'a mut HashMap<Uuid, VendorAgingDetail>,
+    vendor_id: Uuid,
+    vendor_name: &str,
+) -> &'a mut VendorAgingDetail {
+    vendor_details.entry(vendor_id).or_insert_with(|| VendorAgingDetail {
+        vendor_id,
+        vendor_name: vendor_name.to_string(),
+        current: Decimal::ZERO,
+        days_31_60: Decimal::ZERO,
+        days_61_90: Decimal::ZERO,
+        over_90_days: Decimal::ZERO,
+        total_outstanding: Decimal::ZERO,
+        invoices: Vec::new(),
+        credit_memos: Vec::new(),
+    })
+}
+
+fn advance_vendor_entry<'a>(
+    vendor_details: &'a mut HashMap<Uuid, VendorAdvanceAgingDetail>,
+    vendor_id: Uuid,
+    vendor_name: &str,
+) -> &'a mut VendorAdvanceAgingDetail {
+    vendor_details.entry(vendor_id).or_insert_with(|| VendorAdvanceAgingDetail {
+        vendor_id,
+        vendor_name: vendor_name.to_string(),
+        current: Decimal::ZERO,
+        days_31_60: Decimal::ZERO,
+        days_61_90: Decimal::ZERO,
+        over_90_days: Decimal::ZERO,
+        total_open: Decimal::ZERO,
+        advances: Vec::new(),
+    })
+}
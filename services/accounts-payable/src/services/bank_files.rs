@@ -0,0 +1,309 @@
+use crate::models::*;
+use rust_decimal::Decimal;
+
+/// Length of each of the two free-text "berita" fields KlikBCA accepts.
+const BCA_REMARK_LENGTH: usize = 18;
+
+/// Maximum length of ISO 20022 names and unstructured remittance text.
+const ISO_TEXT_LENGTH: usize = 140;
+
+/// Maximum length of ISO 20022 identifiers such as the end-to-end id.
+const ISO_ID_LENGTH: usize = 35;
+
+/// One transfer in a bank file: everything a run pays a vendor.
+pub(crate) struct VendorTransfer {
+    pub vendor_code: String,
+    pub vendor_email: Option<String>,
+    pub bank_name: String,
+    pub account_number: String,
+    pub account_name: String,
+    pub amount: Decimal,
+    pub invoice_numbers: Vec<String>,
+}
+
+/// Renders the bank upload file for a run's transfers.
+pub(crate) fn render(
+    format: BankFileFormat,
+    run: &PaymentRun,
+    transfers: &[VendorTransfer],
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> Result<BankFile, String> {
+    if transfers.is_empty() {
+        return Err(format!("Payment run {} has nothing to transfer", run.run_number));
+    }
+
+    let (content, extension, content_type) = match format {
+        BankFileFormat::BcaCsv => (bca_csv(run, transfers)?, "csv", "text/csv"),
+        BankFileFormat::MandiriMcm => (mandiri_mcm(run, transfers)?, "csv", "text/csv"),
+        BankFileFormat::Pain001 => (pain001(run, transfers, created_at), "xml", "application/xml"),
+    };
+
+    Ok(BankFile {
+        file_name: format!("{}.{}", run.run_number, extension),
+        content_type: content_type.to_string(),
+        content,
+    })
+}
+
+/// KlikBCA Bisnis bulk transfer: one row per beneficiary after a header
+/// row. Transfers to other banks go through LLG (kliring).
+fn bca_csv(run: &PaymentRun, transfers: &[VendorTransfer]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record([
+        "No", "Tanggal", "Rekening Sumber", "Rekening Tujuan", "Nama Penerima", "Bank Tujuan",
+        "Jenis Transfer", "Mata Uang", "Nominal", "Berita 1", "Berita 2", "Email Penerima",
+    ]).map_err(|e| e.to_string())?;
+
+    for (index, transfer) in transfers.iter().enumerate() {
+        let transfer_type = if is_bank(&transfer.bank_name, &["BCA", "CENTRAL ASIA"]) { "BCA" } else { "LLG" };
+
+        writer.write_record([
+            (index + 1).to_string(),
+            run.payment_date.format("%d/%m/%Y").to_string(),
+            digits(&run.source_account_number),
+            digits(&transfer.account_number),
+            transfer.account_name.clone(),
+            transfer.bank_name.clone(),
+            transfer_type.to_string(),
+            run.currency.clone(),
+            format!("{:.2}", transfer.amount),
+            truncate(&run.run_number, BCA_REMARK_LENGTH),
+            truncate(&transfer.invoice_numbers.join(" "), BCA_REMARK_LENGTH),
+            transfer.vendor_email.clone().unwrap_or_default(),
+        ]).map_err(|e| e.to_string())?;
+    }
+
+    into_string(writer)
+}
+
+/// Mandiri Cash Management bulk transfer: a `P` header record with the
+/// execution date, debit account, transfer count and total, then one record
+/// per beneficiary. IBU is an in-house transfer, OBU an online transfer to
+/// another bank.
+fn mandiri_mcm(run: &PaymentRun, transfers: &[VendorTransfer]) -> Result<String, String> {
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+
+    let total: Decimal = transfers.iter().map(|t| t.amount).sum();
+    writer.write_record([
+        "P".to_string(),
+        run.payment_date.format("%Y%m%d").to_string(),
+        digits(&run.source_account_number),
+        transfers.len().to_string(),
+        format!("{:.2}", total),
+    ]).map_err(|e| e.to_string())?;
+
+    for transfer in transfers {
+        let transfer_type = if is_bank(&transfer.bank_name, &["MANDIRI"]) { "IBU" } else { "OBU" };
+
+        writer.write_record([
+            digits(&transfer.account_number),
+            transfer.account_name.clone(),
+            run.currency.clone(),
+            format!("{:.2}", transfer.amount),
+            transfer.invoice_numbers.join(" "),
+            format!("{}-{}", run.run_number, transfer.vendor_code),
+            transfer_type.to_string(),
+            transfer.bank_name.clone(),
+            transfer.vendor_email.clone().unwrap_or_default(),
+        ]).map_err(|e| e.to_string())?;
+    }
+
+    into_string(writer)
+}
+
+/// ISO 20022 pain.001.001.03 with a single payment information block
+/// debiting the run's source account.
+fn pain001(run: &PaymentRun, transfers: &[VendorTransfer], created_at: chrono::DateTime<chrono::Utc>) -> String {
+    let total: Decimal = transfers.iter().map(|t| t.amount).sum();
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<Document xmlns=\"urn:iso:std:iso:20022:tech:xsd:pain.001.001.03\">\n");
+    xml.push_str("  <CstmrCdtTrfInitn>\n");
+    xml.push_str("    <GrpHdr>\n");
+    xml.push_str(&format!("      <MsgId>{}</MsgId>\n", text(&run.run_number, ISO_ID_LENGTH)));
+    xml.push_str(&format!("      <CreDtTm>{}</CreDtTm>\n", created_at.format("%Y-%m-%dT%H:%M:%S")));
+    xml.push_str(&format!("      <NbOfTxs>{}</NbOfTxs>\n", transfers.len()));
+    xml.push_str(&format!("      <CtrlSum>{:.2}</CtrlSum>\n", total));
+    xml.push_str(&format!("      <InitgPty><Nm>{}</Nm></InitgPty>\n", text(&run.source_account_name, ISO_TEXT_LENGTH)));
+    xml.push_str("    </GrpHdr>\n");
+    xml.push_str("    <PmtInf>\n");
+    xml.push_str(&format!("      <PmtInfId>{}</PmtInfId>\n", text(&run.run_number, ISO_ID_LENGTH)));
+    xml.push_str("      <PmtMtd>TRF</PmtMtd>\n");
+    xml.push_str(&format!("      <NbOfTxs>{}</NbOfTxs>\n", transfers.len()));
+    xml.push_str(&format!("      <CtrlSum>{:.2}</CtrlSum>\n", total));
+    xml.push_str(&format!("      <ReqdExctnDt>{}</ReqdExctnDt>\n", run.payment_date.format("%Y-%m-%d")));
+    xml.push_str(&format!("      <Dbtr><Nm>{}</Nm></Dbtr>\n", text(&run.source_account_name, ISO_TEXT_LENGTH)));
+    xml.push_str(&format!(
+        "      <DbtrAcct><Id><Othr><Id>{}</Id></Othr></Id><Ccy>{}</Ccy></DbtrAcct>\n",
+        text(&digits(&run.source_account_number), ISO_ID_LENGTH),
+        text(&run.currency, 3)
+    ));
+    xml.push_str("      <DbtrAgt><FinInstnId><Othr><Id>NOTPROVIDED</Id></Othr></FinInstnId></DbtrAgt>\n");
+    xml.push_str("      <ChrgBr>SLEV</ChrgBr>\n");
+
+    for transfer in transfers {
+        let end_to_end_id = format!("{}-{}", run.run_number, transfer.vendor_code);
+
+        xml.push_str("      <CdtTrfTxInf>\n");
+        xml.push_str(&format!("        <PmtId><EndToEndId>{}</EndToEndId></PmtId>\n", text(&end_to_end_id, ISO_ID_LENGTH)));
+        xml.push_str(&format!(
+            "        <Amt><InstdAmt Ccy=\"{}\">{:.2}</InstdAmt></Amt>\n",
+            text(&run.currency, 3),
+            transfer.amount
+        ));
+        xml.push_str(&format!("        <CdtrAgt><FinInstnId><Nm>{}</Nm></FinInstnId></CdtrAgt>\n", text(&transfer.bank_name, ISO_TEXT_LENGTH)));
+        xml.push_str(&format!("        <Cdtr><Nm>{}</Nm></Cdtr>\n", text(&transfer.account_name, ISO_TEXT_LENGTH)));
+        xml.push_str(&format!(
+            "        <CdtrAcct><Id><Othr><Id>{}</Id></Othr></Id></CdtrAcct>\n",
+            text(&digits(&transfer.account_number), ISO_ID_LENGTH)
+        ));
+        xml.push_str(&format!(
+            "        <RmtInf><Ustrd>{}</Ustrd></RmtInf>\n",
+            text(&transfer.invoice_numbers.join(" "), ISO_TEXT_LENGTH)
+        ));
+        xml.push_str("      </CdtTrfTxInf>\n");
+    }
+
+    xml.push_str("    </PmtInf>\n");
+    xml.push_str("  </CstmrCdtTrfInitn>\n");
+    xml.push_str("</Document>\n");
+    xml
+}
+
+fn is_bank(bank_name: &str, names: &[&str]) -> bool {
+    let bank_name = bank_name.to_uppercase();
+    names.iter().any(|name| bank_name.contains(name))
+}
+
+/// Account numbers are often entered with dots or dashes; banks want digits.
+fn digits(account_number: &str) -> String {
+    account_number.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+fn truncate(value: &str, length: usize) -> String {
+    value.chars().take(length).collect()
+}
+
+/// Truncates and escapes a value for an XML text node or attribute.
+fn text(value: &str, length: usize) -> String {
+    truncate(value.trim(), length)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn into_string(writer: csv::Writer<Vec<u8>>) -> Result<String, String> {
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn run() -> PaymentRun {
+        PaymentRun {
+            id: Uuid::nil(),
+            company_id: Uuid::nil(),
+            run_number: "PYR-202603-0001".to_string(),
+            payment_date: NaiveDate::from_ymd_opt(2026, 3, 25).unwrap(),
+            currency: "IDR".to_string(),
+            due_date_to: NaiveDate::from_ymd_opt(2026, 3, 31).unwrap(),
+            bank_account_id: None,
+            source_account_number: "123-456-7890".to_string(),
+            source_account_name: "PT Maju & Jaya".to_string(),
+            status: "APPROVED".to_string(),
+            total_outstanding: Decimal::from(3_000_000),
+            total_discount: Decimal::ZERO,
+            total_payment: Decimal::from(3_000_000),
+            total_withholding: Decimal::ZERO,
+            created_by: Uuid::nil(),
+            approved_by: None,
+            approved_at: None,
+            completed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn transfers() -> Vec<VendorTransfer> {
+        vec![
+            VendorTransfer {
+                vendor_code: "V001".to_string(),
+                vendor_email: Some("ar@sumber.co.id".to_string()),
+                bank_name: "Bank Central Asia".to_string(),
+                account_number: "0987654321".to_string(),
+                account_name: "CV Sumber Rejeki".to_string(),
+                amount: Decimal::from(1_000_000),
+                invoice_numbers: vec!["INV-001".to_string(), "INV-002".to_string(), "INV-003".to_string()],
+            },
+            VendorTransfer {
+                vendor_code: "V002".to_string(),
+                vendor_email: None,
+                bank_name: "Bank Mandiri".to_string(),
+                account_number: "1300.0123.4567".to_string(),
+                account_name: "PT Baja, Tbk".to_string(),
+                amount: Decimal::from(2_000_000),
+                invoice_numbers: vec!["B/2026/77".to_string()],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_bca_csv() {
+        let file = render(BankFileFormat::BcaCsv, &run(), &transfers(), Utc::now()).unwrap();
+        assert_eq!(file.file_name, "PYR-202603-0001.csv");
+
+        let lines: Vec<&str> = file.content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "1,25/03/2026,1234567890,0987654321,CV Sumber Rejeki,Bank Central Asia,BCA,IDR,1000000.00,\
+             PYR-202603-0001,INV-001 INV-002 IN,ar@sumber.co.id"
+        );
+        // Names with commas are quoted, other banks go through LLG
+        assert!(lines[2].contains("\"PT Baja, Tbk\",Bank Mandiri,LLG,IDR,2000000.00"));
+    }
+
+    #[test]
+    fn test_mandiri_mcm() {
+        let file = render(BankFileFormat::MandiriMcm, &run(), &transfers(), Utc::now()).unwrap();
+
+        let lines: Vec<&str> = file.content.lines().collect();
+        assert_eq!(lines[0], "P,20260325,1234567890,2,3000000.00");
+        assert!(lines[1].ends_with(",OBU,Bank Central Asia,ar@sumber.co.id"));
+        assert_eq!(
+            lines[2],
+            "130001234567,\"PT Baja, Tbk\",IDR,2000000.00,B/2026/77,PYR-202603-0001-V002,IBU,Bank Mandiri,"
+        );
+    }
+
+    #[test]
+    fn test_pain001() {
+        let created_at = Utc.with_ymd_and_hms(2026, 3, 24, 9, 30, 0).unwrap();
+        let file = render(BankFileFormat::Pain001, &run(), &transfers(), created_at).unwrap();
+        assert_eq!(file.content_type, "application/xml");
+
+        let xml = file.content;
+        assert!(xml.contains("<CreDtTm>2026-03-24T09:30:00</CreDtTm>"));
+        assert_eq!(xml.matches("<NbOfTxs>2</NbOfTxs>").count(), 2);
+        assert_eq!(xml.matches("<CtrlSum>3000000.00</CtrlSum>").count(), 2);
+        assert!(xml.contains("<Dbtr><Nm>PT Maju &amp; Jaya</Nm></Dbtr>"));
+        assert!(xml.contains("<InstdAmt Ccy=\"IDR\">2000000.00</InstdAmt>"));
+        assert!(xml.contains("<EndToEndId>PYR-202603-0001-V001</EndToEndId>"));
+        assert!(xml.contains("<Ustrd>INV-001 INV-002 INV-003</Ustrd>"));
+    }
+
+    #[test]
+    fn test_render_empty_run() {
+        assert!(render(BankFileFormat::Pain001, &run(), &[], Utc::now()).is_err());
+    }
+}
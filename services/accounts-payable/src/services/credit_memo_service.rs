@@ -0,0 +1,963 @@
+use crate::models::*;
+use super::LedgerOutbox;
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Source document type of the ledger entry booked when a credit memo is
+/// approved.
+pub(crate) const CREDIT_MEMO_SOURCE: &str = "VENDOR_CREDIT_MEMO";
+
+/// Source document type of the ledger entry booked for a refund.
+pub(crate) const REFUND_SOURCE: &str = "VENDOR_CREDIT_REFUND";
+
+/// Vendor credits for returns and price corrections. An approved credit is
+/// applied to the vendor's open invoices or refunded in cash.
+pub struct CreditMemoService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+    ledger_outbox: LedgerOutbox,
+}
+
+struct LockedCreditMemo {
+    credit_memo_number: String,
+    vendor_id: Uuid,
+    vendor_name: String,
+    vendor_group: Option<String>,
+    memo_date: chrono::NaiveDate,
+    tax_amount: Decimal,
+    total_amount: Decimal,
+    applied_amount: Decimal,
+    refunded_amount: Decimal,
+    currency: String,
+    exchange_rate: Decimal,
+    status: CreditMemoStatus,
+    journal_entry_id: Option<Uuid>,
+}
+
+impl CreditMemoService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self {
+            audit_logger,
+            ledger_outbox: LedgerOutbox::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Records a draft credit memo. A memo correcting an invoice is in the
+    /// invoice's currency and cannot credit more than the invoice total.
+    pub async fn create_credit_memo(
+        &self,
+        request: CreateCreditMemoRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let vendor = sqlx::query!(
+            "SELECT is_active FROM vendors WHERE id = $1 AND company_id = $2",
+            request.vendor_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor not found".to_string()))?;
+
+        if vendor.is_active != Some(true) {
+            return Err(ServiceError::Validation("Vendor is inactive".to_string()));
+        }
+
+        let line_amounts: Vec<Decimal> = request.lines
+            .iter()
+            .map(|l| (l.quantity.unwrap_or(Decimal::ONE) * l.unit_price).round_dp(2))
+            .collect();
+        let lines_total: Decimal = line_amounts.iter().sum();
+        if lines_total != request.subtotal {
+            return Err(ServiceError::Validation(format!(
+                "Credit memo lines add up to {} but the subtotal is {}",
+                lines_total, request.subtotal
+            )));
+        }
+
+        let dimensions: Vec<_> = request.lines
+            .iter()
+            .flat_map(|l| database::dimensions::line_values(l.department.as_deref(), l.project_code.as_deref(), None))
+            .collect();
+        database::dimensions::ensure_dimensions_exist(&self.db, company_id, &dimensions).await?;
+
+        let total_amount = request.subtotal + request.tax_amount;
+
+        let (currency, exchange_rate) = match request.invoice_id {
+            Some(invoice_id) => {
+                let invoice = sqlx::query!(
+                    r#"
+                    SELECT invoice_number, total_amount, currency, exchange_rate, status as "status_str"
+                    FROM vendor_invoices
+                    WHERE id = $1 AND company_id = $2 AND vendor_id = $3
+                    "#,
+                    invoice_id,
+                    company_id,
+                    request.vendor_id
+                )
+                .fetch_optional(&self.db)
+                .await
+                .map_err(ServiceError::Database)?
+                .ok_or_else(|| ServiceError::NotFound("Invoice not found for this vendor".to_string()))?;
+
+                if !matches!(invoice.status_str.as_deref(), Some("APPROVED") | Some("PAID")) {
+                    return Err(ServiceError::Validation(format!(
+                        "Invoice {} has not been booked, so there is nothing to credit", invoice.invoice_number
+                    )));
+                }
+                if total_amount > invoice.total_amount {
+                    return Err(ServiceError::Validation(format!(
+                        "Credit of {} exceeds invoice {} total of {}",
+                        total_amount, invoice.invoice_number, invoice.total_amount
+                    )));
+                }
+
+                (
+                    invoice.currency.unwrap_or_else(|| "IDR".to_string()),
+                    invoice.exchange_rate.unwrap_or(Decimal::ONE),
+                )
+            }
+            None => ("IDR".to_string(), Decimal::ONE),
+        };
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let credit_memo_id = Uuid::new_v4();
+        let credit_memo_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::VendorCreditMemo,
+            request.memo_date,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vendor_credit_memos (
+                id, company_id, vendor_id, credit_memo_number, vendor_reference, memo_date, reason,
+                invoice_id, subtotal, tax_amount, total_amount, currency, exchange_rate, status,
+                description, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'DRAFT', $14, $15, NOW(), NOW())
+            "#,
+            credit_memo_id,
+            company_id,
+            request.vendor_id,
+            credit_memo_number,
+            request.vendor_reference.as_deref().map(str::trim),
+            request.memo_date,
+            request.reason.to_string(),
+            request.invoice_id,
+            request.subtotal,
+            request.tax_amount,
+            total_amount,
+            currency,
+            exchange_rate,
+            request.description,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (index, (line, line_amount)) in request.lines.iter().zip(&line_amounts).enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO vendor_credit_memo_lines (
+                    id, credit_memo_id, line_number, description, quantity, unit_price, line_amount,
+                    account_id, item_category, department, project_code
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+                Uuid::new_v4(),
+                credit_memo_id,
+                (index + 1) as i32,
+                line.description,
+                line.quantity.unwrap_or(Decimal::ONE),
+                line.unit_price,
+                line_amount,
+                line.account_id,
+                line.item_category.as_deref().map(|c| c.trim().to_uppercase()),
+                line.department,
+                line.project_code
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_credit_memos",
+            credit_memo_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "credit_memo_number": credit_memo_number,
+                "vendor_id": request.vendor_id,
+                "invoice_id": request.invoice_id,
+                "total_amount": total_amount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created vendor credit memo {} for company {}", credit_memo_number, company_id);
+
+        self.get_credit_memo(credit_memo_id, company_id).await
+    }
+
+    pub async fn get_credit_memos(
+        &self,
+        company_id: Uuid,
+        vendor_id: Option<Uuid>,
+        status: Option<CreditMemoStatus>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<VendorCreditMemo>> {
+        sqlx::query_as!(
+            VendorCreditMemo,
+            r#"
+            SELECT cm.id, cm.company_id, cm.vendor_id, v.vendor_name, cm.credit_memo_number, cm.vendor_reference,
+                   cm.memo_date, cm.reason, cm.invoice_id, cm.subtotal, cm.tax_amount, cm.total_amount,
+                   cm.applied_amount, cm.refunded_amount,
+                   cm.total_amount - cm.applied_amount - cm.refunded_amount as "unapplied_amount!",
+                   cm.currency, cm.exchange_rate, cm.status, cm.description, cm.journal_entry_id,
+                   cm.reversal_journal_entry_id, cm.posting_status, cm.created_by, cm.approved_by, cm.approved_at, cm.created_at, cm.updated_at
+            FROM vendor_credit_memos cm
+            JOIN vendors v ON v.id = cm.vendor_id
+            WHERE cm.company_id = $1
+              AND ($2::uuid IS NULL OR cm.vendor_id = $2)
+              AND ($3::text IS NULL OR cm.status = $3)
+            ORDER BY cm.memo_date DESC, cm.credit_memo_number DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            company_id,
+            vendor_id,
+            status.map(|s| s.to_string()),
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_credit_memo(
+        &self,
+        credit_memo_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let credit_memo = sqlx::query_as!(
+            VendorCreditMemo,
+            r#"
+            SELECT cm.id, cm.company_id, cm.vendor_id, v.vendor_name, cm.credit_memo_number, cm.vendor_reference,
+                   cm.memo_date, cm.reason, cm.invoice_id, cm.subtotal, cm.tax_amount, cm.total_amount,
+                   cm.applied_amount, cm.refunded_amount,
+                   cm.total_amount - cm.applied_amount - cm.refunded_amount as "unapplied_amount!",
+                   cm.currency, cm.exchange_rate, cm.status, cm.description, cm.journal_entry_id,
+                   cm.reversal_journal_entry_id, cm.posting_status, cm.created_by, cm.approved_by, cm.approved_at, cm.created_at, cm.updated_at
+            FROM vendor_credit_memos cm
+            JOIN vendors v ON v.id = cm.vendor_id
+            WHERE cm.id = $1 AND cm.company_id = $2
+            "#,
+            credit_memo_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Credit memo not found".to_string()))?;
+
+        let lines = self.get_lines(credit_memo_id).await?;
+
+        let applications = sqlx::query_as!(
+            VendorCreditApplication,
+            r#"
+            SELECT ca.id, ca.credit_memo_id, ca.invoice_id, vi.invoice_number, ca.amount,
+                   ca.application_date, ca.created_by, ca.created_at
+            FROM vendor_credit_applications ca
+            JOIN vendor_invoices vi ON vi.id = ca.invoice_id
+            WHERE ca.credit_memo_id = $1
+            ORDER BY ca.created_at
+            "#,
+            credit_memo_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let refunds = sqlx::query_as!(
+            VendorCreditRefund,
+            r#"
+            SELECT id, credit_memo_id, refund_number, refund_amount, refund_date, payment_method,
+                   bank_account_id, reference, journal_entry_id, posting_status, created_by, created_at
+            FROM vendor_credit_refunds
+            WHERE credit_memo_id = $1
+            ORDER BY refund_date, created_at
+            "#,
+            credit_memo_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(VendorCreditMemoWithDetails { credit_memo, lines, applications, refunds })
+    }
+
+    /// Books a draft credit memo in the general ledger, reversing the
+    /// purchase it corrects: Dr Hutang Dagang, Cr the expense per line and
+    /// PPN Masukan.
+    pub async fn approve_credit_memo(
+        &self,
+        credit_memo_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let memo = self.lock_credit_memo(&mut tx, credit_memo_id, company_id).await?;
+        if memo.status != CreditMemoStatus::Draft {
+            return Err(ServiceError::Validation(format!(
+                "Credit memo {} cannot go from {} to {}",
+                memo.credit_memo_number, memo.status, CreditMemoStatus::Approved
+            )));
+        }
+
+        database::periods::ensure_period_open(&mut *tx, company_id, memo.memo_date, roles).await?;
+
+        let description = format!("Credit memo {} - {}", memo.credit_memo_number, memo.vendor_name);
+        let lines = self.get_lines(credit_memo_id).await?;
+        let posting_lines = credit_memo_posting_lines(&lines, memo.tax_amount, memo.exchange_rate, &description)
+            .map_err(ServiceError::Validation)?;
+
+        let outbox_id = self.ledger_outbox.enqueue_posting(
+            &mut tx,
+            company_id,
+            &LedgerPostingRequest {
+                source_document_type: CREDIT_MEMO_SOURCE.to_string(),
+                source_document_id: credit_memo_id,
+                entry_date: memo.memo_date,
+                description: Some(description),
+                reference: Some(memo.credit_memo_number.clone()),
+                event_type: LedgerEvent::VendorCreditReceived,
+                vendor_group: memo.vendor_group.clone(),
+                lines: posting_lines,
+            },
+            user_id,
+            roles,
+        ).await?;
+        let posting_status = PostingStatus::PendingPosting.to_string();
+
+        sqlx::query!(
+            r#"
+            UPDATE vendor_credit_memos
+            SET status = 'APPROVED', posting_status = $1, approved_by = $2, approved_at = NOW(), updated_at = NOW()
+            WHERE id = $3
+            "#,
+            posting_status,
+            user_id,
+            credit_memo_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_credit_memos",
+            credit_memo_id,
+            "APPROVE",
+            Some(serde_json::json!({ "status": memo.status.to_string() })),
+            Some(serde_json::json!({
+                "status": CreditMemoStatus::Approved.to_string(),
+                "posting_status": posting_status
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.ledger_outbox.dispatch(outbox_id).await?;
+
+        tracing::info!("Credit memo {} approved by user {}", memo.credit_memo_number, user_id);
+
+        self.get_credit_memo(credit_memo_id, company_id).await
+    }
+
+    /// Cancels a draft, or an approved memo none of which has been applied
+    /// or refunded; the latter's ledger entry is reversed today.
+    pub async fn cancel_credit_memo(
+        &self,
+        credit_memo_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let memo = self.lock_credit_memo(&mut tx, credit_memo_id, company_id).await?;
+        let unused = memo.applied_amount == Decimal::ZERO && memo.refunded_amount == Decimal::ZERO;
+        let allowed = match memo.status {
+            CreditMemoStatus::Draft => true,
+            CreditMemoStatus::Approved => unused,
+            _ => false,
+        };
+        if !allowed {
+            return Err(ServiceError::Validation(format!(
+                "Credit memo {} cannot go from {} to {}",
+                memo.credit_memo_number, memo.status, CreditMemoStatus::Cancelled
+            )));
+        }
+
+        let mut outbox_id = None;
+        if memo.status == CreditMemoStatus::Approved {
+            // The reversal is booked today
+            let reversal_date = chrono::Utc::now().date_naive();
+            database::periods::ensure_period_open(&mut *tx, company_id, reversal_date, roles).await?;
+
+            outbox_id = self.ledger_outbox.enqueue_reversal(
+                &mut tx,
+                company_id,
+                CREDIT_MEMO_SOURCE,
+                credit_memo_id,
+                memo.journal_entry_id,
+                reversal_date,
+                &format!("Credit memo {} cancelled", memo.credit_memo_number),
+                user_id,
+                roles,
+            ).await?;
+        }
+        let posting_status = match outbox_id {
+            Some(_) => PostingStatus::PendingPosting,
+            None => PostingStatus::NotPosted,
+        }.to_string();
+
+        sqlx::query!(
+            "UPDATE vendor_credit_memos SET status = 'CANCELLED', posting_status = $1, updated_at = NOW() WHERE id = $2",
+            posting_status,
+            credit_memo_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_credit_memos",
+            credit_memo_id,
+            "CANCEL",
+            Some(serde_json::json!({ "status": memo.status.to_string() })),
+            Some(serde_json::json!({
+                "status": CreditMemoStatus::Cancelled.to_string(),
+                "posting_status": posting_status
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        if let Some(outbox_id) = outbox_id {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+
+        tracing::info!("Credit memo {} cancelled by user {}", memo.credit_memo_number, user_id);
+
+        self.get_credit_memo(credit_memo_id, company_id).await
+    }
+
+    /// Applies an approved credit to approved invoices of the same vendor
+    /// and currency. Like a discount, the credit counts as paid on the
+    /// invoice and is tracked in its credited amount. Both sides are already
+    /// in Hutang Dagang, so nothing is posted.
+    pub async fn apply_credit_memo(
+        &self,
+        credit_memo_id: Uuid,
+        request: ApplyCreditMemoRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let mut invoice_ids: Vec<Uuid> = request.applications.iter().map(|a| a.invoice_id).collect();
+        invoice_ids.sort();
+        invoice_ids.dedup();
+        if invoice_ids.len() != request.applications.len() {
+            return Err(ServiceError::Validation("Each invoice can only be listed once".to_string()));
+        }
+        if request.applications.iter().any(|a| a.amount <= Decimal::ZERO) {
+            return Err(ServiceError::Validation("Applied amounts must be positive".to_string()));
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let memo = self.lock_credit_memo(&mut tx, credit_memo_id, company_id).await?;
+        if memo.status != CreditMemoStatus::Approved {
+            return Err(ServiceError::Validation(format!(
+                "Credit memo {} is {}; only approved credit can be applied", memo.credit_memo_number, memo.status
+            )));
+        }
+
+        let unapplied = memo.total_amount - memo.applied_amount - memo.refunded_amount;
+        let total_applied: Decimal = request.applications.iter().map(|a| a.amount).sum();
+        if total_applied > unapplied {
+            return Err(ServiceError::Validation(format!(
+                "Applying {} exceeds the {} left on credit memo {}",
+                total_applied, unapplied, memo.credit_memo_number
+            )));
+        }
+
+        for application in &request.applications {
+            let invoice = sqlx::query!(
+                r#"
+                SELECT invoice_number, total_amount, paid_amount, COALESCE(currency, 'IDR') as "currency!",
+                       vendor_id, status as "status_str"
+                FROM vendor_invoices
+                WHERE id = $1 AND company_id = $2
+                FOR UPDATE
+                "#,
+                application.invoice_id,
+                company_id
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?
+            .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+            if invoice.vendor_id != memo.vendor_id {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is not from {}", invoice.invoice_number, memo.vendor_name
+                )));
+            }
+            if invoice.status_str.as_deref() != Some("APPROVED") {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is not open for payment", invoice.invoice_number
+                )));
+            }
+            if !invoice.currency.eq_ignore_ascii_case(&memo.currency) {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice {} is in {} but the credit is in {}",
+                    invoice.invoice_number, invoice.currency, memo.currency
+                )));
+            }
+
+            let paid_amount = invoice.paid_amount.unwrap_or(Decimal::ZERO);
+            let outstanding = invoice.total_amount - paid_amount;
+            if application.amount > outstanding {
+                return Err(ServiceError::Validation(format!(
+                    "Applying {} exceeds invoice {} outstanding balance of {}",
+                    application.amount, invoice.invoice_number, outstanding
+                )));
+            }
+
+            let new_status = if paid_amount + application.amount >= invoice.total_amount {
+                InvoiceStatus::Paid
+            } else {
+                InvoiceStatus::Approved
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE vendor_invoices
+                SET paid_amount = COALESCE(paid_amount, 0) + $1,
+                    credited_amount = credited_amount + $1,
+                    status = $2::invoice_status,
+                    updated_at = NOW()
+                WHERE id = $3
+                "#,
+                application.amount,
+                new_status.to_string(),
+                application.invoice_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO vendor_credit_applications (
+                    id, company_id, credit_memo_id, invoice_id, amount, application_date, created_by, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+                "#,
+                Uuid::new_v4(),
+                company_id,
+                credit_memo_id,
+                application.invoice_id,
+                application.amount,
+                request.application_date,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        self.record_usage(&mut tx, credit_memo_id, total_applied, Decimal::ZERO, unapplied).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_credit_memos",
+            credit_memo_id,
+            "APPLY",
+            Some(serde_json::json!({ "applied_amount": memo.applied_amount })),
+            Some(serde_json::json!({
+                "applied_amount": memo.applied_amount + total_applied,
+                "applications": request.applications
+                    .iter()
+                    .map(|a| serde_json::json!({ "invoice_id": a.invoice_id, "amount": a.amount }))
+                    .collect::<Vec<_>>()
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Applied {} of credit memo {} to {} invoice(s)",
+            total_applied, memo.credit_memo_number, request.applications.len()
+        );
+
+        self.get_credit_memo(credit_memo_id, company_id).await
+    }
+
+    /// Records cash the vendor paid back on an approved credit: Dr the bank
+    /// account, Cr Hutang Dagang.
+    pub async fn refund_credit_memo(
+        &self,
+        credit_memo_id: Uuid,
+        request: CreditMemoRefundRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorCreditMemoWithDetails> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.refund_date, roles).await?;
+
+        let memo = self.lock_credit_memo(&mut tx, credit_memo_id, company_id).await?;
+        if memo.status != CreditMemoStatus::Approved {
+            return Err(ServiceError::Validation(format!(
+                "Credit memo {} is {}; only approved credit can be refunded", memo.credit_memo_number, memo.status
+            )));
+        }
+
+        let unapplied = memo.total_amount - memo.applied_amount - memo.refunded_amount;
+        if request.refund_amount > unapplied {
+            return Err(ServiceError::Validation(format!(
+                "Refund of {} exceeds the {} left on credit memo {}",
+                request.refund_amount, unapplied, memo.credit_memo_number
+            )));
+        }
+
+        let refund_id = Uuid::new_v4();
+        let refund_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::VendorRefund,
+            request.refund_date,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vendor_credit_refunds (
+                id, company_id, credit_memo_id, refund_number, refund_amount, refund_date,
+                payment_method, bank_account_id, reference, posting_status, created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'PENDING_POSTING', $10, NOW())
+            "#,
+            refund_id,
+            company_id,
+            credit_memo_id,
+            refund_number,
+            request.refund_amount,
+            request.refund_date,
+            request.payment_method,
+            request.bank_account_id,
+            request.reference,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let ledger_amount = (request.refund_amount * memo.exchange_rate).round_dp(2);
+        let description = format!("Refund {} - {} {}", refund_number, memo.vendor_name, memo.credit_memo_number);
+
+        let outbox_id = self.ledger_outbox.enqueue_posting(
+            &mut tx,
+            company_id,
+            &LedgerPostingRequest {
+                source_document_type: REFUND_SOURCE.to_string(),
+                source_document_id: refund_id,
+                entry_date: request.refund_date,
+                description: Some(description.clone()),
+                reference: request.reference.clone().or_else(|| Some(refund_number.clone())),
+                event_type: LedgerEvent::VendorRefundReceived,
+                vendor_group: memo.vendor_group.clone(),
+                lines: vec![
+                    LedgerPostingLine {
+                        account_id: request.bank_account_id,
+                        account_role: Some(LedgerAccountRole::Bank),
+                        item_category: None,
+                        tax_type: None,
+                        description: Some(description.clone()),
+                        debit_amount: ledger_amount,
+                        credit_amount: Decimal::ZERO,
+                        department: None,
+                        project_code: None,
+                        foreign: None,
+                    },
+                    LedgerPostingLine {
+                        account_id: None,
+                        account_role: Some(LedgerAccountRole::ApControl),
+                        item_category: None,
+                        tax_type: None,
+                        description: Some(description),
+                        debit_amount: Decimal::ZERO,
+                        credit_amount: ledger_amount,
+                        department: None,
+                        project_code: None,
+                        foreign: None,
+                    },
+                ],
+            },
+            user_id,
+            roles,
+        ).await?;
+
+        self.record_usage(&mut tx, credit_memo_id, Decimal::ZERO, request.refund_amount, unapplied).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_credit_memos",
+            credit_memo_id,
+            "REFUND",
+            Some(serde_json::json!({ "refunded_amount": memo.refunded_amount })),
+            Some(serde_json::json!({
+                "refunded_amount": memo.refunded_amount + request.refund_amount,
+                "refund_number": refund_number
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.ledger_outbox.dispatch(outbox_id).await?;
+
+        tracing::info!(
+            "Recorded refund {} of {} on credit memo {}",
+            refund_number, request.refund_amount, memo.credit_memo_number
+        );
+
+        self.get_credit_memo(credit_memo_id, company_id).await
+    }
+
+    async fn get_lines(&self, credit_memo_id: Uuid) -> ServiceResult<Vec<VendorCreditMemoLine>> {
+        sqlx::query_as!(
+            VendorCreditMemoLine,
+            r#"
+            SELECT id, credit_memo_id, line_number, description, quantity, unit_price, line_amount,
+                   account_id, item_category, department, project_code
+            FROM vendor_credit_memo_lines
+            WHERE credit_memo_id = $1
+            ORDER BY line_number
+            "#,
+            credit_memo_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// Adds to what has been applied and refunded, closing the memo once
+    /// nothing is left of it.
+    async fn record_usage(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        credit_memo_id: Uuid,
+        applied: Decimal,
+        refunded: Decimal,
+        unapplied: Decimal,
+    ) -> ServiceResult<()> {
+        let status = if applied + refunded >= unapplied {
+            CreditMemoStatus::Closed
+        } else {
+            CreditMemoStatus::Approved
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE vendor_credit_memos
+            SET applied_amount = applied_amount + $1,
+                refunded_amount = refunded_amount + $2,
+                status = $3,
+                updated_at = NOW()
+            WHERE id = $4
+            "#,
+            applied,
+            refunded,
+            status.to_string(),
+            credit_memo_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    async fn lock_credit_memo(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        credit_memo_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<LockedCreditMemo> {
+        let row = sqlx::query!(
+            r#"
+            SELECT cm.credit_memo_number, cm.vendor_id, v.vendor_name, v.vendor_group, cm.memo_date,
+                   cm.tax_amount, cm.total_amount, cm.applied_amount, cm.refunded_amount,
+                   cm.currency, cm.exchange_rate, cm.status, cm.journal_entry_id
+            FROM vendor_credit_memos cm
+            JOIN vendors v ON v.id = cm.vendor_id
+            WHERE cm.id = $1 AND cm.company_id = $2
+            FOR UPDATE OF cm
+            "#,
+            credit_memo_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Credit memo not found".to_string()))?;
+
+        Ok(LockedCreditMemo {
+            credit_memo_number: row.credit_memo_number,
+            vendor_id: row.vendor_id,
+            vendor_name: row.vendor_name,
+            vendor_group: row.vendor_group,
+            memo_date: row.memo_date,
+            tax_amount: row.tax_amount,
+            total_amount: row.total_amount,
+            applied_amount: row.applied_amount,
+            refunded_amount: row.refunded_amount,
+            currency: row.currency,
+            exchange_rate: row.exchange_rate,
+            status: row.status.parse().map_err(ServiceError::Internal)?,
+            journal_entry_id: row.journal_entry_id,
+        })
+    }
+}
+
+/// Ledger lines for approving a credit memo, the mirror of an invoice's:
+/// each line credits its own account (or the expense account the posting
+/// rules give its item category), the tax reverses input VAT and the total
+/// is debited to the AP control account. Amounts are converted at the
+/// memo's rate; the debit is the sum of the rounded credits.
+fn credit_memo_posting_lines(
+    lines: &[VendorCreditMemoLine],
+    tax_amount: Decimal,
+    exchange_rate: Decimal,
+    description: &str,
+) -> Result<Vec<LedgerPostingLine>, String> {
+    if lines.is_empty() {
+        return Err("Credit memo has no lines to book".to_string());
+    }
+
+    let mut posting = Vec::with_capacity(lines.len() + 2);
+    for line in lines {
+        posting.push(LedgerPostingLine {
+            account_id: line.account_id,
+            account_role: line.account_id.is_none().then_some(LedgerAccountRole::Expense),
+            item_category: line.item_category.clone(),
+            tax_type: None,
+            description: Some(line.description.clone()),
+            debit_amount: Decimal::ZERO,
+            credit_amount: (line.line_amount * exchange_rate).round_dp(2),
+            department: line.department.clone(),
+            project_code: line.project_code.clone(),
+            foreign: None,
+        });
+    }
+
+    if tax_amount > Decimal::ZERO {
+        posting.push(LedgerPostingLine {
+            account_id: None,
+            account_role: Some(LedgerAccountRole::InputVat),
+            item_category: None,
+            tax_type: Some("PPN".to_string()),
+            description: Some(format!("Koreksi PPN Masukan {}", description)),
+            debit_amount: Decimal::ZERO,
+            credit_amount: (tax_amount * exchange_rate).round_dp(2),
+            department: None,
+            project_code: None,
+            foreign: None,
+        });
+    }
+
+    let total: Decimal = posting.iter().map(|l| l.credit_amount).sum();
+    posting.push(LedgerPostingLine {
+        account_id: None,
+        account_role: Some(LedgerAccountRole::ApControl),
+        item_category: None,
+        tax_type: None,
+        description: Some(description.to_string()),
+        debit_amount: total,
+        credit_amount: Decimal::ZERO,
+        department: None,
+        project_code: None,
+        foreign: None,
+    });
+
+    Ok(posting)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line_number: i32, amount: Decimal, account_id: Option<Uuid>) -> VendorCreditMemoLine {
+        VendorCreditMemoLine {
+            id: Uuid::new_v4(),
+            credit_memo_id: Uuid::nil(),
+            line_number,
+            description: format!("Line {}", line_number),
+            quantity: Decimal::ONE,
+            unit_price: amount,
+            line_amount: amount,
+            account_id,
+            item_category: Some("GOODS".to_string()),
+            department: None,
+            project_code: None,
+        }
+    }
+
+    #[test]
+    fn test_credit_memo_posting_lines() {
+        let account = Some(Uuid::new_v4());
+        let lines = vec![line(1, Decimal::from(400_000), account), line(2, Decimal::from(100_000), None)];
+
+        let posting = credit_memo_posting_lines(&lines, Decimal::from(55_000), Decimal::ONE, "VCM-1").unwrap();
+        assert_eq!(posting.len(), 4);
+        assert_eq!(posting[0].account_id, account);
+        assert_eq!(posting[1].account_role, Some(LedgerAccountRole::Expense));
+        assert_eq!(posting[2].account_role, Some(LedgerAccountRole::InputVat));
+        assert_eq!(posting[2].credit_amount, Decimal::from(55_000));
+        assert_eq!(posting[3].account_role, Some(LedgerAccountRole::ApControl));
+        assert_eq!(posting[3].debit_amount, Decimal::from(555_000));
+
+        let debits: Decimal = posting.iter().map(|l| l.debit_amount).sum();
+        let credits: Decimal = posting.iter().map(|l| l.credit_amount).sum();
+        assert_eq!(debits, credits);
+
+        // Foreign-currency memo, no tax
+        let posting = credit_memo_posting_lines(&[line(1, Decimal::new(1_055, 2), account)], Decimal::ZERO, Decimal::from(15_500), "VCM-2").unwrap();
+        assert_eq!(posting.len(), 2);
+        assert_eq!(posting[1].debit_amount, Decimal::from(163_525));
+
+        assert!(credit_memo_posting_lines(&[], Decimal::ZERO, Decimal::ONE, "VCM-3").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::models::*;
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, identity::{self, IDENTITY_HEADER}};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+/// Source document type of stock moves booked for goods receipt lines.
+const RECEIPT_LINE_SOURCE: &str = "GOODS_RECEIPT_LINE";
+
+/// Books received goods into stock through the inventory service, on
+/// behalf of the user receiving them.
+#[derive(Clone)]
+pub struct InventoryClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl InventoryClient {
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("INVENTORY_MANAGEMENT_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3008".to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Records a stock IN transaction for a goods receipt line and returns
+    /// its id. The inventory service books each receipt line once, so a
+    /// call whose response was lost can be repeated.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn receive_stock(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+        receipt_line_id: Uuid,
+        item_id: Uuid,
+        transaction_date: NaiveDate,
+        quantity: Decimal,
+        unit_cost: Decimal,
+        reference: &str,
+    ) -> ServiceResult<Uuid> {
+        let response = self.client
+            .post(format!("{}/transactions", self.base_url))
+            .header("X-Company-ID", company_id.to_string())
+            .header("X-User-ID", user_id.to_string())
+            .header(IDENTITY_HEADER, identity::forward(user_id, company_id, roles)?)
+            .json(&serde_json::json!({
+                "company_id": company_id,
+                "item_id": item_id,
+                "transaction_type": "IN",
+                "transaction_date": transaction_date,
+                "quantity": quantity,
+                "unit_cost": unit_cost,
+                "reference": reference,
+                "source_document_type": RECEIPT_LINE_SOURCE,
+                "source_document_id": receipt_line_id,
+            }))
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to call inventory service: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body.get("message").and_then(|m| m.as_str()).map(str::to_string))
+                .unwrap_or_else(|| format!("Inventory service returned status {}", status));
+
+            return Err(match status.as_u16() {
+                400 => ServiceError::Validation(message),
+                403 => ServiceError::Authorization(message),
+                404 => ServiceError::NotFound(message),
+                409 => ServiceError::Conflict(message),
+                _ => ServiceError::ExternalService(message),
+            });
+        }
+
+        let transaction: RecordedStockTransaction = response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to parse inventory response: {}", e)))?;
+
+        Ok(transaction.id)
+    }
+}
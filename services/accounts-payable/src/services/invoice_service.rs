@@ -0,0 +1,891 @@
+use crate::models::*;
+use super::{LedgerClient, LedgerOutbox, MatchingService};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use database::budgets::BudgetWarning;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Source document type of the ledger entry booked on approval.
+pub(crate) const INVOICE_SOURCE: &str = "VENDOR_INVOICE";
+
+pub struct InvoiceService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+    ledger_client: LedgerClient,
+    ledger_outbox: LedgerOutbox,
+    matching_service: MatchingService,
+}
+
+struct InvoiceRow {
+    id: Uuid,
+    company_id: Uuid,
+    vendor_id: Uuid,
+    vendor_name: Option<String>,
+    invoice_number: String,
+    invoice_date: chrono::NaiveDate,
+    due_date: chrono::NaiveDate,
+    subtotal: Decimal,
+    tax_amount: Option<Decimal>,
+    total_amount: Decimal,
+    paid_amount: Option<Decimal>,
+    status_str: Option<String>,
+    description: Option<String>,
+    journal_entry_id: Option<Uuid>,
+    posting_status: String,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<InvoiceRow> for VendorInvoice {
+    fn from(row: InvoiceRow) -> Self {
+        let tax_amount = row.tax_amount.unwrap_or(Decimal::ZERO);
+        let paid_amount = row.paid_amount.unwrap_or(Decimal::ZERO);
+
+        VendorInvoice {
+            id: row.id,
+            company_id: row.company_id,
+            vendor_id: row.vendor_id,
+            vendor_name: row.vendor_name,
+            invoice_number: row.invoice_number,
+            invoice_date: row.invoice_date,
+            due_date: row.due_date,
+            subtotal: row.subtotal,
+            tax_amount,
+            total_amount: row.total_amount,
+            paid_amount,
+            outstanding_amount: row.total_amount - paid_amount,
+            status: row.status_str.as_deref()
+                .and_then(|s| s.parse::<InvoiceStatus>().ok())
+                .unwrap_or(InvoiceStatus::Draft),
+            description: row.description,
+            journal_entry_id: row.journal_entry_id,
+            posting_status: row.posting_status.parse().unwrap_or(PostingStatus::NotPosted),
+            created_at: row.created_at.unwrap_or_else(chrono::Utc::now),
+            updated_at: row.updated_at.unwrap_or_else(chrono::Utc::now),
+            budget_warnings: Vec::new(),
+        }
+    }
+}
+
+impl InvoiceService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self {
+            audit_logger,
+            ledger_client: LedgerClient::from_env(),
+            ledger_outbox: LedgerOutbox::new(db.clone()),
+            matching_service: MatchingService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn create_invoice(
+        &self,
+        request: CreateVendorInvoiceRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorInvoice> {
+        let vendor = sqlx::query!(
+            "SELECT payment_terms, is_active, vendor_group, currency FROM vendors WHERE id = $1 AND company_id = $2",
+            request.vendor_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor not found".to_string()))?;
+
+        if vendor.is_active != Some(true) {
+            return Err(ServiceError::Validation("Vendor is inactive".to_string()));
+        }
+
+        let line_amounts: Vec<Decimal> = request.lines
+            .iter()
+            .map(|l| (l.quantity.unwrap_or(Decimal::ONE) * l.unit_price).round_dp(2))
+            .collect();
+        if !request.lines.is_empty() {
+            let lines_total: Decimal = line_amounts.iter().sum();
+            if lines_total != request.subtotal {
+                return Err(ServiceError::Validation(format!(
+                    "Invoice lines add up to {} but the subtotal is {}",
+                    lines_total, request.subtotal
+                )));
+            }
+        }
+
+        // A typo would otherwise only surface when the ledger rejects the posting
+        let dimensions: Vec<_> = request.lines
+            .iter()
+            .flat_map(|l| database::dimensions::line_values(l.department.as_deref(), l.project_code.as_deref(), None))
+            .collect();
+        database::dimensions::ensure_dimensions_exist(&self.db, company_id, &dimensions).await?;
+
+        // Lines billing a purchase order must bill an open order of this vendor
+        let po_line_ids: Vec<Uuid> = request.lines.iter().filter_map(|l| l.po_line_id).collect();
+        if !po_line_ids.is_empty() {
+            let valid = sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(DISTINCT pol.id) as "count!"
+                FROM purchase_order_lines pol
+                JOIN purchase_orders po ON po.id = pol.purchase_order_id
+                WHERE pol.id = ANY($1) AND po.company_id = $2 AND po.vendor_id = $3
+                  AND po.status IN ('APPROVED', 'PARTIALLY_RECEIVED', 'RECEIVED')
+                "#,
+                &po_line_ids,
+                company_id,
+                request.vendor_id
+            )
+            .fetch_one(&self.db)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            let mut distinct = po_line_ids.clone();
+            distinct.sort();
+            distinct.dedup();
+            if valid != distinct.len() as i64 {
+                return Err(ServiceError::Validation(
+                    "Invoice lines reference purchase order lines that are not open for this vendor".to_string()
+                ));
+            }
+        }
+
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM vendor_invoices
+                          WHERE company_id = $1 AND vendor_id = $2 AND invoice_number = $3)
+            "#,
+            company_id,
+            request.vendor_id,
+            request.invoice_number
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .unwrap_or(false);
+
+        if existing {
+            return Err(ServiceError::Conflict(
+                format!("Invoice '{}' already exists for this vendor", request.invoice_number)
+            ));
+        }
+
+        let due_date = request.invoice_date + chrono::Duration::days(vendor.payment_terms.unwrap_or(30) as i64);
+        let total_amount = request.subtotal + request.tax_amount;
+        let invoice_id = Uuid::new_v4();
+
+        // Kept at the rate on the invoice date until approval books it
+        let currency = request.currency
+            .as_deref()
+            .or(vendor.currency.as_deref())
+            .map(|c| c.trim().to_uppercase());
+        let rate = self.ledger_client.booking_rate(company_id, user_id, currency.as_deref(), request.invoice_date).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.invoice_date, roles).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vendor_invoices (
+                id, company_id, vendor_id, invoice_number, invoice_date, due_date,
+                subtotal, tax_amount, total_amount, paid_amount, status, description,
+                currency, exchange_rate, posting_status, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 0, 'DRAFT', $10, $11, $12, 'NOT_POSTED', $13, NOW(), NOW())
+            "#,
+            invoice_id,
+            company_id,
+            request.vendor_id,
+            request.invoice_number,
+            request.invoice_date,
+            due_date,
+            request.subtotal,
+            request.tax_amount,
+            total_amount,
+            request.description,
+            currency,
+            rate.rate,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (index, (line, line_amount)) in request.lines.iter().zip(&line_amounts).enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO vendor_invoice_lines (
+                    id, invoice_id, line_number, description, quantity, unit_price, line_amount,
+                    account_id, item_category, department, project_code, po_line_id
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                "#,
+                Uuid::new_v4(),
+                invoice_id,
+                (index + 1) as i32,
+                line.description,
+                line.quantity.unwrap_or(Decimal::ONE),
+                line.unit_price,
+                line_amount,
+                line.account_id,
+                line.item_category.as_deref().map(|c| c.trim().to_uppercase()),
+                line.department,
+                line.project_code,
+                line.po_line_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        // Out-of-tolerance invoices are still created; they wait in the
+        // matching-exceptions queue and cannot be approved
+        if !po_line_ids.is_empty() {
+            let exceptions = self.matching_service.match_invoice(&mut tx, invoice_id, company_id).await?;
+            if !exceptions.is_empty() {
+                tracing::warn!(
+                    "Invoice {} has {} matching exception(s)", request.invoice_number, exceptions.len()
+                );
+            }
+        }
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_invoices",
+            invoice_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "invoice_number": request.invoice_number,
+                "vendor_id": request.vendor_id,
+                "total_amount": total_amount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created vendor invoice {} for company {}", request.invoice_number, company_id);
+
+        let lines = self.get_invoice_lines(invoice_id).await?;
+        let budget_warnings = self.budget_warnings(
+            company_id, user_id, request.invoice_date, vendor.vendor_group, &lines, request.subtotal, &rate
+        ).await;
+
+        let invoice = self.get_invoice_by_id(invoice_id, company_id).await?;
+        Ok(VendorInvoice { budget_warnings, ..invoice })
+    }
+
+    pub async fn get_invoices(
+        &self,
+        company_id: Uuid,
+        filters: InvoiceFilters,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<VendorInvoice>> {
+        let rows = sqlx::query_as!(
+            InvoiceRow,
+            r#"
+            SELECT vi.id, vi.company_id, vi.vendor_id, v.vendor_name as "vendor_name?", vi.invoice_number,
+                   vi.invoice_date, vi.due_date, vi.subtotal, vi.tax_amount, vi.total_amount, vi.paid_amount,
+                   vi.status as "status_str", vi.description, vi.journal_entry_id, vi.posting_status,
+                   vi.created_at, vi.updated_at
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.company_id = $1
+              AND ($2::text IS NULL OR vi.status::text = $2)
+              AND ($3::uuid IS NULL OR vi.vendor_id = $3)
+              AND ($4::date IS NULL OR vi.invoice_date >= $4)
+              AND ($5::date IS NULL OR vi.invoice_date <= $5)
+            ORDER BY vi.invoice_date DESC, vi.invoice_number
+            LIMIT $6 OFFSET $7
+            "#,
+            company_id,
+            filters.status.map(|s| s.to_uppercase()),
+            filters.vendor_id,
+            filters.date_from,
+            filters.date_to,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(rows.into_iter().map(VendorInvoice::from).collect())
+    }
+
+    pub async fn get_invoice_by_id(&self, invoice_id: Uuid, company_id: Uuid) -> ServiceResult<VendorInvoice> {
+        let row = sqlx::query_as!(
+            InvoiceRow,
+            r#"
+            SELECT vi.id, vi.company_id, vi.vendor_id, v.vendor_name as "vendor_name?", vi.invoice_number,
+                   vi.invoice_date, vi.due_date, vi.subtotal, vi.tax_amount, vi.total_amount, vi.paid_amount,
+                   vi.status as "status_str", vi.description, vi.journal_entry_id, vi.posting_status,
+                   vi.created_at, vi.updated_at
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.id = $1 AND vi.company_id = $2
+            "#,
+            invoice_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+        Ok(row.into())
+    }
+
+    pub async fn get_invoice_lines(&self, invoice_id: Uuid) -> ServiceResult<Vec<VendorInvoiceLine>> {
+        sqlx::query_as!(
+            VendorInvoiceLine,
+            r#"
+            SELECT id, invoice_id, line_number, description, quantity as "quantity!", unit_price, line_amount,
+                   account_id, item_category, department, project_code, po_line_id
+            FROM vendor_invoice_lines
+            WHERE invoice_id = $1
+            ORDER BY line_number
+            "#,
+            invoice_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// Purchase order lines among those the invoice bills that order stock
+    /// items, with the average unit cost their receipts booked the goods at
+    /// in the functional currency. Lines nothing was received on yet have
+    /// no cost.
+    async fn stock_po_lines(&self, lines: &[VendorInvoiceLine]) -> ServiceResult<HashMap<Uuid, Option<Decimal>>> {
+        let po_line_ids: Vec<Uuid> = lines.iter().filter_map(|l| l.po_line_id).collect();
+        if po_line_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT pol.id,
+                   SUM(grl.quantity * grl.unit_cost) / NULLIF(SUM(grl.quantity), 0) as unit_cost
+            FROM purchase_order_lines pol
+            LEFT JOIN goods_receipt_lines grl ON grl.po_line_id = pol.id AND grl.unit_cost IS NOT NULL
+            WHERE pol.id = ANY($1) AND pol.item_id IS NOT NULL
+            GROUP BY pol.id
+            "#,
+            &po_line_ids
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row.unit_cost)).collect())
+    }
+
+    /// Moves an invoice through its workflow. Approval requires the invoice
+    /// to match its purchase orders and receipts within tolerance, then books
+    /// it in the general ledger (Dr expense per line and PPN Masukan, Cr
+    /// Hutang Dagang); cancelling an approved, unpaid invoice reverses that
+    /// entry. The ledger posting is queued with the status change and sent
+    /// once it is saved. PAID is only ever set by payments.
+    pub async fn update_invoice_status(
+        &self,
+        invoice_id: Uuid,
+        company_id: Uuid,
+        status: InvoiceStatus,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorInvoice> {
+        // Booked at the ledger's BI middle rate on the invoice date, read
+        // before the invoice is locked
+        let mut rate = None;
+        if matches!(status, InvoiceStatus::Approved) {
+            let invoice = sqlx::query!(
+                "SELECT invoice_date, currency FROM vendor_invoices WHERE id = $1 AND company_id = $2",
+                invoice_id,
+                company_id
+            )
+            .fetch_optional(&self.db)
+            .await
+            .map_err(ServiceError::Database)?
+            .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+            rate = Some(self.ledger_client.booking_rate(company_id, user_id, invoice.currency.as_deref(), invoice.invoice_date).await?);
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let current = sqlx::query!(
+            r#"
+            SELECT vi.invoice_number, vi.invoice_date, vi.subtotal, vi.tax_amount, vi.paid_amount,
+                   vi.journal_entry_id, vi.posting_status, vi.status as "status_str",
+                   v.vendor_name, v.vendor_group
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.id = $1 AND vi.company_id = $2
+            FOR UPDATE OF vi
+            "#,
+            invoice_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+        let current_status = current.status_str.as_deref()
+            .and_then(|s| s.parse::<InvoiceStatus>().ok())
+            .unwrap_or(InvoiceStatus::Draft);
+
+        let allowed = match (&current_status, &status) {
+            (InvoiceStatus::Draft, InvoiceStatus::Pending) => true,
+            (InvoiceStatus::Draft | InvoiceStatus::Pending, InvoiceStatus::Approved) => true,
+            (InvoiceStatus::Draft | InvoiceStatus::Pending, InvoiceStatus::Cancelled) => true,
+            (InvoiceStatus::Approved, InvoiceStatus::Cancelled) => {
+                current.paid_amount.unwrap_or(Decimal::ZERO) == Decimal::ZERO
+            }
+            _ => false,
+        };
+        if !allowed {
+            return Err(ServiceError::Validation(format!(
+                "Invoice {} cannot go from {} to {}",
+                current.invoice_number, current_status, status
+            )));
+        }
+
+        let memo = format!("Invoice {} - {}", current.invoice_number, current.vendor_name);
+        let mut posting_status = current.posting_status.clone();
+        let mut outbox_id = None;
+
+        match (&current_status, &status) {
+            (_, InvoiceStatus::Approved) => {
+                // Approval books the invoice, so its date must fall in an open period
+                database::periods::ensure_period_open(&mut *tx, company_id, current.invoice_date, roles).await?;
+
+                // Match again against what has been received by now
+                let exceptions = self.matching_service.match_invoice(&mut tx, invoice_id, company_id).await?;
+                if !exceptions.is_empty() {
+                    // Keep the fresh results for the exceptions queue
+                    tx.commit().await.map_err(ServiceError::Database)?;
+                    return Err(ServiceError::Validation(format!(
+                        "Invoice {} is outside the matching tolerances: {}",
+                        current.invoice_number,
+                        exceptions.join("; ")
+                    )));
+                }
+
+                let rate = rate.as_ref().ok_or_else(|| {
+                    ServiceError::Internal("Approval without an exchange rate".to_string())
+                })?;
+                let lines = self.get_invoice_lines(invoice_id).await?;
+                let stock_po_lines = self.stock_po_lines(&lines).await?;
+                let posting_lines = invoice_posting_lines(
+                    &lines,
+                    &stock_po_lines,
+                    current.subtotal,
+                    current.tax_amount.unwrap_or(Decimal::ZERO),
+                    rate,
+                    &memo,
+                );
+
+                outbox_id = Some(self.ledger_outbox.enqueue_posting(
+                    &mut tx,
+                    company_id,
+                    &LedgerPostingRequest {
+                        source_document_type: INVOICE_SOURCE.to_string(),
+                        source_document_id: invoice_id,
+                        entry_date: current.invoice_date,
+                        description: Some(memo.clone()),
+                        reference: Some(current.invoice_number.clone()),
+                        event_type: LedgerEvent::VendorInvoiceApproved,
+                        vendor_group: current.vendor_group.clone(),
+                        lines: posting_lines,
+                    },
+                    user_id,
+                    roles,
+                ).await?);
+                posting_status = PostingStatus::PendingPosting.to_string();
+            }
+            (InvoiceStatus::Approved, InvoiceStatus::Cancelled) => {
+                // The reversal is booked today
+                let reversal_date = chrono::Utc::now().date_naive();
+                database::periods::ensure_period_open(&mut *tx, company_id, reversal_date, roles).await?;
+
+                outbox_id = self.ledger_outbox.enqueue_reversal(
+                    &mut tx,
+                    company_id,
+                    INVOICE_SOURCE,
+                    invoice_id,
+                    current.journal_entry_id,
+                    reversal_date,
+                    &format!("Invoice {} cancelled", current.invoice_number),
+                    user_id,
+                    roles,
+                ).await?;
+                posting_status = match outbox_id {
+                    Some(_) => PostingStatus::PendingPosting,
+                    None => PostingStatus::NotPosted,
+                }.to_string();
+            }
+            _ => {}
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE vendor_invoices
+            SET status = $1::invoice_status,
+                posting_status = $2,
+                approved_by = CASE WHEN $1 = 'APPROVED' THEN $3 ELSE approved_by END,
+                exchange_rate = COALESCE($6, exchange_rate),
+                updated_at = NOW()
+            WHERE id = $4 AND company_id = $5
+            "#,
+            status.to_string(),
+            posting_status,
+            user_id,
+            invoice_id,
+            company_id,
+            rate.as_ref().map(|r| r.rate)
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_invoices",
+            invoice_id,
+            "STATUS_CHANGE",
+            Some(serde_json::json!({ "status": current_status.to_string() })),
+            Some(serde_json::json!({
+                "status": status.to_string(),
+                "posting_status": posting_status
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Invoice {} moved to {} by user {}", current.invoice_number, status, user_id);
+
+        if let Some(outbox_id) = outbox_id {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+
+        let mut budget_warnings = Vec::new();
+        if let (InvoiceStatus::Approved, Some(rate)) = (&status, &rate) {
+            let lines = self.get_invoice_lines(invoice_id).await?;
+            budget_warnings = self.budget_warnings(
+                company_id, user_id, current.invoice_date, current.vendor_group.clone(), &lines, current.subtotal, rate
+            ).await;
+        }
+
+        let invoice = self.get_invoice_by_id(invoice_id, company_id).await?;
+        Ok(VendorInvoice { budget_warnings, ..invoice })
+    }
+
+    /// Budgets the invoice would exceed when booked on `date`, in the
+    /// functional currency. Warnings never block the invoice, so a ledger
+    /// that cannot be reached is only logged.
+    #[allow(clippy::too_many_arguments)]
+    async fn budget_warnings(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        date: chrono::NaiveDate,
+        vendor_group: Option<String>,
+        lines: &[VendorInvoiceLine],
+        subtotal: Decimal,
+        rate: &BookingRate,
+    ) -> Vec<BudgetWarning> {
+        let stock_po_lines = match self.stock_po_lines(lines).await {
+            Ok(stock_po_lines) => stock_po_lines,
+            Err(e) => {
+                tracing::warn!("Budget check for company {} failed: {}", company_id, e);
+                return Vec::new();
+            }
+        };
+
+        let check = LedgerBudgetCheckRequest {
+            date,
+            event_type: LedgerEvent::VendorInvoiceApproved,
+            vendor_group,
+            lines: budget_check_lines(lines, &stock_po_lines, subtotal, rate),
+        };
+        if check.lines.is_empty() {
+            return Vec::new();
+        }
+
+        match self.ledger_client.check_budget(company_id, user_id, &check).await {
+            Ok(warnings) => warnings,
+            Err(e) => {
+                tracing::warn!("Budget check for company {} failed: {}", company_id, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// The expense side of the invoice as booked on approval. Stock bought
+/// through purchase orders is not spending against a budget.
+fn budget_check_lines(
+    lines: &[VendorInvoiceLine],
+    stock_po_lines: &HashMap<Uuid, Option<Decimal>>,
+    subtotal: Decimal,
+    rate: &BookingRate,
+) -> Vec<LedgerBudgetCheckLine> {
+    expense_lines(lines, stock_po_lines, subtotal, rate)
+        .into_iter()
+        .filter(|line| !matches!(
+            line.account_role,
+            Some(LedgerAccountRole::GoodsReceivedNotInvoiced | LedgerAccountRole::PurchasePriceVariance)
+        ))
+        .map(|line| LedgerBudgetCheckLine {
+            account_id: line.account_id,
+            item_category: line.item_category,
+            department: line.department,
+            project_code: line.project_code,
+            amount: line.debit_amount,
+        })
+        .collect()
+}
+
+/// One debit per invoice line, to its own account or the expense account
+/// the posting rules give its item category. Lines billing stock ordered on
+/// a purchase order clear the goods received not invoiced the receipt
+/// booked instead, at the receipt's cost; the difference from the invoiced
+/// amount goes to purchase price variance. An invoice entered without lines
+/// books its subtotal to the vendor's expense account.
+fn expense_lines(
+    lines: &[VendorInvoiceLine],
+    stock_po_lines: &HashMap<Uuid, Option<Decimal>>,
+    subtotal: Decimal,
+    rate: &BookingRate,
+) -> Vec<LedgerPostingLine> {
+    let expense_line = |account_id: Option<Uuid>, role, item_category, description, amount: Decimal, department, project_code| {
+        LedgerPostingLine {
+            account_id,
+            account_role: account_id.is_none().then_some(role),
+            item_category,
+            tax_type: None,
+            description: Some(description),
+            debit_amount: amount.max(Decimal::ZERO),
+            credit_amount: (-amount).max(Decimal::ZERO),
+            department,
+            project_code,
+            foreign: None,
+        }
+    };
+
+    if lines.is_empty() {
+        if subtotal.is_zero() {
+            return Vec::new();
+        }
+        let amount = rate.to_functional(subtotal);
+        return vec![expense_line(None, LedgerAccountRole::Expense, None, "Invoice subtotal".to_string(), amount, None, None)];
+    }
+
+    lines
+        .iter()
+        .flat_map(|line| {
+            let amount = rate.to_functional(line.line_amount);
+            let line_for = |account_id, role, description, amount| expense_line(
+                account_id,
+                role,
+                line.item_category.clone(),
+                description,
+                amount,
+                line.department.clone(),
+                line.project_code.clone(),
+            );
+
+            let received_cost = line.po_line_id
+                .and_then(|id| stock_po_lines.get(&id))
+                .map(|unit_cost| unit_cost.map_or(amount, |cost| (line.quantity * cost).round_dp(2)));
+            match received_cost {
+                // The variance comes first so the clearing line stays last
+                Some(cost) => {
+                    let variance = (amount != cost).then(|| line_for(
+                        None,
+                        LedgerAccountRole::PurchasePriceVariance,
+                        format!("Selisih harga {}", line.description),
+                        amount - cost,
+                    ));
+                    let clearing = line_for(None, LedgerAccountRole::GoodsReceivedNotInvoiced, line.description.clone(), cost);
+                    variance.into_iter().chain(std::iter::once(clearing)).collect::<Vec<_>>()
+                }
+                None => vec![line_for(line.account_id, LedgerAccountRole::Expense, line.description.clone(), amount)],
+            }
+        })
+        .collect()
+}
+
+/// Ledger lines for approving an invoice: the expense lines, the tax to
+/// input VAT and the total credited to the AP control account. The credit
+/// carries the invoice currency so the ledger can revalue it; its converted
+/// amount is authoritative and any rounding difference goes to the last
+/// expense line so the entry always balances.
+fn invoice_posting_lines(
+    lines: &[VendorInvoiceLine],
+    stock_po_lines: &HashMap<Uuid, Option<Decimal>>,
+    subtotal: Decimal,
+    tax_amount: Decimal,
+    rate: &BookingRate,
+    memo: &str,
+) -> Vec<LedgerPostingLine> {
+    let mut posting = expense_lines(lines, stock_po_lines, subtotal, rate);
+    let payable = rate.to_functional(subtotal + tax_amount);
+
+    let tax_line = (tax_amount > Decimal::ZERO).then(|| LedgerPostingLine {
+        account_id: None,
+        account_role: Some(LedgerAccountRole::InputVat),
+        item_category: None,
+        tax_type: Some("PPN".to_string()),
+        description: Some(format!("PPN Masukan {}", memo)),
+        debit_amount: rate.to_functional(tax_amount),
+        credit_amount: Decimal::ZERO,
+        department: None,
+        project_code: None,
+        foreign: None,
+    });
+
+    let debits: Decimal = posting.iter().chain(tax_line.iter()).map(|l| l.debit_amount - l.credit_amount).sum();
+    if let Some(last) = posting.last_mut() {
+        last.debit_amount += payable - debits;
+    }
+    posting.extend(tax_line);
+
+    posting.push(LedgerPostingLine {
+        account_id: None,
+        account_role: Some(LedgerAccountRole::ApControl),
+        item_category: None,
+        tax_type: None,
+        description: Some(memo.to_string()),
+        debit_amount: Decimal::ZERO,
+        credit_amount: payable,
+        department: None,
+        project_code: None,
+        foreign: rate.foreign(subtotal + tax_amount),
+    });
+
+    posting
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn line(line_number: i32, amount: &str, account_id: Option<Uuid>) -> VendorInvoiceLine {
+        VendorInvoiceLine {
+            id: Uuid::new_v4(),
+            invoice_id: Uuid::nil(),
+            line_number,
+            description: format!("Line {}", line_number),
+            quantity: Decimal::ONE,
+            unit_price: Decimal::from_str(amount).unwrap(),
+            line_amount: Decimal::from_str(amount).unwrap(),
+            account_id,
+            item_category: Some("SERVICES".to_string()),
+            department: None,
+            project_code: None,
+            po_line_id: None,
+        }
+    }
+
+    fn rupiah() -> BookingRate {
+        BookingRate { currency: Some("IDR".to_string()), rate: Decimal::ONE, functional_currency: "IDR".to_string() }
+    }
+
+    #[test]
+    fn test_invoice_posting_lines() {
+        let none = HashMap::new();
+        let account = Some(Uuid::new_v4());
+        let lines = vec![line(1, "1000000", account), line(2, "500000", account)];
+
+        let posting = invoice_posting_lines(&lines, &none, Decimal::from(1_500_000), Decimal::from(165_000), &rupiah(), "INV-1");
+        assert_eq!(posting.len(), 4);
+        assert_eq!(posting[0].account_role, None);
+        assert_eq!(posting[2].account_role, Some(LedgerAccountRole::InputVat));
+        assert_eq!(posting[3].account_role, Some(LedgerAccountRole::ApControl));
+        assert_eq!(posting[3].credit_amount, Decimal::from(1_665_000));
+        assert_eq!(posting[3].foreign, None);
+
+        let debits: Decimal = posting.iter().map(|l| l.debit_amount).sum();
+        let credits: Decimal = posting.iter().map(|l| l.credit_amount).sum();
+        assert_eq!(debits, credits);
+
+        // Foreign-currency invoice: the payable carries the dollar amount and
+        // the expense lines absorb the rounding of the converted total
+        let usd = BookingRate {
+            currency: Some("USD".to_string()),
+            rate: Decimal::from_str("15500.5").unwrap(),
+            functional_currency: "IDR".to_string(),
+        };
+        let lines = vec![line(1, "10.55", account), line(2, "10.55", account)];
+        let posting = invoice_posting_lines(&lines, &none, Decimal::from_str("21.10").unwrap(), Decimal::ZERO, &usd, "INV-2");
+        assert_eq!(posting.len(), 3);
+        assert_eq!(posting[2].credit_amount, Decimal::from(327_061));
+        assert_eq!(posting[2].foreign, Some(ForeignAmount {
+            currency: "USD".to_string(),
+            foreign_amount: Decimal::from_str("21.10").unwrap(),
+            exchange_rate: usd.rate,
+        }));
+        assert_eq!(posting[0].debit_amount + posting[1].debit_amount, Decimal::from(327_061));
+
+        // Lines without an account leave it to the posting rules
+        let posting = invoice_posting_lines(&[line(1, "100", None)], &none, Decimal::from(100), Decimal::ZERO, &rupiah(), "INV-3");
+        assert_eq!(posting[0].account_role, Some(LedgerAccountRole::Expense));
+        assert_eq!(posting[0].item_category.as_deref(), Some("SERVICES"));
+
+        // An invoice without lines books its subtotal as one expense line
+        let posting = invoice_posting_lines(&[], &none, Decimal::from(250_000), Decimal::from(27_500), &rupiah(), "INV-4");
+        assert_eq!(posting.len(), 3);
+        assert_eq!(posting[0].account_role, Some(LedgerAccountRole::Expense));
+        assert_eq!(posting[0].debit_amount, Decimal::from(250_000));
+        assert_eq!(posting[2].credit_amount, Decimal::from(277_500));
+
+        let checks = budget_check_lines(&[], &none, Decimal::from(250_000), &rupiah());
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].amount, Decimal::from(250_000));
+
+        // Stock ordered on a purchase order clears goods received not
+        // invoiced, whatever account the line names, and is not budgeted
+        let po_line_id = Uuid::new_v4();
+        let stock_line = VendorInvoiceLine { po_line_id: Some(po_line_id), ..line(1, "400000", account) };
+        let lines = vec![stock_line, line(2, "100000", None)];
+        let stock = HashMap::from([(po_line_id, Some(Decimal::from(400_000)))]);
+        let posting = invoice_posting_lines(&lines, &stock, Decimal::from(500_000), Decimal::ZERO, &rupiah(), "INV-5");
+        assert_eq!(posting[0].account_id, None);
+        assert_eq!(posting[0].account_role, Some(LedgerAccountRole::GoodsReceivedNotInvoiced));
+        assert_eq!(posting[1].account_role, Some(LedgerAccountRole::Expense));
+
+        let checks = budget_check_lines(&lines, &stock, Decimal::from(500_000), &rupiah());
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].amount, Decimal::from(100_000));
+
+        // Goods received at a lower cost than invoiced clear GRNI at the
+        // receipt's value and book the rest as price variance
+        let stock = HashMap::from([(po_line_id, Some(Decimal::from(390_000)))]);
+        let posting = invoice_posting_lines(&lines, &stock, Decimal::from(500_000), Decimal::ZERO, &rupiah(), "INV-6");
+        assert_eq!(posting[0].account_role, Some(LedgerAccountRole::PurchasePriceVariance));
+        assert_eq!(posting[0].debit_amount, Decimal::from(10_000));
+        assert_eq!(posting[1].account_role, Some(LedgerAccountRole::GoodsReceivedNotInvoiced));
+        assert_eq!(posting[1].debit_amount, Decimal::from(390_000));
+
+        // A higher receipt cost credits the variance
+        let stock = HashMap::from([(po_line_id, Some(Decimal::from(410_000)))]);
+        let posting = invoice_posting_lines(&lines, &stock, Decimal::from(500_000), Decimal::ZERO, &rupiah(), "INV-7");
+        assert_eq!(posting[0].credit_amount, Decimal::from(10_000));
+        assert_eq!(posting[1].debit_amount, Decimal::from(410_000));
+        let debits: Decimal = posting.iter().map(|l| l.debit_amount).sum();
+        let credits: Decimal = posting.iter().map(|l| l.credit_amount).sum();
+        assert_eq!(debits, credits);
+
+        let checks = budget_check_lines(&lines, &stock, Decimal::from(500_000), &rupiah());
+        assert_eq!(checks.len(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::models::*;
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, identity::{self, IDENTITY_HEADER}};
+use database::{budgets::BudgetWarning, exchange_rates::{RateTable, RateType}};
+use uuid::Uuid;
+
+/// Posts AP documents to the general ledger service over HTTP, on behalf
+/// of the user acting on the document, and reads exchange rates from it.
+#[derive(Clone)]
+pub struct LedgerClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(serde::Deserialize)]
+struct JournalEntryResponse {
+    journal_entry: PostedJournalEntry,
+}
+
+impl LedgerClient {
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("GENERAL_LEDGER_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3004".to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Posts the entry for a document. The ledger returns the existing entry
+    /// when the document was already posted, so a failed caller can retry.
+    pub async fn post_document(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+        posting: &LedgerPostingRequest,
+    ) -> ServiceResult<PostedJournalEntry> {
+        let request = self.client
+            .post(format!("{}/subledger-postings", self.base_url))
+            .json(posting);
+
+        self.send::<JournalEntryResponse>(request, company_id, user_id, roles)
+            .await
+            .map(|body| body.journal_entry)
+    }
+
+    /// Posts the reversal of a ledger entry.
+    pub async fn reverse_entry(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+        entry_id: Uuid,
+        reversal_date: NaiveDate,
+        reason: &str,
+    ) -> ServiceResult<PostedJournalEntry> {
+        let request = self.client
+            .post(format!("{}/journal-entries/{}/reverse", self.base_url, entry_id))
+            .json(&serde_json::json!({
+                "reversal_date": reversal_date,
+                "reason": reason,
+            }));
+
+        self.send::<JournalEntryResponse>(request, company_id, user_id, roles)
+            .await
+            .map(|body| body.journal_entry)
+    }
+
+    /// A ledger entry, to find the reversal of an entry that was already
+    /// reversed.
+    pub async fn get_entry(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> ServiceResult<PostedJournalEntry> {
+        let request = self.client
+            .get(format!("{}/journal-entries/{}", self.base_url, entry_id));
+
+        self.send::<JournalEntryResponse>(request, company_id, user_id, &[])
+            .await
+            .map(|body| body.journal_entry)
+    }
+
+    /// Budgets of the ledger the spending would exceed. Empty unless the
+    /// company has budget warnings enabled.
+    pub async fn check_budget(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        check: &LedgerBudgetCheckRequest,
+    ) -> ServiceResult<Vec<BudgetWarning>> {
+        let request = self.client
+            .post(format!("{}/budgets/check", self.base_url))
+            .json(check);
+
+        self.send(request, company_id, user_id, &[]).await
+    }
+
+    /// The ledger's functional currency and its rates on `date` for the
+    /// given currencies.
+    pub async fn rate_table(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        currencies: &[String],
+        rate_type: RateType,
+        date: NaiveDate,
+    ) -> ServiceResult<RateTable> {
+        let request = self.client
+            .get(format!("{}/exchange-rates/table", self.base_url))
+            .query(&[
+                ("currencies", currencies.join(",")),
+                ("rate_type", rate_type.to_string()),
+                ("date", date.to_string()),
+            ]);
+
+        self.send(request, company_id, user_id, &[]).await
+    }
+
+    /// The ledger's BI middle rate for a document in `currency` on `date`;
+    /// documents without a currency are in the functional one.
+    pub async fn booking_rate(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        currency: Option<&str>,
+        date: NaiveDate,
+    ) -> ServiceResult<BookingRate> {
+        let currencies: Vec<String> = currency.iter().map(|c| c.to_string()).collect();
+        let rates = self.rate_table(company_id, user_id, &currencies, RateType::BiMiddle, date).await?;
+
+        BookingRate::from_table(&rates, currency).ok_or_else(|| ServiceError::Validation(format!(
+            "No BI_MIDDLE exchange rate for {} on or before {}",
+            currency.unwrap_or_default(),
+            date
+        )))
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<T> {
+        let response = request
+            .header("X-Company-ID", company_id.to_string())
+            .header("X-User-ID", user_id.to_string())
+            .header(IDENTITY_HEADER, identity::forward(user_id, company_id, roles)?)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to call general ledger: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            // Pass the ledger's own message through, e.g. a closed period
+            let message = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body.get("message").and_then(|m| m.as_str()).map(str::to_string))
+                .unwrap_or_else(|| format!("General ledger returned status {}", status));
+
+            return Err(match status.as_u16() {
+                400 => ServiceError::Validation(message),
+                403 => ServiceError::Authorization(message),
+                404 => ServiceError::NotFound(message),
+                409 => ServiceError::Conflict(message),
+                _ => ServiceError::ExternalService(message),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to parse general ledger response: {}", e)))
+    }
+}
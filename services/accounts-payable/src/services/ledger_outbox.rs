@@ -0,0 +1,600 @@
+use crate::models::*;
+use super::{
+    advance_service::{ADVANCE_REFUND_SOURCE, ADVANCE_SOURCE, APPLICATION_SOURCE},
+    credit_memo_service::{CREDIT_MEMO_SOURCE, REFUND_SOURCE}, invoice_service::INVOICE_SOURCE,
+    payment_service::PAYMENT_SOURCE, purchase_order_service::GOODS_RECEIPT_SOURCE, LedgerClient,
+};
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Queued postings sent per dispatch run.
+const DISPATCH_BATCH_SIZE: i64 = 100;
+
+/// Seconds a dispatcher holds a posting before another one may pick it up.
+const CLAIM_SECONDS: f64 = 120.0;
+
+const POST: &str = "POST";
+const REVERSE: &str = "REVERSE";
+
+/// Ledger postings of AP documents. A posting is queued in the transaction
+/// that changes the document and sent to the general ledger once that
+/// transaction has committed, so a slow or unavailable ledger never holds
+/// document locks and a rolled-back change is never booked. The ledger
+/// books each source document once, which makes sending a posting again
+/// after a lost response safe.
+#[derive(Clone)]
+pub struct LedgerOutbox {
+    db: PgPool,
+    ledger_client: LedgerClient,
+}
+
+struct OutboxRow {
+    id: Uuid,
+    company_id: Uuid,
+    source_document_type: String,
+    source_document_id: Uuid,
+    action: String,
+    posting: Option<serde_json::Value>,
+    reverse_entry_id: Option<Uuid>,
+    reversal_date: Option<NaiveDate>,
+    reason: Option<String>,
+    requested_by: Uuid,
+    requested_roles: Vec<String>,
+}
+
+/// What sending a queued posting came to.
+enum Outcome {
+    Posted(Uuid),
+    /// The entry to reverse is still waiting to be booked
+    Waiting,
+    /// The original posting never reached the ledger; nothing to reverse
+    NothingToReverse,
+}
+
+impl LedgerOutbox {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            ledger_client: LedgerClient::from_env(),
+            db,
+        }
+    }
+
+    /// Queues the entry for a document, to be posted as `user_id` with the
+    /// roles they act with now.
+    pub async fn enqueue_posting(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        company_id: Uuid,
+        posting: &LedgerPostingRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<Uuid> {
+        let payload = serde_json::to_value(posting)
+            .map_err(|e| ServiceError::Internal(format!("Failed to queue ledger posting: {}", e)))?;
+
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO ledger_outbox (
+                id, company_id, source_document_type, source_document_id, action, posting,
+                requested_by, requested_roles, status, created_at
+            )
+            VALUES ($1, $2, $3, $4, 'POST', $5, $6, $7, 'PENDING', NOW())
+            ON CONFLICT (company_id, source_document_type, source_document_id, action) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            posting.source_document_type,
+            posting.source_document_id,
+            payload,
+            user_id,
+            roles
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict(format!(
+            "{} {} has already been sent to the ledger",
+            posting.source_document_type, posting.source_document_id
+        )))
+    }
+
+    /// Queues the reversal of a document's entry. `entry_id` is the entry
+    /// the document was booked with before postings were queued; otherwise
+    /// the entry of its queued posting is reversed once that is booked.
+    /// Returns `None` when the posting never reached the ledger: it is
+    /// cancelled and there is nothing to reverse.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enqueue_reversal(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        company_id: Uuid,
+        source_document_type: &str,
+        source_document_id: Uuid,
+        entry_id: Option<Uuid>,
+        reversal_date: NaiveDate,
+        reason: &str,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<Option<Uuid>> {
+        let original = sqlx::query!(
+            r#"
+            SELECT id, status, journal_entry_id
+            FROM ledger_outbox
+            WHERE company_id = $1 AND source_document_type = $2 AND source_document_id = $3 AND action = 'POST'
+            FOR UPDATE
+            "#,
+            company_id,
+            source_document_type,
+            source_document_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let reverse_entry_id = match original {
+            // Rejected postings were never booked
+            Some(original) if original.status == "FAILED" || original.status == "CANCELLED" => {
+                sqlx::query!("UPDATE ledger_outbox SET status = 'CANCELLED' WHERE id = $1", original.id)
+                    .execute(&mut **tx)
+                    .await
+                    .map_err(ServiceError::Database)?;
+                return Ok(None);
+            }
+            // A pending posting may already be booked; reverse whatever it gives
+            Some(original) => original.journal_entry_id,
+            None if entry_id.is_none() => return Ok(None),
+            None => entry_id,
+        };
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO ledger_outbox (
+                id, company_id, source_document_type, source_document_id, action,
+                reverse_entry_id, reversal_date, reason, requested_by, requested_roles, status, created_at
+            )
+            VALUES ($1, $2, $3, $4, 'REVERSE', $5, $6, $7, $8, $9, 'PENDING', NOW())
+            ON CONFLICT (company_id, source_document_type, source_document_id, action) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            source_document_type,
+            source_document_id,
+            reverse_entry_id,
+            reversal_date,
+            reason,
+            user_id,
+            roles
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict(format!(
+            "{} {} has already been reversed",
+            source_document_type, source_document_id
+        )))?;
+
+        Ok(Some(id))
+    }
+
+    /// Sends a queued posting to the ledger and records the entry on its
+    /// document. A rejected posting is marked failed on the document and
+    /// left for a retry; an unreachable ledger leaves it queued. Only local
+    /// database errors are returned.
+    pub async fn dispatch(&self, id: Uuid) -> ServiceResult<()> {
+        let claimed = sqlx::query_as!(
+            OutboxRow,
+            r#"
+            UPDATE ledger_outbox
+            SET locked_until = NOW() + make_interval(secs => $2), attempts = attempts + 1
+            WHERE id = $1 AND status = 'PENDING' AND (locked_until IS NULL OR locked_until < NOW())
+            RETURNING id, company_id, source_document_type, source_document_id, action, posting,
+                      reverse_entry_id, reversal_date, reason, requested_by, requested_roles
+            "#,
+            id,
+            CLAIM_SECONDS
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Done, or another dispatcher has it
+        let Some(row) = claimed else {
+            return Ok(());
+        };
+
+        let outcome = match row.action.as_str() {
+            POST => self.post(&row).await,
+            _ => self.reverse(&row).await,
+        };
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        match outcome {
+            Ok(Outcome::Posted(entry_id)) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE ledger_outbox
+                    SET status = 'POSTED', journal_entry_id = $2, last_error = NULL,
+                        locked_until = NULL, posted_at = NOW()
+                    WHERE id = $1
+                    "#,
+                    row.id,
+                    entry_id
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(ServiceError::Database)?;
+
+                record_on_document(&mut tx, &row, Some(entry_id), PostingStatus::Posted).await?;
+            }
+            Ok(Outcome::Waiting) => {
+                sqlx::query!("UPDATE ledger_outbox SET locked_until = NULL WHERE id = $1", row.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(ServiceError::Database)?;
+            }
+            Ok(Outcome::NothingToReverse) => {
+                // The original is left failed or cancelled; retrying it
+                // would book a document that has since been reversed
+                sqlx::query!(
+                    r#"
+                    UPDATE ledger_outbox SET status = 'CANCELLED', locked_until = NULL
+                    WHERE company_id = $1 AND source_document_type = $2 AND source_document_id = $3
+                    "#,
+                    row.company_id,
+                    row.source_document_type,
+                    row.source_document_id
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(ServiceError::Database)?;
+
+                record_on_document(&mut tx, &row, None, PostingStatus::NotPosted).await?;
+            }
+            // The ledger refused the posting; sending it again won't help
+            Err(e @ (ServiceError::Validation(_)
+                | ServiceError::Authorization(_)
+                | ServiceError::NotFound(_)
+                | ServiceError::Conflict(_))) => {
+                tracing::warn!(
+                    "Ledger rejected {} of {} {}: {}",
+                    row.action, row.source_document_type, row.source_document_id, e
+                );
+
+                sqlx::query!(
+                    "UPDATE ledger_outbox SET status = 'FAILED', last_error = $2, locked_until = NULL WHERE id = $1",
+                    row.id,
+                    e.to_string()
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(ServiceError::Database)?;
+
+                record_on_document(&mut tx, &row, None, PostingStatus::PostingFailed).await?;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not send {} of {} {} to the ledger, will retry: {}",
+                    row.action, row.source_document_type, row.source_document_id, e
+                );
+
+                sqlx::query!(
+                    "UPDATE ledger_outbox SET last_error = $2, locked_until = NULL WHERE id = $1",
+                    row.id,
+                    e.to_string()
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(ServiceError::Database)?;
+            }
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    /// Sends queued postings, oldest first, for the scheduler. Returns how
+    /// many were attempted.
+    pub async fn dispatch_pending(&self) -> ServiceResult<usize> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM ledger_outbox
+            WHERE status = 'PENDING' AND (locked_until IS NULL OR locked_until < NOW())
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+            DISPATCH_BATCH_SIZE
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for id in &ids {
+            self.dispatch(*id).await?;
+        }
+
+        Ok(ids.len())
+    }
+
+    pub async fn get_entries(
+        &self,
+        company_id: Uuid,
+        status: Option<String>,
+        source_document_id: Option<Uuid>,
+    ) -> ServiceResult<Vec<LedgerOutboxEntry>> {
+        sqlx::query_as!(
+            LedgerOutboxEntry,
+            r#"
+            SELECT id, company_id, source_document_type, source_document_id, action, status,
+                   attempts, last_error, journal_entry_id, requested_by, created_at, posted_at
+            FROM ledger_outbox
+            WHERE company_id = $1
+              AND ($2::text IS NULL OR status = $2)
+              AND ($3::uuid IS NULL OR source_document_id = $3)
+            ORDER BY created_at DESC
+            LIMIT 500
+            "#,
+            company_id,
+            status.map(|s| s.to_uppercase()),
+            source_document_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// Sends a rejected posting again, as the user retrying it, e.g. once
+    /// the missing posting rule has been set up.
+    pub async fn retry(
+        &self,
+        id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<LedgerOutboxEntry> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let row = sqlx::query_as!(
+            OutboxRow,
+            r#"
+            UPDATE ledger_outbox
+            SET status = 'PENDING', requested_by = $3, requested_roles = $4, last_error = NULL
+            WHERE id = $1 AND company_id = $2 AND status = 'FAILED'
+            RETURNING id, company_id, source_document_type, source_document_id, action, posting,
+                      reverse_entry_id, reversal_date, reason, requested_by, requested_roles
+            "#,
+            id,
+            company_id,
+            user_id,
+            roles
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("No failed ledger posting with this id".to_string()))?;
+
+        record_on_document(&mut tx, &row, None, PostingStatus::PendingPosting).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.dispatch(id).await?;
+
+        self.get_entries(company_id, None, Some(row.source_document_id))
+            .await?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| ServiceError::NotFound("Ledger posting not found".to_string()))
+    }
+
+    async fn post(&self, row: &OutboxRow) -> ServiceResult<Outcome> {
+        let posting: LedgerPostingRequest = row.posting
+            .clone()
+            .ok_or_else(|| ServiceError::Internal("Queued posting has no entry".to_string()))
+            .and_then(|p| serde_json::from_value(p)
+                .map_err(|e| ServiceError::Internal(format!("Invalid queued posting: {}", e))))?;
+
+        let entry = self.ledger_client
+            .post_document(row.company_id, row.requested_by, &row.requested_roles, &posting)
+            .await?;
+
+        tracing::info!("{} {} booked as {}", row.source_document_type, row.source_document_id, entry.entry_number);
+
+        Ok(Outcome::Posted(entry.id))
+    }
+
+    async fn reverse(&self, row: &OutboxRow) -> ServiceResult<Outcome> {
+        let entry_id = match row.reverse_entry_id {
+            Some(entry_id) => entry_id,
+            None => {
+                let original = sqlx::query!(
+                    r#"
+                    SELECT status, journal_entry_id FROM ledger_outbox
+                    WHERE company_id = $1 AND source_document_type = $2 AND source_document_id = $3
+                      AND action = 'POST'
+                    "#,
+                    row.company_id,
+                    row.source_document_type,
+                    row.source_document_id
+                )
+                .fetch_optional(&self.db)
+                .await
+                .map_err(ServiceError::Database)?;
+
+                match original {
+                    Some(original) if original.status == "POSTED" => original.journal_entry_id.ok_or_else(|| {
+                        ServiceError::Internal("Posted ledger posting has no entry".to_string())
+                    })?,
+                    Some(original) if original.status == "PENDING" => return Ok(Outcome::Waiting),
+                    _ => return Ok(Outcome::NothingToReverse),
+                }
+            }
+        };
+
+        let reversal_date = row.reversal_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+        let reason = row.reason.clone().unwrap_or_default();
+
+        match self.ledger_client
+            .reverse_entry(row.company_id, row.requested_by, &row.requested_roles, entry_id, reversal_date, &reason)
+            .await
+        {
+            Ok(reversal) => {
+                tracing::info!(
+                    "{} {} reversed with {}",
+                    row.source_document_type, row.source_document_id, reversal.entry_number
+                );
+                Ok(Outcome::Posted(reversal.id))
+            }
+            // Reversed by an earlier attempt whose response was lost
+            Err(ServiceError::Conflict(message)) => {
+                let original = self.ledger_client.get_entry(row.company_id, row.requested_by, entry_id).await?;
+                original.reversed_by_entry_id
+                    .map(Outcome::Posted)
+                    .ok_or(ServiceError::Conflict(message))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Records where the ledger stands on the document a posting is for.
+async fn record_on_document(
+    tx: &mut Transaction<'_, Postgres>,
+    row: &OutboxRow,
+    entry_id: Option<Uuid>,
+    status: PostingStatus,
+) -> ServiceResult<()> {
+    let status = status.to_string();
+
+    let result = match (row.source_document_type.as_str(), row.action.as_str()) {
+        (INVOICE_SOURCE, POST) => sqlx::query!(
+            r#"
+            UPDATE vendor_invoices
+            SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (INVOICE_SOURCE, REVERSE) => sqlx::query!(
+            "UPDATE vendor_invoices SET posting_status = $1, updated_at = NOW() WHERE id = $2",
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (PAYMENT_SOURCE, POST) => sqlx::query!(
+            "UPDATE vendor_payments SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2 WHERE id = $3",
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (PAYMENT_SOURCE, REVERSE) => sqlx::query!(
+            r#"
+            UPDATE vendor_payments
+            SET reversal_journal_entry_id = COALESCE($1, reversal_journal_entry_id), posting_status = $2
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (GOODS_RECEIPT_SOURCE, POST) => sqlx::query!(
+            "UPDATE goods_receipts SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2 WHERE id = $3",
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (CREDIT_MEMO_SOURCE, POST) => sqlx::query!(
+            r#"
+            UPDATE vendor_credit_memos
+            SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (CREDIT_MEMO_SOURCE, REVERSE) => sqlx::query!(
+            r#"
+            UPDATE vendor_credit_memos
+            SET reversal_journal_entry_id = COALESCE($1, reversal_journal_entry_id), posting_status = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (REFUND_SOURCE, POST) => sqlx::query!(
+            "UPDATE vendor_credit_refunds SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2 WHERE id = $3",
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (ADVANCE_SOURCE, POST) => sqlx::query!(
+            r#"
+            UPDATE vendor_advances
+            SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (ADVANCE_SOURCE, REVERSE) => sqlx::query!(
+            r#"
+            UPDATE vendor_advances
+            SET reversal_journal_entry_id = COALESCE($1, reversal_journal_entry_id), posting_status = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (APPLICATION_SOURCE, POST) => sqlx::query!(
+            "UPDATE vendor_advance_applications SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2 WHERE id = $3",
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (ADVANCE_REFUND_SOURCE, POST) => sqlx::query!(
+            "UPDATE vendor_advance_refunds SET journal_entry_id = COALESCE($1, journal_entry_id), posting_status = $2 WHERE id = $3",
+            entry_id,
+            status,
+            row.source_document_id
+        )
+        .execute(&mut **tx)
+        .await,
+        (source, action) => {
+            tracing::warn!("No document to record {} of {} {} on", action, source, row.source_document_id);
+            return Ok(());
+        }
+    };
+
+    result.map(|_| ()).map_err(ServiceError::Database)
+}
@@ -0,0 +1,557 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Three-way matching of vendor invoice lines against the purchase order
+/// lines they bill and the goods received on them.
+pub struct MatchingService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+}
+
+struct MatchHeaderRow {
+    id: Uuid,
+    invoice_number: String,
+    vendor_id: Uuid,
+    vendor_name: String,
+    invoice_date: chrono::NaiveDate,
+    total_amount: Decimal,
+    status_str: Option<String>,
+    match_status: String,
+    matched_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl MatchingService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self { db, audit_logger }
+    }
+
+    pub async fn get_settings(&self, company_id: Uuid) -> ServiceResult<MatchingSettings> {
+        let settings = sqlx::query_as!(
+            MatchingSettings,
+            r#"
+            SELECT company_id, price_tolerance_percent, quantity_tolerance_percent,
+                   updated_by, updated_at
+            FROM ap_matching_settings
+            WHERE company_id = $1
+            "#,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(settings.unwrap_or(MatchingSettings {
+            company_id,
+            price_tolerance_percent: Decimal::ZERO,
+            quantity_tolerance_percent: Decimal::ZERO,
+            updated_by: None,
+            updated_at: None,
+        }))
+    }
+
+    pub async fn update_settings(
+        &self,
+        company_id: Uuid,
+        request: UpdateMatchingSettingsRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<MatchingSettings> {
+        let settings = sqlx::query_as!(
+            MatchingSettings,
+            r#"
+            INSERT INTO ap_matching_settings
+            (company_id, price_tolerance_percent, quantity_tolerance_percent, updated_by, updated_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (company_id) DO UPDATE
+            SET price_tolerance_percent = EXCLUDED.price_tolerance_percent,
+                quantity_tolerance_percent = EXCLUDED.quantity_tolerance_percent,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING company_id, price_tolerance_percent, quantity_tolerance_percent,
+                      updated_by, updated_at
+            "#,
+            company_id,
+            request.price_tolerance_percent,
+            request.quantity_tolerance_percent,
+            user_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tracing::info!("Matching tolerances updated for company {} by user {}", company_id, user_id);
+
+        Ok(settings)
+    }
+
+    /// Matches every line of the invoice that bills a purchase order line,
+    /// replacing the previous results, and fills in the invoice's PO number
+    /// and received date from what it was matched against. Returns the
+    /// explanations of the lines outside tolerance.
+    pub async fn match_invoice(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        invoice_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<Vec<String>> {
+        let settings = self.get_settings(company_id).await?;
+
+        // Locking the PO lines keeps two invoices from both billing the
+        // same received quantity
+        let lines = sqlx::query!(
+            r#"
+            SELECT vil.id, vil.line_number, vil.quantity as "quantity!", vil.unit_price,
+                   pol.id as po_line_id, po.po_number, pol.quantity as ordered_quantity,
+                   pol.received_quantity, pol.unit_price as po_unit_price,
+                   COALESCE((
+                       SELECT SUM(ol.quantity)
+                       FROM vendor_invoice_lines ol
+                       JOIN vendor_invoices oi ON oi.id = ol.invoice_id
+                       WHERE ol.po_line_id = pol.id
+                         AND oi.id <> vil.invoice_id
+                         AND oi.status <> 'CANCELLED'
+                   ), 0) as "invoiced_elsewhere!"
+            FROM vendor_invoice_lines vil
+            JOIN purchase_order_lines pol ON pol.id = vil.po_line_id
+            JOIN purchase_orders po ON po.id = pol.purchase_order_id
+            WHERE vil.invoice_id = $1 AND po.company_id = $2
+            ORDER BY vil.line_number
+            FOR UPDATE OF pol
+            "#,
+            invoice_id,
+            company_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!("DELETE FROM invoice_line_matches WHERE invoice_id = $1", invoice_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        let mut billed_here: HashMap<Uuid, Decimal> = HashMap::new();
+        let mut exceptions = Vec::new();
+
+        for line in &lines {
+            let earlier = billed_here.entry(line.po_line_id).or_insert(Decimal::ZERO);
+            let previously_invoiced = line.invoiced_elsewhere + *earlier;
+            *earlier += line.quantity;
+
+            let outcome = match_line(
+                line.quantity,
+                line.unit_price,
+                line.po_unit_price,
+                line.received_quantity,
+                previously_invoiced,
+                settings.price_tolerance_percent,
+                settings.quantity_tolerance_percent,
+            );
+
+            if let Some(explanation) = &outcome.explanation {
+                exceptions.push(format!("Line {} (PO {}): {}", line.line_number, line.po_number, explanation));
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO invoice_line_matches (
+                    id, invoice_id, invoice_line_id, po_line_id, ordered_quantity, received_quantity,
+                    previously_invoiced_quantity, invoice_quantity, po_unit_price, invoice_unit_price,
+                    price_variance, price_variance_percent, quantity_variance, status, explanation, matched_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, NOW())
+                "#,
+                Uuid::new_v4(),
+                invoice_id,
+                line.id,
+                line.po_line_id,
+                line.ordered_quantity,
+                line.received_quantity,
+                previously_invoiced,
+                line.quantity,
+                line.po_unit_price,
+                line.unit_price,
+                outcome.price_variance,
+                outcome.price_variance_percent,
+                outcome.quantity_variance,
+                outcome.status.to_string(),
+                outcome.explanation
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        let match_status = if lines.is_empty() {
+            MatchStatus::NotRequired
+        } else if exceptions.is_empty() {
+            MatchStatus::Matched
+        } else {
+            MatchStatus::Exception
+        };
+
+        let mut po_numbers: Vec<&str> = lines.iter().map(|l| l.po_number.as_str()).collect();
+        po_numbers.sort();
+        po_numbers.dedup();
+        let po_number = (po_numbers.len() == 1).then(|| po_numbers[0].to_string());
+
+        let po_line_ids: Vec<Uuid> = lines.iter().map(|l| l.po_line_id).collect();
+        let received_date = sqlx::query_scalar!(
+            r#"
+            SELECT MAX(gr.receipt_date)
+            FROM goods_receipts gr
+            JOIN goods_receipt_lines grl ON grl.receipt_id = gr.id
+            WHERE grl.po_line_id = ANY($1)
+            "#,
+            &po_line_ids
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE vendor_invoices
+            SET match_status = $1,
+                matched_at = NOW(),
+                purchase_order_number = COALESCE($2, purchase_order_number),
+                received_date = COALESCE($3, received_date),
+                updated_at = NOW()
+            WHERE id = $4
+            "#,
+            match_status.to_string(),
+            po_number,
+            received_date,
+            invoice_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(exceptions)
+    }
+
+    /// Matches a draft or pending invoice again, e.g. after more goods were
+    /// received or the tolerances changed.
+    pub async fn rematch_invoice(
+        &self,
+        invoice_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<InvoiceMatch> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let invoice = sqlx::query!(
+            r#"
+            SELECT invoice_number, status as "status_str", match_status
+            FROM vendor_invoices
+            WHERE id = $1 AND company_id = $2
+            FOR UPDATE
+            "#,
+            invoice_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+        let status = invoice.status_str.as_deref()
+            .and_then(|s| s.parse::<InvoiceStatus>().ok())
+            .unwrap_or(InvoiceStatus::Draft);
+        if !matches!(status, InvoiceStatus::Draft | InvoiceStatus::Pending) {
+            return Err(ServiceError::Validation(format!(
+                "Invoice {} is {} and can no longer be matched", invoice.invoice_number, status
+            )));
+        }
+
+        let exceptions = self.match_invoice(&mut tx, invoice_id, company_id).await?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "vendor_invoices",
+            invoice_id,
+            "MATCH",
+            Some(serde_json::json!({ "match_status": invoice.match_status })),
+            Some(serde_json::json!({ "exceptions": exceptions })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Invoice {} matched with {} exception(s) by user {}",
+            invoice.invoice_number, exceptions.len(), user_id
+        );
+
+        self.get_invoice_match(invoice_id, company_id).await
+    }
+
+    pub async fn get_invoice_match(&self, invoice_id: Uuid, company_id: Uuid) -> ServiceResult<InvoiceMatch> {
+        let header = sqlx::query_as!(
+            MatchHeaderRow,
+            r#"
+            SELECT vi.id, vi.invoice_number, vi.vendor_id, v.vendor_name, vi.invoice_date,
+                   vi.total_amount, vi.status as "status_str", vi.match_status, vi.matched_at
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.id = $1 AND vi.company_id = $2
+            "#,
+            invoice_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+
+        let mut lines = self.get_line_matches(&[invoice_id]).await?;
+        to_invoice_match(header, &mut lines)
+    }
+
+    /// Draft and pending invoices outside tolerance, oldest first. These
+    /// cannot be approved until they are matched again successfully.
+    pub async fn get_exceptions(
+        &self,
+        company_id: Uuid,
+        vendor_id: Option<Uuid>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<InvoiceMatch>> {
+        let headers = sqlx::query_as!(
+            MatchHeaderRow,
+            r#"
+            SELECT vi.id, vi.invoice_number, vi.vendor_id, v.vendor_name, vi.invoice_date,
+                   vi.total_amount, vi.status as "status_str", vi.match_status, vi.matched_at
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.company_id = $1
+              AND vi.match_status = 'EXCEPTION'
+              AND vi.status IN ('DRAFT', 'PENDING')
+              AND ($2::uuid IS NULL OR vi.vendor_id = $2)
+            ORDER BY vi.invoice_date, vi.invoice_number
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            vendor_id,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let invoice_ids: Vec<Uuid> = headers.iter().map(|h| h.id).collect();
+        let mut lines = self.get_line_matches(&invoice_ids).await?;
+
+        headers
+            .into_iter()
+            .map(|header| to_invoice_match(header, &mut lines))
+            .collect()
+    }
+
+    async fn get_line_matches(&self, invoice_ids: &[Uuid]) -> ServiceResult<HashMap<Uuid, Vec<InvoiceLineMatch>>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT m.invoice_id, m.invoice_line_id, vil.line_number, m.po_line_id, po.po_number,
+                   m.ordered_quantity, m.received_quantity, m.previously_invoiced_quantity,
+                   m.invoice_quantity, m.po_unit_price, m.invoice_unit_price, m.price_variance,
+                   m.price_variance_percent, m.quantity_variance, m.status, m.explanation
+            FROM invoice_line_matches m
+            JOIN vendor_invoice_lines vil ON vil.id = m.invoice_line_id
+            JOIN purchase_order_lines pol ON pol.id = m.po_line_id
+            JOIN purchase_orders po ON po.id = pol.purchase_order_id
+            WHERE m.invoice_id = ANY($1)
+            ORDER BY m.invoice_id, vil.line_number
+            "#,
+            invoice_ids
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut lines: HashMap<Uuid, Vec<InvoiceLineMatch>> = HashMap::new();
+        for row in rows {
+            lines.entry(row.invoice_id).or_default().push(InvoiceLineMatch {
+                invoice_line_id: row.invoice_line_id,
+                line_number: row.line_number,
+                po_line_id: row.po_line_id,
+                po_number: row.po_number,
+                ordered_quantity: row.ordered_quantity,
+                received_quantity: row.received_quantity,
+                previously_invoiced_quantity: row.previously_invoiced_quantity,
+                invoice_quantity: row.invoice_quantity,
+                po_unit_price: row.po_unit_price,
+                invoice_unit_price: row.invoice_unit_price,
+                price_variance: row.price_variance,
+                price_variance_percent: row.price_variance_percent,
+                quantity_variance: row.quantity_variance,
+                status: row.status.parse().map_err(ServiceError::Internal)?,
+                explanation: row.explanation,
+            });
+        }
+
+        Ok(lines)
+    }
+}
+
+fn to_invoice_match(
+    header: MatchHeaderRow,
+    lines: &mut HashMap<Uuid, Vec<InvoiceLineMatch>>,
+) -> ServiceResult<InvoiceMatch> {
+    Ok(InvoiceMatch {
+        invoice_id: header.id,
+        invoice_number: header.invoice_number,
+        vendor_id: header.vendor_id,
+        vendor_name: header.vendor_name,
+        invoice_date: header.invoice_date,
+        total_amount: header.total_amount,
+        invoice_status: header.status_str.unwrap_or_else(|| InvoiceStatus::Draft.to_string()),
+        match_status: header.match_status.parse().map_err(ServiceError::Internal)?,
+        matched_at: header.matched_at,
+        lines: lines.remove(&header.id).unwrap_or_default(),
+    })
+}
+
+struct LineMatchOutcome {
+    price_variance: Decimal,
+    price_variance_percent: Option<Decimal>,
+    quantity_variance: Decimal,
+    status: MatchStatus,
+    explanation: Option<String>,
+}
+
+/// Compares one invoice line with its PO line. The unit price may deviate
+/// from the PO price by the price tolerance either way; the quantity billed
+/// so far, this line included, may exceed the received quantity by the
+/// quantity tolerance.
+fn match_line(
+    invoice_quantity: Decimal,
+    invoice_unit_price: Decimal,
+    po_unit_price: Decimal,
+    received_quantity: Decimal,
+    previously_invoiced: Decimal,
+    price_tolerance_percent: Decimal,
+    quantity_tolerance_percent: Decimal,
+) -> LineMatchOutcome {
+    let hundred = Decimal::ONE_HUNDRED;
+    let mut problems = Vec::new();
+
+    let price_variance = invoice_unit_price - po_unit_price;
+    let price_variance_percent = (po_unit_price != Decimal::ZERO)
+        .then(|| (price_variance / po_unit_price * hundred).round_dp(2));
+    match price_variance_percent {
+        Some(percent) if percent.abs() > price_tolerance_percent => problems.push(format!(
+            "unit price {} is {}% {} the PO price {} (tolerance {}%)",
+            invoice_unit_price.normalize(),
+            percent.abs().normalize(),
+            if percent > Decimal::ZERO { "above" } else { "below" },
+            po_unit_price.normalize(),
+            price_tolerance_percent.normalize()
+        )),
+        None if price_variance != Decimal::ZERO => problems.push(format!(
+            "unit price {} billed on a PO line without a price",
+            invoice_unit_price.normalize()
+        )),
+        _ => {}
+    }
+
+    let billed = previously_invoiced + invoice_quantity;
+    let quantity_variance = (billed - received_quantity).max(Decimal::ZERO);
+    if quantity_variance > received_quantity * quantity_tolerance_percent / hundred {
+        let mut problem = if received_quantity == Decimal::ZERO {
+            format!("quantity {} billed but nothing has been received", billed.normalize())
+        } else {
+            format!(
+                "quantity billed {} exceeds the {} received",
+                billed.normalize(),
+                received_quantity.normalize()
+            )
+        };
+        if previously_invoiced > Decimal::ZERO {
+            problem.push_str(&format!(" ({} billed before)", previously_invoiced.normalize()));
+        }
+        if quantity_tolerance_percent > Decimal::ZERO && received_quantity > Decimal::ZERO {
+            problem.push_str(&format!(" beyond the {}% tolerance", quantity_tolerance_percent.normalize()));
+        }
+        problems.push(problem);
+    }
+
+    LineMatchOutcome {
+        price_variance,
+        price_variance_percent,
+        quantity_variance,
+        status: if problems.is_empty() { MatchStatus::Matched } else { MatchStatus::Exception },
+        explanation: (!problems.is_empty()).then(|| problems.join("; ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn d(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_match_line() {
+        // Exact match
+        let outcome = match_line(d("10"), d("10000"), d("10000"), d("10"), d("0"), d("0"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Matched);
+        assert_eq!(outcome.price_variance_percent, Some(d("0")));
+        assert!(outcome.explanation.is_none());
+
+        // Price within a 2% tolerance, either way
+        let outcome = match_line(d("10"), d("10150"), d("10000"), d("10"), d("0"), d("2"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Matched);
+        assert_eq!(outcome.price_variance, d("150"));
+        let outcome = match_line(d("10"), d("9850"), d("10000"), d("10"), d("0"), d("2"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Matched);
+
+        // Price outside tolerance
+        let outcome = match_line(d("10"), d("10500"), d("10000"), d("10"), d("0"), d("2"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Exception);
+        assert_eq!(outcome.price_variance_percent, Some(d("5")));
+        assert_eq!(
+            outcome.explanation.as_deref(),
+            Some("unit price 10500 is 5% above the PO price 10000 (tolerance 2%)")
+        );
+
+        // Billing more than received, counting earlier invoices
+        let outcome = match_line(d("4"), d("10000"), d("10000"), d("10"), d("8"), d("0"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Exception);
+        assert_eq!(outcome.quantity_variance, d("2"));
+        assert_eq!(
+            outcome.explanation.as_deref(),
+            Some("quantity billed 12 exceeds the 10 received (8 billed before)")
+        );
+
+        // ...unless within the quantity tolerance
+        let outcome = match_line(d("4"), d("10000"), d("10000"), d("10"), d("8"), d("0"), d("20"));
+        assert_eq!(outcome.status, MatchStatus::Matched);
+        let outcome = match_line(d("4"), d("10000"), d("10000"), d("10"), d("8"), d("0"), d("10"));
+        assert_eq!(
+            outcome.explanation.as_deref(),
+            Some("quantity billed 12 exceeds the 10 received (8 billed before) beyond the 10% tolerance")
+        );
+
+        // Nothing received yet, and a price variance on top
+        let outcome = match_line(d("5"), d("11000"), d("10000"), d("0"), d("0"), d("2"), d("50"));
+        assert_eq!(outcome.status, MatchStatus::Exception);
+        assert_eq!(
+            outcome.explanation.as_deref(),
+            Some("unit price 11000 is 10% above the PO price 10000 (tolerance 2%); \
+                  quantity 5 billed but nothing has been received")
+        );
+
+        // Billing a free PO line
+        let outcome = match_line(d("1"), d("100"), d("0"), d("1"), d("0"), d("5"), d("0"));
+        assert_eq!(outcome.status, MatchStatus::Exception);
+        assert_eq!(outcome.price_variance_percent, None);
+    }
+}
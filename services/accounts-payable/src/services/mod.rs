@@ -2,8 +2,29 @@ pub mod vendor_service;
 pub mod invoice_service;
 pub mod payment_service;
 pub mod aging_service;
+pub mod purchase_order_service;
+pub mod matching_service;
+pub mod payment_run_service;
+pub mod bank_files;
+pub mod withholding_service;
+pub mod credit_memo_service;
+pub mod advance_service;
+pub mod ledger_client;
+pub mod ledger_outbox;
+pub mod inventory_client;
+pub mod tax_client;
 
 pub use vendor_service::VendorService;
 pub use invoice_service::InvoiceService;
 pub use payment_service::PaymentService;
-pub use aging_service::AgingService;
\ No newline at end of file
+pub use aging_service::AgingService;
+pub use purchase_order_service::PurchaseOrderService;
+pub use matching_service::MatchingService;
+pub use payment_run_service::PaymentRunService;
+pub use withholding_service::WithholdingService;
+pub use credit_memo_service::CreditMemoService;
+pub use advance_service::AdvanceService;
+pub use ledger_client::LedgerClient;
+pub use ledger_outbox::LedgerOutbox;
+pub use inventory_client::InventoryClient;
+pub use tax_client::TaxClient;
\ No newline at end of file
@@ -0,0 +1,716 @@
+use crate::models::*;
+use super::{bank_files, withholding_service, PaymentService};
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Payment method recorded on the payments a run makes.
+const RUN_PAYMENT_METHOD: &str = "BANK_TRANSFER";
+
+pub struct PaymentRunService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+    payment_service: PaymentService,
+}
+
+impl PaymentRunService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self {
+            audit_logger,
+            payment_service: PaymentService::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Proposes a run of the approved, unpaid invoices matching the request,
+    /// with early-payment discounts applied and the tax to withhold from each
+    /// payment. Invoices already proposed in another open run are left out.
+    pub async fn create_run(
+        &self,
+        request: CreatePaymentRunRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PaymentRunWithItems> {
+        let currency = request.currency.as_deref().unwrap_or("IDR").to_uppercase();
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let invoices = sqlx::query!(
+            r#"
+            SELECT vi.id, vi.invoice_date, vi.subtotal, vi.total_amount, COALESCE(vi.paid_amount, 0) as "paid_amount!",
+                   v.early_payment_discount_percent, v.early_payment_discount_days,
+                   v.npwp, v.withholding_profile
+            FROM vendor_invoices vi
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE vi.company_id = $1
+              AND vi.status = 'APPROVED'
+              AND COALESCE(vi.currency, 'IDR') = $2
+              AND vi.total_amount > COALESCE(vi.paid_amount, 0)
+              AND (cardinality($3::uuid[]) = 0 OR vi.vendor_id = ANY($3))
+              AND (vi.due_date <= $4
+                   OR ($5 AND v.early_payment_discount_percent > 0
+                       AND COALESCE(vi.paid_amount, 0) = 0
+                       AND vi.invoice_date + v.early_payment_discount_days >= $6))
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM payment_run_items pri
+                  JOIN payment_runs pr ON pr.id = pri.run_id
+                  WHERE pri.invoice_id = vi.id
+                    AND pri.payment_id IS NULL
+                    AND pr.status IN ('DRAFT', 'APPROVED')
+              )
+            ORDER BY v.vendor_name, vi.due_date, vi.invoice_number
+            FOR UPDATE OF vi
+            "#,
+            company_id,
+            currency,
+            &request.vendor_ids,
+            request.due_date_to,
+            request.take_early_discounts,
+            request.payment_date
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if invoices.is_empty() {
+            return Err(ServiceError::Validation(
+                "No approved invoices are due for payment with these criteria".to_string()
+            ));
+        }
+
+        let run_id = Uuid::new_v4();
+        let run_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::PaymentRun,
+            request.payment_date,
+        ).await?;
+
+        let items = invoices
+            .iter()
+            .map(|invoice| -> ServiceResult<(Uuid, Decimal, Decimal, Decimal)> {
+                let outstanding = invoice.total_amount - invoice.paid_amount;
+                let discount = early_payment_discount(
+                    invoice.total_amount,
+                    invoice.paid_amount,
+                    invoice.invoice_date,
+                    request.payment_date,
+                    invoice.early_payment_discount_percent,
+                    invoice.early_payment_discount_days,
+                );
+                let withholding = withholding_service::withholding_for(
+                    invoice.withholding_profile.parse().map_err(ServiceError::Internal)?,
+                    invoice.npwp.as_deref(),
+                    outstanding - discount,
+                    invoice.subtotal,
+                    invoice.total_amount,
+                );
+                Ok((invoice.id, outstanding, discount, withholding.tax_amount))
+            })
+            .collect::<ServiceResult<Vec<_>>>()?;
+
+        let total_outstanding: Decimal = items.iter().map(|(_, outstanding, _, _)| *outstanding).sum();
+        let total_discount: Decimal = items.iter().map(|(_, _, discount, _)| *discount).sum();
+        let total_withholding: Decimal = items.iter().map(|(_, _, _, withholding)| *withholding).sum();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_runs (
+                id, company_id, run_number, payment_date, currency, due_date_to, bank_account_id,
+                source_account_number, source_account_name, status,
+                total_outstanding, total_discount, total_payment, total_withholding, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'DRAFT', $10, $11, $12, $13, $14, NOW(), NOW())
+            "#,
+            run_id,
+            company_id,
+            run_number,
+            request.payment_date,
+            currency,
+            request.due_date_to,
+            request.bank_account_id,
+            request.source_account_number.trim(),
+            request.source_account_name.trim(),
+            total_outstanding,
+            total_discount,
+            total_outstanding - total_discount,
+            total_withholding,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (invoice_id, outstanding, discount, withholding) in &items {
+            sqlx::query!(
+                r#"
+                INSERT INTO payment_run_items (
+                    id, run_id, invoice_id, outstanding_amount, discount_amount, payment_amount, withholding_amount
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                Uuid::new_v4(),
+                run_id,
+                invoice_id,
+                outstanding,
+                discount,
+                *outstanding - *discount,
+                withholding
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "payment_runs",
+            run_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "run_number": run_number,
+                "invoice_count": items.len(),
+                "total_payment": total_outstanding - total_discount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Proposed payment run {} with {} invoice(s) for company {}", run_number, items.len(), company_id);
+
+        self.get_run(run_id, company_id).await
+    }
+
+    pub async fn get_runs(
+        &self,
+        company_id: Uuid,
+        status: Option<PaymentRunStatus>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<PaymentRun>> {
+        sqlx::query_as!(
+            PaymentRun,
+            r#"
+            SELECT id, company_id, run_number, payment_date, currency, due_date_to, bank_account_id,
+                   source_account_number, source_account_name, status,
+                   total_outstanding, total_discount, total_payment, total_withholding,
+                   created_by, approved_by, approved_at, completed_at, created_at, updated_at
+            FROM payment_runs
+            WHERE company_id = $1
+              AND ($2::text IS NULL OR status = $2)
+            ORDER BY payment_date DESC, run_number DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            status.map(|s| s.to_string()),
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_run(&self, run_id: Uuid, company_id: Uuid) -> ServiceResult<PaymentRunWithItems> {
+        let payment_run = self.get_run_header(run_id, company_id).await?;
+
+        let items = sqlx::query_as!(
+            PaymentRunItem,
+            r#"
+            SELECT pri.id, pri.run_id, pri.invoice_id, vi.invoice_number, vi.invoice_date, vi.due_date,
+                   vi.vendor_id, v.vendor_name, pri.outstanding_amount, pri.discount_amount,
+                   pri.payment_amount, pri.withholding_amount, pri.payment_id,
+                   vp.payment_number as "payment_number?"
+            FROM payment_run_items pri
+            JOIN vendor_invoices vi ON vi.id = pri.invoice_id
+            JOIN vendors v ON v.id = vi.vendor_id
+            LEFT JOIN vendor_payments vp ON vp.id = pri.payment_id
+            WHERE pri.run_id = $1
+            ORDER BY v.vendor_name, vi.due_date, vi.invoice_number
+            "#,
+            run_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(PaymentRunWithItems { payment_run, items })
+    }
+
+    /// Drops an unpaid invoice from a run under review, or from an approved
+    /// run whose payment for it cannot go through.
+    pub async fn remove_item(
+        &self,
+        run_id: Uuid,
+        item_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PaymentRunWithItems> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let (run_number, status) = self.lock_run(&mut tx, run_id, company_id).await?;
+        if !matches!(status, PaymentRunStatus::Draft | PaymentRunStatus::Approved) {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} is {} and can no longer be changed", run_number, status
+            )));
+        }
+
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM payment_run_items
+            WHERE id = $1 AND run_id = $2 AND payment_id IS NULL
+            RETURNING invoice_id
+            "#,
+            item_id,
+            run_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Unpaid payment run item not found".to_string()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE payment_runs
+            SET total_outstanding = totals.outstanding,
+                total_discount = totals.discount,
+                total_payment = totals.payment,
+                total_withholding = totals.withholding,
+                updated_at = NOW()
+            FROM (
+                SELECT COALESCE(SUM(outstanding_amount), 0) as outstanding,
+                       COALESCE(SUM(discount_amount), 0) as discount,
+                       COALESCE(SUM(payment_amount), 0) as payment,
+                       COALESCE(SUM(withholding_amount), 0) as withholding
+                FROM payment_run_items
+                WHERE run_id = $1
+            ) totals
+            WHERE id = $1
+            "#,
+            run_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "payment_runs",
+            run_id,
+            "REMOVE_INVOICE",
+            Some(serde_json::json!({ "invoice_id": removed.invoice_id })),
+            None,
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.get_run(run_id, company_id).await
+    }
+
+    /// Approves a reviewed run. Every vendor in it needs bank details, or
+    /// the run could not be exported.
+    pub async fn approve_run(
+        &self,
+        run_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PaymentRunWithItems> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let (run_number, status) = self.lock_run(&mut tx, run_id, company_id).await?;
+        if status != PaymentRunStatus::Draft {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} cannot go from {} to {}", run_number, status, PaymentRunStatus::Approved
+            )));
+        }
+
+        let missing = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT v.vendor_name
+            FROM payment_run_items pri
+            JOIN vendor_invoices vi ON vi.id = pri.invoice_id
+            JOIN vendors v ON v.id = vi.vendor_id
+            WHERE pri.run_id = $1
+              AND (NULLIF(TRIM(v.bank_name), '') IS NULL OR NULLIF(TRIM(v.bank_account_number), '') IS NULL)
+            ORDER BY v.vendor_name
+            "#,
+            run_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if !missing.is_empty() {
+            return Err(ServiceError::Validation(format!(
+                "Vendors without bank details: {}", missing.join(", ")
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE payment_runs
+            SET status = 'APPROVED', approved_by = $1, approved_at = NOW(), updated_at = NOW()
+            WHERE id = $2
+            "#,
+            user_id,
+            run_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "payment_runs",
+            run_id,
+            "APPROVE",
+            Some(serde_json::json!({ "status": status.to_string() })),
+            Some(serde_json::json!({ "status": PaymentRunStatus::Approved.to_string() })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Payment run {} approved by user {}", run_number, user_id);
+
+        self.get_run(run_id, company_id).await
+    }
+
+    /// Records a payment for every unpaid invoice of an approved run, each
+    /// posted to the ledger on its own. When one fails, the invoices paid so
+    /// far stay paid and executing the run again picks up the rest. The
+    /// withholding total is restated from what the payments withheld.
+    pub async fn execute_run(
+        &self,
+        run_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<PaymentRunWithItems> {
+        let run = self.get_run(run_id, company_id).await?;
+        let status = run.payment_run.status.parse::<PaymentRunStatus>().map_err(ServiceError::Internal)?;
+        if status != PaymentRunStatus::Approved {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} must be approved before it is executed", run.payment_run.run_number
+            )));
+        }
+
+        for item in run.items.iter().filter(|item| item.payment_id.is_none()) {
+            let payment = PaymentRequest {
+                payment_amount: item.payment_amount,
+                payment_date: run.payment_run.payment_date,
+                payment_method: RUN_PAYMENT_METHOD.to_string(),
+                bank_account_id: run.payment_run.bank_account_id,
+                payment_reference: Some(run.payment_run.run_number.clone()),
+            };
+
+            self.payment_service.record_payment(
+                item.invoice_id,
+                company_id,
+                &payment,
+                item.discount_amount,
+                Some(item.id),
+                user_id,
+                roles,
+            ).await?;
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE payment_runs
+            SET status = 'COMPLETED',
+                total_withholding = (
+                    SELECT COALESCE(SUM(withholding_amount), 0) FROM payment_run_items WHERE run_id = $1
+                ),
+                completed_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'APPROVED'
+            "#,
+            run_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "payment_runs",
+            run_id,
+            "EXECUTE",
+            Some(serde_json::json!({ "status": status.to_string() })),
+            Some(serde_json::json!({
+                "status": PaymentRunStatus::Completed.to_string(),
+                "total_payment": run.payment_run.total_payment
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Payment run {} executed: {} invoice(s), {} {}",
+            run.payment_run.run_number, run.items.len(), run.payment_run.total_payment, run.payment_run.currency
+        );
+
+        self.get_run(run_id, company_id).await
+    }
+
+    pub async fn cancel_run(
+        &self,
+        run_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PaymentRunWithItems> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let (run_number, status) = self.lock_run(&mut tx, run_id, company_id).await?;
+        if !matches!(status, PaymentRunStatus::Draft | PaymentRunStatus::Approved) {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} cannot go from {} to {}", run_number, status, PaymentRunStatus::Cancelled
+            )));
+        }
+
+        let paid = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM payment_run_items WHERE run_id = $1 AND payment_id IS NOT NULL"#,
+            run_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if paid > 0 {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} has already paid {} invoice(s); execute it to finish", run_number, paid
+            )));
+        }
+
+        sqlx::query!(
+            "UPDATE payment_runs SET status = 'CANCELLED', updated_at = NOW() WHERE id = $1",
+            run_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "payment_runs",
+            run_id,
+            "CANCEL",
+            Some(serde_json::json!({ "status": status.to_string() })),
+            Some(serde_json::json!({ "status": PaymentRunStatus::Cancelled.to_string() })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Payment run {} cancelled by user {}", run_number, user_id);
+
+        self.get_run(run_id, company_id).await
+    }
+
+    /// Bank upload file with one transfer per vendor, for approved and
+    /// completed runs.
+    pub async fn export_run(
+        &self,
+        run_id: Uuid,
+        company_id: Uuid,
+        format: BankFileFormat,
+    ) -> ServiceResult<BankFile> {
+        let run = self.get_run_header(run_id, company_id).await?;
+        let status = run.status.parse::<PaymentRunStatus>().map_err(ServiceError::Internal)?;
+        if !matches!(status, PaymentRunStatus::Approved | PaymentRunStatus::Completed) {
+            return Err(ServiceError::Validation(format!(
+                "Payment run {} is {}; only approved runs can be exported", run.run_number, status
+            )));
+        }
+
+        let transfers = self.get_remittances_for(&run).await?
+            .into_iter()
+            .map(|advice| match (advice.bank_name, advice.bank_account_number) {
+                (Some(bank_name), Some(account_number)) => Ok(bank_files::VendorTransfer {
+                    vendor_code: advice.vendor_code,
+                    vendor_email: advice.vendor_email,
+                    bank_name,
+                    account_number,
+                    account_name: advice.bank_account_name.unwrap_or(advice.vendor_name),
+                    amount: advice.total_transfer,
+                    invoice_numbers: advice.lines.into_iter().map(|l| l.invoice_number).collect(),
+                }),
+                _ => Err(ServiceError::Validation(format!(
+                    "Vendor {} has no bank details", advice.vendor_name
+                ))),
+            })
+            .collect::<ServiceResult<Vec<_>>>()?;
+
+        let file = bank_files::render(format, &run, &transfers, chrono::Utc::now())
+            .map_err(ServiceError::Validation)?;
+
+        tracing::info!("Exported payment run {} as {}", run.run_number, file.file_name);
+
+        Ok(file)
+    }
+
+    /// Remittance advice for each vendor the run pays.
+    pub async fn get_remittances(&self, run_id: Uuid, company_id: Uuid) -> ServiceResult<Vec<RemittanceAdvice>> {
+        let run = self.get_run_header(run_id, company_id).await?;
+        self.get_remittances_for(&run).await
+    }
+
+    async fn get_remittances_for(&self, run: &PaymentRun) -> ServiceResult<Vec<RemittanceAdvice>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT v.id as vendor_id, v.vendor_code, v.vendor_name, v.email,
+                   v.bank_name, v.bank_account_number, v.bank_account_name,
+                   vi.invoice_number, vi.invoice_date, vp.payment_number as "payment_number?",
+                   pri.outstanding_amount, pri.discount_amount, pri.payment_amount, pri.withholding_amount
+            FROM payment_run_items pri
+            JOIN vendor_invoices vi ON vi.id = pri.invoice_id
+            JOIN vendors v ON v.id = vi.vendor_id
+            LEFT JOIN vendor_payments vp ON vp.id = pri.payment_id
+            WHERE pri.run_id = $1
+            ORDER BY v.vendor_name, v.id, vi.invoice_date, vi.invoice_number
+            "#,
+            run.id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut advices: Vec<RemittanceAdvice> = Vec::new();
+        for row in rows {
+            if advices.last().map(|a| a.vendor_id) != Some(row.vendor_id) {
+                advices.push(RemittanceAdvice {
+                    run_number: run.run_number.clone(),
+                    payment_date: run.payment_date,
+                    currency: run.currency.clone(),
+                    vendor_id: row.vendor_id,
+                    vendor_code: row.vendor_code,
+                    vendor_name: row.vendor_name,
+                    vendor_email: row.email,
+                    bank_name: row.bank_name.filter(|b| !b.trim().is_empty()),
+                    bank_account_number: row.bank_account_number.filter(|a| !a.trim().is_empty()),
+                    bank_account_name: row.bank_account_name.filter(|n| !n.trim().is_empty()),
+                    lines: Vec::new(),
+                    total_outstanding: Decimal::ZERO,
+                    total_discount: Decimal::ZERO,
+                    total_payment: Decimal::ZERO,
+                    total_withholding: Decimal::ZERO,
+                    total_transfer: Decimal::ZERO,
+                });
+            }
+
+            if let Some(advice) = advices.last_mut() {
+                advice.total_outstanding += row.outstanding_amount;
+                advice.total_discount += row.discount_amount;
+                advice.total_payment += row.payment_amount;
+                advice.total_withholding += row.withholding_amount;
+                advice.total_transfer += row.payment_amount - row.withholding_amount;
+                advice.lines.push(RemittanceLine {
+                    invoice_number: row.invoice_number,
+                    invoice_date: row.invoice_date,
+                    payment_number: row.payment_number,
+                    outstanding_amount: row.outstanding_amount,
+                    discount_amount: row.discount_amount,
+                    payment_amount: row.payment_amount,
+                    withholding_amount: row.withholding_amount,
+                });
+            }
+        }
+
+        Ok(advices)
+    }
+
+    async fn get_run_header(&self, run_id: Uuid, company_id: Uuid) -> ServiceResult<PaymentRun> {
+        sqlx::query_as!(
+            PaymentRun,
+            r#"
+            SELECT id, company_id, run_number, payment_date, currency, due_date_to, bank_account_id,
+                   source_account_number, source_account_name, status,
+                   total_outstanding, total_discount, total_payment, total_withholding,
+                   created_by, approved_by, approved_at, completed_at, created_at, updated_at
+            FROM payment_runs
+            WHERE id = $1 AND company_id = $2
+            "#,
+            run_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Payment run not found".to_string()))
+    }
+
+    async fn lock_run(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        run_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<(String, PaymentRunStatus)> {
+        let run = sqlx::query!(
+            "SELECT run_number, status FROM payment_runs WHERE id = $1 AND company_id = $2 FOR UPDATE",
+            run_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Payment run not found".to_string()))?;
+
+        let status = run.status.parse::<PaymentRunStatus>().map_err(ServiceError::Internal)?;
+        Ok((run.run_number, status))
+    }
+}
+
+/// Discount for paying an invoice early: the vendor's percentage of the
+/// invoice total, when nothing has been paid on it yet and the payment date
+/// is within the discount days of the invoice date.
+fn early_payment_discount(
+    total_amount: Decimal,
+    paid_amount: Decimal,
+    invoice_date: NaiveDate,
+    payment_date: NaiveDate,
+    discount_percent: Decimal,
+    discount_days: i32,
+) -> Decimal {
+    let within_days = payment_date <= invoice_date + chrono::Duration::days(discount_days as i64);
+
+    if discount_percent > Decimal::ZERO && paid_amount == Decimal::ZERO && within_days {
+        (total_amount * discount_percent / Decimal::ONE_HUNDRED).round_dp(2)
+    } else {
+        Decimal::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_early_payment_discount() {
+        let invoice_date = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let total = Decimal::from(11_100_000);
+        let two = Decimal::from(2);
+
+        // 2/10: up to ten days after the invoice date
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        assert_eq!(early_payment_discount(total, Decimal::ZERO, invoice_date, day(11), two, 10), Decimal::from(222_000));
+        assert_eq!(early_payment_discount(total, Decimal::ZERO, invoice_date, day(12), two, 10), Decimal::ZERO);
+
+        // Partly paid invoices and vendors without terms get none
+        assert_eq!(early_payment_discount(total, Decimal::ONE, invoice_date, day(5), two, 10), Decimal::ZERO);
+        assert_eq!(early_payment_discount(total, Decimal::ZERO, invoice_date, day(5), Decimal::ZERO, 10), Decimal::ZERO);
+
+        // Rounded to the cent
+        let discount = early_payment_discount(Decimal::new(33_333, 2), Decimal::ZERO, invoice_date, day(1), Decimal::new(15, 1), 0);
+        assert_eq!(discount, Decimal::new(500, 2));
+    }
+}
@@ -1,18 +1,31 @@
 use crate::models::*;
+use super::{withholding_service, LedgerClient, LedgerOutbox, WithholdingService};
 use common::{ServiceResult, ServiceError};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// Source document type of the ledger entry booked for a payment.
+pub(crate) const PAYMENT_SOURCE: &str = "VENDOR_PAYMENT";
+
 pub struct PaymentService {
     db: PgPool,
     audit_logger: database::audit::AuditLogger,
+    ledger_client: LedgerClient,
+    ledger_outbox: LedgerOutbox,
+    withholding_service: WithholdingService,
 }
 
 impl PaymentService {
     pub fn new(db: PgPool) -> Self {
         let audit_logger = database::audit::AuditLogger::new(db.clone());
-        Self { db, audit_logger }
+        Self {
+            audit_logger,
+            ledger_client: LedgerClient::from_env(),
+            ledger_outbox: LedgerOutbox::new(db.clone()),
+            withholding_service: WithholdingService::new(db.clone()),
+            db,
+        }
     }
 
     pub async fn process_payment(
@@ -21,6 +34,28 @@ impl PaymentService {
         company_id: Uuid,
         payment: PaymentRequest,
         user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<VendorInvoice> {
+        self.record_payment(invoice_id, company_id, &payment, Decimal::ZERO, None, user_id, roles).await
+    }
+
+    /// Pays an invoice, settling `discount_amount` of it as an early-payment
+    /// discount on top of the amount paid. Tax the vendor's withholding
+    /// profile requires is withheld from the amount paid and a bukti potong
+    /// issued for it. A payment made by a payment run is linked to its run
+    /// item in the same transaction, so an item is never paid twice. The
+    /// payment is saved pending posting and its ledger entry sent once it
+    /// is committed.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn record_payment(
+        &self,
+        invoice_id: Uuid,
+        company_id: Uuid,
+        payment: &PaymentRequest,
+        discount_amount: Decimal,
+        run_item_id: Option<Uuid>,
+        user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<VendorInvoice> {
         if payment.payment_amount <= Decimal::ZERO {
             return Err(ServiceError::Validation(
@@ -28,16 +63,49 @@ impl PaymentService {
             ));
         }
 
+        // The bank leg is booked at the ledger's BI middle rate on the
+        // payment date, read before the invoice is locked
+        let currency = sqlx::query_scalar!(
+            "SELECT currency FROM vendor_invoices WHERE id = $1 AND company_id = $2",
+            invoice_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
+        let payment_rate = self.ledger_client.booking_rate(company_id, user_id, currency.as_deref(), payment.payment_date).await?;
+        // Withholding on a foreign-currency invoice is taxed at the KMK rate
+        let tax_rates = match payment_rate.foreign_currency() {
+            Some(foreign) => Some(self.ledger_client.rate_table(
+                company_id,
+                user_id,
+                &[foreign.to_string()],
+                database::exchange_rates::RateType::Tax,
+                payment.payment_date,
+            ).await?),
+            None => None,
+        };
+        let to_tax_base = |amount: Decimal| match &tax_rates {
+            Some(rates) => rates.tax_base(amount, currency.as_deref()),
+            None => Ok(payment_rate.to_functional(amount)),
+        };
+
         let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
 
+        // Payment date must fall in a period that accepts postings
+        database::periods::ensure_period_open(&mut *tx, company_id, payment.payment_date, roles).await?;
+
         // Get current invoice details
         let current_invoice = sqlx::query!(
             r#"
-            SELECT vi.total_amount, vi.paid_amount, vi.status as "status_str",
-                   v.vendor_name, vi.invoice_number
+            SELECT vi.subtotal, vi.total_amount, vi.paid_amount, vi.status as "status_str",
+                   vi.vendor_id, v.vendor_name, v.vendor_group, v.npwp, v.withholding_profile,
+                   vi.invoice_number, vi.currency, vi.exchange_rate
             FROM vendor_invoices vi
             JOIN vendors v ON vi.vendor_id = v.id
             WHERE vi.id = $1 AND vi.company_id = $2
+            FOR UPDATE OF vi
             "#,
             invoice_id,
             company_id
@@ -47,16 +115,34 @@ impl PaymentService {
         .map_err(ServiceError::Database)?
         .ok_or_else(|| ServiceError::NotFound("Invoice not found".to_string()))?;
 
+        // Only approved invoices are in the ledger, so only they can be paid
+        if current_invoice.status_str.as_deref() != Some("APPROVED") {
+            return Err(ServiceError::Validation(format!(
+                "Invoice {} must be approved before it can be paid",
+                current_invoice.invoice_number
+            )));
+        }
+
         let remaining_amount = current_invoice.total_amount - current_invoice.paid_amount;
-        
-        if payment.payment_amount > remaining_amount {
+        let settled_amount = payment.payment_amount + discount_amount;
+
+        if settled_amount > remaining_amount {
             return Err(ServiceError::Validation(
                 format!("Payment amount ({}) exceeds remaining balance ({})", 
-                    payment.payment_amount, remaining_amount)
+                    settled_amount, remaining_amount)
             ));
         }
 
-        let new_paid_amount = current_invoice.paid_amount + payment.payment_amount;
+        let withholding = withholding_service::withholding_for(
+            current_invoice.withholding_profile.parse().map_err(ServiceError::Internal)?,
+            current_invoice.npwp.as_deref(),
+            payment.payment_amount,
+            current_invoice.subtotal,
+            current_invoice.total_amount,
+        );
+
+        // The discount counts as paid; it is tracked separately on the invoice
+        let new_paid_amount = current_invoice.paid_amount + settled_amount;
         let new_status = if new_paid_amount >= current_invoice.total_amount {
             InvoiceStatus::Paid
         } else {
@@ -65,44 +151,215 @@ impl PaymentService {
 
         // Record the payment
         let payment_id = Uuid::new_v4();
+        let payment_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::VendorPayment,
+            payment.payment_date,
+        ).await?;
+
         sqlx::query!(
             r#"
             INSERT INTO vendor_payments (
-                id, invoice_id, company_id, payment_amount, payment_date, 
-                payment_method, bank_account_id, payment_reference, created_by, created_at
+                id, invoice_id, company_id, payment_number, payment_amount, payment_date, 
+                payment_method, bank_account_id, payment_reference, discount_amount, withholding_amount,
+                posting_status, created_by, created_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, 'PENDING_POSTING', $12, NOW())
             "#,
             payment_id,
             invoice_id,
             company_id,
+            payment_number,
             payment.payment_amount,
             payment.payment_date,
             payment.payment_method,
             payment.bank_account_id,
             payment.payment_reference,
+            discount_amount,
+            withholding.tax_amount,
             user_id
         )
         .execute(&mut *tx)
         .await
         .map_err(ServiceError::Database)?;
 
+        if let Some(item_id) = run_item_id {
+            let linked = sqlx::query!(
+                r#"
+                UPDATE payment_run_items SET payment_id = $1, withholding_amount = $3
+                WHERE id = $2 AND payment_id IS NULL
+                "#,
+                payment_id,
+                item_id,
+                withholding.tax_amount
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            if linked.rows_affected() == 0 {
+                return Err(ServiceError::Conflict(format!(
+                    "Invoice {} has already been paid by its payment run",
+                    current_invoice.invoice_number
+                )));
+            }
+        }
+
+        // Dr AP control for everything settled at the invoice's rate; Cr the
+        // payment's bank account and purchase discounts at the payment date's
+        // rate and the tax withheld at its tax base. On foreign-currency
+        // invoices the difference is the realized FX gain or loss; otherwise
+        // the bank takes whatever rounding the conversion leaves.
+        let rate = BookingRate {
+            currency: current_invoice.currency.clone(),
+            rate: current_invoice.exchange_rate.unwrap_or(Decimal::ONE),
+            functional_currency: payment_rate.functional_currency.clone(),
+        };
+        let settled_ledger_amount = rate.to_functional(settled_amount);
+        let withholding_ledger_amount = if withholding.tax_amount > Decimal::ZERO {
+            to_tax_base(withholding.tax_amount)?
+        } else {
+            Decimal::ZERO
+        };
+        let discount_ledger_amount = payment_rate.to_functional(discount_amount);
+        let bank_amount = payment.payment_amount - withholding.tax_amount;
+        let bank_ledger_amount = match rate.foreign_currency() {
+            Some(_) => payment_rate.to_functional(bank_amount),
+            None => settled_ledger_amount - withholding_ledger_amount - discount_ledger_amount,
+        };
+        let fx_difference = settled_ledger_amount - bank_ledger_amount - withholding_ledger_amount - discount_ledger_amount;
+        let memo = format!("Payment {} - {} {}", payment_number, current_invoice.vendor_name, current_invoice.invoice_number);
+
+        let mut lines = vec![
+            LedgerPostingLine {
+                account_id: None,
+                account_role: Some(LedgerAccountRole::ApControl),
+                item_category: None,
+                tax_type: None,
+                description: Some(memo.clone()),
+                debit_amount: settled_ledger_amount,
+                credit_amount: Decimal::ZERO,
+                department: None,
+                project_code: None,
+                foreign: rate.foreign(settled_amount),
+            },
+            LedgerPostingLine {
+                account_id: payment.bank_account_id,
+                account_role: Some(LedgerAccountRole::Bank),
+                item_category: None,
+                tax_type: None,
+                description: Some(memo.clone()),
+                debit_amount: Decimal::ZERO,
+                credit_amount: bank_ledger_amount,
+                department: None,
+                project_code: None,
+                foreign: payment_rate.foreign(bank_amount),
+            },
+        ];
+        if withholding_ledger_amount > Decimal::ZERO {
+            lines.push(LedgerPostingLine {
+                account_id: None,
+                account_role: Some(LedgerAccountRole::WithholdingTax),
+                item_category: None,
+                tax_type: Some("PPH23".to_string()),
+                description: Some(format!("PPh 23 {}", current_invoice.invoice_number)),
+                debit_amount: Decimal::ZERO,
+                credit_amount: withholding_ledger_amount,
+                department: None,
+                project_code: None,
+                foreign: None,
+            });
+        }
+        if discount_ledger_amount > Decimal::ZERO {
+            lines.push(LedgerPostingLine {
+                account_id: None,
+                account_role: Some(LedgerAccountRole::PurchaseDiscount),
+                item_category: None,
+                tax_type: None,
+                description: Some(format!("Potongan pembelian {}", current_invoice.invoice_number)),
+                debit_amount: Decimal::ZERO,
+                credit_amount: discount_ledger_amount,
+                department: None,
+                project_code: None,
+                foreign: None,
+            });
+        }
+        if fx_difference != Decimal::ZERO {
+            lines.push(LedgerPostingLine {
+                account_id: None,
+                account_role: Some(LedgerAccountRole::RealizedFxGainLoss),
+                item_category: None,
+                tax_type: None,
+                description: Some(format!("Selisih kurs {}", current_invoice.invoice_number)),
+                debit_amount: (-fx_difference).max(Decimal::ZERO),
+                credit_amount: fx_difference.max(Decimal::ZERO),
+                department: None,
+                project_code: None,
+                foreign: None,
+            });
+        }
+
+        let outbox_id = self.ledger_outbox.enqueue_posting(
+            &mut tx,
+            company_id,
+            &LedgerPostingRequest {
+                source_document_type: PAYMENT_SOURCE.to_string(),
+                source_document_id: payment_id,
+                entry_date: payment.payment_date,
+                description: Some(memo.clone()),
+                reference: payment.payment_reference.clone().or_else(|| Some(payment_number.clone())),
+                event_type: LedgerEvent::VendorPaymentMade,
+                vendor_group: current_invoice.vendor_group.clone(),
+                lines,
+            },
+            user_id,
+            roles,
+        ).await?;
+
+        // The slip and tax return are in rupiah, whatever the invoice currency
+        let mut slip_number = None;
+        if withholding_ledger_amount > Decimal::ZERO {
+            slip_number = Some(self.withholding_service.issue_slip(
+                &mut tx,
+                withholding_service::NewWithholdingSlip {
+                    company_id,
+                    payment_id,
+                    invoice_id,
+                    vendor_id: current_invoice.vendor_id,
+                    invoice_number: &current_invoice.invoice_number,
+                    vendor_name: &current_invoice.vendor_name,
+                    vendor_npwp: current_invoice.npwp.as_deref(),
+                    withholding_date: payment.payment_date,
+                    withholding: withholding_service::Withholding {
+                        rate: withholding.rate,
+                        base_amount: to_tax_base(withholding.base_amount)?,
+                        tax_amount: withholding_ledger_amount,
+                    },
+                },
+                user_id,
+            ).await?);
+        }
+
         // Update invoice payment status
         let updated_invoice = sqlx::query!(
             r#"
             UPDATE vendor_invoices 
             SET paid_amount = $1,
                 status = $2::invoice_status,
+                discount_amount = COALESCE(discount_amount, 0) + $5,
                 updated_at = NOW()
             WHERE id = $3 AND company_id = $4
             RETURNING id, company_id, vendor_id, invoice_number, invoice_date, due_date,
                       subtotal, tax_amount, total_amount, paid_amount,
-                      status as "status_str", description, journal_entry_id, created_at, updated_at
+                      status as "status_str", description, journal_entry_id, posting_status,
+                      created_at, updated_at
             "#,
             new_paid_amount,
             new_status.to_string(),
             invoice_id,
-            company_id
+            company_id,
+            discount_amount
         )
         .fetch_one(&mut *tx)
         .await
@@ -121,14 +378,26 @@ impl PaymentService {
             Some(serde_json::json!({
                 "new_paid_amount": new_paid_amount,
                 "new_status": new_status.to_string(),
+                "payment_number": payment_number,
                 "payment_amount": payment.payment_amount,
-                "payment_method": payment.payment_method
+                "discount_amount": discount_amount,
+                "withholding_amount": withholding.tax_amount,
+                "withholding_slip_number": slip_number,
+                "payment_method": payment.payment_method,
+                "posting_status": PostingStatus::PendingPosting.to_string()
             })),
             user_id,
         ).await.map_err(ServiceError::Database)?;
 
         tx.commit().await.map_err(ServiceError::Database)?;
 
+        // The slip goes to the tax service after the ledger, so it can name
+        // the payment's entry
+        self.ledger_outbox.dispatch(outbox_id).await?;
+        if slip_number.is_some() {
+            self.withholding_service.report_slips(Some(payment_id), roles).await?;
+        }
+
         let status = updated_invoice.status_str.as_deref()
             .and_then(|s| s.parse::<InvoiceStatus>().ok())
             .unwrap_or(InvoiceStatus::Draft);
@@ -149,8 +418,10 @@ impl PaymentService {
             status,
             description: updated_invoice.description,
             journal_entry_id: updated_invoice.journal_entry_id,
+            posting_status: updated_invoice.posting_status.parse().unwrap_or(PostingStatus::NotPosted),
             created_at: updated_invoice.created_at,
             updated_at: updated_invoice.updated_at,
+            budget_warnings: Vec::new(),
         };
 
         tracing::info!("Processed payment of {} for invoice {} by user {}", 
@@ -168,9 +439,10 @@ impl PaymentService {
             VendorPayment,
             r#"
             SELECT 
-                id, invoice_id, company_id, payment_amount, payment_date,
-                payment_method, bank_account_id, payment_reference,
-                created_by, created_at
+                id, invoice_id, company_id, payment_number, payment_amount, payment_date,
+                payment_method, bank_account_id, payment_reference, check_number, discount_amount,
+                withholding_amount, is_reversed as "is_reversed!", journal_entry_id, reversal_journal_entry_id,
+                posting_status, created_by, created_at
             FROM vendor_payments
             WHERE invoice_id = $1 AND company_id = $2
             ORDER BY payment_date DESC, created_at DESC
@@ -185,21 +457,62 @@ impl PaymentService {
         Ok(payments)
     }
 
+    /// Payments of the company, including reversed ones, for bank
+    /// reconciliation in the ledger. Payments without a bank account are
+    /// included when filtering by account.
+    pub async fn get_payments(
+        &self,
+        company_id: Uuid,
+        filters: PaymentFilters,
+    ) -> ServiceResult<Vec<VendorPayment>> {
+        sqlx::query_as!(
+            VendorPayment,
+            r#"
+            SELECT 
+                id, invoice_id, company_id, payment_number, payment_amount, payment_date,
+                payment_method, bank_account_id, payment_reference, check_number, discount_amount,
+                withholding_amount, is_reversed as "is_reversed!", journal_entry_id, reversal_journal_entry_id,
+                posting_status, created_by, created_at
+            FROM vendor_payments
+            WHERE company_id = $1
+              AND ($2::uuid IS NULL OR bank_account_id = $2 OR bank_account_id IS NULL)
+              AND ($3::date IS NULL OR payment_date >= $3)
+              AND ($4::date IS NULL OR payment_date <= $4)
+              AND (cardinality($5::uuid[]) = 0 OR id = ANY($5))
+            ORDER BY payment_date, payment_number
+            "#,
+            company_id,
+            filters.bank_account_id,
+            filters.date_from,
+            filters.date_to,
+            &filters.ids
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
     pub async fn reverse_payment(
         &self,
         payment_id: Uuid,
         company_id: Uuid,
         reason: String,
         user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<()> {
         let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
 
+        // The reversal is booked today, so today's period must be open
+        let reversal_date = chrono::Utc::now().date_naive();
+        database::periods::ensure_period_open(&mut *tx, company_id, reversal_date, roles).await?;
+
         // Get payment details
         let payment = sqlx::query!(
             r#"
-            SELECT invoice_id, payment_amount
+            SELECT invoice_id, payment_number, payment_amount, discount_amount, is_reversed, journal_entry_id
             FROM vendor_payments
             WHERE id = $1 AND company_id = $2
+            FOR UPDATE
             "#,
             payment_id,
             company_id
@@ -209,13 +522,45 @@ impl PaymentService {
         .map_err(ServiceError::Database)?
         .ok_or_else(|| ServiceError::NotFound("Payment not found".to_string()))?;
 
+        if payment.is_reversed == Some(true) {
+            return Err(ServiceError::Conflict(
+                format!("Payment {} is already reversed", payment.payment_number)
+            ));
+        }
+
+        // Payments made before ledger posting existed have no entry to reverse
+        let outbox_id = self.ledger_outbox.enqueue_reversal(
+            &mut tx,
+            company_id,
+            PAYMENT_SOURCE,
+            payment_id,
+            payment.journal_entry_id,
+            reversal_date,
+            &reason,
+            user_id,
+            roles,
+        ).await?;
+        let posting_status = match outbox_id {
+            Some(_) => PostingStatus::PendingPosting,
+            None => PostingStatus::NotPosted,
+        };
+
+        let slip_number = self.withholding_service.cancel_slip(
+            &mut tx,
+            payment_id,
+            company_id,
+            &reason,
+            user_id,
+        ).await?;
+
         // Update invoice paid amount
         sqlx::query!(
             r#"
             UPDATE vendor_invoices 
-            SET paid_amount = paid_amount - $1,
+            SET paid_amount = paid_amount - $1 - $4,
+                discount_amount = COALESCE(discount_amount, 0) - $4,
                 status = CASE 
-                    WHEN (paid_amount - $1) <= 0 THEN 'APPROVED'::invoice_status
+                    WHEN status = 'PAID'::invoice_status THEN 'APPROVED'::invoice_status
                     ELSE status
                 END,
                 updated_at = NOW()
@@ -223,7 +568,8 @@ impl PaymentService {
             "#,
             payment.payment_amount,
             payment.invoice_id,
-            company_id
+            company_id,
+            payment.discount_amount
         )
         .execute(&mut *tx)
         .await
@@ -236,13 +582,15 @@ impl PaymentService {
             SET is_reversed = true, 
                 reversal_reason = $1,
                 reversed_by = $2,
-                reversed_at = NOW()
+                reversed_at = NOW(),
+                posting_status = $5
             WHERE id = $3 AND company_id = $4
             "#,
             reason,
             user_id,
             payment_id,
-            company_id
+            company_id,
+            posting_status.to_string()
         )
         .execute(&mut *tx)
         .await
@@ -257,13 +605,22 @@ impl PaymentService {
             None,
             Some(serde_json::json!({
                 "reason": reason,
-                "payment_amount": payment.payment_amount
+                "payment_amount": payment.payment_amount,
+                "cancelled_withholding_slip_number": slip_number,
+                "posting_status": posting_status.to_string()
             })),
             user_id,
         ).await.map_err(ServiceError::Database)?;
 
         tx.commit().await.map_err(ServiceError::Database)?;
 
+        if let Some(outbox_id) = outbox_id {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+        if slip_number.is_some() {
+            self.withholding_service.report_slips(Some(payment_id), roles).await?;
+        }
+
         tracing::info!("Reversed payment {} for invoice {} by user {}", 
             payment_id, payment.invoice_id, user_id);
 
@@ -0,0 +1,948 @@
+use crate::models::*;
+use super::{InventoryClient, LedgerClient, LedgerOutbox};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Source document type of the ledger entry booking received stock.
+pub(crate) const GOODS_RECEIPT_SOURCE: &str = "GOODS_RECEIPT";
+
+/// Receipt lines whose stock is booked per run of `book_pending_stock`.
+const STOCK_BATCH_SIZE: i64 = 100;
+
+/// Statuses in which an order still expects deliveries or invoices.
+const OPEN_STATUSES: [PurchaseOrderStatus; 3] = [
+    PurchaseOrderStatus::Approved,
+    PurchaseOrderStatus::PartiallyReceived,
+    PurchaseOrderStatus::Received,
+];
+
+pub struct PurchaseOrderService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+    inventory_client: InventoryClient,
+    ledger_client: LedgerClient,
+    ledger_outbox: LedgerOutbox,
+}
+
+impl PurchaseOrderService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self {
+            audit_logger,
+            inventory_client: InventoryClient::from_env(),
+            ledger_client: LedgerClient::from_env(),
+            ledger_outbox: LedgerOutbox::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn create_purchase_order(
+        &self,
+        request: CreatePurchaseOrderRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PurchaseOrderWithLines> {
+        check_po_lines(&request.lines).map_err(ServiceError::Validation)?;
+
+        let vendor = sqlx::query!(
+            "SELECT is_active, currency FROM vendors WHERE id = $1 AND company_id = $2",
+            request.vendor_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Vendor not found".to_string()))?;
+
+        if vendor.is_active != Some(true) {
+            return Err(ServiceError::Validation("Vendor is inactive".to_string()));
+        }
+
+        let currency = request.currency
+            .as_deref()
+            .or(vendor.currency.as_deref())
+            .map(|c| c.trim().to_uppercase());
+
+        let amounts: Vec<(Decimal, Decimal)> = request.lines
+            .iter()
+            .map(|l| line_amounts(l.quantity, l.unit_price, l.tax_rate.unwrap_or(Decimal::ZERO)))
+            .collect();
+        let subtotal: Decimal = amounts.iter().map(|(amount, _)| *amount).sum();
+        let tax_amount: Decimal = amounts.iter().map(|(_, tax)| *tax).sum();
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let po_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::PurchaseOrder,
+            request.po_date,
+        ).await?;
+
+        let po_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO purchase_orders (
+                id, company_id, vendor_id, po_number, po_date, expected_delivery_date, currency,
+                subtotal, tax_amount, total_amount, status, notes, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'DRAFT', $11, $12, NOW(), NOW())
+            "#,
+            po_id,
+            company_id,
+            request.vendor_id,
+            po_number,
+            request.po_date,
+            request.expected_delivery_date,
+            currency,
+            subtotal,
+            tax_amount,
+            subtotal + tax_amount,
+            request.notes,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (index, (line, (line_amount, line_tax))) in request.lines.iter().zip(&amounts).enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO purchase_order_lines (
+                    id, purchase_order_id, line_number, item_id, item_code, description, quantity,
+                    unit_price, tax_rate, line_amount, tax_amount, received_quantity, account_id
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, 0, $12)
+                "#,
+                Uuid::new_v4(),
+                po_id,
+                (index + 1) as i32,
+                line.item_id,
+                line.item_code,
+                line.description,
+                line.quantity,
+                line.unit_price,
+                line.tax_rate.unwrap_or(Decimal::ZERO),
+                line_amount,
+                line_tax,
+                line.account_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "purchase_orders",
+            po_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "po_number": po_number,
+                "vendor_id": request.vendor_id,
+                "total_amount": subtotal + tax_amount
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created purchase order {} for company {}", po_number, company_id);
+
+        self.get_purchase_order(po_id, company_id).await
+    }
+
+    pub async fn get_purchase_orders(
+        &self,
+        company_id: Uuid,
+        filters: PurchaseOrderFilters,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<PurchaseOrder>> {
+        sqlx::query_as!(
+            PurchaseOrder,
+            r#"
+            SELECT po.id, po.company_id, po.vendor_id, v.vendor_name as "vendor_name?", po.po_number,
+                   po.po_date, po.expected_delivery_date, po.currency, po.subtotal,
+                   COALESCE(po.tax_amount, 0) as "tax_amount!", po.total_amount, po.status as "status!",
+                   po.notes, po.created_by, po.approved_by,
+                   po.created_at as "created_at!", po.updated_at as "updated_at!"
+            FROM purchase_orders po
+            JOIN vendors v ON v.id = po.vendor_id
+            WHERE po.company_id = $1
+              AND ($2::text IS NULL OR po.status = $2)
+              AND ($3::uuid IS NULL OR po.vendor_id = $3)
+              AND ($4::date IS NULL OR po.po_date >= $4)
+              AND ($5::date IS NULL OR po.po_date <= $5)
+            ORDER BY po.po_date DESC, po.po_number DESC
+            LIMIT $6 OFFSET $7
+            "#,
+            company_id,
+            filters.status.map(|s| s.to_string()),
+            filters.vendor_id,
+            filters.date_from,
+            filters.date_to,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_purchase_order(&self, po_id: Uuid, company_id: Uuid) -> ServiceResult<PurchaseOrderWithLines> {
+        let purchase_order = sqlx::query_as!(
+            PurchaseOrder,
+            r#"
+            SELECT po.id, po.company_id, po.vendor_id, v.vendor_name as "vendor_name?", po.po_number,
+                   po.po_date, po.expected_delivery_date, po.currency, po.subtotal,
+                   COALESCE(po.tax_amount, 0) as "tax_amount!", po.total_amount, po.status as "status!",
+                   po.notes, po.created_by, po.approved_by,
+                   po.created_at as "created_at!", po.updated_at as "updated_at!"
+            FROM purchase_orders po
+            JOIN vendors v ON v.id = po.vendor_id
+            WHERE po.id = $1 AND po.company_id = $2
+            "#,
+            po_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Purchase order not found".to_string()))?;
+
+        let lines = sqlx::query_as!(
+            PurchaseOrderLine,
+            r#"
+            SELECT id, purchase_order_id, line_number, item_id, item_code, description, quantity,
+                   unit_price, tax_rate, line_amount, tax_amount, received_quantity, account_id
+            FROM purchase_order_lines
+            WHERE purchase_order_id = $1
+            ORDER BY line_number
+            "#,
+            po_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(PurchaseOrderWithLines { purchase_order, lines })
+    }
+
+    pub async fn approve_purchase_order(
+        &self,
+        po_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PurchaseOrderWithLines> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        self.transition(&mut tx, po_id, company_id, &[PurchaseOrderStatus::Draft], PurchaseOrderStatus::Approved, user_id)
+            .await?;
+
+        sqlx::query!("UPDATE purchase_orders SET approved_by = $1 WHERE id = $2", user_id, po_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.get_purchase_order(po_id, company_id).await
+    }
+
+    /// Closes an order that will get no further deliveries; whatever is
+    /// still open on it drops off the open-PO report.
+    pub async fn close_purchase_order(
+        &self,
+        po_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PurchaseOrderWithLines> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        self.transition(&mut tx, po_id, company_id, &OPEN_STATUSES, PurchaseOrderStatus::Closed, user_id)
+            .await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.get_purchase_order(po_id, company_id).await
+    }
+
+    /// Only orders with nothing received or invoiced against them can be
+    /// cancelled; others are closed.
+    pub async fn cancel_purchase_order(
+        &self,
+        po_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PurchaseOrderWithLines> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        self.transition(
+            &mut tx,
+            po_id,
+            company_id,
+            &[PurchaseOrderStatus::Draft, PurchaseOrderStatus::Approved],
+            PurchaseOrderStatus::Cancelled,
+            user_id,
+        ).await?;
+
+        let in_use = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM purchase_order_lines pol
+                WHERE pol.purchase_order_id = $1
+                  AND (pol.received_quantity > 0 OR EXISTS(
+                      SELECT 1 FROM vendor_invoice_lines vil
+                      JOIN vendor_invoices vi ON vi.id = vil.invoice_id
+                      WHERE vil.po_line_id = pol.id AND vi.status <> 'CANCELLED'
+                  ))
+            ) as "exists!"
+            "#,
+            po_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if in_use {
+            return Err(ServiceError::Conflict(
+                "Purchase order has receipts or invoices; close it instead".to_string()
+            ));
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        self.get_purchase_order(po_id, company_id).await
+    }
+
+    /// Records a goods receipt note. Item lines are valued in the functional
+    /// currency at the order currency's rate on the receipt date and booked
+    /// to inventory against goods received not invoiced. Their stock moves
+    /// are sent to the inventory service once the receipt is saved; lines it
+    /// could not book yet are retried by `book_pending_stock`.
+    pub async fn receive_goods(
+        &self,
+        po_id: Uuid,
+        company_id: Uuid,
+        request: CreateGoodsReceiptRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<GoodsReceiptWithLines> {
+        // The order currency never changes, so its rate is read before the
+        // order is locked
+        let currency = sqlx::query_scalar!(
+            "SELECT currency FROM purchase_orders WHERE id = $1 AND company_id = $2",
+            po_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Purchase order not found".to_string()))?;
+        let rate = self.ledger_client.booking_rate(company_id, user_id, currency.as_deref(), request.receipt_date).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let po = sqlx::query!(
+            r#"
+            SELECT po.po_number, po.status as "status!", v.vendor_name, v.vendor_group
+            FROM purchase_orders po
+            JOIN vendors v ON v.id = po.vendor_id
+            WHERE po.id = $1 AND po.company_id = $2
+            FOR UPDATE OF po
+            "#,
+            po_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Purchase order not found".to_string()))?;
+
+        let status = po.status.parse::<PurchaseOrderStatus>().map_err(ServiceError::Internal)?;
+        if !matches!(status, PurchaseOrderStatus::Approved | PurchaseOrderStatus::PartiallyReceived) {
+            return Err(ServiceError::Validation(format!(
+                "Purchase order {} is {} and cannot receive goods", po.po_number, status
+            )));
+        }
+
+        // Stock is valued on receipt, so the receipt date must be open
+        database::periods::ensure_period_open(&mut *tx, company_id, request.receipt_date, roles).await?;
+
+        let po_lines = sqlx::query!(
+            r#"
+            SELECT id, item_id, description, quantity, unit_price, received_quantity
+            FROM purchase_order_lines
+            WHERE purchase_order_id = $1
+            ORDER BY line_number
+            "#,
+            po_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let ordered: Vec<(Uuid, Decimal, Decimal)> = po_lines
+            .iter()
+            .map(|l| (l.id, l.quantity, l.received_quantity))
+            .collect();
+        let received = apply_receipt(&ordered, &request.lines).map_err(ServiceError::Validation)?;
+
+        // Item lines go into stock at their cost in the functional currency
+        let mut receipt_lines = Vec::with_capacity(request.lines.len());
+        let mut stock = Vec::new();
+        for line in &request.lines {
+            let po_line = po_lines.iter().find(|l| l.id == line.po_line_id)
+                .ok_or_else(|| ServiceError::Internal("Receipt line lost its order line".to_string()))?;
+
+            let unit_cost = po_line.item_id.map(|_| rate.to_functional(po_line.unit_price));
+            if let Some(unit_cost) = unit_cost {
+                stock.push((po_line.description.clone(), (line.quantity * unit_cost).round_dp(2)));
+            }
+            receipt_lines.push((line, unit_cost));
+        }
+
+        let receipt_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::GoodsReceipt,
+            request.receipt_date,
+        ).await?;
+
+        let memo = format!("Goods receipt {} / {} - {}", receipt_number, po.po_number, po.vendor_name);
+        let posting_lines = receipt_posting_lines(&stock, &memo);
+        let posting_status = if posting_lines.is_empty() {
+            PostingStatus::NotPosted
+        } else {
+            PostingStatus::PendingPosting
+        };
+
+        let receipt_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO goods_receipts (
+                id, company_id, purchase_order_id, receipt_number, receipt_date, notes,
+                currency, exchange_rate, posting_status, created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW())
+            "#,
+            receipt_id,
+            company_id,
+            po_id,
+            receipt_number,
+            request.receipt_date,
+            request.notes,
+            currency,
+            rate.rate,
+            posting_status.to_string(),
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (line, unit_cost) in receipt_lines {
+            sqlx::query!(
+                r#"
+                INSERT INTO goods_receipt_lines (id, receipt_id, po_line_id, quantity, unit_cost)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                Uuid::new_v4(),
+                receipt_id,
+                line.po_line_id,
+                line.quantity,
+                unit_cost
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        for (po_line_id, new_received) in &received {
+            sqlx::query!(
+                "UPDATE purchase_order_lines SET received_quantity = $1 WHERE id = $2",
+                new_received,
+                po_line_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        let new_status = status_after_receipt(
+            &po_lines.iter().map(|l| (l.quantity, received.get(&l.id).copied().unwrap_or(l.received_quantity))).collect::<Vec<_>>()
+        );
+        sqlx::query!(
+            "UPDATE purchase_orders SET status = $1, updated_at = NOW() WHERE id = $2",
+            new_status.to_string(),
+            po_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut outbox_id = None;
+        if !posting_lines.is_empty() {
+            outbox_id = Some(self.ledger_outbox.enqueue_posting(
+                &mut tx,
+                company_id,
+                &LedgerPostingRequest {
+                    source_document_type: GOODS_RECEIPT_SOURCE.to_string(),
+                    source_document_id: receipt_id,
+                    entry_date: request.receipt_date,
+                    description: Some(memo),
+                    reference: Some(receipt_number.clone()),
+                    event_type: LedgerEvent::GoodsReceived,
+                    vendor_group: po.vendor_group.clone(),
+                    lines: posting_lines,
+                },
+                user_id,
+                roles,
+            ).await?);
+        }
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "goods_receipts",
+            receipt_id,
+            "CREATE",
+            None,
+            Some(serde_json::json!({
+                "receipt_number": receipt_number,
+                "po_number": po.po_number,
+                "po_status": new_status.to_string(),
+                "posting_status": posting_status.to_string()
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Received goods {} against purchase order {}", receipt_number, po.po_number);
+
+        if let Some(outbox_id) = outbox_id {
+            self.ledger_outbox.dispatch(outbox_id).await?;
+        }
+        self.book_stock(Some(receipt_id), roles).await?;
+
+        self.get_receipt(receipt_id, company_id).await
+    }
+
+    /// Books the stock of receipt lines the inventory service has not
+    /// confirmed yet, e.g. because it was unreachable when the goods were
+    /// received. Run by the scheduler.
+    pub async fn book_pending_stock(&self) -> ServiceResult<usize> {
+        self.book_stock(None, &[]).await
+    }
+
+    /// Sends the stock moves of unbooked item lines, of one receipt or of
+    /// all, on behalf of the user who received them. Each move carries its
+    /// receipt line as key, so a line sent twice is booked once. Lines the
+    /// inventory service rejects are logged and stay unbooked.
+    async fn book_stock(&self, receipt_id: Option<Uuid>, roles: &[String]) -> ServiceResult<usize> {
+        let lines = sqlx::query!(
+            r#"
+            SELECT grl.id, grl.quantity, grl.unit_cost as "unit_cost!", pol.item_id as "item_id!",
+                   gr.company_id, gr.receipt_number, gr.receipt_date, gr.created_by, po.po_number
+            FROM goods_receipt_lines grl
+            JOIN goods_receipts gr ON gr.id = grl.receipt_id
+            JOIN purchase_order_lines pol ON pol.id = grl.po_line_id
+            JOIN purchase_orders po ON po.id = gr.purchase_order_id
+            WHERE grl.inventory_transaction_id IS NULL
+              AND grl.unit_cost IS NOT NULL
+              AND pol.item_id IS NOT NULL
+              AND ($1::uuid IS NULL OR grl.receipt_id = $1)
+            ORDER BY gr.created_at, pol.line_number
+            LIMIT $2
+            "#,
+            receipt_id,
+            STOCK_BATCH_SIZE
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut booked = 0;
+        for line in lines {
+            // The reference ties the stock move back to the receipt
+            let reference = format!("{} / {}", line.receipt_number, line.po_number);
+            let result = self.inventory_client.receive_stock(
+                line.company_id,
+                line.created_by,
+                roles,
+                line.id,
+                line.item_id,
+                line.receipt_date,
+                line.quantity,
+                line.unit_cost,
+                &reference,
+            ).await;
+
+            match result {
+                Ok(transaction_id) => {
+                    sqlx::query!(
+                        "UPDATE goods_receipt_lines SET inventory_transaction_id = $1 WHERE id = $2",
+                        transaction_id,
+                        line.id
+                    )
+                    .execute(&self.db)
+                    .await
+                    .map_err(ServiceError::Database)?;
+                    booked += 1;
+                }
+                Err(e) => tracing::warn!(
+                    "Stock for receipt {} line {} not booked yet: {}", line.receipt_number, line.id, e
+                ),
+            }
+        }
+
+        Ok(booked)
+    }
+
+    pub async fn get_receipts(&self, po_id: Uuid, company_id: Uuid) -> ServiceResult<Vec<GoodsReceiptWithLines>> {
+        let receipt_ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM goods_receipts
+            WHERE purchase_order_id = $1 AND company_id = $2
+            ORDER BY receipt_date, receipt_number
+            "#,
+            po_id,
+            company_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut receipts = Vec::with_capacity(receipt_ids.len());
+        for receipt_id in receipt_ids {
+            receipts.push(self.get_receipt(receipt_id, company_id).await?);
+        }
+
+        Ok(receipts)
+    }
+
+    pub async fn get_receipt(&self, receipt_id: Uuid, company_id: Uuid) -> ServiceResult<GoodsReceiptWithLines> {
+        let receipt = sqlx::query_as!(
+            GoodsReceipt,
+            r#"
+            SELECT id, company_id, purchase_order_id, receipt_number, receipt_date, notes,
+                   currency, exchange_rate, journal_entry_id, posting_status, created_by,
+                   created_at as "created_at!"
+            FROM goods_receipts
+            WHERE id = $1 AND company_id = $2
+            "#,
+            receipt_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Goods receipt not found".to_string()))?;
+
+        let lines = sqlx::query_as!(
+            GoodsReceiptLine,
+            r#"
+            SELECT grl.id, grl.receipt_id, grl.po_line_id, grl.quantity, grl.unit_cost, grl.inventory_transaction_id
+            FROM goods_receipt_lines grl
+            JOIN purchase_order_lines pol ON pol.id = grl.po_line_id
+            WHERE grl.receipt_id = $1
+            ORDER BY pol.line_number
+            "#,
+            receipt_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(GoodsReceiptWithLines { receipt, lines })
+    }
+
+    /// Lines of approved, partially and fully received orders with what is
+    /// still to be delivered and what has been received but not billed.
+    pub async fn get_open_po_report(
+        &self,
+        company_id: Uuid,
+        vendor_id: Option<Uuid>,
+    ) -> ServiceResult<OpenPurchaseOrderReport> {
+        let open_statuses: Vec<String> = OPEN_STATUSES.iter().map(|s| s.to_string()).collect();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT po.id as po_id, po.po_number, po.po_date, po.expected_delivery_date, po.vendor_id,
+                   v.vendor_name, po.status as "status!", pol.id as po_line_id, pol.line_number,
+                   pol.item_code, pol.description, pol.unit_price, pol.quantity, pol.received_quantity,
+                   COALESCE((
+                       SELECT SUM(vil.quantity)
+                       FROM vendor_invoice_lines vil
+                       JOIN vendor_invoices vi ON vi.id = vil.invoice_id
+                       WHERE vil.po_line_id = pol.id AND vi.status <> 'CANCELLED'
+                   ), 0) as "invoiced_quantity!"
+            FROM purchase_orders po
+            JOIN vendors v ON v.id = po.vendor_id
+            JOIN purchase_order_lines pol ON pol.purchase_order_id = po.id
+            WHERE po.company_id = $1
+              AND po.status = ANY($2)
+              AND ($3::uuid IS NULL OR po.vendor_id = $3)
+            ORDER BY po.po_date, po.po_number, pol.line_number
+            "#,
+            company_id,
+            &open_statuses,
+            vendor_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let lines: Vec<OpenPurchaseOrderLine> = rows
+            .into_iter()
+            .map(|row| {
+                let open_quantity = (row.quantity - row.received_quantity).max(Decimal::ZERO);
+
+                OpenPurchaseOrderLine {
+                    purchase_order_id: row.po_id,
+                    po_number: row.po_number,
+                    po_date: row.po_date,
+                    expected_delivery_date: row.expected_delivery_date,
+                    vendor_id: row.vendor_id,
+                    vendor_name: row.vendor_name,
+                    status: row.status,
+                    po_line_id: row.po_line_id,
+                    line_number: row.line_number,
+                    item_code: row.item_code,
+                    description: row.description,
+                    unit_price: row.unit_price,
+                    ordered_quantity: row.quantity,
+                    received_quantity: row.received_quantity,
+                    invoiced_quantity: row.invoiced_quantity,
+                    open_quantity,
+                    uninvoiced_quantity: (row.received_quantity - row.invoiced_quantity).max(Decimal::ZERO),
+                    open_amount: (open_quantity * row.unit_price).round_dp(2),
+                }
+            })
+            .collect();
+
+        Ok(OpenPurchaseOrderReport {
+            company_id,
+            report_date: chrono::Utc::now().date_naive(),
+            total_open_amount: lines.iter().map(|l| l.open_amount).sum(),
+            lines,
+            generated_at: chrono::Utc::now(),
+        })
+    }
+
+    async fn transition(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        po_id: Uuid,
+        company_id: Uuid,
+        from: &[PurchaseOrderStatus],
+        to: PurchaseOrderStatus,
+        user_id: Uuid,
+    ) -> ServiceResult<()> {
+        let po = sqlx::query!(
+            r#"
+            SELECT po_number, status as "status!"
+            FROM purchase_orders
+            WHERE id = $1 AND company_id = $2
+            FOR UPDATE
+            "#,
+            po_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Purchase order not found".to_string()))?;
+
+        let current = po.status.parse::<PurchaseOrderStatus>().map_err(ServiceError::Internal)?;
+        if !from.contains(&current) {
+            return Err(ServiceError::Validation(format!(
+                "Purchase order {} cannot go from {} to {}", po.po_number, current, to
+            )));
+        }
+
+        sqlx::query!(
+            "UPDATE purchase_orders SET status = $1, updated_at = NOW() WHERE id = $2",
+            to.to_string(),
+            po_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            tx,
+            "purchase_orders",
+            po_id,
+            "STATUS_CHANGE",
+            Some(serde_json::json!({ "status": current.to_string() })),
+            Some(serde_json::json!({ "status": to.to_string() })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Purchase order {} moved to {} by user {}", po.po_number, to, user_id);
+
+        Ok(())
+    }
+}
+
+/// Ledger lines for a goods receipt: each item line's cost debited to
+/// inventory and the total credited to goods received not invoiced, which
+/// the vendor invoice clears. Empty when nothing went into stock.
+fn receipt_posting_lines(stock: &[(String, Decimal)], memo: &str) -> Vec<LedgerPostingLine> {
+    let total: Decimal = stock.iter().map(|(_, cost)| *cost).sum();
+    if total.is_zero() {
+        return Vec::new();
+    }
+
+    let line = |role, description: &str, debit_amount, credit_amount| LedgerPostingLine {
+        account_id: None,
+        account_role: Some(role),
+        item_category: None,
+        tax_type: None,
+        description: Some(description.to_string()),
+        debit_amount,
+        credit_amount,
+        department: None,
+        project_code: None,
+        foreign: None,
+    };
+
+    let mut posting: Vec<LedgerPostingLine> = stock
+        .iter()
+        .filter(|(_, cost)| !cost.is_zero())
+        .map(|(description, cost)| line(LedgerAccountRole::Inventory, description, *cost, Decimal::ZERO))
+        .collect();
+    posting.push(line(LedgerAccountRole::GoodsReceivedNotInvoiced, memo, Decimal::ZERO, total));
+
+    posting
+}
+
+fn check_po_lines(lines: &[PurchaseOrderLineRequest]) -> Result<(), String> {
+    for (index, line) in lines.iter().enumerate() {
+        if line.quantity <= Decimal::ZERO {
+            return Err(format!("Line {}: quantity must be positive", index + 1));
+        }
+        if line.unit_price < Decimal::ZERO {
+            return Err(format!("Line {}: unit price cannot be negative", index + 1));
+        }
+        let tax_rate = line.tax_rate.unwrap_or(Decimal::ZERO);
+        if tax_rate < Decimal::ZERO || tax_rate > Decimal::ONE_HUNDRED {
+            return Err(format!("Line {}: tax rate must be between 0 and 100", index + 1));
+        }
+    }
+
+    Ok(())
+}
+
+/// Net amount and tax of a line, each rounded to the rupiah cent.
+fn line_amounts(quantity: Decimal, unit_price: Decimal, tax_rate: Decimal) -> (Decimal, Decimal) {
+    let line_amount = (quantity * unit_price).round_dp(2);
+    let tax_amount = (line_amount * tax_rate / Decimal::ONE_HUNDRED).round_dp(2);
+    (line_amount, tax_amount)
+}
+
+/// New received quantity per order line after a receipt. `ordered` holds
+/// (line id, ordered, already received); a line may appear in the receipt
+/// more than once but in total never beyond what is still open.
+fn apply_receipt(
+    ordered: &[(Uuid, Decimal, Decimal)],
+    receipt: &[GoodsReceiptLineRequest],
+) -> Result<HashMap<Uuid, Decimal>, String> {
+    let mut received: HashMap<Uuid, Decimal> = HashMap::new();
+
+    for line in receipt {
+        if line.quantity <= Decimal::ZERO {
+            return Err("Received quantity must be positive".to_string());
+        }
+
+        let (_, quantity, already) = ordered
+            .iter()
+            .find(|(id, _, _)| *id == line.po_line_id)
+            .ok_or_else(|| format!("Line {} is not on this purchase order", line.po_line_id))?;
+
+        let total = received.entry(line.po_line_id).or_insert(*already);
+        *total += line.quantity;
+        if *total > *quantity {
+            return Err(format!(
+                "Receiving {} more would bring line {} to {} of {} ordered",
+                line.quantity, line.po_line_id, total, quantity
+            ));
+        }
+    }
+
+    Ok(received)
+}
+
+/// Order status implied by (ordered, received) per line.
+fn status_after_receipt(lines: &[(Decimal, Decimal)]) -> PurchaseOrderStatus {
+    if lines.iter().all(|(ordered, received)| received >= ordered) {
+        PurchaseOrderStatus::Received
+    } else if lines.iter().any(|(_, received)| *received > Decimal::ZERO) {
+        PurchaseOrderStatus::PartiallyReceived
+    } else {
+        PurchaseOrderStatus::Approved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt_line(po_line_id: Uuid, quantity: i64) -> GoodsReceiptLineRequest {
+        GoodsReceiptLineRequest { po_line_id, quantity: Decimal::from(quantity) }
+    }
+
+    #[test]
+    fn test_line_amounts() {
+        let (amount, tax) = line_amounts(Decimal::new(25, 1), Decimal::from(10_000), Decimal::from(11));
+        assert_eq!(amount, Decimal::from(25_000));
+        assert_eq!(tax, Decimal::from(2_750));
+    }
+
+    #[test]
+    fn test_apply_receipt() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let ordered = vec![
+            (a, Decimal::from(10), Decimal::from(4)),
+            (b, Decimal::from(5), Decimal::ZERO),
+        ];
+
+        let received = apply_receipt(&ordered, &[receipt_line(a, 3), receipt_line(a, 3)]).unwrap();
+        assert_eq!(received[&a], Decimal::from(10));
+        assert!(!received.contains_key(&b));
+
+        assert!(apply_receipt(&ordered, &[receipt_line(a, 7)]).is_err());
+        assert!(apply_receipt(&ordered, &[receipt_line(b, 0)]).is_err());
+        assert!(apply_receipt(&ordered, &[receipt_line(Uuid::new_v4(), 1)]).is_err());
+
+        let status = |lines: &[(i64, i64)]| status_after_receipt(
+            &lines.iter().map(|(o, r)| (Decimal::from(*o), Decimal::from(*r))).collect::<Vec<_>>()
+        );
+        assert_eq!(status(&[(10, 10), (5, 5)]), PurchaseOrderStatus::Received);
+        assert_eq!(status(&[(10, 10), (5, 0)]), PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(status(&[(10, 0), (5, 0)]), PurchaseOrderStatus::Approved);
+    }
+
+    #[test]
+    fn test_receipt_posting_lines() {
+        let stock = vec![
+            ("Steel plate".to_string(), Decimal::from(1_550_050)),
+            ("Bolts".to_string(), Decimal::from(77_500)),
+        ];
+
+        let posting = receipt_posting_lines(&stock, "GRN-1");
+        assert_eq!(posting.len(), 3);
+        assert_eq!(posting[0].account_role, Some(LedgerAccountRole::Inventory));
+        assert_eq!(posting[0].debit_amount, Decimal::from(1_550_050));
+        assert_eq!(posting[2].account_role, Some(LedgerAccountRole::GoodsReceivedNotInvoiced));
+        assert_eq!(posting[2].credit_amount, Decimal::from(1_627_550));
+
+        // Receipts of service lines post nothing
+        assert!(receipt_posting_lines(&[], "GRN-2").is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError, identity::{self, IDENTITY_HEADER}};
+use uuid::Uuid;
+
+/// Reports the tax AP withholds to the Indonesian tax service, on behalf of
+/// the user making the payment.
+#[derive(Clone)]
+pub struct TaxClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl TaxClient {
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("INDONESIAN_TAX_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3005".to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Records a tax transaction and returns its id. The source document
+    /// doubles as idempotency key, so the tax service keeps one transaction
+    /// per source when a call is repeated after a lost response.
+    pub async fn record_transaction(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+        transaction: &TaxTransactionRequest,
+    ) -> ServiceResult<Uuid> {
+        let request = self.client
+            .post(format!("{}/tax-transactions", self.base_url))
+            .header(
+                "Idempotency-Key",
+                format!("{}:{}", transaction.source_document_type, transaction.source_document_id),
+            )
+            .json(transaction);
+
+        let response = self.send(request, company_id, user_id, roles).await?;
+
+        let transaction: RecordedTaxTransaction = response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to parse tax service response: {}", e)))?;
+
+        Ok(transaction.id)
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        company_id: Uuid,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<reqwest::Response> {
+        let response = request
+            .header("X-Company-ID", company_id.to_string())
+            .header("X-User-ID", user_id.to_string())
+            .header(IDENTITY_HEADER, identity::forward(user_id, company_id, roles)?)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to call tax service: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body.get("message").and_then(|m| m.as_str()).map(str::to_string))
+                .unwrap_or_else(|| format!("Tax service returned status {}", status));
+
+            return Err(match status.as_u16() {
+                400 => ServiceError::Validation(message),
+                403 => ServiceError::Authorization(message),
+                404 => ServiceError::NotFound(message),
+                409 => ServiceError::Conflict(message),
+                _ => ServiceError::ExternalService(message),
+            });
+        }
+
+        Ok(response)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::models::*;
 use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Transaction, Postgres};
 use uuid::Uuid;
 
@@ -52,9 +53,15 @@ impl VendorService {
         let vendor = sqlx::query_as!(
             Vendor,
             r#"
-            INSERT INTO vendors (id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, true, NOW(), NOW())
-            RETURNING id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+            INSERT INTO vendors (id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                                 bank_name, bank_account_number, bank_account_name,
+                                 early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                                 is_active, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, true, NOW(), NOW())
+            RETURNING id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                      bank_name, bank_account_number, bank_account_name,
+                      early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                      is_active, created_at, updated_at
             "#,
             vendor_id,
             company_id,
@@ -64,7 +71,14 @@ impl VendorService {
             request.address,
             request.phone,
             request.email,
-            request.payment_terms.unwrap_or(30)
+            request.payment_terms.unwrap_or(30),
+            request.vendor_group.as_deref().map(|g| g.trim().to_uppercase()),
+            request.bank_name,
+            request.bank_account_number,
+            request.bank_account_name,
+            request.early_payment_discount_percent.unwrap_or(Decimal::ZERO),
+            request.early_payment_discount_days.unwrap_or(0),
+            request.withholding_profile.unwrap_or_default().to_string()
         )
         .fetch_one(&mut *tx)
         .await
@@ -103,7 +117,10 @@ impl VendorService {
                 sqlx::query_as!(
                     Vendor,
                     r#"
-                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                           bank_name, bank_account_number, bank_account_name,
+                           early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                           is_active, created_at, updated_at
                     FROM vendors 
                     WHERE company_id = $1 
                       AND (vendor_name ILIKE $2 OR vendor_code ILIKE $2 OR COALESCE(npwp, '') ILIKE $2)
@@ -122,7 +139,10 @@ impl VendorService {
                 sqlx::query_as!(
                     Vendor,
                     r#"
-                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                           bank_name, bank_account_number, bank_account_name,
+                           early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                           is_active, created_at, updated_at
                     FROM vendors 
                     WHERE company_id = $1 AND is_active = true
                       AND (vendor_name ILIKE $2 OR vendor_code ILIKE $2 OR COALESCE(npwp, '') ILIKE $2)
@@ -141,7 +161,10 @@ impl VendorService {
                 sqlx::query_as!(
                     Vendor,
                     r#"
-                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                           bank_name, bank_account_number, bank_account_name,
+                           early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                           is_active, created_at, updated_at
                     FROM vendors 
                     WHERE company_id = $1
                     ORDER BY vendor_name
@@ -158,7 +181,10 @@ impl VendorService {
                 sqlx::query_as!(
                     Vendor,
                     r#"
-                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+                    SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                           bank_name, bank_account_number, bank_account_name,
+                           early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                           is_active, created_at, updated_at
                     FROM vendors 
                     WHERE company_id = $1 AND is_active = true
                     ORDER BY vendor_name
@@ -184,7 +210,10 @@ impl VendorService {
         let vendor = sqlx::query_as!(
             Vendor,
             r#"
-            SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+            SELECT id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                   bank_name, bank_account_number, bank_account_name,
+                   early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                   is_active, created_at, updated_at
             FROM vendors 
             WHERE id = $1 AND company_id = $2
             "#,
@@ -223,9 +252,16 @@ impl VendorService {
             r#"
             UPDATE vendors 
             SET vendor_name = $1, npwp = $2, address = $3, phone = $4, email = $5, 
-                payment_terms = $6, is_active = $7, updated_at = NOW()
+                payment_terms = $6, is_active = $7, vendor_group = $10,
+                bank_name = $11, bank_account_number = $12, bank_account_name = $13,
+                early_payment_discount_percent = $14, early_payment_discount_days = $15,
+                withholding_profile = $16,
+                updated_at = NOW()
             WHERE id = $8 AND company_id = $9
-            RETURNING id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, is_active, created_at, updated_at
+            RETURNING id, company_id, vendor_code, vendor_name, npwp, address, phone, email, payment_terms, vendor_group,
+                      bank_name, bank_account_number, bank_account_name,
+                      early_payment_discount_percent, early_payment_discount_days, withholding_profile,
+                      is_active, created_at, updated_at
             "#,
             request.vendor_name,
             request.npwp,
@@ -235,7 +271,14 @@ impl VendorService {
             request.payment_terms,
             request.is_active,
             vendor_id,
-            company_id
+            company_id,
+            request.vendor_group.as_deref().map(|g| g.trim().to_uppercase()),
+            request.bank_name,
+            request.bank_account_number,
+            request.bank_account_name,
+            request.early_payment_discount_percent,
+            request.early_payment_discount_days,
+            request.withholding_profile.to_string()
         )
         .fetch_optional(&mut *tx)
         .await
@@ -342,13 +385,47 @@ impl VendorService {
         .await
         .map_err(ServiceError::Database)?;
 
+        let credits = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(SUM(total_amount), 0) as "total_credit_amount!",
+                COALESCE(SUM(total_amount - applied_amount - refunded_amount), 0) as "unapplied_credit_amount!"
+            FROM vendor_credit_memos
+            WHERE vendor_id = $1 AND company_id = $2 AND status IN ('APPROVED', 'CLOSED')
+            "#,
+            vendor_id,
+            company_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let advances = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(amount - applied_amount - refunded_amount), 0) as "open_advance_amount!"
+            FROM vendor_advances
+            WHERE vendor_id = $1 AND company_id = $2 AND status = 'OPEN'
+            "#,
+            vendor_id,
+            company_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let outstanding_amount = stats.outstanding_amount.unwrap_or_default();
+
         Ok(VendorStatistics {
             vendor_id,
             total_invoices: stats.total_invoices.unwrap_or(0) as u32,
             total_amount: stats.total_amount.unwrap_or_default(),
             paid_amount: stats.paid_amount.unwrap_or_default(),
-            outstanding_amount: stats.outstanding_amount.unwrap_or_default(),
+            outstanding_amount,
             average_invoice_amount: stats.average_invoice_amount.unwrap_or_default(),
+            total_credit_amount: credits.total_credit_amount,
+            unapplied_credit_amount: credits.unapplied_credit_amount,
+            net_outstanding_amount: outstanding_amount - credits.unapplied_credit_amount,
+            open_advance_amount: advances.open_advance_amount,
         })
     }
 
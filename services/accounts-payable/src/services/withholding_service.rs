@@ -0,0 +1,378 @@
+use crate::models::*;
+use super::TaxClient;
+use chrono::{Datelike, NaiveDate};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Tax type of the withholding slips AP issues.
+const PPH23: &str = "PPH23";
+
+/// Source document type of the tax transaction recorded for a slip.
+const SLIP_SOURCE: &str = "WITHHOLDING_SLIP";
+
+/// Source document type of the tax transaction offsetting a cancelled slip.
+const SLIP_CANCELLATION_SOURCE: &str = "WITHHOLDING_SLIP_CANCELLATION";
+
+/// Slips reported per run of `report_pending_slips`.
+const REPORT_BATCH_SIZE: i64 = 100;
+
+/// Bukti potong for the income tax withheld from vendor payments, and their
+/// tax transactions in the tax service.
+pub struct WithholdingService {
+    db: PgPool,
+    tax_client: TaxClient,
+}
+
+/// Tax withheld from one payment.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Withholding {
+    pub rate: Decimal,
+    pub base_amount: Decimal,
+    pub tax_amount: Decimal,
+}
+
+/// A payment's withholding, in rupiah, to issue a slip for.
+pub(crate) struct NewWithholdingSlip<'a> {
+    pub company_id: Uuid,
+    pub payment_id: Uuid,
+    pub invoice_id: Uuid,
+    pub vendor_id: Uuid,
+    pub invoice_number: &'a str,
+    pub vendor_name: &'a str,
+    pub vendor_npwp: Option<&'a str>,
+    pub withholding_date: NaiveDate,
+    pub withholding: Withholding,
+}
+
+impl WithholdingService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db, tax_client: TaxClient::from_env() }
+    }
+
+    /// Numbers and stores the slip for a payment. The slip is reported to
+    /// the tax service by `report_slips` once the payment is saved. Returns
+    /// the slip number.
+    pub(crate) async fn issue_slip(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        slip: NewWithholdingSlip<'_>,
+        user_id: Uuid,
+    ) -> ServiceResult<String> {
+        let slip_number = database::numbering::next_number(
+            &mut **tx,
+            slip.company_id,
+            database::numbering::DocumentType::WithholdingSlip,
+            slip.withholding_date,
+        ).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO withholding_slips (
+                id, company_id, slip_number, payment_id, invoice_id, vendor_id, tax_type,
+                withholding_date, vendor_npwp, vendor_name, tax_base_amount, tax_rate, tax_amount,
+                status, created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'ISSUED', $14, NOW())
+            "#,
+            Uuid::new_v4(),
+            slip.company_id,
+            slip_number,
+            slip.payment_id,
+            slip.invoice_id,
+            slip.vendor_id,
+            PPH23,
+            slip.withholding_date,
+            slip.vendor_npwp,
+            slip.vendor_name,
+            slip.withholding.base_amount,
+            slip.withholding.rate,
+            slip.withholding.tax_amount,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(slip_number)
+    }
+
+    /// Cancels the slip of a reversed payment. A slip the tax service has
+    /// recorded is offset there by `report_slips` once the reversal is
+    /// saved. Returns the slip number, if the payment had one.
+    pub(crate) async fn cancel_slip(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        payment_id: Uuid,
+        company_id: Uuid,
+        reason: &str,
+        user_id: Uuid,
+    ) -> ServiceResult<Option<String>> {
+        sqlx::query_scalar!(
+            r#"
+            UPDATE withholding_slips
+            SET status = 'CANCELLED', cancelled_at = NOW(), cancelled_by = $3, cancellation_reason = $4
+            WHERE payment_id = $1 AND company_id = $2 AND status = 'ISSUED'
+            RETURNING slip_number
+            "#,
+            payment_id,
+            company_id,
+            user_id,
+            reason
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// Reports slips the tax service has not recorded yet, e.g. because it
+    /// was unreachable when the payment was made. Run by the scheduler.
+    pub async fn report_pending_slips(&self) -> ServiceResult<usize> {
+        self.report_slips(None, &[]).await
+    }
+
+    /// Records issued slips with the tax service, and offsets cancelled
+    /// slips it had recorded, of one payment or of all, on behalf of the
+    /// user who issued or cancelled them. Each transaction carries the slip
+    /// as its source document and idempotency key, so a slip reported twice
+    /// is recorded once. Failures are logged and retried on the next run.
+    pub(crate) async fn report_slips(&self, payment_id: Option<Uuid>, roles: &[String]) -> ServiceResult<usize> {
+        let slips = sqlx::query!(
+            r#"
+            SELECT ws.id, ws.company_id, ws.slip_number, ws.withholding_date, ws.vendor_npwp, ws.vendor_name,
+                   ws.tax_base_amount, ws.tax_amount, ws.status, ws.created_by, ws.cancelled_by,
+                   ws.cancelled_at, ws.cancellation_reason, vi.invoice_number,
+                   vp.journal_entry_id, vp.reversal_journal_entry_id
+            FROM withholding_slips ws
+            JOIN vendor_invoices vi ON vi.id = ws.invoice_id
+            JOIN vendor_payments vp ON vp.id = ws.payment_id
+            WHERE ($1::uuid IS NULL OR ws.payment_id = $1)
+              AND ((ws.status = 'ISSUED' AND ws.tax_reported_at IS NULL)
+                   OR (ws.status = 'CANCELLED' AND ws.tax_reported_at IS NOT NULL
+                       AND ws.cancellation_reported_at IS NULL))
+            ORDER BY ws.created_at
+            LIMIT $2
+            "#,
+            payment_id,
+            REPORT_BATCH_SIZE
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut reported = 0;
+        for slip in slips {
+            // Reported in the period withheld; a cancellation corrects it
+            let tax_period = slip.withholding_date.with_day(1).unwrap_or(slip.withholding_date);
+            let cancelled = slip.status == "CANCELLED";
+
+            let (request, user_id) = if cancelled {
+                let cancelled_on = slip.cancelled_at.map(|at| at.date_naive()).unwrap_or(slip.withholding_date);
+                (
+                    TaxTransactionRequest {
+                        tax_type: PPH23.to_string(),
+                        transaction_date: cancelled_on,
+                        tax_period,
+                        tax_base_amount: -slip.tax_base_amount,
+                        tax_amount: -slip.tax_amount,
+                        tax_invoice_number: Some(slip.slip_number.clone()),
+                        vendor_npwp: slip.vendor_npwp.clone(),
+                        vendor_name: Some(slip.vendor_name.clone()),
+                        description: Some(format!(
+                            "Pembatalan PPh 23 {} - {}: {}",
+                            slip.invoice_number,
+                            slip.vendor_name,
+                            slip.cancellation_reason.as_deref().unwrap_or_default()
+                        )),
+                        journal_entry_id: slip.reversal_journal_entry_id,
+                        source_document_type: SLIP_CANCELLATION_SOURCE.to_string(),
+                        source_document_id: slip.id,
+                    },
+                    slip.cancelled_by.unwrap_or(slip.created_by),
+                )
+            } else {
+                (
+                    TaxTransactionRequest {
+                        tax_type: PPH23.to_string(),
+                        transaction_date: slip.withholding_date,
+                        tax_period,
+                        tax_base_amount: slip.tax_base_amount,
+                        tax_amount: slip.tax_amount,
+                        tax_invoice_number: Some(slip.slip_number.clone()),
+                        vendor_npwp: slip.vendor_npwp.clone(),
+                        vendor_name: Some(slip.vendor_name.clone()),
+                        description: Some(format!("PPh 23 {} - {}", slip.invoice_number, slip.vendor_name)),
+                        journal_entry_id: slip.journal_entry_id,
+                        source_document_type: SLIP_SOURCE.to_string(),
+                        source_document_id: slip.id,
+                    },
+                    slip.created_by,
+                )
+            };
+
+            // A conflict means the tax service already holds the transaction
+            let tax_transaction_id = match self.tax_client.record_transaction(slip.company_id, user_id, roles, &request).await {
+                Ok(id) => Some(id),
+                Err(ServiceError::Conflict(_)) => None,
+                Err(e) => {
+                    tracing::warn!("Withholding slip {} not reported to the tax service yet: {}", slip.slip_number, e);
+                    continue;
+                }
+            };
+
+            let result = if cancelled {
+                sqlx::query!(
+                    r#"
+                    UPDATE withholding_slips
+                    SET reversal_tax_transaction_id = COALESCE($1, reversal_tax_transaction_id),
+                        cancellation_reported_at = NOW()
+                    WHERE id = $2
+                    "#,
+                    tax_transaction_id,
+                    slip.id
+                )
+                .execute(&self.db)
+                .await
+            } else {
+                sqlx::query!(
+                    r#"
+                    UPDATE withholding_slips
+                    SET tax_transaction_id = COALESCE($1, tax_transaction_id), tax_reported_at = NOW()
+                    WHERE id = $2
+                    "#,
+                    tax_transaction_id,
+                    slip.id
+                )
+                .execute(&self.db)
+                .await
+            };
+            result.map_err(ServiceError::Database)?;
+            reported += 1;
+        }
+
+        Ok(reported)
+    }
+
+    pub async fn get_slips(
+        &self,
+        company_id: Uuid,
+        vendor_id: Option<Uuid>,
+        date_from: Option<NaiveDate>,
+        date_to: Option<NaiveDate>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<WithholdingSlip>> {
+        sqlx::query_as!(
+            WithholdingSlip,
+            r#"
+            SELECT ws.id, ws.company_id, ws.slip_number, ws.payment_id, vp.payment_number,
+                   ws.invoice_id, vi.invoice_number, ws.vendor_id, ws.tax_type, ws.withholding_date,
+                   ws.vendor_npwp, ws.vendor_name, ws.tax_base_amount, ws.tax_rate, ws.tax_amount,
+                   ws.tax_transaction_id, ws.tax_reported_at, ws.status, ws.cancelled_at, ws.created_by, ws.created_at
+            FROM withholding_slips ws
+            JOIN vendor_payments vp ON vp.id = ws.payment_id
+            JOIN vendor_invoices vi ON vi.id = ws.invoice_id
+            WHERE ws.company_id = $1
+              AND ($2::uuid IS NULL OR ws.vendor_id = $2)
+              AND ($3::date IS NULL OR ws.withholding_date >= $3)
+              AND ($4::date IS NULL OR ws.withholding_date <= $4)
+            ORDER BY ws.withholding_date DESC, ws.slip_number DESC
+            LIMIT $5 OFFSET $6
+            "#,
+            company_id,
+            vendor_id,
+            date_from,
+            date_to,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_slip(&self, slip_id: Uuid, company_id: Uuid) -> ServiceResult<WithholdingSlip> {
+        sqlx::query_as!(
+            WithholdingSlip,
+            r#"
+            SELECT ws.id, ws.company_id, ws.slip_number, ws.payment_id, vp.payment_number,
+                   ws.invoice_id, vi.invoice_number, ws.vendor_id, ws.tax_type, ws.withholding_date,
+                   ws.vendor_npwp, ws.vendor_name, ws.tax_base_amount, ws.tax_rate, ws.tax_amount,
+                   ws.tax_transaction_id, ws.tax_reported_at, ws.status, ws.cancelled_at, ws.created_by, ws.created_at
+            FROM withholding_slips ws
+            JOIN vendor_payments vp ON vp.id = ws.payment_id
+            JOIN vendor_invoices vi ON vi.id = ws.invoice_id
+            WHERE ws.id = $1 AND ws.company_id = $2
+            "#,
+            slip_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Withholding slip not found".to_string()))
+    }
+}
+
+/// Tax withheld from a payment of `payment_amount` on an invoice. PPh 23 is
+/// 2% of the payment's share of the invoice amount before VAT, or 4% when
+/// the vendor has no NPWP.
+pub(crate) fn withholding_for(
+    profile: WithholdingProfile,
+    vendor_npwp: Option<&str>,
+    payment_amount: Decimal,
+    subtotal: Decimal,
+    total_amount: Decimal,
+) -> Withholding {
+    if profile == WithholdingProfile::None || total_amount <= Decimal::ZERO {
+        return Withholding::default();
+    }
+
+    let has_npwp = vendor_npwp.is_some_and(|npwp| npwp.chars().any(|c| c.is_ascii_digit()));
+    let rate = if has_npwp { Decimal::from(2) } else { Decimal::from(4) };
+
+    let base_amount = if subtotal >= total_amount {
+        payment_amount
+    } else {
+        (payment_amount * subtotal / total_amount).round_dp(2)
+    };
+
+    Withholding {
+        rate,
+        base_amount,
+        tax_amount: (base_amount * rate / Decimal::ONE_HUNDRED).round_dp(2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_withholding_for() {
+        let npwp = Some("01.234.567.8-901.234");
+
+        // 2% of the amount before 11% VAT
+        let full = withholding_for(WithholdingProfile::Pph23, npwp, Decimal::from(11_100_000), Decimal::from(10_000_000), Decimal::from(11_100_000));
+        assert_eq!(full.rate, Decimal::from(2));
+        assert_eq!(full.base_amount, Decimal::from(10_000_000));
+        assert_eq!(full.tax_amount, Decimal::from(200_000));
+
+        // A partial payment withholds on its share, doubled without an NPWP
+        let partial = withholding_for(WithholdingProfile::Pph23, Some(" "), Decimal::from(5_550_000), Decimal::from(10_000_000), Decimal::from(11_100_000));
+        assert_eq!(partial.rate, Decimal::from(4));
+        assert_eq!(partial.base_amount, Decimal::from(5_000_000));
+        assert_eq!(partial.tax_amount, Decimal::from(200_000));
+
+        // Invoices without VAT withhold on the whole payment
+        let no_vat = withholding_for(WithholdingProfile::Pph23, None, Decimal::new(100_005, 2), Decimal::new(100_005, 2), Decimal::new(100_005, 2));
+        assert_eq!(no_vat.base_amount, Decimal::new(100_005, 2));
+        assert_eq!(no_vat.tax_amount, Decimal::new(4_000, 2));
+
+        // Vendors without a profile have nothing withheld
+        assert_eq!(
+            withholding_for(WithholdingProfile::None, npwp, Decimal::from(1_000), Decimal::from(1_000), Decimal::from(1_000)),
+            Withholding::default()
+        );
+    }
+}
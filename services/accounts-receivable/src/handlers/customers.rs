@@ -79,10 +79,58 @@ pub async fn get_customer_credit_info(
     Path(customer_id): Path<Uuid>,
 ) -> ServiceResult<Json<CustomerCreditInfo>> {
     let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
     
     let credit_info = state.customer_service
-        .get_customer_credit_info(customer_id, company_id)
+        .get_customer_credit_info(customer_id, company_id, user_id)
         .await?;
     
     Ok(Json(credit_info))
+}
+
+pub async fn get_customer_payments(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<CustomerPayment>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let ids = params.get("ids")
+        .map(|ids| {
+            ids.split(',')
+                .filter(|id| !id.trim().is_empty())
+                .map(|id| Uuid::parse_str(id.trim())
+                    .map_err(|_| common::ServiceError::Validation(format!("Invalid payment id: {}", id))))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let filters = PaymentFilters {
+        bank_account_id: params.get("bank_account_id").and_then(|id| Uuid::parse_str(id).ok()),
+        date_from: params.get("date_from")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        date_to: params.get("date_to")
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        ids,
+    };
+
+    let payments = state.customer_service
+        .get_payments(company_id, filters)
+        .await?;
+
+    Ok(Json(payments))
+}
+
+pub async fn get_subledger_balance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<SubledgerBalance>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let balance = state.aging_service
+        .get_subledger_balance(company_id)
+        .await?;
+
+    Ok(Json(balance))
 }
\ No newline at end of file
@@ -56,7 +56,9 @@ async fn main() -> anyhow::Result<()> {
         .route("/invoices/:id/status", put(update_invoice_status))
         .route("/invoices/:id/payment", put(receive_payment))
         .route("/invoices/:id/payments", get(get_payment_history))
+        .route("/payments", get(get_customer_payments))
         .route("/aging-report", get(get_customer_aging_report))
+        .route("/subledger-balance", get(get_subledger_balance))
         .route("/credit-limit-check", post(check_credit_limit))
         .with_state(app_state);
 
@@ -86,11 +86,15 @@ pub struct CustomerPayment {
     pub id: Uuid,
     pub invoice_id: Uuid,
     pub company_id: Uuid,
+    pub payment_number: String,
     pub payment_amount: Decimal,
     pub payment_date: NaiveDate,
     pub payment_method: String,
     pub bank_account_id: Option<Uuid>,
     pub payment_reference: Option<String>,
+    pub check_number: Option<String>,
+    pub is_reversed: bool,
+    pub journal_entry_id: Option<Uuid>,
     pub created_by: Uuid,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
@@ -162,12 +166,24 @@ pub struct PaymentRequest {
     pub payment_reference: Option<String>,
 }
 
+/// Open receivables in the functional currency at the rates they were
+/// booked at, for reconciling the ledger's AR control account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubledgerBalance {
+    pub company_id: Uuid,
+    pub open_invoices: Decimal,
+    pub outstanding: Decimal,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomerAgingReport {
     pub company_id: Uuid,
     pub report_date: NaiveDate,
     pub summary: AgingSummary,
     pub customer_details: Vec<CustomerAgingDetail>,
+    /// Currencies with no rate on the report date; their invoices are
+    /// listed but not included in any total
+    pub unrated_currencies: Vec<String>,
     pub generated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -203,6 +219,8 @@ pub struct InvoiceAgingItem {
     pub due_date: NaiveDate,
     pub days_overdue: i32,
     pub outstanding_amount: Decimal,
+    /// Set when no rate was available; the amount is then in this currency
+    pub unrated_currency: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -224,4 +242,12 @@ pub struct InvoiceFilters {
     pub customer_id: Option<Uuid>,
     pub date_from: Option<NaiveDate>,
     pub date_to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentFilters {
+    pub bank_account_id: Option<Uuid>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub ids: Vec<Uuid>,
 }
\ No newline at end of file
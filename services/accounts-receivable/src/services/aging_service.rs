@@ -1,6 +1,8 @@
 use crate::models::*;
+use super::{customer_service, LedgerClient};
 use common::{ServiceResult, ServiceError};
 use chrono::NaiveDate;
+use database::exchange_rates::{distinct_currencies, RateType};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -8,16 +10,24 @@ use uuid::Uuid;
 
 pub struct AgingService {
     db: PgPool,
+    ledger_client: LedgerClient,
 }
 
 impl AgingService {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self {
+            db,
+            ledger_client: LedgerClient::from_env(),
+        }
     }
 
+    /// Foreign-currency invoices are aged in the functional currency at the
+    /// ledger's BI middle rate on the report date. Invoices in a currency
+    /// without a rate are listed as unrated and left out of the totals.
     pub async fn generate_customer_aging_report(
         &self,
         company_id: Uuid,
+        user_id: Uuid,
         as_of_date: Option<NaiveDate>,
     ) -> ServiceResult<CustomerAgingReport> {
         let report_date = as_of_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
@@ -33,6 +43,7 @@ impl AgingService {
                 ci.invoice_date,
                 ci.due_date,
                 ci.total_amount - ci.paid_amount as outstanding_amount,
+                ci.currency,
                 $2 - ci.due_date as days_overdue
             FROM customer_invoices ci
             JOIN customers c ON ci.customer_id = c.id
@@ -49,6 +60,12 @@ impl AgingService {
         .await
         .map_err(ServiceError::Database)?;
 
+        let currencies = distinct_currencies(aging_data.iter().filter_map(|row| row.currency.as_deref()));
+        let rates = self.ledger_client
+            .rate_table(company_id, user_id, &currencies, RateType::BiMiddle, report_date)
+            .await?;
+        let mut unrated_currencies = Vec::new();
+
         let mut customer_details: HashMap<Uuid, CustomerAgingDetail> = HashMap::new();
         let mut summary = AgingSummary {
             current: Decimal::ZERO,
@@ -60,8 +77,22 @@ impl AgingService {
         };
 
         for row in aging_data {
-            let outstanding = row.outstanding_amount.unwrap_or(Decimal::ZERO);
             let days_overdue = row.days_overdue.unwrap_or(0);
+            let foreign_outstanding = row.outstanding_amount.unwrap_or(Decimal::ZERO);
+            let Some(outstanding) = rates.to_functional(foreign_outstanding, row.currency.as_deref()) else {
+                unrated_currencies.extend(row.currency.clone());
+                customer_entry(&mut customer_details, row.customer_id, &row.customer_name, row.credit_limit)
+                    .invoices.push(InvoiceAgingItem {
+                        invoice_id: row.invoice_id,
+                        invoice_number: row.invoice_number,
+                        invoice_date: row.invoice_date,
+                        due_date: row.due_date,
+                        days_overdue,
+                        outstanding_amount: foreign_outstanding,
+                        unrated_currency: row.currency,
+                    });
+                continue;
+            };
             
             // Update summary
             summary.total_outstanding += outstanding;
@@ -75,20 +106,7 @@ impl AgingService {
             }
 
             // Update customer detail
-            let customer_detail = customer_details.entry(row.customer_id).or_insert_with(|| {
-                CustomerAgingDetail {
-                    customer_id: row.customer_id,
-                    customer_name: row.customer_name.clone(),
-                    credit_limit: row.credit_limit,
-                    current: Decimal::ZERO,
-                    days_31_60: Decimal::ZERO,
-                    days_61_90: Decimal::ZERO,
-                    over_90_days: Decimal::ZERO,
-                    total_outstanding: Decimal::ZERO,
-                    credit_utilization: 0.0,
-                    invoices: Vec::new(),
-                }
-            });
+            let customer_detail = customer_entry(&mut customer_details, row.customer_id, &row.customer_name, row.credit_limit);
 
             customer_detail.total_outstanding += outstanding;
             match days_overdue {
@@ -105,6 +123,7 @@ impl AgingService {
                 due_date: row.due_date,
                 days_overdue,
                 outstanding_amount: outstanding,
+                unrated_currency: None,
             });
         }
 
@@ -122,6 +141,7 @@ impl AgingService {
             report_date,
             summary,
             customer_details: customer_details.into_values().collect(),
+            unrated_currencies: distinct_currencies(unrated_currencies.iter().map(String::as_str)),
             generated_at: chrono::Utc::now(),
         };
 
@@ -131,24 +151,40 @@ impl AgingService {
         Ok(report)
     }
 
+    /// What the AR control account should hold before revaluation: approved
+    /// invoices at their booked rates.
+    pub async fn get_subledger_balance(&self, company_id: Uuid) -> ServiceResult<SubledgerBalance> {
+        let open_invoices = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM((total_amount - COALESCE(paid_amount, 0)) * COALESCE(exchange_rate, 1)), 0) as "outstanding!"
+            FROM customer_invoices
+            WHERE company_id = $1 AND status IN ('APPROVED', 'PAID')
+            "#,
+            company_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .round_dp(2);
+
+        Ok(SubledgerBalance {
+            company_id,
+            open_invoices,
+            outstanding: open_invoices,
+        })
+    }
+
+    /// Balances are valued at today's BI middle rate from the ledger.
     pub async fn get_customers_over_credit_limit(
         &self,
         company_id: Uuid,
+        user_id: Uuid,
     ) -> ServiceResult<Vec<CustomerCreditInfo>> {
-        let results = sqlx::query!(
+        let customers = sqlx::query!(
             r#"
-            SELECT 
-                c.id,
-                c.customer_name,
-                c.credit_limit,
-                COALESCE(SUM(ci.total_amount - ci.paid_amount), 0) as current_outstanding
-            FROM customers c
-            LEFT JOIN customer_invoices ci ON c.id = ci.customer_id 
-                AND ci.status != 'CANCELLED' AND ci.total_amount > ci.paid_amount
-            WHERE c.company_id = $1 AND c.is_active = true
-            GROUP BY c.id, c.customer_name, c.credit_limit
-            HAVING c.credit_limit > 0 AND COALESCE(SUM(ci.total_amount - ci.paid_amount), 0) > c.credit_limit
-            ORDER BY (COALESCE(SUM(ci.total_amount - ci.paid_amount), 0) - c.credit_limit) DESC
+            SELECT id, customer_name, credit_limit
+            FROM customers
+            WHERE company_id = $1 AND is_active = true AND credit_limit > 0
             "#,
             company_id
         )
@@ -156,10 +192,17 @@ impl AgingService {
         .await
         .map_err(ServiceError::Database)?;
 
+        let balances = customer_service::open_balances(
+            &self.db, &self.ledger_client, company_id, user_id, None, chrono::Utc::now().date_naive()
+        ).await?;
+
         let mut customers_over_limit = Vec::new();
 
-        for row in results {
-            let current_outstanding = row.current_outstanding.unwrap_or(Decimal::ZERO);
+        for row in customers {
+            let current_outstanding = balances.get(&row.id).copied().unwrap_or(Decimal::ZERO);
+            if current_outstanding <= row.credit_limit {
+                continue;
+            }
             let available_credit = row.credit_limit - current_outstanding;
             let credit_utilization = if row.credit_limit > Decimal::ZERO {
                 (current_outstanding / row.credit_limit * Decimal::new(100, 0))
@@ -181,6 +224,29 @@ impl AgingService {
             });
         }
 
+        // Furthest over the limit first
+        customers_over_limit.sort_by(|a, b| a.available_credit.cmp(&b.available_credit));
+
         Ok(customers_over_limit)
     }
+}
+
+fn customer_entry<'a>(
+    customer_details: &'a mut HashMap<Uuid, CustomerAgingDetail>,
+    customer_id: Uuid,
+    customer_name: &str,
+    credit_limit: Decimal,
+) -> &'a mut CustomerAgingDetail {
+    customer_details.entry(customer_id).or_insert_with(|| CustomerAgingDetail {
+        customer_id,
+        customer_name: customer_name.to_string(),
+        credit_limit,
+        current: Decimal::ZERO,
+        days_31_60: Decimal::ZERO,
+        days_61_90: Decimal::ZERO,
+        over_90_days: Decimal::ZERO,
+        total_outstanding: Decimal::ZERO,
+        credit_utilization: 0.0,
+        invoices: Vec::new(),
+    })
 }
\ No newline at end of file
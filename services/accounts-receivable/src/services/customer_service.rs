@@ -1,18 +1,23 @@
 use crate::models::*;
+use super::LedgerClient;
+use chrono::NaiveDate;
 use common::{ServiceResult, ServiceError, PaginationParams};
+use database::exchange_rates::{distinct_currencies, RateType};
 use rust_decimal::Decimal;
 use sqlx::{PgPool, Transaction, Postgres};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 pub struct CustomerService {
     db: PgPool,
     audit_logger: database::audit::AuditLogger,
+    ledger_client: LedgerClient,
 }
 
 impl CustomerService {
     pub fn new(db: PgPool) -> Self {
         let audit_logger = database::audit::AuditLogger::new(db.clone());
-        Self { db, audit_logger }
+        Self { db, audit_logger, ledger_client: LedgerClient::from_env() }
     }
 
     pub async fn create_customer(
@@ -201,23 +206,17 @@ impl CustomerService {
         Ok(customer)
     }
 
+    /// Outstanding balances of foreign-currency invoices count at today's BI
+    /// middle rate from the ledger.
     pub async fn check_credit_limit(
         &self,
         customer_id: Uuid,
         company_id: Uuid,
+        user_id: Uuid,
         additional_amount: Decimal,
     ) -> ServiceResult<bool> {
         let result = sqlx::query!(
-            r#"
-            SELECT 
-                c.credit_limit,
-                COALESCE(SUM(ci.total_amount - ci.paid_amount), 0) as current_outstanding
-            FROM customers c
-            LEFT JOIN customer_invoices ci ON c.id = ci.customer_id 
-                AND ci.status != 'CANCELLED' AND ci.total_amount > ci.paid_amount
-            WHERE c.id = $1 AND c.company_id = $2
-            GROUP BY c.id, c.credit_limit
-            "#,
+            "SELECT credit_limit FROM customers WHERE id = $1 AND company_id = $2",
             customer_id,
             company_id
         )
@@ -226,7 +225,10 @@ impl CustomerService {
         .map_err(ServiceError::Database)?;
 
         if let Some(row) = result {
-            let current_outstanding = row.current_outstanding.unwrap_or(Decimal::ZERO);
+            let balances = open_balances(
+                &self.db, &self.ledger_client, company_id, user_id, Some(customer_id), chrono::Utc::now().date_naive()
+            ).await?;
+            let current_outstanding = balances.get(&customer_id).copied().unwrap_or(Decimal::ZERO);
             let available_credit = row.credit_limit - current_outstanding;
             Ok(available_credit >= additional_amount)
         } else {
@@ -234,17 +236,51 @@ impl CustomerService {
         }
     }
 
+    /// Customer payments, including reversed ones, for bank reconciliation
+    /// in the ledger. Payments without a bank account are included when
+    /// filtering by account.
+    pub async fn get_payments(
+        &self,
+        company_id: Uuid,
+        filters: PaymentFilters,
+    ) -> ServiceResult<Vec<CustomerPayment>> {
+        sqlx::query_as!(
+            CustomerPayment,
+            r#"
+            SELECT 
+                id, invoice_id, company_id, payment_number, payment_amount, payment_date,
+                payment_method, bank_account_id, payment_reference, check_number,
+                is_reversed as "is_reversed!", journal_entry_id, created_by, created_at
+            FROM customer_payments
+            WHERE company_id = $1
+              AND ($2::uuid IS NULL OR bank_account_id = $2 OR bank_account_id IS NULL)
+              AND ($3::date IS NULL OR payment_date >= $3)
+              AND ($4::date IS NULL OR payment_date <= $4)
+              AND (cardinality($5::uuid[]) = 0 OR id = ANY($5))
+            ORDER BY payment_date, payment_number
+            "#,
+            company_id,
+            filters.bank_account_id,
+            filters.date_from,
+            filters.date_to,
+            &filters.ids
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
     pub async fn get_customer_credit_info(
         &self,
         customer_id: Uuid,
         company_id: Uuid,
+        user_id: Uuid,
     ) -> ServiceResult<CustomerCreditInfo> {
         let result = sqlx::query!(
             r#"
             SELECT 
                 c.credit_limit,
                 c.customer_name,
-                COALESCE(SUM(ci.total_amount - ci.paid_amount), 0) as current_outstanding,
                 COUNT(ci.id) FILTER (WHERE ci.status != 'CANCELLED' AND ci.total_amount > ci.paid_amount) as outstanding_invoices,
                 MAX(ci.due_date) FILTER (WHERE ci.status != 'PAID' AND ci.status != 'CANCELLED') as oldest_due_date
             FROM customers c
@@ -260,7 +296,10 @@ impl CustomerService {
         .map_err(ServiceError::Database)?
         .ok_or_else(|| ServiceError::NotFound("Customer not found".to_string()))?;
 
-        let current_outstanding = result.current_outstanding.unwrap_or(Decimal::ZERO);
+        let balances = open_balances(
+            &self.db, &self.ledger_client, company_id, user_id, Some(customer_id), chrono::Utc::now().date_naive()
+        ).await?;
+        let current_outstanding = balances.get(&customer_id).copied().unwrap_or(Decimal::ZERO);
         let available_credit = result.credit_limit - current_outstanding;
         let credit_utilization = if result.credit_limit > Decimal::ZERO {
             (current_outstanding / result.credit_limit * Decimal::new(100, 0)).to_string().parse().unwrap_or(0.0)
@@ -303,4 +342,50 @@ impl CustomerService {
         let clean_npwp: String = npwp.chars().filter(|c| c.is_ascii_digit()).collect();
         clean_npwp.len() == 15
     }
-}
\ No newline at end of file
+}
+
+/// Open invoice balances per customer in the functional currency at the
+/// ledger's BI middle rate on `date`. A currency without a rate is an error,
+/// since the balance could not be compared with a credit limit.
+pub(crate) async fn open_balances(
+    db: &PgPool,
+    ledger_client: &LedgerClient,
+    company_id: Uuid,
+    user_id: Uuid,
+    customer_id: Option<Uuid>,
+    date: NaiveDate,
+) -> ServiceResult<HashMap<Uuid, Decimal>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT customer_id, currency, SUM(total_amount - COALESCE(paid_amount, 0)) as "outstanding!"
+        FROM customer_invoices
+        WHERE company_id = $1 AND ($2::uuid IS NULL OR customer_id = $2)
+              AND status != 'CANCELLED' AND total_amount > COALESCE(paid_amount, 0)
+        GROUP BY customer_id, currency
+        "#,
+        company_id,
+        customer_id
+    )
+    .fetch_all(db)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    let currencies = distinct_currencies(rows.iter().filter_map(|row| row.currency.as_deref()));
+    let rates = ledger_client
+        .rate_table(company_id, user_id, &currencies, RateType::BiMiddle, date)
+        .await?;
+
+    let mut balances = HashMap::new();
+    for row in rows {
+        let outstanding = rates.to_functional(row.outstanding, row.currency.as_deref()).ok_or_else(|| {
+            ServiceError::Validation(format!(
+                "No BI_MIDDLE exchange rate for {} on or before {}; open invoices in it cannot be valued",
+                row.currency.as_deref().unwrap_or_default(),
+                date
+            ))
+        })?;
+        *balances.entry(row.customer_id).or_insert(Decimal::ZERO) += outstanding;
+    }
+
+    Ok(balances)
+}
@@ -0,0 +1,62 @@
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, identity::{self, IDENTITY_HEADER}};
+use database::exchange_rates::{RateTable, RateType};
+use uuid::Uuid;
+
+/// Reads exchange rates from the general ledger service, which maintains
+/// the company's rate table.
+#[derive(Clone)]
+pub struct LedgerClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl LedgerClient {
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("GENERAL_LEDGER_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3004".to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The ledger's functional currency and its rates on `date` for the
+    /// given currencies.
+    pub async fn rate_table(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        currencies: &[String],
+        rate_type: RateType,
+        date: NaiveDate,
+    ) -> ServiceResult<RateTable> {
+        let response = self.client
+            .get(format!("{}/exchange-rates/table", self.base_url))
+            .query(&[
+                ("currencies", currencies.join(",")),
+                ("rate_type", rate_type.to_string()),
+                ("date", date.to_string()),
+            ])
+            .header("X-Company-ID", company_id.to_string())
+            .header("X-User-ID", user_id.to_string())
+            .header(IDENTITY_HEADER, identity::forward(user_id, company_id, &[])?)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to call general ledger: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ServiceError::ExternalService(format!(
+                "General ledger returned status {} for exchange rates", status
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to parse general ledger response: {}", e)))
+    }
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use common::identity::{Identity, IDENTITY_HEADER, IDENTITY_HEADERS};
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::AppState;
+
+/// Access of the token bearer as resolved by the auth service.
+#[derive(Deserialize)]
+struct AccessResponse {
+    user_id: Uuid,
+    company_id: Uuid,
+    company_ids: Vec<Uuid>,
+    roles: Vec<String>,
+}
+
+/// Authenticates every request outside `/health` and the auth routes.
+///
+/// Identity headers sent by the client are always dropped. For authenticated
+/// requests the gateway sets them from what the auth service resolved for
+/// the bearer token, plus a signed identity token services take roles from.
+/// A client picks one of its companies by sending `X-Company-ID`.
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let requested_company = request
+        .headers()
+        .get("X-Company-ID")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| Uuid::parse_str(s).ok());
+
+    for header in IDENTITY_HEADERS {
+        request.headers_mut().remove(*header);
+    }
+
+    let path = request.uri().path();
+    if path == "/health" || path.starts_with("/api/v1/auth/") {
+        return Ok(next.run(request).await);
+    }
+
+    let authorization = request
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .filter(|h| h.starts_with("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?
+        .to_string();
+
+    let access = resolve_access(&state, &authorization, requested_company).await?;
+
+    let identity = Identity::new(access.user_id, access.company_id, access.company_ids, access.roles);
+    let token = identity.sign().map_err(|e| {
+        tracing::error!("Failed to sign identity token: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let headers = request.headers_mut();
+    for (name, value) in [
+        ("X-User-ID", identity.sub.to_string()),
+        ("X-Company-ID", identity.company_id.to_string()),
+        ("X-User-Roles", identity.roles.join(",")),
+        (IDENTITY_HEADER, token),
+    ] {
+        let value = HeaderValue::from_str(&value).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        headers.insert(name, value);
+    }
+
+    Ok(next.run(request).await)
+}
+
+async fn resolve_access(
+    state: &AppState,
+    authorization: &str,
+    requested_company: Option<Uuid>,
+) -> Result<AccessResponse, StatusCode> {
+    let mut request = reqwest::Client::new()
+        .get(format!("{}/access", state.config.auth_service_url))
+        .header("Authorization", authorization)
+        .timeout(std::time::Duration::from_secs(state.config.request_timeout_seconds));
+    if let Some(company_id) = requested_company {
+        request = request.query(&[("company_id", company_id.to_string())]);
+    }
+
+    let response = request.send().await.map_err(|e| {
+        tracing::error!("Auth service unavailable: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    // reqwest has its own http types, so compare plain status codes
+    match response.status().as_u16() {
+        200..=299 => response.json().await.map_err(|e| {
+            tracing::error!("Invalid auth service response: {}", e);
+            StatusCode::BAD_GATEWAY
+        }),
+        403 => Err(StatusCode::FORBIDDEN),
+        400..=499 => Err(StatusCode::UNAUTHORIZED),
+        _ => Err(StatusCode::BAD_GATEWAY),
+    }
+}
@@ -1,9 +1,12 @@
-use axum::{extract::State, http::StatusCode, response::Json, extract::Query};
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, response::Json, extract::Query};
 use std::{sync::Arc, collections::HashMap};
 use validator::Validate;
 use crate::{AppState, models::*, utils::*};
 use common::{ServiceError, ServiceResult};
 
+/// Role that grants and revokes the roles of a company's users.
+const ADMIN_ROLE: &str = "ADMIN";
+
 pub async fn register(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<RegisterRequest>,
@@ -30,6 +33,8 @@ pub async fn register(
         return Err(ServiceError::Conflict("User already exists".to_string()));
     }
 
+    let mut tx = state.db.begin().await?;
+
     sqlx::query!(
         r#"
         INSERT INTO users (id, email, password_hash, full_name, company_id, is_active)
@@ -41,9 +46,25 @@ pub async fn register(
         payload.full_name,
         company_uuid
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
+    .await?;
+
+    // The first user of a company administers it until they grant ADMIN on
+    sqlx::query!(
+        r#"
+        INSERT INTO user_company_roles (user_id, company_id, role, granted_by, created_at)
+        SELECT $1, $2, $3, NULL, NOW()
+        WHERE NOT EXISTS (SELECT 1 FROM user_company_roles WHERE company_id = $2 AND role = $3)
+        "#,
+        user_id,
+        company_uuid,
+        ADMIN_ROLE
+    )
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(Json(serde_json::json!({
         "message": "User registered successfully",
         "user_id": user_id
@@ -92,4 +113,259 @@ pub async fn verify_token(
     Ok(Json(claims))
 }
 
+/// Resolves a bearer access token to the user's companies and their roles in
+/// the company they act in, which defaults to the user's own. Called by the
+/// API gateway for every authenticated request.
+pub async fn get_access(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<AccessResponse>> {
+    let (user_id, home_company_id) = bearer_user(&state, &headers).await?;
+
+    let mut company_ids = vec![home_company_id];
+    let granted = sqlx::query_scalar!(
+        "SELECT DISTINCT company_id FROM user_company_roles WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+    for company_id in granted {
+        if !company_ids.contains(&company_id) {
+            company_ids.push(company_id);
+        }
+    }
+
+    let company_id = match params.get("company_id") {
+        Some(requested) => uuid::Uuid::parse_str(requested)
+            .map_err(|_| ServiceError::Validation("Invalid company ID".to_string()))?,
+        None => home_company_id,
+    };
+    if !company_ids.contains(&company_id) {
+        return Err(ServiceError::Authorization("No access to the requested company".to_string()));
+    }
+
+    let roles = sqlx::query_scalar!(
+        "SELECT role FROM user_company_roles WHERE user_id = $1 AND company_id = $2 ORDER BY role",
+        user_id,
+        company_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(AccessResponse {
+        user_id,
+        company_id,
+        company_ids,
+        roles,
+    }))
+}
+
+/// Roles granted in a company. Only its admins may list them.
+pub async fn list_company_roles(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<UserCompanyRole>>> {
+    let (user_id, home_company_id) = bearer_user(&state, &headers).await?;
+    let company_id = match params.get("company_id") {
+        Some(requested) => uuid::Uuid::parse_str(requested)
+            .map_err(|_| ServiceError::Validation("Invalid company ID".to_string()))?,
+        None => home_company_id,
+    };
+    ensure_company_admin(&state, user_id, company_id).await?;
+
+    let roles = sqlx::query_as!(
+        UserCompanyRole,
+        r#"
+        SELECT ucr.user_id, u.email, u.full_name, ucr.company_id, ucr.role, ucr.granted_by, ucr.created_at
+        FROM user_company_roles ucr
+        JOIN users u ON u.id = ucr.user_id
+        WHERE ucr.company_id = $1
+        ORDER BY u.email, ucr.role
+        "#,
+        company_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(roles))
+}
+
+/// Grants a user a role in a company, which also gives them access to it.
+/// Only the company's admins may grant roles.
+pub async fn grant_company_role(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<GrantRoleRequest>,
+) -> ServiceResult<(StatusCode, Json<UserCompanyRole>)> {
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let (granted_by, home_company_id) = bearer_user(&state, &headers).await?;
+    let company_id = payload.company_id.unwrap_or(home_company_id);
+    ensure_company_admin(&state, granted_by, company_id).await?;
+
+    let role = payload.role.trim().to_uppercase();
+    if role.is_empty() {
+        return Err(ServiceError::Validation("Role is required".to_string()));
+    }
+
+    let user_exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND is_active = true)",
+        payload.user_id
+    )
+    .fetch_one(&state.db)
+    .await?
+    .unwrap_or(false);
+    if !user_exists {
+        return Err(ServiceError::NotFound("User not found".to_string()));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_company_roles (user_id, company_id, role, granted_by, created_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (user_id, company_id, role) DO NOTHING
+        "#,
+        payload.user_id,
+        company_id,
+        role,
+        granted_by
+    )
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!("User {} granted {} to user {} in company {}", granted_by, role, payload.user_id, company_id);
+
+    let granted = get_company_role(&state, payload.user_id, company_id, &role).await?;
+    Ok((StatusCode::CREATED, Json(granted)))
+}
+
+/// Revokes a user's role in a company. The last admin of a company cannot
+/// be revoked, so someone can always manage its roles.
+pub async fn revoke_company_role(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((user_id, role)): Path<(uuid::Uuid, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<StatusCode> {
+    let (revoked_by, home_company_id) = bearer_user(&state, &headers).await?;
+    let company_id = match params.get("company_id") {
+        Some(requested) => uuid::Uuid::parse_str(requested)
+            .map_err(|_| ServiceError::Validation("Invalid company ID".to_string()))?,
+        None => home_company_id,
+    };
+    ensure_company_admin(&state, revoked_by, company_id).await?;
+
+    let role = role.trim().to_uppercase();
+    let mut tx = state.db.begin().await?;
+
+    if role == ADMIN_ROLE {
+        // Locks the company's admins so two revocations cannot remove the last two
+        let admins = sqlx::query_scalar!(
+            "SELECT user_id FROM user_company_roles WHERE company_id = $1 AND role = $2 FOR UPDATE",
+            company_id,
+            ADMIN_ROLE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if admins.iter().all(|admin| *admin == user_id) {
+            return Err(ServiceError::Conflict(
+                "The last admin of a company cannot be revoked".to_string(),
+            ));
+        }
+    }
+
+    let revoked = sqlx::query!(
+        "DELETE FROM user_company_roles WHERE user_id = $1 AND company_id = $2 AND role = $3",
+        user_id,
+        company_id,
+        role
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if revoked == 0 {
+        return Err(ServiceError::NotFound("Role not granted".to_string()));
+    }
+
+    tx.commit().await?;
+
+    tracing::info!("User {} revoked {} from user {} in company {}", revoked_by, role, user_id, company_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The active user a bearer access token was issued to, with their own
+/// company.
+async fn bearer_user(state: &AppState, headers: &HeaderMap) -> ServiceResult<(uuid::Uuid, uuid::Uuid)> {
+    let token = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| ServiceError::Authentication("Missing bearer token".to_string()))?;
+
+    let claims = verify_jwt_token(&state.jwt_secret, token)?;
+    let user_id = uuid::Uuid::parse_str(&claims.sub)
+        .map_err(|_| ServiceError::Authentication("Invalid token subject".to_string()))?;
+
+    let company_id = sqlx::query_scalar!(
+        "SELECT company_id FROM users WHERE id = $1 AND is_active = true",
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ServiceError::Authentication("User is not active".to_string()))?;
+
+    Ok((user_id, company_id))
+}
+
+async fn ensure_company_admin(state: &AppState, user_id: uuid::Uuid, company_id: uuid::Uuid) -> ServiceResult<()> {
+    let is_admin = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM user_company_roles WHERE user_id = $1 AND company_id = $2 AND role = $3)",
+        user_id,
+        company_id,
+        ADMIN_ROLE
+    )
+    .fetch_one(&state.db)
+    .await?
+    .unwrap_or(false);
+
+    if is_admin {
+        Ok(())
+    } else {
+        Err(ServiceError::Authorization(format!(
+            "Managing roles requires the {} role in the company",
+            ADMIN_ROLE
+        )))
+    }
+}
+
+async fn get_company_role(
+    state: &AppState,
+    user_id: uuid::Uuid,
+    company_id: uuid::Uuid,
+    role: &str,
+) -> ServiceResult<UserCompanyRole> {
+    sqlx::query_as!(
+        UserCompanyRole,
+        r#"
+        SELECT ucr.user_id, u.email, u.full_name, ucr.company_id, ucr.role, ucr.granted_by, ucr.created_at
+        FROM user_company_roles ucr
+        JOIN users u ON u.id = ucr.user_id
+        WHERE ucr.user_id = $1 AND ucr.company_id = $2 AND ucr.role = $3
+        "#,
+        user_id,
+        company_id,
+        role
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ServiceError::NotFound("Role not granted".to_string()))
+}
+
 // Add other auth handlers...
\ No newline at end of file
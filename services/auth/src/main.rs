@@ -2,7 +2,7 @@ mod handlers;
 mod models;
 mod utils;
 
-use axum::{routing::{get, post}, Router};
+use axum::{routing::{delete, get, post}, Router};
 use handlers::*;
 use std::sync::Arc;
 use tracing::info;
@@ -42,6 +42,9 @@ async fn main() -> anyhow::Result<()> {
         .route("/refresh", post(refresh_token))
         .route("/logout", post(logout))
         .route("/verify", get(verify_token))
+        .route("/access", get(get_access))
+        .route("/roles", get(list_company_roles).post(grant_company_role))
+        .route("/roles/:user_id/:role", delete(revoke_company_role))
         .with_state(app_state);
 
     let bind_addr = std::env::var("AUTH_SERVICE_BIND")
@@ -27,6 +27,37 @@ pub struct LoginResponse {
     pub expires_in: i64,
 }
 
+/// Who the bearer of an access token is and what they may do in the
+/// company they act in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessResponse {
+    pub user_id: uuid::Uuid,
+    pub company_id: uuid::Uuid,
+    pub company_ids: Vec<uuid::Uuid>,
+    pub roles: Vec<String>,
+}
+
+/// A role a user holds in a company.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserCompanyRole {
+    pub user_id: uuid::Uuid,
+    pub email: String,
+    pub full_name: String,
+    pub company_id: uuid::Uuid,
+    pub role: String,
+    pub granted_by: Option<uuid::Uuid>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct GrantRoleRequest {
+    pub user_id: uuid::Uuid,
+    /// Defaults to the granting admin's own company
+    pub company_id: Option<uuid::Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Role must be 1 to 50 characters"))]
+    pub role: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct RegisterRequest {
     #[validate(email(message = "Invalid email format"))]
@@ -0,0 +1,146 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_allocation_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateAllocationRuleRequest>,
+) -> ServiceResult<Json<AllocationRuleWithTargets>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let rule = state.allocation_service
+        .create_rule(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn get_allocation_rules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<AllocationRule>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let is_active = params.get("is_active").and_then(|a| a.parse().ok());
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let rules = state.allocation_service
+        .get_rules(company_id, is_active, pagination)
+        .await?;
+
+    Ok(Json(rules))
+}
+
+pub async fn get_allocation_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> ServiceResult<Json<AllocationRuleWithTargets>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let rule = state.allocation_service
+        .get_rule(rule_id, company_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn update_allocation_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+    Json(payload): Json<UpdateAllocationRuleRequest>,
+) -> ServiceResult<Json<AllocationRuleWithTargets>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let rule = state.allocation_service
+        .update_rule(rule_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn delete_allocation_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    state.allocation_service
+        .delete_rule(rule_id, company_id, user_id)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn preview_allocation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+    Json(payload): Json<AllocationRunRequest>,
+) -> ServiceResult<Json<AllocationPreview>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let preview = state.allocation_service
+        .preview_allocation(rule_id, company_id, &payload)
+        .await?;
+
+    Ok(Json(preview))
+}
+
+pub async fn run_allocation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+    Json(payload): Json<AllocationRunRequest>,
+) -> ServiceResult<Json<AllocationRunResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let result = state.allocation_service
+        .run_allocation(rule_id, company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn get_allocation_runs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<AllocationRun>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let rule_id = params.get("rule_id").and_then(|id| id.parse().ok());
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let runs = state.allocation_service
+        .get_runs(company_id, rule_id, pagination)
+        .await?;
+
+    Ok(Json(runs))
+}
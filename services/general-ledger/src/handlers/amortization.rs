@@ -0,0 +1,73 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_amortization_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateAmortizationScheduleRequest>,
+) -> ServiceResult<Json<AmortizationScheduleWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let schedule = state.amortization_service
+        .create_schedule(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(schedule))
+}
+
+pub async fn get_amortization_schedules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<AmortizationSchedule>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let schedules = state.amortization_service
+        .get_schedules(company_id, params.get("status").cloned(), pagination)
+        .await?;
+
+    Ok(Json(schedules))
+}
+
+pub async fn get_amortization_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(schedule_id): Path<Uuid>,
+) -> ServiceResult<Json<AmortizationScheduleWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let schedule = state.amortization_service
+        .get_schedule(schedule_id, company_id)
+        .await?;
+
+    Ok(Json(schedule))
+}
+
+pub async fn cancel_amortization_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(schedule_id): Path<Uuid>,
+) -> ServiceResult<Json<AmortizationScheduleWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let schedule = state.amortization_service
+        .cancel_schedule(schedule_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(schedule))
+}
@@ -0,0 +1,101 @@
+use axum::{extract::{Path, State}, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_approval_policy(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ApprovalPolicyRequest>,
+) -> ServiceResult<Json<ApprovalPolicy>> {
+    let identity = extract_identity(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let policy = state.approval_service
+        .create_policy(identity.company_id, payload, identity.sub, &identity.roles)
+        .await?;
+
+    Ok(Json(policy))
+}
+
+pub async fn get_approval_policies(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<Vec<ApprovalPolicy>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let policies = state.approval_service
+        .get_policies(company_id)
+        .await?;
+
+    Ok(Json(policies))
+}
+
+pub async fn update_approval_policy(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(policy_id): Path<Uuid>,
+    Json(payload): Json<ApprovalPolicyRequest>,
+) -> ServiceResult<Json<ApprovalPolicy>> {
+    let identity = extract_identity(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let policy = state.approval_service
+        .update_policy(policy_id, identity.company_id, payload, &identity.roles)
+        .await?;
+
+    Ok(Json(policy))
+}
+
+pub async fn delete_approval_policy(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(policy_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let identity = extract_identity(&headers)?;
+
+    state.approval_service
+        .delete_policy(policy_id, identity.company_id, &identity.roles)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn approve_journal_entry(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(entry_id): Path<Uuid>,
+    Json(payload): Json<ApproveJournalEntryRequest>,
+) -> ServiceResult<Json<JournalApprovalStatus>> {
+    // The approver and the roles that count towards policies must come
+    // from the verified identity, never from plain headers
+    let identity = extract_identity(&headers)?;
+
+    let status = state.approval_service
+        .approve_entry(entry_id, identity.company_id, payload, identity.sub, &identity.roles)
+        .await?;
+
+    Ok(Json(status))
+}
+
+pub async fn get_journal_entry_approvals(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(entry_id): Path<Uuid>,
+) -> ServiceResult<Json<JournalApprovalStatus>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let status = state.approval_service
+        .get_approval_status(entry_id, company_id)
+        .await?;
+
+    Ok(Json(status))
+}
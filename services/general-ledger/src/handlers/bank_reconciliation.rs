@@ -0,0 +1,205 @@
+use axum::{body::Bytes, extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use rust_decimal::Decimal;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+fn bank_account_param(params: &HashMap<String, String>) -> ServiceResult<Uuid> {
+    params.get("bank_account_id")
+        .ok_or_else(|| ServiceError::Validation("Missing bank_account_id parameter".to_string()))?
+        .parse()
+        .map_err(|_| ServiceError::Validation("Invalid bank_account_id".to_string()))
+}
+
+/// Accepts the statement file as the request body. `format` is CSV or
+/// MT940; `opening_balance` is only needed when the file carries none and
+/// there is no earlier statement for the account.
+pub async fn import_bank_statement(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> ServiceResult<Json<BankStatementImportResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let bank_account_id = bank_account_param(&params)?;
+    let format = params.get("format")
+        .map(|f| f.parse::<BankStatementFormat>())
+        .transpose()
+        .map_err(ServiceError::Validation)?
+        .unwrap_or(BankStatementFormat::Csv);
+    let opening_balance = params.get("opening_balance")
+        .map(|b| Decimal::from_str(b))
+        .transpose()
+        .map_err(|_| ServiceError::Validation("Invalid opening_balance".to_string()))?;
+
+    let result = state.bank_reconciliation_service
+        .import_statement(
+            company_id,
+            bank_account_id,
+            format,
+            &body,
+            params.get("file_name").cloned(),
+            opening_balance,
+            user_id,
+        )
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn get_bank_statements(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<BankStatement>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let bank_account_id = params.get("bank_account_id").and_then(|id| id.parse().ok());
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let statements = state.bank_reconciliation_service
+        .get_statements(company_id, bank_account_id, pagination)
+        .await?;
+
+    Ok(Json(statements))
+}
+
+pub async fn get_bank_statement(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(statement_id): Path<Uuid>,
+) -> ServiceResult<Json<BankStatementWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let statement = state.bank_reconciliation_service
+        .get_statement(statement_id, company_id)
+        .await?;
+
+    Ok(Json(statement))
+}
+
+pub async fn auto_match_bank_statement_lines(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<AutoMatchRequest>,
+) -> ServiceResult<Json<AutoMatchResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let result = state.bank_reconciliation_service
+        .auto_match(company_id, user_id, payload)
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn match_bank_statement_line(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(line_id): Path<Uuid>,
+    Json(payload): Json<MatchStatementLineRequest>,
+) -> ServiceResult<Json<BankStatementLine>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let line = state.bank_reconciliation_service
+        .match_line(line_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(line))
+}
+
+pub async fn unmatch_bank_statement_line(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(line_id): Path<Uuid>,
+) -> ServiceResult<Json<BankStatementLine>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let line = state.bank_reconciliation_service
+        .unmatch_line(line_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(line))
+}
+
+pub async fn create_bank_adjustment(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(line_id): Path<Uuid>,
+    Json(payload): Json<BankAdjustmentRequest>,
+) -> ServiceResult<Json<JournalEntryWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let entry = state.bank_reconciliation_service
+        .create_adjustment(line_id, company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(entry))
+}
+
+/// Reconciliation of `bank_account_id` on `as_of_date` (today by default).
+pub async fn get_bank_reconciliation_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<BankReconciliationReport>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let bank_account_id = bank_account_param(&params)?;
+    let as_of_date = params.get("as_of_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let report = state.bank_reconciliation_service
+        .get_report(company_id, user_id, bank_account_id, as_of_date)
+        .await?;
+
+    Ok(Json(report))
+}
+
+pub async fn create_bank_reconciliation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateBankReconciliationRequest>,
+) -> ServiceResult<Json<BankReconciliation>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let reconciliation = state.bank_reconciliation_service
+        .create_reconciliation(company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(reconciliation))
+}
+
+pub async fn get_bank_reconciliations(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<BankReconciliation>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let bank_account_id = params.get("bank_account_id").and_then(|id| id.parse().ok());
+
+    let reconciliations = state.bank_reconciliation_service
+        .get_reconciliations(company_id, bank_account_id)
+        .await?;
+
+    Ok(Json(reconciliations))
+}
@@ -0,0 +1,179 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use database::budgets::BudgetWarning;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateBudgetRequest>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let budget = state.budget_service
+        .create_budget(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn create_budget_from_actuals(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateBudgetFromActualsRequest>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let budget = state.budget_service
+        .create_from_actuals(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn get_budgets(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<Budget>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let fiscal_year = params.get("fiscal_year").and_then(|y| y.parse().ok());
+    let status = params.get("status").cloned();
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let budgets = state.budget_service
+        .get_budgets(company_id, fiscal_year, status, pagination)
+        .await?;
+
+    Ok(Json(budgets))
+}
+
+pub async fn get_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let budget = state.budget_service
+        .get_budget(budget_id, company_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn update_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+    Json(payload): Json<UpdateBudgetRequest>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let budget = state.budget_service
+        .update_budget(budget_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn delete_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    state.budget_service
+        .delete_budget(budget_id, company_id, user_id)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn create_budget_version(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+    Json(payload): Json<CreateBudgetVersionRequest>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let budget = state.budget_service
+        .create_version(budget_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn activate_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let budget = state.budget_service
+        .activate_budget(budget_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn archive_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(budget_id): Path<Uuid>,
+) -> ServiceResult<Json<BudgetWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let budget = state.budget_service
+        .archive_budget(budget_id, company_id, user_id)
+        .await?;
+
+    Ok(Json(budget))
+}
+
+pub async fn check_budget(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<BudgetCheckRequest>,
+) -> ServiceResult<Json<Vec<BudgetWarning>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let warnings = state.budget_service
+        .check_budget(company_id, payload)
+        .await?;
+
+    Ok(Json(warnings))
+}
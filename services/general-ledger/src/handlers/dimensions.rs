@@ -0,0 +1,63 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_dimension_value(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateDimensionValueRequest>,
+) -> ServiceResult<Json<DimensionValue>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let value = state.dimension_service
+        .create_value(company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(value))
+}
+
+pub async fn get_dimension_values(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<DimensionValue>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let dimension_type = params.get("dimension_type")
+        .map(|t| t.parse::<DimensionType>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+
+    let values = state.dimension_service
+        .get_values(company_id, dimension_type)
+        .await?;
+
+    Ok(Json(values))
+}
+
+pub async fn update_dimension_value(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(value_id): Path<Uuid>,
+    Json(payload): Json<UpdateDimensionValueRequest>,
+) -> ServiceResult<Json<DimensionValue>> {
+    let company_id = extract_company_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let value = state.dimension_service
+        .update_value(value_id, company_id, payload)
+        .await?;
+
+    Ok(Json(value))
+}
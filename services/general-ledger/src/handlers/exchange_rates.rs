@@ -0,0 +1,162 @@
+use axum::{body::Bytes, extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_exchange_rate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateExchangeRateRequest>,
+) -> ServiceResult<Json<ExchangeRate>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let rate = state.exchange_rate_service
+        .create_rate(company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(rate))
+}
+
+pub async fn get_exchange_rates(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<ExchangeRate>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let rate_type = params.get("rate_type")
+        .map(|t| t.parse::<RateType>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+    let date_from = params.get("date_from")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let date_to = params.get("date_to")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+
+    let rates = state.exchange_rate_service
+        .get_rates(company_id, params.get("currency").cloned(), rate_type, date_from, date_to)
+        .await?;
+
+    Ok(Json(rates))
+}
+
+/// Rate in effect for `currency` on `date` (today by default), falling back
+/// to the nearest earlier rate.
+pub async fn lookup_exchange_rate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<ExchangeRate>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let currency = params.get("currency")
+        .ok_or_else(|| ServiceError::Validation("Missing currency parameter".to_string()))?;
+    let rate_type = params.get("rate_type")
+        .map(|t| t.parse::<RateType>())
+        .transpose()
+        .map_err(ServiceError::Validation)?
+        .unwrap_or(RateType::BiMiddle);
+    let date = params.get("date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let rate = state.exchange_rate_service
+        .lookup_rate(company_id, currency, rate_type, date)
+        .await?;
+
+    Ok(Json(rate))
+}
+
+/// Rates for several currencies (`currencies=USD,SGD`) on `date`, today by
+/// default. Currencies without a rate are left out of the table.
+pub async fn get_exchange_rate_table(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<database::exchange_rates::RateTable>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let currencies = database::exchange_rates::distinct_currencies(
+        params.get("currencies")
+            .map(|c| c.split(',').filter(|c| !c.trim().is_empty()).collect::<Vec<_>>())
+            .unwrap_or_default()
+    );
+    let rate_type = params.get("rate_type")
+        .map(|t| t.parse::<RateType>())
+        .transpose()
+        .map_err(ServiceError::Validation)?
+        .unwrap_or(RateType::BiMiddle);
+    let date = params.get("date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let table = state.exchange_rate_service
+        .rate_table(company_id, &currencies, rate_type, date)
+        .await?;
+
+    Ok(Json(table))
+}
+
+pub async fn update_exchange_rate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rate_id): Path<Uuid>,
+    Json(payload): Json<UpdateExchangeRateRequest>,
+) -> ServiceResult<Json<ExchangeRate>> {
+    let company_id = extract_company_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let rate = state.exchange_rate_service
+        .update_rate(rate_id, company_id, payload)
+        .await?;
+
+    Ok(Json(rate))
+}
+
+pub async fn delete_exchange_rate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rate_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let company_id = extract_company_id(&headers)?;
+
+    state.exchange_rate_service
+        .delete_rate(rate_id, company_id)
+        .await?;
+
+    Ok(())
+}
+
+/// Accepts a CSV file as the request body. `rate_type` defaults to TAX for
+/// the weekly KMK table.
+pub async fn import_exchange_rates(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> ServiceResult<Json<ExchangeRateImportResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let rate_type = params.get("rate_type")
+        .map(|t| t.parse::<RateType>())
+        .transpose()
+        .map_err(ServiceError::Validation)?
+        .unwrap_or(RateType::Tax);
+
+    let result = state.exchange_rate_service
+        .import_rates(company_id, rate_type, &body, params.get("source").cloned(), user_id)
+        .await?;
+
+    Ok(Json(result))
+}
@@ -0,0 +1,63 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn get_fiscal_periods(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<FiscalPeriod>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let fiscal_year = params.get("fiscal_year").and_then(|y| y.parse().ok());
+
+    let periods = state.period_service
+        .get_periods(company_id, fiscal_year)
+        .await?;
+
+    Ok(Json(periods))
+}
+
+pub async fn generate_fiscal_periods(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<GenerateFiscalPeriodsRequest>,
+) -> ServiceResult<Json<Vec<FiscalPeriod>>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let periods = state.period_service
+        .generate_fiscal_year(company_id, payload.fiscal_year, user_id)
+        .await?;
+
+    Ok(Json(periods))
+}
+
+pub async fn update_fiscal_period_status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(period_id): Path<Uuid>,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<FiscalPeriod>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let status = params.get("status")
+        .ok_or_else(|| ServiceError::Validation("Missing status parameter".to_string()))?
+        .parse::<PeriodStatus>()
+        .map_err(|e| ServiceError::Validation(format!("Invalid status: {}", e)))?;
+
+    let period = state.period_service
+        .update_period_status(period_id, company_id, status, user_id, &roles)
+        .await?;
+
+    Ok(Json(period))
+}
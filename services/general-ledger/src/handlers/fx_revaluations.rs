@@ -0,0 +1,33 @@
+use axum::{extract::State, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*};
+
+pub async fn run_fx_revaluation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<FxRevaluationRequest>,
+) -> ServiceResult<Json<FxRevaluationResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let result = state.fx_service
+        .revalue(company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn get_fx_revaluations(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<Vec<FxRevaluation>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let revaluations = state.fx_service
+        .get_revaluations(company_id)
+        .await?;
+
+    Ok(Json(revaluations))
+}
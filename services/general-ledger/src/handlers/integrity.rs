@@ -0,0 +1,19 @@
+use axum::{extract::State, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*};
+
+/// Runs every integrity check for the company and returns the findings.
+pub async fn get_integrity_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<IntegrityReport>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let report = state.integrity_service
+        .verify(company_id, user_id)
+        .await?;
+
+    Ok(Json(report))
+}
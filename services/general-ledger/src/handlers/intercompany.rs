@@ -0,0 +1,46 @@
+use axum::{extract::{Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_intercompany_entry(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateIntercompanyEntryRequest>,
+) -> ServiceResult<Json<IntercompanyEntryResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    // Booking into the counterparty needs access to it as well
+    ensure_company_access(&headers, payload.counterparty_company_id)?;
+
+    let result = state.journal_service
+        .create_intercompany_entry(company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn get_intercompany_mismatches(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<IntercompanyMismatchReport>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let as_of_date = params.get("as_of_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let report = state.journal_service
+        .get_intercompany_mismatches(company_id, as_of_date)
+        .await?;
+
+    Ok(Json(report))
+}
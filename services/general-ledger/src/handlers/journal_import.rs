@@ -0,0 +1,41 @@
+use axum::{body::Bytes, extract::{Query, State}, http::{header, HeaderMap}, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+/// Accepts the raw file as the request body. The format comes from the
+/// `format` query parameter or, failing that, the Content-Type header.
+/// Imports are dry runs unless `dry_run=false` is passed.
+pub async fn import_journal_entries(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> ServiceResult<Json<JournalImportResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let format = match params.get("format") {
+        Some(format) => format.parse::<ImportFormat>().map_err(ServiceError::Validation)?,
+        None => {
+            let content_type = headers.get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            if content_type.contains("spreadsheetml") {
+                ImportFormat::Xlsx
+            } else {
+                ImportFormat::Csv
+            }
+        }
+    };
+
+    let dry_run = params.get("dry_run").map(|v| v != "false").unwrap_or(true);
+    let post = params.get("post").map(|v| v == "true").unwrap_or(false);
+
+    let result = state.import_service
+        .import_entries(company_id, format, &body, dry_run, post, user_id, &roles)
+        .await?;
+
+    Ok(Json(result))
+}
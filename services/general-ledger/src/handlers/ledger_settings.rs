@@ -0,0 +1,70 @@
+use axum::{extract::{Path, State}, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn get_ledger_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<LedgerSettings>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let settings = state.settings_service
+        .get_settings(company_id)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+pub async fn update_ledger_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateLedgerSettingsRequest>,
+) -> ServiceResult<Json<LedgerSettings>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let settings = state.settings_service
+        .update_settings(company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+pub async fn get_document_number_formats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<Vec<DocumentNumberFormat>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let formats = state.settings_service
+        .get_number_formats(company_id)
+        .await?;
+
+    Ok(Json(formats))
+}
+
+pub async fn update_document_number_format(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(document_type): Path<String>,
+    Json(payload): Json<UpdateDocumentNumberFormatRequest>,
+) -> ServiceResult<Json<DocumentNumberFormat>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let document_type = document_type
+        .parse::<DocumentType>()
+        .map_err(ServiceError::Validation)?;
+
+    let format = state.settings_service
+        .update_number_format(company_id, document_type, payload, user_id)
+        .await?;
+
+    Ok(Json(format))
+}
@@ -1,7 +1,43 @@
+pub mod allocations;
+pub mod amortization;
+pub mod approvals;
+pub mod bank_reconciliation;
+pub mod budgets;
+pub mod dimensions;
+pub mod exchange_rates;
+pub mod fiscal_periods;
+pub mod fx_revaluations;
 pub mod health;
+pub mod integrity;
+pub mod intercompany;
+pub mod journal_import;
 pub mod journal_entries;
+pub mod ledger_settings;
+pub mod posting_rules;
+pub mod recurring_templates;
 pub mod reports;
+pub mod reversals;
+pub mod subledger_postings;
+pub mod year_end;
 
+pub use allocations::*;
+pub use amortization::*;
+pub use approvals::*;
+pub use bank_reconciliation::*;
+pub use budgets::*;
+pub use dimensions::*;
+pub use exchange_rates::*;
+pub use fiscal_periods::*;
+pub use fx_revaluations::*;
 pub use health::*;
+pub use integrity::*;
+pub use intercompany::*;
+pub use journal_import::*;
 pub use journal_entries::*;
-pub use reports::*;
\ No newline at end of file
+pub use ledger_settings::*;
+pub use posting_rules::*;
+pub use recurring_templates::*;
+pub use reports::*;
+pub use reversals::*;
+pub use subledger_postings::*;
+pub use year_end::*;
\ No newline at end of file
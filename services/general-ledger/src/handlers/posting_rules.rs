@@ -0,0 +1,106 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_posting_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreatePostingRuleRequest>,
+) -> ServiceResult<Json<PostingRule>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let rule = state.posting_rule_service
+        .create_rule(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn get_posting_rules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<PostingRule>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let event_type = params.get("event_type")
+        .map(|e| e.parse::<PostingEvent>())
+        .transpose()
+        .map_err(ServiceError::Validation)?;
+    let is_active = params.get("is_active").and_then(|a| a.parse().ok());
+
+    let rules = state.posting_rule_service
+        .get_rules(company_id, event_type, is_active)
+        .await?;
+
+    Ok(Json(rules))
+}
+
+pub async fn get_posting_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> ServiceResult<Json<PostingRule>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let rule = state.posting_rule_service
+        .get_rule(rule_id, company_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn update_posting_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+    Json(payload): Json<UpdatePostingRuleRequest>,
+) -> ServiceResult<Json<PostingRule>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let rule = state.posting_rule_service
+        .update_rule(rule_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+pub async fn delete_posting_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(rule_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    state.posting_rule_service
+        .delete_rule(rule_id, company_id, user_id)
+        .await?;
+
+    Ok(())
+}
+
+/// Which account a role would post to, for subledgers that need to know
+/// before they post.
+pub async fn resolve_posting_account(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolvePostingAccountRequest>,
+) -> ServiceResult<Json<ResolvedPostingAccount>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let resolved = state.posting_rule_service
+        .resolve_account(company_id, &payload)
+        .await?;
+
+    Ok(Json(resolved))
+}
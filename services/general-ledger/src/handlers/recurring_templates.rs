@@ -0,0 +1,93 @@
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*, PaginationParams};
+
+pub async fn create_recurring_template(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateRecurringTemplateRequest>,
+) -> ServiceResult<Json<RecurringTemplateWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let template = state.recurring_service
+        .create_template(payload, company_id, user_id)
+        .await?;
+
+    Ok(Json(template))
+}
+
+pub async fn get_recurring_templates(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<RecurringTemplate>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let pagination = PaginationParams {
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        offset: params.get("offset").and_then(|o| o.parse().ok()),
+    };
+
+    let templates = state.recurring_service
+        .get_templates(company_id, pagination)
+        .await?;
+
+    Ok(Json(templates))
+}
+
+pub async fn get_recurring_template(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(template_id): Path<Uuid>,
+) -> ServiceResult<Json<RecurringTemplateWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let template = state.recurring_service
+        .get_template(template_id, company_id)
+        .await?;
+
+    Ok(Json(template))
+}
+
+pub async fn update_recurring_template(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(template_id): Path<Uuid>,
+    Json(payload): Json<UpdateRecurringTemplateRequest>,
+) -> ServiceResult<Json<RecurringTemplateWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let template = state.recurring_service
+        .update_template(template_id, company_id, payload, user_id)
+        .await?;
+
+    Ok(Json(template))
+}
+
+pub async fn delete_recurring_template(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(template_id): Path<Uuid>,
+) -> ServiceResult<()> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    state.recurring_service
+        .delete_template(template_id, company_id, user_id)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use axum::{extract::{Query, State}, http::HeaderMap, response::Json};
+use std::{collections::HashMap, sync::Arc};
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*};
+
+pub async fn get_trial_balance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<TrialBalance>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let as_of_date = params.get("as_of_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let filter = DimensionFilter::from_params(&params);
+    let pivot = database::dimensions::pivot_from_params(&params)?;
+
+    let trial_balance = state.balance_service
+        .get_trial_balance(company_id, as_of_date, filter, pivot)
+        .await?;
+
+    Ok(Json(trial_balance))
+}
+
+pub async fn get_account_balances(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<Vec<AccountBalance>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let as_of_date = params.get("as_of_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+    let balances = state.balance_service
+        .get_account_balances(company_id, as_of_date)
+        .await?;
+
+    Ok(Json(balances))
+}
+
+pub async fn rebuild_account_balances(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<BalanceRebuildResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    tracing::info!("User {} requested balance rebuild for company {}", user_id, company_id);
+
+    let result = state.balance_service
+        .rebuild_balances(company_id)
+        .await?;
+
+    Ok(Json(result))
+}
@@ -0,0 +1,22 @@
+use axum::{extract::{Path, State}, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::{AppState, models::*};
+use common::{ServiceResult, extractors::*};
+
+pub async fn reverse_journal_entry(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(entry_id): Path<Uuid>,
+    Json(payload): Json<ReverseJournalEntryRequest>,
+) -> ServiceResult<Json<JournalEntryWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    let reversal = state.journal_service
+        .reverse_entry(entry_id, company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(reversal))
+}
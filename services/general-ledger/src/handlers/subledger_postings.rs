@@ -0,0 +1,27 @@
+use axum::{extract::State, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+/// Called by the subledger services to post the entry for one of their
+/// documents; safe to retry.
+pub async fn post_subledger_entry(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SubledgerPostingRequest>,
+) -> ServiceResult<Json<JournalEntryWithLines>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let entry = state.subledger_service
+        .post_document(company_id, payload, user_id, &roles)
+        .await?;
+
+    Ok(Json(entry))
+}
@@ -0,0 +1,38 @@
+use axum::{extract::State, http::HeaderMap, response::Json};
+use std::sync::Arc;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn close_fiscal_year(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<YearEndCloseRequest>,
+) -> ServiceResult<Json<YearEndCloseResult>> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+    let roles = extract_user_roles(&headers);
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let result = state.year_end_service
+        .close_fiscal_year(company_id, payload.fiscal_year, user_id, &roles)
+        .await?;
+
+    Ok(Json(result))
+}
+
+pub async fn get_year_end_closings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ServiceResult<Json<Vec<YearEndClosing>>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let closings = state.year_end_service
+        .get_closings(company_id)
+        .await?;
+
+    Ok(Json(closings))
+}
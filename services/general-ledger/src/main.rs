@@ -1,5 +1,6 @@
 mod handlers;
 mod models;
+mod scheduler;
 mod services;
 mod utils;
 
@@ -12,6 +13,24 @@ use tracing::info;
 pub struct AppState {
     db: sqlx::PgPool,
     audit_logger: database::audit::AuditLogger,
+    journal_service: services::JournalService,
+    balance_service: services::BalanceService,
+    approval_service: services::ApprovalService,
+    dimension_service: services::DimensionService,
+    recurring_service: services::RecurringService,
+    amortization_service: services::AmortizationService,
+    allocation_service: services::AllocationService,
+    budget_service: services::BudgetService,
+    period_service: services::PeriodService,
+    posting_rule_service: services::PostingRuleService,
+    settings_service: services::SettingsService,
+    subledger_service: services::SubledgerService,
+    year_end_service: services::YearEndService,
+    import_service: services::ImportService,
+    integrity_service: services::IntegrityService,
+    fx_service: services::FxService,
+    exchange_rate_service: services::ExchangeRateService,
+    bank_reconciliation_service: services::BankReconciliationService,
 }
 
 #[tokio::main]
@@ -26,21 +45,136 @@ async fn main() -> anyhow::Result<()> {
 
     let pool = database::create_database_pool("general-ledger").await?;
     let audit_logger = database::audit::AuditLogger::new(pool.clone());
+    let journal_service = services::JournalService::new(pool.clone());
+    let balance_service = services::BalanceService::new(pool.clone());
+    let approval_service = services::ApprovalService::new(pool.clone());
+    let dimension_service = services::DimensionService::new(pool.clone());
+    let recurring_service = services::RecurringService::new(pool.clone());
+    let amortization_service = services::AmortizationService::new(pool.clone());
+    let allocation_service = services::AllocationService::new(pool.clone());
+    let budget_service = services::BudgetService::new(pool.clone());
+    let period_service = services::PeriodService::new(pool.clone());
+    let posting_rule_service = services::PostingRuleService::new(pool.clone());
+    let settings_service = services::SettingsService::new(pool.clone());
+    let subledger_service = services::SubledgerService::new(pool.clone());
+    let year_end_service = services::YearEndService::new(pool.clone());
+    let import_service = services::ImportService::new(pool.clone());
+    let integrity_service = services::IntegrityService::new(pool.clone());
+    let fx_service = services::FxService::new(pool.clone());
+    let exchange_rate_service = services::ExchangeRateService::new(pool.clone());
+    let bank_reconciliation_service = services::BankReconciliationService::new(pool.clone());
+
+    tokio::spawn(scheduler::run(pool.clone()));
 
     let app_state = Arc::new(AppState { 
         db: pool,
         audit_logger,
+        journal_service,
+        balance_service,
+        approval_service,
+        dimension_service,
+        recurring_service,
+        amortization_service,
+        allocation_service,
+        budget_service,
+        period_service,
+        posting_rule_service,
+        settings_service,
+        subledger_service,
+        year_end_service,
+        import_service,
+        integrity_service,
+        fx_service,
+        exchange_rate_service,
+        bank_reconciliation_service,
     });
 
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/journal-entries", post(create_journal_entry))
         .route("/journal-entries", get(get_journal_entries))
+        .route("/journal-entries/import", post(import_journal_entries))
         .route("/journal-entries/:id", get(get_journal_entry_with_lines))
         .route("/journal-entries/:id", axum::routing::delete(delete_journal_entry))
         .route("/journal-entries/:id/status", put(update_journal_entry_status))
+        .route("/journal-entries/:id/reverse", post(reverse_journal_entry))
+        .route("/journal-entries/:id/approve", post(approve_journal_entry))
+        .route("/journal-entries/:id/approvals", get(get_journal_entry_approvals))
+        .route("/subledger-postings", post(post_subledger_entry))
+        .route("/posting-rules", post(create_posting_rule))
+        .route("/posting-rules", get(get_posting_rules))
+        .route("/posting-rules/resolve", post(resolve_posting_account))
+        .route("/posting-rules/:id", get(get_posting_rule))
+        .route("/posting-rules/:id", put(update_posting_rule))
+        .route("/posting-rules/:id", axum::routing::delete(delete_posting_rule))
+        .route("/intercompany-entries", post(create_intercompany_entry))
+        .route("/intercompany/mismatches", get(get_intercompany_mismatches))
+        .route("/approval-policies", post(create_approval_policy))
+        .route("/approval-policies", get(get_approval_policies))
+        .route("/approval-policies/:id", put(update_approval_policy))
+        .route("/approval-policies/:id", axum::routing::delete(delete_approval_policy))
+        .route("/recurring-templates", post(create_recurring_template))
+        .route("/recurring-templates", get(get_recurring_templates))
+        .route("/recurring-templates/:id", get(get_recurring_template))
+        .route("/recurring-templates/:id", put(update_recurring_template))
+        .route("/recurring-templates/:id", axum::routing::delete(delete_recurring_template))
+        .route("/amortization-schedules", post(create_amortization_schedule))
+        .route("/amortization-schedules", get(get_amortization_schedules))
+        .route("/amortization-schedules/:id", get(get_amortization_schedule))
+        .route("/amortization-schedules/:id/cancel", post(cancel_amortization_schedule))
+        .route("/allocation-rules", post(create_allocation_rule))
+        .route("/allocation-rules", get(get_allocation_rules))
+        .route("/allocation-rules/:id", get(get_allocation_rule))
+        .route("/allocation-rules/:id", put(update_allocation_rule))
+        .route("/allocation-rules/:id", axum::routing::delete(delete_allocation_rule))
+        .route("/allocation-rules/:id/preview", post(preview_allocation))
+        .route("/allocation-rules/:id/run", post(run_allocation))
+        .route("/allocation-runs", get(get_allocation_runs))
+        .route("/budgets", post(create_budget))
+        .route("/budgets", get(get_budgets))
+        .route("/budgets/from-actuals", post(create_budget_from_actuals))
+        .route("/budgets/check", post(check_budget))
+        .route("/budgets/:id", get(get_budget))
+        .route("/budgets/:id", put(update_budget))
+        .route("/budgets/:id", axum::routing::delete(delete_budget))
+        .route("/budgets/:id/versions", post(create_budget_version))
+        .route("/budgets/:id/activate", post(activate_budget))
+        .route("/budgets/:id/archive", post(archive_budget))
+        .route("/dimensions", post(create_dimension_value))
+        .route("/dimensions", get(get_dimension_values))
+        .route("/dimensions/:id", put(update_dimension_value))
+        .route("/fiscal-periods", get(get_fiscal_periods))
+        .route("/fiscal-periods/generate", post(generate_fiscal_periods))
+        .route("/fiscal-periods/:id/status", put(update_fiscal_period_status))
+        .route("/year-end-close", post(close_fiscal_year))
+        .route("/year-end-closings", get(get_year_end_closings))
+        .route("/exchange-rates", post(create_exchange_rate))
+        .route("/exchange-rates", get(get_exchange_rates))
+        .route("/exchange-rates/lookup", get(lookup_exchange_rate))
+        .route("/exchange-rates/table", get(get_exchange_rate_table))
+        .route("/exchange-rates/import", post(import_exchange_rates))
+        .route("/exchange-rates/:id", put(update_exchange_rate))
+        .route("/exchange-rates/:id", axum::routing::delete(delete_exchange_rate))
+        .route("/fx-revaluations", post(run_fx_revaluation))
+        .route("/fx-revaluations", get(get_fx_revaluations))
+        .route("/bank-statements", get(get_bank_statements))
+        .route("/bank-statements/import", post(import_bank_statement))
+        .route("/bank-statements/auto-match", post(auto_match_bank_statement_lines))
+        .route("/bank-statements/:id", get(get_bank_statement))
+        .route("/bank-statement-lines/:id/match", post(match_bank_statement_line))
+        .route("/bank-statement-lines/:id/unmatch", post(unmatch_bank_statement_line))
+        .route("/bank-statement-lines/:id/adjustment", post(create_bank_adjustment))
+        .route("/bank-reconciliation/report", get(get_bank_reconciliation_report))
+        .route("/bank-reconciliations", post(create_bank_reconciliation))
+        .route("/bank-reconciliations", get(get_bank_reconciliations))
+        .route("/ledger-settings", get(get_ledger_settings))
+        .route("/ledger-settings", put(update_ledger_settings))
+        .route("/document-number-formats", get(get_document_number_formats))
+        .route("/document-number-formats/:document_type", put(update_document_number_format))
         .route("/trial-balance", get(get_trial_balance))
         .route("/account-balances", get(get_account_balances))
+        .route("/account-balances/rebuild", post(rebuild_account_balances))
+        .route("/integrity-report", get(get_integrity_report))
         .with_state(app_state);
 
     let bind_addr = std::env::var("GENERAL_LEDGER_SERVICE_BIND")
@@ -0,0 +1,1704 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use uuid::Uuid;
+use validator::Validate;
+
+pub use database::dimensions::{DimensionFilter, DimensionType};
+pub use database::exchange_rates::{ExchangeRate, RateType};
+pub use database::numbering::{DocumentNumberFormat, DocumentType};
+pub use database::periods::PeriodStatus;
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq)]
+#[sqlx(type_name = "journal_entry_status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JournalEntryStatus {
+    Draft,
+    PendingApproval,
+    Approved,
+    Posted,
+    Cancelled,
+}
+
+impl std::str::FromStr for JournalEntryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DRAFT" => Ok(JournalEntryStatus::Draft),
+            "PENDING_APPROVAL" => Ok(JournalEntryStatus::PendingApproval),
+            "APPROVED" => Ok(JournalEntryStatus::Approved),
+            "POSTED" => Ok(JournalEntryStatus::Posted),
+            "CANCELLED" => Ok(JournalEntryStatus::Cancelled),
+            _ => Err(format!("Invalid journal entry status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for JournalEntryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalEntryStatus::Draft => write!(f, "DRAFT"),
+            JournalEntryStatus::PendingApproval => write!(f, "PENDING_APPROVAL"),
+            JournalEntryStatus::Approved => write!(f, "APPROVED"),
+            JournalEntryStatus::Posted => write!(f, "POSTED"),
+            JournalEntryStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub entry_number: String,
+    pub entry_date: NaiveDate,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub total_debit: Decimal,
+    pub total_credit: Decimal,
+    pub status: JournalEntryStatus,
+    pub is_posted: bool,
+    pub is_recurring: bool,
+    pub recurring_template_id: Option<Uuid>,
+    pub source_document_type: Option<String>,
+    pub source_document_id: Option<Uuid>,
+    pub auto_reverse_on: Option<NaiveDate>,
+    pub reversed_by_entry_id: Option<Uuid>,
+    pub created_by: Uuid,
+    pub approved_by: Option<Uuid>,
+    pub posted_by: Option<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub approved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub posted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntryLine {
+    pub id: Uuid,
+    pub journal_entry_id: Uuid,
+    pub account_id: Uuid,
+    pub account_code: Option<String>,
+    pub account_name: Option<String>,
+    pub description: Option<String>,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub line_number: i32,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+    pub currency: Option<String>,
+    pub foreign_amount: Option<Decimal>,
+    pub exchange_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntryWithLines {
+    pub journal_entry: JournalEntry,
+    pub lines: Vec<JournalEntryLine>,
+    /// Budgets the entry would exceed, when budget warnings are enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub budget_warnings: Vec<database::budgets::BudgetWarning>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateJournalEntryRequest {
+    pub company_id: Uuid,
+    pub entry_date: NaiveDate,
+    #[validate(length(max = 1000, message = "Description cannot exceed 1000 characters"))]
+    pub description: Option<String>,
+    #[validate(length(max = 100, message = "Reference cannot exceed 100 characters"))]
+    pub reference: Option<String>,
+    /// When set, a reversal is posted automatically on this date (typically
+    /// the first day of the next period for month-end accruals).
+    pub auto_reverse_on: Option<NaiveDate>,
+    /// Reverse automatically on the first day of the next period. Implied
+    /// by `auto_reverse_on`, which picks a different date.
+    #[serde(default)]
+    pub auto_reverse: bool,
+    #[validate(length(min = 2, message = "Journal entry must have at least 2 lines"))]
+    pub lines: Vec<CreateJournalEntryLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Clone)]
+pub struct CreateJournalEntryLineRequest {
+    pub account_id: Uuid,
+    pub description: Option<String>,
+    /// Debit and credit are always in the company's functional currency.
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+    /// Transaction currency of a foreign-currency line, with the amount in
+    /// that currency and the rate used to convert it.
+    pub currency: Option<String>,
+    pub foreign_amount: Option<Decimal>,
+    pub exchange_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReverseJournalEntryRequest {
+    pub reversal_date: Option<NaiveDate>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "CSV" => Ok(ImportFormat::Csv),
+            "XLSX" => Ok(ImportFormat::Xlsx),
+            _ => Err(format!("Invalid import format: {}", s))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalImportError {
+    pub reference: Option<String>,
+    /// Spreadsheet row number (the header is row 1); `None` for problems
+    /// with the entry as a whole.
+    pub row_number: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalImportEntry {
+    pub reference: String,
+    pub entry_date: Option<NaiveDate>,
+    pub row_numbers: Vec<usize>,
+    pub total_debit: Decimal,
+    pub total_credit: Decimal,
+    pub journal_entry_id: Option<Uuid>,
+    pub entry_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalImportResult {
+    pub batch_id: Uuid,
+    pub dry_run: bool,
+    pub committed: bool,
+    pub rows_read: usize,
+    pub entries: Vec<JournalImportEntry>,
+    pub errors: Vec<JournalImportError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DimensionValue {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub dimension_type: String,
+    pub code: String,
+    pub name: String,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateDimensionValueRequest {
+    pub dimension_type: DimensionType,
+    #[validate(length(min = 1, max = 50, message = "Code must be 1-50 characters"))]
+    pub code: String,
+    #[validate(length(min = 1, max = 255, message = "Name must be 1-255 characters"))]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateDimensionValueRequest {
+    #[validate(length(min = 1, max = 255, message = "Name must be 1-255 characters"))]
+    pub name: String,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub policy_name: String,
+    pub min_amount: Option<Decimal>,
+    pub account_type: Option<String>,
+    pub required_approvals: i32,
+    pub required_role: Option<String>,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ApprovalPolicyRequest {
+    #[validate(length(min = 1, max = 100, message = "Policy name must be 1-100 characters"))]
+    pub policy_name: String,
+    pub min_amount: Option<Decimal>,
+    pub account_type: Option<String>,
+    #[validate(range(min = 1, max = 10, message = "Required approvals must be between 1 and 10"))]
+    pub required_approvals: i32,
+    #[validate(length(min = 1, max = 50, message = "Required role must be 1-50 characters"))]
+    pub required_role: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntryApproval {
+    pub id: Uuid,
+    pub journal_entry_id: Uuid,
+    pub approver_id: Uuid,
+    pub approver_roles: Vec<String>,
+    pub comments: Option<String>,
+    pub approved_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApproveJournalEntryRequest {
+    pub comments: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApprovalRequirement {
+    pub policy_id: Option<Uuid>,
+    pub policy_name: String,
+    pub required_approvals: i32,
+    pub required_role: Option<String>,
+    pub approvals_received: i32,
+    pub is_satisfied: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalApprovalStatus {
+    pub journal_entry_id: Uuid,
+    pub status: JournalEntryStatus,
+    pub requirements: Vec<ApprovalRequirement>,
+    pub approvals: Vec<JournalEntryApproval>,
+    pub is_fully_approved: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RecurringFrequency {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl RecurringFrequency {
+    pub fn months(&self) -> u32 {
+        match self {
+            RecurringFrequency::Monthly => 1,
+            RecurringFrequency::Quarterly => 3,
+            RecurringFrequency::Yearly => 12,
+        }
+    }
+}
+
+impl std::str::FromStr for RecurringFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "MONTHLY" => Ok(RecurringFrequency::Monthly),
+            "QUARTERLY" => Ok(RecurringFrequency::Quarterly),
+            "YEARLY" => Ok(RecurringFrequency::Yearly),
+            _ => Err(format!("Invalid recurring frequency: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for RecurringFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecurringFrequency::Monthly => write!(f, "MONTHLY"),
+            RecurringFrequency::Quarterly => write!(f, "QUARTERLY"),
+            RecurringFrequency::Yearly => write!(f, "YEARLY"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringTemplate {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub template_name: String,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub frequency: String,
+    pub start_date: NaiveDate,
+    pub next_run_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub last_run_date: Option<NaiveDate>,
+    pub auto_post: bool,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringTemplateLine {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub account_id: Uuid,
+    pub description: Option<String>,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+    pub line_number: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringTemplateWithLines {
+    pub template: RecurringTemplate,
+    pub lines: Vec<RecurringTemplateLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateRecurringTemplateRequest {
+    #[validate(length(min = 1, max = 255, message = "Template name must be 1-255 characters"))]
+    pub template_name: String,
+    pub description: Option<String>,
+    #[validate(length(max = 100, message = "Reference cannot exceed 100 characters"))]
+    pub reference: Option<String>,
+    pub frequency: RecurringFrequency,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub auto_post: bool,
+    #[validate(length(min = 2, message = "Template must have at least 2 lines"))]
+    pub lines: Vec<CreateJournalEntryLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateRecurringTemplateRequest {
+    #[validate(length(min = 1, max = 255, message = "Template name must be 1-255 characters"))]
+    pub template_name: String,
+    pub description: Option<String>,
+    #[validate(length(max = 100, message = "Reference cannot exceed 100 characters"))]
+    pub reference: Option<String>,
+    pub frequency: RecurringFrequency,
+    pub next_run_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub auto_post: bool,
+    pub is_active: bool,
+    #[validate(length(min = 2, message = "Template must have at least 2 lines"))]
+    pub lines: Vec<CreateJournalEntryLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RecurringRunSummary {
+    pub templates_processed: u32,
+    pub entries_created: u32,
+    /// Runs not generated because their period was locked
+    pub runs_skipped: u32,
+    pub templates_failed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AmortizationScheduleType {
+    /// Prepaid expense carried as an asset (Biaya Dibayar Dimuka)
+    Prepaid,
+    /// Expense accrued into a liability ahead of the invoice
+    Accrual,
+}
+
+impl AmortizationScheduleType {
+    /// Account type the schedule's balance account must have.
+    pub fn balance_account_type(&self) -> &'static str {
+        match self {
+            AmortizationScheduleType::Prepaid => "ASSET",
+            AmortizationScheduleType::Accrual => "LIABILITY",
+        }
+    }
+}
+
+impl std::str::FromStr for AmortizationScheduleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "PREPAID" => Ok(AmortizationScheduleType::Prepaid),
+            "ACCRUAL" => Ok(AmortizationScheduleType::Accrual),
+            _ => Err(format!("Invalid amortization schedule type: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for AmortizationScheduleType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmortizationScheduleType::Prepaid => write!(f, "PREPAID"),
+            AmortizationScheduleType::Accrual => write!(f, "ACCRUAL"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AmortizationMethod {
+    StraightLine,
+    Custom,
+}
+
+impl std::str::FromStr for AmortizationMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "STRAIGHT_LINE" => Ok(AmortizationMethod::StraightLine),
+            "CUSTOM" => Ok(AmortizationMethod::Custom),
+            _ => Err(format!("Invalid amortization method: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for AmortizationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmortizationMethod::StraightLine => write!(f, "STRAIGHT_LINE"),
+            AmortizationMethod::Custom => write!(f, "CUSTOM"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationSchedule {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub schedule_name: String,
+    pub description: Option<String>,
+    pub schedule_type: String,
+    pub method: String,
+    pub balance_account_id: Uuid,
+    pub expense_account_id: Uuid,
+    pub total_amount: Decimal,
+    pub periods: i32,
+    pub start_date: NaiveDate,
+    pub auto_post: bool,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+    pub status: String,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationScheduleLine {
+    pub id: Uuid,
+    pub schedule_id: Uuid,
+    pub period_number: i32,
+    pub period_date: NaiveDate,
+    pub amount: Decimal,
+    pub journal_entry_id: Option<Uuid>,
+    /// Balance left on the schedule after this period
+    pub remaining_balance: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmortizationScheduleWithLines {
+    pub schedule: AmortizationSchedule,
+    pub lines: Vec<AmortizationScheduleLine>,
+    pub amortized_amount: Decimal,
+    pub remaining_balance: Decimal,
+}
+
+/// `amounts` is required for the CUSTOM method, one per period, and must add
+/// up to `total_amount`.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateAmortizationScheduleRequest {
+    #[validate(length(min = 1, max = 255, message = "Schedule name must be 1-255 characters"))]
+    pub schedule_name: String,
+    pub description: Option<String>,
+    pub schedule_type: AmortizationScheduleType,
+    pub method: AmortizationMethod,
+    pub balance_account_id: Uuid,
+    pub expense_account_id: Uuid,
+    pub total_amount: Decimal,
+    #[validate(range(min = 1, max = 600, message = "Periods must be between 1 and 600"))]
+    pub periods: i32,
+    pub start_date: NaiveDate,
+    #[serde(default)]
+    pub amounts: Vec<Decimal>,
+    pub auto_post: Option<bool>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AmortizationRunSummary {
+    pub schedules_processed: u32,
+    pub entries_created: u32,
+    pub schedules_failed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BankStatementFormat {
+    Csv,
+    Mt940,
+}
+
+impl std::str::FromStr for BankStatementFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "CSV" => Ok(BankStatementFormat::Csv),
+            "MT940" | "STA" => Ok(BankStatementFormat::Mt940),
+            _ => Err(format!("Invalid bank statement format: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for BankStatementFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BankStatementFormat::Csv => write!(f, "CSV"),
+            BankStatementFormat::Mt940 => write!(f, "MT940"),
+        }
+    }
+}
+
+/// What a bank statement line has been matched to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BankMatchType {
+    /// A journal entry line on the bank account
+    JournalLine,
+    VendorPayment,
+    CustomerPayment,
+}
+
+impl std::str::FromStr for BankMatchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "JOURNAL_LINE" => Ok(BankMatchType::JournalLine),
+            "VENDOR_PAYMENT" => Ok(BankMatchType::VendorPayment),
+            "CUSTOMER_PAYMENT" => Ok(BankMatchType::CustomerPayment),
+            _ => Err(format!("Invalid bank match type: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for BankMatchType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BankMatchType::JournalLine => write!(f, "JOURNAL_LINE"),
+            BankMatchType::VendorPayment => write!(f, "VENDOR_PAYMENT"),
+            BankMatchType::CustomerPayment => write!(f, "CUSTOMER_PAYMENT"),
+        }
+    }
+}
+
+/// A vendor or customer payment as reported by the payables or receivables
+/// service, for matching against bank statement lines.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubledgerPayment {
+    pub id: Uuid,
+    pub payment_number: String,
+    pub payment_date: NaiveDate,
+    /// Amount settled on the invoice
+    pub payment_amount: Decimal,
+    /// Income tax withheld from a vendor payment; the bank paid the rest
+    #[serde(default)]
+    pub withholding_amount: Decimal,
+    pub bank_account_id: Option<Uuid>,
+    pub payment_reference: Option<String>,
+    pub check_number: Option<String>,
+    pub is_reversed: bool,
+    /// Ledger entry the payment was posted with
+    pub journal_entry_id: Option<Uuid>,
+}
+
+/// Open payables or receivables as reported by the owning service, in the
+/// functional currency at the rates the documents were booked at.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubledgerBalance {
+    pub outstanding: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankStatement {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub bank_account_id: Uuid,
+    pub statement_number: Option<String>,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub opening_balance: Decimal,
+    pub closing_balance: Decimal,
+    pub source_format: String,
+    pub file_name: Option<String>,
+    pub imported_by: Uuid,
+    pub imported_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankStatementLine {
+    pub id: Uuid,
+    pub statement_id: Uuid,
+    pub bank_account_id: Uuid,
+    pub line_number: i32,
+    pub transaction_date: NaiveDate,
+    /// Deposits are positive, withdrawals negative
+    pub amount: Decimal,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub bank_reference: Option<String>,
+    pub match_status: String,
+    pub match_type: Option<String>,
+    pub matched_record_id: Option<Uuid>,
+    pub match_method: Option<String>,
+    pub matched_by: Option<Uuid>,
+    pub matched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub reconciliation_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankStatementWithLines {
+    pub statement: BankStatement,
+    pub lines: Vec<BankStatementLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankStatementImportResult {
+    pub statement: BankStatement,
+    pub lines_imported: u32,
+    pub lines_matched: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutoMatchRequest {
+    pub bank_account_id: Uuid,
+    /// Days either side of the statement date a book item may fall (default 3)
+    pub date_window_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutoMatchResult {
+    pub lines_considered: u32,
+    pub lines_matched: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchStatementLineRequest {
+    pub match_type: BankMatchType,
+    pub record_id: Uuid,
+}
+
+/// Books a bank fee (withdrawal) or interest (deposit) that appears only on
+/// the statement against `account_id`, and matches the line to it.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct BankAdjustmentRequest {
+    pub account_id: Uuid,
+    #[validate(length(max = 1000, message = "Description cannot exceed 1000 characters"))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankReconciliation {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub bank_account_id: Uuid,
+    pub as_of_date: NaiveDate,
+    pub statement_balance: Decimal,
+    pub book_balance: Decimal,
+    pub reconciled_balance: Decimal,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBankReconciliationRequest {
+    pub bank_account_id: Uuid,
+    pub as_of_date: NaiveDate,
+}
+
+/// A book item on the bank account not yet cleared by the statement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutstandingBookItem {
+    pub journal_entry_id: Uuid,
+    pub journal_entry_line_id: Uuid,
+    pub entry_number: String,
+    pub entry_date: NaiveDate,
+    pub description: Option<String>,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankReconciliationReport {
+    pub bank_account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub as_of_date: NaiveDate,
+    pub statement_balance: Decimal,
+    pub deposits_in_transit: Decimal,
+    pub outstanding_payments: Decimal,
+    pub adjusted_statement_balance: Decimal,
+    pub book_balance: Decimal,
+    /// Statement lines not yet recorded in the books (fees, interest, ...)
+    pub unrecorded_statement_items: Decimal,
+    pub adjusted_book_balance: Decimal,
+    pub difference: Decimal,
+    pub outstanding_book_items: Vec<OutstandingBookItem>,
+    pub unrecorded_statement_lines: Vec<BankStatementLine>,
+    pub last_reconciliation: Option<BankReconciliation>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllocationBasis {
+    /// Target weights are percentages adding up to 100
+    FixedPercentage,
+    /// Target weights are driver quantities such as headcount or floor area
+    Driver,
+}
+
+impl std::str::FromStr for AllocationBasis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "FIXED_PERCENTAGE" => Ok(AllocationBasis::FixedPercentage),
+            "DRIVER" => Ok(AllocationBasis::Driver),
+            _ => Err(format!("Invalid allocation basis: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for AllocationBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AllocationBasis::FixedPercentage => write!(f, "FIXED_PERCENTAGE"),
+            AllocationBasis::Driver => write!(f, "DRIVER"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRule {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub rule_name: String,
+    pub description: Option<String>,
+    pub source_account_id: Uuid,
+    pub source_cost_center: Option<String>,
+    pub basis: String,
+    pub driver_name: Option<String>,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRuleTarget {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub line_number: i32,
+    pub cost_center: String,
+    /// Account debited for this target; the source account when not set
+    pub account_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub weight: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRuleWithTargets {
+    pub rule: AllocationRule,
+    pub targets: Vec<AllocationRuleTarget>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationTargetRequest {
+    pub cost_center: String,
+    pub account_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub weight: Decimal,
+}
+
+/// Spreads the balance of `source_account_id` (only the `source_cost_center`
+/// pool when given) over the targets in proportion to their weights.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateAllocationRuleRequest {
+    #[validate(length(min = 1, max = 255, message = "Rule name must be 1-255 characters"))]
+    pub rule_name: String,
+    pub description: Option<String>,
+    pub source_account_id: Uuid,
+    pub source_cost_center: Option<String>,
+    pub basis: AllocationBasis,
+    pub driver_name: Option<String>,
+    #[validate(length(min = 1, message = "Rule must have at least one target"))]
+    pub targets: Vec<AllocationTargetRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateAllocationRuleRequest {
+    #[validate(length(min = 1, max = 255, message = "Rule name must be 1-255 characters"))]
+    pub rule_name: String,
+    pub description: Option<String>,
+    pub source_cost_center: Option<String>,
+    pub basis: AllocationBasis,
+    pub driver_name: Option<String>,
+    pub is_active: bool,
+    #[validate(length(min = 1, message = "Rule must have at least one target"))]
+    pub targets: Vec<AllocationTargetRequest>,
+}
+
+/// `entry_date` defaults to `period_end`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRunRequest {
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub entry_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationPreviewLine {
+    pub cost_center: String,
+    pub account_id: Uuid,
+    pub department: Option<String>,
+    pub weight: Decimal,
+    /// Share of the pool as a percentage
+    pub share: Decimal,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationPreview {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub source_account_id: Uuid,
+    pub source_cost_center: Option<String>,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub entry_date: NaiveDate,
+    pub source_amount: Decimal,
+    pub lines: Vec<AllocationPreviewLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRun {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub rule_id: Uuid,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub source_amount: Decimal,
+    pub journal_entry_id: Uuid,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationRunResult {
+    pub run: AllocationRun,
+    pub journal_entry: JournalEntryWithLines,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BudgetStatus {
+    Draft,
+    /// The version used for budget warnings and by default in reports
+    Active,
+    Archived,
+}
+
+impl std::str::FromStr for BudgetStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DRAFT" => Ok(BudgetStatus::Draft),
+            "ACTIVE" => Ok(BudgetStatus::Active),
+            "ARCHIVED" => Ok(BudgetStatus::Archived),
+            _ => Err(format!("Invalid budget status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for BudgetStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetStatus::Draft => write!(f, "DRAFT"),
+            BudgetStatus::Active => write!(f, "ACTIVE"),
+            BudgetStatus::Archived => write!(f, "ARCHIVED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub budget_name: String,
+    pub fiscal_year: i32,
+    pub version: i32,
+    pub description: Option<String>,
+    pub status: String,
+    pub based_on_budget_id: Option<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetLine {
+    pub id: Uuid,
+    pub budget_id: Uuid,
+    pub account_id: Uuid,
+    pub account_code: Option<String>,
+    pub account_name: Option<String>,
+    /// First day of the budgeted month
+    pub period_start: NaiveDate,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetWithLines {
+    pub budget: Budget,
+    pub lines: Vec<BudgetLine>,
+    pub total_amount: Decimal,
+}
+
+/// Amount budgeted for an account in the month containing `period_start`,
+/// in the account's natural direction (spending for expenses, income for
+/// revenue).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BudgetLineRequest {
+    pub account_id: Uuid,
+    pub period_start: NaiveDate,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateBudgetRequest {
+    #[validate(length(min = 1, max = 255, message = "Budget name must be 1-255 characters"))]
+    pub budget_name: String,
+    #[validate(range(min = 2000, max = 2100, message = "Invalid fiscal year"))]
+    pub fiscal_year: i32,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub lines: Vec<BudgetLineRequest>,
+}
+
+/// Replaces the name, description and every line of a draft budget.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateBudgetRequest {
+    #[validate(length(min = 1, max = 255, message = "Budget name must be 1-255 characters"))]
+    pub budget_name: String,
+    pub description: Option<String>,
+    pub lines: Vec<BudgetLineRequest>,
+}
+
+/// Seeds a budget with the previous fiscal year's posted revenue and
+/// expense per account and month, raised by `uplift_percent`.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateBudgetFromActualsRequest {
+    #[validate(length(min = 1, max = 255, message = "Budget name must be 1-255 characters"))]
+    pub budget_name: String,
+    #[validate(range(min = 2000, max = 2100, message = "Invalid fiscal year"))]
+    pub fiscal_year: i32,
+    pub description: Option<String>,
+    pub uplift_percent: Option<Decimal>,
+    #[serde(default)]
+    pub by_department: bool,
+    #[serde(default)]
+    pub by_project: bool,
+}
+
+/// Copies a budget into the next version for the same year, optionally
+/// raising every line by `uplift_percent`.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateBudgetVersionRequest {
+    #[validate(length(min = 1, max = 255, message = "Budget name must be 1-255 characters"))]
+    pub budget_name: Option<String>,
+    pub description: Option<String>,
+    pub uplift_percent: Option<Decimal>,
+}
+
+/// Addressed by account id, or for subledger documents by the expense
+/// account the posting rules give the item category.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetCheckLineRequest {
+    pub account_id: Option<Uuid>,
+    #[serde(default)]
+    pub item_category: Option<String>,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+/// Spending to test against the remaining budget before it is booked.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetCheckRequest {
+    pub date: NaiveDate,
+    /// Required for lines without an account_id
+    #[serde(default)]
+    pub event_type: Option<PostingEvent>,
+    #[serde(default)]
+    pub vendor_group: Option<String>,
+    pub lines: Vec<BudgetCheckLineRequest>,
+}
+
+/// A posted entry requested by a subledger service (payables, receivables,
+/// inventory) for one of its documents. Posting the same document twice
+/// returns the entry already posted, so callers can safely retry.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SubledgerPostingRequest {
+    /// e.g. VENDOR_INVOICE or VENDOR_PAYMENT
+    #[validate(length(min = 1, max = 50, message = "Source document type must be 1-50 characters"))]
+    pub source_document_type: String,
+    pub source_document_id: Uuid,
+    pub entry_date: NaiveDate,
+    #[validate(length(max = 1000, message = "Description cannot exceed 1000 characters"))]
+    pub description: Option<String>,
+    #[validate(length(max = 100, message = "Reference cannot exceed 100 characters"))]
+    pub reference: Option<String>,
+    /// Required when any line is addressed by `account_role`
+    pub event_type: Option<PostingEvent>,
+    pub vendor_group: Option<String>,
+    #[validate(length(min = 2, message = "Journal entry must have at least 2 lines"))]
+    pub lines: Vec<SubledgerPostingLine>,
+}
+
+/// A line addressed by account id, by the role the account plays in the
+/// event (resolved through the company's posting rules) or by account code.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubledgerPostingLine {
+    pub account_id: Option<Uuid>,
+    pub account_role: Option<PostingAccountRole>,
+    pub account_code: Option<String>,
+    pub item_category: Option<String>,
+    pub tax_type: Option<String>,
+    pub description: Option<String>,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    /// Foreign-currency amount of a monetary line, so it is revalued
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub foreign_amount: Option<Decimal>,
+    #[serde(default)]
+    pub exchange_rate: Option<Decimal>,
+}
+
+/// Business events the subledgers post to the ledger. Only events a
+/// subledger actually posts are listed; rules for anything else would never
+/// be consulted.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PostingEvent {
+    VendorInvoiceApproved,
+    VendorPaymentMade,
+    VendorCreditReceived,
+    VendorRefundReceived,
+    VendorAdvancePaid,
+    VendorAdvanceApplied,
+    VendorAdvanceRefunded,
+    /// Purchase order receipt, before the vendor invoices it
+    GoodsReceived,
+}
+
+impl PostingEvent {
+    /// Account roles that take part in the event
+    pub fn roles(&self) -> &'static [PostingAccountRole] {
+        use PostingAccountRole::*;
+
+        match self {
+            PostingEvent::VendorInvoiceApproved => &[ApControl, Expense, InputVat, Inventory, GoodsReceivedNotInvoiced, PurchasePriceVariance, WithholdingTax],
+            PostingEvent::VendorPaymentMade => &[ApControl, Bank, WithholdingTax, PurchaseDiscount, RealizedFxGainLoss],
+            PostingEvent::VendorCreditReceived => &[ApControl, Expense, InputVat, Inventory],
+            PostingEvent::VendorRefundReceived => &[ApControl, Bank],
+            PostingEvent::VendorAdvancePaid => &[VendorAdvance, InputVat, Bank],
+            PostingEvent::VendorAdvanceApplied => &[ApControl, VendorAdvance, InputVat],
+            PostingEvent::VendorAdvanceRefunded => &[Bank, VendorAdvance, InputVat],
+            PostingEvent::GoodsReceived => &[Inventory, GoodsReceivedNotInvoiced],
+        }
+    }
+}
+
+impl std::str::FromStr for PostingEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "VENDOR_INVOICE_APPROVED" => Ok(PostingEvent::VendorInvoiceApproved),
+            "VENDOR_PAYMENT_MADE" => Ok(PostingEvent::VendorPaymentMade),
+            "VENDOR_CREDIT_RECEIVED" => Ok(PostingEvent::VendorCreditReceived),
+            "VENDOR_REFUND_RECEIVED" => Ok(PostingEvent::VendorRefundReceived),
+            "VENDOR_ADVANCE_PAID" => Ok(PostingEvent::VendorAdvancePaid),
+            "VENDOR_ADVANCE_APPLIED" => Ok(PostingEvent::VendorAdvanceApplied),
+            "VENDOR_ADVANCE_REFUNDED" => Ok(PostingEvent::VendorAdvanceRefunded),
+            "GOODS_RECEIVED" => Ok(PostingEvent::GoodsReceived),
+            _ => Err(format!("Invalid posting event: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PostingEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostingEvent::VendorInvoiceApproved => write!(f, "VENDOR_INVOICE_APPROVED"),
+            PostingEvent::VendorPaymentMade => write!(f, "VENDOR_PAYMENT_MADE"),
+            PostingEvent::VendorCreditReceived => write!(f, "VENDOR_CREDIT_RECEIVED"),
+            PostingEvent::VendorRefundReceived => write!(f, "VENDOR_REFUND_RECEIVED"),
+            PostingEvent::VendorAdvancePaid => write!(f, "VENDOR_ADVANCE_PAID"),
+            PostingEvent::VendorAdvanceApplied => write!(f, "VENDOR_ADVANCE_APPLIED"),
+            PostingEvent::VendorAdvanceRefunded => write!(f, "VENDOR_ADVANCE_REFUNDED"),
+            PostingEvent::GoodsReceived => write!(f, "GOODS_RECEIVED"),
+        }
+    }
+}
+
+/// The part an account plays in a posting event
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PostingAccountRole {
+    ApControl,
+    Expense,
+    InputVat,
+    Bank,
+    Inventory,
+    GoodsReceivedNotInvoiced,
+    WithholdingTax,
+    /// Early-payment discount taken from a vendor
+    PurchaseDiscount,
+    /// Down payments made to vendors ahead of their invoices
+    VendorAdvance,
+    /// Rate difference realized when a foreign-currency document is settled
+    RealizedFxGainLoss,
+    /// Difference between what received stock was booked at and what the
+    /// vendor invoices for it
+    PurchasePriceVariance,
+}
+
+impl PostingAccountRole {
+    /// Account types an account must have to fill the role
+    pub fn account_types(&self) -> &'static [&'static str] {
+        match self {
+            PostingAccountRole::ApControl
+            | PostingAccountRole::GoodsReceivedNotInvoiced
+            | PostingAccountRole::WithholdingTax => &["LIABILITY"],
+            PostingAccountRole::InputVat
+            | PostingAccountRole::Bank
+            | PostingAccountRole::Inventory
+            | PostingAccountRole::VendorAdvance => &["ASSET"],
+            // Purchases may be capitalised
+            PostingAccountRole::Expense => &["EXPENSE", "ASSET"],
+            PostingAccountRole::PurchasePriceVariance => &["EXPENSE"],
+            PostingAccountRole::PurchaseDiscount
+            | PostingAccountRole::RealizedFxGainLoss => &["EXPENSE", "REVENUE"],
+        }
+    }
+
+    /// Account used when no rule matches, from the default Indonesian chart
+    pub fn default_account_code(&self) -> Option<&'static str> {
+        match self {
+            PostingAccountRole::ApControl => Some("2100"),
+            PostingAccountRole::InputVat => Some("1410"),
+            PostingAccountRole::Bank => Some("1110"),
+            PostingAccountRole::Inventory => Some("1300"),
+            PostingAccountRole::GoodsReceivedNotInvoiced => Some("2150"),
+            PostingAccountRole::WithholdingTax => Some("2122"),
+            PostingAccountRole::PurchaseDiscount => Some("4210"),
+            PostingAccountRole::VendorAdvance => Some("1420"),
+            PostingAccountRole::RealizedFxGainLoss => Some("4220"),
+            PostingAccountRole::PurchasePriceVariance => Some("5110"),
+            PostingAccountRole::Expense => None,
+        }
+    }
+}
+
+impl std::str::FromStr for PostingAccountRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "AP_CONTROL" => Ok(PostingAccountRole::ApControl),
+            "EXPENSE" => Ok(PostingAccountRole::Expense),
+            "INPUT_VAT" => Ok(PostingAccountRole::InputVat),
+            "BANK" => Ok(PostingAccountRole::Bank),
+            "INVENTORY" => Ok(PostingAccountRole::Inventory),
+            "GOODS_RECEIVED_NOT_INVOICED" => Ok(PostingAccountRole::GoodsReceivedNotInvoiced),
+            "WITHHOLDING_TAX" => Ok(PostingAccountRole::WithholdingTax),
+            "PURCHASE_DISCOUNT" => Ok(PostingAccountRole::PurchaseDiscount),
+            "VENDOR_ADVANCE" => Ok(PostingAccountRole::VendorAdvance),
+            "REALIZED_FX_GAIN_LOSS" => Ok(PostingAccountRole::RealizedFxGainLoss),
+            "PURCHASE_PRICE_VARIANCE" => Ok(PostingAccountRole::PurchasePriceVariance),
+            _ => Err(format!("Invalid account role: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PostingAccountRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostingAccountRole::ApControl => write!(f, "AP_CONTROL"),
+            PostingAccountRole::Expense => write!(f, "EXPENSE"),
+            PostingAccountRole::InputVat => write!(f, "INPUT_VAT"),
+            PostingAccountRole::Bank => write!(f, "BANK"),
+            PostingAccountRole::Inventory => write!(f, "INVENTORY"),
+            PostingAccountRole::GoodsReceivedNotInvoiced => write!(f, "GOODS_RECEIVED_NOT_INVOICED"),
+            PostingAccountRole::WithholdingTax => write!(f, "WITHHOLDING_TAX"),
+            PostingAccountRole::PurchaseDiscount => write!(f, "PURCHASE_DISCOUNT"),
+            PostingAccountRole::VendorAdvance => write!(f, "VENDOR_ADVANCE"),
+            PostingAccountRole::RealizedFxGainLoss => write!(f, "REALIZED_FX_GAIN_LOSS"),
+            PostingAccountRole::PurchasePriceVariance => write!(f, "PURCHASE_PRICE_VARIANCE"),
+        }
+    }
+}
+
+/// Maps an event's account role to a GL account. The criteria are optional
+/// and narrow the rule down; when several rules match, the most specific
+/// one wins.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostingRule {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub event_type: String,
+    pub account_role: String,
+    pub vendor_group: Option<String>,
+    pub item_category: Option<String>,
+    pub tax_type: Option<String>,
+    pub account_id: Uuid,
+    pub account_code: Option<String>,
+    pub account_name: Option<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreatePostingRuleRequest {
+    pub event_type: PostingEvent,
+    pub account_role: PostingAccountRole,
+    #[validate(length(min = 1, max = 50, message = "Vendor group must be 1-50 characters"))]
+    pub vendor_group: Option<String>,
+    #[validate(length(min = 1, max = 20, message = "Item category must be 1-20 characters"))]
+    pub item_category: Option<String>,
+    #[validate(length(min = 1, max = 20, message = "Tax type must be 1-20 characters"))]
+    pub tax_type: Option<String>,
+    pub account_id: Uuid,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePostingRuleRequest {
+    pub account_id: Uuid,
+    pub description: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolvePostingAccountRequest {
+    pub event_type: PostingEvent,
+    pub account_role: PostingAccountRole,
+    pub vendor_group: Option<String>,
+    pub item_category: Option<String>,
+    pub tax_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolvedPostingAccount {
+    pub account_id: Uuid,
+    /// The matching rule; none when the role's default account was used
+    pub rule_id: Option<Uuid>,
+}
+
+/// Invariants checked by the ledger integrity verifier
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IntegrityCheck {
+    /// Posted entry whose lines do not balance
+    UnbalancedEntry,
+    /// Header total_debit/total_credit differ from the sum of the lines
+    EntryTotals,
+    /// Line posted to a missing, inactive or other-company account
+    LineAccount,
+    /// `account_balances` differs from a recomputation from posted lines
+    AccountBalances,
+    /// Open vendor invoices differ from the AP control account
+    PayablesControl,
+    /// Open customer invoices differ from the AR control account
+    ReceivablesControl,
+}
+
+impl IntegrityCheck {
+    pub const ALL: [IntegrityCheck; 6] = [
+        IntegrityCheck::UnbalancedEntry,
+        IntegrityCheck::EntryTotals,
+        IntegrityCheck::LineAccount,
+        IntegrityCheck::AccountBalances,
+        IntegrityCheck::PayablesControl,
+        IntegrityCheck::ReceivablesControl,
+    ];
+}
+
+impl std::fmt::Display for IntegrityCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityCheck::UnbalancedEntry => write!(f, "UNBALANCED_ENTRY"),
+            IntegrityCheck::EntryTotals => write!(f, "ENTRY_TOTALS"),
+            IntegrityCheck::LineAccount => write!(f, "LINE_ACCOUNT"),
+            IntegrityCheck::AccountBalances => write!(f, "ACCOUNT_BALANCES"),
+            IntegrityCheck::PayablesControl => write!(f, "PAYABLES_CONTROL"),
+            IntegrityCheck::ReceivablesControl => write!(f, "RECEIVABLES_CONTROL"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FindingSeverity {
+    /// The books are wrong and need correcting
+    Error,
+    /// Worth a look, e.g. a check that could not run
+    Warning,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityFinding {
+    pub check: IntegrityCheck,
+    pub severity: FindingSeverity,
+    /// JOURNAL_ENTRY, JOURNAL_LINE, ACCOUNT or ACCOUNT_BALANCE
+    pub entity_type: String,
+    pub entity_id: Option<Uuid>,
+    /// Entry number or account code, for people reading the report
+    pub reference: Option<String>,
+    pub message: String,
+    pub expected: Option<Decimal>,
+    pub actual: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityCheckResult {
+    pub check: IntegrityCheck,
+    pub passed: bool,
+    pub error_count: u32,
+    pub warning_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub company_id: Uuid,
+    pub checks: Vec<IntegrityCheckResult>,
+    pub findings: Vec<IntegrityFinding>,
+    pub is_clean: bool,
+    pub checked_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FiscalPeriod {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub fiscal_year: i32,
+    pub period_number: i32,
+    pub period_name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub status: PeriodStatus,
+    pub closed_by: Option<Uuid>,
+    pub closed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct GenerateFiscalPeriodsRequest {
+    #[validate(range(min = 2000, max = 2100, message = "Fiscal year must be between 2000 and 2100"))]
+    pub fiscal_year: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerSettings {
+    pub company_id: Uuid,
+    pub retained_earnings_account_id: Option<Uuid>,
+    pub intercompany_receivable_account_id: Option<Uuid>,
+    pub intercompany_payable_account_id: Option<Uuid>,
+    pub unrealized_fx_gain_account_id: Option<Uuid>,
+    pub unrealized_fx_loss_account_id: Option<Uuid>,
+    /// Control accounts the AP/AR subledgers are reconciled against
+    pub ap_control_account_id: Option<Uuid>,
+    pub ar_control_account_id: Option<Uuid>,
+    /// Warn when a journal entry would exceed the remaining active budget
+    pub budget_warnings: bool,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateLedgerSettingsRequest {
+    pub retained_earnings_account_id: Option<Uuid>,
+    pub intercompany_receivable_account_id: Option<Uuid>,
+    pub intercompany_payable_account_id: Option<Uuid>,
+    pub unrealized_fx_gain_account_id: Option<Uuid>,
+    pub unrealized_fx_loss_account_id: Option<Uuid>,
+    pub ap_control_account_id: Option<Uuid>,
+    pub ar_control_account_id: Option<Uuid>,
+    #[serde(default)]
+    pub budget_warnings: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateDocumentNumberFormatRequest {
+    #[validate(length(min = 1, max = 100, message = "Pattern must be 1-100 characters"))]
+    pub pattern: String,
+}
+
+/// A charge between two companies. `lines` are booked in the requesting
+/// company and `counterparty_lines` in the counterparty; each side is
+/// balanced against its configured intercompany receivable or payable account.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateIntercompanyEntryRequest {
+    pub counterparty_company_id: Uuid,
+    pub entry_date: NaiveDate,
+    pub description: Option<String>,
+    #[validate(length(max = 100))]
+    pub reference: Option<String>,
+    #[validate(length(min = 1, message = "At least one line is required"))]
+    pub lines: Vec<CreateJournalEntryLineRequest>,
+    #[validate(length(min = 1, message = "At least one counterparty line is required"))]
+    pub counterparty_lines: Vec<CreateJournalEntryLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntercompanyTransaction {
+    pub id: Uuid,
+    pub source_company_id: Uuid,
+    pub target_company_id: Uuid,
+    pub source_entry_id: Uuid,
+    pub target_entry_id: Uuid,
+    pub source_account_id: Uuid,
+    pub target_account_id: Uuid,
+    pub amount: Decimal,
+    pub entry_date: NaiveDate,
+    pub description: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntercompanyEntryResult {
+    pub transaction: IntercompanyTransaction,
+    pub source_entry: JournalEntryWithLines,
+    pub target_entry: JournalEntryWithLines,
+}
+
+/// Net intercompany position against one counterparty as recorded by each
+/// side. A positive balance is a receivable; the two should cancel out.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntercompanyBalance {
+    pub counterparty_company_id: Uuid,
+    pub our_balance: Decimal,
+    pub counterparty_balance: Decimal,
+    pub difference: Decimal,
+}
+
+/// An intercompany transaction whose two entries are no longer in step,
+/// e.g. because one side was reversed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntercompanyLegMismatch {
+    pub transaction_id: Uuid,
+    pub counterparty_company_id: Uuid,
+    pub entry_date: NaiveDate,
+    pub our_entry_number: String,
+    pub our_status: String,
+    pub our_reversed: bool,
+    pub counterparty_entry_number: String,
+    pub counterparty_status: String,
+    pub counterparty_reversed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntercompanyMismatchReport {
+    pub company_id: Uuid,
+    pub as_of_date: NaiveDate,
+    pub balances: Vec<IntercompanyBalance>,
+    pub mismatched_transactions: Vec<IntercompanyLegMismatch>,
+    pub is_reconciled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateExchangeRateRequest {
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter code"))]
+    pub currency: String,
+    pub rate_type: RateType,
+    pub effective_date: NaiveDate,
+    pub rate: Decimal,
+    #[validate(length(max = 100))]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateExchangeRateRequest {
+    pub rate: Decimal,
+    #[validate(length(max = 100))]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExchangeRateImportError {
+    pub row_number: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExchangeRateImportResult {
+    pub rate_type: RateType,
+    pub rows_read: usize,
+    pub committed: bool,
+    pub rates: Vec<ExchangeRate>,
+    pub errors: Vec<ExchangeRateImportError>,
+}
+
+/// Period-end restatement of open foreign-currency balances. `rates` maps
+/// currency codes to the closing rate in the functional currency; currencies
+/// not listed use the exchange-rate table (BI middle rate by default).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxRevaluationRequest {
+    pub revaluation_date: NaiveDate,
+    #[serde(default)]
+    pub rates: std::collections::HashMap<String, Decimal>,
+    pub rate_type: Option<RateType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxRevaluation {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub revaluation_date: NaiveDate,
+    pub journal_entry_id: Uuid,
+    pub rates: serde_json::Value,
+    pub total_gain: Decimal,
+    pub total_loss: Decimal,
+    pub created_by: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxRevaluationLine {
+    pub account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub currency: String,
+    pub foreign_balance: Decimal,
+    pub book_balance: Decimal,
+    pub rate: Decimal,
+    pub revalued_balance: Decimal,
+    pub adjustment: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxRevaluationResult {
+    pub revaluation_date: NaiveDate,
+    pub reverses_on: NaiveDate,
+    pub lines: Vec<FxRevaluationLine>,
+    pub total_gain: Decimal,
+    pub total_loss: Decimal,
+    pub revaluation: Option<FxRevaluation>,
+    pub journal_entry: Option<JournalEntryWithLines>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct YearEndCloseRequest {
+    #[validate(range(min = 2000, max = 2100, message = "Fiscal year must be between 2000 and 2100"))]
+    pub fiscal_year: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct YearEndClosing {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub fiscal_year: i32,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub journal_entry_id: Uuid,
+    pub retained_earnings_account_id: Uuid,
+    pub net_income: Decimal,
+    pub closed_by: Uuid,
+    pub closed_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct YearEndCloseResult {
+    pub fiscal_year: i32,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub accounts_closed: u32,
+    pub net_income: Decimal,
+    pub closing: Option<YearEndClosing>,
+    pub journal_entry: Option<JournalEntryWithLines>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub account_type: String,
+    pub normal_balance: String,
+    pub balance_date: Option<NaiveDate>,
+    pub beginning_balance: Decimal,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrialBalanceLine {
+    pub account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub account_type: String,
+    pub dimension_value: Option<String>,
+    pub total_debits: Decimal,
+    pub total_credits: Decimal,
+    pub debit_balance: Decimal,
+    pub credit_balance: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrialBalance {
+    pub company_id: Uuid,
+    pub as_of_date: NaiveDate,
+    pub filter: DimensionFilter,
+    pub pivot: Option<DimensionType>,
+    pub accounts: Vec<TrialBalanceLine>,
+    pub total_debits: Decimal,
+    pub total_credits: Decimal,
+    pub is_balanced: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceRebuildResult {
+    pub company_id: Uuid,
+    pub accounts_rebuilt: u32,
+    pub balance_rows: u32,
+    pub rebuilt_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,72 @@
+//! Background jobs for the general ledger service
+
+use crate::services::{AmortizationService, IntegrityService, JournalService, RecurringService};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+pub async fn run(pool: PgPool) {
+    let interval_secs = std::env::var("GENERAL_LEDGER_SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(3600);
+
+    let journal_service = JournalService::new(pool.clone());
+    let recurring_service = RecurringService::new(pool.clone());
+    let amortization_service = AmortizationService::new(pool.clone());
+    let integrity_service = IntegrityService::new(pool);
+    let mut last_integrity_check = None;
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+
+    info!("Ledger scheduler running every {} seconds", interval_secs);
+
+    loop {
+        ticker.tick().await;
+        let today = chrono::Utc::now().date_naive();
+
+        // Recurring templates, including runs missed while the service was down
+        match recurring_service.run_due_templates(today).await {
+            Ok(summary) if summary.entries_created > 0 || summary.templates_failed > 0 => info!(
+                "Recurring run created {} entries from {} templates ({} failed)",
+                summary.entries_created, summary.templates_processed, summary.templates_failed
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Recurring template run failed: {}", e),
+        }
+
+        // Amortization periods that have fallen due
+        match amortization_service.run_due_schedules(today).await {
+            Ok(summary) if summary.entries_created > 0 || summary.schedules_failed > 0 => info!(
+                "Amortization run created {} entries from {} schedules ({} failed)",
+                summary.entries_created, summary.schedules_processed, summary.schedules_failed
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Amortization run failed: {}", e),
+        }
+
+        // Auto-reversals scheduled for today or earlier
+        match journal_service.process_due_reversals(today).await {
+            Ok(0) => {}
+            Ok(count) => info!("Posted {} automatic reversals", count),
+            Err(e) => error!("Automatic reversal run failed: {}", e),
+        }
+
+        // Integrity verification, once a day
+        if last_integrity_check != Some(today) {
+            match integrity_service.verify_all().await {
+                Ok(reports) => {
+                    for report in reports.iter().filter(|r| !r.is_clean) {
+                        let failed: Vec<String> = report.checks
+                            .iter()
+                            .filter(|c| !c.passed)
+                            .map(|c| format!("{} ({})", c.check, c.error_count))
+                            .collect();
+                        warn!("Ledger integrity issues for company {}: {}", report.company_id, failed.join(", "));
+                    }
+                    last_integrity_check = Some(today);
+                }
+                Err(e) => error!("Ledger integrity check failed: {}", e),
+            }
+        }
+    }
+}
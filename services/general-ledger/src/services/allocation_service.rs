@@ -0,0 +1,625 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const ALLOCATION_SOURCE: &str = "ALLOCATION";
+
+pub struct AllocationService {
+    db: PgPool,
+    journal_service: JournalService,
+}
+
+impl AllocationService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn create_rule(
+        &self,
+        request: CreateAllocationRuleRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<AllocationRuleWithTargets> {
+        self.validate_rule(
+            company_id,
+            request.source_account_id,
+            &request.source_cost_center,
+            request.basis,
+            &request.targets,
+        ).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let rule_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO allocation_rules
+            (id, company_id, rule_name, description, source_account_id, source_cost_center, basis,
+             driver_name, is_active, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, true, $9, NOW(), NOW())
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.rule_name,
+            request.description,
+            request.source_account_id,
+            request.source_cost_center,
+            request.basis.to_string(),
+            request.driver_name,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.insert_targets(&mut tx, rule_id, &request.targets).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created allocation rule {} with {} targets for company {}",
+            request.rule_name, request.targets.len(), company_id);
+
+        self.get_rule(rule_id, company_id).await
+    }
+
+    pub async fn get_rules(
+        &self,
+        company_id: Uuid,
+        is_active: Option<bool>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<AllocationRule>> {
+        sqlx::query_as!(
+            AllocationRule,
+            r#"
+            SELECT id, company_id, rule_name, description, source_account_id, source_cost_center, basis,
+                   driver_name, is_active as "is_active!", created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM allocation_rules
+            WHERE company_id = $1 AND ($2::boolean IS NULL OR is_active = $2)
+            ORDER BY rule_name
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            is_active,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_rule(&self, rule_id: Uuid, company_id: Uuid) -> ServiceResult<AllocationRuleWithTargets> {
+        let rule = sqlx::query_as!(
+            AllocationRule,
+            r#"
+            SELECT id, company_id, rule_name, description, source_account_id, source_cost_center, basis,
+                   driver_name, is_active as "is_active!", created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM allocation_rules
+            WHERE id = $1 AND company_id = $2
+            "#,
+            rule_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Allocation rule not found".to_string()))?;
+
+        let targets = sqlx::query_as!(
+            AllocationRuleTarget,
+            r#"
+            SELECT id, rule_id, line_number, cost_center, account_id, department, weight
+            FROM allocation_rule_targets
+            WHERE rule_id = $1
+            ORDER BY line_number
+            "#,
+            rule_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(AllocationRuleWithTargets { rule, targets })
+    }
+
+    /// Replaces the rule's settings and targets. Runs already posted keep
+    /// the split they were made with.
+    pub async fn update_rule(
+        &self,
+        rule_id: Uuid,
+        company_id: Uuid,
+        request: UpdateAllocationRuleRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<AllocationRuleWithTargets> {
+        let existing = self.get_rule(rule_id, company_id).await?;
+
+        self.validate_rule(
+            company_id,
+            existing.rule.source_account_id,
+            &request.source_cost_center,
+            request.basis,
+            &request.targets,
+        ).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE allocation_rules
+            SET rule_name = $1, description = $2, source_cost_center = $3, basis = $4, driver_name = $5,
+                is_active = $6, updated_at = NOW()
+            WHERE id = $7
+            "#,
+            request.rule_name,
+            request.description,
+            request.source_cost_center,
+            request.basis.to_string(),
+            request.driver_name,
+            request.is_active,
+            rule_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Replace the targets wholesale
+        sqlx::query!("DELETE FROM allocation_rule_targets WHERE rule_id = $1", rule_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        self.insert_targets(&mut tx, rule_id, &request.targets).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Allocation rule {} updated by user {}", rule_id, user_id);
+
+        self.get_rule(rule_id, company_id).await
+    }
+
+    /// Rules that have been run are kept for the audit trail and can only
+    /// be deactivated.
+    pub async fn delete_rule(&self, rule_id: Uuid, company_id: Uuid, user_id: Uuid) -> ServiceResult<()> {
+        let has_runs = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM allocation_runs WHERE rule_id = $1) as "exists!""#,
+            rule_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if has_runs {
+            return Err(ServiceError::Conflict(
+                "Allocation rule has been run; deactivate it instead".to_string()
+            ));
+        }
+
+        let result = sqlx::query!(
+            "DELETE FROM allocation_rules WHERE id = $1 AND company_id = $2",
+            rule_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Allocation rule not found".to_string()));
+        }
+
+        tracing::info!("Allocation rule {} deleted by user {}", rule_id, user_id);
+
+        Ok(())
+    }
+
+    /// What a run would post: the pool's balance for the period split over
+    /// the targets by weight. Nothing is written.
+    pub async fn preview_allocation(
+        &self,
+        rule_id: Uuid,
+        company_id: Uuid,
+        request: &AllocationRunRequest,
+    ) -> ServiceResult<AllocationPreview> {
+        if request.period_start > request.period_end {
+            return Err(ServiceError::Validation("Period start must not be after period end".to_string()));
+        }
+
+        let AllocationRuleWithTargets { rule, targets } = self.get_rule(rule_id, company_id).await?;
+
+        let source_amount = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(jel.debit_amount - jel.credit_amount), 0) as "balance!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            WHERE je.company_id = $1 AND jel.account_id = $2 AND je.status = 'POSTED'
+              AND je.entry_date BETWEEN $3 AND $4
+              AND ($5::text IS NULL OR jel.cost_center = $5)
+              AND COALESCE(je.source_document_type, '') <> $6
+              AND NOT EXISTS (
+                  SELECT 1 FROM journal_entries orig
+                  WHERE je.source_document_type = 'JOURNAL_REVERSAL'
+                    AND orig.id = je.source_document_id AND orig.source_document_type = $6
+              )
+            "#,
+            company_id,
+            rule.source_account_id,
+            request.period_start,
+            request.period_end,
+            rule.source_cost_center,
+            ALLOCATION_SOURCE
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if source_amount <= Decimal::ZERO {
+            return Err(ServiceError::Validation(format!(
+                "Nothing to allocate: the pool balance for {} to {} is {}",
+                request.period_start, request.period_end, source_amount
+            )));
+        }
+
+        let weights: Vec<Decimal> = targets.iter().map(|t| t.weight).collect();
+        let total_weight: Decimal = weights.iter().sum();
+        let amounts = split_by_weight(source_amount, &weights);
+
+        let lines = targets
+            .into_iter()
+            .zip(amounts)
+            .map(|(target, amount)| AllocationPreviewLine {
+                cost_center: target.cost_center,
+                account_id: target.account_id.unwrap_or(rule.source_account_id),
+                department: target.department,
+                share: (target.weight * Decimal::ONE_HUNDRED / total_weight).round_dp(4),
+                weight: target.weight,
+                amount,
+            })
+            .collect();
+
+        Ok(AllocationPreview {
+            rule_id: rule.id,
+            rule_name: rule.rule_name,
+            source_account_id: rule.source_account_id,
+            source_cost_center: rule.source_cost_center,
+            period_start: request.period_start,
+            period_end: request.period_end,
+            entry_date: request.entry_date.unwrap_or(request.period_end),
+            source_amount,
+            lines,
+        })
+    }
+
+    /// Posts the previewed split as one balanced entry: the pool is credited
+    /// and each target cost center debited. A rule runs once per period
+    /// unless the earlier entry has been reversed.
+    pub async fn run_allocation(
+        &self,
+        rule_id: Uuid,
+        company_id: Uuid,
+        request: AllocationRunRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<AllocationRunResult> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // One run per rule at a time
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext('allocation_rule:' || $1::text))",
+            rule_id.to_string()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let is_active = sqlx::query_scalar!(
+            r#"SELECT is_active as "is_active!" FROM allocation_rules WHERE id = $1 AND company_id = $2"#,
+            rule_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Allocation rule not found".to_string()))?;
+
+        if !is_active {
+            return Err(ServiceError::Validation("Allocation rule is inactive".to_string()));
+        }
+
+        let already_run = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM allocation_runs r
+                JOIN journal_entries je ON je.id = r.journal_entry_id
+                WHERE r.rule_id = $1 AND r.period_start <= $3 AND r.period_end >= $2
+                  AND je.reversed_by_entry_id IS NULL
+            ) as "exists!"
+            "#,
+            rule_id,
+            request.period_start,
+            request.period_end
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if already_run {
+            return Err(ServiceError::Conflict(format!(
+                "Rule has already been run for a period overlapping {} to {}; reverse that entry first",
+                request.period_start, request.period_end
+            )));
+        }
+
+        let preview = self.preview_allocation(rule_id, company_id, &request).await?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, preview.entry_date, roles).await?;
+
+        let description = format!(
+            "{} allocation {} to {}",
+            preview.rule_name, preview.period_start, preview.period_end
+        );
+
+        let mut lines: Vec<CreateJournalEntryLineRequest> = preview.lines
+            .iter()
+            .map(|line| CreateJournalEntryLineRequest {
+                account_id: line.account_id,
+                description: Some(description.clone()),
+                debit_amount: line.amount,
+                credit_amount: Decimal::ZERO,
+                department: line.department.clone(),
+                project_code: None,
+                cost_center: Some(line.cost_center.clone()),
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            })
+            .collect();
+
+        lines.push(CreateJournalEntryLineRequest {
+            account_id: preview.source_account_id,
+            description: Some(description.clone()),
+            debit_amount: Decimal::ZERO,
+            credit_amount: preview.source_amount,
+            department: None,
+            project_code: None,
+            cost_center: preview.source_cost_center.clone(),
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        });
+
+        let run_id = Uuid::new_v4();
+
+        let journal_entry = self.journal_service
+            .insert_entry(
+                &mut tx,
+                CreateJournalEntryRequest {
+                    company_id,
+                    entry_date: preview.entry_date,
+                    description: Some(description),
+                    reference: None,
+                    auto_reverse_on: None,
+                    auto_reverse: false,
+                    lines,
+                },
+                EntryOrigin::system(ALLOCATION_SOURCE, run_id),
+                user_id,
+            )
+            .await?;
+
+        let run = sqlx::query_as!(
+            AllocationRun,
+            r#"
+            INSERT INTO allocation_runs
+            (id, company_id, rule_id, period_start, period_end, source_amount, journal_entry_id, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            RETURNING id, company_id, rule_id, period_start, period_end, source_amount, journal_entry_id,
+                      created_by, created_at as "created_at!"
+            "#,
+            run_id,
+            company_id,
+            rule_id,
+            preview.period_start,
+            preview.period_end,
+            preview.source_amount,
+            journal_entry.journal_entry.id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Allocation rule {} posted {} over {} cost centers in {}",
+            preview.rule_name, preview.source_amount, preview.lines.len(), journal_entry.journal_entry.entry_number);
+
+        Ok(AllocationRunResult { run, journal_entry })
+    }
+
+    pub async fn get_runs(
+        &self,
+        company_id: Uuid,
+        rule_id: Option<Uuid>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<AllocationRun>> {
+        sqlx::query_as!(
+            AllocationRun,
+            r#"
+            SELECT id, company_id, rule_id, period_start, period_end, source_amount, journal_entry_id,
+                   created_by, created_at as "created_at!"
+            FROM allocation_runs
+            WHERE company_id = $1 AND ($2::uuid IS NULL OR rule_id = $2)
+            ORDER BY period_end DESC, created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            rule_id,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    async fn validate_rule(
+        &self,
+        company_id: Uuid,
+        source_account_id: Uuid,
+        source_cost_center: &Option<String>,
+        basis: AllocationBasis,
+        targets: &[AllocationTargetRequest],
+    ) -> ServiceResult<()> {
+        check_targets(basis, targets).map_err(ServiceError::Validation)?;
+
+        let mut account_ids: Vec<Uuid> = targets
+            .iter()
+            .filter_map(|t| t.account_id)
+            .chain(std::iter::once(source_account_id))
+            .collect();
+        account_ids.sort();
+        account_ids.dedup();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, company_id).await?;
+
+        let dimension_line = |cost_center: Option<String>, department: Option<String>| CreateJournalEntryLineRequest {
+            account_id: source_account_id,
+            description: None,
+            debit_amount: Decimal::ZERO,
+            credit_amount: Decimal::ZERO,
+            department,
+            project_code: None,
+            cost_center,
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        };
+
+        let lines: Vec<CreateJournalEntryLineRequest> = targets
+            .iter()
+            .map(|t| dimension_line(Some(t.cost_center.clone()), t.department.clone()))
+            .chain(std::iter::once(dimension_line(source_cost_center.clone(), None)))
+            .collect();
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, &lines).await
+    }
+
+    async fn insert_targets(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        rule_id: Uuid,
+        targets: &[AllocationTargetRequest],
+    ) -> ServiceResult<()> {
+        for (index, target) in targets.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO allocation_rule_targets
+                (id, rule_id, line_number, cost_center, account_id, department, weight)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                Uuid::new_v4(),
+                rule_id,
+                (index + 1) as i32,
+                target.cost_center,
+                target.account_id,
+                target.department,
+                target.weight
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Targets need a cost center and a positive weight, percentages must add
+/// up to 100 and each cost center/account pair may appear only once.
+fn check_targets(basis: AllocationBasis, targets: &[AllocationTargetRequest]) -> Result<(), String> {
+    if targets.is_empty() {
+        return Err("Rule must have at least one target".to_string());
+    }
+    if targets.iter().any(|t| t.cost_center.trim().is_empty()) {
+        return Err("Every target needs a cost center".to_string());
+    }
+    if targets.iter().any(|t| t.weight <= Decimal::ZERO) {
+        return Err("Every target weight must be positive".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    for target in targets {
+        if !seen.insert((target.cost_center.as_str(), target.account_id)) {
+            return Err(format!("Cost center {} appears more than once", target.cost_center));
+        }
+    }
+
+    if basis == AllocationBasis::FixedPercentage {
+        let total: Decimal = targets.iter().map(|t| t.weight).sum();
+        if total != Decimal::ONE_HUNDRED {
+            return Err(format!("Percentages add up to {} instead of 100", total));
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits `total` in proportion to `weights`, rounded to whole cents, with
+/// the rounding difference on the last target so the lines add up exactly.
+fn split_by_weight(total: Decimal, weights: &[Decimal]) -> Vec<Decimal> {
+    let total_weight: Decimal = weights.iter().sum();
+    let mut amounts: Vec<Decimal> = weights
+        .iter()
+        .map(|w| (total * w / total_weight).round_dp(2))
+        .collect();
+
+    if let Some(last) = amounts.len().checked_sub(1) {
+        let others: Decimal = amounts[..last].iter().sum();
+        amounts[last] = total - others;
+    }
+
+    amounts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn target(cost_center: &str, weight: i64) -> AllocationTargetRequest {
+        AllocationTargetRequest {
+            cost_center: cost_center.to_string(),
+            account_id: None,
+            department: None,
+            weight: Decimal::from(weight),
+        }
+    }
+
+    #[test]
+    fn test_split_by_weight_keeps_total() {
+        let amounts = split_by_weight(Decimal::from(1_000_000), &[Decimal::ONE; 3]);
+        assert_eq!(amounts[0], Decimal::from_str("333333.33").unwrap());
+        assert_eq!(amounts[2], Decimal::from_str("333333.34").unwrap());
+
+        // Headcount 12, 5 and 3
+        let amounts = split_by_weight(Decimal::from(7_500_000), &[12, 5, 3].map(Decimal::from));
+        assert_eq!(amounts, [4_500_000, 1_875_000, 1_125_000].map(Decimal::from));
+    }
+
+    #[test]
+    fn test_check_targets() {
+        let fixed = AllocationBasis::FixedPercentage;
+        assert!(check_targets(fixed, &[target("PROD", 60), target("SALES", 40)]).is_ok());
+        assert!(check_targets(fixed, &[target("PROD", 60), target("SALES", 30)]).is_err());
+        assert!(check_targets(fixed, &[target("PROD", 50), target("PROD", 50)]).is_err());
+        assert!(check_targets(AllocationBasis::Driver, &[target("PROD", 12), target("SALES", 0)]).is_err());
+        assert!(check_targets(AllocationBasis::Driver, &[target("PROD", 12), target("SALES", 5)]).is_ok());
+    }
+}
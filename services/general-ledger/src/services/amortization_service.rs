@@ -0,0 +1,482 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService, SettingsService};
+use chrono::{Months, NaiveDate};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct AmortizationService {
+    db: PgPool,
+    journal_service: JournalService,
+    settings_service: SettingsService,
+}
+
+impl AmortizationService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            settings_service: SettingsService::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates a schedule that moves `total_amount` from the balance account
+    /// (prepaid asset or accrued liability) to the expense account, one
+    /// monthly period at a time starting on `start_date`.
+    pub async fn create_schedule(
+        &self,
+        request: CreateAmortizationScheduleRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<AmortizationScheduleWithLines> {
+        let amounts = period_amounts(request.method, request.total_amount, request.periods, &request.amounts)
+            .map_err(ServiceError::Validation)?;
+        let dates = period_dates(request.start_date, request.periods)
+            .ok_or_else(|| ServiceError::Validation("Schedule runs past the supported date range".to_string()))?;
+
+        if request.balance_account_id == request.expense_account_id {
+            return Err(ServiceError::Validation(
+                "Balance and expense accounts must be different".to_string()
+            ));
+        }
+
+        self.settings_service
+            .validate_account_type(company_id, request.balance_account_id, request.schedule_type.balance_account_type())
+            .await?;
+        self.settings_service
+            .validate_account_type(company_id, request.expense_account_id, "EXPENSE")
+            .await?;
+
+        let expense_line = CreateJournalEntryLineRequest {
+            account_id: request.expense_account_id,
+            description: None,
+            debit_amount: Decimal::ZERO,
+            credit_amount: Decimal::ZERO,
+            department: request.department.clone(),
+            project_code: request.project_code.clone(),
+            cost_center: request.cost_center.clone(),
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        };
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, &[expense_line]).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let schedule_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO amortization_schedules
+            (id, company_id, schedule_name, description, schedule_type, method, balance_account_id,
+             expense_account_id, total_amount, periods, start_date, auto_post, department, project_code,
+             cost_center, status, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, 'ACTIVE', $16, NOW(), NOW())
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.schedule_name,
+            request.description,
+            request.schedule_type.to_string(),
+            request.method.to_string(),
+            request.balance_account_id,
+            request.expense_account_id,
+            request.total_amount,
+            request.periods,
+            request.start_date,
+            request.auto_post.unwrap_or(true),
+            request.department,
+            request.project_code,
+            request.cost_center,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (index, (amount, period_date)) in amounts.into_iter().zip(dates).enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO amortization_schedule_lines (id, schedule_id, period_number, period_date, amount)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                Uuid::new_v4(),
+                schedule_id,
+                (index + 1) as i32,
+                period_date,
+                amount
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created amortization schedule {} over {} periods for company {}",
+            request.schedule_name, request.periods, company_id);
+
+        self.get_schedule(schedule_id, company_id).await
+    }
+
+    pub async fn get_schedules(
+        &self,
+        company_id: Uuid,
+        status: Option<String>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<AmortizationSchedule>> {
+        sqlx::query_as!(
+            AmortizationSchedule,
+            r#"
+            SELECT id, company_id, schedule_name, description, schedule_type, method, balance_account_id,
+                   expense_account_id, total_amount, periods, start_date, auto_post as "auto_post!",
+                   department, project_code, cost_center, status, created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM amortization_schedules
+            WHERE company_id = $1 AND ($2::text IS NULL OR status = $2)
+            ORDER BY start_date DESC, schedule_name
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            status.map(|s| s.to_uppercase()),
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// The schedule with every period and the balance left after it.
+    pub async fn get_schedule(
+        &self,
+        schedule_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<AmortizationScheduleWithLines> {
+        let schedule = sqlx::query_as!(
+            AmortizationSchedule,
+            r#"
+            SELECT id, company_id, schedule_name, description, schedule_type, method, balance_account_id,
+                   expense_account_id, total_amount, periods, start_date, auto_post as "auto_post!",
+                   department, project_code, cost_center, status, created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM amortization_schedules
+            WHERE id = $1 AND company_id = $2
+            "#,
+            schedule_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Amortization schedule not found".to_string()))?;
+
+        let lines = sqlx::query_as!(
+            AmortizationScheduleLine,
+            r#"
+            SELECT l.id, l.schedule_id, l.period_number, l.period_date, l.amount, l.journal_entry_id,
+                   s.total_amount - SUM(l.amount) OVER (ORDER BY l.period_number) as "remaining_balance!"
+            FROM amortization_schedule_lines l
+            JOIN amortization_schedules s ON s.id = l.schedule_id
+            WHERE l.schedule_id = $1
+            ORDER BY l.period_number
+            "#,
+            schedule_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let amortized_amount: Decimal = lines
+            .iter()
+            .filter(|l| l.journal_entry_id.is_some())
+            .map(|l| l.amount)
+            .sum();
+        let remaining_balance = schedule.total_amount - amortized_amount;
+
+        Ok(AmortizationScheduleWithLines { schedule, lines, amortized_amount, remaining_balance })
+    }
+
+    /// Stops an active schedule. Periods already booked keep their entries;
+    /// the rest are never generated.
+    pub async fn cancel_schedule(
+        &self,
+        schedule_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<AmortizationScheduleWithLines> {
+        let status = sqlx::query_scalar!(
+            "SELECT status FROM amortization_schedules WHERE id = $1 AND company_id = $2",
+            schedule_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Amortization schedule not found".to_string()))?;
+
+        if status != "ACTIVE" {
+            return Err(ServiceError::Conflict(format!("Schedule is already {}", status)));
+        }
+
+        sqlx::query!(
+            "UPDATE amortization_schedules SET status = 'CANCELLED', updated_at = NOW() WHERE id = $1",
+            schedule_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tracing::info!("Amortization schedule {} cancelled by user {}", schedule_id, user_id);
+
+        self.get_schedule(schedule_id, company_id).await
+    }
+
+    /// Books every period that has fallen due on active schedules, each
+    /// schedule in its own transaction. Periods missed while the runner was
+    /// down are caught up on the next pass.
+    pub async fn run_due_schedules(&self, as_of: NaiveDate) -> ServiceResult<AmortizationRunSummary> {
+        let due_schedules = sqlx::query_scalar!(
+            r#"
+            SELECT s.id FROM amortization_schedules s
+            WHERE s.status = 'ACTIVE'
+              AND EXISTS (
+                  SELECT 1 FROM amortization_schedule_lines l
+                  WHERE l.schedule_id = s.id AND l.journal_entry_id IS NULL AND l.period_date <= $1
+              )
+            ORDER BY s.start_date
+            "#,
+            as_of
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut summary = AmortizationRunSummary::default();
+
+        for schedule_id in due_schedules {
+            match self.run_schedule(schedule_id, as_of).await {
+                Ok(created) => {
+                    summary.schedules_processed += 1;
+                    summary.entries_created += created;
+                }
+                Err(e) => {
+                    summary.schedules_failed += 1;
+                    tracing::error!("Amortization schedule {} failed: {}", schedule_id, e);
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    async fn run_schedule(&self, schedule_id: Uuid, as_of: NaiveDate) -> ServiceResult<u32> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Skip schedules another runner is already working on
+        let schedule = match sqlx::query!(
+            r#"
+            SELECT company_id, schedule_name, description, balance_account_id, expense_account_id,
+                   periods, COALESCE(auto_post, true) as "auto_post!", department, project_code,
+                   cost_center, created_by
+            FROM amortization_schedules
+            WHERE id = $1 AND status = 'ACTIVE'
+            FOR UPDATE SKIP LOCKED
+            "#,
+            schedule_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        {
+            Some(schedule) => schedule,
+            None => return Ok(0),
+        };
+
+        let due_periods = sqlx::query!(
+            r#"
+            SELECT id, period_number, period_date, amount
+            FROM amortization_schedule_lines
+            WHERE schedule_id = $1 AND journal_entry_id IS NULL AND period_date <= $2
+            ORDER BY period_number
+            "#,
+            schedule_id,
+            as_of
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Accounts may have been deactivated since the schedule was set up
+        let account_ids = [schedule.expense_account_id, schedule.balance_account_id];
+        super::validation::validate_accounts_exist(&self.db, &account_ids, schedule.company_id).await?;
+
+        let mut created = 0;
+
+        for period in due_periods {
+            // System-generated entries never override a soft close
+            database::periods::ensure_period_open(&mut *tx, schedule.company_id, period.period_date, &[]).await?;
+
+            let line = |account_id: Uuid, debit_amount: Decimal, credit_amount: Decimal| CreateJournalEntryLineRequest {
+                account_id,
+                description: schedule.description.clone(),
+                debit_amount,
+                credit_amount,
+                department: schedule.department.clone(),
+                project_code: schedule.project_code.clone(),
+                cost_center: schedule.cost_center.clone(),
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            };
+
+            let request = CreateJournalEntryRequest {
+                company_id: schedule.company_id,
+                entry_date: period.period_date,
+                description: Some(format!(
+                    "{} ({}/{})",
+                    schedule.schedule_name, period.period_number, schedule.periods
+                )),
+                reference: None,
+                auto_reverse_on: None,
+                auto_reverse: false,
+                lines: vec![
+                    line(schedule.expense_account_id, period.amount, Decimal::ZERO),
+                    line(schedule.balance_account_id, Decimal::ZERO, period.amount),
+                ],
+            };
+
+            let entry = self.journal_service
+                .insert_entry(
+                    &mut tx,
+                    request,
+                    EntryOrigin::amortization(schedule_id, schedule.auto_post),
+                    schedule.created_by,
+                )
+                .await?;
+
+            sqlx::query!(
+                "UPDATE amortization_schedule_lines SET journal_entry_id = $1 WHERE id = $2",
+                entry.journal_entry.id,
+                period.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            created += 1;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE amortization_schedules
+            SET status = CASE
+                    WHEN EXISTS (
+                        SELECT 1 FROM amortization_schedule_lines
+                        WHERE schedule_id = $1 AND journal_entry_id IS NULL
+                    ) THEN status
+                    ELSE 'COMPLETED'
+                END,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            schedule_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        if created > 0 {
+            tracing::info!("Amortization schedule {} booked {} periods", schedule.schedule_name, created);
+        }
+
+        Ok(created)
+    }
+}
+
+/// Amount booked in each period. Straight-line splits the total evenly and
+/// puts the rounding difference in the last period; custom amounts are taken
+/// as given but must cover every period and add up to the total.
+fn period_amounts(
+    method: AmortizationMethod,
+    total_amount: Decimal,
+    periods: i32,
+    custom_amounts: &[Decimal],
+) -> Result<Vec<Decimal>, String> {
+    if total_amount <= Decimal::ZERO {
+        return Err("Total amount must be positive".to_string());
+    }
+    if periods < 1 {
+        return Err("Schedule must have at least one period".to_string());
+    }
+
+    let amounts = match method {
+        AmortizationMethod::StraightLine => {
+            if !custom_amounts.is_empty() {
+                return Err("Period amounts can only be given with the CUSTOM method".to_string());
+            }
+            let per_period = (total_amount / Decimal::from(periods)).round_dp(2);
+            let mut amounts = vec![per_period; periods as usize];
+            amounts[periods as usize - 1] = total_amount - per_period * Decimal::from(periods - 1);
+            amounts
+        }
+        AmortizationMethod::Custom => {
+            if custom_amounts.len() != periods as usize {
+                return Err(format!(
+                    "Expected {} period amounts, got {}",
+                    periods,
+                    custom_amounts.len()
+                ));
+            }
+            let sum: Decimal = custom_amounts.iter().sum();
+            if sum != total_amount {
+                return Err(format!(
+                    "Period amounts add up to {} but the total is {}",
+                    sum, total_amount
+                ));
+            }
+            custom_amounts.to_vec()
+        }
+    };
+
+    if amounts.iter().any(|a| *a <= Decimal::ZERO) {
+        return Err("Every period amount must be positive".to_string());
+    }
+
+    Ok(amounts)
+}
+
+/// Monthly period dates anchored on the start date, so month-end schedules
+/// stay on the month end.
+fn period_dates(start_date: NaiveDate, periods: i32) -> Option<Vec<NaiveDate>> {
+    (0..periods as u32)
+        .map(|offset| start_date.checked_add_months(Months::new(offset)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_straight_line_puts_rounding_in_last_period() {
+        let total = Decimal::from_str("12000000.00").unwrap();
+        let amounts = period_amounts(AmortizationMethod::StraightLine, total, 7, &[]).unwrap();
+
+        assert_eq!(amounts.len(), 7);
+        assert_eq!(amounts[0], Decimal::from_str("1714285.71").unwrap());
+        assert_eq!(amounts[6], Decimal::from_str("1714285.74").unwrap());
+        assert_eq!(amounts.iter().sum::<Decimal>(), total);
+    }
+
+    #[test]
+    fn test_custom_amounts_must_match_total() {
+        let amounts = [Decimal::from(100), Decimal::from(200)];
+        assert!(period_amounts(AmortizationMethod::Custom, Decimal::from(300), 2, &amounts).is_ok());
+        assert!(period_amounts(AmortizationMethod::Custom, Decimal::from(400), 2, &amounts).is_err());
+        assert!(period_amounts(AmortizationMethod::Custom, Decimal::from(300), 3, &amounts).is_err());
+    }
+}
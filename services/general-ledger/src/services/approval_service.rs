@@ -0,0 +1,421 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+const ACCOUNT_TYPES: &[&str] = &["ASSET", "LIABILITY", "EQUITY", "REVENUE", "EXPENSE"];
+
+pub struct ApprovalService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+}
+
+impl ApprovalService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self { db, audit_logger }
+    }
+
+    pub async fn create_policy(
+        &self,
+        company_id: Uuid,
+        request: ApprovalPolicyRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<ApprovalPolicy> {
+        ensure_policy_admin(roles)?;
+        let request = self.normalize_policy(request)?;
+
+        let policy = sqlx::query_as!(
+            ApprovalPolicy,
+            r#"
+            INSERT INTO journal_approval_policies
+            (id, company_id, policy_name, min_amount, account_type, required_approvals,
+             required_role, is_active, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW(), NOW())
+            RETURNING id, company_id, policy_name, min_amount, account_type, required_approvals,
+                      required_role, is_active as "is_active!", created_by,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.policy_name,
+            request.min_amount,
+            request.account_type,
+            request.required_approvals,
+            request.required_role,
+            request.is_active.unwrap_or(true),
+            user_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tracing::info!("Created approval policy {} for company {}", policy.policy_name, company_id);
+
+        Ok(policy)
+    }
+
+    pub async fn get_policies(&self, company_id: Uuid) -> ServiceResult<Vec<ApprovalPolicy>> {
+        sqlx::query_as!(
+            ApprovalPolicy,
+            r#"
+            SELECT id, company_id, policy_name, min_amount, account_type, required_approvals,
+                   required_role, is_active as "is_active!", created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM journal_approval_policies
+            WHERE company_id = $1
+            ORDER BY policy_name
+            "#,
+            company_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn update_policy(
+        &self,
+        policy_id: Uuid,
+        company_id: Uuid,
+        request: ApprovalPolicyRequest,
+        roles: &[String],
+    ) -> ServiceResult<ApprovalPolicy> {
+        ensure_policy_admin(roles)?;
+        let request = self.normalize_policy(request)?;
+
+        sqlx::query_as!(
+            ApprovalPolicy,
+            r#"
+            UPDATE journal_approval_policies
+            SET policy_name = $1, min_amount = $2, account_type = $3, required_approvals = $4,
+                required_role = $5, is_active = COALESCE($6, is_active), updated_at = NOW()
+            WHERE id = $7 AND company_id = $8
+            RETURNING id, company_id, policy_name, min_amount, account_type, required_approvals,
+                      required_role, is_active as "is_active!", created_by,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            request.policy_name,
+            request.min_amount,
+            request.account_type,
+            request.required_approvals,
+            request.required_role,
+            request.is_active,
+            policy_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Approval policy not found".to_string()))
+    }
+
+    pub async fn delete_policy(&self, policy_id: Uuid, company_id: Uuid, roles: &[String]) -> ServiceResult<()> {
+        ensure_policy_admin(roles)?;
+
+        let result = sqlx::query!(
+            "DELETE FROM journal_approval_policies WHERE id = $1 AND company_id = $2",
+            policy_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Approval policy not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_approval_status(
+        &self,
+        entry_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<JournalApprovalStatus> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let entry = sqlx::query!(
+            r#"
+            SELECT status as "status: JournalEntryStatus", total_debit
+            FROM journal_entries
+            WHERE id = $1 AND company_id = $2
+            "#,
+            entry_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Journal entry not found".to_string()))?;
+
+        let status = self.evaluate(&mut tx, entry_id, company_id, entry.total_debit, entry.status).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        Ok(status)
+    }
+
+    /// Records one approval of a pending entry. The entry moves to `Approved`
+    /// once every matching policy is satisfied; entries no policy matches need
+    /// a single approval. The creator of an entry can never approve it.
+    pub async fn approve_entry(
+        &self,
+        entry_id: Uuid,
+        company_id: Uuid,
+        request: ApproveJournalEntryRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<JournalApprovalStatus> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let entry = sqlx::query!(
+            r#"
+            SELECT entry_number, entry_date, total_debit, created_by,
+                   status as "status: JournalEntryStatus"
+            FROM journal_entries
+            WHERE id = $1 AND company_id = $2
+            FOR UPDATE
+            "#,
+            entry_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Journal entry not found".to_string()))?;
+
+        if entry.status != JournalEntryStatus::PendingApproval {
+            return Err(ServiceError::Validation(
+                format!("Journal entry {} is not pending approval", entry.entry_number)
+            ));
+        }
+
+        if entry.created_by == user_id {
+            return Err(ServiceError::Authorization(
+                "The creator of a journal entry cannot approve it".to_string()
+            ));
+        }
+
+        // Approving requires the entry's period to accept postings
+        database::periods::ensure_period_open(&mut *tx, company_id, entry.entry_date, roles).await?;
+
+        let approval_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO journal_entry_approvals
+            (id, journal_entry_id, company_id, approver_id, approver_roles, comments, approved_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
+            ON CONFLICT (journal_entry_id, approver_id) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            entry_id,
+            company_id,
+            user_id,
+            roles,
+            request.comments
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if approval_id.is_none() {
+            return Err(ServiceError::Conflict(
+                format!("Journal entry {} has already been approved by this user", entry.entry_number)
+            ));
+        }
+
+        let mut status = self.evaluate(&mut tx, entry_id, company_id, entry.total_debit, entry.status).await?;
+
+        if status.is_fully_approved {
+            sqlx::query!(
+                r#"
+                UPDATE journal_entries
+                SET status = 'APPROVED', approved_by = $1, approved_at = NOW()
+                WHERE id = $2 AND company_id = $3
+                "#,
+                user_id,
+                entry_id,
+                company_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            status.status = JournalEntryStatus::Approved;
+        }
+
+        // Log audit trail
+        self.audit_logger.log_activity(
+            &mut tx,
+            "journal_entries",
+            entry_id,
+            "APPROVE",
+            None,
+            Some(serde_json::json!({
+                "approver_id": user_id,
+                "approver_roles": roles,
+                "fully_approved": status.is_fully_approved
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Journal entry {} approved by user {} ({})", entry.entry_number, user_id,
+            if status.is_fully_approved { "fully approved" } else { "awaiting further approvals" });
+
+        Ok(status)
+    }
+
+    /// Drops recorded approvals, e.g. when an entry is sent back to draft.
+    pub async fn clear_approvals(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        entry_id: Uuid,
+    ) -> ServiceResult<()> {
+        sqlx::query!("DELETE FROM journal_entry_approvals WHERE journal_entry_id = $1", entry_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    async fn evaluate(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        entry_id: Uuid,
+        company_id: Uuid,
+        total_debit: rust_decimal::Decimal,
+        status: JournalEntryStatus,
+    ) -> ServiceResult<JournalApprovalStatus> {
+        // Policies match on entry total and on the types of accounts touched
+        let policies = sqlx::query_as!(
+            ApprovalPolicy,
+            r#"
+            SELECT p.id, p.company_id, p.policy_name, p.min_amount, p.account_type, p.required_approvals,
+                   p.required_role, p.is_active as "is_active!", p.created_by,
+                   p.created_at as "created_at!", p.updated_at as "updated_at!"
+            FROM journal_approval_policies p
+            WHERE p.company_id = $1
+              AND p.is_active = true
+              AND (p.min_amount IS NULL OR $2 >= p.min_amount)
+              AND (p.account_type IS NULL OR EXISTS (
+                  SELECT 1
+                  FROM journal_entry_lines jel
+                  JOIN accounts a ON a.id = jel.account_id
+                  WHERE jel.journal_entry_id = $3 AND a.account_type::text = p.account_type
+              ))
+            ORDER BY p.policy_name
+            "#,
+            company_id,
+            total_debit,
+            entry_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let approvals = sqlx::query_as!(
+            JournalEntryApproval,
+            r#"
+            SELECT id, journal_entry_id, approver_id, approver_roles, comments,
+                   approved_at as "approved_at!"
+            FROM journal_entry_approvals
+            WHERE journal_entry_id = $1
+            ORDER BY approved_at
+            "#,
+            entry_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let requirements = evaluate_requirements(&policies, &approvals);
+        let is_fully_approved = requirements.iter().all(|r| r.is_satisfied);
+
+        Ok(JournalApprovalStatus {
+            journal_entry_id: entry_id,
+            status,
+            requirements,
+            approvals,
+            is_fully_approved,
+        })
+    }
+
+    fn normalize_policy(&self, mut request: ApprovalPolicyRequest) -> ServiceResult<ApprovalPolicyRequest> {
+        if let Some(account_type) = request.account_type.as_mut() {
+            *account_type = account_type.to_uppercase();
+            if !ACCOUNT_TYPES.contains(&account_type.as_str()) {
+                return Err(ServiceError::Validation(
+                    format!("Invalid account type: {}", account_type)
+                ));
+            }
+        }
+
+        if let Some(min_amount) = request.min_amount {
+            if min_amount < rust_decimal::Decimal::ZERO {
+                return Err(ServiceError::Validation("Minimum amount cannot be negative".to_string()));
+            }
+        }
+
+        request.required_role = request.required_role.map(|role| role.to_uppercase());
+
+        Ok(request)
+    }
+}
+
+/// Approval policies are maintained by the same roles that manage periods.
+fn ensure_policy_admin(roles: &[String]) -> ServiceResult<()> {
+    if database::periods::has_override_role(roles) {
+        return Ok(());
+    }
+
+    Err(ServiceError::Authorization(format!(
+        "Managing approval policies requires one of the roles: {}",
+        database::periods::PERIOD_OVERRIDE_ROLES.join(", ")
+    )))
+}
+
+/// Checks recorded approvals against the matching policies. A policy with a
+/// required role only counts approvals given by holders of that role.
+pub fn evaluate_requirements(
+    policies: &[ApprovalPolicy],
+    approvals: &[JournalEntryApproval],
+) -> Vec<ApprovalRequirement> {
+    if policies.is_empty() {
+        let received = approvals.len() as i32;
+        return vec![ApprovalRequirement {
+            policy_id: None,
+            policy_name: "Default".to_string(),
+            required_approvals: 1,
+            required_role: None,
+            approvals_received: received,
+            is_satisfied: received >= 1,
+        }];
+    }
+
+    policies
+        .iter()
+        .map(|policy| {
+            let received = approvals
+                .iter()
+                .filter(|approval| match &policy.required_role {
+                    Some(role) => approval.approver_roles.iter().any(|r| r.eq_ignore_ascii_case(role)),
+                    None => true,
+                })
+                .count() as i32;
+
+            ApprovalRequirement {
+                policy_id: Some(policy.id),
+                policy_name: policy.policy_name.clone(),
+                required_approvals: policy.required_approvals,
+                required_role: policy.required_role.clone(),
+                approvals_received: received,
+                is_satisfied: received >= policy.required_approvals,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,419 @@
+use crate::models::*;
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Maintains the `account_balances` table: one row per account per day with
+/// posted debits/credits and running beginning/ending balances in the
+/// account's normal-balance direction.
+///
+/// Rows are always recomputed from posted journal lines rather than adjusted
+/// incrementally, so refreshing the same account/day twice gives the same
+/// result and cancelled or reversed entries drop out on the next refresh.
+pub struct BalanceService {
+    db: PgPool,
+}
+
+impl BalanceService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Refreshes the balance rows for every account touched by a journal entry.
+    /// Call inside the transaction that posts, cancels or reverses the entry.
+    pub async fn refresh_for_entry(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        company_id: Uuid,
+        entry_id: Uuid,
+    ) -> ServiceResult<()> {
+        let touched = sqlx::query!(
+            r#"
+            SELECT DISTINCT jel.account_id, je.entry_date
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON jel.journal_entry_id = je.id
+            WHERE je.id = $1 AND je.company_id = $2
+            ORDER BY jel.account_id
+            "#,
+            entry_id,
+            company_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for row in touched {
+            self.refresh_account_day(tx, company_id, row.account_id, row.entry_date).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn refresh_account_day(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        company_id: Uuid,
+        account_id: Uuid,
+        balance_date: NaiveDate,
+    ) -> ServiceResult<()> {
+        // Lock the account row so concurrent postings (and full rebuilds)
+        // don't interleave the running-balance recomputation
+        sqlx::query!(
+            "SELECT id FROM accounts WHERE id = $1 AND company_id = $2 FOR UPDATE",
+            account_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Recompute the day's movement from posted lines
+        sqlx::query!(
+            r#"
+            INSERT INTO account_balances
+            (id, company_id, account_id, balance_date, debit_amount, credit_amount)
+            SELECT $1, $2, $3, $4,
+                   COALESCE(SUM(jel.debit_amount), 0),
+                   COALESCE(SUM(jel.credit_amount), 0)
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON jel.journal_entry_id = je.id
+            WHERE je.company_id = $2
+              AND jel.account_id = $3
+              AND je.entry_date = $4
+              AND je.is_posted = true
+              AND je.status = 'POSTED'
+            ON CONFLICT (company_id, account_id, balance_date)
+            DO UPDATE SET debit_amount = EXCLUDED.debit_amount,
+                          credit_amount = EXCLUDED.credit_amount
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            account_id,
+            balance_date
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Drop days that no longer carry any posted movement
+        sqlx::query!(
+            r#"
+            DELETE FROM account_balances
+            WHERE company_id = $1 AND account_id = $2 AND balance_date = $3
+              AND debit_amount = 0 AND credit_amount = 0
+            "#,
+            company_id,
+            account_id,
+            balance_date
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Roll the running balance forward from the changed day
+        sqlx::query!(
+            r#"
+            UPDATE account_balances ab
+            SET beginning_balance = running.ending_balance - running.net_change,
+                ending_balance = running.ending_balance
+            FROM (
+                SELECT b.id,
+                       CASE WHEN a.normal_balance = 'CREDIT'
+                            THEN b.credit_amount - b.debit_amount
+                            ELSE b.debit_amount - b.credit_amount END AS net_change,
+                       SUM(CASE WHEN a.normal_balance = 'CREDIT'
+                                THEN b.credit_amount - b.debit_amount
+                                ELSE b.debit_amount - b.credit_amount END)
+                           OVER (ORDER BY b.balance_date) AS ending_balance
+                FROM account_balances b
+                JOIN accounts a ON a.id = b.account_id
+                WHERE b.company_id = $1 AND b.account_id = $2
+            ) running
+            WHERE ab.id = running.id AND ab.balance_date >= $3
+            "#,
+            company_id,
+            account_id,
+            balance_date
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    /// Throws away and rebuilds every balance row for a company from posted
+    /// journal lines. Safe to run repeatedly.
+    pub async fn rebuild_balances(&self, company_id: Uuid) -> ServiceResult<BalanceRebuildResult> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Block concurrent postings for the whole company while rebuilding
+        sqlx::query!(
+            "SELECT id FROM accounts WHERE company_id = $1 FOR UPDATE",
+            company_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!("DELETE FROM account_balances WHERE company_id = $1", company_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO account_balances
+            (id, company_id, account_id, balance_date, debit_amount, credit_amount)
+            SELECT md5(je.company_id::text || jel.account_id::text || je.entry_date::text)::uuid,
+                   je.company_id, jel.account_id, je.entry_date,
+                   SUM(jel.debit_amount), SUM(jel.credit_amount)
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON jel.journal_entry_id = je.id
+            WHERE je.company_id = $1
+              AND je.is_posted = true
+              AND je.status = 'POSTED'
+            GROUP BY je.company_id, jel.account_id, je.entry_date
+            "#,
+            company_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .rows_affected();
+
+        sqlx::query!(
+            r#"
+            UPDATE account_balances ab
+            SET beginning_balance = running.ending_balance - running.net_change,
+                ending_balance = running.ending_balance
+            FROM (
+                SELECT b.id,
+                       CASE WHEN a.normal_balance = 'CREDIT'
+                            THEN b.credit_amount - b.debit_amount
+                            ELSE b.debit_amount - b.credit_amount END AS net_change,
+                       SUM(CASE WHEN a.normal_balance = 'CREDIT'
+                                THEN b.credit_amount - b.debit_amount
+                                ELSE b.debit_amount - b.credit_amount END)
+                           OVER (PARTITION BY b.account_id ORDER BY b.balance_date) AS ending_balance
+                FROM account_balances b
+                JOIN accounts a ON a.id = b.account_id
+                WHERE b.company_id = $1
+            ) running
+            WHERE ab.id = running.id
+            "#,
+            company_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let accounts_rebuilt = sqlx::query_scalar!(
+            "SELECT COUNT(DISTINCT account_id) FROM account_balances WHERE company_id = $1",
+            company_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .unwrap_or(0);
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Rebuilt {} balance rows across {} accounts for company {}",
+            inserted, accounts_rebuilt, company_id
+        );
+
+        Ok(BalanceRebuildResult {
+            company_id,
+            accounts_rebuilt: accounts_rebuilt as u32,
+            balance_rows: inserted as u32,
+            rebuilt_at: chrono::Utc::now(),
+        })
+    }
+
+    pub async fn get_account_balances(
+        &self,
+        company_id: Uuid,
+        as_of_date: NaiveDate,
+    ) -> ServiceResult<Vec<AccountBalance>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                a.id,
+                a.account_code,
+                a.account_name,
+                a.account_type::text as "account_type!",
+                a.normal_balance,
+                ab.balance_date as "balance_date?",
+                ab.beginning_balance as "beginning_balance?",
+                ab.debit_amount as "debit_amount?",
+                ab.credit_amount as "credit_amount?",
+                ab.ending_balance as "ending_balance?"
+            FROM accounts a
+            LEFT JOIN LATERAL (
+                SELECT b.balance_date, b.beginning_balance, b.debit_amount, b.credit_amount, b.ending_balance
+                FROM account_balances b
+                WHERE b.company_id = a.company_id
+                  AND b.account_id = a.id
+                  AND b.balance_date <= $2
+                ORDER BY b.balance_date DESC
+                LIMIT 1
+            ) ab ON true
+            WHERE a.company_id = $1 AND a.is_active = true
+            ORDER BY a.account_code
+            "#,
+            company_id,
+            as_of_date
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AccountBalance {
+                account_id: row.id,
+                account_code: row.account_code,
+                account_name: row.account_name,
+                account_type: row.account_type,
+                normal_balance: row.normal_balance.unwrap_or_else(|| "DEBIT".to_string()),
+                balance_date: row.balance_date,
+                beginning_balance: row.beginning_balance.unwrap_or(Decimal::ZERO),
+                debit_amount: row.debit_amount.unwrap_or(Decimal::ZERO),
+                credit_amount: row.credit_amount.unwrap_or(Decimal::ZERO),
+                balance: row.ending_balance.unwrap_or(Decimal::ZERO),
+            })
+            .collect())
+    }
+
+    /// Trial balance as of a date. Without dimension filters or a pivot it is
+    /// read from `account_balances`; otherwise it is aggregated from posted
+    /// journal lines, with one line per account and dimension value when
+    /// pivoting (untagged lines are grouped under a null value).
+    pub async fn get_trial_balance(
+        &self,
+        company_id: Uuid,
+        as_of_date: NaiveDate,
+        filter: DimensionFilter,
+        pivot: Option<DimensionType>,
+    ) -> ServiceResult<TrialBalance> {
+        let rows = if filter.is_empty() && pivot.is_none() {
+            sqlx::query_as!(
+                TrialBalanceRow,
+                r#"
+                SELECT
+                    a.id as account_id,
+                    a.account_code,
+                    a.account_name,
+                    a.account_type::text as "account_type!",
+                    NULL::text as dimension_value,
+                    COALESCE(SUM(ab.debit_amount), 0) as "total_debits!",
+                    COALESCE(SUM(ab.credit_amount), 0) as "total_credits!"
+                FROM accounts a
+                LEFT JOIN account_balances ab ON ab.account_id = a.id
+                    AND ab.company_id = a.company_id
+                    AND ab.balance_date <= $2
+                WHERE a.company_id = $1 AND a.is_active = true
+                GROUP BY a.id, a.account_code, a.account_name, a.account_type
+                ORDER BY a.account_code
+                "#,
+                company_id,
+                as_of_date
+            )
+            .fetch_all(&self.db)
+            .await
+            .map_err(ServiceError::Database)?
+        } else {
+            sqlx::query_as!(
+                TrialBalanceRow,
+                r#"
+                SELECT
+                    a.id as account_id,
+                    a.account_code,
+                    a.account_name,
+                    a.account_type::text as "account_type!",
+                    CASE $3::text
+                        WHEN 'DEPARTMENT' THEN jel.department
+                        WHEN 'PROJECT' THEN jel.project_code
+                        WHEN 'COST_CENTER' THEN jel.cost_center
+                    END as dimension_value,
+                    COALESCE(SUM(jel.debit_amount), 0) as "total_debits!",
+                    COALESCE(SUM(jel.credit_amount), 0) as "total_credits!"
+                FROM journal_entry_lines jel
+                JOIN journal_entries je ON jel.journal_entry_id = je.id
+                JOIN accounts a ON a.id = jel.account_id
+                WHERE je.company_id = $1
+                  AND je.is_posted = true
+                  AND je.status = 'POSTED'
+                  AND je.entry_date <= $2
+                  AND ($4::text IS NULL OR jel.department = $4)
+                  AND ($5::text IS NULL OR jel.project_code = $5)
+                  AND ($6::text IS NULL OR jel.cost_center = $6)
+                GROUP BY a.id, a.account_code, a.account_name, a.account_type, 5
+                ORDER BY a.account_code, 5
+                "#,
+                company_id,
+                as_of_date,
+                pivot.map(|p| p.to_string()),
+                filter.department.as_deref(),
+                filter.project_code.as_deref(),
+                filter.cost_center.as_deref()
+            )
+            .fetch_all(&self.db)
+            .await
+            .map_err(ServiceError::Database)?
+        };
+
+        let mut accounts = Vec::new();
+        let mut total_debits = Decimal::ZERO;
+        let mut total_credits = Decimal::ZERO;
+
+        for row in rows {
+            let net = row.total_debits - row.total_credits;
+            let (debit_balance, credit_balance) = if net >= Decimal::ZERO {
+                (net, Decimal::ZERO)
+            } else {
+                (Decimal::ZERO, net.abs())
+            };
+
+            total_debits += debit_balance;
+            total_credits += credit_balance;
+
+            accounts.push(TrialBalanceLine {
+                account_id: row.account_id,
+                account_code: row.account_code,
+                account_name: row.account_name,
+                account_type: row.account_type,
+                dimension_value: row.dimension_value,
+                total_debits: row.total_debits,
+                total_credits: row.total_credits,
+                debit_balance,
+                credit_balance,
+            });
+        }
+
+        Ok(TrialBalance {
+            company_id,
+            as_of_date,
+            filter,
+            pivot,
+            accounts,
+            total_debits,
+            total_credits,
+            is_balanced: total_debits == total_credits,
+        })
+    }
+}
+
+struct TrialBalanceRow {
+    account_id: Uuid,
+    account_code: String,
+    account_name: String,
+    account_type: String,
+    dimension_value: Option<String>,
+    total_debits: Decimal,
+    total_credits: Decimal,
+}
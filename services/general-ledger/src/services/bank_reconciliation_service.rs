@@ -0,0 +1,1297 @@
+use crate::models::*;
+use super::{bank_statement_parser, journal_service::EntryOrigin, JournalService, SubledgerClient};
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, PaginationParams};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Days either side of a statement line a book item may be dated and still
+/// be matched automatically.
+const DEFAULT_MATCH_WINDOW_DAYS: i64 = 3;
+
+/// A book item a statement line can be matched to.
+#[derive(Debug, Clone)]
+struct MatchCandidate {
+    match_type: BankMatchType,
+    record_id: Uuid,
+    date: NaiveDate,
+    amount: Decimal,
+    references: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct UnmatchedLine {
+    id: Uuid,
+    date: NaiveDate,
+    amount: Decimal,
+    text: String,
+}
+
+pub struct BankReconciliationService {
+    db: PgPool,
+    journal_service: JournalService,
+    subledger_client: SubledgerClient,
+    audit_logger: database::audit::AuditLogger,
+}
+
+impl BankReconciliationService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            subledger_client: SubledgerClient::from_env(),
+            audit_logger: database::audit::AuditLogger::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Loads a CSV or MT940 statement for a bank account and runs automatic
+    /// matching over it. The opening balance comes from the file when it has
+    /// one, then from `opening_balance`, then from the previous statement.
+    /// A statement whose period overlaps one already imported for the account
+    /// is rejected, so the same transactions cannot be loaded twice.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_statement(
+        &self,
+        company_id: Uuid,
+        bank_account_id: Uuid,
+        format: BankStatementFormat,
+        data: &[u8],
+        file_name: Option<String>,
+        opening_balance: Option<Decimal>,
+        user_id: Uuid,
+    ) -> ServiceResult<BankStatementImportResult> {
+        bank_account(&self.db, company_id, bank_account_id).await?;
+
+        let parsed = match format {
+            BankStatementFormat::Csv => bank_statement_parser::parse_csv(data),
+            BankStatementFormat::Mt940 => bank_statement_parser::parse_mt940(data),
+        }
+        .map_err(ServiceError::Validation)?;
+
+        let period_start = parsed.lines.iter().map(|l| l.transaction_date).min()
+            .ok_or_else(|| ServiceError::Validation("The statement has no transactions".to_string()))?;
+        let period_end = parsed.lines.iter().map(|l| l.transaction_date).max().unwrap_or(period_start);
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Imports wait for a reconciliation of the account in progress and
+        // for each other
+        lock_bank_account(&mut tx, bank_account_id).await?;
+
+        if let Some(last) = last_reconciliation(&mut *tx, bank_account_id).await? {
+            if period_start <= last.as_of_date {
+                return Err(ServiceError::Validation(format!(
+                    "The account is reconciled through {}; statement lines on or before that date cannot be imported",
+                    last.as_of_date
+                )));
+            }
+        }
+
+        if let Some(statement_number) = &parsed.statement_number {
+            let exists = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM bank_statements WHERE bank_account_id = $1 AND statement_number = $2
+                ) as "exists!"
+                "#,
+                bank_account_id,
+                statement_number
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            if exists {
+                return Err(ServiceError::Conflict(format!(
+                    "Statement {} has already been imported",
+                    statement_number
+                )));
+            }
+        }
+
+        let overlapping = sqlx::query!(
+            r#"
+            SELECT statement_number, period_start, period_end
+            FROM bank_statements
+            WHERE bank_account_id = $1 AND period_start <= $3 AND period_end >= $2
+            ORDER BY period_start
+            LIMIT 1
+            "#,
+            bank_account_id,
+            period_start,
+            period_end
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if let Some(existing) = overlapping {
+            return Err(ServiceError::Conflict(format!(
+                "Statement {} already covers {} to {}, which overlaps this statement's {} to {}",
+                existing.statement_number.unwrap_or_else(|| "imported earlier".to_string()),
+                existing.period_start,
+                existing.period_end,
+                period_start,
+                period_end
+            )));
+        }
+
+        let opening_balance = match parsed.opening_balance.or(opening_balance) {
+            Some(balance) => balance,
+            None => sqlx::query_scalar!(
+                r#"
+                SELECT closing_balance FROM bank_statements
+                WHERE bank_account_id = $1 AND period_end <= $2
+                ORDER BY period_end DESC, imported_at DESC
+                LIMIT 1
+                "#,
+                bank_account_id,
+                period_start
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?
+            .ok_or_else(|| ServiceError::Validation(
+                "Opening balance is required for the first statement of an account".to_string()
+            ))?,
+        };
+
+        let movement: Decimal = parsed.lines.iter().map(|l| l.amount).sum();
+        let closing_balance = parsed.closing_balance.unwrap_or(opening_balance + movement);
+        if opening_balance + movement != closing_balance {
+            return Err(ServiceError::Validation(format!(
+                "Statement does not balance: opening {} plus transactions {} is not closing {}",
+                opening_balance, movement, closing_balance
+            )));
+        }
+
+        let statement = sqlx::query_as!(
+            BankStatement,
+            r#"
+            INSERT INTO bank_statements
+            (id, company_id, bank_account_id, statement_number, period_start, period_end, opening_balance,
+             closing_balance, source_format, file_name, imported_by, imported_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+            RETURNING id, company_id, bank_account_id, statement_number, period_start, period_end,
+                      opening_balance, closing_balance, source_format, file_name, imported_by,
+                      imported_at as "imported_at!"
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            bank_account_id,
+            parsed.statement_number,
+            period_start,
+            period_end,
+            opening_balance,
+            closing_balance,
+            format.to_string(),
+            file_name,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for (index, line) in parsed.lines.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO bank_statement_lines
+                (id, statement_id, company_id, bank_account_id, line_number, transaction_date, amount,
+                 description, reference, bank_reference, match_status)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'UNMATCHED')
+                "#,
+                Uuid::new_v4(),
+                statement.id,
+                company_id,
+                bank_account_id,
+                (index + 1) as i32,
+                line.transaction_date,
+                line.amount,
+                line.description,
+                line.reference,
+                line.bank_reference
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Imported {} statement {} with {} lines for bank account {}",
+            format, statement.id, parsed.lines.len(), bank_account_id);
+
+        let matched = self.auto_match(company_id, user_id, AutoMatchRequest {
+            bank_account_id,
+            date_window_days: None,
+        }).await?;
+
+        Ok(BankStatementImportResult {
+            statement,
+            lines_imported: parsed.lines.len() as u32,
+            lines_matched: matched.lines_matched,
+        })
+    }
+
+    pub async fn get_statements(
+        &self,
+        company_id: Uuid,
+        bank_account_id: Option<Uuid>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<BankStatement>> {
+        sqlx::query_as!(
+            BankStatement,
+            r#"
+            SELECT id, company_id, bank_account_id, statement_number, period_start, period_end,
+                   opening_balance, closing_balance, source_format, file_name, imported_by,
+                   imported_at as "imported_at!"
+            FROM bank_statements
+            WHERE company_id = $1 AND ($2::uuid IS NULL OR bank_account_id = $2)
+            ORDER BY period_start DESC, imported_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            company_id,
+            bank_account_id,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_statement(
+        &self,
+        statement_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<BankStatementWithLines> {
+        let statement = sqlx::query_as!(
+            BankStatement,
+            r#"
+            SELECT id, company_id, bank_account_id, statement_number, period_start, period_end,
+                   opening_balance, closing_balance, source_format, file_name, imported_by,
+                   imported_at as "imported_at!"
+            FROM bank_statements
+            WHERE id = $1 AND company_id = $2
+            "#,
+            statement_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Bank statement not found".to_string()))?;
+
+        let lines = sqlx::query_as!(
+            BankStatementLine,
+            r#"
+            SELECT id, statement_id, bank_account_id, line_number, transaction_date, amount, description,
+                   reference, bank_reference, match_status, match_type, matched_record_id, match_method,
+                   matched_by, matched_at, reconciliation_id
+            FROM bank_statement_lines
+            WHERE statement_id = $1
+            ORDER BY line_number
+            "#,
+            statement_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(BankStatementWithLines { statement, lines })
+    }
+
+    /// Matches unmatched statement lines of a bank account to ledger lines
+    /// and AP/AR payments with the same amount within the date window.
+    /// A reference found in the statement text decides between candidates;
+    /// lines that remain ambiguous are left for manual matching.
+    pub async fn auto_match(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        request: AutoMatchRequest,
+    ) -> ServiceResult<AutoMatchResult> {
+        let window = request.date_window_days.unwrap_or(DEFAULT_MATCH_WINDOW_DAYS).clamp(0, 31);
+
+        let lines: Vec<UnmatchedLine> = sqlx::query!(
+            r#"
+            SELECT id, transaction_date, amount, description, reference, bank_reference
+            FROM bank_statement_lines
+            WHERE company_id = $1 AND bank_account_id = $2
+              AND match_status = 'UNMATCHED' AND reconciliation_id IS NULL
+            ORDER BY transaction_date, line_number
+            "#,
+            company_id,
+            request.bank_account_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .into_iter()
+        .map(|row| UnmatchedLine {
+            id: row.id,
+            date: row.transaction_date,
+            amount: row.amount,
+            text: [row.reference, row.bank_reference, row.description]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect();
+
+        let (Some(first), Some(last)) = (
+            lines.iter().map(|l| l.date).min(),
+            lines.iter().map(|l| l.date).max(),
+        ) else {
+            return Ok(AutoMatchResult { lines_considered: 0, lines_matched: 0 });
+        };
+
+        let from = first - chrono::Duration::days(window);
+        let to = last + chrono::Duration::days(window);
+        let candidates = self.match_candidates(company_id, user_id, request.bank_account_id, from, to).await?;
+        let matches = pick_matches(&lines, &candidates, window);
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        for (line_id, candidate) in &matches {
+            let candidate = &candidates[*candidate];
+            sqlx::query!(
+                r#"
+                UPDATE bank_statement_lines
+                SET match_status = 'MATCHED', match_type = $1, matched_record_id = $2,
+                    match_method = 'AUTO', matched_by = NULL, matched_at = NOW()
+                WHERE id = $3 AND match_status = 'UNMATCHED'
+                "#,
+                candidate.match_type.to_string(),
+                candidate.record_id,
+                line_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        if !matches.is_empty() {
+            tracing::info!("Auto-matched {} of {} statement lines for bank account {}",
+                matches.len(), lines.len(), request.bank_account_id);
+        }
+
+        Ok(AutoMatchResult {
+            lines_considered: lines.len() as u32,
+            lines_matched: matches.len() as u32,
+        })
+    }
+
+    pub async fn match_line(
+        &self,
+        line_id: Uuid,
+        company_id: Uuid,
+        request: MatchStatementLineRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<BankStatementLine> {
+        let line = self.open_line(line_id, company_id).await?;
+        if line.match_status != "UNMATCHED" {
+            return Err(ServiceError::Conflict("Statement line is already matched".to_string()));
+        }
+
+        let amount = match request.match_type {
+            BankMatchType::JournalLine => sqlx::query_scalar!(
+                r#"
+                SELECT jel.debit_amount - jel.credit_amount as "amount!"
+                FROM journal_entry_lines jel
+                JOIN journal_entries je ON je.id = jel.journal_entry_id
+                WHERE jel.id = $1 AND je.company_id = $2 AND jel.account_id = $3 AND je.status = 'POSTED'
+                "#,
+                request.record_id,
+                company_id,
+                line.bank_account_id
+            )
+            .fetch_optional(&self.db)
+            .await
+            .map_err(ServiceError::Database)?,
+            BankMatchType::VendorPayment | BankMatchType::CustomerPayment => self.subledger_client
+                .payments_by_id(request.match_type, company_id, user_id, &[request.record_id])
+                .await?
+                .into_iter()
+                .find(|p| {
+                    p.id == request.record_id
+                        && !p.is_reversed
+                        && p.bank_account_id.map_or(true, |id| id == line.bank_account_id)
+                })
+                .map(|p| bank_amount(request.match_type, &p)),
+        }
+        .ok_or_else(|| ServiceError::NotFound(format!(
+            "No posted {} {} on this bank account",
+            request.match_type, request.record_id
+        )))?;
+
+        if amount != line.amount {
+            return Err(ServiceError::Validation(format!(
+                "Statement amount {} does not equal the book amount {}",
+                line.amount, amount
+            )));
+        }
+
+        let already_matched = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM bank_statement_lines WHERE match_type = $1 AND matched_record_id = $2
+            ) as "exists!"
+            "#,
+            request.match_type.to_string(),
+            request.record_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if already_matched {
+            return Err(ServiceError::Conflict("That record is already matched to another statement line".to_string()));
+        }
+
+        let line = sqlx::query_as!(
+            BankStatementLine,
+            r#"
+            UPDATE bank_statement_lines
+            SET match_status = 'MATCHED', match_type = $1, matched_record_id = $2,
+                match_method = 'MANUAL', matched_by = $3, matched_at = NOW()
+            WHERE id = $4 AND match_status = 'UNMATCHED'
+            RETURNING id, statement_id, bank_account_id, line_number, transaction_date, amount, description,
+                      reference, bank_reference, match_status, match_type, matched_record_id, match_method,
+                      matched_by, matched_at, reconciliation_id
+            "#,
+            request.match_type.to_string(),
+            request.record_id,
+            user_id,
+            line_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict("Statement line is already matched".to_string()))?;
+
+        tracing::info!("Statement line {} matched to {} {} by user {}",
+            line_id, request.match_type, request.record_id, user_id);
+
+        Ok(line)
+    }
+
+    /// Clears a match. Adjusting entries made for the line stay in the
+    /// ledger and show up as outstanding book items until reversed.
+    pub async fn unmatch_line(
+        &self,
+        line_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<BankStatementLine> {
+        let line = self.open_line(line_id, company_id).await?;
+        if line.match_status != "MATCHED" {
+            return Err(ServiceError::Conflict("Statement line is not matched".to_string()));
+        }
+
+        let line = sqlx::query_as!(
+            BankStatementLine,
+            r#"
+            UPDATE bank_statement_lines
+            SET match_status = 'UNMATCHED', match_type = NULL, matched_record_id = NULL,
+                match_method = NULL, matched_by = NULL, matched_at = NULL
+            WHERE id = $1 AND reconciliation_id IS NULL
+            RETURNING id, statement_id, bank_account_id, line_number, transaction_date, amount, description,
+                      reference, bank_reference, match_status, match_type, matched_record_id, match_method,
+                      matched_by, matched_at, reconciliation_id
+            "#,
+            line_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict("Statement line is part of a locked reconciliation".to_string()))?;
+
+        tracing::info!("Statement line {} unmatched by user {}", line_id, user_id);
+
+        Ok(line)
+    }
+
+    /// Posts an entry for a fee or interest line that only appears on the
+    /// statement and matches the line to the entry's bank leg.
+    pub async fn create_adjustment(
+        &self,
+        line_id: Uuid,
+        company_id: Uuid,
+        request: BankAdjustmentRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<JournalEntryWithLines> {
+        let line = self.open_line(line_id, company_id).await?;
+        if line.match_status != "UNMATCHED" {
+            return Err(ServiceError::Conflict("Statement line is already matched".to_string()));
+        }
+        if request.account_id == line.bank_account_id {
+            return Err(ServiceError::Validation(
+                "The adjustment account must differ from the bank account".to_string()
+            ));
+        }
+
+        super::validation::validate_accounts_exist(&self.db, &[request.account_id], company_id).await?;
+
+        let description = request.description
+            .or_else(|| line.description.clone())
+            .unwrap_or_else(|| if line.amount > Decimal::ZERO { "Bank interest" } else { "Bank charges" }.to_string());
+
+        let (bank_debit, bank_credit) = if line.amount > Decimal::ZERO {
+            (line.amount, Decimal::ZERO)
+        } else {
+            (Decimal::ZERO, -line.amount)
+        };
+
+        let entry_line = |account_id: Uuid, debit_amount: Decimal, credit_amount: Decimal| CreateJournalEntryLineRequest {
+            account_id,
+            description: Some(description.clone()),
+            debit_amount,
+            credit_amount,
+            department: None,
+            project_code: None,
+            cost_center: None,
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        };
+
+        let entry_request = CreateJournalEntryRequest {
+            company_id,
+            entry_date: line.transaction_date,
+            description: Some(description.clone()),
+            reference: line.reference.clone().or_else(|| line.bank_reference.clone()),
+            auto_reverse_on: None,
+            auto_reverse: false,
+            lines: vec![
+                entry_line(line.bank_account_id, bank_debit, bank_credit),
+                entry_line(request.account_id, bank_credit, bank_debit),
+            ],
+        };
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, line.transaction_date, roles).await?;
+
+        let entry = self.journal_service
+            .insert_entry(&mut tx, entry_request, EntryOrigin::system("BANK_ADJUSTMENT", line_id), user_id)
+            .await?;
+
+        let bank_line_id = entry.lines
+            .iter()
+            .find(|l| l.account_id == line.bank_account_id)
+            .map(|l| l.id)
+            .ok_or_else(|| ServiceError::Internal("Adjustment entry has no bank line".to_string()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE bank_statement_lines
+            SET match_status = 'MATCHED', match_type = 'JOURNAL_LINE', matched_record_id = $1,
+                match_method = 'ADJUSTMENT', matched_by = $2, matched_at = NOW()
+            WHERE id = $3
+            "#,
+            bank_line_id,
+            user_id,
+            line_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Posted bank adjustment {} for statement line {}", entry.journal_entry.entry_number, line_id);
+
+        Ok(entry)
+    }
+
+    /// Statement balance and book balance on `as_of_date` with the items
+    /// that explain the difference between them.
+    pub async fn get_report(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        bank_account_id: Uuid,
+        as_of_date: NaiveDate,
+    ) -> ServiceResult<BankReconciliationReport> {
+        let mut conn = self.db.acquire().await.map_err(ServiceError::Database)?;
+        self.build_report(&mut conn, company_id, user_id, bank_account_id, as_of_date).await
+    }
+
+    /// The report read through `conn`, so a reconciliation can lock exactly
+    /// the balances it checked.
+    async fn build_report(
+        &self,
+        conn: &mut PgConnection,
+        company_id: Uuid,
+        user_id: Uuid,
+        bank_account_id: Uuid,
+        as_of_date: NaiveDate,
+    ) -> ServiceResult<BankReconciliationReport> {
+        let (account_code, account_name) = bank_account(&mut *conn, company_id, bank_account_id).await?;
+
+        let statement_balance = sqlx::query_scalar!(
+            r#"
+            SELECT bs.opening_balance + COALESCE((
+                SELECT SUM(bsl.amount) FROM bank_statement_lines bsl
+                WHERE bsl.statement_id = bs.id AND bsl.transaction_date <= $3
+            ), 0) as "balance!"
+            FROM bank_statements bs
+            WHERE bs.company_id = $1 AND bs.bank_account_id = $2 AND bs.period_start <= $3
+            ORDER BY bs.period_start DESC, bs.imported_at DESC
+            LIMIT 1
+            "#,
+            company_id,
+            bank_account_id,
+            as_of_date
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Validation(format!(
+            "No bank statement imported for this account on or before {}",
+            as_of_date
+        )))?;
+
+        let book_balance = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(jel.debit_amount - jel.credit_amount), 0) as "balance!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            WHERE je.company_id = $1 AND jel.account_id = $2 AND je.status = 'POSTED' AND je.entry_date <= $3
+            "#,
+            company_id,
+            bank_account_id,
+            as_of_date
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Payments matched by statement lines up to the report date clear the
+        // entries they were posted with; those never posted clear nothing
+        let matched_payments = sqlx::query!(
+            r#"
+            SELECT match_type as "match_type!", matched_record_id as "matched_record_id!"
+            FROM bank_statement_lines
+            WHERE company_id = $1 AND bank_account_id = $2 AND transaction_date <= $3
+              AND match_type IN ('VENDOR_PAYMENT', 'CUSTOMER_PAYMENT') AND matched_record_id IS NOT NULL
+            "#,
+            company_id,
+            bank_account_id,
+            as_of_date
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut posted_payment_ids = Vec::new();
+        let mut cleared_entry_ids = Vec::new();
+        for kind in [BankMatchType::VendorPayment, BankMatchType::CustomerPayment] {
+            let ids: Vec<Uuid> = matched_payments
+                .iter()
+                .filter(|row| row.match_type == kind.to_string())
+                .map(|row| row.matched_record_id)
+                .collect();
+
+            for payment in self.subledger_client.payments_by_id(kind, company_id, user_id, &ids).await? {
+                if let Some(entry_id) = payment.journal_entry_id {
+                    posted_payment_ids.push(payment.id);
+                    cleared_entry_ids.push(entry_id);
+                }
+            }
+        }
+
+        // Book lines not cleared by a statement line dated on or before the
+        // report date, directly or through the AP/AR payment they belong to
+        let outstanding_book_items = sqlx::query_as!(
+            OutstandingBookItem,
+            r#"
+            SELECT je.id as journal_entry_id, jel.id as journal_entry_line_id, je.entry_number,
+                   je.entry_date, COALESCE(jel.description, je.description) as description,
+                   jel.debit_amount - jel.credit_amount as "amount!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            WHERE je.company_id = $1 AND jel.account_id = $2 AND je.status = 'POSTED' AND je.entry_date <= $3
+              AND NOT EXISTS (
+                  SELECT 1 FROM bank_statement_lines bsl
+                  WHERE bsl.bank_account_id = $2 AND bsl.transaction_date <= $3
+                    AND bsl.match_type = 'JOURNAL_LINE' AND bsl.matched_record_id = jel.id
+              )
+              AND NOT (je.id = ANY($4))
+            ORDER BY je.entry_date, je.entry_number
+            "#,
+            company_id,
+            bank_account_id,
+            as_of_date,
+            &cleared_entry_ids
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Statement lines with nothing behind them in the ledger: unmatched,
+        // or matched to a payment that was never posted
+        let unrecorded_statement_lines = sqlx::query_as!(
+            BankStatementLine,
+            r#"
+            SELECT bsl.id, bsl.statement_id, bsl.bank_account_id, bsl.line_number, bsl.transaction_date,
+                   bsl.amount, bsl.description, bsl.reference, bsl.bank_reference, bsl.match_status,
+                   bsl.match_type, bsl.matched_record_id, bsl.match_method, bsl.matched_by, bsl.matched_at,
+                   bsl.reconciliation_id
+            FROM bank_statement_lines bsl
+            WHERE bsl.company_id = $1 AND bsl.bank_account_id = $2 AND bsl.transaction_date <= $3
+              AND (
+                  bsl.match_status = 'UNMATCHED'
+                  OR (bsl.match_type IN ('VENDOR_PAYMENT', 'CUSTOMER_PAYMENT')
+                      AND NOT (bsl.matched_record_id = ANY($4)))
+              )
+            ORDER BY bsl.transaction_date, bsl.line_number
+            "#,
+            company_id,
+            bank_account_id,
+            as_of_date,
+            &posted_payment_ids
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let deposits_in_transit: Decimal = outstanding_book_items
+            .iter()
+            .filter(|i| i.amount > Decimal::ZERO)
+            .map(|i| i.amount)
+            .sum();
+        let outstanding_payments: Decimal = outstanding_book_items
+            .iter()
+            .filter(|i| i.amount < Decimal::ZERO)
+            .map(|i| -i.amount)
+            .sum();
+        let unrecorded_statement_items: Decimal = unrecorded_statement_lines.iter().map(|l| l.amount).sum();
+
+        let adjusted_statement_balance = statement_balance + deposits_in_transit - outstanding_payments;
+        let adjusted_book_balance = book_balance + unrecorded_statement_items;
+
+        Ok(BankReconciliationReport {
+            bank_account_id,
+            account_code,
+            account_name,
+            as_of_date,
+            statement_balance,
+            deposits_in_transit,
+            outstanding_payments,
+            adjusted_statement_balance,
+            book_balance,
+            unrecorded_statement_items,
+            adjusted_book_balance,
+            difference: adjusted_statement_balance - adjusted_book_balance,
+            outstanding_book_items,
+            unrecorded_statement_lines,
+            last_reconciliation: last_reconciliation(&mut *conn, bank_account_id).await?,
+        })
+    }
+
+    /// Locks the reconciled balance on `as_of_date`. The report must show no
+    /// difference; matched statement lines up to that date can no longer be
+    /// unmatched, no earlier statement lines can be imported and no entries
+    /// dated on or before it can be posted to the account.
+    pub async fn create_reconciliation(
+        &self,
+        company_id: Uuid,
+        request: CreateBankReconciliationRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<BankReconciliation> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // One reconciliation per bank account at a time. Postings to the
+        // account wait until it is locked and are then checked against it.
+        lock_bank_account(&mut tx, request.bank_account_id).await?;
+
+        if let Some(last) = last_reconciliation(&mut *tx, request.bank_account_id).await? {
+            if request.as_of_date <= last.as_of_date {
+                return Err(ServiceError::Conflict(format!(
+                    "The account is already reconciled through {}",
+                    last.as_of_date
+                )));
+            }
+        }
+
+        let report = self
+            .build_report(&mut tx, company_id, user_id, request.bank_account_id, request.as_of_date)
+            .await?;
+        if !report.difference.is_zero() {
+            return Err(ServiceError::Validation(format!(
+                "Reconciliation does not balance: difference of {}",
+                report.difference
+            )));
+        }
+
+        let reconciliation = sqlx::query_as!(
+            BankReconciliation,
+            r#"
+            INSERT INTO bank_reconciliations
+            (id, company_id, bank_account_id, as_of_date, statement_balance, book_balance,
+             reconciled_balance, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            RETURNING id, company_id, bank_account_id, as_of_date, statement_balance, book_balance,
+                      reconciled_balance, created_by, created_at as "created_at!"
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.bank_account_id,
+            request.as_of_date,
+            report.statement_balance,
+            report.book_balance,
+            report.adjusted_book_balance,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE bank_statement_lines
+            SET reconciliation_id = $1
+            WHERE bank_account_id = $2 AND transaction_date <= $3
+              AND match_status = 'MATCHED' AND reconciliation_id IS NULL
+            "#,
+            reconciliation.id,
+            request.bank_account_id,
+            request.as_of_date
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        self.audit_logger.log_activity(
+            &mut tx,
+            "bank_reconciliations",
+            reconciliation.id,
+            "BANK_RECONCILIATION",
+            None,
+            Some(serde_json::json!({
+                "bank_account_id": request.bank_account_id,
+                "as_of_date": request.as_of_date,
+                "statement_balance": report.statement_balance,
+                "book_balance": report.book_balance,
+                "reconciled_balance": reconciliation.reconciled_balance,
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Bank account {} reconciled through {} at {}",
+            report.account_code, request.as_of_date, reconciliation.reconciled_balance);
+
+        Ok(reconciliation)
+    }
+
+    pub async fn get_reconciliations(
+        &self,
+        company_id: Uuid,
+        bank_account_id: Option<Uuid>,
+    ) -> ServiceResult<Vec<BankReconciliation>> {
+        sqlx::query_as!(
+            BankReconciliation,
+            r#"
+            SELECT id, company_id, bank_account_id, as_of_date, statement_balance, book_balance,
+                   reconciled_balance, created_by, created_at as "created_at!"
+            FROM bank_reconciliations
+            WHERE company_id = $1 AND ($2::uuid IS NULL OR bank_account_id = $2)
+            ORDER BY as_of_date DESC
+            "#,
+            company_id,
+            bank_account_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// A statement line of the company that is not yet in a locked reconciliation.
+    async fn open_line(&self, line_id: Uuid, company_id: Uuid) -> ServiceResult<BankStatementLine> {
+        let line = sqlx::query_as!(
+            BankStatementLine,
+            r#"
+            SELECT id, statement_id, bank_account_id, line_number, transaction_date, amount, description,
+                   reference, bank_reference, match_status, match_type, matched_record_id, match_method,
+                   matched_by, matched_at, reconciliation_id
+            FROM bank_statement_lines
+            WHERE id = $1 AND company_id = $2
+            "#,
+            line_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Statement line not found".to_string()))?;
+
+        if line.reconciliation_id.is_some() {
+            return Err(ServiceError::Conflict("Statement line is part of a locked reconciliation".to_string()));
+        }
+
+        Ok(line)
+    }
+
+    /// Unmatched book items on the bank account between `from` and `to`.
+    /// Ledger lines of entries that belong to an AP/AR payment are offered
+    /// as the payment instead, which carries the better references.
+    async fn match_candidates(
+        &self,
+        company_id: Uuid,
+        user_id: Uuid,
+        bank_account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> ServiceResult<Vec<MatchCandidate>> {
+        let mut candidates = Vec::new();
+        let mut payment_entry_ids = Vec::new();
+
+        for kind in [BankMatchType::VendorPayment, BankMatchType::CustomerPayment] {
+            let payments = self.subledger_client
+                .payments_between(kind, company_id, user_id, bank_account_id, from, to)
+                .await?;
+            payment_entry_ids.extend(payments.iter().filter_map(|p| p.journal_entry_id));
+
+            let ids: Vec<Uuid> = payments.iter().map(|p| p.id).collect();
+            let matched: HashSet<Uuid> = sqlx::query_scalar!(
+                r#"
+                SELECT matched_record_id as "matched_record_id!"
+                FROM bank_statement_lines
+                WHERE match_type = $1 AND matched_record_id = ANY($2)
+                "#,
+                kind.to_string(),
+                &ids
+            )
+            .fetch_all(&self.db)
+            .await
+            .map_err(ServiceError::Database)?
+            .into_iter()
+            .collect();
+
+            candidates.extend(
+                payments
+                    .into_iter()
+                    .filter(|p| !p.is_reversed && !matched.contains(&p.id))
+                    .map(|p| MatchCandidate {
+                        match_type: kind,
+                        record_id: p.id,
+                        date: p.payment_date,
+                        amount: bank_amount(kind, &p),
+                        references: [Some(p.payment_number), p.payment_reference, p.check_number]
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                    }),
+            );
+        }
+
+        let journal_lines = sqlx::query!(
+            r#"
+            SELECT jel.id, je.entry_date, jel.debit_amount - jel.credit_amount as "amount!",
+                   je.entry_number, je.reference
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            WHERE je.company_id = $1 AND jel.account_id = $2 AND je.status = 'POSTED'
+              AND je.entry_date BETWEEN $3 AND $4
+              AND NOT EXISTS (
+                  SELECT 1 FROM bank_statement_lines bsl
+                  WHERE bsl.match_type = 'JOURNAL_LINE' AND bsl.matched_record_id = jel.id
+              )
+              AND NOT (je.id = ANY($5))
+            "#,
+            company_id,
+            bank_account_id,
+            from,
+            to,
+            &payment_entry_ids
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        candidates.extend(journal_lines.into_iter().map(|row| MatchCandidate {
+            match_type: BankMatchType::JournalLine,
+            record_id: row.id,
+            date: row.entry_date,
+            amount: row.amount,
+            references: [Some(row.entry_number), row.reference].into_iter().flatten().collect(),
+        }));
+
+        Ok(candidates)
+    }
+}
+
+/// Rejects posting, cancelling or reversing an entry that moves a bank
+/// account on or before the date the account is reconciled through. Takes a
+/// shared lock on each account of the entry so the check cannot race a
+/// reconciliation being locked. Call inside the posting transaction.
+pub async fn ensure_not_reconciled(
+    tx: &mut Transaction<'_, Postgres>,
+    company_id: Uuid,
+    entry_id: Uuid,
+) -> ServiceResult<()> {
+    let entry = sqlx::query!(
+        r#"
+        SELECT je.entry_date, array_agg(DISTINCT jel.account_id ORDER BY jel.account_id) as "account_ids!"
+        FROM journal_entries je
+        JOIN journal_entry_lines jel ON jel.journal_entry_id = je.id
+        WHERE je.id = $1 AND je.company_id = $2
+        GROUP BY je.id, je.entry_date
+        "#,
+        entry_id,
+        company_id
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    let Some(entry) = entry else {
+        return Ok(());
+    };
+
+    for account_id in &entry.account_ids {
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock_shared(hashtext('bank_reconciliation:' || $1::text))",
+            account_id.to_string()
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+    }
+
+    let reconciled = sqlx::query!(
+        r#"
+        SELECT a.account_code, MAX(br.as_of_date) as "as_of_date!"
+        FROM bank_reconciliations br
+        JOIN accounts a ON a.id = br.bank_account_id
+        WHERE br.bank_account_id = ANY($1) AND br.as_of_date >= $2
+        GROUP BY a.account_code
+        ORDER BY a.account_code
+        LIMIT 1
+        "#,
+        &entry.account_ids,
+        entry.entry_date
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    if let Some(reconciled) = reconciled {
+        return Err(ServiceError::Validation(format!(
+            "Bank account {} is reconciled through {}; entries dated {} cannot change it",
+            reconciled.account_code, reconciled.as_of_date, entry.entry_date
+        )));
+    }
+
+    Ok(())
+}
+
+/// Serializes imports and reconciliations of a bank account, and makes
+/// postings to it wait for them.
+async fn lock_bank_account(tx: &mut Transaction<'_, Postgres>, bank_account_id: Uuid) -> ServiceResult<()> {
+    sqlx::query!(
+        "SELECT pg_advisory_xact_lock(hashtext('bank_reconciliation:' || $1::text))",
+        bank_account_id.to_string()
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(())
+}
+
+async fn last_reconciliation<'e, E>(executor: E, bank_account_id: Uuid) -> ServiceResult<Option<BankReconciliation>>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as!(
+        BankReconciliation,
+        r#"
+        SELECT id, company_id, bank_account_id, as_of_date, statement_balance, book_balance,
+               reconciled_balance, created_by, created_at as "created_at!"
+        FROM bank_reconciliations
+        WHERE bank_account_id = $1
+        ORDER BY as_of_date DESC
+        LIMIT 1
+        "#,
+        bank_account_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)
+}
+
+/// Code and name of an active asset account of the company.
+async fn bank_account<'e, E>(executor: E, company_id: Uuid, bank_account_id: Uuid) -> ServiceResult<(String, String)>
+where
+    E: PgExecutor<'e>,
+{
+    let account = sqlx::query!(
+        r#"
+        SELECT account_code, account_name, account_type::text as "account_type!", is_active
+        FROM accounts
+        WHERE id = $1 AND company_id = $2
+        "#,
+        bank_account_id,
+        company_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)?
+    .ok_or_else(|| ServiceError::NotFound("Bank account not found".to_string()))?;
+
+    if account.account_type != "ASSET" || account.is_active != Some(true) {
+        return Err(ServiceError::Validation(format!(
+            "Account {} is not an active asset account",
+            account.account_code
+        )));
+    }
+
+    Ok((account.account_code, account.account_name))
+}
+
+/// What a payment does to the bank balance: vendor payments leave the
+/// account net of the tax withheld from them, customer payments come in.
+fn bank_amount(kind: BankMatchType, payment: &SubledgerPayment) -> Decimal {
+    match kind {
+        BankMatchType::VendorPayment => -(payment.payment_amount - payment.withholding_amount),
+        _ => payment.payment_amount,
+    }
+}
+
+/// Pairs statement lines with candidates of exactly the same amount within
+/// `window_days`. A candidate whose reference appears in the statement text
+/// wins, then the closest date; when the best two are indistinguishable the
+/// line is left unmatched. Each candidate is used at most once.
+fn pick_matches(
+    lines: &[UnmatchedLine],
+    candidates: &[MatchCandidate],
+    window_days: i64,
+) -> Vec<(Uuid, usize)> {
+    let mut used = vec![false; candidates.len()];
+    let mut matches = Vec::new();
+
+    for line in lines {
+        let text = normalize_reference(&line.text);
+
+        let mut scored: Vec<(bool, i64, usize)> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(index, c)| !used[*index] && c.amount == line.amount)
+            .map(|(index, c)| {
+                let distance = (c.date - line.date).num_days().abs();
+                let reference_hit = c.references
+                    .iter()
+                    .map(|r| normalize_reference(r))
+                    .any(|r| r.len() >= 4 && text.contains(&r));
+                (reference_hit, distance, index)
+            })
+            .filter(|(_, distance, _)| *distance <= window_days)
+            .collect();
+
+        // Reference hits first, then the nearest date
+        scored.sort_by_key(|(reference_hit, distance, _)| (!reference_hit, *distance));
+
+        match scored.as_slice() {
+            [] => {}
+            [(hit, distance, _), (next_hit, next_distance, _), ..]
+                if hit == next_hit && distance == next_distance => {}
+            [(_, _, index), ..] => {
+                used[*index] = true;
+                matches.push((line.id, *index));
+            }
+        }
+    }
+
+    matches
+}
+
+fn normalize_reference(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn candidate(day: u32, amount: i64, reference: &str) -> MatchCandidate {
+        MatchCandidate {
+            match_type: BankMatchType::JournalLine,
+            record_id: Uuid::new_v4(),
+            date: date(day),
+            amount: Decimal::from(amount),
+            references: vec![reference.to_string()],
+        }
+    }
+
+    fn line(day: u32, amount: i64, text: &str) -> UnmatchedLine {
+        UnmatchedLine { id: Uuid::new_v4(), date: date(day), amount: Decimal::from(amount), text: text.to_string() }
+    }
+
+    #[test]
+    fn test_reference_breaks_ties_and_ambiguous_lines_stay_open() {
+        let candidates = vec![
+            candidate(4, 2_500_000, "PAY-0001"),
+            candidate(4, 2_500_000, "PAY-0002"),
+            candidate(10, -6_500, "JE-000031"),
+            candidate(10, -6_500, "JE-000032"),
+        ];
+        let lines = vec![
+            line(5, 2_500_000, "TRSF E-BANKING PAY 0002"),
+            line(5, 2_500_000, "SETORAN TUNAI"),
+            line(11, -6_500, "BIAYA ADM"),
+            line(30, 1_000, "BUNGA"),
+        ];
+
+        let matches = pick_matches(&lines, &candidates, 3);
+
+        // The referenced payment goes first, leaving one candidate for the cash deposit
+        assert_eq!(matches, vec![(lines[0].id, 1), (lines[1].id, 0)]);
+    }
+
+    #[test]
+    fn test_nearest_date_within_window() {
+        let candidates = vec![candidate(1, -100_000, "X"), candidate(6, -100_000, "Y")];
+        let lines = vec![line(5, -100_000, "DEBIT"), line(20, -100_000, "DEBIT")];
+
+        let matches = pick_matches(&lines, &candidates, 3);
+        assert_eq!(matches, vec![(lines[0].id, 1)]);
+    }
+
+    #[test]
+    fn test_vendor_payment_leaves_bank_net_of_withholding() {
+        let payment = SubledgerPayment {
+            id: Uuid::new_v4(),
+            payment_number: "PAY-0001".to_string(),
+            payment_date: date(4),
+            payment_amount: Decimal::from(11_100_000),
+            withholding_amount: Decimal::from(200_000),
+            bank_account_id: None,
+            payment_reference: None,
+            check_number: None,
+            is_reversed: false,
+            journal_entry_id: None,
+        };
+
+        assert_eq!(bank_amount(BankMatchType::VendorPayment, &payment), Decimal::from(-10_900_000));
+        assert_eq!(bank_amount(BankMatchType::CustomerPayment, &payment), Decimal::from(11_100_000));
+    }
+}
@@ -0,0 +1,386 @@
+//! Bank statement parsers for internet-banking CSV exports and SWIFT MT940
+//! files. Amounts are signed from the company's point of view: deposits are
+//! positive, withdrawals negative.
+
+use super::exchange_rate_service::parse_decimal;
+use super::import_service::parse_date;
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedStatement {
+    pub statement_number: Option<String>,
+    pub account_number: Option<String>,
+    pub opening_balance: Option<Decimal>,
+    pub closing_balance: Option<Decimal>,
+    pub lines: Vec<ParsedStatementLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedStatementLine {
+    pub transaction_date: NaiveDate,
+    pub amount: Decimal,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub bank_reference: Option<String>,
+    /// Running balance after the line, when the export includes one
+    pub balance: Option<Decimal>,
+}
+
+/// Reads a CSV export with a date column, a description and either a signed
+/// `amount` (optionally suffixed `DB`/`CR`, as in BCA exports, or paired with
+/// a D/C column) or separate `debit` and `credit` columns. Indonesian headers
+/// (`tanggal`, `keterangan`, `mutasi`, `debet`, `kredit`, `saldo`) are
+/// accepted too. Any invalid row rejects the whole file.
+pub fn parse_csv(data: &[u8]) -> Result<ParsedStatement, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV: {}", e))?
+        .iter()
+        .map(|h| h.to_lowercase().replace([' ', '-', '.'], "_"))
+        .collect();
+
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+
+    let date_column = column(&["date", "transaction_date", "posting_date", "tanggal", "tgl_transaksi"])
+        .ok_or_else(|| "Missing required column date".to_string())?;
+    let description_column = column(&["description", "remarks", "keterangan", "uraian"]);
+    let reference_column = column(&["reference", "ref", "referensi", "no_referensi"]);
+    let amount_column = column(&["amount", "mutasi", "jumlah", "nominal"]);
+    let direction_column = column(&["type", "dc", "d_c", "db_cr", "jenis"]);
+    let debit_column = column(&["debit", "debet"]);
+    let credit_column = column(&["credit", "kredit"]);
+    let balance_column = column(&["balance", "saldo"]);
+
+    if amount_column.is_none() && (debit_column.is_none() || credit_column.is_none()) {
+        return Err("Missing required column amount (or debit and credit)".to_string());
+    }
+
+    let mut lines = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let row_number = index + 2;
+        let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+        if record.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+
+        let cell = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+
+        let transaction_date = parse_date(cell(Some(date_column)))
+            .ok_or_else(|| format!("Row {}: invalid date '{}'", row_number, cell(Some(date_column))))?;
+
+        let amount = match amount_column {
+            Some(_) => {
+                let amount = parse_statement_amount(cell(amount_column))
+                    .ok_or_else(|| format!("Row {}: invalid amount '{}'", row_number, cell(amount_column)))?;
+                match cell(direction_column).to_uppercase().as_str() {
+                    "D" | "DB" | "DEBIT" | "DEBET" => -amount.abs(),
+                    "C" | "K" | "CR" | "CREDIT" | "KREDIT" => amount.abs(),
+                    _ => amount,
+                }
+            }
+            None => {
+                let debit = parse_optional_amount(cell(debit_column))
+                    .ok_or_else(|| format!("Row {}: invalid debit '{}'", row_number, cell(debit_column)))?;
+                let credit = parse_optional_amount(cell(credit_column))
+                    .ok_or_else(|| format!("Row {}: invalid credit '{}'", row_number, cell(credit_column)))?;
+                credit.abs() - debit.abs()
+            }
+        };
+
+        if amount.is_zero() {
+            return Err(format!("Row {}: amount is zero", row_number));
+        }
+
+        let balance = match cell(balance_column) {
+            "" => None,
+            value => Some(
+                parse_statement_amount(value)
+                    .ok_or_else(|| format!("Row {}: invalid balance '{}'", row_number, value))?,
+            ),
+        };
+
+        lines.push(ParsedStatementLine {
+            transaction_date,
+            amount,
+            description: non_empty(cell(description_column)),
+            reference: non_empty(cell(reference_column)),
+            bank_reference: None,
+            balance,
+        });
+    }
+
+    let closing_balance = lines.last().and_then(|l| l.balance);
+    let opening_balance = lines.first().and_then(|l| l.balance.map(|b| b - l.amount));
+
+    Ok(ParsedStatement {
+        statement_number: None,
+        account_number: None,
+        opening_balance,
+        closing_balance,
+        lines,
+    })
+}
+
+/// Reads a SWIFT MT940 customer statement: `:25:` account, `:28C:` statement
+/// number, `:60F:`/`:62F:` balances and `:61:` transactions, each followed by
+/// an optional `:86:` narrative.
+pub fn parse_mt940(data: &[u8]) -> Result<ParsedStatement, String> {
+    let text = String::from_utf8_lossy(data);
+
+    // Collect tagged fields, joining continuation lines onto the previous one
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() || line.starts_with('{') || line.starts_with('-') {
+            continue;
+        }
+        if let Some((tag, value)) = line.strip_prefix(':').and_then(|rest| rest.split_once(':')) {
+            if !tag.is_empty() && tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+                fields.push((tag.to_string(), value.to_string()));
+                continue;
+            }
+        }
+        if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+
+    if fields.is_empty() {
+        return Err("Not an MT940 statement".to_string());
+    }
+
+    let mut statement = ParsedStatement::default();
+    let mut narrative_target: Option<usize> = None;
+
+    for (tag, value) in fields {
+        match tag.as_str() {
+            "25" => statement.account_number = non_empty(&value),
+            "28" | "28C" => statement.statement_number = non_empty(&value),
+            "60F" | "60M" if statement.opening_balance.is_none() => {
+                statement.opening_balance = Some(parse_mt940_balance(&value)?);
+            }
+            "62F" | "62M" => {
+                statement.closing_balance = Some(parse_mt940_balance(&value)?);
+                narrative_target = None;
+            }
+            "61" => {
+                statement.lines.push(parse_mt940_transaction(&value)?);
+                narrative_target = Some(statement.lines.len() - 1);
+            }
+            "86" => {
+                if let Some(index) = narrative_target.take() {
+                    let narrative = value.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+                    statement.lines[index].description = non_empty(&narrative);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if statement.lines.is_empty() && statement.opening_balance.is_none() {
+        return Err("Not an MT940 statement".to_string());
+    }
+
+    Ok(statement)
+}
+
+/// `C240301IDR15000000,00`: credit/debit mark, date, currency and amount.
+fn parse_mt940_balance(value: &str) -> Result<Decimal, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid MT940 balance '{}'", value);
+
+    let mark = value.chars().next().ok_or_else(invalid)?;
+    let amount = value.get(10..).map(parse_mt940_amount).ok_or_else(invalid)?.ok_or_else(invalid)?;
+
+    match mark {
+        'C' => Ok(amount),
+        'D' => Ok(-amount),
+        _ => Err(invalid()),
+    }
+}
+
+/// `2403010301D150000,00NTRFINV-001//BANKREF`: value date, optional entry
+/// date, debit/credit mark, amount, transaction type, customer reference and
+/// bank reference.
+fn parse_mt940_transaction(value: &str) -> Result<ParsedStatementLine, String> {
+    let first_line = value.lines().next().unwrap_or_default().trim();
+    let invalid = || format!("Invalid MT940 transaction '{}'", first_line);
+
+    let value_date = first_line.get(..6)
+        .and_then(|d| NaiveDate::parse_from_str(&format!("20{}", d), "%Y%m%d").ok())
+        .ok_or_else(invalid)?;
+    let mut rest = &first_line[6..];
+
+    // The entry (booking) date is what appears on the printed statement
+    let mut transaction_date = value_date;
+    if rest.len() >= 4 && rest[..4].chars().all(|c| c.is_ascii_digit()) {
+        let month: u32 = rest[..2].parse().map_err(|_| invalid())?;
+        let day: u32 = rest[2..4].parse().map_err(|_| invalid())?;
+        let year = match (month, value_date.month()) {
+            (12, 1) => value_date.year() - 1,
+            (1, 12) => value_date.year() + 1,
+            _ => value_date.year(),
+        };
+        transaction_date = NaiveDate::from_ymd_opt(year, month, day).ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+
+    let (sign, after_mark) = if let Some(r) = rest.strip_prefix("RC") {
+        (-Decimal::ONE, r)
+    } else if let Some(r) = rest.strip_prefix("RD") {
+        (Decimal::ONE, r)
+    } else if let Some(r) = rest.strip_prefix('C') {
+        (Decimal::ONE, r)
+    } else if let Some(r) = rest.strip_prefix('D') {
+        (-Decimal::ONE, r)
+    } else {
+        return Err(invalid());
+    };
+
+    // Optional funds code (third letter of the currency code)
+    let after_funds = match after_mark.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => &after_mark[1..],
+        _ => after_mark,
+    };
+
+    let amount_len = after_funds
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .unwrap_or(after_funds.len());
+    let amount = parse_mt940_amount(&after_funds[..amount_len]).ok_or_else(invalid)?;
+
+    // Four-character transaction type such as NTRF or NCHG
+    let references = after_funds.get(amount_len + 4..).unwrap_or_default();
+    let (reference, bank_reference) = match references.split_once("//") {
+        Some((customer, bank)) => (customer, Some(bank)),
+        None => (references, None),
+    };
+
+    Ok(ParsedStatementLine {
+        transaction_date,
+        amount: sign * amount,
+        description: None,
+        reference: non_empty(reference).filter(|r| r != "NONREF"),
+        bank_reference: bank_reference.and_then(non_empty),
+        balance: None,
+    })
+}
+
+/// MT940 amounts always use a comma as the decimal separator.
+fn parse_mt940_amount(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value.replace(',', ".").trim_end_matches('.').parse().ok()
+}
+
+/// A statement amount such as `-150.000,00`, `(150,000.00)` or
+/// `1,500,000.00 DB`.
+fn parse_statement_amount(value: &str) -> Option<Decimal> {
+    let mut value = value.trim().to_uppercase();
+    let mut negative = false;
+
+    if let Some(v) = value.strip_suffix("DB") {
+        negative = true;
+        value = v.to_string();
+    } else if let Some(v) = value.strip_suffix("CR") {
+        value = v.to_string();
+    }
+
+    let mut value = value.trim().trim_start_matches("RP").trim().to_string();
+    if value.starts_with('(') && value.ends_with(')') {
+        negative = true;
+        value = value[1..value.len() - 1].to_string();
+    }
+    if let Some(v) = value.strip_prefix('-') {
+        negative = !negative;
+        value = v.to_string();
+    }
+
+    let amount = parse_decimal(&value)?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// Blank cells count as zero.
+fn parse_optional_amount(value: &str) -> Option<Decimal> {
+    if value.trim().is_empty() {
+        return Some(Decimal::ZERO);
+    }
+    parse_statement_amount(value)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() { None } else { Some(value.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_mt940_statement() {
+        let data = "{1:F01BMRIIDJAXXXX0000000000}{2:O940}{4:\n\
+                    :20:STMT240301\n\
+                    :25:1230004567890\n\
+                    :28C:00061/001\n\
+                    :60F:C240229IDR15000000,00\n\
+                    :61:2403010301D6500,00NCHGNONREF//FEE0301\n\
+                    :86:BIAYA ADM\n\
+                    :61:2403020302C2500000,NTRFINV-2024-001//TRF889\n\
+                    :86:TRSF E-BANKING CR PT MAJU\n\
+                    JAYA INV-2024-001\n\
+                    :62F:C240302IDR17493500,00\n\
+                    -}";
+
+        let statement = parse_mt940(data.as_bytes()).unwrap();
+        assert_eq!(statement.account_number.as_deref(), Some("1230004567890"));
+        assert_eq!(statement.statement_number.as_deref(), Some("00061/001"));
+        assert_eq!(statement.opening_balance, Some(dec("15000000")));
+        assert_eq!(statement.closing_balance, Some(dec("17493500")));
+        assert_eq!(statement.lines.len(), 2);
+
+        let fee = &statement.lines[0];
+        assert_eq!(fee.transaction_date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(fee.amount, dec("-6500"));
+        assert_eq!(fee.reference, None);
+        assert_eq!(fee.bank_reference.as_deref(), Some("FEE0301"));
+
+        let transfer = &statement.lines[1];
+        assert_eq!(transfer.amount, dec("2500000"));
+        assert_eq!(transfer.reference.as_deref(), Some("INV-2024-001"));
+        assert_eq!(transfer.description.as_deref(), Some("TRSF E-BANKING CR PT MAJU JAYA INV-2024-001"));
+    }
+
+    #[test]
+    fn test_parse_bca_style_csv() {
+        let data = "Tanggal,Keterangan,Mutasi,Saldo\n\
+                    01/03/2024,BIAYA ADM,\"6,500.00 DB\",\"14,993,500.00\"\n\
+                    02/03/2024,TRSF E-BANKING CR INV-2024-001,\"2,500,000.00 CR\",\"17,493,500.00\"\n";
+
+        let statement = parse_csv(data.as_bytes()).unwrap();
+        assert_eq!(statement.lines.len(), 2);
+        assert_eq!(statement.lines[0].amount, dec("-6500"));
+        assert_eq!(statement.lines[1].amount, dec("2500000"));
+        assert_eq!(statement.opening_balance, Some(dec("15000000")));
+        assert_eq!(statement.closing_balance, Some(dec("17493500")));
+
+        let split = "date,description,debit,credit\n2024-03-01,Fee,6500,\n2024-03-02,Interest,,\"1.250,50\"\n";
+        let statement = parse_csv(split.as_bytes()).unwrap();
+        assert_eq!(statement.lines[0].amount, dec("-6500"));
+        assert_eq!(statement.lines[1].amount, dec("1250.50"));
+    }
+}
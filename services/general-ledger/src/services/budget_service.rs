@@ -0,0 +1,612 @@
+use crate::models::*;
+use super::{PeriodService, PostingRuleService};
+use chrono::{Months, NaiveDate};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use database::budgets::{month_start, BudgetCheckLine, BudgetWarning};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+pub struct BudgetService {
+    db: PgPool,
+    period_service: PeriodService,
+    posting_rule_service: PostingRuleService,
+}
+
+impl BudgetService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            period_service: PeriodService::new(db.clone()),
+            posting_rule_service: PostingRuleService::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates the next draft version of the budget for the fiscal year.
+    pub async fn create_budget(
+        &self,
+        request: CreateBudgetRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let lines = self.validate_lines(company_id, request.fiscal_year, request.lines).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let budget_id = self.insert_budget(
+            &mut tx,
+            company_id,
+            &request.budget_name,
+            request.fiscal_year,
+            request.description.as_deref(),
+            None,
+            user_id,
+        ).await?;
+        self.insert_lines(&mut tx, budget_id, &lines).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created budget {} for {} with {} lines (company {})",
+            request.budget_name, request.fiscal_year, lines.len(), company_id);
+
+        self.get_budget(budget_id, company_id).await
+    }
+
+    /// Seeds a draft budget with last year's posted revenue and expense per
+    /// account and month, moved forward a year and raised by the uplift.
+    pub async fn create_from_actuals(
+        &self,
+        request: CreateBudgetFromActualsRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let (start, end) = self.period_service
+            .fiscal_year_bounds(company_id, request.fiscal_year - 1)
+            .await?;
+        let uplift = request.uplift_percent.unwrap_or(Decimal::ZERO);
+
+        let actuals = sqlx::query!(
+            r#"
+            SELECT jel.account_id,
+                   DATE_TRUNC('month', je.entry_date)::date as "period_start!",
+                   CASE WHEN $4 THEN jel.department END as department,
+                   CASE WHEN $5 THEN jel.project_code END as project_code,
+                   SUM(CASE WHEN a.account_type = 'REVENUE' THEN jel.credit_amount - jel.debit_amount
+                            ELSE jel.debit_amount - jel.credit_amount END) as "amount!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            JOIN accounts a ON a.id = jel.account_id
+            WHERE je.company_id = $1 AND je.status = 'POSTED'
+              AND je.entry_date BETWEEN $2 AND $3
+              AND a.account_type IN ('REVENUE', 'EXPENSE')
+              AND COALESCE(je.source_document_type, '') <> 'YEAR_END_CLOSE'
+            GROUP BY 1, 2, 3, 4
+            ORDER BY 2, 1
+            "#,
+            company_id,
+            start,
+            end,
+            request.by_department,
+            request.by_project
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let lines: Vec<BudgetLineRequest> = actuals
+            .into_iter()
+            .filter(|row| row.amount > Decimal::ZERO)
+            .filter_map(|row| Some(BudgetLineRequest {
+                account_id: row.account_id,
+                period_start: row.period_start.checked_add_months(Months::new(12))?,
+                department: row.department,
+                project_code: row.project_code,
+                amount: apply_uplift(row.amount, uplift),
+            }))
+            .collect();
+
+        if lines.is_empty() {
+            return Err(ServiceError::Validation(format!(
+                "No posted revenue or expense in fiscal year {} to copy",
+                request.fiscal_year - 1
+            )));
+        }
+
+        let budget = self.create_budget(
+            CreateBudgetRequest {
+                budget_name: request.budget_name,
+                fiscal_year: request.fiscal_year,
+                description: request.description,
+                lines,
+            },
+            company_id,
+            user_id,
+        ).await?;
+
+        tracing::info!("Budget {} seeded from {} actuals with {}% uplift",
+            budget.budget.id, request.fiscal_year - 1, uplift);
+
+        Ok(budget)
+    }
+
+    /// Copies a budget into a new draft version of the same year.
+    pub async fn create_version(
+        &self,
+        budget_id: Uuid,
+        company_id: Uuid,
+        request: CreateBudgetVersionRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let source = self.get_budget(budget_id, company_id).await?;
+        let uplift = request.uplift_percent.unwrap_or(Decimal::ZERO);
+
+        let lines: Vec<BudgetLineRequest> = source.lines
+            .into_iter()
+            .map(|line| BudgetLineRequest {
+                account_id: line.account_id,
+                period_start: line.period_start,
+                department: line.department,
+                project_code: line.project_code,
+                amount: apply_uplift(line.amount, uplift),
+            })
+            .collect();
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let new_budget_id = self.insert_budget(
+            &mut tx,
+            company_id,
+            request.budget_name.as_deref().unwrap_or(&source.budget.budget_name),
+            source.budget.fiscal_year,
+            request.description.as_deref().or(source.budget.description.as_deref()),
+            Some(budget_id),
+            user_id,
+        ).await?;
+        self.insert_lines(&mut tx, new_budget_id, &lines).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Budget {} copied to new version {} with {}% uplift", budget_id, new_budget_id, uplift);
+
+        self.get_budget(new_budget_id, company_id).await
+    }
+
+    pub async fn get_budgets(
+        &self,
+        company_id: Uuid,
+        fiscal_year: Option<i32>,
+        status: Option<String>,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<Budget>> {
+        sqlx::query_as!(
+            Budget,
+            r#"
+            SELECT id, company_id, budget_name, fiscal_year, version, description, status,
+                   based_on_budget_id, created_by, created_at as "created_at!", updated_at as "updated_at!"
+            FROM budgets
+            WHERE company_id = $1
+              AND ($2::integer IS NULL OR fiscal_year = $2)
+              AND ($3::text IS NULL OR status = $3)
+            ORDER BY fiscal_year DESC, version DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            company_id,
+            fiscal_year,
+            status.map(|s| s.to_uppercase()),
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_budget(&self, budget_id: Uuid, company_id: Uuid) -> ServiceResult<BudgetWithLines> {
+        let budget = sqlx::query_as!(
+            Budget,
+            r#"
+            SELECT id, company_id, budget_name, fiscal_year, version, description, status,
+                   based_on_budget_id, created_by, created_at as "created_at!", updated_at as "updated_at!"
+            FROM budgets
+            WHERE id = $1 AND company_id = $2
+            "#,
+            budget_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Budget not found".to_string()))?;
+
+        let lines = sqlx::query_as!(
+            BudgetLine,
+            r#"
+            SELECT bl.id, bl.budget_id, bl.account_id, a.account_code as "account_code?",
+                   a.account_name as "account_name?", bl.period_start, bl.department, bl.project_code,
+                   bl.amount
+            FROM budget_lines bl
+            LEFT JOIN accounts a ON a.id = bl.account_id
+            WHERE bl.budget_id = $1
+            ORDER BY a.account_code, bl.period_start, bl.department, bl.project_code
+            "#,
+            budget_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let total_amount = lines.iter().map(|l| l.amount).sum();
+
+        Ok(BudgetWithLines { budget, lines, total_amount })
+    }
+
+    /// Replaces the lines of a draft budget.
+    pub async fn update_budget(
+        &self,
+        budget_id: Uuid,
+        company_id: Uuid,
+        request: UpdateBudgetRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let budget = self.get_budget(budget_id, company_id).await?.budget;
+        if budget.status != BudgetStatus::Draft.to_string() {
+            return Err(ServiceError::Conflict(format!(
+                "Only draft budgets can be edited; create a new version of this {} budget",
+                budget.status.to_lowercase()
+            )));
+        }
+
+        let lines = self.validate_lines(company_id, budget.fiscal_year, request.lines).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            "UPDATE budgets SET budget_name = $1, description = $2, updated_at = NOW() WHERE id = $3",
+            request.budget_name,
+            request.description,
+            budget_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!("DELETE FROM budget_lines WHERE budget_id = $1", budget_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        self.insert_lines(&mut tx, budget_id, &lines).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Budget {} updated by user {}", budget_id, user_id);
+
+        self.get_budget(budget_id, company_id).await
+    }
+
+    pub async fn delete_budget(&self, budget_id: Uuid, company_id: Uuid, user_id: Uuid) -> ServiceResult<()> {
+        let result = sqlx::query!(
+            "DELETE FROM budgets WHERE id = $1 AND company_id = $2 AND status = 'DRAFT'",
+            budget_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Draft budget not found".to_string()));
+        }
+
+        tracing::info!("Budget {} deleted by user {}", budget_id, user_id);
+
+        Ok(())
+    }
+
+    /// Makes this version the active budget for its fiscal year; the version
+    /// active until now is archived.
+    pub async fn activate_budget(
+        &self,
+        budget_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let budget = sqlx::query!(
+            "SELECT fiscal_year, status FROM budgets WHERE id = $1 AND company_id = $2 FOR UPDATE",
+            budget_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Budget not found".to_string()))?;
+
+        if budget.status == BudgetStatus::Active.to_string() {
+            return Err(ServiceError::Conflict("Budget is already active".to_string()));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE budgets SET status = 'ARCHIVED', updated_at = NOW()
+            WHERE company_id = $1 AND fiscal_year = $2 AND status = 'ACTIVE'
+            "#,
+            company_id,
+            budget.fiscal_year
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query!(
+            "UPDATE budgets SET status = 'ACTIVE', updated_at = NOW() WHERE id = $1",
+            budget_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Budget {} activated for {} by user {}", budget_id, budget.fiscal_year, user_id);
+
+        self.get_budget(budget_id, company_id).await
+    }
+
+    pub async fn archive_budget(
+        &self,
+        budget_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<BudgetWithLines> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE budgets SET status = 'ARCHIVED', updated_at = NOW()
+            WHERE id = $1 AND company_id = $2 AND status <> 'ARCHIVED'
+            "#,
+            budget_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Budget not found or already archived".to_string()));
+        }
+
+        tracing::info!("Budget {} archived by user {}", budget_id, user_id);
+
+        self.get_budget(budget_id, company_id).await
+    }
+
+    /// Which active budgets the given spending would exceed. Empty unless
+    /// budget warnings are enabled in the ledger settings. Lines without an
+    /// account are checked against the expense account the posting rules
+    /// would book them to.
+    pub async fn check_budget(
+        &self,
+        company_id: Uuid,
+        request: BudgetCheckRequest,
+    ) -> ServiceResult<Vec<BudgetWarning>> {
+        let mut lines: Vec<BudgetCheckLine> = Vec::with_capacity(request.lines.len());
+        for line in request.lines {
+            let account_id = match line.account_id {
+                Some(account_id) => account_id,
+                None => {
+                    let event_type = request.event_type.ok_or_else(|| ServiceError::Validation(
+                        "event_type is required for lines without an account_id".to_string()
+                    ))?;
+
+                    self.posting_rule_service
+                        .resolve_account(company_id, &ResolvePostingAccountRequest {
+                            event_type,
+                            account_role: PostingAccountRole::Expense,
+                            vendor_group: request.vendor_group.clone(),
+                            item_category: line.item_category.clone(),
+                            tax_type: None,
+                        })
+                        .await?
+                        .account_id
+                }
+            };
+
+            lines.push(BudgetCheckLine {
+                account_id,
+                department: line.department,
+                project_code: line.project_code,
+                amount: line.amount,
+            });
+        }
+
+        database::budgets::budget_warnings(&self.db, company_id, request.date, &lines).await
+    }
+
+    async fn validate_lines(
+        &self,
+        company_id: Uuid,
+        fiscal_year: i32,
+        lines: Vec<BudgetLineRequest>,
+    ) -> ServiceResult<Vec<BudgetLineRequest>> {
+        let (start, end) = self.period_service.fiscal_year_bounds(company_id, fiscal_year).await?;
+        let lines = normalize_lines(lines, start, end).map_err(ServiceError::Validation)?;
+
+        let mut account_ids: Vec<Uuid> = lines.iter().map(|l| l.account_id).collect();
+        account_ids.sort();
+        account_ids.dedup();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, company_id).await?;
+
+        let dimension_lines: Vec<CreateJournalEntryLineRequest> = lines
+            .iter()
+            .filter(|l| l.department.is_some() || l.project_code.is_some())
+            .map(|l| CreateJournalEntryLineRequest {
+                account_id: l.account_id,
+                description: None,
+                debit_amount: Decimal::ZERO,
+                credit_amount: Decimal::ZERO,
+                department: l.department.clone(),
+                project_code: l.project_code.clone(),
+                cost_center: None,
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            })
+            .collect();
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, &dimension_lines).await?;
+
+        Ok(lines)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_budget(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        company_id: Uuid,
+        budget_name: &str,
+        fiscal_year: i32,
+        description: Option<&str>,
+        based_on_budget_id: Option<Uuid>,
+        user_id: Uuid,
+    ) -> ServiceResult<Uuid> {
+        // Serialize version numbering per company and year
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext('budget_version:' || $1::text || ':' || $2::text))",
+            company_id.to_string(),
+            fiscal_year.to_string()
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO budgets
+            (id, company_id, budget_name, fiscal_year, version, description, status, based_on_budget_id,
+             created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4,
+                    (SELECT COALESCE(MAX(version), 0) + 1 FROM budgets WHERE company_id = $2 AND fiscal_year = $4),
+                    $5, 'DRAFT', $6, $7, NOW(), NOW())
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            budget_name,
+            fiscal_year,
+            description,
+            based_on_budget_id,
+            user_id
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    async fn insert_lines(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        budget_id: Uuid,
+        lines: &[BudgetLineRequest],
+    ) -> ServiceResult<()> {
+        for line in lines {
+            sqlx::query!(
+                r#"
+                INSERT INTO budget_lines (id, budget_id, account_id, period_start, department, project_code, amount)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                Uuid::new_v4(),
+                budget_id,
+                line.account_id,
+                line.period_start,
+                line.department,
+                line.project_code,
+                line.amount
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Amount raised by `percent` (negative to cut), rounded to whole cents.
+fn apply_uplift(amount: Decimal, percent: Decimal) -> Decimal {
+    (amount * (Decimal::ONE_HUNDRED + percent) / Decimal::ONE_HUNDRED).round_dp(2)
+}
+
+/// Moves every line to the first of its month and checks it falls inside
+/// the fiscal year, is not negative and is not budgeted twice.
+fn normalize_lines(
+    lines: Vec<BudgetLineRequest>,
+    year_start: NaiveDate,
+    year_end: NaiveDate,
+) -> Result<Vec<BudgetLineRequest>, String> {
+    let mut seen = HashSet::new();
+
+    lines
+        .into_iter()
+        .map(|mut line| {
+            if line.period_start < year_start || line.period_start > year_end {
+                return Err(format!(
+                    "Budget month {} is outside the fiscal year {} to {}",
+                    line.period_start, year_start, year_end
+                ));
+            }
+            if line.amount < Decimal::ZERO {
+                return Err("Budget amounts cannot be negative".to_string());
+            }
+
+            line.period_start = month_start(line.period_start);
+            let key = (line.account_id, line.period_start, line.department.clone(), line.project_code.clone());
+            if !seen.insert(key) {
+                return Err(format!(
+                    "Account {} is budgeted more than once for {}",
+                    line.account_id, line.period_start
+                ));
+            }
+
+            Ok(line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_apply_uplift() {
+        assert_eq!(apply_uplift(Decimal::from(12_500_000), Decimal::from(8)), Decimal::from(13_500_000));
+        assert_eq!(
+            apply_uplift(Decimal::from_str("333.33").unwrap(), Decimal::from_str("-2.5").unwrap()),
+            Decimal::from_str("325.00").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_normalize_lines() {
+        let line = |period_start: NaiveDate, amount: i64| BudgetLineRequest {
+            account_id: Uuid::nil(),
+            period_start,
+            department: None,
+            project_code: None,
+            amount: Decimal::from(amount),
+        };
+        let (start, end) = (date(2025, 1, 1), date(2025, 12, 31));
+
+        let lines = normalize_lines(vec![line(date(2025, 3, 15), 100)], start, end).unwrap();
+        assert_eq!(lines[0].period_start, date(2025, 3, 1));
+
+        assert!(normalize_lines(vec![line(date(2026, 1, 1), 100)], start, end).is_err());
+        assert!(normalize_lines(vec![line(date(2025, 3, 1), -1)], start, end).is_err());
+        assert!(normalize_lines(vec![line(date(2025, 3, 1), 1), line(date(2025, 3, 20), 2)], start, end).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Master lists of departments, projects and cost centers that journal
+/// lines may be tagged with.
+pub struct DimensionService {
+    db: PgPool,
+}
+
+impl DimensionService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn create_value(
+        &self,
+        company_id: Uuid,
+        request: CreateDimensionValueRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<DimensionValue> {
+        let code = request.code.trim().to_string();
+
+        let value = sqlx::query_as!(
+            DimensionValue,
+            r#"
+            INSERT INTO dimension_values
+            (id, company_id, dimension_type, code, name, is_active, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, true, $6, NOW(), NOW())
+            ON CONFLICT (company_id, dimension_type, code) DO NOTHING
+            RETURNING id, company_id, dimension_type, code, name, is_active as "is_active!",
+                      created_by, created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.dimension_type.to_string(),
+            code,
+            request.name,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict(
+            format!("{} {} already exists", request.dimension_type, code)
+        ))?;
+
+        tracing::info!("Created {} {} for company {}", value.dimension_type, value.code, company_id);
+
+        Ok(value)
+    }
+
+    pub async fn get_values(
+        &self,
+        company_id: Uuid,
+        dimension_type: Option<DimensionType>,
+    ) -> ServiceResult<Vec<DimensionValue>> {
+        sqlx::query_as!(
+            DimensionValue,
+            r#"
+            SELECT id, company_id, dimension_type, code, name, is_active as "is_active!",
+                   created_by, created_at as "created_at!", updated_at as "updated_at!"
+            FROM dimension_values
+            WHERE company_id = $1 AND ($2::text IS NULL OR dimension_type = $2)
+            ORDER BY dimension_type, code
+            "#,
+            company_id,
+            dimension_type.map(|t| t.to_string())
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn update_value(
+        &self,
+        value_id: Uuid,
+        company_id: Uuid,
+        request: UpdateDimensionValueRequest,
+    ) -> ServiceResult<DimensionValue> {
+        sqlx::query_as!(
+            DimensionValue,
+            r#"
+            UPDATE dimension_values
+            SET name = $1, is_active = $2, updated_at = NOW()
+            WHERE id = $3 AND company_id = $4
+            RETURNING id, company_id, dimension_type, code, name, is_active as "is_active!",
+                      created_by, created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            request.name,
+            request.is_active,
+            value_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Dimension value not found".to_string()))
+    }
+}
+
+/// Checks every department, project and cost center used on the lines
+/// against the company's active master lists.
+pub async fn validate_line_dimensions(
+    db: &PgPool,
+    company_id: Uuid,
+    lines: &[CreateJournalEntryLineRequest],
+) -> ServiceResult<()> {
+    let values: Vec<(DimensionType, String)> = lines
+        .iter()
+        .flat_map(|line| {
+            [
+                (DimensionType::Department, &line.department),
+                (DimensionType::Project, &line.project_code),
+                (DimensionType::CostCenter, &line.cost_center),
+            ]
+        })
+        .filter_map(|(dimension_type, value)| {
+            value.as_ref()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| (dimension_type, v.clone()))
+        })
+        .collect();
+
+    database::dimensions::ensure_dimensions_exist(db, company_id, &values).await
+}
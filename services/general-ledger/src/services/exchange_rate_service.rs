@@ -0,0 +1,372 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::str::FromStr;
+use utils::CurrencyUtils;
+use uuid::Uuid;
+
+/// A rate read from an import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedRate {
+    pub row_number: usize,
+    pub currency: String,
+    pub effective_date: NaiveDate,
+    pub rate: Decimal,
+}
+
+pub struct ExchangeRateService {
+    db: PgPool,
+}
+
+impl ExchangeRateService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn create_rate(
+        &self,
+        company_id: Uuid,
+        request: CreateExchangeRateRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<ExchangeRate> {
+        self.validate_currency(company_id, &request.currency).await?;
+
+        let rate = database::exchange_rates::upsert_rate(
+            &self.db,
+            company_id,
+            &request.currency,
+            request.rate_type,
+            request.effective_date,
+            request.rate,
+            request.source.as_deref(),
+            user_id,
+        ).await?;
+
+        tracing::info!("Set {} rate for {} effective {} to {} (company {})",
+            rate.rate_type, rate.currency, rate.effective_date, rate.rate, company_id);
+
+        Ok(rate)
+    }
+
+    pub async fn get_rates(
+        &self,
+        company_id: Uuid,
+        currency: Option<String>,
+        rate_type: Option<RateType>,
+        date_from: Option<NaiveDate>,
+        date_to: Option<NaiveDate>,
+    ) -> ServiceResult<Vec<ExchangeRate>> {
+        sqlx::query_as!(
+            ExchangeRate,
+            r#"
+            SELECT id, company_id, currency, rate_type, effective_date, rate, source, created_by,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM exchange_rates
+            WHERE company_id = $1
+              AND ($2::text IS NULL OR currency = $2)
+              AND ($3::text IS NULL OR rate_type = $3)
+              AND ($4::date IS NULL OR effective_date >= $4)
+              AND ($5::date IS NULL OR effective_date <= $5)
+            ORDER BY currency, rate_type, effective_date DESC
+            "#,
+            company_id,
+            currency.map(|c| c.to_uppercase()),
+            rate_type.map(|t| t.to_string()),
+            date_from,
+            date_to
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// The rate in effect on `date`: the latest one on or before it.
+    pub async fn lookup_rate(
+        &self,
+        company_id: Uuid,
+        currency: &str,
+        rate_type: RateType,
+        date: NaiveDate,
+    ) -> ServiceResult<ExchangeRate> {
+        database::exchange_rates::find_rate(&self.db, company_id, currency, rate_type, date)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(format!(
+                "No {} exchange rate for {} on or before {}",
+                rate_type, currency.to_uppercase(), date
+            )))
+    }
+
+    /// The functional currency and the rates in effect on `date` for the
+    /// given currencies, for services that convert balances with them.
+    pub async fn rate_table(
+        &self,
+        company_id: Uuid,
+        currencies: &[String],
+        rate_type: RateType,
+        date: NaiveDate,
+    ) -> ServiceResult<database::exchange_rates::RateTable> {
+        let functional_currency = database::exchange_rates::functional_currency(&self.db, company_id).await?;
+        let currencies: Vec<String> = currencies
+            .iter()
+            .filter(|c| !c.eq_ignore_ascii_case(&functional_currency))
+            .cloned()
+            .collect();
+        let rates = database::exchange_rates::rates_as_of(&self.db, company_id, &currencies, rate_type, date).await?;
+
+        Ok(database::exchange_rates::RateTable {
+            functional_currency,
+            rate_type,
+            as_of_date: date,
+            rates,
+        })
+    }
+
+    pub async fn update_rate(
+        &self,
+        rate_id: Uuid,
+        company_id: Uuid,
+        request: UpdateExchangeRateRequest,
+    ) -> ServiceResult<ExchangeRate> {
+        if request.rate <= Decimal::ZERO {
+            return Err(ServiceError::Validation("Rate must be positive".to_string()));
+        }
+
+        sqlx::query_as!(
+            ExchangeRate,
+            r#"
+            UPDATE exchange_rates
+            SET rate = $1, source = $2, updated_at = NOW()
+            WHERE id = $3 AND company_id = $4
+            RETURNING id, company_id, currency, rate_type, effective_date, rate, source, created_by,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            request.rate,
+            request.source,
+            rate_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Exchange rate not found".to_string()))
+    }
+
+    pub async fn delete_rate(&self, rate_id: Uuid, company_id: Uuid) -> ServiceResult<()> {
+        let result = sqlx::query!(
+            "DELETE FROM exchange_rates WHERE id = $1 AND company_id = $2",
+            rate_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Exchange rate not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Loads rates from a CSV file such as the weekly KMK tax-rate table.
+    /// Nothing is saved unless every row is valid.
+    pub async fn import_rates(
+        &self,
+        company_id: Uuid,
+        rate_type: RateType,
+        data: &[u8],
+        source: Option<String>,
+        user_id: Uuid,
+    ) -> ServiceResult<ExchangeRateImportResult> {
+        let (rows, mut errors) = parse_rate_csv(data).map_err(ServiceError::Validation)?;
+        let rows_read = rows.len() + errors.len();
+
+        let functional_currency = database::exchange_rates::functional_currency(&self.db, company_id).await?;
+        for row in &rows {
+            if let Err(message) = check_currency(&row.currency, &functional_currency) {
+                errors.push(ExchangeRateImportError { row_number: row.row_number, message });
+            }
+        }
+        errors.sort_by_key(|e| e.row_number);
+
+        if !errors.is_empty() || rows.is_empty() {
+            return Ok(ExchangeRateImportResult {
+                rate_type,
+                rows_read,
+                committed: false,
+                rates: Vec::new(),
+                errors,
+            });
+        }
+
+        let source = source.unwrap_or_else(|| match rate_type {
+            RateType::Tax => "KMK".to_string(),
+            _ => "CSV import".to_string(),
+        });
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+        let mut rates = Vec::new();
+
+        for row in rows {
+            let rate = database::exchange_rates::upsert_rate(
+                &mut *tx,
+                company_id,
+                &row.currency,
+                rate_type,
+                row.effective_date,
+                row.rate,
+                Some(&source),
+                user_id,
+            ).await?;
+            rates.push(rate);
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Imported {} {} exchange rates for company {}", rates.len(), rate_type, company_id);
+
+        Ok(ExchangeRateImportResult {
+            rate_type,
+            rows_read,
+            committed: true,
+            rates,
+            errors,
+        })
+    }
+
+    async fn validate_currency(&self, company_id: Uuid, currency: &str) -> ServiceResult<()> {
+        let functional_currency = database::exchange_rates::functional_currency(&self.db, company_id).await?;
+        check_currency(currency, &functional_currency).map_err(ServiceError::Validation)
+    }
+}
+
+fn check_currency(currency: &str, functional_currency: &str) -> Result<(), String> {
+    if !CurrencyUtils::is_valid_currency(currency) {
+        return Err(format!("Unsupported currency {}", currency));
+    }
+    if currency.eq_ignore_ascii_case(functional_currency) {
+        return Err(format!("{} is the functional currency", currency.to_uppercase()));
+    }
+    Ok(())
+}
+
+/// Parses a number written either as `15,234.56` or, as in KMK tables and
+/// Indonesian bank exports, `15.234,56`. When only one kind of separator
+/// appears, a single comma is the decimal separator and repeated commas or
+/// dots are thousands separators.
+pub(crate) fn parse_decimal(value: &str) -> Option<Decimal> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let normalized = match (value.rfind(','), value.rfind('.')) {
+        (Some(comma), Some(dot)) if comma > dot => value.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => value.replace(',', ""),
+        (Some(_), None) if value.matches(',').count() > 1 => value.replace(',', ""),
+        (Some(_), None) => value.replace(',', "."),
+        (None, Some(_)) if value.matches('.').count() > 1 => value.replace('.', ""),
+        _ => value,
+    };
+
+    Decimal::from_str(&normalized).ok()
+}
+
+/// Reads `currency`, `rate` and `effective_date` columns (Indonesian KMK
+/// headers `mata_uang`, `kurs`/`nilai` and `berlaku_mulai` are accepted too).
+/// An optional `unit` column divides the rate, since KMK quotes JPY per 100.
+fn parse_rate_csv(data: &[u8]) -> Result<(Vec<ImportedRate>, Vec<ExchangeRateImportError>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV: {}", e))?
+        .iter()
+        .map(|h| h.to_lowercase().replace([' ', '-'], "_"))
+        .collect();
+
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let required = |names: &[&str]| column(names).ok_or_else(|| format!("Missing required column {}", names[0]));
+
+    let currency_column = required(&["currency", "mata_uang", "kode"])?;
+    let rate_column = required(&["rate", "kurs", "nilai"])?;
+    let date_column = required(&["effective_date", "valid_from", "berlaku_mulai", "date", "tanggal"])?;
+    let unit_column = column(&["unit", "satuan"]);
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let row_number = index + 2;
+        let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+        if record.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+
+        let cell = |i: usize| record.get(i).unwrap_or_default();
+        let mut row_error = |message: String| errors.push(ExchangeRateImportError { row_number, message });
+
+        let currency = cell(currency_column).to_uppercase();
+        let effective_date = super::import_service::parse_date(cell(date_column));
+        let rate = parse_decimal(cell(rate_column)).filter(|r| *r > Decimal::ZERO);
+        let unit = match unit_column.map(cell).filter(|u| !u.is_empty()) {
+            Some(unit) => parse_decimal(unit).filter(|u| *u > Decimal::ZERO),
+            None => Some(Decimal::ONE),
+        };
+
+        if currency.is_empty() {
+            row_error("Currency is required".to_string());
+        }
+        if effective_date.is_none() {
+            row_error(format!("Invalid effective date '{}'", cell(date_column)));
+        }
+        if rate.is_none() {
+            row_error(format!("Invalid rate '{}'", cell(rate_column)));
+        }
+        if unit.is_none() {
+            row_error("Invalid unit".to_string());
+        }
+
+        if let (false, Some(effective_date), Some(rate), Some(unit)) = (currency.is_empty(), effective_date, rate, unit) {
+            rows.push(ImportedRate {
+                row_number,
+                currency,
+                effective_date,
+                rate: (rate / unit).round_dp(6),
+            });
+        }
+    }
+
+    Ok((rows, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_kmk_rate_table() {
+        assert_eq!(parse_decimal("15.234,56"), Decimal::from_str("15234.56").ok());
+        assert_eq!(parse_decimal("15,234.56"), Decimal::from_str("15234.56").ok());
+        assert_eq!(parse_decimal("1.015.234"), Decimal::from_str("1015234").ok());
+        assert_eq!(parse_decimal("10,5"), Decimal::from_str("10.5").ok());
+        assert_eq!(parse_decimal("1,500,000"), Decimal::from_str("1500000").ok());
+
+        let csv = "Mata Uang,Satuan,Kurs,Berlaku Mulai\n\
+                   USD,1,\"15.234,56\",2024-03-06\n\
+                   JPY,100,\"10.250,00\",06/03/2024\n\
+                   EUR,1,abc,2024-03-06\n";
+
+        let (rows, errors) = parse_rate_csv(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].rate, Decimal::from_str("15234.56").unwrap());
+        assert_eq!(rows[1].currency, "JPY");
+        assert_eq!(rows[1].rate, Decimal::from_str("102.5").unwrap());
+        assert_eq!(rows[1].effective_date, NaiveDate::from_ymd_opt(2024, 3, 6).unwrap());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row_number, 4);
+    }
+}
@@ -0,0 +1,489 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService, SettingsService};
+use common::{ServiceResult, ServiceError};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use utils::CurrencyUtils;
+use uuid::Uuid;
+
+/// Source document type of revaluation entries.
+pub(crate) const FX_REVALUATION_SOURCE: &str = "FX_REVALUATION";
+
+pub struct FxService {
+    db: PgPool,
+    journal_service: JournalService,
+    settings_service: SettingsService,
+    audit_logger: database::audit::AuditLogger,
+}
+
+impl FxService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            settings_service: SettingsService::new(db.clone()),
+            audit_logger: database::audit::AuditLogger::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Restates open foreign-currency balances of asset and liability
+    /// accounts at the given rates and posts the difference to unrealized
+    /// FX gain/loss. The entry reverses automatically the next day, so every
+    /// revaluation is measured against the original booked amounts.
+    pub async fn revalue(
+        &self,
+        company_id: Uuid,
+        request: FxRevaluationRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<FxRevaluationResult> {
+        let revaluation_date = request.revaluation_date;
+        let reverses_on = revaluation_date
+            .succ_opt()
+            .ok_or_else(|| ServiceError::Validation("Invalid revaluation date".to_string()))?;
+
+        let mut rates = HashMap::new();
+        for (currency, rate) in request.rates {
+            if rate <= Decimal::ZERO {
+                return Err(ServiceError::Validation(format!("Rate for {} must be positive", currency)));
+            }
+            rates.insert(currency.trim().to_uppercase(), rate);
+        }
+
+        let (functional_currency, _) = company_currency(&self.db, company_id).await?;
+        let (gain_account_id, loss_account_id) = self.settings_service
+            .fx_revaluation_accounts(company_id)
+            .await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // One revaluation per company at a time
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext('fx_revaluation:' || $1::text))",
+            company_id.to_string()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let period_end = sqlx::query_scalar!(
+            r#"
+            SELECT end_date
+            FROM fiscal_periods
+            WHERE company_id = $1 AND $2 BETWEEN start_date AND end_date
+            "#,
+            company_id,
+            revaluation_date
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if let Some(period_end) = period_end {
+            if period_end != revaluation_date {
+                return Err(ServiceError::Validation(format!(
+                    "Revaluation date must be the last day of its fiscal period ({})",
+                    period_end
+                )));
+            }
+        }
+
+        database::periods::ensure_period_open(&mut *tx, company_id, revaluation_date, roles).await?;
+
+        // An earlier revaluation still in effect would be counted twice
+        let outstanding = sqlx::query_scalar!(
+            r#"
+            SELECT entry_number
+            FROM journal_entries
+            WHERE company_id = $1
+              AND source_document_type = $2
+              AND status = 'POSTED'
+              AND reversed_by_entry_id IS NULL
+              AND entry_date <= $3
+            ORDER BY entry_date
+            LIMIT 1
+            "#,
+            company_id,
+            FX_REVALUATION_SOURCE,
+            revaluation_date
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if let Some(entry_number) = outstanding {
+            return Err(ServiceError::Conflict(format!(
+                "Revaluation {} has not been reversed yet",
+                entry_number
+            )));
+        }
+
+        // Booked balances per account and currency, leaving out earlier
+        // revaluations and their reversals
+        let balances = sqlx::query!(
+            r#"
+            SELECT jel.account_id, a.account_code, a.account_name, jel.currency as "currency!",
+                   COALESCE(SUM(CASE WHEN jel.debit_amount > 0 THEN jel.foreign_amount ELSE -jel.foreign_amount END), 0) as "foreign_balance!",
+                   SUM(jel.debit_amount - jel.credit_amount) as "book_balance!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            JOIN accounts a ON a.id = jel.account_id
+            WHERE je.company_id = $1
+              AND je.is_posted = true
+              AND je.status = 'POSTED'
+              AND je.entry_date <= $2
+              AND jel.currency IS NOT NULL
+              AND jel.currency <> $3
+              AND a.account_type IN ('ASSET', 'LIABILITY')
+              AND a.account_subtype IS DISTINCT FROM 'FIXED_ASSET'
+              AND je.source_document_type IS DISTINCT FROM $4
+              AND NOT EXISTS (
+                  SELECT 1 FROM journal_entries original
+                  WHERE je.source_document_type = 'JOURNAL_REVERSAL'
+                    AND original.id = je.source_document_id
+                    AND original.source_document_type = $4
+              )
+            GROUP BY jel.account_id, a.account_code, a.account_name, jel.currency
+            ORDER BY a.account_code, jel.currency
+            "#,
+            company_id,
+            revaluation_date,
+            functional_currency,
+            FX_REVALUATION_SOURCE
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut missing: Vec<String> = balances
+            .iter()
+            .filter(|b| !rates.contains_key(&b.currency))
+            .map(|b| b.currency.clone())
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        // Currencies without a rate in the request use the rate table
+        let rate_type = request.rate_type.unwrap_or(RateType::BiMiddle);
+        let stored = database::exchange_rates::rates_as_of(
+            &mut *tx, company_id, &missing, rate_type, revaluation_date
+        ).await?;
+        missing.retain(|currency| !stored.contains_key(currency));
+        rates.extend(stored);
+
+        if !missing.is_empty() {
+            return Err(ServiceError::Validation(format!(
+                "No {} revaluation rate on or before {} for: {}",
+                rate_type,
+                revaluation_date,
+                missing.join(", ")
+            )));
+        }
+
+        let mut lines = Vec::new();
+        let mut entry_lines = Vec::new();
+        let mut total_gain = Decimal::ZERO;
+        let mut total_loss = Decimal::ZERO;
+
+        for balance in balances {
+            let rate = rates[&balance.currency];
+            let revalued_balance = CurrencyUtils::round_to_currency(balance.foreign_balance * rate, &functional_currency);
+            let adjustment = revalued_balance - balance.book_balance;
+
+            if !adjustment.is_zero() {
+                if adjustment > Decimal::ZERO {
+                    total_gain += adjustment;
+                } else {
+                    total_loss -= adjustment;
+                }
+
+                // The adjustment only restates the functional amount, so
+                // the line carries no foreign amount of its own
+                entry_lines.push(CreateJournalEntryLineRequest {
+                    account_id: balance.account_id,
+                    description: Some(format!("Revaluation of {} balance at {}", balance.currency, rate)),
+                    debit_amount: if adjustment > Decimal::ZERO { adjustment } else { Decimal::ZERO },
+                    credit_amount: if adjustment < Decimal::ZERO { -adjustment } else { Decimal::ZERO },
+                    department: None,
+                    project_code: None,
+                    cost_center: None,
+                    currency: None,
+                    foreign_amount: None,
+                    exchange_rate: None,
+                });
+            }
+
+            lines.push(FxRevaluationLine {
+                account_id: balance.account_id,
+                account_code: balance.account_code,
+                account_name: balance.account_name,
+                currency: balance.currency,
+                foreign_balance: balance.foreign_balance,
+                book_balance: balance.book_balance,
+                rate,
+                revalued_balance,
+                adjustment,
+            });
+        }
+
+        if entry_lines.is_empty() {
+            tracing::info!("FX revaluation for company {} as of {}: nothing to adjust", company_id, revaluation_date);
+
+            return Ok(FxRevaluationResult {
+                revaluation_date,
+                reverses_on,
+                lines,
+                total_gain,
+                total_loss,
+                revaluation: None,
+                journal_entry: None,
+            });
+        }
+
+        for (account_id, amount, is_gain) in [(gain_account_id, total_gain, true), (loss_account_id, total_loss, false)] {
+            if amount.is_zero() {
+                continue;
+            }
+            entry_lines.push(CreateJournalEntryLineRequest {
+                account_id,
+                description: Some(if is_gain { "Unrealized FX gain" } else { "Unrealized FX loss" }.to_string()),
+                debit_amount: if is_gain { Decimal::ZERO } else { amount },
+                credit_amount: if is_gain { amount } else { Decimal::ZERO },
+                department: None,
+                project_code: None,
+                cost_center: None,
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            });
+        }
+
+        let revaluation_id = Uuid::new_v4();
+        let entry_request = CreateJournalEntryRequest {
+            company_id,
+            entry_date: revaluation_date,
+            description: Some(format!("Unrealized FX revaluation as of {}", revaluation_date)),
+            reference: Some(format!("FXREV-{}", revaluation_date.format("%Y%m%d"))),
+            auto_reverse_on: Some(reverses_on),
+            auto_reverse: false,
+            lines: entry_lines,
+        };
+
+        let entry = self.journal_service
+            .insert_entry(&mut tx, entry_request, EntryOrigin::system(FX_REVALUATION_SOURCE, revaluation_id), user_id)
+            .await?;
+
+        let revaluation = sqlx::query_as!(
+            FxRevaluation,
+            r#"
+            INSERT INTO fx_revaluations
+            (id, company_id, revaluation_date, journal_entry_id, rates, total_gain, total_loss, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            RETURNING id, company_id, revaluation_date, journal_entry_id, rates, total_gain, total_loss,
+                      created_by, created_at as "created_at!"
+            "#,
+            revaluation_id,
+            company_id,
+            revaluation_date,
+            entry.journal_entry.id,
+            serde_json::to_value(&rates).unwrap_or_default(),
+            total_gain,
+            total_loss,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Log audit trail
+        self.audit_logger.log_activity(
+            &mut tx,
+            "fx_revaluations",
+            revaluation_id,
+            "FX_REVALUATION",
+            None,
+            Some(serde_json::json!({
+                "revaluation_date": revaluation_date,
+                "journal_entry_id": entry.journal_entry.id,
+                "total_gain": total_gain,
+                "total_loss": total_loss,
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "FX revaluation {} for company {} as of {}: gain {}, loss {}",
+            entry.journal_entry.entry_number, company_id, revaluation_date, total_gain, total_loss
+        );
+
+        Ok(FxRevaluationResult {
+            revaluation_date,
+            reverses_on,
+            lines,
+            total_gain,
+            total_loss,
+            revaluation: Some(revaluation),
+            journal_entry: Some(entry),
+        })
+    }
+
+    pub async fn get_revaluations(&self, company_id: Uuid) -> ServiceResult<Vec<FxRevaluation>> {
+        sqlx::query_as!(
+            FxRevaluation,
+            r#"
+            SELECT id, company_id, revaluation_date, journal_entry_id, rates, total_gain, total_loss,
+                   created_by, created_at as "created_at!"
+            FROM fx_revaluations
+            WHERE company_id = $1
+            ORDER BY revaluation_date DESC, created_at DESC
+            "#,
+            company_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+}
+
+/// The company's functional currency and whether foreign-currency lines
+/// are enabled, from `company_settings`.
+pub async fn company_currency(db: &PgPool, company_id: Uuid) -> ServiceResult<(String, bool)> {
+    let settings = sqlx::query!(
+        "SELECT default_currency, enable_multi_currency FROM company_settings WHERE company_id = $1",
+        company_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(match settings {
+        Some(s) => (
+            s.default_currency.unwrap_or_else(|| "IDR".to_string()),
+            s.enable_multi_currency.unwrap_or(false),
+        ),
+        None => ("IDR".to_string(), false),
+    })
+}
+
+/// Checks the currency fields of every line that carries them. Foreign
+/// lines without an exchange rate take the BI middle rate in effect on the
+/// entry date from the rate table.
+pub async fn validate_line_currencies(
+    db: &PgPool,
+    company_id: Uuid,
+    entry_date: chrono::NaiveDate,
+    lines: &mut [CreateJournalEntryLineRequest],
+) -> ServiceResult<()> {
+    let has_foreign_lines = lines
+        .iter()
+        .any(|l| l.currency.is_some() || l.foreign_amount.is_some() || l.exchange_rate.is_some());
+    if !has_foreign_lines {
+        return Ok(());
+    }
+
+    let (functional_currency, multi_currency) = company_currency(db, company_id).await?;
+    if !multi_currency {
+        return Err(ServiceError::Validation(
+            "Multi-currency is not enabled for this company".to_string()
+        ));
+    }
+
+    for (index, line) in lines.iter_mut().enumerate() {
+        if let (Some(currency), None) = (line.currency.as_deref(), line.exchange_rate) {
+            if !currency.eq_ignore_ascii_case(&functional_currency) {
+                let rate = database::exchange_rates::get_rate(
+                    db, company_id, currency, RateType::BiMiddle, entry_date
+                ).await?;
+                line.exchange_rate = Some(rate);
+            }
+        }
+        check_line_currency(index + 1, line, &functional_currency).map_err(ServiceError::Validation)?;
+    }
+
+    Ok(())
+}
+
+/// A foreign-currency line needs a supported currency other than the
+/// functional one, a positive amount and rate, and a single debit or credit
+/// equal to the converted amount.
+fn check_line_currency(
+    line_number: usize,
+    line: &CreateJournalEntryLineRequest,
+    functional_currency: &str,
+) -> Result<(), String> {
+    let Some(currency) = line.currency.as_deref() else {
+        if line.foreign_amount.is_some() || line.exchange_rate.is_some() {
+            return Err(format!("Line {}: currency is required with a foreign amount or rate", line_number));
+        }
+        return Ok(());
+    };
+
+    if !CurrencyUtils::is_valid_currency(currency) {
+        return Err(format!("Line {}: unsupported currency {}", line_number, currency));
+    }
+    if currency.eq_ignore_ascii_case(functional_currency) {
+        return Err(format!(
+            "Line {}: {} is the functional currency; leave the currency fields empty",
+            line_number, currency
+        ));
+    }
+
+    let (Some(foreign_amount), Some(exchange_rate)) = (line.foreign_amount, line.exchange_rate) else {
+        return Err(format!("Line {}: foreign amount and exchange rate are required", line_number));
+    };
+    if foreign_amount <= Decimal::ZERO || exchange_rate <= Decimal::ZERO {
+        return Err(format!("Line {}: foreign amount and exchange rate must be positive", line_number));
+    }
+
+    let amount = match (line.debit_amount.is_zero(), line.credit_amount.is_zero()) {
+        (false, true) => line.debit_amount,
+        (true, false) => line.credit_amount,
+        _ => return Err(format!("Line {}: exactly one of debit or credit must be set", line_number)),
+    };
+
+    let expected = CurrencyUtils::convert_currency(foreign_amount, currency, functional_currency, Some(exchange_rate))
+        .map_err(|e| format!("Line {}: {}", line_number, e))?;
+    if amount != expected {
+        return Err(format!(
+            "Line {}: amount {} does not match {} {} at {} ({})",
+            line_number, amount, foreign_amount, currency, exchange_rate, expected
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(debit: i64, credit: i64, currency: Option<&str>, foreign: Option<i64>, rate: Option<i64>) -> CreateJournalEntryLineRequest {
+        CreateJournalEntryLineRequest {
+            account_id: Uuid::nil(),
+            description: None,
+            debit_amount: Decimal::from(debit),
+            credit_amount: Decimal::from(credit),
+            department: None,
+            project_code: None,
+            cost_center: None,
+            currency: currency.map(str::to_string),
+            foreign_amount: foreign.map(Decimal::from),
+            exchange_rate: rate.map(Decimal::from),
+        }
+    }
+
+    #[test]
+    fn foreign_line_amount_must_match_conversion() {
+        assert!(check_line_currency(1, &line(1_500_000, 0, Some("USD"), Some(100), Some(15_000)), "IDR").is_ok());
+        assert!(check_line_currency(1, &line(0, 1_500_000, Some("USD"), Some(100), Some(15_000)), "IDR").is_ok());
+        assert!(check_line_currency(1, &line(500, 0, None, None, None), "IDR").is_ok());
+
+        assert!(check_line_currency(1, &line(1_400_000, 0, Some("USD"), Some(100), Some(15_000)), "IDR").is_err());
+        assert!(check_line_currency(1, &line(1_500_000, 0, Some("USD"), None, Some(15_000)), "IDR").is_err());
+        assert!(check_line_currency(1, &line(1_500_000, 0, Some("IDR"), Some(100), Some(15_000)), "IDR").is_err());
+        assert!(check_line_currency(1, &line(500, 0, None, Some(100), None), "IDR").is_err());
+    }
+}
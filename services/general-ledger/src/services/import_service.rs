@@ -0,0 +1,481 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService};
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Source document type stamped on imported entries; also used to detect
+/// references that were already imported.
+const IMPORT_SOURCE: &str = "JOURNAL_IMPORT";
+
+/// One journal line as read from the file, before any validation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportRow {
+    pub row_number: usize,
+    pub reference: String,
+    pub entry_date: String,
+    pub description: String,
+    pub account_code: String,
+    pub line_description: String,
+    pub debit: String,
+    pub credit: String,
+    pub department: String,
+    pub project_code: String,
+    pub cost_center: String,
+}
+
+pub struct ImportService {
+    db: PgPool,
+    journal_service: JournalService,
+}
+
+impl ImportService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Imports journal lines grouped into entries by their reference.
+    ///
+    /// Every group is checked the same way a manual entry is. In dry-run mode,
+    /// or when any row fails, nothing is written and the report lists every
+    /// problem; otherwise all entries are created in a single transaction.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_entries(
+        &self,
+        company_id: Uuid,
+        format: ImportFormat,
+        data: &[u8],
+        dry_run: bool,
+        post: bool,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<JournalImportResult> {
+        // Posting straight away skips the approval flow
+        if post && !database::periods::has_override_role(roles) {
+            return Err(ServiceError::Authorization(format!(
+                "Importing posted entries requires one of the roles: {}",
+                database::periods::PERIOD_OVERRIDE_ROLES.join(", ")
+            )));
+        }
+
+        let rows = match format {
+            ImportFormat::Csv => parse_csv(data),
+            ImportFormat::Xlsx => parse_xlsx(data),
+        }
+        .map_err(ServiceError::Validation)?;
+
+        if rows.is_empty() {
+            return Err(ServiceError::Validation("The file contains no journal lines".to_string()));
+        }
+
+        let accounts = self.resolve_accounts(company_id, &rows).await?;
+        let already_imported = self.imported_references(company_id, &rows).await?;
+
+        let mut errors = Vec::new();
+        let mut entries = Vec::new();
+        let mut requests = Vec::new();
+
+        for (reference, group) in group_by_reference(&rows) {
+            let Some(reference) = reference else {
+                for row in group {
+                    errors.push(JournalImportError {
+                        reference: None,
+                        row_number: Some(row.row_number),
+                        message: "Reference is required".to_string(),
+                    });
+                }
+                continue;
+            };
+
+            let error_count = errors.len();
+            let mut entry_date: Option<NaiveDate> = None;
+            let mut lines = Vec::new();
+
+            for row in &group {
+                let mut row_error = |message: String| errors.push(JournalImportError {
+                    reference: Some(reference.clone()),
+                    row_number: Some(row.row_number),
+                    message,
+                });
+
+                match parse_date(&row.entry_date) {
+                    Some(date) if entry_date.is_none() || entry_date == Some(date) => entry_date = Some(date),
+                    Some(date) => row_error(format!(
+                        "Entry date {} differs from {} on the first line of the entry",
+                        date, entry_date.unwrap_or(date)
+                    )),
+                    None => row_error(format!("Invalid entry date '{}'", row.entry_date)),
+                }
+
+                let account_id = accounts.get(&row.account_code).copied();
+                if account_id.is_none() {
+                    row_error(if row.account_code.is_empty() {
+                        "Account code is required".to_string()
+                    } else {
+                        format!("Unknown or inactive account code {}", row.account_code)
+                    });
+                }
+
+                let debit_amount = parse_amount(&row.debit);
+                let credit_amount = parse_amount(&row.credit);
+                if debit_amount.is_none() {
+                    row_error(format!("Invalid debit amount '{}'", row.debit));
+                }
+                if credit_amount.is_none() {
+                    row_error(format!("Invalid credit amount '{}'", row.credit));
+                }
+
+                if let (Some(account_id), Some(debit_amount), Some(credit_amount)) = (account_id, debit_amount, credit_amount) {
+                    lines.push(CreateJournalEntryLineRequest {
+                        account_id,
+                        description: non_empty(&row.line_description),
+                        debit_amount,
+                        credit_amount,
+                        department: non_empty(&row.department),
+                        project_code: non_empty(&row.project_code),
+                        cost_center: non_empty(&row.cost_center),
+                        currency: None,
+                        foreign_amount: None,
+                        exchange_rate: None,
+                    });
+                }
+            }
+
+            // Entry-level checks only make sense once every row parsed
+            let rows_valid = errors.len() == error_count;
+            let mut entry_error = |message: String| errors.push(JournalImportError {
+                reference: Some(reference.clone()),
+                row_number: None,
+                message,
+            });
+
+            if already_imported.contains(&reference) {
+                entry_error(format!("Reference {} has already been imported", reference));
+            }
+
+            let request = CreateJournalEntryRequest {
+                company_id,
+                entry_date: entry_date.unwrap_or_default(),
+                description: group.iter().find_map(|row| non_empty(&row.description)),
+                reference: Some(reference.clone()),
+                auto_reverse_on: None,
+                auto_reverse: false,
+                lines,
+            };
+
+            if rows_valid {
+                if let Err(e) = request.validate() {
+                    entry_error(format!("{:?}", e));
+                }
+                if let Err(e) = super::validation::validate_journal_entry(&request.lines) {
+                    entry_error(rejection(e)?);
+                }
+                if let Err(e) = super::dimension_service::validate_line_dimensions(&self.db, company_id, &request.lines).await {
+                    entry_error(rejection(e)?);
+                }
+                if let Err(e) = database::periods::ensure_period_open(&self.db, company_id, request.entry_date, roles).await {
+                    entry_error(rejection(e)?);
+                }
+            }
+
+            entries.push(JournalImportEntry {
+                reference: reference.clone(),
+                entry_date,
+                row_numbers: group.iter().map(|row| row.row_number).collect(),
+                total_debit: request.lines.iter().map(|l| l.debit_amount).sum(),
+                total_credit: request.lines.iter().map(|l| l.credit_amount).sum(),
+                journal_entry_id: None,
+                entry_number: None,
+            });
+            requests.push(request);
+        }
+
+        let batch_id = Uuid::new_v4();
+
+        if dry_run || !errors.is_empty() {
+            tracing::info!("Journal import for company {} checked {} rows: {} entries, {} errors (dry run: {})",
+                company_id, rows.len(), entries.len(), errors.len(), dry_run);
+
+            return Ok(JournalImportResult {
+                batch_id,
+                dry_run,
+                committed: false,
+                rows_read: rows.len(),
+                entries,
+                errors,
+            });
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        for (entry, request) in entries.iter_mut().zip(requests) {
+            let created = self.journal_service
+                .insert_entry(&mut tx, request, EntryOrigin::imported(batch_id, post), user_id)
+                .await?;
+            entry.journal_entry_id = Some(created.journal_entry.id);
+            entry.entry_number = Some(created.journal_entry.entry_number);
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Imported {} journal entries from {} rows for company {} (batch {}) by user {}",
+            entries.len(), rows.len(), company_id, batch_id, user_id);
+
+        Ok(JournalImportResult {
+            batch_id,
+            dry_run,
+            committed: true,
+            rows_read: rows.len(),
+            entries,
+            errors,
+        })
+    }
+
+    async fn resolve_accounts(
+        &self,
+        company_id: Uuid,
+        rows: &[ImportRow],
+    ) -> ServiceResult<HashMap<String, Uuid>> {
+        let codes: Vec<String> = rows
+            .iter()
+            .map(|row| row.account_code.clone())
+            .filter(|code| !code.is_empty())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let accounts = sqlx::query!(
+            r#"
+            SELECT id, account_code
+            FROM accounts
+            WHERE company_id = $1 AND is_active = true AND account_code = ANY($2)
+            "#,
+            company_id,
+            &codes
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(accounts.into_iter().map(|a| (a.account_code, a.id)).collect())
+    }
+
+    async fn imported_references(
+        &self,
+        company_id: Uuid,
+        rows: &[ImportRow],
+    ) -> ServiceResult<HashSet<String>> {
+        let references: Vec<String> = rows
+            .iter()
+            .map(|row| row.reference.clone())
+            .filter(|reference| !reference.is_empty())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT reference as "reference!"
+            FROM journal_entries
+            WHERE company_id = $1
+              AND source_document_type = $2
+              AND status <> 'CANCELLED'
+              AND reference = ANY($3)
+            "#,
+            company_id,
+            IMPORT_SOURCE,
+            &references
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(existing.into_iter().collect())
+    }
+}
+
+/// Turns a rejected business rule into a report message; anything else
+/// (e.g. a database failure) aborts the import.
+fn rejection(error: ServiceError) -> ServiceResult<String> {
+    match error {
+        ServiceError::Validation(message) | ServiceError::Authorization(message) => Ok(message),
+        other => Err(other),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() { None } else { Some(value.to_string()) }
+}
+
+/// Groups rows by reference in order of first appearance. Rows without a
+/// reference end up in a single `None` group.
+fn group_by_reference(rows: &[ImportRow]) -> Vec<(Option<String>, Vec<&ImportRow>)> {
+    let mut groups: Vec<(Option<String>, Vec<&ImportRow>)> = Vec::new();
+    let mut positions: HashMap<Option<String>, usize> = HashMap::new();
+
+    for row in rows {
+        let key = non_empty(&row.reference);
+        let position = *positions.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, Vec::new()));
+            groups.len() - 1
+        });
+        groups[position].1.push(row);
+    }
+
+    groups
+}
+
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d/%m/%Y"))
+        .ok()
+}
+
+/// Blank cells count as zero.
+fn parse_amount(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Some(Decimal::ZERO);
+    }
+    Decimal::from_str(value).ok()
+}
+
+/// Maps the header row onto `ImportRow` fields and converts the data rows.
+fn rows_from_records(records: Vec<Vec<String>>) -> Result<Vec<ImportRow>, String> {
+    let mut records = records.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| "The file is empty".to_string())?
+        .iter()
+        .map(|h| h.trim().to_lowercase().replace([' ', '-'], "_"))
+        .collect();
+
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let required = |names: &[&str]| column(names).ok_or_else(|| format!("Missing required column {}", names[0]));
+
+    let reference = required(&["reference", "ref"])?;
+    let entry_date = required(&["entry_date", "date"])?;
+    let account_code = required(&["account_code", "account"])?;
+    let debit = required(&["debit", "debit_amount"])?;
+    let credit = required(&["credit", "credit_amount"])?;
+    let description = column(&["description"]);
+    let line_description = column(&["line_description", "memo"]);
+    let department = column(&["department"]);
+    let project_code = column(&["project_code", "project"]);
+    let cost_center = column(&["cost_center"]);
+
+    Ok(records
+        .enumerate()
+        .filter(|(_, record)| record.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|(index, record)| {
+            let cell = |i: Option<usize>| {
+                i.and_then(|i| record.get(i)).map(|v| v.trim().to_string()).unwrap_or_default()
+            };
+            ImportRow {
+                row_number: index + 2,
+                reference: cell(Some(reference)),
+                entry_date: cell(Some(entry_date)),
+                description: cell(description),
+                account_code: cell(Some(account_code)),
+                line_description: cell(line_description),
+                debit: cell(Some(debit)),
+                credit: cell(Some(credit)),
+                department: cell(department),
+                project_code: cell(project_code),
+                cost_center: cell(cost_center),
+            }
+        })
+        .collect())
+}
+
+fn parse_csv(data: &[u8]) -> Result<Vec<ImportRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data);
+
+    let records = reader
+        .records()
+        .map(|record| {
+            record
+                .map(|r| r.iter().map(str::to_string).collect())
+                .map_err(|e| format!("Invalid CSV: {}", e))
+        })
+        .collect::<Result<Vec<Vec<String>>, String>>()?;
+
+    rows_from_records(records)
+}
+
+/// Reads the first worksheet. Numeric cells keep their shortest decimal
+/// form and date cells are converted to `YYYY-MM-DD`.
+fn parse_xlsx(data: &[u8]) -> Result<Vec<ImportRow>, String> {
+    use calamine::{DataType, Reader, Xlsx};
+
+    let mut workbook = Xlsx::new(std::io::Cursor::new(data))
+        .map_err(|e| format!("Invalid XLSX file: {}", e))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| "The workbook has no worksheets".to_string())?
+        .map_err(|e| format!("Invalid XLSX file: {}", e))?;
+
+    // Excel serial dates count days from 1899-12-30
+    let excel_epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default();
+
+    let records = range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    DataType::String(s) => s.clone(),
+                    DataType::Float(f) => f.to_string(),
+                    DataType::Int(i) => i.to_string(),
+                    DataType::Bool(b) => b.to_string(),
+                    DataType::DateTime(serial) => excel_epoch
+                        .checked_add_signed(chrono::Duration::days(serial.trunc() as i64))
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect();
+
+    rows_from_records(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_csv_rows_and_groups_by_reference() {
+        let csv = "Reference,Date,Account Code,Debit,Credit,Project\n\
+                   OB-1,2023-12-31,1110,1500000,,PRJ-A\n\
+                   OB-1,2023-12-31,3100,,1500000,\n\
+                   ,,,,,\n\
+                   OB-2,31/12/2023,5100,250.50,0,\n";
+
+        let rows = parse_csv(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].row_number, 2);
+        assert_eq!(rows[0].project_code, "PRJ-A");
+        assert_eq!(rows[2].row_number, 5);
+        assert_eq!(parse_date(&rows[2].entry_date), NaiveDate::from_ymd_opt(2023, 12, 31));
+        assert_eq!(parse_amount(&rows[1].debit), Some(Decimal::ZERO));
+
+        let groups = group_by_reference(&rows);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].1.len(), 2);
+
+        assert!(parse_csv(b"Reference,Date,Debit,Credit\n").is_err());
+    }
+}
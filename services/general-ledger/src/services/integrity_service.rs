@@ -0,0 +1,475 @@
+use crate::models::*;
+use super::{fx_service::FX_REVALUATION_SOURCE, SettingsService, SubledgerClient};
+use common::{ServiceResult, ServiceError, identity::SYSTEM_USER_ID};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Findings reported per check at most; a drifted ledger tends to produce
+/// the same finding thousands of times and the first few are enough to act on.
+const MAX_FINDINGS_PER_CHECK: i64 = 500;
+
+/// Read-only verification of the ledger's invariants. Nothing is repaired
+/// here: balance drift is fixed with a balance rebuild, the rest needs a
+/// correcting entry or a data fix.
+pub struct IntegrityService {
+    db: PgPool,
+    settings_service: SettingsService,
+    subledger_client: SubledgerClient,
+}
+
+impl IntegrityService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            settings_service: SettingsService::new(db.clone()),
+            subledger_client: SubledgerClient::from_env(),
+            db,
+        }
+    }
+
+    pub async fn verify(&self, company_id: Uuid, user_id: Uuid) -> ServiceResult<IntegrityReport> {
+        let mut findings = Vec::new();
+
+        findings.extend(self.check_unbalanced_entries(company_id).await?);
+        findings.extend(self.check_entry_totals(company_id).await?);
+        findings.extend(self.check_line_accounts(company_id).await?);
+        findings.extend(self.check_account_balances(company_id).await?);
+        findings.extend(self.check_subledgers(company_id, user_id).await?);
+
+        let checks = summarize(&findings);
+        let is_clean = checks.iter().all(|c| c.passed);
+
+        if !is_clean {
+            tracing::warn!("Ledger integrity check found {} issues for company {}", findings.len(), company_id);
+        }
+
+        Ok(IntegrityReport {
+            company_id,
+            checks,
+            findings,
+            is_clean,
+            checked_at: chrono::Utc::now(),
+        })
+    }
+
+    /// Verifies every company with journal entries, for the scheduler, which
+    /// reads the subledgers as the system user.
+    pub async fn verify_all(&self) -> ServiceResult<Vec<IntegrityReport>> {
+        let company_ids = sqlx::query_scalar!("SELECT DISTINCT company_id FROM journal_entries")
+            .fetch_all(&self.db)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        let mut reports = Vec::with_capacity(company_ids.len());
+        for company_id in company_ids {
+            reports.push(self.verify(company_id, SYSTEM_USER_ID).await?);
+        }
+
+        Ok(reports)
+    }
+
+    async fn check_unbalanced_entries(&self, company_id: Uuid) -> ServiceResult<Vec<IntegrityFinding>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT je.id, je.entry_number,
+                   COALESCE(SUM(jel.debit_amount), 0) as "debits!",
+                   COALESCE(SUM(jel.credit_amount), 0) as "credits!"
+            FROM journal_entries je
+            LEFT JOIN journal_entry_lines jel ON jel.journal_entry_id = je.id
+            WHERE je.company_id = $1 AND je.status = 'POSTED'
+            GROUP BY je.id, je.entry_number
+            HAVING COALESCE(SUM(jel.debit_amount), 0) <> COALESCE(SUM(jel.credit_amount), 0)
+            ORDER BY je.entry_number
+            LIMIT $2
+            "#,
+            company_id,
+            MAX_FINDINGS_PER_CHECK
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| IntegrityFinding {
+                check: IntegrityCheck::UnbalancedEntry,
+                severity: FindingSeverity::Error,
+                entity_type: "JOURNAL_ENTRY".to_string(),
+                entity_id: Some(row.id),
+                reference: Some(row.entry_number.clone()),
+                message: format!(
+                    "Posted entry {} has debits {} and credits {}",
+                    row.entry_number, row.debits, row.credits
+                ),
+                expected: Some(row.debits),
+                actual: Some(row.credits),
+            })
+            .collect())
+    }
+
+    async fn check_entry_totals(&self, company_id: Uuid) -> ServiceResult<Vec<IntegrityFinding>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT je.id, je.entry_number, je.total_debit, je.total_credit,
+                   COALESCE(SUM(jel.debit_amount), 0) as "debits!",
+                   COALESCE(SUM(jel.credit_amount), 0) as "credits!"
+            FROM journal_entries je
+            LEFT JOIN journal_entry_lines jel ON jel.journal_entry_id = je.id
+            WHERE je.company_id = $1 AND je.status <> 'CANCELLED'
+            GROUP BY je.id, je.entry_number, je.total_debit, je.total_credit
+            HAVING je.total_debit <> COALESCE(SUM(jel.debit_amount), 0)
+                OR je.total_credit <> COALESCE(SUM(jel.credit_amount), 0)
+            ORDER BY je.entry_number
+            LIMIT $2
+            "#,
+            company_id,
+            MAX_FINDINGS_PER_CHECK
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut findings = Vec::new();
+        for row in rows {
+            for (side, header, lines) in [
+                ("total_debit", row.total_debit, row.debits),
+                ("total_credit", row.total_credit, row.credits),
+            ] {
+                if header != lines {
+                    findings.push(IntegrityFinding {
+                        check: IntegrityCheck::EntryTotals,
+                        severity: FindingSeverity::Error,
+                        entity_type: "JOURNAL_ENTRY".to_string(),
+                        entity_id: Some(row.id),
+                        reference: Some(row.entry_number.clone()),
+                        message: format!(
+                            "Entry {} has {} {} but its lines sum to {}",
+                            row.entry_number, side, header, lines
+                        ),
+                        expected: Some(lines),
+                        actual: Some(header),
+                    });
+                }
+            }
+        }
+
+        Ok(findings)
+    }
+
+    async fn check_line_accounts(&self, company_id: Uuid) -> ServiceResult<Vec<IntegrityFinding>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT jel.id, jel.line_number, jel.account_id, je.entry_number,
+                   a.account_code as "account_code?",
+                   a.company_id as "account_company_id?"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON je.id = jel.journal_entry_id
+            LEFT JOIN accounts a ON a.id = jel.account_id
+            WHERE je.company_id = $1 AND je.status <> 'CANCELLED'
+              AND (a.id IS NULL OR a.company_id <> je.company_id OR a.is_active IS DISTINCT FROM true)
+            ORDER BY je.entry_number, jel.line_number
+            LIMIT $2
+            "#,
+            company_id,
+            MAX_FINDINGS_PER_CHECK
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let (severity, problem) = match (row.account_code.as_deref(), row.account_company_id) {
+                    (None, _) => (FindingSeverity::Error, format!("missing account {}", row.account_id)),
+                    (Some(code), Some(owner)) if owner != company_id => {
+                        (FindingSeverity::Error, format!("account {} of company {}", code, owner))
+                    }
+                    // Deactivating an account after posting to it is allowed but
+                    // leaves a balance nobody sees in the chart
+                    (Some(code), _) => (FindingSeverity::Warning, format!("inactive account {}", code)),
+                };
+
+                IntegrityFinding {
+                    check: IntegrityCheck::LineAccount,
+                    severity,
+                    entity_type: "JOURNAL_LINE".to_string(),
+                    entity_id: Some(row.id),
+                    reference: Some(row.entry_number.clone()),
+                    message: format!("Line {} of entry {} references {}", row.line_number, row.entry_number, problem),
+                    expected: None,
+                    actual: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn check_account_balances(&self, company_id: Uuid) -> ServiceResult<Vec<IntegrityFinding>> {
+        // Daily movement: stored rows against posted lines
+        let movements = sqlx::query!(
+            r#"
+            WITH expected AS (
+                SELECT jel.account_id, je.entry_date as balance_date,
+                       SUM(jel.debit_amount) as debit_amount, SUM(jel.credit_amount) as credit_amount
+                FROM journal_entry_lines jel
+                JOIN journal_entries je ON je.id = jel.journal_entry_id
+                WHERE je.company_id = $1 AND je.is_posted = true AND je.status = 'POSTED'
+                GROUP BY jel.account_id, je.entry_date
+            ),
+            stored AS (
+                SELECT account_id, balance_date, debit_amount, credit_amount
+                FROM account_balances
+                WHERE company_id = $1
+            )
+            SELECT COALESCE(e.account_id, s.account_id) as "account_id!",
+                   COALESCE(e.balance_date, s.balance_date) as "balance_date!",
+                   a.account_code as "account_code?",
+                   COALESCE(e.debit_amount, 0) as "expected_debit!",
+                   COALESCE(e.credit_amount, 0) as "expected_credit!",
+                   COALESCE(s.debit_amount, 0) as "stored_debit!",
+                   COALESCE(s.credit_amount, 0) as "stored_credit!"
+            FROM expected e
+            FULL OUTER JOIN stored s ON s.account_id = e.account_id AND s.balance_date = e.balance_date
+            LEFT JOIN accounts a ON a.id = COALESCE(e.account_id, s.account_id)
+            WHERE COALESCE(e.debit_amount, 0) <> COALESCE(s.debit_amount, 0)
+               OR COALESCE(e.credit_amount, 0) <> COALESCE(s.credit_amount, 0)
+            ORDER BY 3, 2
+            LIMIT $2
+            "#,
+            company_id,
+            MAX_FINDINGS_PER_CHECK
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Running balance: first day per account where the stored ending
+        // balance stops matching the sum of the stored movements
+        let running = sqlx::query!(
+            r#"
+            SELECT DISTINCT ON (r.account_id)
+                   r.id, r.account_id, r.account_code, r.balance_date,
+                   r.ending_balance as "stored!", r.expected as "expected!"
+            FROM (
+                SELECT b.id, b.account_id, a.account_code, b.balance_date,
+                       COALESCE(b.ending_balance, 0) as ending_balance,
+                       SUM(CASE WHEN a.normal_balance = 'CREDIT'
+                                THEN COALESCE(b.credit_amount, 0) - COALESCE(b.debit_amount, 0)
+                                ELSE COALESCE(b.debit_amount, 0) - COALESCE(b.credit_amount, 0) END)
+                           OVER (PARTITION BY b.account_id ORDER BY b.balance_date) as expected
+                FROM account_balances b
+                JOIN accounts a ON a.id = b.account_id
+                WHERE b.company_id = $1
+            ) r
+            WHERE r.ending_balance <> r.expected
+            ORDER BY r.account_id, r.balance_date
+            LIMIT $2
+            "#,
+            company_id,
+            MAX_FINDINGS_PER_CHECK
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut findings: Vec<IntegrityFinding> = movements
+            .into_iter()
+            .map(|row| {
+                let account = row.account_code.clone().unwrap_or_else(|| row.account_id.to_string());
+                IntegrityFinding {
+                    check: IntegrityCheck::AccountBalances,
+                    severity: FindingSeverity::Error,
+                    entity_type: "ACCOUNT".to_string(),
+                    entity_id: Some(row.account_id),
+                    reference: row.account_code,
+                    message: format!(
+                        "Account {} on {}: stored debits/credits {}/{}, posted lines give {}/{}",
+                        account, row.balance_date, row.stored_debit, row.stored_credit,
+                        row.expected_debit, row.expected_credit
+                    ),
+                    expected: Some(row.expected_debit - row.expected_credit),
+                    actual: Some(row.stored_debit - row.stored_credit),
+                }
+            })
+            .collect();
+
+        findings.extend(running.into_iter().map(|row| IntegrityFinding {
+            check: IntegrityCheck::AccountBalances,
+            severity: FindingSeverity::Error,
+            entity_type: "ACCOUNT_BALANCE".to_string(),
+            entity_id: Some(row.id),
+            reference: Some(row.account_code.clone()),
+            message: format!(
+                "Account {} ending balance on {} is {} but its movements add up to {}",
+                row.account_code, row.balance_date, row.stored, row.expected
+            ),
+            expected: Some(row.expected),
+            actual: Some(row.stored),
+        }));
+
+        Ok(findings)
+    }
+
+    /// Open AP/AR documents as the payables and receivables services report
+    /// them, against the posted balance of the control accounts. The services
+    /// value documents at their booked rates, so revaluation adjustments still
+    /// in effect on a control account are added to the expected balance.
+    async fn check_subledgers(&self, company_id: Uuid, user_id: Uuid) -> ServiceResult<Vec<IntegrityFinding>> {
+        let (ap_account, ar_account) = self.settings_service.control_accounts(company_id).await?;
+
+        let payables = self.subledger_client.payables_balance(company_id, user_id).await;
+        let receivables = self.subledger_client.receivables_balance(company_id, user_id).await;
+
+        let mut findings = Vec::new();
+        for (check, account_id, balance, subledger) in [
+            (IntegrityCheck::PayablesControl, ap_account, payables, "payables"),
+            (IntegrityCheck::ReceivablesControl, ar_account, receivables, "receivables"),
+        ] {
+            let Some(account_id) = account_id else {
+                findings.push(IntegrityFinding {
+                    check,
+                    severity: FindingSeverity::Warning,
+                    entity_type: "ACCOUNT".to_string(),
+                    entity_id: None,
+                    reference: None,
+                    message: format!("No {} control account configured; check skipped", subledger),
+                    expected: None,
+                    actual: None,
+                });
+                continue;
+            };
+
+            let booked = match balance {
+                Ok(balance) => balance.outstanding,
+                Err(e) => {
+                    tracing::warn!("Could not read {} balance for company {}: {}", subledger, company_id, e);
+                    findings.push(IntegrityFinding {
+                        check,
+                        severity: FindingSeverity::Warning,
+                        entity_type: "ACCOUNT".to_string(),
+                        entity_id: Some(account_id),
+                        reference: None,
+                        message: format!("Could not read the open {} balance; check skipped", subledger),
+                        expected: None,
+                        actual: None,
+                    });
+                    continue;
+                }
+            };
+
+            // Revaluation entries and their reversals both count; once an
+            // adjustment is reversed the pair nets to zero
+            let control = sqlx::query!(
+                r#"
+                SELECT a.account_code, a.normal_balance,
+                       COALESCE(SUM(jel.debit_amount - jel.credit_amount)
+                           FILTER (WHERE je.status = 'POSTED' AND je.is_posted = true), 0) as "balance!",
+                       COALESCE(SUM(jel.debit_amount - jel.credit_amount)
+                           FILTER (WHERE je.status = 'POSTED' AND je.is_posted = true
+                                     AND (je.source_document_type = $3
+                                          OR (je.source_document_type = 'JOURNAL_REVERSAL' AND EXISTS (
+                                              SELECT 1 FROM journal_entries original
+                                              WHERE original.id = je.source_document_id
+                                                AND original.source_document_type = $3)))), 0) as "revaluation!"
+                FROM accounts a
+                LEFT JOIN journal_entry_lines jel ON jel.account_id = a.id
+                LEFT JOIN journal_entries je ON je.id = jel.journal_entry_id AND je.company_id = $1
+                WHERE a.id = $2
+                GROUP BY a.account_code, a.normal_balance
+                "#,
+                company_id,
+                account_id,
+                FX_REVALUATION_SOURCE
+            )
+            .fetch_one(&self.db)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            let (balance, revaluation) = if control.normal_balance.as_deref() == Some("CREDIT") {
+                (-control.balance, -control.revaluation)
+            } else {
+                (control.balance, control.revaluation)
+            };
+            let expected = (booked + revaluation).round_dp(2);
+
+            if balance != expected {
+                findings.push(IntegrityFinding {
+                    check,
+                    severity: FindingSeverity::Error,
+                    entity_type: "ACCOUNT".to_string(),
+                    entity_id: Some(account_id),
+                    reference: Some(control.account_code.clone()),
+                    message: format!(
+                        "Open {} total {} plus revaluation {} but control account {} has balance {}",
+                        subledger, booked, revaluation, control.account_code, balance
+                    ),
+                    expected: Some(expected),
+                    actual: Some(balance),
+                });
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+/// One result per check, in a fixed order; a check passes while it has no
+/// errors, warnings alone don't fail it.
+fn summarize(findings: &[IntegrityFinding]) -> Vec<IntegrityCheckResult> {
+    IntegrityCheck::ALL
+        .iter()
+        .map(|&check| {
+            let count = |severity| findings
+                .iter()
+                .filter(|f| f.check == check && f.severity == severity)
+                .count() as u32;
+            let error_count = count(FindingSeverity::Error);
+
+            IntegrityCheckResult {
+                check,
+                passed: error_count == 0,
+                error_count,
+                warning_count: count(FindingSeverity::Warning),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(check: IntegrityCheck, severity: FindingSeverity) -> IntegrityFinding {
+        IntegrityFinding {
+            check,
+            severity,
+            entity_type: "JOURNAL_ENTRY".to_string(),
+            entity_id: None,
+            reference: None,
+            message: String::new(),
+            expected: None,
+            actual: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let findings = vec![
+            finding(IntegrityCheck::EntryTotals, FindingSeverity::Error),
+            finding(IntegrityCheck::EntryTotals, FindingSeverity::Error),
+            finding(IntegrityCheck::LineAccount, FindingSeverity::Warning),
+        ];
+
+        let checks = summarize(&findings);
+        assert_eq!(checks.len(), IntegrityCheck::ALL.len());
+
+        let totals = checks.iter().find(|c| c.check == IntegrityCheck::EntryTotals).unwrap();
+        assert!(!totals.passed);
+        assert_eq!(totals.error_count, 2);
+
+        let accounts = checks.iter().find(|c| c.check == IntegrityCheck::LineAccount).unwrap();
+        assert!(accounts.passed);
+        assert_eq!(accounts.warning_count, 1);
+
+        assert!(checks.iter().filter(|c| c.check != IntegrityCheck::EntryTotals).all(|c| c.passed));
+    }
+}
@@ -1,48 +1,234 @@
 use crate::models::*;
+use super::{bank_reconciliation_service, ApprovalService, BalanceService, SettingsService};
 use common::{ServiceResult, ServiceError};
-use sqlx::PgPool;
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+/// Source document type of both entries of an intercompany transaction.
+const INTERCOMPANY_SOURCE: &str = "INTERCOMPANY";
+
+/// Describes where a journal entry comes from and the status it is created in.
+pub struct EntryOrigin {
+    pub status: JournalEntryStatus,
+    pub source_document_type: Option<String>,
+    pub source_document_id: Option<Uuid>,
+    pub recurring_template_id: Option<Uuid>,
+}
+
+impl EntryOrigin {
+    /// A manually entered draft awaiting the normal approval flow.
+    pub fn manual() -> Self {
+        Self {
+            status: JournalEntryStatus::Draft,
+            source_document_type: None,
+            source_document_id: None,
+            recurring_template_id: None,
+        }
+    }
+
+    /// A posted reversal of another journal entry.
+    pub fn reversal_of(entry_id: Uuid) -> Self {
+        Self::system("JOURNAL_REVERSAL", entry_id)
+    }
+
+    /// A posted entry generated by the ledger itself for a source document,
+    /// such as a year-end closing.
+    pub fn system(source_document_type: &str, source_document_id: Uuid) -> Self {
+        Self {
+            status: JournalEntryStatus::Posted,
+            source_document_type: Some(source_document_type.to_string()),
+            source_document_id: Some(source_document_id),
+            recurring_template_id: None,
+        }
+    }
+
+    /// An entry loaded by a bulk import batch, either left as a draft or
+    /// posted straight away.
+    pub fn imported(batch_id: Uuid, post: bool) -> Self {
+        Self {
+            status: if post { JournalEntryStatus::Posted } else { JournalEntryStatus::Draft },
+            source_document_type: Some("JOURNAL_IMPORT".to_string()),
+            source_document_id: Some(batch_id),
+            recurring_template_id: None,
+        }
+    }
+
+    /// One side of an intercompany transaction. Both sides start as drafts
+    /// and go through each company's own approval flow.
+    pub fn intercompany(transaction_id: Uuid) -> Self {
+        Self {
+            status: JournalEntryStatus::Draft,
+            source_document_type: Some(INTERCOMPANY_SOURCE.to_string()),
+            source_document_id: Some(transaction_id),
+            recurring_template_id: None,
+        }
+    }
+
+    /// One period of an amortization schedule, either left as a draft or
+    /// posted straight away.
+    pub fn amortization(schedule_id: Uuid, auto_post: bool) -> Self {
+        Self {
+            status: if auto_post { JournalEntryStatus::Posted } else { JournalEntryStatus::Draft },
+            source_document_type: Some("AMORTIZATION".to_string()),
+            source_document_id: Some(schedule_id),
+            recurring_template_id: None,
+        }
+    }
+
+    /// An entry generated from a recurring template, either left as a draft
+    /// or posted straight away.
+    pub fn recurring(template_id: Uuid, auto_post: bool) -> Self {
+        Self {
+            status: if auto_post { JournalEntryStatus::Posted } else { JournalEntryStatus::Draft },
+            source_document_type: None,
+            source_document_id: None,
+            recurring_template_id: Some(template_id),
+        }
+    }
+}
+
+/// Who a posting is made for, which decides how period locks apply.
+enum PostingAuthority<'a> {
+    /// A user, who needs an override role for soft-closed periods
+    User(&'a [String]),
+    /// A scheduled job completing an entry a user already posted
+    System,
+}
+
 pub struct JournalService {
     db: PgPool,
+    balance_service: BalanceService,
+    approval_service: ApprovalService,
+    settings_service: SettingsService,
 }
 
 impl JournalService {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self {
+            balance_service: BalanceService::new(db.clone()),
+            approval_service: ApprovalService::new(db.clone()),
+            settings_service: SettingsService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn get_entry(&self, entry_id: Uuid, company_id: Uuid) -> ServiceResult<JournalEntry> {
+        sqlx::query_as!(
+            JournalEntry,
+            r#"
+            SELECT id, company_id, entry_number, entry_date, description, reference,
+                   total_debit, total_credit, 
+                   status as "status: JournalEntryStatus", is_posted, 
+                   is_recurring as "is_recurring!", recurring_template_id,
+                   source_document_type, source_document_id, auto_reverse_on, reversed_by_entry_id,
+                   created_by, approved_by, posted_by, created_at, approved_at, posted_at
+            FROM journal_entries
+            WHERE id = $1 AND company_id = $2
+            "#,
+            entry_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Journal entry not found".to_string()))
     }
 
     pub async fn create_entry(
         &self,
-        request: CreateJournalEntryRequest,
+        mut request: CreateJournalEntryRequest,
         user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<JournalEntryWithLines> {
         // Validate business rules
         super::validation::validate_journal_entry(&request.lines)?;
+        super::fx_service::validate_line_currencies(&self.db, request.company_id, request.entry_date, &mut request.lines).await?;
         
         let account_ids: Vec<Uuid> = request.lines.iter().map(|l| l.account_id).collect();
         super::validation::validate_accounts_exist(&self.db, &account_ids, request.company_id).await?;
+        super::dimension_service::validate_line_dimensions(&self.db, request.company_id, &request.lines).await?;
+
+        if request.auto_reverse && request.auto_reverse_on.is_none() {
+            request.auto_reverse_on = Some(
+                database::periods::next_period_start(&self.db, request.company_id, request.entry_date).await?
+            );
+        }
+
+        if let Some(auto_reverse_on) = request.auto_reverse_on {
+            if auto_reverse_on <= request.entry_date {
+                return Err(ServiceError::Validation(
+                    "Auto-reversal date must be after the entry date".to_string()
+                ));
+            }
+        }
+
+        let budget_lines: Vec<database::budgets::BudgetCheckLine> = request.lines
+            .iter()
+            .map(|l| database::budgets::BudgetCheckLine {
+                account_id: l.account_id,
+                department: l.department.clone(),
+                project_code: l.project_code.clone(),
+                amount: l.debit_amount - l.credit_amount,
+            })
+            .collect();
+        let budget_warnings = database::budgets::budget_warnings(
+            &self.db,
+            request.company_id,
+            request.entry_date,
+            &budget_lines,
+        ).await?;
 
         let mut tx = self.db.begin().await
             .map_err(|e| ServiceError::Database(e))?;
-        
+
+        database::periods::ensure_period_open(&mut *tx, request.company_id, request.entry_date, roles).await?;
+
+        let entry = self.insert_entry(&mut tx, request, EntryOrigin::manual(), user_id).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        Ok(JournalEntryWithLines { budget_warnings, ..entry })
+    }
+
+    /// Inserts a journal entry and its lines inside an existing transaction.
+    /// Callers are responsible for validating the lines first. Entries created
+    /// directly as `Posted` are reflected in account balances immediately.
+    pub async fn insert_entry(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        request: CreateJournalEntryRequest,
+        origin: EntryOrigin,
+        user_id: Uuid,
+    ) -> ServiceResult<JournalEntryWithLines> {
         let total_debits: rust_decimal::Decimal = request.lines.iter().map(|l| l.debit_amount).sum();
         let total_credits: rust_decimal::Decimal = request.lines.iter().map(|l| l.credit_amount).sum();
         
         let entry_id = Uuid::new_v4();
+        let is_posted = matches!(origin.status, JournalEntryStatus::Posted);
+        let posted_by = if is_posted { Some(user_id) } else { None };
         
-        let entry_number = self.generate_entry_number(request.company_id, request.entry_date).await?;
+        let entry_number = database::numbering::next_number(
+            &mut **tx,
+            request.company_id,
+            database::numbering::DocumentType::JournalEntry,
+            request.entry_date,
+        ).await?;
 
         let journal_entry = sqlx::query_as!(
             JournalEntry,
             r#"
             INSERT INTO journal_entries 
             (id, company_id, entry_number, entry_date, description, reference, total_debit, total_credit, 
-             status, is_posted, created_by, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, false, $10, NOW())
+             status, is_posted, is_recurring, recurring_template_id, source_document_type, source_document_id,
+             auto_reverse_on, created_by, posted_by, created_at, posted_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, NOW(),
+                    CASE WHEN $10 THEN NOW() END)
             RETURNING id, company_id, entry_number, entry_date, description, reference, 
                       total_debit, total_credit, 
                       status as "status: JournalEntryStatus", is_posted, 
+                      is_recurring as "is_recurring!", recurring_template_id,
+                      source_document_type, source_document_id, auto_reverse_on, reversed_by_entry_id,
                       created_by, approved_by, posted_by, created_at, approved_at, posted_at
             "#,
             entry_id,
@@ -53,10 +239,17 @@ impl JournalService {
             request.reference,
             total_debits,
             total_credits,
-            JournalEntryStatus::Draft as JournalEntryStatus,
-            user_id
+            origin.status as JournalEntryStatus,
+            is_posted,
+            origin.recurring_template_id.is_some(),
+            origin.recurring_template_id,
+            origin.source_document_type,
+            origin.source_document_id,
+            request.auto_reverse_on,
+            user_id,
+            posted_by
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut **tx)
         .await
         .map_err(ServiceError::Database)?;
 
@@ -68,7 +261,7 @@ impl JournalService {
                 "SELECT account_code, account_name FROM accounts WHERE id = $1",
                 line_request.account_id
             )
-            .fetch_optional(&mut *tx)
+            .fetch_optional(&mut **tx)
             .await
             .map_err(ServiceError::Database)?;
             
@@ -76,9 +269,13 @@ impl JournalService {
                 JournalEntryLine,
                 r#"
                 INSERT INTO journal_entry_lines 
-                (id, journal_entry_id, account_id, description, debit_amount, credit_amount, line_number)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
-                RETURNING id, journal_entry_id, account_id, description, debit_amount, credit_amount, line_number
+                (id, journal_entry_id, account_id, description, debit_amount, credit_amount, line_number,
+                 department, project_code, cost_center, currency, foreign_amount, exchange_rate)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                RETURNING id, journal_entry_id, account_id, 
+                          NULL::varchar as account_code, NULL::varchar as account_name,
+                          description, debit_amount, credit_amount, line_number,
+                          department, project_code, cost_center, currency, foreign_amount, exchange_rate
                 "#,
                 line_id,
                 entry_id,
@@ -86,9 +283,15 @@ impl JournalService {
                 line_request.description,
                 line_request.debit_amount,
                 line_request.credit_amount,
-                (index + 1) as i32
+                (index + 1) as i32,
+                line_request.department,
+                line_request.project_code,
+                line_request.cost_center,
+                line_request.currency,
+                line_request.foreign_amount,
+                line_request.exchange_rate
             )
-            .fetch_one(&mut *tx)
+            .fetch_one(&mut **tx)
             .await
             .map_err(ServiceError::Database)?;
             
@@ -101,31 +304,253 @@ impl JournalService {
             lines.push(line_with_account);
         }
 
-        tx.commit().await.map_err(ServiceError::Database)?;
+        if is_posted {
+            bank_reconciliation_service::ensure_not_reconciled(tx, journal_entry.company_id, entry_id).await?;
+            self.balance_service.refresh_for_entry(tx, journal_entry.company_id, entry_id).await?;
+        }
 
         Ok(JournalEntryWithLines {
             journal_entry,
             lines,
+            budget_warnings: Vec::new(),
         })
     }
 
-    async fn generate_entry_number(
+    /// Creates and posts a mirror of a posted entry with debits and credits
+    /// swapped. The reversal points back at the original through
+    /// `source_document_type`/`source_document_id`.
+    pub async fn reverse_entry(
+        &self,
+        entry_id: Uuid,
+        company_id: Uuid,
+        request: ReverseJournalEntryRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<JournalEntryWithLines> {
+        self.post_reversal(entry_id, company_id, request, user_id, PostingAuthority::User(roles)).await
+    }
+
+    /// Reverses an entry together with the other side of its intercompany
+    /// transaction, if any, so the two companies never drift apart.
+    async fn post_reversal(
+        &self,
+        entry_id: Uuid,
+        company_id: Uuid,
+        request: ReverseJournalEntryRequest,
+        user_id: Uuid,
+        authority: PostingAuthority<'_>,
+    ) -> ServiceResult<JournalEntryWithLines> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let reversal = self
+            .reverse_in_tx(&mut tx, entry_id, company_id, &request, user_id, &authority)
+            .await?;
+
+        if let Some((partner_id, partner_company_id)) = self.intercompany_partner(&mut tx, entry_id).await? {
+            // Roles only count in the company they were granted for
+            let partner_authority = match authority {
+                PostingAuthority::User(_) => PostingAuthority::User(&[]),
+                PostingAuthority::System => PostingAuthority::System,
+            };
+            match self
+                .reverse_in_tx(&mut tx, partner_id, partner_company_id, &request, user_id, &partner_authority)
+                .await
+            {
+                Ok(partner_reversal) => tracing::info!(
+                    "Reversed linked intercompany entry with {}",
+                    partner_reversal.journal_entry.entry_number
+                ),
+                // The other side was already reversed on its own
+                Err(ServiceError::Conflict(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        Ok(reversal)
+    }
+
+    async fn reverse_in_tx(
         &self,
+        tx: &mut Transaction<'_, Postgres>,
+        entry_id: Uuid,
         company_id: Uuid,
-        entry_date: chrono::NaiveDate,
-    ) -> ServiceResult<String> {
-        let entry_number = sqlx::query_scalar!(
-            "SELECT generate_entry_number($1, $2)",
+        request: &ReverseJournalEntryRequest,
+        user_id: Uuid,
+        authority: &PostingAuthority<'_>,
+    ) -> ServiceResult<JournalEntryWithLines> {
+        let original = sqlx::query!(
+            r#"
+            SELECT entry_number, entry_date, reference, is_posted,
+                   status as "status: JournalEntryStatus", reversed_by_entry_id
+            FROM journal_entries
+            WHERE id = $1 AND company_id = $2
+            FOR UPDATE
+            "#,
+            entry_id,
+            company_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Journal entry not found".to_string()))?;
+
+        if !original.is_posted || original.status != JournalEntryStatus::Posted {
+            return Err(ServiceError::Validation(
+                "Only posted journal entries can be reversed".to_string()
+            ));
+        }
+
+        if original.reversed_by_entry_id.is_some() {
+            return Err(ServiceError::Conflict(
+                format!("Journal entry {} has already been reversed", original.entry_number)
+            ));
+        }
+
+        let reversal_date = request.reversal_date
+            .unwrap_or_else(|| chrono::Utc::now().date_naive());
+
+        if reversal_date < original.entry_date {
+            return Err(ServiceError::Validation(
+                "Reversal date cannot be before the original entry date".to_string()
+            ));
+        }
+
+        match *authority {
+            PostingAuthority::User(roles) => {
+                database::periods::ensure_period_open(&mut **tx, company_id, reversal_date, roles).await?
+            }
+            PostingAuthority::System => {
+                database::periods::ensure_period_not_closed(&mut **tx, company_id, reversal_date).await?
+            }
+        }
+
+        let original_lines = sqlx::query!(
+            r#"
+            SELECT account_id, description, debit_amount, credit_amount,
+                   department, project_code, cost_center, currency, foreign_amount, exchange_rate
+            FROM journal_entry_lines
+            WHERE journal_entry_id = $1
+            ORDER BY line_number
+            "#,
+            entry_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Swap debits and credits
+        let lines = original_lines
+            .into_iter()
+            .map(|line| CreateJournalEntryLineRequest {
+                account_id: line.account_id,
+                description: line.description,
+                debit_amount: line.credit_amount,
+                credit_amount: line.debit_amount,
+                department: line.department,
+                project_code: line.project_code,
+                cost_center: line.cost_center,
+                currency: line.currency,
+                foreign_amount: line.foreign_amount,
+                exchange_rate: line.exchange_rate,
+            })
+            .collect();
+
+        let description = match &request.reason {
+            Some(reason) => format!("Reversal of {}: {}", original.entry_number, reason),
+            None => format!("Reversal of {}", original.entry_number),
+        };
+
+        let reversal_request = CreateJournalEntryRequest {
             company_id,
-            entry_date
+            entry_date: reversal_date,
+            description: Some(description),
+            reference: original.reference,
+            auto_reverse_on: None,
+            auto_reverse: false,
+            lines,
+        };
+
+        let reversal = self
+            .insert_entry(tx, reversal_request, EntryOrigin::reversal_of(entry_id), user_id)
+            .await?;
+
+        sqlx::query!(
+            "UPDATE journal_entries SET reversed_by_entry_id = $1, updated_at = NOW() WHERE id = $2",
+            reversal.journal_entry.id,
+            entry_id
         )
-        .fetch_one(&self.db)
+        .execute(&mut **tx)
         .await
         .map_err(ServiceError::Database)?;
-        
-        Ok(entry_number.unwrap_or_else(|| {
-            format!("JE-{}-000001", entry_date.format("%Y"))
-        }))
+
+        tracing::info!(
+            "Reversed journal entry {} with {}",
+            original.entry_number, reversal.journal_entry.entry_number
+        );
+
+        Ok(reversal)
+    }
+
+    /// Posts reversals for every entry whose `auto_reverse_on` date has
+    /// arrived. Returns the number of reversals created.
+    ///
+    /// The reversal was scheduled when the entry was posted, so it may land
+    /// in a soft-closed period. One rejected by the ledger's rules, e.g.
+    /// because its period is closed, is marked with the error and not
+    /// retried; it has to be reversed by hand.
+    pub async fn process_due_reversals(&self, as_of: chrono::NaiveDate) -> ServiceResult<u32> {
+        let due_entries = sqlx::query!(
+            r#"
+            SELECT id, company_id, entry_number, auto_reverse_on as "auto_reverse_on!", created_by, posted_by
+            FROM journal_entries
+            WHERE auto_reverse_on <= $1
+              AND reversed_by_entry_id IS NULL
+              AND auto_reverse_error IS NULL
+              AND is_posted = true
+              AND status = 'POSTED'
+            ORDER BY auto_reverse_on, entry_number
+            "#,
+            as_of
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut reversed = 0;
+        for entry in due_entries {
+            let request = ReverseJournalEntryRequest {
+                reversal_date: Some(entry.auto_reverse_on),
+                reason: Some("Automatic reversal".to_string()),
+            };
+            let user_id = entry.posted_by.unwrap_or(entry.created_by);
+
+            match self
+                .post_reversal(entry.id, entry.company_id, request, user_id, PostingAuthority::System)
+                .await
+            {
+                Ok(_) => reversed += 1,
+                // Retried on the next run
+                Err(e @ (ServiceError::Database(_) | ServiceError::Internal(_))) => {
+                    tracing::error!("Automatic reversal of journal entry {} failed: {}", entry.entry_number, e)
+                }
+                Err(e) => {
+                    tracing::error!("Automatic reversal of journal entry {} failed: {}", entry.entry_number, e);
+
+                    sqlx::query!(
+                        "UPDATE journal_entries SET auto_reverse_error = $1, updated_at = NOW() WHERE id = $2",
+                        e.to_string(),
+                        entry.id
+                    )
+                    .execute(&self.db)
+                    .await
+                    .map_err(ServiceError::Database)?;
+                }
+            }
+        }
+
+        Ok(reversed)
     }
 
     pub async fn update_status(
@@ -134,12 +559,21 @@ impl JournalService {
         company_id: Uuid,
         new_status: JournalEntryStatus,
         user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<JournalEntry> {
+        // Approval is recorded per approver and checked against approval policies
+        if matches!(new_status, JournalEntryStatus::Approved) {
+            self.approval_service
+                .approve_entry(entry_id, company_id, ApproveJournalEntryRequest { comments: None }, user_id, roles)
+                .await?;
+            return self.get_entry(entry_id, company_id).await;
+        }
+
         let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
 
         let current_entry = sqlx::query!(
             r#"
-            SELECT status as "status: JournalEntryStatus", is_posted 
+            SELECT status as "status: JournalEntryStatus", is_posted, entry_date
             FROM journal_entries 
             WHERE id = $1 AND company_id = $2
             "#,
@@ -158,27 +592,26 @@ impl JournalService {
             ));
         }
 
-        let journal_entry = match new_status {
-            JournalEntryStatus::Approved => {
-                sqlx::query_as!(
-                    JournalEntry,
-                    r#"
-                    UPDATE journal_entries 
-                    SET status = $1, approved_by = $2, approved_at = NOW()
-                    WHERE id = $3 AND company_id = $4
-                    RETURNING id, company_id, entry_number, entry_date, description, reference,
-                              total_debit, total_credit, 
-                              status as "status: JournalEntryStatus", is_posted, 
-                              created_by, approved_by, posted_by, created_at, approved_at, posted_at
-                    "#,
-                    new_status as JournalEntryStatus,
-                    user_id,
-                    entry_id,
-                    company_id
-                )
-                .fetch_one(&mut *tx)
-                .await
+        // Posting requires the entry's period to accept postings
+        if matches!(new_status, JournalEntryStatus::Posted) {
+            database::periods::ensure_period_open(&mut *tx, company_id, current_entry.entry_date, roles).await?;
+        }
+
+        // Both sides of an intercompany transaction are posted or cancelled together
+        if matches!(new_status, JournalEntryStatus::Posted | JournalEntryStatus::Cancelled) {
+            if let Some((partner_entry_id, partner_company_id)) = self.intercompany_partner(&mut tx, entry_id).await? {
+                // Roles only count in the company they were granted for
+                self.transition_linked_entry(&mut tx, partner_entry_id, partner_company_id, &new_status, user_id, &[])
+                    .await?;
             }
+        }
+
+        // An entry sent back to draft has to collect its approvals again
+        if matches!(new_status, JournalEntryStatus::Draft) {
+            self.approval_service.clear_approvals(&mut tx, entry_id).await?;
+        }
+
+        let journal_entry = match new_status {
             JournalEntryStatus::Posted => {
                 sqlx::query_as!(
                     JournalEntry,
@@ -189,6 +622,8 @@ impl JournalService {
                     RETURNING id, company_id, entry_number, entry_date, description, reference,
                               total_debit, total_credit, 
                               status as "status: JournalEntryStatus", is_posted, 
+                              is_recurring as "is_recurring!", recurring_template_id,
+                              source_document_type, source_document_id, auto_reverse_on, reversed_by_entry_id,
                               created_by, approved_by, posted_by, created_at, approved_at, posted_at
                     "#,
                     new_status as JournalEntryStatus,
@@ -209,6 +644,8 @@ impl JournalService {
                     RETURNING id, company_id, entry_number, entry_date, description, reference,
                               total_debit, total_credit, 
                               status as "status: JournalEntryStatus", is_posted, 
+                              is_recurring as "is_recurring!", recurring_template_id,
+                              source_document_type, source_document_id, auto_reverse_on, reversed_by_entry_id,
                               created_by, approved_by, posted_by, created_at, approved_at, posted_at
                     "#,
                     new_status as JournalEntryStatus,
@@ -222,11 +659,340 @@ impl JournalService {
 
         let journal_entry = journal_entry.map_err(ServiceError::Database)?;
 
+        // Keep account balances in step with posted lines
+        if matches!(new_status, JournalEntryStatus::Posted) || current_entry.is_posted {
+            bank_reconciliation_service::ensure_not_reconciled(&mut tx, company_id, entry_id).await?;
+            self.balance_service.refresh_for_entry(&mut tx, company_id, entry_id).await?;
+        }
+
         tx.commit().await.map_err(ServiceError::Database)?;
 
         Ok(journal_entry)
     }
 
+    /// Creates the two entries of an intercompany charge from one request.
+    /// Each side is balanced against its company's intercompany receivable
+    /// (when it is owed money) or payable (when it owes) account. Callers
+    /// check that the user may act in the counterparty company.
+    pub async fn create_intercompany_entry(
+        &self,
+        company_id: Uuid,
+        request: CreateIntercompanyEntryRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<IntercompanyEntryResult> {
+        let counterparty_id = request.counterparty_company_id;
+        if counterparty_id == company_id {
+            return Err(ServiceError::Validation(
+                "Counterparty must be a different company".to_string()
+            ));
+        }
+
+        let source_net: Decimal = request.lines.iter().map(|l| l.debit_amount - l.credit_amount).sum();
+        let target_net: Decimal = request.counterparty_lines.iter().map(|l| l.debit_amount - l.credit_amount).sum();
+
+        if source_net.is_zero() {
+            return Err(ServiceError::Validation(
+                "Intercompany lines must leave a balance to settle with the counterparty".to_string()
+            ));
+        }
+        if source_net + target_net != Decimal::ZERO {
+            return Err(ServiceError::Validation(format!(
+                "Counterparty lines must mirror the company's lines: net {} against {}",
+                source_net, target_net
+            )));
+        }
+
+        let (source_receivable, source_payable) = self.settings_service.intercompany_accounts(company_id).await?;
+        let (target_receivable, target_payable) = self.settings_service.intercompany_accounts(counterparty_id).await?;
+
+        // The side whose lines are net credits is owed the amount
+        let (source_account_id, target_account_id) = if source_net < Decimal::ZERO {
+            (source_receivable, target_payable)
+        } else {
+            (source_payable, target_receivable)
+        };
+
+        let source_request = self.intercompany_side(
+            company_id, &request, request.lines.clone(), source_account_id, source_net,
+        ).await?;
+        let target_request = self.intercompany_side(
+            counterparty_id, &request, request.counterparty_lines.clone(), target_account_id, target_net,
+        ).await?;
+
+        let transaction_id = Uuid::new_v4();
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Roles only count in the company they were granted for
+        database::periods::ensure_period_open(&mut *tx, company_id, request.entry_date, roles).await?;
+        database::periods::ensure_period_open(&mut *tx, counterparty_id, request.entry_date, &[]).await?;
+
+        let source_entry = self
+            .insert_entry(&mut tx, source_request, EntryOrigin::intercompany(transaction_id), user_id)
+            .await?;
+        let target_entry = self
+            .insert_entry(&mut tx, target_request, EntryOrigin::intercompany(transaction_id), user_id)
+            .await?;
+
+        let transaction = sqlx::query_as!(
+            IntercompanyTransaction,
+            r#"
+            INSERT INTO intercompany_transactions
+            (id, source_company_id, target_company_id, source_entry_id, target_entry_id,
+             source_account_id, target_account_id, amount, entry_date, description, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+            RETURNING id, source_company_id, target_company_id, source_entry_id, target_entry_id,
+                      source_account_id, target_account_id, amount, entry_date, description,
+                      created_by, created_at as "created_at!"
+            "#,
+            transaction_id,
+            company_id,
+            counterparty_id,
+            source_entry.journal_entry.id,
+            target_entry.journal_entry.id,
+            source_account_id,
+            target_account_id,
+            source_net.abs(),
+            request.entry_date,
+            request.description,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Created intercompany transaction {} between {} ({}) and {} ({})",
+            transaction_id, company_id, source_entry.journal_entry.entry_number,
+            counterparty_id, target_entry.journal_entry.entry_number
+        );
+
+        Ok(IntercompanyEntryResult {
+            transaction,
+            source_entry,
+            target_entry,
+        })
+    }
+
+    /// Validates one company's lines and appends the intercompany leg that
+    /// balances them.
+    async fn intercompany_side(
+        &self,
+        company_id: Uuid,
+        request: &CreateIntercompanyEntryRequest,
+        mut lines: Vec<CreateJournalEntryLineRequest>,
+        intercompany_account_id: Uuid,
+        net: Decimal,
+    ) -> ServiceResult<CreateJournalEntryRequest> {
+        lines.push(CreateJournalEntryLineRequest {
+            account_id: intercompany_account_id,
+            description: request.description.clone(),
+            debit_amount: if net < Decimal::ZERO { -net } else { Decimal::ZERO },
+            credit_amount: if net > Decimal::ZERO { net } else { Decimal::ZERO },
+            department: None,
+            project_code: None,
+            cost_center: None,
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        });
+
+        super::validation::validate_journal_entry(&lines)?;
+        super::fx_service::validate_line_currencies(&self.db, company_id, request.entry_date, &mut lines).await?;
+
+        let account_ids: Vec<Uuid> = lines.iter().map(|l| l.account_id).collect();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, company_id).await?;
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, &lines).await?;
+
+        Ok(CreateJournalEntryRequest {
+            company_id,
+            entry_date: request.entry_date,
+            description: request.description.clone(),
+            reference: request.reference.clone(),
+            auto_reverse_on: None,
+            auto_reverse: false,
+            lines,
+        })
+    }
+
+    /// The other entry (and its company) of the intercompany transaction
+    /// `entry_id` belongs to, if any.
+    async fn intercompany_partner(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        entry_id: Uuid,
+    ) -> ServiceResult<Option<(Uuid, Uuid)>> {
+        let partner = sqlx::query!(
+            r#"
+            SELECT CASE WHEN source_entry_id = $1 THEN target_entry_id ELSE source_entry_id END as "entry_id!",
+                   CASE WHEN source_entry_id = $1 THEN target_company_id ELSE source_company_id END as "company_id!"
+            FROM intercompany_transactions
+            WHERE source_entry_id = $1 OR target_entry_id = $1
+            "#,
+            entry_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(partner.map(|p| (p.entry_id, p.company_id)))
+    }
+
+    /// Applies a posting or cancellation to the linked side of an
+    /// intercompany transaction, under the same rules as the entry itself.
+    async fn transition_linked_entry(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        entry_id: Uuid,
+        company_id: Uuid,
+        new_status: &JournalEntryStatus,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<()> {
+        let linked = sqlx::query!(
+            r#"
+            SELECT entry_number, status as "status: JournalEntryStatus", is_posted, entry_date
+            FROM journal_entries
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            entry_id
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if !self.can_transition_status(&linked.status, new_status, linked.is_posted) {
+            return Err(ServiceError::Validation(format!(
+                "Linked intercompany entry {} is {:?} and cannot be moved to {:?}",
+                linked.entry_number, linked.status, new_status
+            )));
+        }
+
+        let posting = matches!(new_status, JournalEntryStatus::Posted);
+        if posting {
+            database::periods::ensure_period_open(&mut **tx, company_id, linked.entry_date, roles).await?;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE journal_entries
+            SET status = $1,
+                is_posted = is_posted OR $2,
+                posted_by = CASE WHEN $2 THEN $3 ELSE posted_by END,
+                posted_at = CASE WHEN $2 THEN NOW() ELSE posted_at END
+            WHERE id = $4
+            "#,
+            new_status.clone() as JournalEntryStatus,
+            posting,
+            user_id,
+            entry_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if posting {
+            bank_reconciliation_service::ensure_not_reconciled(tx, company_id, entry_id).await?;
+            self.balance_service.refresh_for_entry(tx, company_id, entry_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares each side's recorded intercompany position for consolidation
+    /// and lists transactions whose two entries have drifted apart.
+    pub async fn get_intercompany_mismatches(
+        &self,
+        company_id: Uuid,
+        as_of_date: chrono::NaiveDate,
+    ) -> ServiceResult<IntercompanyMismatchReport> {
+        // Reversals of either entry count towards the position
+        let balances = sqlx::query!(
+            r#"
+            WITH legs AS (
+                SELECT source_company_id AS company_id, target_company_id AS counterparty_id,
+                       source_entry_id AS entry_id, source_account_id AS account_id
+                FROM intercompany_transactions
+                WHERE $1 IN (source_company_id, target_company_id)
+                UNION ALL
+                SELECT target_company_id, source_company_id, target_entry_id, target_account_id
+                FROM intercompany_transactions
+                WHERE $1 IN (source_company_id, target_company_id)
+            )
+            SELECT CASE WHEN l.company_id = $1 THEN l.counterparty_id ELSE l.company_id END as "counterparty_company_id!",
+                   COALESCE(SUM(jel.debit_amount - jel.credit_amount) FILTER (WHERE l.company_id = $1), 0) as "our_balance!",
+                   COALESCE(SUM(jel.debit_amount - jel.credit_amount) FILTER (WHERE l.company_id <> $1), 0) as "counterparty_balance!"
+            FROM legs l
+            JOIN journal_entries je
+              ON (je.id = l.entry_id OR (je.source_document_type = 'JOURNAL_REVERSAL' AND je.source_document_id = l.entry_id))
+             AND je.status = 'POSTED'
+             AND je.entry_date <= $2
+            JOIN journal_entry_lines jel ON jel.journal_entry_id = je.id AND jel.account_id = l.account_id
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            company_id,
+            as_of_date
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let balances: Vec<IntercompanyBalance> = balances
+            .into_iter()
+            .map(|row| IntercompanyBalance {
+                counterparty_company_id: row.counterparty_company_id,
+                our_balance: row.our_balance,
+                counterparty_balance: row.counterparty_balance,
+                difference: row.our_balance + row.counterparty_balance,
+            })
+            .collect();
+
+        let mismatched_transactions = sqlx::query_as!(
+            IntercompanyLegMismatch,
+            r#"
+            SELECT t.id as transaction_id,
+                   CASE WHEN t.source_company_id = $1 THEN t.target_company_id ELSE t.source_company_id END as "counterparty_company_id!",
+                   t.entry_date,
+                   ours.entry_number as our_entry_number,
+                   ours.status::text as "our_status!",
+                   ours.reversed_by_entry_id IS NOT NULL as "our_reversed!",
+                   theirs.entry_number as counterparty_entry_number,
+                   theirs.status::text as "counterparty_status!",
+                   theirs.reversed_by_entry_id IS NOT NULL as "counterparty_reversed!"
+            FROM intercompany_transactions t
+            JOIN journal_entries ours
+              ON ours.id = CASE WHEN t.source_company_id = $1 THEN t.source_entry_id ELSE t.target_entry_id END
+            JOIN journal_entries theirs
+              ON theirs.id = CASE WHEN t.source_company_id = $1 THEN t.target_entry_id ELSE t.source_entry_id END
+            WHERE $1 IN (t.source_company_id, t.target_company_id)
+              AND t.entry_date <= $2
+              AND (ours.status <> theirs.status
+                   OR (ours.reversed_by_entry_id IS NULL) <> (theirs.reversed_by_entry_id IS NULL))
+            ORDER BY t.entry_date, ours.entry_number
+            "#,
+            company_id,
+            as_of_date
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let is_reconciled = mismatched_transactions.is_empty()
+            && balances.iter().all(|b| b.difference.is_zero());
+
+        Ok(IntercompanyMismatchReport {
+            company_id,
+            as_of_date,
+            balances,
+            mismatched_transactions,
+            is_reconciled,
+        })
+    }
+
     fn can_transition_status(
         &self,
         current: &JournalEntryStatus,
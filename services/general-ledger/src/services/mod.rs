@@ -1,5 +1,42 @@
+pub mod allocation_service;
+pub mod amortization_service;
+pub mod approval_service;
+pub mod bank_reconciliation_service;
+pub mod bank_statement_parser;
+pub mod budget_service;
 pub mod journal_service;
+pub mod import_service;
+pub mod integrity_service;
+pub mod balance_service;
+pub mod dimension_service;
+pub mod exchange_rate_service;
+pub mod fx_service;
+pub mod recurring_service;
+pub mod period_service;
+pub mod posting_rule_service;
+pub mod settings_service;
+pub mod subledger_client;
+pub mod subledger_service;
+pub mod year_end_service;
 pub mod validation;
 
+pub use allocation_service::AllocationService;
+pub use amortization_service::AmortizationService;
+pub use approval_service::ApprovalService;
+pub use bank_reconciliation_service::BankReconciliationService;
+pub use budget_service::BudgetService;
 pub use journal_service::JournalService;
+pub use import_service::ImportService;
+pub use integrity_service::IntegrityService;
+pub use balance_service::BalanceService;
+pub use dimension_service::DimensionService;
+pub use exchange_rate_service::ExchangeRateService;
+pub use fx_service::FxService;
+pub use recurring_service::RecurringService;
+pub use period_service::PeriodService;
+pub use posting_rule_service::PostingRuleService;
+pub use settings_service::SettingsService;
+pub use subledger_client::SubledgerClient;
+pub use subledger_service::SubledgerService;
+pub use year_end_service::YearEndService;
 pub use validation::*;
\ No newline at end of file
@@ -0,0 +1,191 @@
+use crate::models::*;
+use chrono::{Datelike, Months, NaiveDate};
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct PeriodService {
+    db: PgPool,
+    audit_logger: database::audit::AuditLogger,
+}
+
+impl PeriodService {
+    pub fn new(db: PgPool) -> Self {
+        let audit_logger = database::audit::AuditLogger::new(db.clone());
+        Self { db, audit_logger }
+    }
+
+    pub async fn get_periods(
+        &self,
+        company_id: Uuid,
+        fiscal_year: Option<i32>,
+    ) -> ServiceResult<Vec<FiscalPeriod>> {
+        sqlx::query_as!(
+            FiscalPeriod,
+            r#"
+            SELECT id, company_id, fiscal_year, period_number, period_name, start_date, end_date,
+                   status as "status: PeriodStatus", closed_by, closed_at,
+                   created_at as "created_at!", updated_at as "updated_at!"
+            FROM fiscal_periods
+            WHERE company_id = $1 AND ($2::int IS NULL OR fiscal_year = $2)
+            ORDER BY start_date
+            "#,
+            company_id,
+            fiscal_year
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    /// Creates the twelve monthly periods of a fiscal year, starting from the
+    /// month of `company_settings.fiscal_year_start`. Existing periods are kept.
+    pub async fn generate_fiscal_year(
+        &self,
+        company_id: Uuid,
+        fiscal_year: i32,
+        user_id: Uuid,
+    ) -> ServiceResult<Vec<FiscalPeriod>> {
+        let (year_start, _) = self.fiscal_year_bounds(company_id, fiscal_year).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        for period_number in 1..=12u32 {
+            let start_date = year_start
+                .checked_add_months(Months::new(period_number - 1))
+                .ok_or_else(|| ServiceError::Validation("Invalid fiscal year".to_string()))?;
+            let end_date = start_date
+                .checked_add_months(Months::new(1))
+                .and_then(|d| d.pred_opt())
+                .ok_or_else(|| ServiceError::Validation("Invalid fiscal year".to_string()))?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO fiscal_periods
+                (id, company_id, fiscal_year, period_number, period_name, start_date, end_date, status)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, 'OPEN')
+                ON CONFLICT (company_id, fiscal_year, period_number) DO NOTHING
+                "#,
+                Uuid::new_v4(),
+                company_id,
+                fiscal_year,
+                period_number as i32,
+                start_date.format("%Y-%m").to_string(),
+                start_date,
+                end_date
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+        }
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Generated fiscal periods for {} (company {}) by user {}", fiscal_year, company_id, user_id);
+
+        self.get_periods(company_id, Some(fiscal_year)).await
+    }
+
+    pub async fn update_period_status(
+        &self,
+        period_id: Uuid,
+        company_id: Uuid,
+        new_status: PeriodStatus,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<FiscalPeriod> {
+        if !database::periods::has_override_role(roles) {
+            return Err(ServiceError::Authorization(format!(
+                "Changing period status requires one of the roles: {}",
+                database::periods::PERIOD_OVERRIDE_ROLES.join(", ")
+            )));
+        }
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let current = sqlx::query!(
+            r#"
+            SELECT status as "status: PeriodStatus", period_name
+            FROM fiscal_periods
+            WHERE id = $1 AND company_id = $2
+            FOR UPDATE
+            "#,
+            period_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Fiscal period not found".to_string()))?;
+
+        let period = sqlx::query_as!(
+            FiscalPeriod,
+            r#"
+            UPDATE fiscal_periods
+            SET status = $1,
+                closed_by = CASE WHEN $1 = 'OPEN'::fiscal_period_status THEN NULL ELSE $2 END,
+                closed_at = CASE WHEN $1 = 'OPEN'::fiscal_period_status THEN NULL ELSE NOW() END,
+                updated_at = NOW()
+            WHERE id = $3 AND company_id = $4
+            RETURNING id, company_id, fiscal_year, period_number, period_name, start_date, end_date,
+                      status as "status: PeriodStatus", closed_by, closed_at,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            new_status as PeriodStatus,
+            user_id,
+            period_id,
+            company_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Log audit trail
+        self.audit_logger.log_activity(
+            &mut tx,
+            "fiscal_periods",
+            period_id,
+            "STATUS_CHANGE",
+            Some(serde_json::json!({ "status": current.status.to_string() })),
+            Some(serde_json::json!({ "status": new_status.to_string() })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Period {} moved from {} to {} by user {}",
+            current.period_name, current.status, new_status, user_id);
+
+        Ok(period)
+    }
+
+    /// First and last day of a fiscal year, based on the month and day of
+    /// `company_settings.fiscal_year_start` (January 1 when not configured).
+    pub async fn fiscal_year_bounds(
+        &self,
+        company_id: Uuid,
+        fiscal_year: i32,
+    ) -> ServiceResult<(NaiveDate, NaiveDate)> {
+        let configured_start = sqlx::query_scalar!(
+            "SELECT fiscal_year_start FROM company_settings WHERE company_id = $1",
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let (month, day) = configured_start
+            .map(|d| (d.month(), d.day()))
+            .unwrap_or((1, 1));
+
+        let start = NaiveDate::from_ymd_opt(fiscal_year, month, day)
+            .or_else(|| NaiveDate::from_ymd_opt(fiscal_year, month, 1))
+            .ok_or_else(|| ServiceError::Validation("Invalid fiscal year".to_string()))?;
+        let end = start
+            .checked_add_months(Months::new(12))
+            .and_then(|d| d.pred_opt())
+            .ok_or_else(|| ServiceError::Validation("Invalid fiscal year".to_string()))?;
+
+        Ok((start, end))
+    }
+}
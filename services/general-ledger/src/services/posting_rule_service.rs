@@ -0,0 +1,368 @@
+use crate::models::*;
+use super::SettingsService;
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The one place that decides which GL account a subledger event posts to.
+/// Subledgers name the role an account plays (AP control, input VAT, ...)
+/// and the company's posting rules pick the account, falling back to the
+/// role's account in the default chart.
+pub struct PostingRuleService {
+    db: PgPool,
+    settings_service: SettingsService,
+}
+
+impl PostingRuleService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            settings_service: SettingsService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn create_rule(
+        &self,
+        request: CreatePostingRuleRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<PostingRule> {
+        check_role(request.event_type, request.account_role).map_err(ServiceError::Validation)?;
+        self.validate_account(company_id, request.account_id, request.account_role).await?;
+
+        let vendor_group = normalize_criterion(request.vendor_group);
+        let item_category = normalize_criterion(request.item_category);
+        let tax_type = normalize_criterion(request.tax_type);
+
+        let rule_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO posting_rules
+            (id, company_id, event_type, account_role, vendor_group, item_category, tax_type,
+             account_id, description, is_active, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, true, $10, NOW(), NOW())
+            ON CONFLICT (company_id, event_type, account_role, COALESCE(vendor_group, ''),
+                         COALESCE(item_category, ''), COALESCE(tax_type, '')) DO NOTHING
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.event_type.to_string(),
+            request.account_role.to_string(),
+            vendor_group,
+            item_category,
+            tax_type,
+            request.account_id,
+            request.description,
+            user_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict(format!(
+            "A {} rule for {} with the same criteria already exists",
+            request.account_role, request.event_type
+        )))?;
+
+        tracing::info!("Created posting rule {} for {} {} in company {}",
+            rule_id, request.event_type, request.account_role, company_id);
+
+        self.get_rule(rule_id, company_id).await
+    }
+
+    pub async fn get_rules(
+        &self,
+        company_id: Uuid,
+        event_type: Option<PostingEvent>,
+        is_active: Option<bool>,
+    ) -> ServiceResult<Vec<PostingRule>> {
+        sqlx::query_as!(
+            PostingRule,
+            r#"
+            SELECT pr.id, pr.company_id, pr.event_type, pr.account_role, pr.vendor_group, pr.item_category,
+                   pr.tax_type, pr.account_id, a.account_code as "account_code?", a.account_name as "account_name?",
+                   pr.description, pr.is_active as "is_active!", pr.created_by,
+                   pr.created_at as "created_at!", pr.updated_at as "updated_at!"
+            FROM posting_rules pr
+            LEFT JOIN accounts a ON a.id = pr.account_id
+            WHERE pr.company_id = $1
+              AND ($2::text IS NULL OR pr.event_type = $2)
+              AND ($3::boolean IS NULL OR pr.is_active = $3)
+            ORDER BY pr.event_type, pr.account_role, pr.vendor_group NULLS FIRST,
+                     pr.item_category NULLS FIRST, pr.tax_type NULLS FIRST
+            "#,
+            company_id,
+            event_type.map(|e| e.to_string()),
+            is_active
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_rule(&self, rule_id: Uuid, company_id: Uuid) -> ServiceResult<PostingRule> {
+        sqlx::query_as!(
+            PostingRule,
+            r#"
+            SELECT pr.id, pr.company_id, pr.event_type, pr.account_role, pr.vendor_group, pr.item_category,
+                   pr.tax_type, pr.account_id, a.account_code as "account_code?", a.account_name as "account_name?",
+                   pr.description, pr.is_active as "is_active!", pr.created_by,
+                   pr.created_at as "created_at!", pr.updated_at as "updated_at!"
+            FROM posting_rules pr
+            LEFT JOIN accounts a ON a.id = pr.account_id
+            WHERE pr.id = $1 AND pr.company_id = $2
+            "#,
+            rule_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Posting rule not found".to_string()))
+    }
+
+    /// Only the account, description and active flag can change; different
+    /// criteria make a different rule.
+    pub async fn update_rule(
+        &self,
+        rule_id: Uuid,
+        company_id: Uuid,
+        request: UpdatePostingRuleRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<PostingRule> {
+        let existing = self.get_rule(rule_id, company_id).await?;
+        let role = existing.account_role.parse::<PostingAccountRole>()
+            .map_err(ServiceError::Internal)?;
+
+        self.validate_account(company_id, request.account_id, role).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE posting_rules
+            SET account_id = $1, description = $2, is_active = $3, updated_at = NOW()
+            WHERE id = $4 AND company_id = $5
+            "#,
+            request.account_id,
+            request.description,
+            request.is_active,
+            rule_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tracing::info!("Posting rule {} updated by user {}", rule_id, user_id);
+
+        self.get_rule(rule_id, company_id).await
+    }
+
+    pub async fn delete_rule(&self, rule_id: Uuid, company_id: Uuid, user_id: Uuid) -> ServiceResult<()> {
+        let result = sqlx::query!(
+            "DELETE FROM posting_rules WHERE id = $1 AND company_id = $2",
+            rule_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Posting rule not found".to_string()));
+        }
+
+        tracing::info!("Posting rule {} deleted by user {}", rule_id, user_id);
+
+        Ok(())
+    }
+
+    /// The account a role posts to for the given criteria: the most specific
+    /// active rule, else the configured control account or the role's
+    /// account in the default chart.
+    pub async fn resolve_account(
+        &self,
+        company_id: Uuid,
+        request: &ResolvePostingAccountRequest,
+    ) -> ServiceResult<ResolvedPostingAccount> {
+        check_role(request.event_type, request.account_role).map_err(ServiceError::Validation)?;
+
+        let rules = sqlx::query_as!(
+            PostingRule,
+            r#"
+            SELECT pr.id, pr.company_id, pr.event_type, pr.account_role, pr.vendor_group, pr.item_category,
+                   pr.tax_type, pr.account_id, NULL::text as "account_code?", NULL::text as "account_name?",
+                   pr.description, pr.is_active as "is_active!", pr.created_by,
+                   pr.created_at as "created_at!", pr.updated_at as "updated_at!"
+            FROM posting_rules pr
+            WHERE pr.company_id = $1 AND pr.event_type = $2 AND pr.account_role = $3 AND pr.is_active = true
+            "#,
+            company_id,
+            request.event_type.to_string(),
+            request.account_role.to_string()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if let Some(rule) = select_rule(
+            &rules,
+            request.vendor_group.as_deref(),
+            request.item_category.as_deref(),
+            request.tax_type.as_deref(),
+        ) {
+            return Ok(ResolvedPostingAccount { account_id: rule.account_id, rule_id: Some(rule.id) });
+        }
+
+        let fallback = match request.account_role {
+            PostingAccountRole::ApControl => self.settings_service.control_accounts(company_id).await?.0,
+            role => match role.default_account_code() {
+                Some(code) => sqlx::query_scalar!(
+                    "SELECT id FROM accounts WHERE company_id = $1 AND account_code = $2",
+                    company_id,
+                    code
+                )
+                .fetch_optional(&self.db)
+                .await
+                .map_err(ServiceError::Database)?,
+                None => None,
+            },
+        };
+
+        fallback
+            .map(|account_id| ResolvedPostingAccount { account_id, rule_id: None })
+            .ok_or_else(|| ServiceError::Validation(format!(
+                "No posting rule maps {} for {} and there is no default account",
+                request.account_role, request.event_type
+            )))
+    }
+
+    /// The account must be an active account of the company whose type
+    /// fits the role.
+    async fn validate_account(
+        &self,
+        company_id: Uuid,
+        account_id: Uuid,
+        role: PostingAccountRole,
+    ) -> ServiceResult<()> {
+        let account = sqlx::query!(
+            r#"
+            SELECT account_code, account_type::text as "account_type!", is_active
+            FROM accounts
+            WHERE id = $1 AND company_id = $2
+            "#,
+            account_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Validation("Account not found".to_string()))?;
+
+        if account.is_active != Some(true) {
+            return Err(ServiceError::Validation(
+                format!("Account {} is inactive", account.account_code)
+            ));
+        }
+
+        if !role.account_types().contains(&account.account_type.as_str()) {
+            return Err(ServiceError::Validation(format!(
+                "Account {} is {} but {} needs {}",
+                account.account_code, account.account_type, role, role.account_types().join(" or ")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn check_role(event: PostingEvent, role: PostingAccountRole) -> Result<(), String> {
+    if event.roles().contains(&role) {
+        Ok(())
+    } else {
+        Err(format!("{} does not take part in {}", role, event))
+    }
+}
+
+fn normalize_criterion(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| !v.is_empty())
+}
+
+/// Picks the rule to apply from the active rules of one event and role.
+/// A rule applies when each of its criteria equals the document's; among
+/// those the one with the most criteria wins, then vendor group outranks
+/// item category, which outranks tax type.
+fn select_rule<'a>(
+    rules: &'a [PostingRule],
+    vendor_group: Option<&str>,
+    item_category: Option<&str>,
+    tax_type: Option<&str>,
+) -> Option<&'a PostingRule> {
+    let matches = |criterion: &Option<String>, value: Option<&str>| match criterion {
+        Some(c) => value.map(|v| v.trim().eq_ignore_ascii_case(c)).unwrap_or(false),
+        None => true,
+    };
+
+    rules
+        .iter()
+        .filter(|r| {
+            matches(&r.vendor_group, vendor_group)
+                && matches(&r.item_category, item_category)
+                && matches(&r.tax_type, tax_type)
+        })
+        .max_by_key(|r| {
+            let specificity = [&r.vendor_group, &r.item_category, &r.tax_type]
+                .iter()
+                .filter(|c| c.is_some())
+                .count();
+            (specificity, r.vendor_group.is_some(), r.item_category.is_some())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(vendor_group: Option<&str>, item_category: Option<&str>, tax_type: Option<&str>) -> PostingRule {
+        PostingRule {
+            id: Uuid::new_v4(),
+            company_id: Uuid::nil(),
+            event_type: "VENDOR_INVOICE_APPROVED".to_string(),
+            account_role: "EXPENSE".to_string(),
+            vendor_group: vendor_group.map(str::to_string),
+            item_category: item_category.map(str::to_string),
+            tax_type: tax_type.map(str::to_string),
+            account_id: Uuid::new_v4(),
+            account_code: None,
+            account_name: None,
+            description: None,
+            is_active: true,
+            created_by: Uuid::nil(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_select_rule_prefers_most_specific() {
+        let rules = vec![
+            rule(None, None, None),
+            rule(Some("IMPORT"), None, None),
+            rule(None, Some("RAW"), None),
+            rule(Some("IMPORT"), Some("RAW"), None),
+        ];
+
+        let pick = |vg, ic| select_rule(&rules, vg, ic, None).map(|r| r.id);
+
+        assert_eq!(pick(None, None), Some(rules[0].id));
+        assert_eq!(pick(Some("import"), None), Some(rules[1].id));
+        assert_eq!(pick(Some("LOCAL"), Some("RAW")), Some(rules[2].id));
+        assert_eq!(pick(Some("IMPORT"), Some("RAW")), Some(rules[3].id));
+        // Vendor group outranks item category at equal specificity
+        assert_eq!(pick(Some("IMPORT"), Some("SPARES")), Some(rules[1].id));
+
+        let narrow = vec![rule(None, None, Some("PPN"))];
+        assert!(select_rule(&narrow, None, None, None).is_none());
+        assert!(select_rule(&narrow, None, None, Some("PPH23")).is_none());
+    }
+}
@@ -0,0 +1,536 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService};
+use chrono::{Months, NaiveDate};
+use common::{ServiceResult, ServiceError, PaginationParams};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+pub struct RecurringService {
+    db: PgPool,
+    journal_service: JournalService,
+}
+
+impl RecurringService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn create_template(
+        &self,
+        request: CreateRecurringTemplateRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<RecurringTemplateWithLines> {
+        self.validate_template(company_id, &request.lines, request.start_date, request.end_date).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let template = sqlx::query_as!(
+            RecurringTemplate,
+            r#"
+            INSERT INTO recurring_journal_templates
+            (id, company_id, template_name, description, reference, frequency, start_date,
+             next_run_date, end_date, auto_post, is_active, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8, $9, true, $10, NOW(), NOW())
+            RETURNING id, company_id, template_name, description, reference, frequency,
+                      start_date as "start_date!", next_run_date, end_date, last_run_date,
+                      auto_post as "auto_post!", is_active as "is_active!", created_by,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            request.template_name,
+            request.description,
+            request.reference,
+            request.frequency.to_string(),
+            request.start_date,
+            request.end_date,
+            request.auto_post,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let lines = self.insert_template_lines(&mut tx, template.id, request.lines).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Created recurring template {} for company {}", template.template_name, company_id);
+
+        Ok(RecurringTemplateWithLines { template, lines })
+    }
+
+    pub async fn get_templates(
+        &self,
+        company_id: Uuid,
+        pagination: PaginationParams,
+    ) -> ServiceResult<Vec<RecurringTemplate>> {
+        sqlx::query_as!(
+            RecurringTemplate,
+            r#"
+            SELECT id, company_id, template_name, description, reference, frequency,
+                   COALESCE(start_date, next_run_date) as "start_date!", next_run_date, end_date, last_run_date,
+                   COALESCE(auto_post, false) as "auto_post!", COALESCE(is_active, true) as "is_active!",
+                   created_by, created_at as "created_at!", updated_at as "updated_at!"
+            FROM recurring_journal_templates
+            WHERE company_id = $1
+            ORDER BY template_name
+            LIMIT $2 OFFSET $3
+            "#,
+            company_id,
+            pagination.limit(),
+            pagination.offset()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub async fn get_template(
+        &self,
+        template_id: Uuid,
+        company_id: Uuid,
+    ) -> ServiceResult<RecurringTemplateWithLines> {
+        let template = sqlx::query_as!(
+            RecurringTemplate,
+            r#"
+            SELECT id, company_id, template_name, description, reference, frequency,
+                   COALESCE(start_date, next_run_date) as "start_date!", next_run_date, end_date, last_run_date,
+                   COALESCE(auto_post, false) as "auto_post!", COALESCE(is_active, true) as "is_active!",
+                   created_by, created_at as "created_at!", updated_at as "updated_at!"
+            FROM recurring_journal_templates
+            WHERE id = $1 AND company_id = $2
+            "#,
+            template_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Recurring template not found".to_string()))?;
+
+        let lines = self.get_template_lines(&self.db, template_id).await?;
+
+        Ok(RecurringTemplateWithLines { template, lines })
+    }
+
+    pub async fn update_template(
+        &self,
+        template_id: Uuid,
+        company_id: Uuid,
+        request: UpdateRecurringTemplateRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<RecurringTemplateWithLines> {
+        self.validate_template(company_id, &request.lines, request.next_run_date, request.end_date).await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        let template = sqlx::query_as!(
+            RecurringTemplate,
+            r#"
+            UPDATE recurring_journal_templates
+            SET template_name = $1, description = $2, reference = $3, frequency = $4,
+                start_date = CASE WHEN next_run_date <> $5 THEN $5 ELSE COALESCE(start_date, $5) END,
+                next_run_date = $5, end_date = $6, auto_post = $7, is_active = $8, updated_at = NOW()
+            WHERE id = $9 AND company_id = $10
+            RETURNING id, company_id, template_name, description, reference, frequency,
+                      COALESCE(start_date, next_run_date) as "start_date!", next_run_date, end_date, last_run_date,
+                      auto_post as "auto_post!", is_active as "is_active!", created_by,
+                      created_at as "created_at!", updated_at as "updated_at!"
+            "#,
+            request.template_name,
+            request.description,
+            request.reference,
+            request.frequency.to_string(),
+            request.next_run_date,
+            request.end_date,
+            request.auto_post,
+            request.is_active,
+            template_id,
+            company_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("Recurring template not found".to_string()))?;
+
+        // Replace the line definitions wholesale
+        sqlx::query!("DELETE FROM recurring_journal_template_lines WHERE template_id = $1", template_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+        let lines = self.insert_template_lines(&mut tx, template_id, request.lines).await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Recurring template {} updated by user {}", template_id, user_id);
+
+        Ok(RecurringTemplateWithLines { template, lines })
+    }
+
+    pub async fn delete_template(
+        &self,
+        template_id: Uuid,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<()> {
+        // Entries already generated keep their recurring_template_id for traceability
+        let result = sqlx::query!(
+            "DELETE FROM recurring_journal_templates WHERE id = $1 AND company_id = $2",
+            template_id,
+            company_id
+        )
+        .execute(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFound("Recurring template not found".to_string()));
+        }
+
+        tracing::info!("Recurring template {} deleted by user {}", template_id, user_id);
+
+        Ok(())
+    }
+
+    /// Generates entries for every active template that is due. Each template
+    /// is processed in its own transaction and catches up all runs missed
+    /// since its `next_run_date`, so a runner that was down for a while
+    /// simply produces the overdue entries on its next pass. Runs dated in a
+    /// soft-closed or closed period are recorded as skipped and not retried.
+    pub async fn run_due_templates(&self, as_of: NaiveDate) -> ServiceResult<RecurringRunSummary> {
+        let due_templates = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM recurring_journal_templates
+            WHERE is_active = true AND next_run_date <= $1
+            ORDER BY next_run_date
+            "#,
+            as_of
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut summary = RecurringRunSummary::default();
+
+        for template_id in due_templates {
+            match self.run_template(template_id, as_of).await {
+                Ok((created, skipped)) => {
+                    summary.templates_processed += 1;
+                    summary.entries_created += created;
+                    summary.runs_skipped += skipped;
+                }
+                Err(e) => {
+                    summary.templates_failed += 1;
+                    tracing::error!("Recurring template {} failed: {}", template_id, e);
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Generates the due runs of one template. Returns the number of entries
+    /// created and of runs skipped.
+    async fn run_template(&self, template_id: Uuid, as_of: NaiveDate) -> ServiceResult<(u32, u32)> {
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Skip templates another runner is already working on
+        let template = match sqlx::query!(
+            r#"
+            SELECT company_id, template_name, description, reference, frequency,
+                   COALESCE(start_date, next_run_date) as "start_date!", next_run_date, end_date,
+                   COALESCE(auto_post, false) as "auto_post!", created_by
+            FROM recurring_journal_templates
+            WHERE id = $1 AND is_active = true AND next_run_date <= $2
+            FOR UPDATE SKIP LOCKED
+            "#,
+            template_id,
+            as_of
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?
+        {
+            Some(template) => template,
+            None => return Ok((0, 0)),
+        };
+
+        let frequency: RecurringFrequency = template.frequency.parse()
+            .map_err(ServiceError::Validation)?;
+
+        let template_lines = self.get_template_lines(&mut *tx, template_id).await?;
+        let lines: Vec<CreateJournalEntryLineRequest> = template_lines
+            .into_iter()
+            .map(|line| CreateJournalEntryLineRequest {
+                account_id: line.account_id,
+                description: line.description,
+                debit_amount: line.debit_amount,
+                credit_amount: line.credit_amount,
+                department: line.department,
+                project_code: line.project_code,
+                cost_center: line.cost_center,
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            })
+            .collect();
+
+        // Lines were valid when saved, but accounts may have been deactivated since
+        super::validation::validate_journal_entry(&lines)?;
+        let account_ids: Vec<Uuid> = lines.iter().map(|l| l.account_id).collect();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, template.company_id).await?;
+
+        let mut run_date = template.next_run_date;
+        let mut last_run_date = None;
+        let mut created = 0;
+        let mut skipped = 0;
+
+        while run_date <= as_of && template.end_date.map_or(true, |end| run_date <= end) {
+            // The unique (recurring_template_id, entry_date) index also guards this
+            let already_generated = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM journal_entries
+                    WHERE recurring_template_id = $1 AND entry_date = $2
+                ) as "exists!"
+                "#,
+                template_id,
+                run_date
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            if !already_generated {
+                // System-generated entries never override a soft close. A run
+                // in a locked period is skipped so it does not hold up the
+                // runs after it.
+                match database::periods::ensure_period_open(&mut *tx, template.company_id, run_date, &[]).await {
+                    Ok(()) => {
+                        let request = CreateJournalEntryRequest {
+                            company_id: template.company_id,
+                            entry_date: run_date,
+                            description: template.description.clone()
+                                .or_else(|| Some(template.template_name.clone())),
+                            reference: template.reference.clone(),
+                            auto_reverse_on: None,
+                            auto_reverse: false,
+                            lines: lines.clone(),
+                        };
+
+                        self.journal_service
+                            .insert_entry(
+                                &mut tx,
+                                request,
+                                EntryOrigin::recurring(template_id, template.auto_post),
+                                template.created_by,
+                            )
+                            .await?;
+                        created += 1;
+                    }
+                    Err(e @ (ServiceError::Validation(_) | ServiceError::Authorization(_))) => {
+                        tracing::warn!(
+                            "Recurring template {} skipped its run on {}: {}",
+                            template.template_name, run_date, e
+                        );
+                        self.record_skipped_run(&mut tx, template_id, run_date, &e.to_string()).await?;
+                        skipped += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            last_run_date = Some(run_date);
+            run_date = next_run_date(template.start_date, run_date, frequency);
+        }
+
+        let still_active = template.end_date.map_or(true, |end| run_date <= end);
+
+        sqlx::query!(
+            r#"
+            UPDATE recurring_journal_templates
+            SET next_run_date = $1, last_run_date = COALESCE($2, last_run_date),
+                is_active = $3, updated_at = NOW()
+            WHERE id = $4
+            "#,
+            run_date,
+            last_run_date,
+            still_active,
+            template_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        if created > 0 || skipped > 0 {
+            tracing::info!(
+                "Recurring template {} generated {} entries and skipped {} runs, next run {}",
+                template.template_name, created, skipped, run_date
+            );
+        }
+
+        Ok((created, skipped))
+    }
+
+    async fn record_skipped_run(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        template_id: Uuid,
+        run_date: NaiveDate,
+        reason: &str,
+    ) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO recurring_journal_skipped_runs (template_id, run_date, reason, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (template_id, run_date) DO NOTHING
+            "#,
+            template_id,
+            run_date,
+            reason
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(())
+    }
+
+    async fn validate_template(
+        &self,
+        company_id: Uuid,
+        lines: &[CreateJournalEntryLineRequest],
+        first_run: NaiveDate,
+        end_date: Option<NaiveDate>,
+    ) -> ServiceResult<()> {
+        super::validation::validate_journal_entry(lines)?;
+
+        let account_ids: Vec<Uuid> = lines.iter().map(|l| l.account_id).collect();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, company_id).await?;
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, lines).await?;
+
+        if let Some(end) = end_date {
+            if end < first_run {
+                return Err(ServiceError::Validation(
+                    "End date cannot be before the first run date".to_string()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn insert_template_lines(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        template_id: Uuid,
+        lines: Vec<CreateJournalEntryLineRequest>,
+    ) -> ServiceResult<Vec<RecurringTemplateLine>> {
+        let mut inserted = Vec::new();
+
+        for (index, line) in lines.into_iter().enumerate() {
+            let row = sqlx::query_as!(
+                RecurringTemplateLine,
+                r#"
+                INSERT INTO recurring_journal_template_lines
+                (id, template_id, account_id, description, debit_amount, credit_amount,
+                 department, project_code, cost_center, line_number)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING id, template_id, account_id, description, debit_amount, credit_amount,
+                          department, project_code, cost_center, line_number
+                "#,
+                Uuid::new_v4(),
+                template_id,
+                line.account_id,
+                line.description,
+                line.debit_amount,
+                line.credit_amount,
+                line.department,
+                line.project_code,
+                line.cost_center,
+                (index + 1) as i32
+            )
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            inserted.push(row);
+        }
+
+        Ok(inserted)
+    }
+
+    async fn get_template_lines<'e, E>(
+        &self,
+        executor: E,
+        template_id: Uuid,
+    ) -> ServiceResult<Vec<RecurringTemplateLine>>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        sqlx::query_as!(
+            RecurringTemplateLine,
+            r#"
+            SELECT id, template_id, account_id, description, debit_amount, credit_amount,
+                   department, project_code, cost_center, line_number
+            FROM recurring_journal_template_lines
+            WHERE template_id = $1
+            ORDER BY line_number
+            "#,
+            template_id
+        )
+        .fetch_all(executor)
+        .await
+        .map_err(ServiceError::Database)
+    }
+}
+
+/// Next occurrence after `current`, counted from the template's start date so
+/// month-end schedules don't drift (Jan 31 → Feb 28 → Mar 31).
+pub fn next_run_date(start_date: NaiveDate, current: NaiveDate, frequency: RecurringFrequency) -> NaiveDate {
+    use chrono::Datelike;
+
+    let elapsed_months = (current.year() - start_date.year()) * 12
+        + current.month() as i32 - start_date.month() as i32;
+    let next_offset = elapsed_months.max(0) as u32 + frequency.months();
+
+    start_date
+        .checked_add_months(Months::new(next_offset))
+        .unwrap_or(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monthly_schedule_keeps_month_end_anchor() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let feb = next_run_date(start, start, RecurringFrequency::Monthly);
+        assert_eq!(feb, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        let mar = next_run_date(start, feb, RecurringFrequency::Monthly);
+        assert_eq!(mar, NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+    }
+
+    #[test]
+    fn test_quarterly_and_yearly_schedule() {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        assert_eq!(
+            next_run_date(start, start, RecurringFrequency::Quarterly),
+            NaiveDate::from_ymd_opt(2024, 6, 15).unwrap()
+        );
+        assert_eq!(
+            next_run_date(start, start, RecurringFrequency::Yearly),
+            NaiveDate::from_ymd_opt(2025, 3, 15).unwrap()
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Code of the default "Laba Ditahan" account, used when a company has not
+/// configured a retained-earnings account explicitly.
+const DEFAULT_RETAINED_EARNINGS_CODE: &str = "3200";
+
+/// Codes of the default "Hutang Dagang" and "Piutang Dagang" accounts, used
+/// when no AP/AR control account is configured.
+const DEFAULT_AP_CONTROL_CODE: &str = "2100";
+const DEFAULT_AR_CONTROL_CODE: &str = "1200";
+
+pub struct SettingsService {
+    db: PgPool,
+}
+
+impl SettingsService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_settings(&self, company_id: Uuid) -> ServiceResult<LedgerSettings> {
+        let settings = sqlx::query_as!(
+            LedgerSettings,
+            r#"
+            SELECT company_id, retained_earnings_account_id,
+                   intercompany_receivable_account_id, intercompany_payable_account_id,
+                   unrealized_fx_gain_account_id, unrealized_fx_loss_account_id,
+                   ap_control_account_id, ar_control_account_id,
+                   COALESCE(budget_warnings, false) as "budget_warnings!",
+                   updated_by, updated_at
+            FROM ledger_settings
+            WHERE company_id = $1
+            "#,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(settings.unwrap_or(LedgerSettings {
+            company_id,
+            retained_earnings_account_id: None,
+            intercompany_receivable_account_id: None,
+            intercompany_payable_account_id: None,
+            unrealized_fx_gain_account_id: None,
+            unrealized_fx_loss_account_id: None,
+            ap_control_account_id: None,
+            ar_control_account_id: None,
+            budget_warnings: false,
+            updated_by: None,
+            updated_at: None,
+        }))
+    }
+
+    pub async fn update_settings(
+        &self,
+        company_id: Uuid,
+        request: UpdateLedgerSettingsRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<LedgerSettings> {
+        if let Some(account_id) = request.retained_earnings_account_id {
+            self.validate_account_type(company_id, account_id, "EQUITY").await?;
+        }
+        if let Some(account_id) = request.intercompany_receivable_account_id {
+            self.validate_account_type(company_id, account_id, "ASSET").await?;
+        }
+        if let Some(account_id) = request.intercompany_payable_account_id {
+            self.validate_account_type(company_id, account_id, "LIABILITY").await?;
+        }
+        if let Some(account_id) = request.unrealized_fx_gain_account_id {
+            self.validate_account_type(company_id, account_id, "REVENUE").await?;
+        }
+        if let Some(account_id) = request.unrealized_fx_loss_account_id {
+            self.validate_account_type(company_id, account_id, "EXPENSE").await?;
+        }
+        if let Some(account_id) = request.ap_control_account_id {
+            self.validate_account_type(company_id, account_id, "LIABILITY").await?;
+        }
+        if let Some(account_id) = request.ar_control_account_id {
+            self.validate_account_type(company_id, account_id, "ASSET").await?;
+        }
+
+        let settings = sqlx::query_as!(
+            LedgerSettings,
+            r#"
+            INSERT INTO ledger_settings
+            (company_id, retained_earnings_account_id, intercompany_receivable_account_id,
+             intercompany_payable_account_id, unrealized_fx_gain_account_id,
+             unrealized_fx_loss_account_id, ap_control_account_id, ar_control_account_id,
+             budget_warnings, updated_by, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW())
+            ON CONFLICT (company_id) DO UPDATE
+            SET retained_earnings_account_id = EXCLUDED.retained_earnings_account_id,
+                intercompany_receivable_account_id = EXCLUDED.intercompany_receivable_account_id,
+                intercompany_payable_account_id = EXCLUDED.intercompany_payable_account_id,
+                unrealized_fx_gain_account_id = EXCLUDED.unrealized_fx_gain_account_id,
+                unrealized_fx_loss_account_id = EXCLUDED.unrealized_fx_loss_account_id,
+                ap_control_account_id = EXCLUDED.ap_control_account_id,
+                ar_control_account_id = EXCLUDED.ar_control_account_id,
+                budget_warnings = EXCLUDED.budget_warnings,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING company_id, retained_earnings_account_id,
+                      intercompany_receivable_account_id, intercompany_payable_account_id,
+                      unrealized_fx_gain_account_id, unrealized_fx_loss_account_id,
+                      ap_control_account_id, ar_control_account_id,
+                      COALESCE(budget_warnings, false) as "budget_warnings!",
+                      updated_by, updated_at
+            "#,
+            company_id,
+            request.retained_earnings_account_id,
+            request.intercompany_receivable_account_id,
+            request.intercompany_payable_account_id,
+            request.unrealized_fx_gain_account_id,
+            request.unrealized_fx_loss_account_id,
+            request.ap_control_account_id,
+            request.ar_control_account_id,
+            request.budget_warnings,
+            user_id
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        tracing::info!("Ledger settings updated for company {} by user {}", company_id, user_id);
+
+        Ok(settings)
+    }
+
+    pub async fn get_number_formats(&self, company_id: Uuid) -> ServiceResult<Vec<DocumentNumberFormat>> {
+        database::numbering::get_formats(&self.db, company_id).await
+    }
+
+    pub async fn update_number_format(
+        &self,
+        company_id: Uuid,
+        document_type: DocumentType,
+        request: UpdateDocumentNumberFormatRequest,
+        user_id: Uuid,
+    ) -> ServiceResult<DocumentNumberFormat> {
+        database::numbering::set_format(&self.db, company_id, document_type, &request.pattern, user_id).await
+    }
+
+    /// The configured retained-earnings account, falling back to account 3200.
+    pub async fn retained_earnings_account(&self, company_id: Uuid) -> ServiceResult<Uuid> {
+        if let Some(account_id) = self.get_settings(company_id).await?.retained_earnings_account_id {
+            return Ok(account_id);
+        }
+
+        sqlx::query_scalar!(
+            "SELECT id FROM accounts WHERE company_id = $1 AND account_code = $2 AND is_active = true",
+            company_id,
+            DEFAULT_RETAINED_EARNINGS_CODE
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Validation(
+            "No retained earnings account configured and account 3200 not found".to_string()
+        ))
+    }
+
+    /// The intercompany receivable and payable accounts, which must be
+    /// configured before a company can take part in intercompany entries.
+    pub async fn intercompany_accounts(&self, company_id: Uuid) -> ServiceResult<(Uuid, Uuid)> {
+        let settings = self.get_settings(company_id).await?;
+
+        match (settings.intercompany_receivable_account_id, settings.intercompany_payable_account_id) {
+            (Some(receivable), Some(payable)) => Ok((receivable, payable)),
+            _ => Err(ServiceError::Validation(format!(
+                "Company {} has no intercompany receivable/payable accounts configured",
+                company_id
+            ))),
+        }
+    }
+
+    /// The unrealized FX gain and loss accounts used by revaluation runs.
+    pub async fn fx_revaluation_accounts(&self, company_id: Uuid) -> ServiceResult<(Uuid, Uuid)> {
+        let settings = self.get_settings(company_id).await?;
+
+        match (settings.unrealized_fx_gain_account_id, settings.unrealized_fx_loss_account_id) {
+            (Some(gain), Some(loss)) => Ok((gain, loss)),
+            _ => Err(ServiceError::Validation(
+                "Unrealized FX gain/loss accounts must be configured in ledger settings".to_string()
+            )),
+        }
+    }
+
+    /// The AP and AR control accounts, falling back to accounts 2100 and
+    /// 1200. Either is None when neither is available.
+    pub async fn control_accounts(&self, company_id: Uuid) -> ServiceResult<(Option<Uuid>, Option<Uuid>)> {
+        let settings = self.get_settings(company_id).await?;
+
+        let ap = match settings.ap_control_account_id {
+            Some(account_id) => Some(account_id),
+            None => self.account_by_code(company_id, DEFAULT_AP_CONTROL_CODE).await?,
+        };
+        let ar = match settings.ar_control_account_id {
+            Some(account_id) => Some(account_id),
+            None => self.account_by_code(company_id, DEFAULT_AR_CONTROL_CODE).await?,
+        };
+
+        Ok((ap, ar))
+    }
+
+    async fn account_by_code(&self, company_id: Uuid, account_code: &str) -> ServiceResult<Option<Uuid>> {
+        sqlx::query_scalar!(
+            "SELECT id FROM accounts WHERE company_id = $1 AND account_code = $2",
+            company_id,
+            account_code
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+
+    pub(crate) async fn validate_account_type(
+        &self,
+        company_id: Uuid,
+        account_id: Uuid,
+        expected_type: &str,
+    ) -> ServiceResult<()> {
+        let account = sqlx::query!(
+            r#"
+            SELECT account_code, account_type::text as "account_type!", is_active
+            FROM accounts
+            WHERE id = $1 AND company_id = $2
+            "#,
+            account_id,
+            company_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Validation("Account not found".to_string()))?;
+
+        if account.is_active != Some(true) {
+            return Err(ServiceError::Validation(
+                format!("Account {} is inactive", account.account_code)
+            ));
+        }
+
+        if account.account_type != expected_type {
+            return Err(ServiceError::Validation(
+                format!("Account {} must be of type {}", account.account_code, expected_type)
+            ));
+        }
+
+        Ok(())
+    }
+}
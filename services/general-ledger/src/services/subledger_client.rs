@@ -0,0 +1,124 @@
+use crate::models::*;
+use chrono::NaiveDate;
+use common::{ServiceResult, ServiceError, identity::{self, IDENTITY_HEADER}};
+use uuid::Uuid;
+
+/// Reads vendor and customer documents from the payables and receivables
+/// services, which own them, for bank reconciliation and integrity checks.
+#[derive(Clone)]
+pub struct SubledgerClient {
+    client: reqwest::Client,
+    payables_url: String,
+    receivables_url: String,
+}
+
+impl SubledgerClient {
+    pub fn from_env() -> Self {
+        let payables_url = std::env::var("ACCOUNTS_PAYABLE_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3006".to_string());
+        let receivables_url = std::env::var("ACCOUNTS_RECEIVABLE_SERVICE_URL")
+            .unwrap_or_else(|_| "http://localhost:3007".to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            payables_url: payables_url.trim_end_matches('/').to_string(),
+            receivables_url: receivables_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Payments of `kind` dated between `from` and `to` that went through the
+    /// bank account or were recorded without one, reversed ones included.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn payments_between(
+        &self,
+        kind: BankMatchType,
+        company_id: Uuid,
+        user_id: Uuid,
+        bank_account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> ServiceResult<Vec<SubledgerPayment>> {
+        self.payments(kind, company_id, user_id, &[
+            ("bank_account_id", bank_account_id.to_string()),
+            ("date_from", from.to_string()),
+            ("date_to", to.to_string()),
+        ]).await
+    }
+
+    /// Payments of `kind` with the given ids. Unknown ids are left out.
+    pub async fn payments_by_id(
+        &self,
+        kind: BankMatchType,
+        company_id: Uuid,
+        user_id: Uuid,
+        ids: &[Uuid],
+    ) -> ServiceResult<Vec<SubledgerPayment>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids = ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(",");
+        self.payments(kind, company_id, user_id, &[("ids", ids)]).await
+    }
+
+    /// Open payables at their booked rates.
+    pub async fn payables_balance(&self, company_id: Uuid, user_id: Uuid) -> ServiceResult<SubledgerBalance> {
+        self.get(&self.payables_url, "accounts payable", "subledger-balance", company_id, user_id, &[]).await
+    }
+
+    /// Open receivables at their booked rates.
+    pub async fn receivables_balance(&self, company_id: Uuid, user_id: Uuid) -> ServiceResult<SubledgerBalance> {
+        self.get(&self.receivables_url, "accounts receivable", "subledger-balance", company_id, user_id, &[]).await
+    }
+
+    async fn payments(
+        &self,
+        kind: BankMatchType,
+        company_id: Uuid,
+        user_id: Uuid,
+        query: &[(&str, String)],
+    ) -> ServiceResult<Vec<SubledgerPayment>> {
+        match kind {
+            BankMatchType::VendorPayment => {
+                self.get(&self.payables_url, "accounts payable", "payments", company_id, user_id, query).await
+            }
+            BankMatchType::CustomerPayment => {
+                self.get(&self.receivables_url, "accounts receivable", "payments", company_id, user_id, query).await
+            }
+            BankMatchType::JournalLine => Ok(Vec::new()),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        base_url: &str,
+        service: &str,
+        path: &str,
+        company_id: Uuid,
+        user_id: Uuid,
+        query: &[(&str, String)],
+    ) -> ServiceResult<T> {
+        let response = self.client
+            .get(format!("{}/{}", base_url, path))
+            .query(query)
+            .header("X-Company-ID", company_id.to_string())
+            .header("X-User-ID", user_id.to_string())
+            .header(IDENTITY_HEADER, identity::forward(user_id, company_id, &[])?)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to call {}: {}", service, e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ServiceError::ExternalService(format!(
+                "Request for {} to {} returned status {}", path, service, status
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ServiceError::ExternalService(format!("Failed to parse {} response: {}", service, e)))
+    }
+}
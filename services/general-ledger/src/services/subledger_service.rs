@@ -0,0 +1,222 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService, PostingRuleService};
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Posts journal entries on behalf of the subledger services. Each source
+/// document gets at most one live entry: a repeated request returns the
+/// entry already posted for it unless that entry has been reversed.
+pub struct SubledgerService {
+    db: PgPool,
+    journal_service: JournalService,
+    posting_rule_service: PostingRuleService,
+}
+
+impl SubledgerService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            posting_rule_service: PostingRuleService::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn post_document(
+        &self,
+        company_id: Uuid,
+        request: SubledgerPostingRequest,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<JournalEntryWithLines> {
+        let source_document_type = request.source_document_type.trim().to_uppercase();
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // Serialize postings for the same document so a retry racing the
+        // original request can't post it twice
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext('subledger:' || $1::text || ':' || $2::text))",
+            source_document_type,
+            request.source_document_id.to_string()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM journal_entries
+            WHERE company_id = $1 AND source_document_type = $2 AND source_document_id = $3
+              AND status = 'POSTED' AND reversed_by_entry_id IS NULL
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            company_id,
+            source_document_type,
+            request.source_document_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if let Some(entry_id) = existing {
+            tracing::info!("{} {} already posted as entry {}", source_document_type, request.source_document_id, entry_id);
+            return self.entry_with_lines(entry_id, company_id).await;
+        }
+
+        let mut lines = self.resolve_lines(
+            company_id,
+            request.event_type,
+            request.vendor_group.as_deref(),
+            request.lines,
+        ).await?;
+
+        super::validation::validate_journal_entry(&lines)?;
+        super::fx_service::validate_line_currencies(&self.db, company_id, request.entry_date, &mut lines).await?;
+        let account_ids: Vec<Uuid> = lines.iter().map(|l| l.account_id).collect();
+        super::validation::validate_accounts_exist(&self.db, &account_ids, company_id).await?;
+        super::dimension_service::validate_line_dimensions(&self.db, company_id, &lines).await?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, request.entry_date, roles).await?;
+
+        let entry = self.journal_service
+            .insert_entry(
+                &mut tx,
+                CreateJournalEntryRequest {
+                    company_id,
+                    entry_date: request.entry_date,
+                    description: request.description,
+                    reference: request.reference,
+                    auto_reverse_on: None,
+                    auto_reverse: false,
+                    lines,
+                },
+                EntryOrigin::system(&source_document_type, request.source_document_id),
+                user_id,
+            )
+            .await?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!(
+            "Posted {} for {} {}",
+            entry.journal_entry.entry_number, source_document_type, request.source_document_id
+        );
+
+        Ok(entry)
+    }
+
+    /// Turns account roles (through the posting rules) and account codes
+    /// into account ids; lines given by id pass through.
+    async fn resolve_lines(
+        &self,
+        company_id: Uuid,
+        event_type: Option<PostingEvent>,
+        vendor_group: Option<&str>,
+        lines: Vec<SubledgerPostingLine>,
+    ) -> ServiceResult<Vec<CreateJournalEntryLineRequest>> {
+        let mut role_accounts = Vec::with_capacity(lines.len());
+        for line in &lines {
+            let account_id = match (line.account_id, line.account_role) {
+                (None, Some(account_role)) => {
+                    let event_type = event_type.ok_or_else(|| ServiceError::Validation(
+                        "event_type is required for lines addressed by account_role".to_string()
+                    ))?;
+
+                    let resolved = self.posting_rule_service
+                        .resolve_account(company_id, &ResolvePostingAccountRequest {
+                            event_type,
+                            account_role,
+                            vendor_group: vendor_group.map(str::to_string),
+                            item_category: line.item_category.clone(),
+                            tax_type: line.tax_type.clone(),
+                        })
+                        .await?;
+                    Some(resolved.account_id)
+                }
+                _ => None,
+            };
+            role_accounts.push(account_id);
+        }
+
+        let codes: Vec<String> = lines
+            .iter()
+            .filter(|l| l.account_id.is_none() && l.account_role.is_none())
+            .filter_map(|l| l.account_code.clone())
+            .collect();
+
+        let accounts: HashMap<String, Uuid> = sqlx::query!(
+            "SELECT id, account_code FROM accounts WHERE company_id = $1 AND account_code = ANY($2)",
+            company_id,
+            &codes
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .into_iter()
+        .map(|row| (row.account_code, row.id))
+        .collect();
+
+        lines
+            .into_iter()
+            .zip(role_accounts)
+            .map(|(line, role_account)| {
+                let account_id = match (line.account_id.or(role_account), line.account_code.as_deref()) {
+                    (Some(account_id), _) => account_id,
+                    (None, Some(code)) => *accounts.get(code).ok_or_else(|| {
+                        ServiceError::Validation(format!("Account {} not found", code))
+                    })?,
+                    (None, None) => {
+                        return Err(ServiceError::Validation(
+                            "Every line needs an account_id, account_role or account_code".to_string()
+                        ))
+                    }
+                };
+
+                Ok(CreateJournalEntryLineRequest {
+                    account_id,
+                    description: line.description,
+                    debit_amount: line.debit_amount,
+                    credit_amount: line.credit_amount,
+                    department: line.department,
+                    project_code: line.project_code,
+                    cost_center: None,
+                    currency: line.currency,
+                    foreign_amount: line.foreign_amount,
+                    exchange_rate: line.exchange_rate,
+                })
+            })
+            .collect()
+    }
+
+    async fn entry_with_lines(&self, entry_id: Uuid, company_id: Uuid) -> ServiceResult<JournalEntryWithLines> {
+        let journal_entry = self.journal_service.get_entry(entry_id, company_id).await?;
+
+        let lines = sqlx::query_as!(
+            JournalEntryLine,
+            r#"
+            SELECT jel.id, jel.journal_entry_id, jel.account_id,
+                   a.account_code as "account_code?", a.account_name as "account_name?",
+                   jel.description, jel.debit_amount, jel.credit_amount, jel.line_number,
+                   jel.department, jel.project_code, jel.cost_center,
+                   jel.currency, jel.foreign_amount, jel.exchange_rate
+            FROM journal_entry_lines jel
+            LEFT JOIN accounts a ON a.id = jel.account_id
+            WHERE jel.journal_entry_id = $1
+            ORDER BY jel.line_number
+            "#,
+            entry_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        Ok(JournalEntryWithLines {
+            journal_entry,
+            lines,
+            budget_warnings: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,222 @@
+use crate::models::*;
+use super::{journal_service::EntryOrigin, JournalService, PeriodService, SettingsService};
+use common::{ServiceResult, ServiceError};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct YearEndService {
+    db: PgPool,
+    journal_service: JournalService,
+    period_service: PeriodService,
+    settings_service: SettingsService,
+    audit_logger: database::audit::AuditLogger,
+}
+
+impl YearEndService {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            journal_service: JournalService::new(db.clone()),
+            period_service: PeriodService::new(db.clone()),
+            settings_service: SettingsService::new(db.clone()),
+            audit_logger: database::audit::AuditLogger::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Closes every REVENUE/EXPENSE balance of the fiscal year into retained
+    /// earnings with a posted entry dated on the last day of the year.
+    ///
+    /// Running it again only closes what is left, so late adjustments posted
+    /// after the first close produce a second, smaller closing entry and a
+    /// fully closed year is a no-op.
+    pub async fn close_fiscal_year(
+        &self,
+        company_id: Uuid,
+        fiscal_year: i32,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> ServiceResult<YearEndCloseResult> {
+        let (period_start, period_end) = self.period_service
+            .fiscal_year_bounds(company_id, fiscal_year)
+            .await?;
+        let retained_earnings_account_id = self.settings_service
+            .retained_earnings_account(company_id)
+            .await?;
+
+        let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
+
+        // One close per company/year at a time
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext('year_end_close:' || $1::text || ':' || $2::text))",
+            company_id.to_string(),
+            fiscal_year.to_string()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        database::periods::ensure_period_open(&mut *tx, company_id, period_end, roles).await?;
+
+        // Remaining income statement balances, including earlier closing entries
+        let balances = sqlx::query!(
+            r#"
+            SELECT jel.account_id, a.account_code,
+                   SUM(jel.debit_amount - jel.credit_amount) as "net_debit!"
+            FROM journal_entry_lines jel
+            JOIN journal_entries je ON jel.journal_entry_id = je.id
+            JOIN accounts a ON a.id = jel.account_id
+            WHERE je.company_id = $1
+              AND je.is_posted = true
+              AND je.status = 'POSTED'
+              AND je.entry_date BETWEEN $2 AND $3
+              AND a.account_type IN ('REVENUE', 'EXPENSE')
+            GROUP BY jel.account_id, a.account_code
+            HAVING SUM(jel.debit_amount - jel.credit_amount) <> 0
+            ORDER BY a.account_code
+            "#,
+            company_id,
+            period_start,
+            period_end
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        if balances.is_empty() {
+            tx.commit().await.map_err(ServiceError::Database)?;
+            tracing::info!("Fiscal year {} for company {} has nothing left to close", fiscal_year, company_id);
+
+            return Ok(YearEndCloseResult {
+                fiscal_year,
+                period_start,
+                period_end,
+                accounts_closed: 0,
+                net_income: Decimal::ZERO,
+                closing: None,
+                journal_entry: None,
+            });
+        }
+
+        // Zero each account by posting the opposite side
+        let mut lines: Vec<CreateJournalEntryLineRequest> = balances
+            .iter()
+            .map(|row| CreateJournalEntryLineRequest {
+                account_id: row.account_id,
+                description: Some(format!("Year-end close {}", fiscal_year)),
+                debit_amount: if row.net_debit < Decimal::ZERO { -row.net_debit } else { Decimal::ZERO },
+                credit_amount: if row.net_debit > Decimal::ZERO { row.net_debit } else { Decimal::ZERO },
+                department: None,
+                project_code: None,
+                cost_center: None,
+                currency: None,
+                foreign_amount: None,
+                exchange_rate: None,
+            })
+            .collect();
+
+        // Net income is credit-positive: revenue credits exceed expense debits
+        let net_income: Decimal = -balances.iter().map(|row| row.net_debit).sum::<Decimal>();
+
+        lines.push(CreateJournalEntryLineRequest {
+            account_id: retained_earnings_account_id,
+            description: Some(format!("Net income transferred for fiscal year {}", fiscal_year)),
+            debit_amount: if net_income < Decimal::ZERO { -net_income } else { Decimal::ZERO },
+            credit_amount: if net_income > Decimal::ZERO { net_income } else { Decimal::ZERO },
+            department: None,
+            project_code: None,
+            cost_center: None,
+            currency: None,
+            foreign_amount: None,
+            exchange_rate: None,
+        });
+        lines.retain(|l| l.debit_amount != Decimal::ZERO || l.credit_amount != Decimal::ZERO);
+
+        let closing_id = Uuid::new_v4();
+        let request = CreateJournalEntryRequest {
+            company_id,
+            entry_date: period_end,
+            description: Some(format!("Year-end closing entry for fiscal year {}", fiscal_year)),
+            reference: Some(format!("CLOSE-{}", fiscal_year)),
+            auto_reverse_on: None,
+            auto_reverse: false,
+            lines,
+        };
+
+        let entry = self.journal_service
+            .insert_entry(&mut tx, request, EntryOrigin::system("YEAR_END_CLOSE", closing_id), user_id)
+            .await?;
+
+        let closing = sqlx::query_as!(
+            YearEndClosing,
+            r#"
+            INSERT INTO year_end_closings
+            (id, company_id, fiscal_year, period_start, period_end, journal_entry_id,
+             retained_earnings_account_id, net_income, closed_by, closed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+            RETURNING id, company_id, fiscal_year, period_start, period_end, journal_entry_id,
+                      retained_earnings_account_id, net_income, closed_by, closed_at as "closed_at!"
+            "#,
+            closing_id,
+            company_id,
+            fiscal_year,
+            period_start,
+            period_end,
+            entry.journal_entry.id,
+            retained_earnings_account_id,
+            net_income,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        // Log audit trail
+        self.audit_logger.log_activity(
+            &mut tx,
+            "year_end_closings",
+            closing_id,
+            "YEAR_END_CLOSE",
+            None,
+            Some(serde_json::json!({
+                "fiscal_year": fiscal_year,
+                "journal_entry_id": entry.journal_entry.id,
+                "net_income": net_income,
+                "accounts_closed": balances.len()
+            })),
+            user_id,
+        ).await.map_err(ServiceError::Database)?;
+
+        tx.commit().await.map_err(ServiceError::Database)?;
+
+        tracing::info!("Closed fiscal year {} for company {}: net income {} via {}",
+            fiscal_year, company_id, net_income, entry.journal_entry.entry_number);
+
+        Ok(YearEndCloseResult {
+            fiscal_year,
+            period_start,
+            period_end,
+            accounts_closed: balances.len() as u32,
+            net_income,
+            closing: Some(closing),
+            journal_entry: Some(entry),
+        })
+    }
+
+    pub async fn get_closings(&self, company_id: Uuid) -> ServiceResult<Vec<YearEndClosing>> {
+        sqlx::query_as!(
+            YearEndClosing,
+            r#"
+            SELECT id, company_id, fiscal_year, period_start, period_end, journal_entry_id,
+                   retained_earnings_account_id, net_income, closed_by, closed_at as "closed_at!"
+            FROM year_end_closings
+            WHERE company_id = $1
+            ORDER BY fiscal_year DESC, closed_at DESC
+            "#,
+            company_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod tax_transactions;
+
+pub use tax_transactions::*;
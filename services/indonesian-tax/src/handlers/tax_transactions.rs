@@ -0,0 +1,24 @@
+use axum::{extract::State, http::{HeaderMap, StatusCode}, response::Json};
+use std::sync::Arc;
+use validator::Validate;
+use crate::{AppState, models::*};
+use common::{ServiceResult, ServiceError, extractors::*};
+
+pub async fn create_tax_transaction(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTaxTransactionRequest>,
+) -> ServiceResult<(StatusCode, Json<TaxTransaction>)> {
+    let company_id = extract_company_id(&headers)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(ServiceError::Validation(format!("{:?}", e)));
+    }
+
+    let transaction = state.tax_service
+        .create_tax_transaction(payload, company_id, user_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(transaction)))
+}
@@ -12,6 +12,7 @@ use tracing::info;
 pub struct AppState {
     db: sqlx::PgPool,
     tax_calculator: services::TaxCalculator,
+    tax_service: services::TaxService,
 }
 
 #[tokio::main]
@@ -26,10 +27,12 @@ async fn main() -> anyhow::Result<()> {
 
     let pool = database::create_database_pool("indonesian-tax").await?;
     let tax_calculator = services::TaxCalculator::new();
+    let tax_service = services::TaxService::new(pool.clone());
 
     let app_state = Arc::new(AppState { 
         db: pool,
         tax_calculator,
+        tax_service,
     });
 
     let app = Router::new()
@@ -0,0 +1,51 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Tax types of the `tax_type` enum
+pub const TAX_TYPES: &[&str] = &["PPN", "PPH21", "PPH22", "PPH23", "PPH25", "PPH29", "PBB"];
+
+/// A tax transaction recorded by another service, such as withholding AP
+/// deducts from a vendor payment. The source document identifies it, so a
+/// repeated request records it once.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateTaxTransactionRequest {
+    pub tax_type: String,
+    pub transaction_date: NaiveDate,
+    /// First day of the month the tax is reported in
+    pub tax_period: NaiveDate,
+    pub tax_base_amount: Decimal,
+    pub tax_amount: Decimal,
+    #[validate(length(max = 50, message = "Tax invoice number must be at most 50 characters"))]
+    pub tax_invoice_number: Option<String>,
+    #[validate(length(max = 20, message = "Vendor NPWP must be at most 20 characters"))]
+    pub vendor_npwp: Option<String>,
+    #[validate(length(max = 255, message = "Vendor name must be at most 255 characters"))]
+    pub vendor_name: Option<String>,
+    pub description: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Source document type must be 1-50 characters"))]
+    pub source_document_type: String,
+    pub source_document_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxTransaction {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub tax_type: String,
+    pub transaction_date: NaiveDate,
+    pub tax_period: NaiveDate,
+    pub tax_base_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub tax_invoice_number: Option<String>,
+    pub vendor_npwp: Option<String>,
+    pub vendor_name: Option<String>,
+    pub description: Option<String>,
+    pub journal_entry_id: Option<Uuid>,
+    pub source_document_type: Option<String>,
+    pub source_document_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+}
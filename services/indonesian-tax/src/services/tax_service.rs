@@ -0,0 +1,75 @@
+use crate::models::*;
+use common::{ServiceResult, ServiceError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct TaxService {
+    db: PgPool,
+}
+
+impl TaxService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Records a transaction once per source document and tax type; a
+    /// repeat of one already recorded is a conflict.
+    pub async fn create_tax_transaction(
+        &self,
+        request: CreateTaxTransactionRequest,
+        company_id: Uuid,
+        user_id: Uuid,
+    ) -> ServiceResult<TaxTransaction> {
+        let tax_type = request.tax_type.trim().to_uppercase();
+        if !TAX_TYPES.contains(&tax_type.as_str()) {
+            return Err(ServiceError::Validation(format!("Invalid tax type: {}", request.tax_type)));
+        }
+
+        let transaction = sqlx::query_as!(
+            TaxTransaction,
+            r#"
+            INSERT INTO tax_transactions (
+                id, company_id, tax_type, transaction_date, tax_period, tax_base_amount, tax_amount,
+                tax_invoice_number, vendor_npwp, vendor_name, description, journal_entry_id,
+                source_document_type, source_document_id, created_at, updated_at
+            )
+            VALUES ($1, $2, $3::tax_type, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, NOW(), NOW())
+            ON CONFLICT (company_id, source_document_type, source_document_id, tax_type)
+                WHERE source_document_id IS NOT NULL
+                DO NOTHING
+            RETURNING id, company_id, tax_type::text as "tax_type!", transaction_date, tax_period,
+                      tax_base_amount, tax_amount, tax_invoice_number, vendor_npwp, vendor_name,
+                      description, journal_entry_id, source_document_type, source_document_id, created_at
+            "#,
+            Uuid::new_v4(),
+            company_id,
+            tax_type,
+            request.transaction_date,
+            request.tax_period,
+            request.tax_base_amount,
+            request.tax_amount,
+            request.tax_invoice_number,
+            request.vendor_npwp,
+            request.vendor_name,
+            request.description,
+            request.journal_entry_id,
+            request.source_document_type,
+            request.source_document_id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::Conflict(format!(
+            "{} transaction for {} {} is already recorded",
+            tax_type, request.source_document_type, request.source_document_id
+        )))?;
+
+        tracing::info!(
+            "User {} recorded {} transaction {} for {} {}",
+            user_id, tax_type, transaction.id, request.source_document_type, request.source_document_id
+        );
+
+        Ok(transaction)
+    }
+}
@@ -73,6 +73,7 @@ impl InventoryService {
         &self,
         request: CreateInventoryTransactionRequest,
         user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<InventoryTransaction> {
         // Validate transaction type
         if !matches!(request.transaction_type.as_str(), "IN" | "OUT") {
@@ -87,8 +88,35 @@ impl InventoryService {
             ));
         }
 
+        // A caller repeating a stock move for the same source document gets
+        // the transaction booked the first time
+        if let (Some(source_type), Some(source_id)) = (&request.source_document_type, request.source_document_id) {
+            let existing = sqlx::query_as!(
+                InventoryTransaction,
+                r#"
+                SELECT id, company_id, item_id, transaction_type, transaction_date,
+                       quantity, unit_cost, total_cost, reference, journal_entry_id, created_at
+                FROM inventory_transactions
+                WHERE company_id = $1 AND source_document_type = $2 AND source_document_id = $3
+                "#,
+                request.company_id,
+                source_type,
+                source_id
+            )
+            .fetch_optional(&self.db)
+            .await
+            .map_err(ServiceError::Database)?;
+
+            if let Some(existing) = existing {
+                return Ok(existing);
+            }
+        }
+
         let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
 
+        // Stock movements are valued postings, so respect the period lock
+        database::periods::ensure_period_open(&mut *tx, request.company_id, request.transaction_date, roles).await?;
+
         // Get current item details and verify ownership
         let item = sqlx::query!(
             r#"
@@ -120,8 +148,9 @@ impl InventoryService {
             InventoryTransaction,
             r#"
             INSERT INTO inventory_transactions 
-            (id, company_id, item_id, transaction_type, transaction_date, quantity, unit_cost, total_cost, reference)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            (id, company_id, item_id, transaction_type, transaction_date, quantity, unit_cost, total_cost, reference,
+             source_document_type, source_document_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING id, company_id, item_id, transaction_type, transaction_date, 
                       quantity, unit_cost, total_cost, reference, journal_entry_id, created_at
             "#,
@@ -133,7 +162,9 @@ impl InventoryService {
             request.quantity,
             request.unit_cost,
             total_cost,
-            request.reference
+            request.reference,
+            request.source_document_type,
+            request.source_document_id
         )
         .fetch_one(&mut *tx)
         .await
@@ -174,6 +205,7 @@ impl InventoryService {
         request: StockAdjustmentRequest,
         company_id: Uuid,
         user_id: Uuid,
+        roles: &[String],
     ) -> ServiceResult<InventoryTransaction> {
         if request.adjustment_quantity == Decimal::ZERO {
             return Err(ServiceError::Validation(
@@ -183,6 +215,10 @@ impl InventoryService {
 
         let mut tx = self.db.begin().await.map_err(ServiceError::Database)?;
 
+        // Adjustments are dated today
+        let adjustment_date = chrono::Utc::now().date_naive();
+        database::periods::ensure_period_open(&mut *tx, company_id, adjustment_date, roles).await?;
+
         // Get current item details
         let item = sqlx::query!(
             r#"
@@ -208,6 +244,12 @@ impl InventoryService {
         }
 
         let transaction_id = Uuid::new_v4();
+        let adjustment_number = database::numbering::next_number(
+            &mut *tx,
+            company_id,
+            database::numbering::DocumentType::InventoryAdjustment,
+            adjustment_date,
+        ).await?;
         let transaction_type = if request.adjustment_quantity > Decimal::ZERO { "IN" } else { "OUT" };
         let abs_quantity = request.adjustment_quantity.abs();
         let total_cost = abs_quantity * item.unit_cost;
@@ -218,7 +260,7 @@ impl InventoryService {
             r#"
             INSERT INTO inventory_transactions 
             (id, company_id, item_id, transaction_type, transaction_date, quantity, unit_cost, total_cost, reference)
-            VALUES ($1, $2, $3, $4, CURRENT_DATE, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $9, $5, $6, $7, $8)
             RETURNING id, company_id, item_id, transaction_type, transaction_date, 
                       quantity, unit_cost, total_cost, reference, journal_entry_id, created_at
             "#,
@@ -229,7 +271,8 @@ impl InventoryService {
             abs_quantity,
             item.unit_cost,
             total_cost,
-            Some(format!("ADJUSTMENT: {}", request.reason))
+            Some(format!("{}: {}", adjustment_number, request.reason)),
+            adjustment_date
         )
         .fetch_one(&mut *tx)
         .await
@@ -247,8 +290,8 @@ impl InventoryService {
 
         tx.commit().await.map_err(ServiceError::Database)?;
 
-        tracing::info!("Stock adjustment for item {} by {} by user {}", 
-            item.item_code, request.adjustment_quantity, user_id);
+        tracing::info!("Stock adjustment {} for item {} by {} by user {}", 
+            adjustment_number, item.item_code, request.adjustment_quantity, user_id);
 
         let transaction_with_details = InventoryTransaction {
             item_code: Some(item.item_code),
@@ -37,8 +37,11 @@ pub async fn generate_income_statement(
         .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
         .unwrap_or(end_date);
 
+    let filter = DimensionFilter::from_params(&params);
+    let pivot = database::dimensions::pivot_from_params(&params)?;
+
     let report = state.financial_report_service
-        .generate_income_statement(company_id, start_date, end_date)
+        .generate_income_statement(company_id, start_date, end_date, filter, pivot)
         .await?;
     
     Ok(Json(report))
@@ -62,6 +65,34 @@ pub async fn generate_trial_balance(
     Ok(Json(report))
 }
 
+/// Budget against actuals; `budget_id` defaults to the active budget for
+/// the period, which defaults to the current year to date.
+pub async fn generate_budget_vs_actual(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> ServiceResult<Json<BudgetVsActualReport>> {
+    let company_id = extract_company_id(&headers)?;
+
+    let end_date = chrono::Utc::now().date_naive();
+    let start_date = params.get("start_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(end_date.year(), 1, 1).unwrap());
+
+    let end_date = params.get("end_date")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .unwrap_or(end_date);
+
+    let budget_id = params.get("budget_id").and_then(|id| id.parse().ok());
+    let filter = DimensionFilter::from_params(&params);
+
+    let report = state.financial_report_service
+        .generate_budget_vs_actual(company_id, budget_id, start_date, end_date, filter)
+        .await?;
+
+    Ok(Json(report))
+}
+
 pub async fn generate_cash_flow(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -46,6 +46,7 @@ async fn main() -> anyhow::Result<()> {
         .route("/reports/income-statement", get(generate_income_statement))
         .route("/reports/cash-flow", get(generate_cash_flow))
         .route("/reports/trial-balance", get(generate_trial_balance))
+        .route("/reports/budget-vs-actual", get(generate_budget_vs_actual))
         .route("/reports/general-ledger", get(generate_general_ledger))
         // Indonesian Tax Reports
         .route("/reports/tax/ppn", get(generate_ppn_report))
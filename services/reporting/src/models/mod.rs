@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+pub use database::dimensions::{DimensionFilter, DimensionType};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BalanceSheetReport {
     pub company_id: Uuid,
@@ -44,13 +46,27 @@ pub struct IncomeStatementReport {
     pub company_id: Uuid,
     pub period_start: NaiveDate,
     pub period_end: NaiveDate,
+    pub filter: DimensionFilter,
+    pub pivot: Option<DimensionType>,
     pub revenue: IncomeStatementSection,
     pub expenses: IncomeStatementSection,
     pub gross_profit: Decimal,
     pub net_income: Decimal,
+    pub by_dimension: Vec<DimensionIncomeStatement>,
     pub generated_at: DateTime<Utc>,
 }
 
+/// Income statement figures for one value of the pivot dimension, e.g. the
+/// P&L of a single project. Lines without the dimension have a null value.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DimensionIncomeStatement {
+    pub dimension_value: Option<String>,
+    pub revenue: Decimal,
+    pub expenses: Decimal,
+    pub net_income: Decimal,
+    pub accounts: Vec<AccountItem>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IncomeStatementSection {
     pub title: String,
@@ -86,6 +102,43 @@ pub struct TrialBalanceAccount {
     pub credit_balance: Decimal,
 }
 
+/// Budgeted against posted amounts per revenue and expense account, in each
+/// account's natural direction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetVsActualReport {
+    pub company_id: Uuid,
+    pub budget_id: Uuid,
+    pub budget_name: String,
+    pub budget_version: i32,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub filter: DimensionFilter,
+    pub lines: Vec<BudgetVsActualLine>,
+    pub revenue_budget: Decimal,
+    pub revenue_actual: Decimal,
+    pub expense_budget: Decimal,
+    pub expense_actual: Decimal,
+    pub net_income_budget: Decimal,
+    pub net_income_actual: Decimal,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetVsActualLine {
+    pub account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub account_type: String,
+    pub budget_amount: Decimal,
+    pub actual_amount: Decimal,
+    /// Actual minus budget
+    pub variance: Decimal,
+    /// None when nothing was budgeted
+    pub variance_percent: Option<Decimal>,
+    /// Revenue above budget or expense below it
+    pub is_favorable: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaxReport {
     pub company_id: Uuid,
@@ -1,9 +1,10 @@
 use crate::models::*;
 use common::{ServiceResult, ServiceError};
 use chrono::NaiveDate;
+use database::budgets::{budget_for_period, BudgetAmount};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
 pub struct FinancialReportService {
@@ -31,9 +32,15 @@ impl FinancialReportService {
                 a.account_subtype as "account_subtype: Option<String>",
                 COALESCE(ab.ending_balance, 0) as balance
             FROM accounts a
-            LEFT JOIN account_balances ab ON a.id = ab.account_id 
-                AND ab.balance_date <= $1
-                AND ab.company_id = $2
+            LEFT JOIN LATERAL (
+                SELECT b.ending_balance
+                FROM account_balances b
+                WHERE b.account_id = a.id
+                    AND b.balance_date <= $1
+                    AND b.company_id = $2
+                ORDER BY b.balance_date DESC
+                LIMIT 1
+            ) ab ON true
             WHERE a.company_id = $2 
                 AND a.is_active = true
                 AND a.account_type IN ('ASSET', 'LIABILITY', 'EQUITY')
@@ -132,11 +139,15 @@ impl FinancialReportService {
         })
     }
 
+    /// Income statement for a period, optionally restricted to lines tagged
+    /// with specific dimension values and broken down by one dimension.
     pub async fn generate_income_statement(
         &self,
         company_id: Uuid,
         start_date: NaiveDate,
         end_date: NaiveDate,
+        filter: DimensionFilter,
+        pivot: Option<DimensionType>,
     ) -> ServiceResult<IncomeStatementReport> {
         let account_balances = sqlx::query!(
             r#"
@@ -146,6 +157,11 @@ impl FinancialReportService {
                 a.account_name,
                 a.account_type as "account_type: String",
                 a.account_subtype as "account_subtype: Option<String>",
+                CASE $4::text
+                    WHEN 'DEPARTMENT' THEN jel.department
+                    WHEN 'PROJECT' THEN jel.project_code
+                    WHEN 'COST_CENTER' THEN jel.cost_center
+                END as dimension_value,
                 SUM(
                     CASE 
                         WHEN a.account_type = 'REVENUE' THEN jel.credit_amount - jel.debit_amount
@@ -162,12 +178,20 @@ impl FinancialReportService {
                 AND je.entry_date >= $2 
                 AND je.entry_date <= $3
                 AND je.is_posted = true
-            GROUP BY a.id, a.account_code, a.account_name, a.account_type, a.account_subtype
-            ORDER BY a.account_code
+                AND COALESCE(je.source_document_type, '') <> 'YEAR_END_CLOSE'
+                AND ($5::text IS NULL OR jel.department = $5)
+                AND ($6::text IS NULL OR jel.project_code = $6)
+                AND ($7::text IS NULL OR jel.cost_center = $7)
+            GROUP BY a.id, a.account_code, a.account_name, a.account_type, a.account_subtype, 6
+            ORDER BY a.account_code, 6
             "#,
             company_id,
             start_date,
-            end_date
+            end_date,
+            pivot.map(|p| p.to_string()),
+            filter.department.as_deref(),
+            filter.project_code.as_deref(),
+            filter.cost_center.as_deref()
         )
         .fetch_all(&self.db)
         .await
@@ -185,42 +209,61 @@ impl FinancialReportService {
             subsections: HashMap::new(),
         };
 
+        let mut by_dimension: BTreeMap<Option<String>, DimensionIncomeStatement> = BTreeMap::new();
+
         // Process account balances
         for row in account_balances {
             let net_amount = row.net_amount.unwrap_or(Decimal::ZERO);
-            let account_item = AccountItem {
-                account_id: row.id,
-                account_code: row.account_code,
-                account_name: row.account_name,
-                balance: net_amount,
+
+            if pivot.is_some() {
+                let group = by_dimension.entry(row.dimension_value.clone()).or_insert_with(|| {
+                    DimensionIncomeStatement {
+                        dimension_value: row.dimension_value.clone(),
+                        revenue: Decimal::ZERO,
+                        expenses: Decimal::ZERO,
+                        net_income: Decimal::ZERO,
+                        accounts: Vec::new(),
+                    }
+                });
+                match row.account_type.as_str() {
+                    "REVENUE" => group.revenue += net_amount,
+                    "EXPENSE" => group.expenses += net_amount,
+                    _ => {}
+                }
+                group.net_income = group.revenue - group.expenses;
+                group.accounts.push(AccountItem {
+                    account_id: row.id,
+                    account_code: row.account_code.clone(),
+                    account_name: row.account_name.clone(),
+                    balance: net_amount,
+                });
+            }
+
+            let (section, default_subtype) = match row.account_type.as_str() {
+                "REVENUE" => (&mut revenue, "OPERATING_REVENUE"),
+                "EXPENSE" => (&mut expenses, "OPERATING_EXPENSE"),
+                _ => continue,
             };
 
-            match row.account_type.as_str() {
-                "REVENUE" => {
-                    let subtype = row.account_subtype.unwrap_or("OPERATING_REVENUE".to_string());
-                    let subsection = revenue.subsections.entry(subtype.clone()).or_insert_with(|| {
-                        IncomeStatementSubsection {
-                            title: Self::get_indonesian_account_subtype_name(&subtype),
-                            accounts: Vec::new(),
-                            total: Decimal::ZERO,
-                        }
-                    });
-                    subsection.total += account_item.balance;
-                    subsection.accounts.push(account_item);
-                },
-                "EXPENSE" => {
-                    let subtype = row.account_subtype.unwrap_or("OPERATING_EXPENSE".to_string());
-                    let subsection = expenses.subsections.entry(subtype.clone()).or_insert_with(|| {
-                        IncomeStatementSubsection {
-                            title: Self::get_indonesian_account_subtype_name(&subtype),
-                            accounts: Vec::new(),
-                            total: Decimal::ZERO,
-                        }
-                    });
-                    subsection.total += account_item.balance;
-                    subsection.accounts.push(account_item);
-                },
-                _ => {}
+            let subtype = row.account_subtype.unwrap_or(default_subtype.to_string());
+            let subsection = section.subsections.entry(subtype.clone()).or_insert_with(|| {
+                IncomeStatementSubsection {
+                    title: Self::get_indonesian_account_subtype_name(&subtype),
+                    accounts: Vec::new(),
+                    total: Decimal::ZERO,
+                }
+            });
+            subsection.total += net_amount;
+
+            // Pivoted rows repeat an account once per dimension value
+            match subsection.accounts.last_mut() {
+                Some(item) if item.account_id == row.id => item.balance += net_amount,
+                _ => subsection.accounts.push(AccountItem {
+                    account_id: row.id,
+                    account_code: row.account_code,
+                    account_name: row.account_name,
+                    balance: net_amount,
+                }),
             }
         }
 
@@ -235,10 +278,13 @@ impl FinancialReportService {
             company_id,
             period_start: start_date,
             period_end: end_date,
+            filter,
+            pivot,
             revenue,
             expenses,
             gross_profit,
             net_income,
+            by_dimension: by_dimension.into_values().collect(),
             generated_at: chrono::Utc::now(),
         })
     }
@@ -319,6 +365,192 @@ impl FinancialReportService {
         })
     }
 
+    /// Budget against actuals from `start_date` to `end_date`. Without a
+    /// `budget_id` the active budget covering the period is used. A dimension
+    /// filter keeps only budget lines set for exactly that dimension value;
+    /// budgets are not kept by cost center, so that filter is rejected.
+    /// Nested budget lines count once and partial months are prorated by day.
+    pub async fn generate_budget_vs_actual(
+        &self,
+        company_id: Uuid,
+        budget_id: Option<Uuid>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        filter: DimensionFilter,
+    ) -> ServiceResult<BudgetVsActualReport> {
+        if filter.cost_center.is_some() {
+            return Err(ServiceError::Validation(
+                "Budgets are not kept by cost center; filter by department or project instead".to_string()
+            ));
+        }
+
+        let budget = sqlx::query!(
+            r#"
+            SELECT b.id, b.budget_name, b.version
+            FROM budgets b
+            WHERE b.company_id = $1
+              AND (b.id = $2 OR ($2::uuid IS NULL AND b.status = 'ACTIVE' AND EXISTS (
+                  SELECT 1 FROM budget_lines bl
+                  WHERE bl.budget_id = b.id
+                    AND bl.period_start BETWEEN DATE_TRUNC('month', $3::date)::date AND $4
+              )))
+            ORDER BY b.fiscal_year DESC
+            LIMIT 1
+            "#,
+            company_id,
+            budget_id,
+            start_date,
+            end_date
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(ServiceError::Database)?
+        .ok_or_else(|| ServiceError::NotFound("No budget found for this period".to_string()))?;
+
+        let budgeted = sqlx::query!(
+            r#"
+            SELECT a.id, a.account_code, a.account_name, a.account_type as "account_type: String",
+                   bl.period_start, bl.department, bl.project_code, bl.amount
+            FROM budget_lines bl
+            JOIN accounts a ON a.id = bl.account_id
+            WHERE bl.budget_id = $1
+                AND a.account_type IN ('REVENUE', 'EXPENSE')
+                AND bl.period_start BETWEEN DATE_TRUNC('month', $2::date)::date AND $3
+                AND ($4::text IS NULL OR bl.department = $4)
+                AND ($5::text IS NULL OR bl.project_code = $5)
+            "#,
+            budget.id,
+            start_date,
+            end_date,
+            filter.department.as_deref(),
+            filter.project_code.as_deref()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let actuals = sqlx::query!(
+            r#"
+            SELECT 
+                a.id,
+                a.account_code,
+                a.account_name,
+                a.account_type as "account_type: String",
+                SUM(
+                    CASE 
+                        WHEN a.account_type = 'REVENUE' THEN jel.credit_amount - jel.debit_amount
+                        ELSE jel.debit_amount - jel.credit_amount
+                    END
+                ) as "amount!"
+            FROM accounts a
+            JOIN journal_entry_lines jel ON a.id = jel.account_id
+            JOIN journal_entries je ON jel.journal_entry_id = je.id
+            WHERE a.company_id = $1 
+                AND a.account_type IN ('REVENUE', 'EXPENSE')
+                AND je.entry_date >= $2 
+                AND je.entry_date <= $3
+                AND je.is_posted = true
+                AND COALESCE(je.source_document_type, '') <> 'YEAR_END_CLOSE'
+                AND ($4::text IS NULL OR jel.department = $4)
+                AND ($5::text IS NULL OR jel.project_code = $5)
+            GROUP BY a.id, a.account_code, a.account_name, a.account_type
+            "#,
+            company_id,
+            start_date,
+            end_date,
+            filter.department.as_deref(),
+            filter.project_code.as_deref()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        let mut lines: BTreeMap<String, BudgetVsActualLine> = BTreeMap::new();
+        let blank_line = |id, code: &str, name: &str, account_type: &str| BudgetVsActualLine {
+            account_id: id,
+            account_code: code.to_string(),
+            account_name: name.to_string(),
+            account_type: account_type.to_string(),
+            budget_amount: Decimal::ZERO,
+            actual_amount: Decimal::ZERO,
+            variance: Decimal::ZERO,
+            variance_percent: None,
+            is_favorable: true,
+        };
+
+        let budget_amounts: Vec<BudgetAmount> = budgeted
+            .iter()
+            .map(|row| BudgetAmount {
+                account_id: row.id,
+                period_start: row.period_start,
+                department: row.department.clone(),
+                project_code: row.project_code.clone(),
+                amount: row.amount,
+            })
+            .collect();
+        let budget_by_account = budget_for_period(&budget_amounts, start_date, end_date);
+
+        for row in &budgeted {
+            if let Some(amount) = budget_by_account.get(&row.id) {
+                lines.entry(row.account_code.clone())
+                    .or_insert_with(|| blank_line(row.id, &row.account_code, &row.account_name, &row.account_type))
+                    .budget_amount = *amount;
+            }
+        }
+        for row in actuals {
+            lines.entry(row.account_code.clone())
+                .or_insert_with(|| blank_line(row.id, &row.account_code, &row.account_name, &row.account_type))
+                .actual_amount += row.amount;
+        }
+
+        let mut revenue_budget = Decimal::ZERO;
+        let mut revenue_actual = Decimal::ZERO;
+        let mut expense_budget = Decimal::ZERO;
+        let mut expense_actual = Decimal::ZERO;
+
+        let lines: Vec<BudgetVsActualLine> = lines
+            .into_values()
+            .map(|mut line| {
+                line.variance = line.actual_amount - line.budget_amount;
+                line.variance_percent = if line.budget_amount.is_zero() {
+                    None
+                } else {
+                    Some((line.variance / line.budget_amount * Decimal::ONE_HUNDRED).round_dp(2))
+                };
+
+                if line.account_type == "REVENUE" {
+                    line.is_favorable = line.variance >= Decimal::ZERO;
+                    revenue_budget += line.budget_amount;
+                    revenue_actual += line.actual_amount;
+                } else {
+                    line.is_favorable = line.variance <= Decimal::ZERO;
+                    expense_budget += line.budget_amount;
+                    expense_actual += line.actual_amount;
+                }
+
+                line
+            })
+            .collect();
+
+        Ok(BudgetVsActualReport {
+            company_id,
+            budget_id: budget.id,
+            budget_name: budget.budget_name,
+            budget_version: budget.version,
+            period_start: start_date,
+            period_end: end_date,
+            filter,
+            lines,
+            revenue_budget,
+            revenue_actual,
+            expense_budget,
+            expense_actual,
+            net_income_budget: revenue_budget - expense_budget,
+            net_income_actual: revenue_actual - expense_actual,
+            generated_at: chrono::Utc::now(),
+        })
+    }
+
     fn get_indonesian_account_subtype_name(subtype: &str) -> String {
         match subtype {
             "CURRENT_ASSET" => "Aset Lancar".to_string(),
@@ -76,6 +76,9 @@ impl ServiceRegistry {
         if let Some(company_id) = headers.get("X-Company-ID") {
             request = request.header("X-Company-ID", company_id);
         }
+        if let Some(identity) = headers.get(common::identity::IDENTITY_HEADER) {
+            request = request.header(common::identity::IDENTITY_HEADER, identity);
+        }
         if let Some(auth) = headers.get("Authorization") {
             request = request.header("Authorization", auth);
         }
@@ -1,6 +1,6 @@
 use axum::http::{HeaderMap, StatusCode};
 use uuid::Uuid;
-use crate::ServiceError;
+use crate::{ServiceError, identity::{Identity, IDENTITY_HEADER}};
 
 pub fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, ServiceError> {
     headers
@@ -16,4 +16,37 @@ pub fn extract_company_id(headers: &HeaderMap) -> Result<Uuid, ServiceError> {
         .and_then(|h| h.to_str().ok())
         .and_then(|s| Uuid::parse_str(s).ok())
         .ok_or_else(|| ServiceError::Authentication("Missing or invalid company ID".to_string()))
-}
\ No newline at end of file
+}
+
+/// Identity of the caller as verified by the gateway. The token has to name
+/// the same user and company as the plain headers.
+pub fn extract_identity(headers: &HeaderMap) -> Result<Identity, ServiceError> {
+    let token = headers
+        .get(IDENTITY_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| ServiceError::Authentication("Missing identity token".to_string()))?;
+    let identity = Identity::verify(token)?;
+
+    if identity.sub != extract_user_id(headers)? || identity.company_id != extract_company_id(headers)? {
+        return Err(ServiceError::Authentication("Identity token does not match the request".to_string()));
+    }
+
+    Ok(identity)
+}
+
+/// Roles of the calling user, taken from the signed identity token. Without
+/// a valid token the caller has no roles.
+pub fn extract_user_roles(headers: &HeaderMap) -> Vec<String> {
+    extract_identity(headers)
+        .map(|identity| identity.roles)
+        .unwrap_or_default()
+}
+
+/// Rejects requests touching a company the caller has no access to.
+pub fn ensure_company_access(headers: &HeaderMap, company_id: Uuid) -> Result<(), ServiceError> {
+    if extract_identity(headers)?.can_access(company_id) {
+        Ok(())
+    } else {
+        Err(ServiceError::Authorization("No access to the requested company".to_string()))
+    }
+}
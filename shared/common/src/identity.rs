@@ -0,0 +1,121 @@
+//! Verified caller identity passed between services
+//!
+//! The API gateway authenticates the user's access token, looks up their
+//! roles with the auth service and forwards a short-lived identity token
+//! signed with the internal secret. Services read roles and accessible
+//! companies only from that token, never from headers the client could set
+//! itself. Services calling each other on behalf of a user sign a new token
+//! from the identity they were given.
+
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::ServiceError;
+
+/// Header carrying the signed identity token.
+pub const IDENTITY_HEADER: &str = "X-Identity-Token";
+
+/// Headers describing the caller. The gateway strips them from client
+/// requests and sets them itself.
+pub const IDENTITY_HEADERS: &[&str] = &["X-User-ID", "X-Company-ID", "X-User-Roles", IDENTITY_HEADER];
+
+/// User that scheduled jobs act as when they call another service; there is
+/// no request user to forward.
+pub const SYSTEM_USER_ID: Uuid = Uuid::nil();
+
+/// How long an identity token stays valid, in seconds.
+const IDENTITY_TTL_SECONDS: i64 = 300;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Identity {
+    /// User the request is made for
+    pub sub: Uuid,
+    /// Company the request acts in
+    pub company_id: Uuid,
+    /// Companies the user may act in, including `company_id`
+    pub company_ids: Vec<Uuid>,
+    /// Roles the user holds in `company_id`
+    pub roles: Vec<String>,
+    pub exp: i64,
+}
+
+impl Identity {
+    pub fn new(user_id: Uuid, company_id: Uuid, company_ids: Vec<Uuid>, roles: Vec<String>) -> Self {
+        let mut company_ids = company_ids;
+        if !company_ids.contains(&company_id) {
+            company_ids.push(company_id);
+        }
+
+        Self {
+            sub: user_id,
+            company_id,
+            company_ids,
+            roles: roles.into_iter().map(|role| role.trim().to_uppercase()).collect(),
+            exp: Utc::now().timestamp() + IDENTITY_TTL_SECONDS,
+        }
+    }
+
+    pub fn can_access(&self, company_id: Uuid) -> bool {
+        self.company_ids.contains(&company_id)
+    }
+
+    /// Signs the identity for forwarding in `IDENTITY_HEADER`.
+    pub fn sign(&self) -> Result<String, ServiceError> {
+        encode(
+            &Header::new(Algorithm::HS256),
+            self,
+            &EncodingKey::from_secret(identity_secret()?.as_bytes()),
+        )
+        .map_err(|e| ServiceError::Internal(format!("Failed to sign identity token: {}", e)))
+    }
+
+    /// Verifies the signature and expiry of an identity token.
+    pub fn verify(token: &str) -> Result<Self, ServiceError> {
+        decode::<Identity>(
+            token,
+            &DecodingKey::from_secret(identity_secret()?.as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        )
+        .map(|data| data.claims)
+        .map_err(|_| ServiceError::Authentication("Invalid identity token".to_string()))
+    }
+}
+
+/// Signed identity for a call one service makes to another on behalf of a
+/// user, limited to the company the call is for.
+pub fn forward(user_id: Uuid, company_id: Uuid, roles: &[String]) -> Result<String, ServiceError> {
+    Identity::new(user_id, company_id, vec![company_id], roles.to_vec()).sign()
+}
+
+/// Secret shared by the gateway and the services. Falls back to the access
+/// token secret for deployments that only configure one.
+fn identity_secret() -> Result<String, ServiceError> {
+    std::env::var("INTERNAL_IDENTITY_SECRET")
+        .or_else(|_| std::env::var("JWT_SECRET"))
+        .map_err(|_| ServiceError::Internal("INTERNAL_IDENTITY_SECRET is not configured".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_round_trip() {
+        std::env::set_var("INTERNAL_IDENTITY_SECRET", "test-identity-secret");
+        let company_id = Uuid::new_v4();
+        let identity = Identity::new(Uuid::new_v4(), company_id, vec![], vec![" controller ".to_string()]);
+
+        let token = identity.sign().unwrap();
+        let verified = Identity::verify(&token).unwrap();
+        assert_eq!(verified, identity);
+        assert_eq!(verified.roles, vec!["CONTROLLER".to_string()]);
+        assert!(verified.can_access(company_id));
+        assert!(!verified.can_access(Uuid::new_v4()));
+
+        // Roles cannot be changed without the secret
+        let mut parts: Vec<String> = token.split('.').map(str::to_string).collect();
+        parts[1] = parts[1].chars().rev().collect();
+        assert!(Identity::verify(&parts.join(".")).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 pub mod types;
 pub mod errors;
 pub mod extractors;
+pub mod identity;
 pub mod health;
 pub mod config;
 
@@ -0,0 +1,234 @@
+//! Remaining-budget checks shared by every service that books expenses
+//!
+//! Budgets are kept per account and month, optionally narrowed to a
+//! department or project. A budget line without a department or project
+//! covers all of them. Checks only run for companies that switched on
+//! `budget_warnings` in their ledger settings, and only against the ACTIVE
+//! budget version; they never block a posting.
+
+use chrono::{Datelike, Months, NaiveDate};
+use common::{ServiceError, ServiceResult};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Spending about to be committed on an expense account.
+#[derive(Debug, Clone)]
+pub struct BudgetCheckLine {
+    pub account_id: Uuid,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BudgetWarning {
+    pub budget_line_id: Uuid,
+    pub account_id: Uuid,
+    pub account_code: String,
+    pub account_name: String,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub period_start: NaiveDate,
+    pub budget_amount: Decimal,
+    pub actual_amount: Decimal,
+    pub requested_amount: Decimal,
+    /// Negative: the amount by which the budget would be exceeded
+    pub remaining_after: Decimal,
+}
+
+/// A budget line's amount for one account and month.
+#[derive(Debug, Clone)]
+pub struct BudgetAmount {
+    pub account_id: Uuid,
+    pub period_start: NaiveDate,
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub amount: Decimal,
+}
+
+/// First day of the month containing `date`, the key budget lines are stored under.
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Budget per account for `start` to `end`. Within an account and month only
+/// the most general lines count: a line without a department or project
+/// already covers the narrower lines beneath it, so they are not added to
+/// it. Months partly inside the range count by the share of their days
+/// inside it.
+pub fn budget_for_period(lines: &[BudgetAmount], start: NaiveDate, end: NaiveDate) -> HashMap<Uuid, Decimal> {
+    // Department lines rank before project lines when both exist
+    let level = |line: &BudgetAmount| match (line.department.is_some(), line.project_code.is_some()) {
+        (false, false) => 0,
+        (true, false) => 1,
+        (false, true) => 2,
+        (true, true) => 3,
+    };
+
+    let mut months: HashMap<(Uuid, NaiveDate), (u8, Decimal)> = HashMap::new();
+    for line in lines {
+        let key = (line.account_id, month_start(line.period_start));
+        let line_level = level(line);
+        let entry = months.entry(key).or_insert((line_level, Decimal::ZERO));
+        if line_level < entry.0 {
+            *entry = (line_level, line.amount);
+        } else if line_level == entry.0 {
+            entry.1 += line.amount;
+        }
+    }
+
+    let mut budget: HashMap<Uuid, Decimal> = HashMap::new();
+    for ((account_id, month), (_, amount)) in months {
+        let next_month = month.checked_add_months(Months::new(1)).unwrap_or(month);
+        let days_in_month = (next_month - month).num_days();
+        let from = start.max(month);
+        let to = end.min(next_month.pred_opt().unwrap_or(month));
+        let days_inside = ((to - from).num_days() + 1).max(0);
+        if days_in_month == 0 || days_inside == 0 {
+            continue;
+        }
+
+        *budget.entry(account_id).or_insert(Decimal::ZERO) +=
+            amount * Decimal::from(days_inside) / Decimal::from(days_in_month);
+    }
+
+    for amount in budget.values_mut() {
+        *amount = amount.round_dp(2);
+    }
+
+    budget
+}
+
+/// Budget lines of the active budget that `lines` would push over their
+/// amount in the month of `date`, counting posted spending so far.
+pub async fn budget_warnings(
+    db: &PgPool,
+    company_id: Uuid,
+    date: NaiveDate,
+    lines: &[BudgetCheckLine],
+) -> ServiceResult<Vec<BudgetWarning>> {
+    let enabled = sqlx::query_scalar!(
+        r#"SELECT COALESCE(budget_warnings, false) as "enabled!" FROM ledger_settings WHERE company_id = $1"#,
+        company_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(ServiceError::Database)?
+    .unwrap_or(false);
+
+    if !enabled {
+        return Ok(Vec::new());
+    }
+
+    let period_start = month_start(date);
+    let mut requested: HashMap<Uuid, Decimal> = HashMap::new();
+    let mut warnings: Vec<BudgetWarning> = Vec::new();
+
+    for line in lines.iter().filter(|l| l.amount > Decimal::ZERO) {
+        // Every budget line covering this account and these dimensions
+        let budget_lines = sqlx::query!(
+            r#"
+            SELECT bl.id, bl.account_id, a.account_code, a.account_name, bl.department, bl.project_code,
+                   bl.amount,
+                   COALESCE((
+                       SELECT SUM(jel.debit_amount - jel.credit_amount)
+                       FROM journal_entry_lines jel
+                       JOIN journal_entries je ON je.id = jel.journal_entry_id
+                       WHERE je.company_id = b.company_id AND jel.account_id = bl.account_id
+                         AND je.status = 'POSTED'
+                         AND je.entry_date >= bl.period_start
+                         AND je.entry_date < bl.period_start + INTERVAL '1 month'
+                         AND (bl.department IS NULL OR jel.department = bl.department)
+                         AND (bl.project_code IS NULL OR jel.project_code = bl.project_code)
+                   ), 0) as "actual!"
+            FROM budget_lines bl
+            JOIN budgets b ON b.id = bl.budget_id
+            JOIN accounts a ON a.id = bl.account_id
+            WHERE b.company_id = $1 AND b.status = 'ACTIVE' AND a.account_type = 'EXPENSE'
+              AND bl.account_id = $2 AND bl.period_start = $3
+              AND (bl.department IS NULL OR bl.department = $4)
+              AND (bl.project_code IS NULL OR bl.project_code = $5)
+            "#,
+            company_id,
+            line.account_id,
+            period_start,
+            line.department,
+            line.project_code
+        )
+        .fetch_all(db)
+        .await
+        .map_err(ServiceError::Database)?;
+
+        for budget_line in budget_lines {
+            let requested_amount = {
+                let total = requested.entry(budget_line.id).or_insert(Decimal::ZERO);
+                *total += line.amount;
+                *total
+            };
+            let remaining_after = budget_line.amount - budget_line.actual - requested_amount;
+
+            warnings.retain(|w| w.budget_line_id != budget_line.id);
+            if remaining_after < Decimal::ZERO {
+                warnings.push(BudgetWarning {
+                    budget_line_id: budget_line.id,
+                    account_id: budget_line.account_id,
+                    account_code: budget_line.account_code,
+                    account_name: budget_line.account_name,
+                    department: budget_line.department,
+                    project_code: budget_line.project_code,
+                    period_start,
+                    budget_amount: budget_line.amount,
+                    actual_amount: budget_line.actual,
+                    requested_amount,
+                    remaining_after,
+                });
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(account_id: Uuid, month: u32, department: Option<&str>, project_code: Option<&str>, amount: i64) -> BudgetAmount {
+        BudgetAmount {
+            account_id,
+            period_start: NaiveDate::from_ymd_opt(2024, month, 1).unwrap(),
+            department: department.map(str::to_string),
+            project_code: project_code.map(str::to_string),
+            amount: Decimal::from(amount),
+        }
+    }
+
+    #[test]
+    fn test_budget_for_period_counts_nested_lines_once_and_prorates() {
+        let rent = Uuid::new_v4();
+        let travel = Uuid::new_v4();
+        let lines = vec![
+            // Company-wide rent already includes the department split
+            amount(rent, 4, None, None, 3_000_000),
+            amount(rent, 4, Some("OPS"), None, 2_000_000),
+            amount(rent, 4, Some("OPS"), Some("P1"), 500_000),
+            // Travel is only budgeted by department
+            amount(travel, 4, Some("OPS"), None, 600_000),
+            amount(travel, 4, Some("SALES"), None, 900_000),
+            amount(travel, 5, Some("OPS"), None, 310_000),
+        ];
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        let full = budget_for_period(&lines, date(4, 1), date(4, 30));
+        assert_eq!(full[&rent], Decimal::from(3_000_000));
+        assert_eq!(full[&travel], Decimal::from(1_500_000));
+
+        // Second half of April and the first 10 days of May
+        let partial = budget_for_period(&lines, date(4, 16), date(5, 10));
+        assert_eq!(partial[&rent], Decimal::from(1_500_000));
+        assert_eq!(partial[&travel], Decimal::from(750_000 + 100_000));
+    }
+}
@@ -0,0 +1,155 @@
+//! Analytical dimensions (department, project, cost center) carried on
+//! journal and invoice lines, and their per-company master lists
+
+use common::{ServiceError, ServiceResult};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DimensionType {
+    Department,
+    Project,
+    CostCenter,
+}
+
+impl std::str::FromStr for DimensionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DEPARTMENT" => Ok(DimensionType::Department),
+            "PROJECT" | "PROJECT_CODE" => Ok(DimensionType::Project),
+            "COST_CENTER" => Ok(DimensionType::CostCenter),
+            _ => Err(format!("Invalid dimension type: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for DimensionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DimensionType::Department => write!(f, "DEPARTMENT"),
+            DimensionType::Project => write!(f, "PROJECT"),
+            DimensionType::CostCenter => write!(f, "COST_CENTER"),
+        }
+    }
+}
+
+/// Restricts a report to lines tagged with the given dimension values.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DimensionFilter {
+    pub department: Option<String>,
+    pub project_code: Option<String>,
+    pub cost_center: Option<String>,
+}
+
+impl DimensionFilter {
+    /// Reads `department`, `project_code` and `cost_center` query parameters.
+    pub fn from_params(params: &HashMap<String, String>) -> Self {
+        let value = |key: &str| params.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        Self {
+            department: value("department"),
+            project_code: value("project_code"),
+            cost_center: value("cost_center"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.department.is_none() && self.project_code.is_none() && self.cost_center.is_none()
+    }
+}
+
+/// Reads the optional `pivot` query parameter naming the dimension to group by.
+pub fn pivot_from_params(params: &HashMap<String, String>) -> ServiceResult<Option<DimensionType>> {
+    params.get("pivot")
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| p.parse::<DimensionType>().map_err(ServiceError::Validation))
+        .transpose()
+}
+
+/// Department, project and cost center tagged on a line, leaving out blank
+/// values, as checked by `ensure_dimensions_exist`.
+pub fn line_values(
+    department: Option<&str>,
+    project_code: Option<&str>,
+    cost_center: Option<&str>,
+) -> Vec<(DimensionType, String)> {
+    [
+        (DimensionType::Department, department),
+        (DimensionType::Project, project_code),
+        (DimensionType::CostCenter, cost_center),
+    ]
+    .into_iter()
+    .filter_map(|(dimension_type, value)| {
+        value.filter(|v| !v.trim().is_empty()).map(|v| (dimension_type, v.to_string()))
+    })
+    .collect()
+}
+
+/// Rejects dimension values that are not active entries of the company's
+/// master lists.
+pub async fn ensure_dimensions_exist<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    values: &[(DimensionType, String)],
+) -> ServiceResult<()>
+where
+    E: PgExecutor<'e>,
+{
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let types: Vec<String> = values.iter().map(|(t, _)| t.to_string()).collect();
+    let codes: Vec<String> = values.iter().map(|(_, c)| c.clone()).collect();
+
+    let unknown = sqlx::query!(
+        r#"
+        SELECT DISTINCT t.dimension_type as "dimension_type!", t.code as "code!"
+        FROM UNNEST($2::text[], $3::text[]) AS t(dimension_type, code)
+        WHERE NOT EXISTS (
+            SELECT 1 FROM dimension_values dv
+            WHERE dv.company_id = $1
+              AND dv.dimension_type = t.dimension_type
+              AND dv.code = t.code
+              AND dv.is_active = true
+        )
+        ORDER BY 1, 2
+        "#,
+        company_id,
+        &types,
+        &codes
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    Err(ServiceError::Validation(format!(
+        "Unknown or inactive dimension values: {}",
+        unknown
+            .iter()
+            .map(|row| format!("{} {}", row.dimension_type, row.code))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_values_skip_blank() {
+        let values = line_values(Some("FIN"), Some("  "), None);
+        assert_eq!(values, vec![(DimensionType::Department, "FIN".to_string())]);
+        assert!(line_values(None, None, None).is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Per-company exchange rates into the functional currency, shared by every
+//! service that converts foreign-currency amounts
+//!
+//! Rates are stored per currency, rate type and effective date. A lookup
+//! uses the latest rate effective on or before the requested date, so weekly
+//! or irregular rate tables work without filling every day.
+//!
+//! The table is maintained in the general ledger. Other services ask the
+//! ledger for a [`RateTable`] instead of keeping their own copy.
+
+use chrono::NaiveDate;
+use common::{ServiceError, ServiceResult};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use std::collections::HashMap;
+use utils::CurrencyUtils;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RateType {
+    /// Market rate on the transaction date
+    Spot,
+    /// Bank Indonesia middle rate (JISDOR), used for accounting conversions
+    BiMiddle,
+    /// Weekly KMK rate set by the Minister of Finance for tax bases
+    Tax,
+}
+
+impl std::str::FromStr for RateType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "SPOT" => Ok(RateType::Spot),
+            "BI_MIDDLE" | "BI" | "JISDOR" => Ok(RateType::BiMiddle),
+            "TAX" | "KMK" => Ok(RateType::Tax),
+            _ => Err(format!("Invalid rate type: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for RateType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateType::Spot => write!(f, "SPOT"),
+            RateType::BiMiddle => write!(f, "BI_MIDDLE"),
+            RateType::Tax => write!(f, "TAX"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExchangeRate {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub currency: String,
+    pub rate_type: String,
+    pub effective_date: NaiveDate,
+    pub rate: Decimal,
+    pub source: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Rates of one type in effect on a date, for stating foreign-currency
+/// balances in the functional currency.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RateTable {
+    pub functional_currency: String,
+    pub rate_type: RateType,
+    pub as_of_date: NaiveDate,
+    /// Keyed by upper-case currency code; currencies without a rate are absent
+    pub rates: HashMap<String, Decimal>,
+}
+
+impl RateTable {
+    /// Rate into the functional currency; one for the functional currency
+    /// itself and for amounts without a currency.
+    pub fn rate_for(&self, currency: Option<&str>) -> Option<Decimal> {
+        match currency {
+            Some(c) if !c.eq_ignore_ascii_case(&self.functional_currency) => {
+                self.rates.get(&c.to_uppercase()).copied()
+            }
+            _ => Some(Decimal::ONE),
+        }
+    }
+
+    /// `amount` in the functional currency, or `None` when the table has no
+    /// rate for its currency.
+    pub fn to_functional(&self, amount: Decimal, currency: Option<&str>) -> Option<Decimal> {
+        self.rate_for(currency)
+            .map(|rate| CurrencyUtils::round_to_currency(amount * rate, &self.functional_currency))
+    }
+
+    /// Tax base in the functional currency of an amount in `currency`. Tax
+    /// bases use the weekly KMK rate, so this must be a TAX rate table.
+    pub fn tax_base(&self, amount: Decimal, currency: Option<&str>) -> ServiceResult<Decimal> {
+        if self.rate_type != RateType::Tax {
+            return Err(ServiceError::Internal(format!(
+                "Tax bases are converted at the KMK rate, not {}", self.rate_type
+            )));
+        }
+
+        self.to_functional(amount, currency).ok_or_else(|| ServiceError::Validation(format!(
+            "No KMK rate for {} on or before {}",
+            currency.unwrap_or_default().to_uppercase(),
+            self.as_of_date
+        )))
+    }
+}
+
+/// Distinct upper-case currency codes, for requesting a [`RateTable`].
+pub fn distinct_currencies<'a>(currencies: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut currencies: Vec<String> = currencies.into_iter().map(|c| c.trim().to_uppercase()).collect();
+    currencies.sort();
+    currencies.dedup();
+    currencies
+}
+
+/// The company's functional currency from `company_settings`, IDR when not
+/// configured.
+pub async fn functional_currency<'e, E>(executor: E, company_id: Uuid) -> ServiceResult<String>
+where
+    E: PgExecutor<'e>,
+{
+    let currency = sqlx::query_scalar!(
+        "SELECT default_currency FROM company_settings WHERE company_id = $1",
+        company_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)?
+    .flatten();
+
+    Ok(currency.unwrap_or_else(|| "IDR".to_string()))
+}
+
+/// The latest rate effective on or before `date`, if any.
+pub async fn find_rate<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    currency: &str,
+    rate_type: RateType,
+    date: NaiveDate,
+) -> ServiceResult<Option<ExchangeRate>>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as!(
+        ExchangeRate,
+        r#"
+        SELECT id, company_id, currency, rate_type, effective_date, rate, source, created_by,
+               created_at as "created_at!", updated_at as "updated_at!"
+        FROM exchange_rates
+        WHERE company_id = $1 AND currency = $2 AND rate_type = $3 AND effective_date <= $4
+        ORDER BY effective_date DESC
+        LIMIT 1
+        "#,
+        company_id,
+        currency.to_uppercase(),
+        rate_type.to_string(),
+        date
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)
+}
+
+/// Like [`find_rate`], but a missing rate is a validation error.
+pub async fn get_rate<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    currency: &str,
+    rate_type: RateType,
+    date: NaiveDate,
+) -> ServiceResult<Decimal>
+where
+    E: PgExecutor<'e>,
+{
+    find_rate(executor, company_id, currency, rate_type, date)
+        .await?
+        .map(|r| r.rate)
+        .ok_or_else(|| ServiceError::Validation(format!(
+            "No {} exchange rate for {} on or before {}",
+            rate_type, currency.to_uppercase(), date
+        )))
+}
+
+/// Rates for several currencies at once, keyed by currency code. Currencies
+/// without a rate on or before `date` are left out.
+pub async fn rates_as_of<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    currencies: &[String],
+    rate_type: RateType,
+    date: NaiveDate,
+) -> ServiceResult<HashMap<String, Decimal>>
+where
+    E: PgExecutor<'e>,
+{
+    if currencies.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let currencies: Vec<String> = currencies.iter().map(|c| c.to_uppercase()).collect();
+
+    let rates = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (currency) currency, rate
+        FROM exchange_rates
+        WHERE company_id = $1 AND currency = ANY($2) AND rate_type = $3 AND effective_date <= $4
+        ORDER BY currency, effective_date DESC
+        "#,
+        company_id,
+        &currencies,
+        rate_type.to_string(),
+        date
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(rates.into_iter().map(|r| (r.currency, r.rate)).collect())
+}
+
+/// Creates the rate for a currency, type and date, or replaces the existing one.
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_rate<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    currency: &str,
+    rate_type: RateType,
+    effective_date: NaiveDate,
+    rate: Decimal,
+    source: Option<&str>,
+    user_id: Uuid,
+) -> ServiceResult<ExchangeRate>
+where
+    E: PgExecutor<'e>,
+{
+    if rate <= Decimal::ZERO {
+        return Err(ServiceError::Validation(format!("Rate for {} must be positive", currency)));
+    }
+
+    sqlx::query_as!(
+        ExchangeRate,
+        r#"
+        INSERT INTO exchange_rates
+        (id, company_id, currency, rate_type, effective_date, rate, source, created_by, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+        ON CONFLICT (company_id, currency, rate_type, effective_date) DO UPDATE
+        SET rate = EXCLUDED.rate, source = EXCLUDED.source, updated_at = NOW()
+        RETURNING id, company_id, currency, rate_type, effective_date, rate, source, created_by,
+                  created_at as "created_at!", updated_at as "updated_at!"
+        "#,
+        Uuid::new_v4(),
+        company_id,
+        currency.trim().to_uppercase(),
+        rate_type.to_string(),
+        effective_date,
+        rate,
+        source,
+        user_id
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(ServiceError::Database)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_table_leaves_unrated_currencies_unconverted() {
+        let table = RateTable {
+            functional_currency: "IDR".to_string(),
+            rate_type: RateType::BiMiddle,
+            as_of_date: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+            rates: HashMap::from([("USD".to_string(), Decimal::new(16_375_5, 1))]),
+        };
+
+        assert_eq!(table.to_functional(Decimal::from(100), Some("usd")), Some(Decimal::from(1_637_550)));
+        assert_eq!(table.to_functional(Decimal::from(100), Some("IDR")), Some(Decimal::from(100)));
+        assert_eq!(table.to_functional(Decimal::from(100), None), Some(Decimal::from(100)));
+        assert_eq!(table.to_functional(Decimal::from(100), Some("SGD")), None);
+    }
+
+    #[test]
+    fn test_tax_base_uses_kmk_rate() {
+        let mut rates = RateTable {
+            functional_currency: "IDR".to_string(),
+            rate_type: RateType::Tax,
+            as_of_date: NaiveDate::from_ymd_opt(2024, 7, 3).unwrap(),
+            rates: HashMap::from([("USD".to_string(), Decimal::new(16_300, 0))]),
+        };
+
+        assert_eq!(rates.tax_base(Decimal::new(1_000, 0), Some("USD")).unwrap(), Decimal::new(16_300_000, 0));
+        assert_eq!(rates.tax_base(Decimal::new(1_000, 0), None).unwrap(), Decimal::new(1_000, 0));
+        assert!(rates.tax_base(Decimal::new(1_000, 0), Some("SGD")).is_err());
+
+        rates.rate_type = RateType::BiMiddle;
+        assert!(rates.tax_base(Decimal::new(1_000, 0), Some("USD")).is_err());
+    }
+}
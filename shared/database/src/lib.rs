@@ -6,6 +6,11 @@ use tracing::info;
 
 pub mod migrations;
 pub mod audit;
+pub mod periods;
+pub mod numbering;
+pub mod dimensions;
+pub mod exchange_rates;
+pub mod budgets;
 
 pub async fn create_database_pool(service_name: &str) -> anyhow::Result<PgPool> {
     let database_url_key = format!("{}_DATABASE_URL", service_name.to_uppercase().replace("-", "_"));
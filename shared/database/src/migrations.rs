@@ -79,6 +79,39 @@ async fn run_auth_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    // User company roles table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_company_roles (
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            company_id UUID NOT NULL,
+            role VARCHAR(50) NOT NULL,
+            granted_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            PRIMARY KEY (user_id, company_id, role)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Companies set up before roles were granted are administered by their
+    // first user
+    sqlx::query!(
+        r#"
+        INSERT INTO user_company_roles (user_id, company_id, role, created_at)
+        SELECT DISTINCT ON (u.company_id) u.id, u.company_id, 'ADMIN', NOW()
+        FROM users u
+        WHERE u.is_active = true
+          AND NOT EXISTS (
+              SELECT 1 FROM user_company_roles ucr WHERE ucr.company_id = u.company_id AND ucr.role = 'ADMIN'
+          )
+        ORDER BY u.company_id, u.created_at, u.id
+        "#
+    )
+    .execute(pool)
+    .await?;
+
     // Create indexes
     sqlx::query!("CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)")
         .execute(pool).await?;
@@ -288,10 +321,86 @@ async fn run_chart_migrations(pool: &PgPool) -> anyhow::Result<()> {
     Ok(())
 }
 
+// ===== DOCUMENT NUMBERING MIGRATIONS =====
+// Shared by every service that issues document numbers
+async fn run_numbering_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    // Document number formats table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS document_number_formats (
+            company_id UUID NOT NULL,
+            document_type VARCHAR(50) NOT NULL,
+            pattern VARCHAR(100) NOT NULL,
+            updated_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            PRIMARY KEY (company_id, document_type)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Customer documents are not numbered from document_sequences
+    sqlx::query!("DELETE FROM document_number_formats WHERE document_type IN ('CUSTOMER_INVOICE', 'CUSTOMER_PAYMENT')")
+        .execute(pool).await?;
+
+    // Document sequences table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS document_sequences (
+            company_id UUID NOT NULL,
+            document_type VARCHAR(50) NOT NULL,
+            period_key VARCHAR(20) NOT NULL,
+            last_value BIGINT NOT NULL DEFAULT 0,
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            PRIMARY KEY (company_id, document_type, period_key)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// ===== EXCHANGE RATE MIGRATIONS =====
+// Maintained by the general ledger, which serves rates to the other services
+async fn run_exchange_rate_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    // Exchange rates table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            currency VARCHAR(3) NOT NULL,
+            rate_type VARCHAR(20) NOT NULL,
+            effective_date DATE NOT NULL,
+            rate DECIMAL(18,6) NOT NULL CHECK (rate > 0),
+            source VARCHAR(100),
+            created_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, currency, rate_type, effective_date)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_exchange_rates_lookup ON exchange_rates(company_id, currency, rate_type, effective_date DESC)")
+        .execute(pool).await?;
+
+    Ok(())
+}
+
 // ===== GENERAL LEDGER MIGRATIONS =====
 async fn run_ledger_migrations(pool: &PgPool) -> anyhow::Result<()> {
     info!("Running general ledger migrations...");
 
+    run_numbering_migrations(pool).await?;
+    run_exchange_rate_migrations(pool).await?;
+
     // Create enums
     sqlx::query!(
         r#"
@@ -336,6 +445,30 @@ async fn run_ledger_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    // Entry numbers are issued per company, so only unique within one
+    sqlx::query!("ALTER TABLE journal_entries DROP CONSTRAINT IF EXISTS journal_entries_entry_number_key")
+        .execute(pool).await?;
+    sqlx::query!(
+        r#"
+        DO $$ BEGIN
+            ALTER TABLE journal_entries
+                ADD CONSTRAINT journal_entries_company_entry_number_key UNIQUE (company_id, entry_number);
+        EXCEPTION
+            WHEN duplicate_table OR duplicate_object THEN null;
+        END $$;
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Reversal tracking columns
+    sqlx::query!("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS auto_reverse_on DATE")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS reversed_by_entry_id UUID REFERENCES journal_entries(id)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS auto_reverse_error TEXT")
+        .execute(pool).await?;
+
     // Journal entry lines table
     sqlx::query!(
         r#"
@@ -357,6 +490,14 @@ async fn run_ledger_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    // Foreign-currency detail on journal lines; debit/credit stay in the functional currency
+    sqlx::query!("ALTER TABLE journal_entry_lines ADD COLUMN IF NOT EXISTS currency VARCHAR(3)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE journal_entry_lines ADD COLUMN IF NOT EXISTS foreign_amount DECIMAL(18,2)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE journal_entry_lines ADD COLUMN IF NOT EXISTS exchange_rate DECIMAL(18,6)")
+        .execute(pool).await?;
+
     // Audit logs table
     sqlx::query!(
         r#"
@@ -397,84 +538,57 @@ async fn run_ledger_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
-    // Account balances materialized view/table for performance
+    sqlx::query!("ALTER TABLE recurring_journal_templates ADD COLUMN IF NOT EXISTS reference VARCHAR(100)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE recurring_journal_templates ADD COLUMN IF NOT EXISTS start_date DATE")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE recurring_journal_templates ADD COLUMN IF NOT EXISTS end_date DATE")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE recurring_journal_templates ADD COLUMN IF NOT EXISTS last_run_date DATE")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE recurring_journal_templates ADD COLUMN IF NOT EXISTS auto_post BOOLEAN DEFAULT FALSE")
+        .execute(pool).await?;
+
+    // Recurring journal template lines table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS account_balances (
+        CREATE TABLE IF NOT EXISTS recurring_journal_template_lines (
             id UUID PRIMARY KEY,
-            company_id UUID NOT NULL,
+            template_id UUID NOT NULL REFERENCES recurring_journal_templates(id) ON DELETE CASCADE,
             account_id UUID NOT NULL,
-            balance_date DATE NOT NULL,
-            beginning_balance DECIMAL(15,2) DEFAULT 0,
+            description TEXT,
             debit_amount DECIMAL(15,2) DEFAULT 0,
             credit_amount DECIMAL(15,2) DEFAULT 0,
-            ending_balance DECIMAL(15,2) DEFAULT 0,
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, account_id, balance_date)
+            department VARCHAR(100),
+            project_code VARCHAR(50),
+            cost_center VARCHAR(50),
+            line_number INTEGER NOT NULL
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Create function for generating entry numbers
+    // Runs of a recurring template that fell in a locked period
     sqlx::query!(
         r#"
-        CREATE OR REPLACE FUNCTION generate_entry_number(company_uuid UUID, entry_date DATE)
-        RETURNS VARCHAR(50) AS $$
-        DECLARE
-            year_part VARCHAR(4);
-            month_part VARCHAR(2);
-            sequence_num INTEGER;
-            entry_number VARCHAR(50);
-        BEGIN
-            year_part := EXTRACT(YEAR FROM entry_date)::VARCHAR;
-            month_part := LPAD(EXTRACT(MONTH FROM entry_date)::VARCHAR, 2, '0');
-            
-            SELECT COALESCE(MAX(CAST(SUBSTRING(entry_number FROM 'JE-\d{4}\d{2}-(\d+)') AS INTEGER)), 0) + 1
-            INTO sequence_num
-            FROM journal_entries 
-            WHERE company_id = company_uuid 
-            AND EXTRACT(YEAR FROM entry_date) = EXTRACT(YEAR FROM entry_date)
-            AND EXTRACT(MONTH FROM entry_date) = EXTRACT(MONTH FROM entry_date);
-            
-            entry_number := 'JE-' || year_part || month_part || '-' || LPAD(sequence_num::VARCHAR, 6, '0');
-            
-            RETURN entry_number;
-        END;
-        $$ LANGUAGE plpgsql;
+        CREATE TABLE IF NOT EXISTS recurring_journal_skipped_runs (
+            template_id UUID NOT NULL REFERENCES recurring_journal_templates(id) ON DELETE CASCADE,
+            run_date DATE NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            PRIMARY KEY (template_id, run_date)
+        )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Create indexes
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_company_id ON journal_entries(company_id)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_date ON journal_entries(entry_date)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_status ON journal_entries(status)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_entry_id ON journal_entry_lines(journal_entry_id)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_account_id ON journal_entry_lines(account_id)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_audit_logs_table_record ON audit_logs(table_name, record_id)")
-        .execute(pool).await?;
-
-    info!("General ledger migrations completed");
-    Ok(())
-}
-
-// ===== INDONESIAN TAX MIGRATIONS =====
-async fn run_tax_migrations(pool: &PgPool) -> anyhow::Result<()> {
-    info!("Running Indonesian tax migrations...");
-
-    // Create tax type enum
+    // Fiscal periods table
     sqlx::query!(
         r#"
         DO $$ BEGIN
-            CREATE TYPE tax_type AS ENUM ('PPN', 'PPH21', 'PPH22', 'PPH23', 'PPH25', 'PPH29', 'PBB');
+            CREATE TYPE fiscal_period_status AS ENUM ('OPEN', 'SOFT_CLOSED', 'CLOSED');
         EXCEPTION
             WHEN duplicate_object THEN null;
         END $$;
@@ -483,51 +597,96 @@ async fn run_tax_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
-    // Tax configurations table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS tax_configurations (
+        CREATE TABLE IF NOT EXISTS fiscal_periods (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            tax_type tax_type NOT NULL,
-            tax_rate DECIMAL(5,2) NOT NULL,
+            fiscal_year INTEGER NOT NULL,
+            period_number INTEGER NOT NULL,
+            period_name VARCHAR(50) NOT NULL,
+            start_date DATE NOT NULL,
+            end_date DATE NOT NULL,
+            status fiscal_period_status NOT NULL DEFAULT 'OPEN',
+            closed_by UUID,
+            closed_at TIMESTAMPTZ,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, fiscal_year, period_number),
+            CHECK (end_date >= start_date)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Dimension master lists (departments, projects, cost centers)
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS dimension_values (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            dimension_type VARCHAR(20) NOT NULL,
+            code VARCHAR(50) NOT NULL,
+            name VARCHAR(255) NOT NULL,
             is_active BOOLEAN DEFAULT TRUE,
-            effective_date DATE NOT NULL,
-            end_date DATE,
-            description TEXT,
-            calculation_method VARCHAR(50) DEFAULT 'PERCENTAGE',
-            minimum_amount DECIMAL(15,2) DEFAULT 0,
-            maximum_amount DECIMAL(15,2),
+            created_by UUID NOT NULL,
             created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW()
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, dimension_type, code)
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Tax transactions table
+    // Journal approval policies table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS tax_transactions (
+        CREATE TABLE IF NOT EXISTS journal_approval_policies (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            tax_type tax_type NOT NULL,
-            transaction_date DATE NOT NULL,
-            tax_period DATE NOT NULL,
-            tax_base_amount DECIMAL(15,2) NOT NULL,
-            tax_amount DECIMAL(15,2) NOT NULL,
-            tax_invoice_number VARCHAR(50),
-            vendor_npwp VARCHAR(20),
-            vendor_name VARCHAR(255),
-            customer_npwp VARCHAR(20),
-            customer_name VARCHAR(255),
-            description TEXT,
-            journal_entry_id UUID,
-            source_document_type VARCHAR(50),
-            source_document_id UUID,
-            is_reversed BOOLEAN DEFAULT FALSE,
-            reversal_reason TEXT,
+            policy_name VARCHAR(100) NOT NULL,
+            min_amount DECIMAL(15,2),
+            account_type VARCHAR(20),
+            required_approvals INTEGER NOT NULL DEFAULT 1,
+            required_role VARCHAR(50),
+            is_active BOOLEAN DEFAULT TRUE,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            CHECK (required_approvals > 0)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Journal entry approvals table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS journal_entry_approvals (
+            id UUID PRIMARY KEY,
+            journal_entry_id UUID NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+            company_id UUID NOT NULL,
+            approver_id UUID NOT NULL,
+            approver_roles TEXT[] NOT NULL DEFAULT '{}',
+            comments TEXT,
+            approved_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(journal_entry_id, approver_id)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Ledger settings table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS ledger_settings (
+            company_id UUID PRIMARY KEY,
+            retained_earnings_account_id UUID,
+            updated_by UUID,
             created_at TIMESTAMPTZ DEFAULT NOW(),
             updated_at TIMESTAMPTZ DEFAULT NOW()
         )
@@ -536,216 +695,1098 @@ async fn run_tax_migrations(pool: &PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
-    // E-Faktur data table (for Indonesian VAT)
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS intercompany_receivable_account_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS intercompany_payable_account_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS unrealized_fx_gain_account_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS unrealized_fx_loss_account_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS budget_warnings BOOLEAN DEFAULT FALSE")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS ap_control_account_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE ledger_settings ADD COLUMN IF NOT EXISTS ar_control_account_id UUID")
+        .execute(pool).await?;
+
+    // Year-end closings table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS efaktur_data (
+        CREATE TABLE IF NOT EXISTS year_end_closings (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            tax_transaction_id UUID NOT NULL REFERENCES tax_transactions(id) ON DELETE CASCADE,
-            faktur_number VARCHAR(50) NOT NULL,
-            faktur_date DATE NOT NULL,
-            vendor_npwp VARCHAR(20) NOT NULL,
-            vendor_name VARCHAR(255) NOT NULL,
-            dpp_amount DECIMAL(15,2) NOT NULL,
-            ppn_amount DECIMAL(15,2) NOT NULL,
-            ppnbm_amount DECIMAL(15,2) DEFAULT 0,
-            referensi VARCHAR(100),
-            status VARCHAR(20) DEFAULT 'DRAFT',
-            uploaded_at TIMESTAMPTZ,
+            fiscal_year INTEGER NOT NULL,
+            period_start DATE NOT NULL,
+            period_end DATE NOT NULL,
+            journal_entry_id UUID NOT NULL REFERENCES journal_entries(id),
+            retained_earnings_account_id UUID NOT NULL,
+            net_income DECIMAL(15,2) NOT NULL,
+            closed_by UUID NOT NULL,
+            closed_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Intercompany transactions table (links the two legs of a cross-entity entry)
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS intercompany_transactions (
+            id UUID PRIMARY KEY,
+            source_company_id UUID NOT NULL,
+            target_company_id UUID NOT NULL,
+            source_entry_id UUID NOT NULL UNIQUE REFERENCES journal_entries(id),
+            target_entry_id UUID NOT NULL UNIQUE REFERENCES journal_entries(id),
+            source_account_id UUID NOT NULL,
+            target_account_id UUID NOT NULL,
+            amount DECIMAL(15,2) NOT NULL,
+            entry_date DATE NOT NULL,
+            description TEXT,
+            created_by UUID NOT NULL,
             created_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, faktur_number)
+            CHECK (source_company_id <> target_company_id)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // FX revaluation runs table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS fx_revaluations (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            revaluation_date DATE NOT NULL,
+            journal_entry_id UUID NOT NULL REFERENCES journal_entries(id),
+            rates JSONB NOT NULL DEFAULT '{}',
+            total_gain DECIMAL(15,2) NOT NULL DEFAULT 0,
+            total_loss DECIMAL(15,2) NOT NULL DEFAULT 0,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Revaluation adjustments are functional-currency only
+    sqlx::query!(
+        r#"
+        UPDATE journal_entry_lines jel
+        SET currency = NULL, foreign_amount = NULL, exchange_rate = NULL
+        FROM journal_entries je
+        WHERE je.id = jel.journal_entry_id
+          AND jel.foreign_amount = 0
+          AND (je.source_document_type = 'FX_REVALUATION'
+               OR (je.source_document_type = 'JOURNAL_REVERSAL'
+                   AND je.source_document_id IN (SELECT journal_entry_id FROM fx_revaluations)))
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Amortization schedules table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS amortization_schedules (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            schedule_name VARCHAR(255) NOT NULL,
+            description TEXT,
+            schedule_type VARCHAR(20) NOT NULL, -- PREPAID, ACCRUAL
+            method VARCHAR(20) NOT NULL, -- STRAIGHT_LINE, CUSTOM
+            balance_account_id UUID NOT NULL,
+            expense_account_id UUID NOT NULL,
+            total_amount DECIMAL(15,2) NOT NULL,
+            periods INTEGER NOT NULL,
+            start_date DATE NOT NULL,
+            auto_post BOOLEAN DEFAULT TRUE,
+            department VARCHAR(100),
+            project_code VARCHAR(50),
+            cost_center VARCHAR(50),
+            status VARCHAR(20) NOT NULL DEFAULT 'ACTIVE', -- ACTIVE, COMPLETED, CANCELLED
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Amortization schedule periods table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS amortization_schedule_lines (
+            id UUID PRIMARY KEY,
+            schedule_id UUID NOT NULL REFERENCES amortization_schedules(id) ON DELETE CASCADE,
+            period_number INTEGER NOT NULL,
+            period_date DATE NOT NULL,
+            amount DECIMAL(15,2) NOT NULL,
+            journal_entry_id UUID REFERENCES journal_entries(id),
+            UNIQUE(schedule_id, period_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Bank reconciliations table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS bank_reconciliations (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            bank_account_id UUID NOT NULL,
+            as_of_date DATE NOT NULL,
+            statement_balance DECIMAL(15,2) NOT NULL,
+            book_balance DECIMAL(15,2) NOT NULL,
+            reconciled_balance DECIMAL(15,2) NOT NULL,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(bank_account_id, as_of_date)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Imported bank statements table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS bank_statements (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            bank_account_id UUID NOT NULL,
+            statement_number VARCHAR(50),
+            period_start DATE NOT NULL,
+            period_end DATE NOT NULL,
+            opening_balance DECIMAL(15,2) NOT NULL,
+            closing_balance DECIMAL(15,2) NOT NULL,
+            source_format VARCHAR(10) NOT NULL, -- CSV, MT940
+            file_name VARCHAR(255),
+            imported_by UUID NOT NULL,
+            imported_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Bank statement lines table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS bank_statement_lines (
+            id UUID PRIMARY KEY,
+            statement_id UUID NOT NULL REFERENCES bank_statements(id) ON DELETE CASCADE,
+            company_id UUID NOT NULL,
+            bank_account_id UUID NOT NULL,
+            line_number INTEGER NOT NULL,
+            transaction_date DATE NOT NULL,
+            amount DECIMAL(15,2) NOT NULL, -- deposits positive, withdrawals negative
+            description TEXT,
+            reference VARCHAR(255),
+            bank_reference VARCHAR(255),
+            match_status VARCHAR(20) NOT NULL DEFAULT 'UNMATCHED', -- UNMATCHED, MATCHED
+            match_type VARCHAR(20), -- JOURNAL_LINE, VENDOR_PAYMENT, CUSTOMER_PAYMENT
+            matched_record_id UUID,
+            match_method VARCHAR(20), -- AUTO, MANUAL, ADJUSTMENT
+            matched_by UUID,
+            matched_at TIMESTAMPTZ,
+            reconciliation_id UUID REFERENCES bank_reconciliations(id)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Cost allocation rules table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS allocation_rules (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            rule_name VARCHAR(255) NOT NULL,
+            description TEXT,
+            source_account_id UUID NOT NULL,
+            source_cost_center VARCHAR(50),
+            basis VARCHAR(20) NOT NULL, -- FIXED_PERCENTAGE, DRIVER
+            driver_name VARCHAR(100),
+            is_active BOOLEAN DEFAULT TRUE,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, rule_name)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Cost allocation targets table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS allocation_rule_targets (
+            id UUID PRIMARY KEY,
+            rule_id UUID NOT NULL REFERENCES allocation_rules(id) ON DELETE CASCADE,
+            line_number INTEGER NOT NULL,
+            cost_center VARCHAR(50) NOT NULL,
+            account_id UUID,
+            department VARCHAR(100),
+            weight DECIMAL(15,4) NOT NULL, -- percentage or driver quantity
+            UNIQUE(rule_id, line_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Cost allocation runs table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS allocation_runs (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            rule_id UUID NOT NULL REFERENCES allocation_rules(id),
+            period_start DATE NOT NULL,
+            period_end DATE NOT NULL,
+            source_amount DECIMAL(15,2) NOT NULL,
+            journal_entry_id UUID NOT NULL REFERENCES journal_entries(id),
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Budgets table, one row per version
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS budgets (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            budget_name VARCHAR(255) NOT NULL,
+            fiscal_year INTEGER NOT NULL,
+            version INTEGER NOT NULL,
+            description TEXT,
+            status VARCHAR(20) NOT NULL DEFAULT 'DRAFT', -- DRAFT, ACTIVE, ARCHIVED
+            based_on_budget_id UUID REFERENCES budgets(id),
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, fiscal_year, version)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Budget amounts per account and month
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS budget_lines (
+            id UUID PRIMARY KEY,
+            budget_id UUID NOT NULL REFERENCES budgets(id) ON DELETE CASCADE,
+            account_id UUID NOT NULL,
+            period_start DATE NOT NULL, -- first day of the month
+            department VARCHAR(100),
+            project_code VARCHAR(50),
+            amount DECIMAL(15,2) NOT NULL
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Posting rules: GL account per event and account role, optionally
+    // narrowed by vendor group, item category or tax type
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS posting_rules (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            event_type VARCHAR(50) NOT NULL,
+            account_role VARCHAR(50) NOT NULL,
+            vendor_group VARCHAR(50),
+            item_category VARCHAR(20),
+            tax_type VARCHAR(20),
+            account_id UUID NOT NULL REFERENCES accounts(id),
+            description TEXT,
+            is_active BOOLEAN DEFAULT TRUE,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Account balances materialized view/table for performance
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS account_balances (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            account_id UUID NOT NULL,
+            balance_date DATE NOT NULL,
+            beginning_balance DECIMAL(15,2) DEFAULT 0,
+            debit_amount DECIMAL(15,2) DEFAULT 0,
+            credit_amount DECIMAL(15,2) DEFAULT 0,
+            ending_balance DECIMAL(15,2) DEFAULT 0,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, account_id, balance_date)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Create function for generating entry numbers
+    sqlx::query!(
+        r#"
+        CREATE OR REPLACE FUNCTION generate_entry_number(company_uuid UUID, entry_date DATE)
+        RETURNS VARCHAR(50) AS $$
+        DECLARE
+            year_part VARCHAR(4);
+            month_part VARCHAR(2);
+            sequence_num INTEGER;
+            new_entry_number VARCHAR(50);
+        BEGIN
+            year_part := EXTRACT(YEAR FROM generate_entry_number.entry_date)::VARCHAR;
+            month_part := LPAD(EXTRACT(MONTH FROM generate_entry_number.entry_date)::VARCHAR, 2, '0');
+            
+            SELECT COALESCE(MAX(CAST(SUBSTRING(je.entry_number FROM 'JE-\d{4}\d{2}-(\d+)') AS INTEGER)), 0) + 1
+            INTO sequence_num
+            FROM journal_entries je
+            WHERE je.company_id = company_uuid 
+            AND je.entry_number LIKE 'JE-' || year_part || month_part || '-%';
+            
+            new_entry_number := 'JE-' || year_part || month_part || '-' || LPAD(sequence_num::VARCHAR, 6, '0');
+            
+            RETURN new_entry_number;
+        END;
+        $$ LANGUAGE plpgsql;
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Continue journal numbering from entries issued before document_sequences existed
+    sqlx::query!(
+        r#"
+        INSERT INTO document_sequences (company_id, document_type, period_key, last_value)
+        SELECT company_id, 'JOURNAL_ENTRY',
+               SUBSTRING(entry_number FROM '^JE-(\d{4})\d{2}-') || '-' || SUBSTRING(entry_number FROM '^JE-\d{4}(\d{2})-'),
+               MAX(CAST(SUBSTRING(entry_number FROM '^JE-\d{6}-(\d+)$') AS BIGINT))
+        FROM journal_entries
+        WHERE entry_number ~ '^JE-\d{6}-\d+$'
+        GROUP BY 1, 2, 3
+        ON CONFLICT (company_id, document_type, period_key) DO NOTHING
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Create indexes
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_company_id ON journal_entries(company_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_date ON journal_entries(entry_date)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_status ON journal_entries(status)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_entry_id ON journal_entry_lines(journal_entry_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_account_id ON journal_entry_lines(account_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_audit_logs_table_record ON audit_logs(table_name, record_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_source ON journal_entries(source_document_type, source_document_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entries_auto_reverse ON journal_entries(auto_reverse_on) WHERE reversed_by_entry_id IS NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_journal_entries_recurring_run ON journal_entries(recurring_template_id, entry_date) WHERE recurring_template_id IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_recurring_templates_next_run ON recurring_journal_templates(next_run_date) WHERE is_active = true")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_recurring_template_lines_template ON recurring_journal_template_lines(template_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_fiscal_periods_company_dates ON fiscal_periods(company_id, start_date, end_date)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_year_end_closings_company_year ON year_end_closings(company_id, fiscal_year)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_project ON journal_entry_lines(project_code) WHERE project_code IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_approval_policies_company ON journal_approval_policies(company_id) WHERE is_active = true")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_intercompany_transactions_source ON intercompany_transactions(source_company_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_intercompany_transactions_target ON intercompany_transactions(target_company_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_journal_entry_lines_currency ON journal_entry_lines(account_id, currency) WHERE currency IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_fx_revaluations_company_date ON fx_revaluations(company_id, revaluation_date)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_amortization_schedules_company ON amortization_schedules(company_id, status)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_amortization_lines_due ON amortization_schedule_lines(period_date) WHERE journal_entry_id IS NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_bank_statements_account ON bank_statements(bank_account_id, period_start)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_bank_statement_lines_account_date ON bank_statement_lines(bank_account_id, transaction_date)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_bank_statement_lines_match ON bank_statement_lines(match_type, matched_record_id) WHERE matched_record_id IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_allocation_runs_rule ON allocation_runs(rule_id, period_start)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_budget_lines_key ON budget_lines(budget_id, account_id, period_start, COALESCE(department, ''), COALESCE(project_code, ''))")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_budget_lines_account ON budget_lines(account_id, period_start)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_posting_rules_key ON posting_rules(company_id, event_type, account_role, COALESCE(vendor_group, ''), COALESCE(item_category, ''), COALESCE(tax_type, ''))")
+        .execute(pool).await?;
+
+    info!("General ledger migrations completed");
+    Ok(())
+}
+
+// ===== INDONESIAN TAX MIGRATIONS =====
+async fn run_tax_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    info!("Running Indonesian tax migrations...");
+
+    // Create tax type enum
+    sqlx::query!(
+        r#"
+        DO $$ BEGIN
+            CREATE TYPE tax_type AS ENUM ('PPN', 'PPH21', 'PPH22', 'PPH23', 'PPH25', 'PPH29', 'PBB');
+        EXCEPTION
+            WHEN duplicate_object THEN null;
+        END $$;
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Tax configurations table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS tax_configurations (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            tax_type tax_type NOT NULL,
+            tax_rate DECIMAL(5,2) NOT NULL,
+            is_active BOOLEAN DEFAULT TRUE,
+            effective_date DATE NOT NULL,
+            end_date DATE,
+            description TEXT,
+            calculation_method VARCHAR(50) DEFAULT 'PERCENTAGE',
+            minimum_amount DECIMAL(15,2) DEFAULT 0,
+            maximum_amount DECIMAL(15,2),
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Tax transactions table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS tax_transactions (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            tax_type tax_type NOT NULL,
+            transaction_date DATE NOT NULL,
+            tax_period DATE NOT NULL,
+            tax_base_amount DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL,
+            tax_invoice_number VARCHAR(50),
+            vendor_npwp VARCHAR(20),
+            vendor_name VARCHAR(255),
+            customer_npwp VARCHAR(20),
+            customer_name VARCHAR(255),
+            description TEXT,
+            journal_entry_id UUID,
+            source_document_type VARCHAR(50),
+            source_document_id UUID,
+            is_reversed BOOLEAN DEFAULT FALSE,
+            reversal_reason TEXT,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // E-Faktur data table (for Indonesian VAT)
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS efaktur_data (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            tax_transaction_id UUID NOT NULL REFERENCES tax_transactions(id) ON DELETE CASCADE,
+            faktur_number VARCHAR(50) NOT NULL,
+            faktur_date DATE NOT NULL,
+            vendor_npwp VARCHAR(20) NOT NULL,
+            vendor_name VARCHAR(255) NOT NULL,
+            dpp_amount DECIMAL(15,2) NOT NULL,
+            ppn_amount DECIMAL(15,2) NOT NULL,
+            ppnbm_amount DECIMAL(15,2) DEFAULT 0,
+            referensi VARCHAR(100),
+            status VARCHAR(20) DEFAULT 'DRAFT',
+            uploaded_at TIMESTAMPTZ,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, faktur_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Tax periods table (for reporting)
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS tax_periods (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            tax_type tax_type NOT NULL,
+            period_year INTEGER NOT NULL,
+            period_month INTEGER,
+            period_quarter INTEGER,
+            status VARCHAR(20) DEFAULT 'OPEN',
+            closed_at TIMESTAMPTZ,
+            closed_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, tax_type, period_year, period_month),
+            UNIQUE(company_id, tax_type, period_year, period_quarter)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Create indexes
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_configurations_company_type ON tax_configurations(company_id, tax_type)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_company_id ON tax_transactions(company_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_period ON tax_transactions(tax_period)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_type ON tax_transactions(tax_type)")
+        .execute(pool).await?;
+    // Services reporting tax for their documents may repeat a call; one
+    // transaction per source document and tax type is kept
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_tax_transactions_source ON tax_transactions(company_id, source_document_type, source_document_id, tax_type) WHERE source_document_id IS NOT NULL")
+        .execute(pool).await?;
+
+    info!("Indonesian tax migrations completed");
+    Ok(())
+}
+
+// ===== ACCOUNTS PAYABLE MIGRATIONS =====
+async fn run_ap_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    info!("Running accounts payable migrations...");
+
+    run_numbering_migrations(pool).await?;
+
+    // Create invoice status enum
+    sqlx::query!(
+        r#"
+        DO $$ BEGIN
+            CREATE TYPE invoice_status AS ENUM ('DRAFT', 'PENDING', 'APPROVED', 'PAID', 'CANCELLED');
+        EXCEPTION
+            WHEN duplicate_object THEN null;
+        END $$;
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Vendors table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS vendors (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            vendor_code VARCHAR(20) NOT NULL,
+            vendor_name VARCHAR(255) NOT NULL,
+            npwp VARCHAR(20),
+            address TEXT,
+            phone VARCHAR(50),
+            email VARCHAR(255),
+            contact_person VARCHAR(255),
+            payment_terms INTEGER DEFAULT 30,
+            bank_name VARCHAR(255),
+            bank_account_number VARCHAR(50),
+            bank_account_name VARCHAR(255),
+            currency VARCHAR(3) DEFAULT 'IDR',
+            is_active BOOLEAN DEFAULT TRUE,
+            notes TEXT,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, vendor_code)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Vendor invoices table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS vendor_invoices (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            vendor_id UUID NOT NULL REFERENCES vendors(id),
+            invoice_number VARCHAR(50) NOT NULL,
+            invoice_date DATE NOT NULL,
+            due_date DATE NOT NULL,
+            subtotal DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) DEFAULT 0,
+            discount_amount DECIMAL(15,2) DEFAULT 0,
+            total_amount DECIMAL(15,2) NOT NULL,
+            paid_amount DECIMAL(15,2) DEFAULT 0,
+            status invoice_status DEFAULT 'DRAFT',
+            description TEXT,
+            purchase_order_number VARCHAR(50),
+            received_date DATE,
+            journal_entry_id UUID,
+            currency VARCHAR(3) DEFAULT 'IDR',
+            exchange_rate DECIMAL(10,4) DEFAULT 1.0000,
+            created_by UUID NOT NULL,
+            approved_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, vendor_id, invoice_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Vendor payments table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS vendor_payments (
+            id UUID PRIMARY KEY,
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
+            company_id UUID NOT NULL,
+            payment_number VARCHAR(50) NOT NULL,
+            payment_amount DECIMAL(15,2) NOT NULL,
+            payment_date DATE NOT NULL,
+            payment_method VARCHAR(50) NOT NULL,
+            bank_account_id UUID,
+            bank_account_number VARCHAR(50),
+            check_number VARCHAR(50),
+            payment_reference VARCHAR(255),
+            notes TEXT,
+            is_reversed BOOLEAN DEFAULT FALSE,
+            reversal_reason TEXT,
+            reversed_by UUID,
+            reversed_at TIMESTAMPTZ,
+            journal_entry_id UUID,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Vendor invoice line items table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS vendor_invoice_lines (
+            id UUID PRIMARY KEY,
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id) ON DELETE CASCADE,
+            line_number INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            quantity DECIMAL(15,4) DEFAULT 1,
+            unit_price DECIMAL(15,2) NOT NULL,
+            line_amount DECIMAL(15,2) NOT NULL,
+            account_id UUID,
+            department VARCHAR(100),
+            project_code VARCHAR(50),
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Purchase orders table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS purchase_orders (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            vendor_id UUID NOT NULL REFERENCES vendors(id),
+            po_number VARCHAR(50) NOT NULL,
+            po_date DATE NOT NULL,
+            expected_delivery_date DATE,
+            subtotal DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) DEFAULT 0,
+            total_amount DECIMAL(15,2) NOT NULL,
+            status VARCHAR(20) DEFAULT 'PENDING',
+            notes TEXT,
+            created_by UUID NOT NULL,
+            approved_by UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            updated_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, po_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Purchase order lines table
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS purchase_order_lines (
+            id UUID PRIMARY KEY,
+            purchase_order_id UUID NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+            line_number INTEGER NOT NULL,
+            item_id UUID,
+            item_code VARCHAR(50),
+            description TEXT NOT NULL,
+            quantity DECIMAL(15,4) NOT NULL,
+            unit_price DECIMAL(15,2) NOT NULL,
+            tax_rate DECIMAL(5,2) NOT NULL DEFAULT 0,
+            line_amount DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            received_quantity DECIMAL(15,4) NOT NULL DEFAULT 0,
+            account_id UUID,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Goods receipt notes against purchase orders
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS goods_receipts (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            purchase_order_id UUID NOT NULL REFERENCES purchase_orders(id),
+            receipt_number VARCHAR(50) NOT NULL,
+            receipt_date DATE NOT NULL,
+            notes TEXT,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(company_id, receipt_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS goods_receipt_lines (
+            id UUID PRIMARY KEY,
+            receipt_id UUID NOT NULL REFERENCES goods_receipts(id) ON DELETE CASCADE,
+            po_line_id UUID NOT NULL REFERENCES purchase_order_lines(id),
+            quantity DECIMAL(15,4) NOT NULL,
+            inventory_transaction_id UUID
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Three-way matching tolerances per company
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS ap_matching_settings (
+            company_id UUID PRIMARY KEY,
+            price_tolerance_percent DECIMAL(5,2) NOT NULL DEFAULT 0,
+            quantity_tolerance_percent DECIMAL(5,2) NOT NULL DEFAULT 0,
+            updated_by UUID,
+            updated_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Latest match result of each invoice line billing a purchase order
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS invoice_line_matches (
+            id UUID PRIMARY KEY,
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id) ON DELETE CASCADE,
+            invoice_line_id UUID NOT NULL REFERENCES vendor_invoice_lines(id) ON DELETE CASCADE,
+            po_line_id UUID NOT NULL REFERENCES purchase_order_lines(id),
+            ordered_quantity DECIMAL(15,4) NOT NULL,
+            received_quantity DECIMAL(15,4) NOT NULL,
+            previously_invoiced_quantity DECIMAL(15,4) NOT NULL,
+            invoice_quantity DECIMAL(15,4) NOT NULL,
+            po_unit_price DECIMAL(15,2) NOT NULL,
+            invoice_unit_price DECIMAL(15,2) NOT NULL,
+            price_variance DECIMAL(15,2) NOT NULL,
+            price_variance_percent DECIMAL(9,2),
+            quantity_variance DECIMAL(15,4) NOT NULL,
+            status VARCHAR(20) NOT NULL,
+            explanation TEXT,
+            matched_at TIMESTAMPTZ DEFAULT NOW(),
+            UNIQUE(invoice_line_id)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Payment runs and the invoices each pays
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS payment_runs (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            run_number VARCHAR(50) NOT NULL,
+            payment_date DATE NOT NULL,
+            currency VARCHAR(3) NOT NULL DEFAULT 'IDR',
+            due_date_to DATE NOT NULL,
+            bank_account_id UUID,
+            source_account_number VARCHAR(50) NOT NULL,
+            source_account_name VARCHAR(255) NOT NULL,
+            status VARCHAR(20) NOT NULL DEFAULT 'DRAFT',
+            total_outstanding DECIMAL(15,2) NOT NULL DEFAULT 0,
+            total_discount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            total_payment DECIMAL(15,2) NOT NULL DEFAULT 0,
+            created_by UUID NOT NULL,
+            approved_by UUID,
+            approved_at TIMESTAMPTZ,
+            completed_at TIMESTAMPTZ,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, run_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS payment_run_items (
+            id UUID PRIMARY KEY,
+            run_id UUID NOT NULL REFERENCES payment_runs(id) ON DELETE CASCADE,
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
+            outstanding_amount DECIMAL(15,2) NOT NULL,
+            discount_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            payment_amount DECIMAL(15,2) NOT NULL,
+            payment_id UUID REFERENCES vendor_payments(id),
+            UNIQUE(run_id, invoice_id)
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Tax periods table (for reporting)
+    // Bukti potong issued for the income tax withheld from vendor payments
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS tax_periods (
+        CREATE TABLE IF NOT EXISTS withholding_slips (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            tax_type tax_type NOT NULL,
-            period_year INTEGER NOT NULL,
-            period_month INTEGER,
-            period_quarter INTEGER,
-            status VARCHAR(20) DEFAULT 'OPEN',
-            closed_at TIMESTAMPTZ,
-            closed_by UUID,
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, tax_type, period_year, period_month),
-            UNIQUE(company_id, tax_type, period_year, period_quarter)
+            slip_number VARCHAR(50) NOT NULL,
+            payment_id UUID NOT NULL REFERENCES vendor_payments(id),
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
+            vendor_id UUID NOT NULL REFERENCES vendors(id),
+            tax_type VARCHAR(10) NOT NULL DEFAULT 'PPH23',
+            withholding_date DATE NOT NULL,
+            vendor_npwp VARCHAR(20),
+            vendor_name VARCHAR(255) NOT NULL,
+            tax_base_amount DECIMAL(15,2) NOT NULL,
+            tax_rate DECIMAL(5,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL,
+            tax_transaction_id UUID,
+            status VARCHAR(20) NOT NULL DEFAULT 'ISSUED',
+            cancelled_at TIMESTAMPTZ,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, slip_number),
+            UNIQUE(payment_id)
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Create indexes
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_configurations_company_type ON tax_configurations(company_id, tax_type)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_company_id ON tax_transactions(company_id)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_period ON tax_transactions(tax_period)")
-        .execute(pool).await?;
-    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_tax_transactions_type ON tax_transactions(tax_type)")
-        .execute(pool).await?;
-
-    info!("Indonesian tax migrations completed");
-    Ok(())
-}
-
-// ===== ACCOUNTS PAYABLE MIGRATIONS =====
-async fn run_ap_migrations(pool: &PgPool) -> anyhow::Result<()> {
-    info!("Running accounts payable migrations...");
+    // Vendor credits (the vendor's credit memo or our debit note), the
+    // invoices they are applied to and the cash refunded on them
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS vendor_credit_memos (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            vendor_id UUID NOT NULL REFERENCES vendors(id),
+            credit_memo_number VARCHAR(50) NOT NULL,
+            vendor_reference VARCHAR(50),
+            memo_date DATE NOT NULL,
+            reason VARCHAR(20) NOT NULL,
+            invoice_id UUID REFERENCES vendor_invoices(id),
+            subtotal DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            total_amount DECIMAL(15,2) NOT NULL,
+            applied_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            refunded_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            currency VARCHAR(3) NOT NULL DEFAULT 'IDR',
+            exchange_rate DECIMAL(10,4) NOT NULL DEFAULT 1.0000,
+            status VARCHAR(20) NOT NULL DEFAULT 'DRAFT',
+            description TEXT,
+            journal_entry_id UUID,
+            created_by UUID NOT NULL,
+            approved_by UUID,
+            approved_at TIMESTAMPTZ,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, credit_memo_number)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
 
-    // Create invoice status enum
     sqlx::query!(
         r#"
-        DO $$ BEGIN
-            CREATE TYPE invoice_status AS ENUM ('DRAFT', 'PENDING', 'APPROVED', 'PAID', 'CANCELLED');
-        EXCEPTION
-            WHEN duplicate_object THEN null;
-        END $$;
+        CREATE TABLE IF NOT EXISTS vendor_credit_memo_lines (
+            id UUID PRIMARY KEY,
+            credit_memo_id UUID NOT NULL REFERENCES vendor_credit_memos(id) ON DELETE CASCADE,
+            line_number INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            quantity DECIMAL(15,4) NOT NULL DEFAULT 1,
+            unit_price DECIMAL(15,2) NOT NULL,
+            line_amount DECIMAL(15,2) NOT NULL,
+            account_id UUID,
+            item_category VARCHAR(20),
+            department VARCHAR(50),
+            project_code VARCHAR(50),
+            UNIQUE(credit_memo_id, line_number)
+        )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Vendors table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS vendors (
+        CREATE TABLE IF NOT EXISTS vendor_credit_applications (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            vendor_code VARCHAR(20) NOT NULL,
-            vendor_name VARCHAR(255) NOT NULL,
-            npwp VARCHAR(20),
-            address TEXT,
-            phone VARCHAR(50),
-            email VARCHAR(255),
-            contact_person VARCHAR(255),
-            payment_terms INTEGER DEFAULT 30,
-            bank_name VARCHAR(255),
-            bank_account_number VARCHAR(50),
-            bank_account_name VARCHAR(255),
-            currency VARCHAR(3) DEFAULT 'IDR',
-            is_active BOOLEAN DEFAULT TRUE,
-            notes TEXT,
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, vendor_code)
+            credit_memo_id UUID NOT NULL REFERENCES vendor_credit_memos(id),
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
+            amount DECIMAL(15,2) NOT NULL,
+            application_date DATE NOT NULL,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Vendor invoices table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS vendor_invoices (
+        CREATE TABLE IF NOT EXISTS vendor_credit_refunds (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            vendor_id UUID NOT NULL REFERENCES vendors(id),
-            invoice_number VARCHAR(50) NOT NULL,
-            invoice_date DATE NOT NULL,
-            due_date DATE NOT NULL,
-            subtotal DECIMAL(15,2) NOT NULL,
-            tax_amount DECIMAL(15,2) DEFAULT 0,
-            discount_amount DECIMAL(15,2) DEFAULT 0,
-            total_amount DECIMAL(15,2) NOT NULL,
-            paid_amount DECIMAL(15,2) DEFAULT 0,
-            status invoice_status DEFAULT 'DRAFT',
-            description TEXT,
-            purchase_order_number VARCHAR(50),
-            received_date DATE,
+            credit_memo_id UUID NOT NULL REFERENCES vendor_credit_memos(id),
+            refund_number VARCHAR(50) NOT NULL,
+            refund_amount DECIMAL(15,2) NOT NULL,
+            refund_date DATE NOT NULL,
+            payment_method VARCHAR(50) NOT NULL,
+            bank_account_id UUID,
+            reference VARCHAR(100),
             journal_entry_id UUID,
-            currency VARCHAR(3) DEFAULT 'IDR',
-            exchange_rate DECIMAL(10,4) DEFAULT 1.0000,
             created_by UUID NOT NULL,
-            approved_by UUID,
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, vendor_id, invoice_number)
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, refund_number)
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Vendor payments table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS vendor_payments (
+        CREATE TABLE IF NOT EXISTS vendor_advances (
             id UUID PRIMARY KEY,
-            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
             company_id UUID NOT NULL,
-            payment_number VARCHAR(50) NOT NULL,
-            payment_amount DECIMAL(15,2) NOT NULL,
-            payment_date DATE NOT NULL,
+            vendor_id UUID NOT NULL REFERENCES vendors(id),
+            advance_number VARCHAR(50) NOT NULL,
+            advance_date DATE NOT NULL,
+            purchase_order_id UUID REFERENCES purchase_orders(id),
+            amount DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            tax_invoice_number VARCHAR(50),
+            applied_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            applied_tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            refunded_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            refunded_tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            currency VARCHAR(3) NOT NULL DEFAULT 'IDR',
+            exchange_rate DECIMAL(10,4) NOT NULL DEFAULT 1,
             payment_method VARCHAR(50) NOT NULL,
             bank_account_id UUID,
-            bank_account_number VARCHAR(50),
-            check_number VARCHAR(50),
-            payment_reference VARCHAR(255),
-            notes TEXT,
-            is_reversed BOOLEAN DEFAULT FALSE,
-            reversal_reason TEXT,
-            reversed_by UUID,
-            reversed_at TIMESTAMPTZ,
+            reference VARCHAR(100),
+            status VARCHAR(20) NOT NULL DEFAULT 'OPEN',
+            description TEXT,
             journal_entry_id UUID,
             created_by UUID NOT NULL,
-            created_at TIMESTAMPTZ DEFAULT NOW()
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, advance_number)
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Vendor invoice line items table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS vendor_invoice_lines (
+        CREATE TABLE IF NOT EXISTS vendor_advance_applications (
             id UUID PRIMARY KEY,
-            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id) ON DELETE CASCADE,
-            line_number INTEGER NOT NULL,
-            description TEXT NOT NULL,
-            quantity DECIMAL(15,4) DEFAULT 1,
-            unit_price DECIMAL(15,2) NOT NULL,
-            line_amount DECIMAL(15,2) NOT NULL,
-            account_id UUID,
-            department VARCHAR(100),
-            project_code VARCHAR(50),
-            created_at TIMESTAMPTZ DEFAULT NOW()
+            company_id UUID NOT NULL,
+            advance_id UUID NOT NULL REFERENCES vendor_advances(id),
+            invoice_id UUID NOT NULL REFERENCES vendor_invoices(id),
+            amount DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            application_date DATE NOT NULL,
+            journal_entry_id UUID,
+            created_by UUID NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
         )
         "#
     )
     .execute(pool)
     .await?;
 
-    // Purchase orders table
     sqlx::query!(
         r#"
-        CREATE TABLE IF NOT EXISTS purchase_orders (
+        CREATE TABLE IF NOT EXISTS vendor_advance_refunds (
             id UUID PRIMARY KEY,
             company_id UUID NOT NULL,
-            vendor_id UUID NOT NULL REFERENCES vendors(id),
-            po_number VARCHAR(50) NOT NULL,
-            po_date DATE NOT NULL,
-            expected_delivery_date DATE,
-            subtotal DECIMAL(15,2) NOT NULL,
-            tax_amount DECIMAL(15,2) DEFAULT 0,
-            total_amount DECIMAL(15,2) NOT NULL,
-            status VARCHAR(20) DEFAULT 'PENDING',
-            notes TEXT,
+            advance_id UUID NOT NULL REFERENCES vendor_advances(id),
+            refund_number VARCHAR(50) NOT NULL,
+            refund_amount DECIMAL(15,2) NOT NULL,
+            tax_amount DECIMAL(15,2) NOT NULL DEFAULT 0,
+            refund_date DATE NOT NULL,
+            payment_method VARCHAR(50) NOT NULL,
+            bank_account_id UUID,
+            reference VARCHAR(100),
+            journal_entry_id UUID,
             created_by UUID NOT NULL,
-            approved_by UUID,
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE(company_id, po_number)
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(company_id, refund_number)
         )
         "#
     )
@@ -768,6 +1809,175 @@ async fn run_ap_migrations(pool: &PgPool) -> anyhow::Result<()> {
     sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_payments_invoice_id ON vendor_payments(invoice_id)")
         .execute(pool).await?;
 
+    sqlx::query!("ALTER TABLE vendor_payments ADD COLUMN IF NOT EXISTS reversal_journal_entry_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendors ADD COLUMN IF NOT EXISTS vendor_group VARCHAR(50)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoice_lines ADD COLUMN IF NOT EXISTS item_category VARCHAR(20)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoice_lines ADD COLUMN IF NOT EXISTS po_line_id UUID REFERENCES purchase_order_lines(id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_purchase_order_lines_po ON purchase_order_lines(purchase_order_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_goods_receipts_po ON goods_receipts(purchase_order_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_invoice_lines_po_line ON vendor_invoice_lines(po_line_id) WHERE po_line_id IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoices ADD COLUMN IF NOT EXISTS match_status VARCHAR(20) NOT NULL DEFAULT 'NOT_REQUIRED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoices ADD COLUMN IF NOT EXISTS matched_at TIMESTAMPTZ")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_invoices_match_exceptions ON vendor_invoices(company_id) WHERE match_status = 'EXCEPTION'")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_invoice_line_matches_invoice ON invoice_line_matches(invoice_id)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendors ADD COLUMN IF NOT EXISTS early_payment_discount_percent DECIMAL(5,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendors ADD COLUMN IF NOT EXISTS early_payment_discount_days INTEGER NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_payments ADD COLUMN IF NOT EXISTS discount_amount DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_payment_runs_company ON payment_runs(company_id, payment_date)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_payment_run_items_invoice ON payment_run_items(invoice_id)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendors ADD COLUMN IF NOT EXISTS withholding_profile VARCHAR(20) NOT NULL DEFAULT 'NONE'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_payments ADD COLUMN IF NOT EXISTS withholding_amount DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE payment_run_items ADD COLUMN IF NOT EXISTS withholding_amount DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE payment_runs ADD COLUMN IF NOT EXISTS total_withholding DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_withholding_slips_company ON withholding_slips(company_id, withholding_date)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoices ADD COLUMN IF NOT EXISTS credited_amount DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_credit_memos_vendor ON vendor_credit_memos(company_id, vendor_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_credit_applications_memo ON vendor_credit_applications(credit_memo_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_credit_applications_invoice ON vendor_credit_applications(invoice_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_credit_refunds_memo ON vendor_credit_refunds(credit_memo_id)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoices ADD COLUMN IF NOT EXISTS advance_applied_amount DECIMAL(15,2) NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_advances_vendor ON vendor_advances(company_id, vendor_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_advances_open ON vendor_advances(company_id, advance_date) WHERE status = 'OPEN'")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_advance_applications_advance ON vendor_advance_applications(advance_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_advance_applications_invoice ON vendor_advance_applications(invoice_id)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_vendor_advance_refunds_advance ON vendor_advance_refunds(advance_id)")
+        .execute(pool).await?;
+
+    // Ledger postings queued with the document change and sent to the
+    // general ledger after the transaction commits
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS ledger_outbox (
+            id UUID PRIMARY KEY,
+            company_id UUID NOT NULL,
+            source_document_type VARCHAR(50) NOT NULL,
+            source_document_id UUID NOT NULL,
+            action VARCHAR(10) NOT NULL,
+            posting JSONB,
+            reverse_entry_id UUID,
+            reversal_date DATE,
+            reason TEXT,
+            requested_by UUID NOT NULL,
+            requested_roles TEXT[] NOT NULL DEFAULT '{}',
+            status VARCHAR(20) NOT NULL DEFAULT 'PENDING',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            journal_entry_id UUID,
+            locked_until TIMESTAMPTZ,
+            created_at TIMESTAMPTZ DEFAULT NOW(),
+            posted_at TIMESTAMPTZ,
+            UNIQUE(company_id, source_document_type, source_document_id, action)
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_ledger_outbox_pending ON ledger_outbox(created_at) WHERE status = 'PENDING'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_invoices ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_payments ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_invoices SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_payments SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+
+    // Purchase orders are priced in the vendor's currency; receipts value
+    // stock in the functional currency and post it to the ledger
+    sqlx::query!("ALTER TABLE purchase_orders ADD COLUMN IF NOT EXISTS currency VARCHAR(3)")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE purchase_orders po SET currency = v.currency FROM vendors v WHERE v.id = po.vendor_id AND po.currency IS NULL")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE goods_receipts ADD COLUMN IF NOT EXISTS currency VARCHAR(3)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE goods_receipts ADD COLUMN IF NOT EXISTS exchange_rate DECIMAL(15,6)")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE goods_receipts ADD COLUMN IF NOT EXISTS journal_entry_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE goods_receipts ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE goods_receipt_lines ADD COLUMN IF NOT EXISTS unit_cost DECIMAL(15,2)")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_goods_receipt_lines_unbooked ON goods_receipt_lines(receipt_id) WHERE inventory_transaction_id IS NULL AND unit_cost IS NOT NULL")
+        .execute(pool).await?;
+
+    // Slips are reported to the tax service after the payment is saved
+    sqlx::query!("ALTER TABLE withholding_slips ADD COLUMN IF NOT EXISTS tax_reported_at TIMESTAMPTZ")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE withholding_slips ADD COLUMN IF NOT EXISTS cancelled_by UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE withholding_slips ADD COLUMN IF NOT EXISTS cancellation_reason TEXT")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE withholding_slips ADD COLUMN IF NOT EXISTS reversal_tax_transaction_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE withholding_slips ADD COLUMN IF NOT EXISTS cancellation_reported_at TIMESTAMPTZ")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE withholding_slips SET tax_reported_at = created_at WHERE tax_transaction_id IS NOT NULL AND tax_reported_at IS NULL")
+        .execute(pool).await?;
+    sqlx::query!("CREATE INDEX IF NOT EXISTS idx_withholding_slips_unreported ON withholding_slips(created_at) WHERE tax_reported_at IS NULL OR cancellation_reported_at IS NULL")
+        .execute(pool).await?;
+
+    // Credit memos and refunds are posted through the ledger outbox
+    sqlx::query!("ALTER TABLE vendor_credit_memos ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_credit_memos ADD COLUMN IF NOT EXISTS reversal_journal_entry_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_credit_refunds ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_credit_memos SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_credit_refunds SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+
+    // So are advances, their applications and refunds
+    sqlx::query!("ALTER TABLE vendor_advances ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_advances ADD COLUMN IF NOT EXISTS reversal_journal_entry_id UUID")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_advance_applications ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("ALTER TABLE vendor_advance_refunds ADD COLUMN IF NOT EXISTS posting_status VARCHAR(20) NOT NULL DEFAULT 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_advances SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_advance_applications SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+    sqlx::query!("UPDATE vendor_advance_refunds SET posting_status = 'POSTED' WHERE journal_entry_id IS NOT NULL AND posting_status = 'NOT_POSTED'")
+        .execute(pool).await?;
+
     info!("Accounts payable migrations completed");
     Ok(())
 }
@@ -776,6 +1986,8 @@ async fn run_ap_migrations(pool: &PgPool) -> anyhow::Result<()> {
 async fn run_ar_migrations(pool: &PgPool) -> anyhow::Result<()> {
     info!("Running accounts receivable migrations...");
 
+    run_numbering_migrations(pool).await?;
+
     // Customers table
     sqlx::query!(
         r#"
@@ -966,6 +2178,8 @@ async fn run_ar_migrations(pool: &PgPool) -> anyhow::Result<()> {
 async fn run_inventory_migrations(pool: &PgPool) -> anyhow::Result<()> {
     info!("Running inventory management migrations...");
 
+    run_numbering_migrations(pool).await?;
+
     // Create item type enum
     sqlx::query!(
         r#"
@@ -1183,6 +2397,9 @@ async fn run_inventory_migrations(pool: &PgPool) -> anyhow::Result<()> {
         .execute(pool).await?;
     sqlx::query!("CREATE INDEX IF NOT EXISTS idx_inventory_transactions_type ON inventory_transactions(transaction_type)")
         .execute(pool).await?;
+    // Callers retrying a stock move name its source so it is booked once
+    sqlx::query!("CREATE UNIQUE INDEX IF NOT EXISTS idx_inventory_transactions_source ON inventory_transactions(company_id, source_document_type, source_document_id) WHERE source_document_id IS NOT NULL")
+        .execute(pool).await?;
 
     info!("Inventory management migrations completed");
     Ok(())
@@ -1205,6 +2422,8 @@ pub async fn create_default_indonesian_chart_of_accounts(
         ("1210", "Piutang Lain-lain", "ASSET", "CURRENT_ASSET", "DEBIT"),
         ("1300", "Persediaan", "ASSET", "CURRENT_ASSET", "DEBIT"),
         ("1400", "Biaya Dibayar Dimuka", "ASSET", "CURRENT_ASSET", "DEBIT"),
+        ("1410", "PPN Masukan", "ASSET", "CURRENT_ASSET", "DEBIT"),
+        ("1420", "Uang Muka Pembelian", "ASSET", "CURRENT_ASSET", "DEBIT"),
         ("1500", "Tanah", "ASSET", "FIXED_ASSET", "DEBIT"),
         ("1510", "Bangunan", "ASSET", "FIXED_ASSET", "DEBIT"),
         ("1520", "Mesin dan Peralatan", "ASSET", "FIXED_ASSET", "DEBIT"),
@@ -1220,6 +2439,7 @@ pub async fn create_default_indonesian_chart_of_accounts(
         ("2122", "Hutang PPh 23", "LIABILITY", "CURRENT_LIABILITY", "CREDIT"),
         ("2130", "Hutang Gaji", "LIABILITY", "CURRENT_LIABILITY", "CREDIT"),
         ("2140", "Hutang Lain-lain", "LIABILITY", "CURRENT_LIABILITY", "CREDIT"),
+        ("2150", "Barang Diterima Belum Ditagih", "LIABILITY", "CURRENT_LIABILITY", "CREDIT"),
         ("2200", "Hutang Bank Jangka Panjang", "LIABILITY", "LONG_TERM_LIABILITY", "CREDIT"),
         
         // EQUITY
@@ -1230,9 +2450,12 @@ pub async fn create_default_indonesian_chart_of_accounts(
         // REVENUE
         ("4100", "Pendapatan Penjualan", "REVENUE", "OPERATING_REVENUE", "CREDIT"),
         ("4200", "Pendapatan Lain-lain", "REVENUE", "NON_OPERATING_REVENUE", "CREDIT"),
+        ("4210", "Potongan Pembelian", "REVENUE", "NON_OPERATING_REVENUE", "CREDIT"),
+        ("4220", "Laba (Rugi) Selisih Kurs", "REVENUE", "NON_OPERATING_REVENUE", "CREDIT"),
         
         // EXPENSES
         ("5100", "Harga Pokok Penjualan", "EXPENSE", "COST_OF_GOODS_SOLD", "DEBIT"),
+        ("5110", "Selisih Harga Pembelian", "EXPENSE", "COST_OF_GOODS_SOLD", "DEBIT"),
         ("6100", "Beban Gaji dan Upah", "EXPENSE", "OPERATING_EXPENSE", "DEBIT"),
         ("6110", "Beban Sewa", "EXPENSE", "OPERATING_EXPENSE", "DEBIT"),
         ("6120", "Beban Listrik", "EXPENSE", "OPERATING_EXPENSE", "DEBIT"),
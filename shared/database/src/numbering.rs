@@ -0,0 +1,368 @@
+//! Gap-free document numbering shared by every service that issues numbers
+//!
+//! Each company keeps one counter per document type and numbering period in
+//! `document_sequences`. The counter is incremented inside the caller's
+//! transaction, so concurrent creates queue on the sequence row and a rolled
+//! back document gives its number back instead of leaving a gap.
+
+use chrono::{Datelike, NaiveDate};
+use common::{ServiceError, ServiceResult};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+/// Longest number any `*_number` column accepts.
+const MAX_NUMBER_LENGTH: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DocumentType {
+    JournalEntry,
+    VendorPayment,
+    PurchaseOrder,
+    GoodsReceipt,
+    InventoryAdjustment,
+    PaymentRun,
+    WithholdingSlip,
+    VendorCreditMemo,
+    VendorRefund,
+    VendorAdvance,
+}
+
+impl DocumentType {
+    pub const ALL: [DocumentType; 10] = [
+        DocumentType::JournalEntry,
+        DocumentType::VendorPayment,
+        DocumentType::PurchaseOrder,
+        DocumentType::GoodsReceipt,
+        DocumentType::InventoryAdjustment,
+        DocumentType::PaymentRun,
+        DocumentType::WithholdingSlip,
+        DocumentType::VendorCreditMemo,
+        DocumentType::VendorRefund,
+        DocumentType::VendorAdvance,
+    ];
+
+    pub fn default_pattern(&self) -> &'static str {
+        match self {
+            DocumentType::JournalEntry => "JE-{YYYY}{MM}-{seq:6}",
+            DocumentType::VendorPayment => "PAY-{YYYY}{MM}-{seq:5}",
+            DocumentType::PurchaseOrder => "PO/{YYYY}/{seq:5}",
+            DocumentType::GoodsReceipt => "GRN-{YYYY}{MM}-{seq:5}",
+            DocumentType::InventoryAdjustment => "ADJ-{YYYY}{MM}-{seq:4}",
+            DocumentType::PaymentRun => "PYR-{YYYY}{MM}-{seq:4}",
+            DocumentType::WithholdingSlip => "BP23-{YYYY}{MM}-{seq:5}",
+            DocumentType::VendorCreditMemo => "VCM-{YYYY}{MM}-{seq:5}",
+            DocumentType::VendorRefund => "VRF-{YYYY}{MM}-{seq:5}",
+            DocumentType::VendorAdvance => "VAD-{YYYY}{MM}-{seq:5}",
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "JOURNAL_ENTRY" => Ok(DocumentType::JournalEntry),
+            "VENDOR_PAYMENT" => Ok(DocumentType::VendorPayment),
+            "PURCHASE_ORDER" => Ok(DocumentType::PurchaseOrder),
+            "GOODS_RECEIPT" => Ok(DocumentType::GoodsReceipt),
+            "INVENTORY_ADJUSTMENT" => Ok(DocumentType::InventoryAdjustment),
+            "PAYMENT_RUN" => Ok(DocumentType::PaymentRun),
+            "WITHHOLDING_SLIP" => Ok(DocumentType::WithholdingSlip),
+            "VENDOR_CREDIT_MEMO" => Ok(DocumentType::VendorCreditMemo),
+            "VENDOR_REFUND" => Ok(DocumentType::VendorRefund),
+            "VENDOR_ADVANCE" => Ok(DocumentType::VendorAdvance),
+            _ => Err(format!("Invalid document type: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentType::JournalEntry => write!(f, "JOURNAL_ENTRY"),
+            DocumentType::VendorPayment => write!(f, "VENDOR_PAYMENT"),
+            DocumentType::PurchaseOrder => write!(f, "PURCHASE_ORDER"),
+            DocumentType::GoodsReceipt => write!(f, "GOODS_RECEIPT"),
+            DocumentType::InventoryAdjustment => write!(f, "INVENTORY_ADJUSTMENT"),
+            DocumentType::PaymentRun => write!(f, "PAYMENT_RUN"),
+            DocumentType::WithholdingSlip => write!(f, "WITHHOLDING_SLIP"),
+            DocumentType::VendorCreditMemo => write!(f, "VENDOR_CREDIT_MEMO"),
+            DocumentType::VendorRefund => write!(f, "VENDOR_REFUND"),
+            DocumentType::VendorAdvance => write!(f, "VENDOR_ADVANCE"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentNumberFormat {
+    pub document_type: DocumentType,
+    pub pattern: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Literal(&'a str),
+    Year,
+    ShortYear,
+    Month,
+    Day,
+    Seq(usize),
+}
+
+/// A parsed numbering pattern such as `JE-{YYYY}{MM}-{seq:6}`.
+///
+/// Supported tokens are `{YYYY}`, `{YY}`, `{MM}`, `{DD}`, `{seq}` and
+/// `{seq:N}` (zero-padded to N digits). The sequence restarts whenever the
+/// date tokens used by the pattern change, so `{YYYY}` numbers yearly and
+/// `{YYYY}{MM}` monthly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberPattern<'a> {
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> NumberPattern<'a> {
+    pub fn parse(pattern: &'a str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                tokens.push(Token::Literal(&rest[..open]));
+            }
+            let close = rest[open..].find('}')
+                .map(|i| i + open)
+                .ok_or_else(|| format!("Unclosed token in pattern {}", pattern))?;
+
+            let token = match &rest[open + 1..close] {
+                "YYYY" => Token::Year,
+                "YY" => Token::ShortYear,
+                "MM" => Token::Month,
+                "DD" => Token::Day,
+                "seq" => Token::Seq(0),
+                other => match other.strip_prefix("seq:").and_then(|w| w.parse::<usize>().ok()) {
+                    Some(width) if (1..=12).contains(&width) => Token::Seq(width),
+                    _ => return Err(format!("Unknown token {{{}}} in pattern {}", other, pattern)),
+                },
+            };
+            tokens.push(token);
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            tokens.push(Token::Literal(rest));
+        }
+
+        if tokens.iter().filter(|t| matches!(t, Token::Seq(_))).count() != 1 {
+            return Err(format!("Pattern {} must contain exactly one {{seq}} token", pattern));
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// Key of the numbering period `date` falls in, e.g. `2024-03` for a
+    /// monthly pattern or `ALL` for a pattern without date tokens.
+    pub fn period_key(&self, date: NaiveDate) -> String {
+        let has = |wanted: &[Token]| self.tokens.iter().any(|t| wanted.contains(t));
+
+        let mut parts = Vec::new();
+        if has(&[Token::Year, Token::ShortYear]) {
+            parts.push(format!("{:04}", date.year()));
+        }
+        if has(&[Token::Month]) {
+            parts.push(format!("{:02}", date.month()));
+        }
+        if has(&[Token::Day]) {
+            parts.push(format!("{:02}", date.day()));
+        }
+
+        if parts.is_empty() {
+            "ALL".to_string()
+        } else {
+            parts.join("-")
+        }
+    }
+
+    pub fn format(&self, date: NaiveDate, sequence: i64) -> String {
+        self.tokens
+            .iter()
+            .map(|token| match token {
+                Token::Literal(text) => text.to_string(),
+                Token::Year => format!("{:04}", date.year()),
+                Token::ShortYear => format!("{:02}", date.year() % 100),
+                Token::Month => format!("{:02}", date.month()),
+                Token::Day => format!("{:02}", date.day()),
+                Token::Seq(width) => format!("{:0width$}", sequence, width = *width),
+            })
+            .collect()
+    }
+}
+
+/// Pattern configured for a document type, or its default.
+pub async fn pattern_for<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    document_type: DocumentType,
+) -> ServiceResult<String>
+where
+    E: PgExecutor<'e>,
+{
+    let pattern = sqlx::query_scalar!(
+        "SELECT pattern FROM document_number_formats WHERE company_id = $1 AND document_type = $2",
+        company_id,
+        document_type.to_string()
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(pattern.unwrap_or_else(|| document_type.default_pattern().to_string()))
+}
+
+/// Issues the next number for a document dated `date`.
+///
+/// Must run on the transaction that inserts the document: the sequence row
+/// stays locked until it commits, and a rollback releases the number.
+pub async fn next_number(
+    conn: &mut PgConnection,
+    company_id: Uuid,
+    document_type: DocumentType,
+    date: NaiveDate,
+) -> ServiceResult<String> {
+    let pattern = pattern_for(&mut *conn, company_id, document_type).await?;
+    let parsed = NumberPattern::parse(&pattern).map_err(ServiceError::Validation)?;
+
+    let sequence = sqlx::query_scalar!(
+        r#"
+        INSERT INTO document_sequences (company_id, document_type, period_key, last_value, updated_at)
+        VALUES ($1, $2, $3, 1, NOW())
+        ON CONFLICT (company_id, document_type, period_key) DO UPDATE
+        SET last_value = document_sequences.last_value + 1,
+            updated_at = NOW()
+        RETURNING last_value
+        "#,
+        company_id,
+        document_type.to_string(),
+        parsed.period_key(date)
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(parsed.format(date, sequence))
+}
+
+pub async fn get_formats<'e, E>(
+    executor: E,
+    company_id: Uuid,
+) -> ServiceResult<Vec<DocumentNumberFormat>>
+where
+    E: PgExecutor<'e>,
+{
+    let configured = sqlx::query!(
+        "SELECT document_type, pattern FROM document_number_formats WHERE company_id = $1",
+        company_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(DocumentType::ALL
+        .iter()
+        .map(|document_type| {
+            let custom = configured
+                .iter()
+                .find(|row| row.document_type == document_type.to_string());
+            DocumentNumberFormat {
+                document_type: *document_type,
+                pattern: custom
+                    .map(|row| row.pattern.clone())
+                    .unwrap_or_else(|| document_type.default_pattern().to_string()),
+                is_default: custom.is_none(),
+            }
+        })
+        .collect())
+}
+
+/// Stores a custom pattern after checking that it parses and that the
+/// longest number it can produce still fits the document number columns.
+pub async fn set_format<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    document_type: DocumentType,
+    pattern: &str,
+    user_id: Uuid,
+) -> ServiceResult<DocumentNumberFormat>
+where
+    E: PgExecutor<'e>,
+{
+    let parsed = NumberPattern::parse(pattern).map_err(ServiceError::Validation)?;
+
+    let longest_date = NaiveDate::from_ymd_opt(2099, 12, 31).unwrap_or_default();
+    if parsed.format(longest_date, 999_999).len() > MAX_NUMBER_LENGTH {
+        return Err(ServiceError::Validation(format!(
+            "Pattern {} produces numbers longer than {} characters",
+            pattern, MAX_NUMBER_LENGTH
+        )));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO document_number_formats (company_id, document_type, pattern, updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (company_id, document_type) DO UPDATE
+        SET pattern = EXCLUDED.pattern,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        "#,
+        company_id,
+        document_type.to_string(),
+        pattern,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    tracing::info!("Numbering pattern for {} set to {} (company {}) by user {}",
+        document_type, pattern, company_id, user_id);
+
+    Ok(DocumentNumberFormat {
+        document_type,
+        pattern: pattern.to_string(),
+        is_default: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_default_patterns() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+
+        let journal = NumberPattern::parse("JE-{YYYY}{MM}-{seq:6}").unwrap();
+        assert_eq!(journal.format(date, 42), "JE-202403-000042");
+        assert_eq!(journal.period_key(date), "2024-03");
+
+        let invoice = NumberPattern::parse("INV/{YYYY}/{seq}").unwrap();
+        assert_eq!(invoice.format(date, 1234), "INV/2024/1234");
+        assert_eq!(invoice.period_key(date), "2024");
+
+        let flat = NumberPattern::parse("DOC{seq:3}").unwrap();
+        assert_eq!(flat.format(date, 7), "DOC007");
+        assert_eq!(flat.period_key(date), "ALL");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(NumberPattern::parse("JE-{YYYY}").is_err());
+        assert!(NumberPattern::parse("JE-{seq}-{seq}").is_err());
+        assert!(NumberPattern::parse("JE-{QQ}-{seq}").is_err());
+        assert!(NumberPattern::parse("JE-{seq:0}").is_err());
+        assert!(NumberPattern::parse("JE-{seq").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Fiscal period locks shared by every service that posts accounting entries
+
+use chrono::{Datelike, NaiveDate};
+use common::{ServiceError, ServiceResult};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Type};
+use uuid::Uuid;
+
+/// Roles allowed to post into a soft-closed period.
+pub const PERIOD_OVERRIDE_ROLES: &[&str] = &["ADMIN", "CONTROLLER", "CFO"];
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "fiscal_period_status", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PeriodStatus {
+    Open,
+    SoftClosed,
+    Closed,
+}
+
+impl std::str::FromStr for PeriodStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "OPEN" => Ok(PeriodStatus::Open),
+            "SOFT_CLOSED" => Ok(PeriodStatus::SoftClosed),
+            "CLOSED" => Ok(PeriodStatus::Closed),
+            _ => Err(format!("Invalid period status: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for PeriodStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeriodStatus::Open => write!(f, "OPEN"),
+            PeriodStatus::SoftClosed => write!(f, "SOFT_CLOSED"),
+            PeriodStatus::Closed => write!(f, "CLOSED"),
+        }
+    }
+}
+
+pub fn has_override_role(roles: &[String]) -> bool {
+    roles.iter().any(|role| {
+        PERIOD_OVERRIDE_ROLES.iter().any(|allowed| role.eq_ignore_ascii_case(allowed))
+    })
+}
+
+/// Status of the period containing `date`. Dates outside any defined period
+/// are treated as open so companies that never set up periods keep working.
+pub async fn period_status<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    date: NaiveDate,
+) -> ServiceResult<PeriodStatus>
+where
+    E: PgExecutor<'e>,
+{
+    // FOR SHARE makes a concurrent close wait for in-flight postings
+    let status = sqlx::query_scalar!(
+        r#"
+        SELECT status as "status!: PeriodStatus"
+        FROM fiscal_periods
+        WHERE company_id = $1 AND $2 BETWEEN start_date AND end_date
+        LIMIT 1
+        FOR SHARE
+        "#,
+        company_id,
+        date
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(status.unwrap_or(PeriodStatus::Open))
+}
+
+/// First day of the period following the one containing `date`. Without a
+/// defined period the calendar month is used.
+pub async fn next_period_start<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    date: NaiveDate,
+) -> ServiceResult<NaiveDate>
+where
+    E: PgExecutor<'e>,
+{
+    let end_date = sqlx::query_scalar!(
+        r#"
+        SELECT end_date
+        FROM fiscal_periods
+        WHERE company_id = $1 AND $2 BETWEEN start_date AND end_date
+        LIMIT 1
+        "#,
+        company_id,
+        date
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(ServiceError::Database)?;
+
+    Ok(match end_date {
+        Some(end_date) => end_date.succ_opt().unwrap_or(end_date),
+        None => first_day_of_next_month(date),
+    })
+}
+
+fn first_day_of_next_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+}
+
+/// Rejects postings dated in a closed period, or in a soft-closed period
+/// unless the caller holds one of `PERIOD_OVERRIDE_ROLES`.
+pub async fn ensure_period_open<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    date: NaiveDate,
+    roles: &[String],
+) -> ServiceResult<()>
+where
+    E: PgExecutor<'e>,
+{
+    match period_status(executor, company_id, date).await? {
+        PeriodStatus::Open => Ok(()),
+        PeriodStatus::SoftClosed if has_override_role(roles) => Ok(()),
+        PeriodStatus::SoftClosed => Err(ServiceError::Authorization(format!(
+            "Accounting period for {} is soft-closed; posting requires one of the roles: {}",
+            date,
+            PERIOD_OVERRIDE_ROLES.join(", ")
+        ))),
+        PeriodStatus::Closed => Err(ServiceError::Validation(format!(
+            "Accounting period for {} is closed",
+            date
+        ))),
+    }
+}
+
+/// Rejects postings dated in a closed period. Used by scheduled jobs that
+/// complete what a user already posted, such as automatic reversals, which
+/// may still land in a soft-closed period.
+pub async fn ensure_period_not_closed<'e, E>(
+    executor: E,
+    company_id: Uuid,
+    date: NaiveDate,
+) -> ServiceResult<()>
+where
+    E: PgExecutor<'e>,
+{
+    match period_status(executor, company_id, date).await? {
+        PeriodStatus::Closed => Err(ServiceError::Validation(format!(
+            "Accounting period for {} is closed",
+            date
+        ))),
+        PeriodStatus::Open | PeriodStatus::SoftClosed => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_day_of_next_month() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(first_day_of_next_month(date(2024, 1, 31)), date(2024, 2, 1));
+        assert_eq!(first_day_of_next_month(date(2024, 2, 1)), date(2024, 3, 1));
+        assert_eq!(first_day_of_next_month(date(2024, 12, 15)), date(2025, 1, 1));
+    }
+}
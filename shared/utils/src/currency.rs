@@ -118,24 +118,6 @@ impl CurrencyUtils {
     pub fn is_valid_currency(currency: &str) -> bool {
         Self::supported_currencies().contains_key(&currency.to_uppercase())
     }
-
-    /// Gets default exchange rates (for demo purposes - would come from API in production)
-    pub fn get_default_exchange_rates() -> HashMap<String, Decimal> {
-        let mut rates = HashMap::new();
-        
-        // Rates against IDR (as of example date)
-        rates.insert("USD".to_string(), Decimal::new(15000, 0)); // 1 USD = 15,000 IDR
-        rates.insert("EUR".to_string(), Decimal::new(16500, 0)); // 1 EUR = 16,500 IDR
-        rates.insert("GBP".to_string(), Decimal::new(19000, 0)); // 1 GBP = 19,000 IDR
-        rates.insert("JPY".to_string(), Decimal::new(100, 0));   // 1 JPY = 100 IDR
-        rates.insert("SGD".to_string(), Decimal::new(11000, 0)); // 1 SGD = 11,000 IDR
-        rates.insert("MYR".to_string(), Decimal::new(3500, 0));  // 1 MYR = 3,500 IDR
-        rates.insert("THB".to_string(), Decimal::new(450, 0));   // 1 THB = 450 IDR
-        rates.insert("PHP".to_string(), Decimal::new(270, 0));   // 1 PHP = 270 IDR
-        rates.insert("VND".to_string(), Decimal::new(6, 1));     // 1 VND = 0.6 IDR
-        
-        rates
-    }
 }
 
 pub struct IndonesianCurrencyUtils;